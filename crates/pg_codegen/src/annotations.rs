@@ -0,0 +1,275 @@
+use std::str::FromStr;
+
+use text_size::TextSize;
+
+use crate::CodegenError;
+
+/// What a generated function returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryKind {
+    /// Exactly one row, failing if there is none
+    One,
+    /// At most one row
+    Optional,
+    /// All rows
+    Many,
+    /// The number of affected rows
+    Exec,
+}
+
+impl FromStr for QueryKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            ":one" => Ok(QueryKind::One),
+            ":optional" => Ok(QueryKind::Optional),
+            ":many" => Ok(QueryKind::Many),
+            ":exec" => Ok(QueryKind::Exec),
+            _ => Err(format!(
+                "Unknown kind `{}`, expected `:one`, `:optional`, `:many` or `:exec`",
+                s
+            )),
+        }
+    }
+}
+
+/// A parameter named with `-- params:`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Param {
+    pub name: String,
+    /// Whether the parameter may be null, which is marked with a trailing `?`
+    pub nullable: bool,
+}
+
+/// A statement named with a `-- name:` comment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    /// The file the query is read from
+    pub path: String,
+    pub name: String,
+    /// The kind given after the name, if any
+    pub kind: Option<QueryKind>,
+    /// The names of `$1`, `$2`, ..., or empty if they are not named
+    pub params: Vec<Param>,
+    pub sql: String,
+    /// The line the statement starts on, starting at 1
+    pub line: usize,
+}
+
+impl Query {
+    /// Returns the kind of the query, which defaults to `:many` if it returns columns and to
+    /// `:exec` otherwise
+    pub fn resolved_kind(&self, has_columns: bool) -> QueryKind {
+        match self.kind {
+            Some(kind) => kind,
+            None if has_columns => QueryKind::Many,
+            None => QueryKind::Exec,
+        }
+    }
+}
+
+/// Returns the named statements of a file
+///
+/// A statement is named by the comments right before it:
+///
+/// ```sql
+/// -- name: find_user :optional
+/// -- params: email, tenant_id?
+/// select id, name from users where email = $1 and tenant_id is not distinct from $2;
+/// ```
+///
+/// Statements without a name are skipped, so that queries can live next to other sql.
+pub fn parse_queries(path: &str, text: &str) -> Result<Vec<Query>, CodegenError> {
+    let mut queries: Vec<Query> = Vec::new();
+    let mut previous_end = TextSize::from(0);
+    for range in pg_statement_splitter::split(text).ranges {
+        let comments = &text[usize::from(previous_end)..usize::from(range.start())];
+        previous_end = range.end();
+        let line = line_of(text, range.start());
+
+        let mut name = None;
+        let mut params = None;
+        for comment in comments.lines().filter_map(|l| l.trim().strip_prefix("--")) {
+            let comment = comment.trim();
+            if let Some(value) = comment.strip_prefix("name:") {
+                if name.is_some() {
+                    return Err(CodegenError::new(
+                        path,
+                        line,
+                        "The query has more than one name",
+                    ));
+                }
+                name = Some(parse_name(path, value, line)?);
+            } else if let Some(value) = comment.strip_prefix("params:") {
+                params = Some(parse_params(path, value, line)?);
+            }
+        }
+
+        let Some((name, kind)) = name else {
+            if params.is_some() {
+                return Err(CodegenError::new(
+                    path,
+                    line,
+                    "`-- params:` is only allowed on queries with a `-- name:`",
+                ));
+            }
+            continue;
+        };
+        if let Some(other) = queries.iter().find(|q| q.name == name) {
+            return Err(CodegenError::new(
+                path,
+                line,
+                format!(
+                    "The name `{}` is already used by the query on line {}",
+                    name, other.line
+                ),
+            ));
+        }
+        queries.push(Query {
+            path: path.to_string(),
+            name,
+            kind,
+            params: params.unwrap_or_default(),
+            sql: text[range].to_string(),
+            line,
+        });
+    }
+    Ok(queries)
+}
+
+/// Parses the value of `-- name:`, e.g. `find_user :one`
+fn parse_name(
+    path: &str,
+    value: &str,
+    line: usize,
+) -> Result<(String, Option<QueryKind>), CodegenError> {
+    let mut words = value.split_whitespace();
+    let name = words
+        .next()
+        .ok_or_else(|| CodegenError::new(path, line, "`-- name:` is missing the name"))?;
+    if !is_identifier(name) {
+        return Err(CodegenError::new(
+            path,
+            line,
+            format!(
+                "`{}` is not a valid name, use letters, digits and underscores",
+                name
+            ),
+        ));
+    }
+    let kind = words
+        .next()
+        .map(|kind| {
+            kind.parse()
+                .map_err(|err| CodegenError::new(path, line, err))
+        })
+        .transpose()?;
+    if let Some(extra) = words.next() {
+        return Err(CodegenError::new(
+            path,
+            line,
+            format!("Unexpected `{}` after the name", extra),
+        ));
+    }
+    Ok((name.to_string(), kind))
+}
+
+/// Parses the value of `-- params:`, e.g. `email, tenant_id?`
+fn parse_params(path: &str, value: &str, line: usize) -> Result<Vec<Param>, CodegenError> {
+    value
+        .split(',')
+        .map(|param| {
+            let param = param.trim();
+            let (name, nullable) = match param.strip_suffix('?') {
+                Some(name) => (name, true),
+                None => (param, false),
+            };
+            if !is_identifier(name) {
+                return Err(CodegenError::new(
+                    path,
+                    line,
+                    format!("`{}` is not a valid parameter name", param),
+                ));
+            }
+            Ok(Param {
+                name: name.to_string(),
+                nullable,
+            })
+        })
+        .collect()
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn line_of(text: &str, offset: TextSize) -> usize {
+    text[..usize::from(offset)].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_queries, Param, Query, QueryKind};
+
+    #[test]
+    fn test_parse_queries() {
+        let text = "create table users (id int);\n\n\
+            -- name: find_user :optional\n\
+            -- params: email, tenant_id?\n\
+            select id from users where email = $1 and tenant_id = $2;\n\n\
+            -- Deletes everything\n\
+            -- name: delete_users\n\
+            delete from users;\n";
+
+        assert_eq!(
+            parse_queries("queries.sql", text).unwrap(),
+            vec![
+                Query {
+                    path: "queries.sql".to_string(),
+                    name: "find_user".to_string(),
+                    kind: Some(QueryKind::Optional),
+                    params: vec![
+                        Param {
+                            name: "email".to_string(),
+                            nullable: false,
+                        },
+                        Param {
+                            name: "tenant_id".to_string(),
+                            nullable: true,
+                        },
+                    ],
+                    sql: "select id from users where email = $1 and tenant_id = $2;".to_string(),
+                    line: 5,
+                },
+                Query {
+                    path: "queries.sql".to_string(),
+                    name: "delete_users".to_string(),
+                    kind: None,
+                    params: vec![],
+                    sql: "delete from users;".to_string(),
+                    line: 9,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_queries_errors() {
+        let error = |text: &str| parse_queries("queries.sql", text).unwrap_err().to_string();
+
+        assert_eq!(
+            error("-- name: find-user\nselect 1;"),
+            "queries.sql:2: `find-user` is not a valid name, use letters, digits and underscores"
+        );
+        assert_eq!(
+            error("-- name: a :all\nselect 1;"),
+            "queries.sql:2: Unknown kind `:all`, expected `:one`, `:optional`, `:many` or `:exec`"
+        );
+        assert_eq!(
+            error("-- name: a\nselect 1;\n-- name: a\nselect 2;"),
+            "queries.sql:4: The name `a` is already used by the query on line 2"
+        );
+    }
+}
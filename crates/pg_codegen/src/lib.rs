@@ -0,0 +1,83 @@
+//! Code generation from annotated sql queries
+//!
+//! Queries are named with `-- name:` comments, described by the database with
+//! [`pg_typecheck::describe_sql`] and turned into typed functions or types of the target
+//! language.
+
+mod annotations;
+mod naming;
+mod rust;
+mod typescript;
+
+use pg_typecheck::StatementDescription;
+
+pub use annotations::{parse_queries, Param, Query, QueryKind};
+pub use rust::generate_rust;
+pub use typescript::{generate_typescript, generate_typescript_schema, SCHEMA_MODULE};
+
+/// An error in the annotations of a query, or a query that cannot be generated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodegenError {
+    pub path: String,
+    /// The line of the query, starting at 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl CodegenError {
+    fn new(path: &str, line: usize, message: impl Into<String>) -> CodegenError {
+        CodegenError {
+            path: path.to_string(),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.path, self.line, self.message)
+    }
+}
+
+/// Returns the kind of a query, which must return rows unless it is `:exec`
+fn resolve_kind(
+    query: &Query,
+    description: &StatementDescription,
+) -> Result<QueryKind, CodegenError> {
+    let kind = query.resolved_kind(!description.columns.is_empty());
+    if kind != QueryKind::Exec && description.columns.is_empty() {
+        return Err(CodegenError::new(
+            &query.path,
+            query.line,
+            "The query returns no rows, use `:exec` instead",
+        ));
+    }
+    Ok(kind)
+}
+
+/// Returns the name of a result column and whether it may be null
+///
+/// Columns may be null unless the database knows better, which can be overridden like in the
+/// query macros of sqlx, with `as "name!"` for non-null and `as "name?"` for nullable columns.
+/// The suffix is removed from the returned name.
+fn column_nullability(
+    description: &StatementDescription,
+    i: usize,
+) -> Result<(&str, bool), String> {
+    let column = &description.columns[i];
+    // `?column?` is the name of columns without one, not a nullable `?column`
+    if column.name == "?column?" {
+        return Err(format!(
+            "Column {} has no usable name, name it with `as`",
+            i + 1
+        ));
+    }
+    Ok(match column.name.strip_suffix('!') {
+        Some(name) => (name, false),
+        None => match column.name.strip_suffix('?') {
+            Some(name) => (name, true),
+            None => (column.name.as_str(), description.nullable[i] != Some(false)),
+        },
+    })
+}
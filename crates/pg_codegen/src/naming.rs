@@ -0,0 +1,71 @@
+/// Returns the words of a name, e.g. `["user", "id"]` for `userId`, `user_id` or `User ID`
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous_lowercase = false;
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            previous_lowercase = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lowercase {
+            words.push(std::mem::take(&mut word));
+        }
+        previous_lowercase = c.is_lowercase() || c.is_ascii_digit();
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Returns the name in `snake_case`, or `None` if it has no letters to start with
+pub fn snake_case(name: &str) -> Option<String> {
+    let name = words(name).join("_");
+    name.starts_with(|c: char| c.is_alphabetic())
+        .then_some(name)
+}
+
+/// Returns the name in `PascalCase`
+pub fn pascal_case(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Returns the name in `camelCase`
+pub fn camel_case(name: &str) -> String {
+    let name = pascal_case(name);
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{camel_case, pascal_case, snake_case};
+
+    #[test]
+    fn test_naming() {
+        assert_eq!(snake_case("userId").as_deref(), Some("user_id"));
+        assert_eq!(snake_case("User ID").as_deref(), Some("user_id"));
+        assert_eq!(snake_case("count_2").as_deref(), Some("count_2"));
+        assert_eq!(snake_case("order-total").as_deref(), Some("order_total"));
+        assert_eq!(snake_case("1st"), None);
+        assert_eq!(pascal_case("find_user_by_email"), "FindUserByEmail");
+        assert_eq!(camel_case("find_user_by_email"), "findUserByEmail");
+    }
+}
@@ -0,0 +1,410 @@
+//! Generates typed functions for sqlx
+
+use std::fmt::Write;
+
+use pg_typecheck::StatementDescription;
+
+use crate::{
+    column_nullability,
+    naming::{pascal_case, snake_case},
+    resolve_kind, CodegenError, Query, QueryKind,
+};
+
+/// The first line of the generated file
+const HEADER: &str = "// Generated by `pglsp generate rust`, do not edit.";
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// A field of a row struct
+struct Field {
+    name: String,
+    column: String,
+    rust_type: String,
+}
+
+/// A parameter of a query function
+struct Parameter {
+    name: String,
+    rust_type: String,
+}
+
+/// Generates a module with a struct for the rows and a function for each query
+///
+/// The functions take any executor of sqlx, e.g. a pool or a transaction:
+///
+/// ```ignore
+/// pub async fn find_user<'e, E>(
+///     executor: E,
+///     email: &str,
+/// ) -> Result<Option<FindUserRow>, sqlx::Error>
+/// where
+///     E: sqlx::PgExecutor<'e>,
+/// ```
+///
+/// Columns are `Option`s if they may be null, see [`column_nullability`].
+pub fn generate_rust(queries: &[(Query, StatementDescription)]) -> Result<String, CodegenError> {
+    let mut code = String::from(HEADER);
+    code.push('\n');
+    for (query, description) in queries {
+        code.push('\n');
+        generate_query(&mut code, query, description)?;
+    }
+    Ok(code)
+}
+
+fn generate_query(
+    code: &mut String,
+    query: &Query,
+    description: &StatementDescription,
+) -> Result<(), CodegenError> {
+    let error = |message: String| CodegenError::new(&query.path, query.line, message);
+
+    let kind = resolve_kind(query, description)?;
+    let fields = fields(description).map_err(error)?;
+    let parameters = parameters(query, description).map_err(error)?;
+
+    let constant = query.name.to_uppercase();
+    let row = format!("{}Row", pascal_case(&query.name));
+    let return_type = match kind {
+        QueryKind::One => row.clone(),
+        QueryKind::Optional => format!("Option<{}>", row),
+        QueryKind::Many => format!("Vec<{}>", row),
+        QueryKind::Exec => "u64".to_string(),
+    };
+
+    writeln!(
+        code,
+        "/// `{}` from {}, line {}",
+        query.name, query.path, query.line
+    )
+    .unwrap();
+    writeln!(
+        code,
+        "pub const {}: &str = {};",
+        constant,
+        string_literal(&query.sql)
+    )
+    .unwrap();
+    code.push('\n');
+
+    if kind != QueryKind::Exec {
+        writeln!(code, "#[derive(Debug, Clone, sqlx::FromRow)]").unwrap();
+        writeln!(code, "pub struct {} {{", row).unwrap();
+        for field in &fields {
+            // sqlx does not strip the `r#` of raw identifiers from column names
+            if field.name != field.column {
+                writeln!(code, "    #[sqlx(rename = {:?})]", field.column).unwrap();
+            }
+            writeln!(code, "    pub {}: {},", field.name, field.rust_type).unwrap();
+        }
+        writeln!(code, "}}\n").unwrap();
+    }
+
+    writeln!(code, "pub async fn {}<'e, E>(", identifier(&query.name)).unwrap();
+    writeln!(code, "    executor: E,").unwrap();
+    for parameter in &parameters {
+        writeln!(code, "    {}: {},", parameter.name, parameter.rust_type).unwrap();
+    }
+    writeln!(code, ") -> Result<{}, sqlx::Error>", return_type).unwrap();
+    writeln!(code, "where\n    E: sqlx::PgExecutor<'e>,\n{{").unwrap();
+    match kind {
+        QueryKind::Exec => writeln!(code, "    sqlx::query({})", constant).unwrap(),
+        _ => writeln!(code, "    sqlx::query_as::<_, {}>({})", row, constant).unwrap(),
+    }
+    for parameter in &parameters {
+        writeln!(code, "        .bind({})", parameter.name).unwrap();
+    }
+    match kind {
+        QueryKind::One => writeln!(code, "        .fetch_one(executor)\n        .await"),
+        QueryKind::Optional => writeln!(code, "        .fetch_optional(executor)\n        .await"),
+        QueryKind::Many => writeln!(code, "        .fetch_all(executor)\n        .await"),
+        QueryKind::Exec => writeln!(
+            code,
+            "        .execute(executor)\n        .await\n        .map(|result| result.rows_affected())"
+        ),
+    }
+    .unwrap();
+    writeln!(code, "}}").unwrap();
+    Ok(())
+}
+
+/// Returns the fields of the row struct of a query
+fn fields(description: &StatementDescription) -> Result<Vec<Field>, String> {
+    let mut fields: Vec<Field> = Vec::new();
+    for (i, column) in description.columns.iter().enumerate() {
+        let (name, nullable) = column_nullability(description, i)?;
+        let field_name = match snake_case(name) {
+            Some(field_name) => identifier(&field_name),
+            None => {
+                return Err(format!(
+                    "Column {} has no usable name, name it with `as`",
+                    i + 1
+                ))
+            }
+        };
+        if fields.iter().any(|f| f.name == field_name) {
+            return Err(format!(
+                "More than one column is named `{}`, rename them with `as`",
+                field_name
+            ));
+        }
+        let rust_type = rust_type(&column.type_name).ok_or_else(|| {
+            format!(
+                "Column `{}` has the unsupported type `{}`, cast it to a supported type, e.g. `::text`",
+                name, column.type_name
+            )
+        })?;
+        fields.push(Field {
+            name: field_name,
+            column: column.name.clone(),
+            rust_type: match nullable {
+                true => format!("Option<{}>", rust_type),
+                false => rust_type,
+            },
+        });
+    }
+    Ok(fields)
+}
+
+/// Returns the parameters of the function of a query, named by `-- params:` or `p1`, `p2`, ...
+fn parameters(query: &Query, description: &StatementDescription) -> Result<Vec<Parameter>, String> {
+    if !query.params.is_empty() && query.params.len() != description.parameters.len() {
+        return Err(format!(
+            "`-- params:` names {} parameters, but the query has {}",
+            query.params.len(),
+            description.parameters.len()
+        ));
+    }
+
+    description
+        .parameters
+        .iter()
+        .enumerate()
+        .map(|(i, type_name)| {
+            let (name, nullable) = match query.params.get(i) {
+                Some(param) => (param.name.clone(), param.nullable),
+                None => (format!("p{}", i + 1), false),
+            };
+            let rust_type = rust_type(type_name).map(|t| parameter_type(&t)).ok_or_else(|| {
+                format!(
+                    "Parameter ${} has the unsupported type `{}`, cast it to a supported type, e.g. `$1::text`",
+                    i + 1,
+                    type_name
+                )
+            })?;
+            Ok(Parameter {
+                name: identifier(&name),
+                rust_type: match nullable {
+                    true => format!("Option<{}>", rust_type),
+                    false => rust_type,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Returns the Rust type that sqlx decodes a Postgres type into
+fn rust_type(type_name: &str) -> Option<String> {
+    if let Some(element) = type_name.strip_suffix("[]") {
+        return rust_type(element).map(|t| format!("Vec<{}>", t));
+    }
+    let rust_type = match type_name {
+        "bool" => "bool",
+        "\"char\"" | "char" => "i8",
+        "int2" => "i16",
+        "int4" => "i32",
+        "int8" => "i64",
+        "float4" => "f32",
+        "float8" => "f64",
+        "oid" => "sqlx::postgres::types::Oid",
+        "text" | "varchar" | "bpchar" | "name" | "citext" => "String",
+        "bytea" => "Vec<u8>",
+        "uuid" => "uuid::Uuid",
+        "json" | "jsonb" => "serde_json::Value",
+        "numeric" => "rust_decimal::Decimal",
+        "timestamptz" => "chrono::DateTime<chrono::Utc>",
+        "timestamp" => "chrono::NaiveDateTime",
+        "date" => "chrono::NaiveDate",
+        "time" => "chrono::NaiveTime",
+        "interval" => "sqlx::postgres::types::PgInterval",
+        "inet" | "cidr" => "ipnetwork::IpNetwork",
+        _ => return None,
+    };
+    Some(rust_type.to_string())
+}
+
+/// Returns the type a parameter is passed as, which borrows instead of taking owned values
+fn parameter_type(rust_type: &str) -> String {
+    match rust_type {
+        "String" => "&str".to_string(),
+        "serde_json::Value" => "&serde_json::Value".to_string(),
+        _ => match rust_type
+            .strip_prefix("Vec<")
+            .and_then(|t| t.strip_suffix('>'))
+        {
+            Some(element) => format!("&[{}]", element),
+            None => rust_type.to_string(),
+        },
+    }
+}
+
+/// Escapes names that are keywords, e.g. `r#type`
+fn identifier(name: &str) -> String {
+    match name {
+        "self" | "super" | "crate" => format!("{}_", name),
+        _ if KEYWORDS.contains(&name) => format!("r#{}", name),
+        _ => name.to_string(),
+    }
+}
+
+/// Returns the sql as a raw string literal with enough `#` to contain it
+fn string_literal(sql: &str) -> String {
+    let mut hashes = String::from("#");
+    while sql.contains(&format!("\"{}", hashes)) {
+        hashes.push('#');
+    }
+    format!("r{}\"{}\"{}", hashes, sql, hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_typecheck::{ResultColumn, StatementDescription};
+
+    use crate::parse_queries;
+
+    use super::generate_rust;
+
+    fn column(name: &str, type_name: &str) -> ResultColumn {
+        ResultColumn {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_generate_rust() {
+        let queries = parse_queries(
+            "queries/users.sql",
+            "-- name: find_user :optional\n\
+            -- params: email\n\
+            select id, name as \"displayName?\", \"type\", tags from users where email = $1;\n\n\
+            -- name: delete_users\n\
+            delete from users where id = any($1);\n",
+        )
+        .unwrap();
+        let descriptions = vec![
+            StatementDescription {
+                columns: vec![
+                    column("id", "int4"),
+                    column("displayName?", "text"),
+                    column("type", "text"),
+                    column("tags", "text[]"),
+                ],
+                nullable: vec![Some(false), Some(false), None, Some(false)],
+                parameters: vec!["text".to_string()],
+            },
+            StatementDescription {
+                columns: vec![],
+                nullable: vec![],
+                parameters: vec!["int8[]".to_string()],
+            },
+        ];
+
+        let code = generate_rust(&queries.into_iter().zip(descriptions).collect::<Vec<_>>());
+
+        assert_eq!(
+            code.unwrap(),
+            r##"// Generated by `pglsp generate rust`, do not edit.
+
+/// `find_user` from queries/users.sql, line 3
+pub const FIND_USER: &str = r#"select id, name as "displayName?", "type", tags from users where email = $1;"#;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct FindUserRow {
+    pub id: i32,
+    #[sqlx(rename = "displayName?")]
+    pub display_name: Option<String>,
+    #[sqlx(rename = "type")]
+    pub r#type: Option<String>,
+    pub tags: Vec<String>,
+}
+
+pub async fn find_user<'e, E>(
+    executor: E,
+    email: &str,
+) -> Result<Option<FindUserRow>, sqlx::Error>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query_as::<_, FindUserRow>(FIND_USER)
+        .bind(email)
+        .fetch_optional(executor)
+        .await
+}
+
+/// `delete_users` from queries/users.sql, line 6
+pub const DELETE_USERS: &str = r#"delete from users where id = any($1);"#;
+
+pub async fn delete_users<'e, E>(
+    executor: E,
+    p1: &[i64],
+) -> Result<u64, sqlx::Error>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query(DELETE_USERS)
+        .bind(p1)
+        .execute(executor)
+        .await
+        .map(|result| result.rows_affected())
+}
+"##
+        );
+    }
+
+    #[test]
+    fn test_generate_rust_errors() {
+        let generate = |sql: &str, description: StatementDescription| {
+            let queries = parse_queries("queries.sql", sql).unwrap();
+            generate_rust(&[(queries[0].clone(), description)])
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            generate(
+                "-- name: a :one\nselect 1, 2;",
+                StatementDescription {
+                    columns: vec![column("?column?", "int4"), column("?column?", "int4")],
+                    nullable: vec![None, None],
+                    parameters: vec![],
+                }
+            ),
+            "queries.sql:2: Column 1 has no usable name, name it with `as`"
+        );
+        assert_eq!(
+            generate(
+                "-- name: a :one\nselect mood from people;",
+                StatementDescription {
+                    columns: vec![column("mood", "mood")],
+                    nullable: vec![None],
+                    parameters: vec![],
+                }
+            ),
+            "queries.sql:2: Column `mood` has the unsupported type `mood`, cast it to a supported type, e.g. `::text`"
+        );
+        assert_eq!(
+            generate(
+                "-- name: a :one\ndelete from people;",
+                StatementDescription::default()
+            ),
+            "queries.sql:2: The query returns no rows, use `:exec` instead"
+        );
+    }
+}
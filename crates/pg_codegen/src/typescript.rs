@@ -0,0 +1,500 @@
+//! Generates TypeScript types for the rows of queries and the tables and enums of the schema
+//!
+//! The types follow how node-postgres returns values: rows have the names of the columns as
+//! keys, `bigint` and `numeric` are strings to not lose precision, and dates are `Date`s.
+
+use std::{collections::BTreeSet, fmt::Write};
+
+use pg_schema_cache::{canonical_type, SchemaCache};
+use pg_typecheck::StatementDescription;
+
+use crate::{
+    column_nullability,
+    naming::{camel_case, pascal_case},
+    resolve_kind, CodegenError, Query, QueryKind,
+};
+
+/// The first line of the generated modules
+const HEADER: &str = "// Generated by `pglsp generate ts`, do not edit.";
+
+/// The module with the types of the schema, which the module of the queries imports from
+pub const SCHEMA_MODULE: &str = "schema";
+
+/// The type of `json` and `jsonb` values, which is part of the schema module
+const JSON_TYPE: &str =
+    "export type Json = string | number | boolean | null | Json[] | { [key: string]: Json };";
+
+const RESERVED_WORDS: &[&str] = &[
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "new",
+    "null",
+    "return",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+];
+
+/// Resolves Postgres types to TypeScript types, including the enums of the schema
+struct Types<'a> {
+    /// The schema, name and type name of every enum
+    enums: Vec<(&'a str, &'a str, String)>,
+}
+
+impl<'a> Types<'a> {
+    fn new(cache: &'a SchemaCache) -> Types<'a> {
+        let enums = cache
+            .types
+            .iter()
+            .filter(|t| !t.enums.values.is_empty())
+            .map(|t| (&*t.schema, t.name.as_str(), type_name(&t.schema, &t.name)))
+            .collect();
+        Types { enums }
+    }
+
+    /// Returns the TypeScript type of a Postgres type, e.g. `string[]` for `varchar(10)[]`
+    ///
+    /// Types of the schema module that are used are added to `imports`. Types that are not known
+    /// are `unknown`.
+    fn resolve(&self, postgres_type: &str, imports: &mut BTreeSet<String>) -> String {
+        let mut name = canonical_type(postgres_type);
+        let mut arrays = 0;
+        while let Some(element) = name.strip_suffix("[]") {
+            name = element.to_string();
+            arrays += 1;
+        }
+        let base = name.split('(').next().unwrap_or_default();
+        let ts_type = match base {
+            "bool" => "boolean".to_string(),
+            "int2" | "int4" | "float4" | "float8" | "oid" => "number".to_string(),
+            "int8" | "numeric" | "money" | "text" | "varchar" | "bpchar" | "char" | "name"
+            | "citext" | "uuid" | "time" | "timetz" | "interval" | "inet" | "cidr" | "macaddr"
+            | "bit" | "varbit" | "xml" | "tsvector" => "string".to_string(),
+            "timestamptz" | "timestamp" | "date" => "Date".to_string(),
+            "bytea" => "Buffer".to_string(),
+            "json" | "jsonb" => {
+                imports.insert("Json".to_string());
+                "Json".to_string()
+            }
+            _ => match self.find_enum(base) {
+                Some(enum_type) => {
+                    imports.insert(enum_type.to_string());
+                    enum_type.to_string()
+                }
+                None => "unknown".to_string(),
+            },
+        };
+        format!("{}{}", ts_type, "[]".repeat(arrays))
+    }
+
+    /// Returns the type of the enum with the given name, which may be qualified
+    fn find_enum(&self, name: &str) -> Option<&str> {
+        let (schema, name) = match name.split_once('.') {
+            Some((schema, name)) => (Some(schema), name),
+            None => (None, name),
+        };
+        self.enums
+            .iter()
+            .filter(|(s, n, _)| {
+                n.eq_ignore_ascii_case(name) && schema.is_none_or(|schema| *s == schema)
+            })
+            // the unqualified name is that of the enum on the search path, usually `public`
+            .min_by_key(|(s, _, _)| *s != "public")
+            .map(|(_, _, ts_type)| ts_type.as_str())
+    }
+}
+
+/// Generates the schema module with a type for every enum and an interface for every table of
+/// the user schemas
+pub fn generate_typescript_schema(cache: &SchemaCache) -> String {
+    let types = Types::new(cache);
+    let mut code = format!(
+        "{}\n\n/** A value of a `json` or `jsonb` column */\n{}\n",
+        HEADER, JSON_TYPE
+    );
+    for schema in cache.user_schemas() {
+        let mut enums = cache
+            .types
+            .iter()
+            .filter(|t| t.schema == schema && !t.enums.values.is_empty())
+            .collect::<Vec<_>>();
+        enums.sort_by(|a, b| a.name.cmp(&b.name));
+        for t in enums {
+            let values = t
+                .enums
+                .values
+                .iter()
+                .map(|v| string_literal(v))
+                .collect::<Vec<_>>()
+                .join(" | ");
+            writeln!(code, "\n/** `{}.{}` */", schema, t.name).unwrap();
+            writeln!(
+                code,
+                "export type {} = {};",
+                type_name(schema, &t.name),
+                values
+            )
+            .unwrap();
+        }
+
+        let mut tables = cache
+            .tables
+            .iter()
+            .filter(|t| t.schema == schema)
+            .collect::<Vec<_>>();
+        tables.sort_by(|a, b| a.name.cmp(&b.name));
+        for table in tables {
+            let mut columns = cache
+                .columns
+                .iter()
+                .filter(|c| c.schema == schema && c.table == table.name)
+                .collect::<Vec<_>>();
+            columns.sort_by_key(|c| c.position);
+            writeln!(code, "\n/** `{}.{}` */", schema, table.name).unwrap();
+            writeln!(
+                code,
+                "export interface {} {{",
+                type_name(schema, &table.name)
+            )
+            .unwrap();
+            for column in columns {
+                let ts_type = types.resolve(&column.type_name, &mut BTreeSet::new());
+                writeln!(
+                    code,
+                    "  {}: {};",
+                    property(&column.name),
+                    nullable(ts_type, column.is_nullable)
+                )
+                .unwrap();
+            }
+            writeln!(code, "}}").unwrap();
+        }
+    }
+    code
+}
+
+/// Generates the module of the queries with their sql, a tuple type of their parameters and an
+/// interface for their rows
+///
+/// ```ts
+/// /** `find_user` from queries/users.sql, line 3 */
+/// export const findUser = `select id, name from users where email = $1;`;
+///
+/// export type FindUserParams = [email: string];
+///
+/// export interface FindUserRow {
+///   id: number;
+///   name: string | null;
+/// }
+/// ```
+///
+/// Columns may be null unless the database knows better, see [`column_nullability`]. Enums and
+/// `Json` are imported from the schema module.
+pub fn generate_typescript(
+    queries: &[(Query, StatementDescription)],
+    cache: &SchemaCache,
+) -> Result<String, CodegenError> {
+    let types = Types::new(cache);
+    let mut imports = BTreeSet::new();
+    let mut body = String::new();
+    for (query, description) in queries {
+        body.push('\n');
+        generate_query(&mut body, query, description, &types, &mut imports)?;
+    }
+
+    let mut code = format!("{}\n", HEADER);
+    if !imports.is_empty() {
+        writeln!(
+            code,
+            "\nimport type {{ {} }} from \"./{}\";",
+            imports.into_iter().collect::<Vec<_>>().join(", "),
+            SCHEMA_MODULE
+        )
+        .unwrap();
+    }
+    code.push_str(&body);
+    Ok(code)
+}
+
+fn generate_query(
+    code: &mut String,
+    query: &Query,
+    description: &StatementDescription,
+    types: &Types,
+    imports: &mut BTreeSet<String>,
+) -> Result<(), CodegenError> {
+    let error = |message: String| CodegenError::new(&query.path, query.line, message);
+
+    let kind = resolve_kind(query, description)?;
+    if !query.params.is_empty() && query.params.len() != description.parameters.len() {
+        return Err(error(format!(
+            "`-- params:` names {} parameters, but the query has {}",
+            query.params.len(),
+            description.parameters.len()
+        )));
+    }
+
+    let name = pascal_case(&query.name);
+    writeln!(
+        code,
+        "/** `{}` from {}, line {} */",
+        query.name, query.path, query.line
+    )
+    .unwrap();
+    writeln!(
+        code,
+        "export const {} = {};\n",
+        identifier(&camel_case(&query.name)),
+        template_literal(&query.sql)
+    )
+    .unwrap();
+
+    let parameters = description
+        .parameters
+        .iter()
+        .enumerate()
+        .map(|(i, type_name)| {
+            let (name, is_nullable) = match query.params.get(i) {
+                Some(param) => (param.name.clone(), param.nullable),
+                None => (format!("p{}", i + 1), false),
+            };
+            format!(
+                "{}: {}",
+                identifier(&name),
+                nullable(types.resolve(type_name, imports), is_nullable)
+            )
+        })
+        .collect::<Vec<_>>();
+    writeln!(
+        code,
+        "export type {}Params = [{}];",
+        name,
+        parameters.join(", ")
+    )
+    .unwrap();
+
+    if kind != QueryKind::Exec {
+        let mut keys = BTreeSet::new();
+        writeln!(code, "\nexport interface {}Row {{", name).unwrap();
+        for (i, column) in description.columns.iter().enumerate() {
+            let (_, is_nullable) = column_nullability(description, i).map_err(error)?;
+            if !keys.insert(&column.name) {
+                return Err(error(format!(
+                    "More than one column is named `{}`, rename them with `as`",
+                    column.name
+                )));
+            }
+            writeln!(
+                code,
+                "  {}: {};",
+                property(&column.name),
+                nullable(types.resolve(&column.type_name, imports), is_nullable)
+            )
+            .unwrap();
+        }
+        writeln!(code, "}}").unwrap();
+    }
+    Ok(())
+}
+
+/// Returns the name of the type of an enum or table, which is prefixed with its schema unless
+/// it is in `public`
+fn type_name(schema: &str, name: &str) -> String {
+    match schema {
+        "public" => pascal_case(name),
+        _ => pascal_case(&format!("{}_{}", schema, name)),
+    }
+}
+
+fn nullable(ts_type: String, nullable: bool) -> String {
+    match nullable {
+        true => format!("{} | null", ts_type),
+        false => ts_type,
+    }
+}
+
+/// Returns the key of a property, which is quoted unless it is an identifier
+fn property(name: &str) -> String {
+    if is_identifier(name) {
+        name.to_string()
+    } else {
+        string_literal(name)
+    }
+}
+
+/// Escapes names that are reserved words, e.g. `default_`
+fn identifier(name: &str) -> String {
+    match RESERVED_WORDS.contains(&name) {
+        true => format!("{}_", name),
+        false => name.to_string(),
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Returns a double quoted string literal
+fn string_literal(value: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c if c.is_control() => write!(literal, "\\u{{{:x}}}", c as u32).unwrap(),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Returns a template literal, which keeps the lines of the sql
+fn template_literal(sql: &str) -> String {
+    format!(
+        "`{}`",
+        sql.replace('\\', "\\\\")
+            .replace('`', "\\`")
+            .replace("${", "\\${")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use pg_typecheck::{ResultColumn, StatementDescription};
+
+    use crate::parse_queries;
+
+    use super::{generate_typescript, generate_typescript_schema};
+
+    fn column(name: &str, type_name: &str) -> ResultColumn {
+        ResultColumn {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_generate_typescript_schema() {
+        let cache = SchemaCache::from_sql(
+            "create type mood as enum ('happy', 'sad');\n\
+            create schema auth;\n\
+            create table auth.users (id bigint primary key, \"Email\" varchar(100) not null, mood mood, tags text[], data jsonb);",
+        );
+
+        assert_eq!(
+            generate_typescript_schema(&cache),
+            r#"// Generated by `pglsp generate ts`, do not edit.
+
+/** A value of a `json` or `jsonb` column */
+export type Json = string | number | boolean | null | Json[] | { [key: string]: Json };
+
+/** `auth.users` */
+export interface AuthUsers {
+  id: string;
+  Email: string;
+  mood: Mood | null;
+  tags: string[] | null;
+  data: Json | null;
+}
+
+/** `public.mood` */
+export type Mood = "happy" | "sad";
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_typescript() {
+        let cache = SchemaCache::from_sql("create type mood as enum ('happy', 'sad');");
+        let queries = parse_queries(
+            "queries/users.sql",
+            "-- name: find_users\n\
+            -- params: mood?\n\
+            select id, name as \"displayName!\", `mood` from users where mood = $1;\n\n\
+            -- name: delete_user\n\
+            delete from users where id = $1;\n",
+        )
+        .unwrap();
+        let descriptions = vec![
+            StatementDescription {
+                columns: vec![
+                    column("id", "int4"),
+                    column("displayName!", "text"),
+                    column("mood", "mood"),
+                ],
+                nullable: vec![Some(false), None, None],
+                parameters: vec!["mood".to_string()],
+            },
+            StatementDescription {
+                columns: vec![],
+                nullable: vec![],
+                parameters: vec!["int8".to_string()],
+            },
+        ];
+
+        let code = generate_typescript(
+            &queries.into_iter().zip(descriptions).collect::<Vec<_>>(),
+            &cache,
+        );
+
+        assert_eq!(
+            code.unwrap(),
+            r#"// Generated by `pglsp generate ts`, do not edit.
+
+import type { Mood } from "./schema";
+
+/** `find_users` from queries/users.sql, line 3 */
+export const findUsers = `select id, name as "displayName!", \`mood\` from users where mood = $1;`;
+
+export type FindUsersParams = [mood: Mood | null];
+
+export interface FindUsersRow {
+  id: number;
+  "displayName!": string;
+  mood: Mood | null;
+}
+
+/** `delete_user` from queries/users.sql, line 6 */
+export const deleteUser = `delete from users where id = $1;`;
+
+export type DeleteUserParams = [p1: string];
+"#
+        );
+    }
+}
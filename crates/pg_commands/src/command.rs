@@ -1,25 +1,42 @@
 pub enum CommandType {
     ExecuteStatement,
+    ExplainStatement,
+    ReloadSchemaCache,
+    Reconnect,
 }
 
 impl CommandType {
-    pub const ALL: [CommandType; 1] = [CommandType::ExecuteStatement];
+    pub const ALL: [CommandType; 4] = [
+        CommandType::ExecuteStatement,
+        CommandType::ExplainStatement,
+        CommandType::ReloadSchemaCache,
+        CommandType::Reconnect,
+    ];
 
     pub fn id(&self) -> &str {
         match self {
             CommandType::ExecuteStatement => "executeStatement",
+            CommandType::ExplainStatement => "explainStatement",
+            CommandType::ReloadSchemaCache => "reloadSchemaCache",
+            CommandType::Reconnect => "reconnect",
         }
     }
 
     pub fn label(&self) -> &str {
         match self {
             CommandType::ExecuteStatement => "Execute Statement",
+            CommandType::ExplainStatement => "Explain Statement",
+            CommandType::ReloadSchemaCache => "Reload Schema Cache",
+            CommandType::Reconnect => "Reconnect",
         }
     }
 
     pub fn from_id(s: &str) -> Option<CommandType> {
         match s {
             "executeStatement" => Some(CommandType::ExecuteStatement),
+            "explainStatement" => Some(CommandType::ExplainStatement),
+            "reloadSchemaCache" => Some(CommandType::ReloadSchemaCache),
+            "reconnect" => Some(CommandType::Reconnect),
             _ => None,
         }
     }
@@ -1,27 +1,111 @@
-use sqlx::{postgres::PgQueryResult, Executor, PgPool};
+use std::time::Duration;
+
+use async_std::stream::StreamExt;
+use pg_query_ext::{protobuf, ChildrenIterator, NodeEnum};
+use serde::Serialize;
+use sqlx::{postgres::PgRow, Column, Either, Executor, PgPool, Row, TypeInfo, ValueRef};
 
 use crate::command::{Command, CommandType};
 
 pub struct ExecuteStatementCommand {
     statement: String,
+    options: ExecuteOptions,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecuteOptions {
+    /// The maximum number of rows returned
+    pub row_limit: usize,
+    pub timeout: Option<Duration>,
+    /// Whether a statement that modifies the database may be executed
+    pub confirmed: bool,
+}
+
+impl Default for ExecuteOptions {
+    fn default() -> Self {
+        Self {
+            row_limit: 1000,
+            timeout: None,
+            confirmed: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ExecuteResult {
+    /// The statement modifies the database and was not executed, because it was not confirmed
+    ConfirmationRequired,
+    Completed(StatementResult),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementResult {
+    pub columns: Vec<ResultColumn>,
+    /// The values in their text representation
+    pub rows: Vec<Vec<Option<String>>>,
+    pub rows_affected: u64,
+    /// Whether there were more rows than the row limit
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultColumn {
+    pub name: String,
+    pub type_name: String,
 }
 
 impl ExecuteStatementCommand {
-    pub fn new(statement: String) -> Self {
-        Self { statement }
+    pub fn new(statement: String, options: ExecuteOptions) -> Self {
+        Self { statement, options }
+    }
+
+    pub async fn run(&self, conn: Option<PgPool>) -> anyhow::Result<ExecuteResult> {
+        let Some(conn) = conn else {
+            return Err(anyhow::anyhow!("No connection to database".to_string()));
+        };
+
+        if !self.options.confirmed && !is_read_only(&self.statement) {
+            return Ok(ExecuteResult::ConfirmationRequired);
+        }
+
+        let result = match self.options.timeout {
+            Some(timeout) => async_std::future::timeout(timeout, self.fetch(&conn))
+                .await
+                .map_err(|_| {
+                    anyhow::anyhow!("Statement timed out after {} seconds", timeout.as_secs())
+                })?,
+            None => self.fetch(&conn).await,
+        };
+
+        result.map(ExecuteResult::Completed)
     }
 
-    pub async fn run(&self, conn: Option<PgPool>) -> anyhow::Result<PgQueryResult> {
-        if let Some(conn) = conn {
-            match conn.execute(self.statement.as_str()).await {
-                Ok(res) => Ok(res),
-                Err(e) => {
-                    return Err(anyhow::anyhow!(e.to_string()));
+    async fn fetch(&self, conn: &PgPool) -> anyhow::Result<StatementResult> {
+        let mut result = StatementResult::default();
+
+        // without arguments, the statement is sent as a simple query and the values are returned
+        // in their text representation
+        let mut stream = conn.fetch_many(self.statement.as_str());
+        while let Some(item) = stream.next().await {
+            match item.map_err(|e| anyhow::anyhow!(e.to_string()))? {
+                Either::Left(done) => result.rows_affected += done.rows_affected(),
+                Either::Right(row) => {
+                    if result.rows.len() == self.options.row_limit {
+                        result.truncated = true;
+                        break;
+                    }
+                    if result.columns.is_empty() {
+                        result.columns = columns(&row);
+                    }
+                    result.rows.push(values(&row)?);
                 }
             }
-        } else {
-            return Err(anyhow::anyhow!("No connection to database".to_string()));
         }
+
+        Ok(result)
     }
 
     pub fn trim_statement(stmt: String, max_length: usize) -> String {
@@ -38,6 +122,78 @@ impl ExecuteStatementCommand {
     }
 }
 
+fn columns(row: &PgRow) -> Vec<ResultColumn> {
+    row.columns()
+        .iter()
+        .map(|c| ResultColumn {
+            name: c.name().to_string(),
+            type_name: c.type_info().name().to_string(),
+        })
+        .collect()
+}
+
+fn values(row: &PgRow) -> anyhow::Result<Vec<Option<String>>> {
+    (0..row.len())
+        .map(|idx| {
+            let value = row.try_get_raw(idx)?;
+            if value.is_null() {
+                return Ok(None);
+            }
+            value
+                .as_str()
+                .map(|v| Some(v.to_string()))
+                .map_err(|e| anyhow::anyhow!(e.to_string()))
+        })
+        .collect()
+}
+
+/// Whether a statement only reads from the database
+///
+/// Statements that cannot be parsed are assumed to modify the database.
+pub fn is_read_only(statement: &str) -> bool {
+    let Ok(root) = pg_query_ext::parse(statement) else {
+        return false;
+    };
+
+    match &root {
+        NodeEnum::SelectStmt(_) => !modifies(root),
+        NodeEnum::ExplainStmt(stmt) => {
+            let analyze = stmt.options.iter().any(|o| match &o.node {
+                Some(NodeEnum::DefElem(d)) => d.defname == "analyze" && !is_false(d),
+                _ => false,
+            });
+            !analyze || !modifies(root)
+        }
+        NodeEnum::VariableShowStmt(_) => true,
+        _ => false,
+    }
+}
+
+/// Whether a query modifies the database, e.g. with `SELECT ... INTO`, row locks or a
+/// data-modifying CTE
+fn modifies(root: NodeEnum) -> bool {
+    ChildrenIterator::new(root).any(|n| {
+        matches!(
+            n,
+            NodeEnum::InsertStmt(_)
+                | NodeEnum::UpdateStmt(_)
+                | NodeEnum::DeleteStmt(_)
+                | NodeEnum::MergeStmt(_)
+                | NodeEnum::IntoClause(_)
+                | NodeEnum::LockingClause(_)
+        )
+    })
+}
+
+fn is_false(option: &protobuf::DefElem) -> bool {
+    match option.arg.as_ref().and_then(|a| a.node.as_ref()) {
+        Some(NodeEnum::Boolean(b)) => !b.boolval,
+        Some(NodeEnum::String(s)) => matches!(s.sval.as_str(), "false" | "off" | "0"),
+        Some(NodeEnum::Integer(i)) => i.ival == 0,
+        _ => false,
+    }
+}
+
 impl Command for ExecuteStatementCommand {
     type ExecuteStatement = ExecuteStatementCommand;
 
@@ -45,3 +201,71 @@ impl Command for ExecuteStatementCommand {
         CommandType::ExecuteStatement
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use async_std::task::block_on;
+    use sqlx::PgPool;
+
+    use super::{is_read_only, ExecuteOptions, ExecuteResult, ExecuteStatementCommand};
+
+    #[test]
+    fn test_is_read_only() {
+        assert!(is_read_only("select * from users"));
+        assert!(is_read_only("explain delete from users"));
+        assert!(is_read_only("show search_path"));
+        assert!(!is_read_only("explain analyze delete from users"));
+        assert!(!is_read_only("select * from users for update"));
+        assert!(!is_read_only("select * into archive from users"));
+        assert!(!is_read_only(
+            "with d as (delete from users returning id) select * from d"
+        ));
+        assert!(!is_read_only("create table t (id int)"));
+    }
+
+    #[test]
+    fn test_execute_statement() {
+        let conn_string = std::env::var("DB_CONNECTION_STRING").unwrap();
+        let pool = block_on(PgPool::connect(conn_string.as_str())).unwrap();
+
+        let command = ExecuteStatementCommand::new(
+            "select i, null::text as empty from generate_series(1, 3) i".to_string(),
+            ExecuteOptions {
+                row_limit: 2,
+                ..Default::default()
+            },
+        );
+        let ExecuteResult::Completed(result) = block_on(command.run(Some(pool.clone()))).unwrap()
+        else {
+            panic!("statement was not executed");
+        };
+        assert_eq!(
+            result
+                .columns
+                .iter()
+                .map(|c| (c.name.as_str(), c.type_name.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("i", "INT4"), ("empty", "TEXT")]
+        );
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Some("1".to_string()), None],
+                vec![Some("2".to_string()), None]
+            ]
+        );
+        assert!(result.truncated);
+
+        let command = ExecuteStatementCommand::new(
+            "create temporary table t (id int)".to_string(),
+            ExecuteOptions {
+                confirmed: false,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            block_on(command.run(Some(pool))).unwrap(),
+            ExecuteResult::ConfirmationRequired
+        );
+    }
+}
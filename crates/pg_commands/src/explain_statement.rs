@@ -0,0 +1,334 @@
+use serde_json::Value;
+use sqlx::{Connection, Either, Executor, PgPool};
+
+use crate::command::{Command, CommandType};
+
+pub struct ExplainStatementCommand {
+    statement: String,
+    /// Whether to execute the statement to report the actual times and row counts
+    analyze: bool,
+}
+
+impl ExplainStatementCommand {
+    pub fn new(statement: String, analyze: bool) -> Self {
+        Self { statement, analyze }
+    }
+
+    /// Returns the plan of the statement as an indented tree
+    pub async fn run(&self, conn: Option<PgPool>) -> anyhow::Result<String> {
+        let Some(conn) = conn else {
+            return Err(anyhow::anyhow!("No connection to database".to_string()));
+        };
+
+        Ok(format_plan(&self.plan(&conn).await?))
+    }
+
+    /// Returns the plan of the statement in the JSON format of `EXPLAIN`
+    ///
+    /// The statement is explained within a transaction that is rolled back, so that the changes
+    /// of a statement executed by `ANALYZE` are discarded. A statement with parameters, e.g.
+    /// `$1`, is prepared and explained with a generic plan, which does not depend on their values.
+    pub async fn plan(&self, pool: &PgPool) -> anyhow::Result<Value> {
+        let options = if self.analyze {
+            "format json, analyze"
+        } else {
+            "format json"
+        };
+
+        let mut conn = pool.acquire().await?;
+        let parameters = match conn
+            .describe(&self.statement)
+            .await
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?
+            .parameters()
+        {
+            Some(Either::Left(types)) => types.len(),
+            Some(Either::Right(count)) => count,
+            None => 0,
+        };
+
+        let mut tx = conn.begin().await?;
+        let plan = async {
+            let explain = if parameters == 0 {
+                format!("explain ({}) {}", options, self.statement)
+            } else {
+                tx.execute("set local plan_cache_mode = force_generic_plan")
+                    .await?;
+                tx.execute(
+                    format!(
+                        "prepare pglsp_explain as {}",
+                        self.statement.trim_end().trim_end_matches(';')
+                    )
+                    .as_str(),
+                )
+                .await?;
+                format!(
+                    "explain ({}) execute pglsp_explain({})",
+                    options,
+                    vec!["null"; parameters].join(", ")
+                )
+            };
+            sqlx::query_scalar::<_, Value>(&explain)
+                .fetch_one(&mut *tx)
+                .await
+        }
+        .await
+        .map_err(|e| anyhow::anyhow!(e.to_string()));
+        let rollback = tx.rollback().await;
+
+        // prepared statements outlive the transaction, and the connection returns to the pool
+        // even if it could not be rolled back
+        if parameters > 0 {
+            let _ = conn.execute("deallocate pglsp_explain").await;
+        }
+
+        rollback?;
+        plan
+    }
+}
+
+/// Formats the output of `EXPLAIN (FORMAT JSON)` like the text format, i.e. one line per plan
+/// node with its estimates, and its actual values if analyzed, followed by its conditions
+pub fn format_plan(explain: &Value) -> String {
+    let mut lines = Vec::new();
+    for result in explain.as_array().into_iter().flatten() {
+        if let Some(plan) = result.get("Plan") {
+            format_node(plan, 0, &mut lines);
+        }
+        for key in ["Planning Time", "Execution Time"] {
+            if let Some(time) = result.get(key).and_then(Value::as_f64) {
+                lines.push(format!("{}: {:.3} ms", key, time));
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+/// The properties of a plan node that are shown below it
+const DETAILS: [&str; 11] = [
+    "Index Cond",
+    "Recheck Cond",
+    "Hash Cond",
+    "Merge Cond",
+    "Join Filter",
+    "Filter",
+    "Rows Removed by Filter",
+    "Sort Key",
+    "Group Key",
+    "Output",
+    "One-Time Filter",
+];
+
+fn format_node(node: &Value, indent: usize, lines: &mut Vec<String>) {
+    let str = |key: &str| node.get(key).and_then(Value::as_str);
+    let num = |key: &str| node.get(key).and_then(Value::as_f64).unwrap_or_default();
+
+    let mut line = node_name(node);
+    if let Some(index) = str("Index Name") {
+        line.push_str(&format!(" using {}", index));
+    }
+    if let Some(relation) = str("Relation Name")
+        .or(str("CTE Name"))
+        .or(str("Function Name"))
+    {
+        line.push_str(" on ");
+        if let Some(schema) = str("Schema") {
+            line.push_str(&format!("{}.", schema));
+        }
+        line.push_str(relation);
+        if let Some(alias) = str("Alias").filter(|a| *a != relation) {
+            line.push_str(&format!(" {}", alias));
+        }
+    }
+    line.push_str(&format!(
+        "  (cost={:.2}..{:.2} rows={} width={})",
+        num("Startup Cost"),
+        num("Total Cost"),
+        num("Plan Rows"),
+        num("Plan Width")
+    ));
+    if node.get("Actual Loops").is_some() {
+        line.push_str(&format!(
+            " (actual time={:.3}..{:.3} rows={} loops={})",
+            num("Actual Startup Time"),
+            num("Actual Total Time"),
+            num("Actual Rows"),
+            num("Actual Loops")
+        ));
+    }
+
+    let prefix = if indent == 0 {
+        String::new()
+    } else {
+        format!("{}->  ", " ".repeat(indent - 4))
+    };
+    lines.push(format!("{}{}", prefix, line));
+
+    let detail_indent = " ".repeat(indent + 2);
+    for key in DETAILS {
+        let value = match node.get(key) {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|i| {
+                    i.as_str()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| i.to_string())
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            Some(Value::Null) | None => continue,
+            Some(value) => value.to_string(),
+        };
+        lines.push(format!("{}{}: {}", detail_indent, key, value));
+    }
+
+    for child in node
+        .get("Plans")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        format_node(child, indent + 6, lines);
+    }
+}
+
+/// Returns the name of a node like the text format does, e.g. `Hash Left Join` or
+/// `HashAggregate`
+fn node_name(node: &Value) -> String {
+    let node_type = node
+        .get("Node Type")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+
+    let name = match (node_type, node.get("Strategy").and_then(Value::as_str)) {
+        ("Aggregate", Some("Sorted")) => "GroupAggregate".to_string(),
+        ("Aggregate", Some("Hashed")) => "HashAggregate".to_string(),
+        ("Aggregate", Some("Mixed")) => "MixedAggregate".to_string(),
+        _ => node_type.to_string(),
+    };
+
+    match node.get("Join Type").and_then(Value::as_str) {
+        Some(join_type) if join_type != "Inner" => match name.strip_suffix(" Join") {
+            Some(method) => format!("{} {} Join", method, join_type),
+            None => format!("{} {} Join", name, join_type),
+        },
+        _ => match node.get("Scan Direction").and_then(Value::as_str) {
+            Some("Backward") => format!("{} Backward", name),
+            _ => name,
+        },
+    }
+}
+
+impl Command for ExplainStatementCommand {
+    type ExecuteStatement = ExplainStatementCommand;
+
+    fn command_type() -> CommandType {
+        CommandType::ExplainStatement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::task::block_on;
+    use sqlx::PgPool;
+
+    use super::{format_plan, ExplainStatementCommand};
+
+    #[test]
+    fn test_format_plan() {
+        let explain = serde_json::json!([{
+            "Plan": {
+                "Node Type": "Hash Join",
+                "Join Type": "Left",
+                "Startup Cost": 1.09,
+                "Total Cost": 2.2,
+                "Plan Rows": 5,
+                "Plan Width": 36,
+                "Hash Cond": "(o.user_id = u.id)",
+                "Plans": [
+                    {
+                        "Node Type": "Seq Scan",
+                        "Relation Name": "orders",
+                        "Alias": "o",
+                        "Startup Cost": 0.0,
+                        "Total Cost": 1.05,
+                        "Plan Rows": 5,
+                        "Plan Width": 8,
+                        "Filter": "(amount > 10)"
+                    },
+                    {
+                        "Node Type": "Hash",
+                        "Startup Cost": 1.04,
+                        "Total Cost": 1.04,
+                        "Plan Rows": 4,
+                        "Plan Width": 32,
+                        "Plans": [
+                            {
+                                "Node Type": "Seq Scan",
+                                "Relation Name": "users",
+                                "Alias": "users",
+                                "Startup Cost": 0.0,
+                                "Total Cost": 1.04,
+                                "Plan Rows": 4,
+                                "Plan Width": 32
+                            }
+                        ]
+                    }
+                ]
+            },
+            "Planning Time": 0.1234
+        }]);
+
+        assert_eq!(
+            format_plan(&explain),
+            [
+                "Hash Left Join  (cost=1.09..2.20 rows=5 width=36)",
+                "  Hash Cond: (o.user_id = u.id)",
+                "  ->  Seq Scan on orders o  (cost=0.00..1.05 rows=5 width=8)",
+                "        Filter: (amount > 10)",
+                "  ->  Hash  (cost=1.04..1.04 rows=4 width=32)",
+                "        ->  Seq Scan on users  (cost=0.00..1.04 rows=4 width=32)",
+                "Planning Time: 0.123 ms",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_explain_statement() {
+        let conn_string = std::env::var("DB_CONNECTION_STRING").unwrap();
+        let pool = block_on(PgPool::connect(conn_string.as_str())).unwrap();
+
+        let command = ExplainStatementCommand::new(
+            "select * from generate_series(1, 3) i where i > 1".to_string(),
+            true,
+        );
+        let plan = block_on(command.run(Some(pool))).unwrap();
+
+        assert!(plan.starts_with("Function Scan on generate_series i  (cost="));
+        assert!(plan.contains(" (actual time="));
+        assert!(plan.contains("\n  Filter: (i > 1)\n"));
+        assert!(plan.contains("\nExecution Time: "));
+    }
+
+    #[test]
+    fn test_explain_statement_with_parameters() {
+        let conn_string = std::env::var("DB_CONNECTION_STRING").unwrap();
+        let pool = block_on(PgPool::connect(conn_string.as_str())).unwrap();
+
+        let command = ExplainStatementCommand::new(
+            "select * from generate_series(1, 3) i where i > $1;".to_string(),
+            false,
+        );
+        let plan = block_on(command.run(Some(pool.clone()))).unwrap();
+        assert!(plan.contains("\n  Filter: (i > $1)"));
+
+        // the prepared statement is deallocated, also if explaining it fails
+        let failing =
+            ExplainStatementCommand::new("select 1 / (0 * coalesce($1::int, 1))".to_string(), true);
+        assert!(block_on(failing.run(Some(pool.clone()))).is_err());
+        let plan = block_on(command.run(Some(pool))).unwrap();
+        assert!(plan.starts_with("Function Scan on generate_series i"));
+    }
+}
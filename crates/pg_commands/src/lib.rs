@@ -1,5 +1,7 @@
 pub mod command;
 pub mod execute_statement;
+pub mod explain_statement;
 
 pub use command::*;
 pub use execute_statement::*;
+pub use explain_statement::*;
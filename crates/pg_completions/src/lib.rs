@@ -12,6 +12,7 @@ pub struct CompletionParams<'a> {
     pub schema: &'a pg_schema_cache::SchemaCache,
     pub text: &'a str,
     pub tree: Option<&'a tree_sitter::Tree>,
+    pub ast: Option<&'a pg_query_ext::NodeEnum>,
 }
 
 #[derive(Debug, Default)]
@@ -30,12 +31,16 @@ pub struct CompletionItem<'a> {
 #[derive(Debug, PartialEq, Eq)]
 pub enum CompletionItemData<'a> {
     Table(&'a pg_schema_cache::Table),
+    ForeignTable(&'a pg_schema_cache::ForeignTable),
+    Column(&'a pg_schema_cache::Column),
 }
 
 impl<'a> CompletionItemData<'a> {
     pub fn label(&self) -> &'a str {
         match self {
             CompletionItemData::Table(t) => t.name.as_str(),
+            CompletionItemData::ForeignTable(t) => t.name.as_str(),
+            CompletionItemData::Column(c) => c.name.as_str(),
         }
     }
 }
@@ -54,9 +59,8 @@ impl<'a> CompletionItem<'a> {
 pub fn complete<'a>(params: &'a CompletionParams<'a>) -> CompletionResult<'a> {
     let mut builder = builder::CompletionBuilder::from(&builder::CompletionConfig {});
 
-    let params = CompletionProviderParams::from(params);
-
-    providers::complete_tables(params, &mut builder);
+    providers::complete_tables(CompletionProviderParams::from(params), &mut builder);
+    providers::complete_columns(CompletionProviderParams::from(params), &mut builder);
 
     builder.finish()
 }
@@ -67,7 +71,7 @@ mod tests {
     use pg_schema_cache::SchemaCache;
     use sqlx::PgPool;
 
-    use crate::{complete, CompletionParams};
+    use crate::{complete, CompletionItemData, CompletionParams};
 
     #[test]
     fn test_complete() {
@@ -91,6 +95,7 @@ mod tests {
             schema: &schema_cache,
             text: input,
             tree: Some(&tree),
+            ast: None,
         };
 
         let result = complete(&p);
@@ -119,10 +124,39 @@ mod tests {
             schema: &schema_cache,
             text: input,
             tree: Some(&tree),
+            ast: None,
         };
 
         let result = complete(&p);
 
         assert!(result.items.len() > 0);
     }
+
+    #[test]
+    fn test_complete_columns() {
+        let input = "select  from users u join events e on e.user_id = u.id;";
+
+        let schema_cache = SchemaCache::from_sql(
+            "create table users (id int, email text);
+            create table events (user_id int, name text);",
+        );
+        let ast = pg_query_ext::parse(input).unwrap();
+
+        let p = CompletionParams {
+            position: 7.into(),
+            schema: &schema_cache,
+            text: input,
+            tree: None,
+            ast: Some(&ast),
+        };
+
+        let labels = complete(&p)
+            .items
+            .iter()
+            .filter(|i| matches!(i.data, CompletionItemData::Column(_)))
+            .map(|i| i.data.label())
+            .collect::<Vec<_>>();
+
+        assert_eq!(labels, vec!["email", "id", "name", "user_id"]);
+    }
 }
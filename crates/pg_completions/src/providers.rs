@@ -1,12 +1,16 @@
+mod columns;
 mod tables;
 
+pub use columns::complete_columns;
 pub use tables::complete_tables;
 
 use crate::CompletionParams;
 
 #[derive(Debug)]
 pub struct CompletionProviderParams<'a> {
+    pub position: text_size::TextSize,
     pub ts_node: Option<tree_sitter::Node<'a>>,
+    pub ast: Option<&'a pg_query_ext::NodeEnum>,
     pub schema: &'a pg_schema_cache::SchemaCache,
     pub source: &'a str,
 }
@@ -39,7 +43,9 @@ impl<'a> From<&'a CompletionParams<'a>> for CompletionProviderParams<'a> {
         };
 
         Self {
+            position: params.position,
             ts_node,
+            ast: params.ast,
             schema: params.schema,
             source: params.text,
         }
@@ -0,0 +1,38 @@
+use text_size::TextRange;
+
+use crate::{builder::CompletionBuilder, CompletionItem, CompletionItemData};
+
+use super::CompletionProviderParams;
+
+/// Completes the columns of the relations the statement reads from
+pub fn complete_columns<'a>(
+    params: CompletionProviderParams<'a>,
+    builder: &mut CompletionBuilder<'a>,
+) {
+    if params.ts_node.is_some_and(|ts| ts.kind() == "relation") {
+        return;
+    }
+    let Some(ast) = params.ast else {
+        return;
+    };
+
+    let range = params
+        .ts_node
+        .map_or(TextRange::empty(params.position), |ts| {
+            TextRange::new(
+                ts.start_byte().try_into().unwrap(),
+                ts.end_byte().try_into().unwrap(),
+            )
+        });
+
+    pg_type_resolver::Scope::from_statement(ast)
+        .columns(params.schema)
+        .into_iter()
+        .for_each(|column| {
+            builder.items.push(CompletionItem::new_simple(
+                1,
+                range,
+                CompletionItemData::Column(column),
+            ));
+        });
+}
@@ -16,14 +16,37 @@ pub fn complete_tables<'a>(
         );
         match ts.kind() {
             "relation" => {
+                // unqualified names only resolve to relations on the search path
+                let search_path = params
+                    .schema
+                    .effective_search_path(&params.schema.search_path, true);
+
                 // todo better search
                 params.schema.tables.iter().for_each(|table| {
+                    // partitions are usually queried through their parent
+                    let score = if table.is_partition() || !search_path.contains(&&*table.schema) {
+                        0
+                    } else {
+                        1
+                    };
                     builder.items.push(CompletionItem::new_simple(
-                        1,
+                        score,
                         range,
                         CompletionItemData::Table(table),
                     ));
                 });
+                params.schema.foreign_tables.iter().for_each(|table| {
+                    let score = if search_path.contains(&&*table.schema) {
+                        1
+                    } else {
+                        0
+                    };
+                    builder.items.push(CompletionItem::new_simple(
+                        score,
+                        range,
+                        CompletionItemData::ForeignTable(table),
+                    ));
+                });
             }
             _ => {}
         }
@@ -1,16 +1,22 @@
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use text_size::TextRange;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Diagnostic {
     pub message: String,
     pub description: Option<String>,
+    /// Identifies the kind of diagnostic, e.g. the name of a lint rule or the SQLSTATE of a
+    /// typecheck error
+    pub code: Option<String>,
     pub severity: Severity,
     pub source: String,
     pub range: TextRange,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
 /// The severity to associate to a diagnostic.
 pub enum Severity {
     /// Reports a hint.
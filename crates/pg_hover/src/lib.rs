@@ -6,7 +6,9 @@
 
 mod resolve;
 
-use pg_schema_cache::SchemaCache;
+use pg_schema_cache::{Column, Function, Relation, SchemaCache};
+use pg_type_resolver::Scope;
+use pg_typecheck::ResultColumn;
 use resolve::Hoverable;
 use text_size::TextRange;
 
@@ -15,9 +17,14 @@ pub struct HoverParams<'a> {
     pub source: &'a str,
     pub enriched_ast: Option<&'a pg_syntax::AST>,
     pub tree: Option<&'a tree_sitter::Tree>,
-    pub schema_cache: SchemaCache,
+    pub schema_cache: &'a SchemaCache,
+    /// The columns the statement returns, as reported by the typechecker
+    pub result_columns: Option<&'a [ResultColumn]>,
 }
 
+/// Function sources longer than this are cut off
+const MAX_SOURCE_LINES: usize = 10;
+
 #[derive(Debug)]
 pub struct HoverResult {
     pub range: Option<TextRange>,
@@ -38,22 +45,393 @@ pub fn hover(params: HoverParams) -> Option<HoverResult> {
     }
 
     match elem.unwrap() {
+        Hoverable::Column(c) => {
+            // columns are resolved through the relations the statement reads from
+            let scope = Scope::from_statement(&params.enriched_ast?.root_node().node);
+            let qualifier = c
+                .schema
+                .iter()
+                .chain(c.table.iter())
+                .cloned()
+                .collect::<Vec<_>>();
+            let column = scope.resolve_column(params.schema_cache, &qualifier, &c.name)?;
+
+            let mut content = format!(
+                "{}.{} {}",
+                column.table,
+                column.name,
+                column_definition(column)
+            );
+            if let Some(comment) = column.comment.as_ref() {
+                content.push('\n');
+                content.push_str(comment);
+            }
+
+            Some(HoverResult {
+                range: Some(c.range),
+                content,
+            })
+        }
+        Hoverable::Statement(range) => {
+            let columns = params.result_columns?;
+            if columns.is_empty() {
+                return None;
+            }
+
+            let mut content = "Returns".to_string();
+            for column in columns {
+                content.push_str(&format!("\n- {} {}", column.name, column.type_name));
+            }
+
+            Some(HoverResult {
+                range: Some(range),
+                content,
+            })
+        }
+        Hoverable::Function(f) => {
+            let overloads = match f.schema.as_deref() {
+                Some(schema) => params
+                    .schema_cache
+                    .functions
+                    .iter()
+                    .filter(|func| {
+                        func.name.as_deref() == Some(f.name.as_str())
+                            && func.schema.as_deref() == Some(schema)
+                    })
+                    .collect(),
+                None => params
+                    .schema_cache
+                    .resolve_functions(&f.name, &params.schema_cache.search_path),
+            };
+
+            // while typing, the call may not match any overload yet
+            let matching = overloads
+                .iter()
+                .copied()
+                .filter(|func| accepts_arg_count(func, f.arg_count))
+                .collect::<Vec<_>>();
+            let overloads = if matching.is_empty() {
+                overloads
+            } else {
+                matching
+            };
+
+            if overloads.is_empty() {
+                return None;
+            }
+
+            Some(HoverResult {
+                range: Some(f.range),
+                content: overloads
+                    .into_iter()
+                    .map(describe_function)
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
+            })
+        }
         Hoverable::Relation(r) => {
-            let table = params.schema_cache.find_table(&r.name, r.schema.as_deref());
+            let relation = match r.schema.as_deref() {
+                Some(schema) => params
+                    .schema_cache
+                    .find_table(&r.name, Some(schema))
+                    .map(Relation::Table)
+                    .or_else(|| {
+                        params
+                            .schema_cache
+                            .find_foreign_table(&r.name, Some(schema))
+                            .map(Relation::ForeignTable)
+                    }),
+                None => params
+                    .schema_cache
+                    .resolve(&r.name, &params.schema_cache.search_path),
+            };
+
+            relation.map(|relation| match relation {
+                Relation::Table(t) => {
+                    let mut content = t.name.to_owned();
+
+                    if t.comment.is_some() {
+                        content.push_str("\n");
+                        content.push_str(t.comment.as_ref().unwrap());
+                    }
+
+                    push_columns(&mut content, params.schema_cache, t.id);
+
+                    // statistics are only available when the cache was loaded from a database
+                    if t.row_estimate >= 0 && !t.size.is_empty() {
+                        content.push_str(&format!("\nRows: ~{}", t.row_estimate));
+                    }
+                    if !t.size.is_empty() {
+                        content.push_str(&format!("\nSize: {}", t.size));
+                    }
+
+                    if let Some(key) = t.partition_key.as_ref() {
+                        content.push_str(&format!("\nPartitioned by {}", key));
 
-            table.map(|t| {
-                let mut content = t.name.to_owned();
+                        for partition in params.schema_cache.find_partitions(t) {
+                            content
+                                .push_str(&format!("\n- {}.{}", partition.schema, partition.name));
+                            if let Some(bound) = partition.partition_bound.as_ref() {
+                                content.push_str(&format!(" {}", bound));
+                            }
+                        }
+                    }
 
-                if t.comment.is_some() {
-                    content.push_str("\n");
-                    content.push_str(t.comment.as_ref().unwrap());
+                    if let Some(parent) = params.schema_cache.find_partition_parent(t) {
+                        content
+                            .push_str(&format!("\nPartition of {}.{}", parent.schema, parent.name));
+                    }
+
+                    HoverResult {
+                        range: Some(r.range),
+                        content,
+                    }
                 }
+                Relation::ForeignTable(t) => {
+                    let mut content = t.name.to_owned();
+
+                    if let Some(comment) = t.comment.as_ref() {
+                        content.push('\n');
+                        content.push_str(comment);
+                    }
+
+                    content.push_str(&format!("\nForeign table on server {}", t.server_name));
+                    if let Some(server) = params.schema_cache.find_foreign_server(t) {
+                        content.push_str(&format!(" ({})", server.foreign_data_wrapper));
+                    }
 
-                return HoverResult {
-                    range: Some(r.range),
-                    content,
-                };
+                    if !t.options.is_empty() {
+                        content.push_str(&format!("\nOptions: {}", t.options.join(", ")));
+                    }
+
+                    HoverResult {
+                        range: Some(r.range),
+                        content,
+                    }
+                }
             })
         }
     }
 }
+
+/// Lists the columns of a table, e.g. `- id int4 NOT NULL PRIMARY KEY`
+fn push_columns(content: &mut String, schema_cache: &SchemaCache, table_id: i64) {
+    let columns = schema_cache.find_columns(table_id);
+    if columns.is_empty() {
+        return;
+    }
+
+    content.push_str("\nColumns:");
+    for column in columns {
+        content.push_str(&format!(
+            "\n- {} {}",
+            column.name,
+            column_definition(column)
+        ));
+        if let Some(comment) = column.comment.as_ref() {
+            content.push_str(&format!(" -- {}", comment));
+        }
+    }
+}
+
+/// Formats the type and constraints of a column, e.g. `text NOT NULL DEFAULT ''::text`
+fn column_definition(column: &Column) -> String {
+    let mut definition = column.type_name.to_string();
+    if !column.is_nullable {
+        definition.push_str(" NOT NULL");
+    }
+    if column.is_primary_key {
+        definition.push_str(" PRIMARY KEY");
+    }
+    if let Some(default) = column.default_value.as_ref() {
+        definition.push_str(&format!(" DEFAULT {}", default));
+    }
+    definition
+}
+
+/// Whether a function can be called with the given number of arguments
+fn accepts_arg_count(function: &Function, count: usize) -> bool {
+    let inputs = function
+        .args
+        .args
+        .iter()
+        .filter(|a| !matches!(a.mode.as_str(), "out" | "table"))
+        .collect::<Vec<_>>();
+    let is_variadic = inputs.iter().any(|a| a.mode == "variadic");
+    let required = inputs
+        .iter()
+        .filter(|a| a.has_default != Some(true) && a.mode != "variadic")
+        .count();
+
+    count >= required && (is_variadic || count <= inputs.len())
+}
+
+/// Describes a function overload, e.g.
+///
+/// ```text
+/// public.add(a integer, b integer) RETURNS integer
+/// IMMUTABLE, LANGUAGE sql
+/// select a + b
+/// ```
+fn describe_function(function: &Function) -> String {
+    let mut content = format!(
+        "{}.{}({})",
+        function.schema.as_deref().unwrap_or_default(),
+        function.name.as_deref().unwrap_or_default(),
+        function.argument_types.as_deref().unwrap_or_default()
+    );
+    if let Some(return_type) = function.return_type.as_ref() {
+        content.push_str(&format!(" RETURNS {}", return_type));
+    }
+
+    content.push_str(&format!("\n{:?}", function.behavior).to_uppercase());
+    if let Some(language) = function.language.as_ref() {
+        content.push_str(&format!(", LANGUAGE {}", language));
+    }
+    if function.security_definer {
+        content.push_str(", SECURITY DEFINER");
+    }
+
+    // the source of other languages, e.g. c, is a symbol name rather than code
+    if matches!(function.language.as_deref(), Some("sql" | "plpgsql")) {
+        if let Some(source) = function.definition.as_deref().map(str::trim) {
+            let lines = source.lines().collect::<Vec<_>>();
+            content.push('\n');
+            content.push_str(&lines[..lines.len().min(MAX_SOURCE_LINES)].join("\n"));
+            if lines.len() > MAX_SOURCE_LINES {
+                content.push_str("\n...");
+            }
+        }
+    }
+
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use pg_typecheck::ResultColumn;
+    use text_size::TextSize;
+
+    use crate::{hover, HoverParams};
+
+    #[test]
+    fn test_hover_table() {
+        let mut schema_cache = SchemaCache::from_sql(
+            "create table users (id serial primary key, email text not null);
+            comment on table users is 'All users';",
+        );
+
+        let source = "select * from users";
+        let root = pg_query_ext::parse(source).unwrap();
+        let ast = pg_syntax::parse_syntax(source, &root).ast;
+
+        let hover_at = |schema_cache: &SchemaCache| {
+            hover(HoverParams {
+                position: TextSize::new(16),
+                source,
+                enriched_ast: Some(&ast),
+                tree: None,
+                result_columns: None,
+                schema_cache,
+            })
+            .unwrap()
+            .content
+        };
+
+        assert_eq!(
+            hover_at(&schema_cache),
+            "users\nAll users\nColumns:\n- id int4 NOT NULL PRIMARY KEY DEFAULT nextval('users_id_seq'::regclass)\n- email text NOT NULL"
+        );
+
+        schema_cache.tables[0].row_estimate = 1200;
+        schema_cache.tables[0].size = "128 kB".to_string();
+        assert!(hover_at(&schema_cache).ends_with("\nRows: ~1200\nSize: 128 kB"));
+    }
+
+    #[test]
+    fn test_hover_column() {
+        let schema_cache = SchemaCache::from_sql(
+            "create table users (id int, email text not null default '');
+            comment on column users.email is 'Where to send mail';",
+        );
+
+        let source = "select u.email from users u";
+        let root = pg_query_ext::parse(source).unwrap();
+        let ast = pg_syntax::parse_syntax(source, &root).ast;
+
+        let result = hover(HoverParams {
+            position: TextSize::new(10),
+            source,
+            enriched_ast: Some(&ast),
+            tree: None,
+            result_columns: None,
+            schema_cache: &schema_cache,
+        })
+        .unwrap();
+
+        assert_eq!(
+            result.content,
+            "users.email text NOT NULL DEFAULT ''\nWhere to send mail"
+        );
+    }
+
+    #[test]
+    fn test_hover_function() {
+        let schema_cache = SchemaCache::from_sql(
+            "create function add(a int4, b int4) returns int4 language sql immutable as 'select a + b';
+            create function add(a int4) returns int4 language sql as 'select a';",
+        );
+
+        let source = "select add(1, 2)";
+        let root = pg_query_ext::parse(source).unwrap();
+        let ast = pg_syntax::parse_syntax(source, &root).ast;
+
+        let result = hover(HoverParams {
+            position: TextSize::new(8),
+            source,
+            enriched_ast: Some(&ast),
+            tree: None,
+            result_columns: None,
+            schema_cache: &schema_cache,
+        })
+        .unwrap();
+
+        assert_eq!(
+            result.content,
+            "public.add(a int4, b int4) RETURNS int4\nIMMUTABLE, LANGUAGE sql\nselect a + b"
+        );
+    }
+
+    #[test]
+    fn test_hover_statement() {
+        let schema_cache = SchemaCache::new();
+
+        let source = "select id, username as name from contact";
+        let root = pg_query_ext::parse(source).unwrap();
+        let ast = pg_syntax::parse_syntax(source, &root).ast;
+
+        let result_columns = vec![
+            ResultColumn {
+                name: "id".to_string(),
+                type_name: "int4".to_string(),
+            },
+            ResultColumn {
+                name: "name".to_string(),
+                type_name: "text".to_string(),
+            },
+        ];
+
+        let result = hover(HoverParams {
+            position: TextSize::new(2),
+            source,
+            enriched_ast: Some(&ast),
+            tree: None,
+            result_columns: Some(&result_columns),
+            schema_cache: &schema_cache,
+        })
+        .unwrap();
+
+        assert_eq!(result.content, "Returns\n- id int4\n- name text");
+    }
+}
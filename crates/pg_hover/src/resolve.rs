@@ -16,9 +16,22 @@ pub struct HoverableColumn {
     pub range: TextRange,
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub struct HoverableFunction {
+    pub name: String,
+    pub schema: Option<String>,
+    /// The number of arguments the function is called with
+    pub arg_count: usize,
+    pub range: TextRange,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum Hoverable {
     Relation(HoverableRelation),
+    Column(HoverableColumn),
+    Function(HoverableFunction),
+    /// The statement as a whole, e.g. when hovering its leading keyword
+    Statement(TextRange),
 }
 
 pub fn resolve_from_enriched_ast(pos: TextSize, ast: &pg_syntax::AST) -> Option<Hoverable> {
@@ -36,6 +49,49 @@ pub fn resolve_from_enriched_ast(pos: TextSize, ast: &pg_syntax::AST) -> Option<
                 },
             }))
         }
+        pg_query_ext::NodeEnum::ColumnRef(ref column_ref) => {
+            let mut fields = column_ref
+                .fields
+                .iter()
+                .map(|f| match &f.node {
+                    Some(pg_query_ext::NodeEnum::String(s)) => Some(s.sval.clone()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            let name = fields.pop()?;
+            let table = fields.pop();
+            let schema = fields.pop();
+            Some(Hoverable::Column(HoverableColumn {
+                range: node.range(),
+                name,
+                table,
+                schema,
+            }))
+        }
+        pg_query_ext::NodeEnum::FuncCall(ref func_call) => {
+            let mut names = func_call
+                .funcname
+                .iter()
+                .map(|n| match &n.node {
+                    Some(pg_query_ext::NodeEnum::String(s)) => Some(s.sval.clone()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some(Hoverable::Function(HoverableFunction {
+                range: node.range(),
+                name: names.pop()?,
+                schema: names.pop(),
+                arg_count: func_call.args.len(),
+            }))
+        }
+        pg_query_ext::NodeEnum::SelectStmt(_)
+        | pg_query_ext::NodeEnum::InsertStmt(_)
+        | pg_query_ext::NodeEnum::UpdateStmt(_)
+        | pg_query_ext::NodeEnum::DeleteStmt(_)
+            if node.start == ast.root_node().start =>
+        {
+            Some(Hoverable::Statement(node.range()))
+        }
         _ => None,
     }
 }
@@ -71,7 +127,7 @@ pub fn resolve_from_tree_sitter(pos: TextSize, tree: &Tree, source: &str) -> Opt
 mod tests {
     use text_size::{TextRange, TextSize};
 
-    use super::{Hoverable, HoverableRelation};
+    use super::{Hoverable, HoverableColumn, HoverableFunction, HoverableRelation};
 
     #[test]
     fn test_resolve_from_enriched_ast() {
@@ -95,6 +151,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_resolve_column_from_enriched_ast() {
+        let input = "select c.name from public.contact c;";
+        let position = TextSize::new(9);
+
+        let root = pg_query_ext::parse(input).unwrap();
+        let ast = pg_syntax::parse_syntax(input, &root).ast;
+
+        assert_eq!(
+            super::resolve_from_enriched_ast(position, &ast),
+            Some(Hoverable::Column(HoverableColumn {
+                range: TextRange::new(TextSize::new(7), TextSize::new(13)),
+                name: "name".to_string(),
+                table: Some("c".to_string()),
+                schema: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_resolve_function_from_enriched_ast() {
+        let input = "select public.add(1, 2);";
+        let position = TextSize::new(14);
+
+        let root = pg_query_ext::parse(input).unwrap();
+        let ast = pg_syntax::parse_syntax(input, &root).ast;
+
+        assert_eq!(
+            super::resolve_from_enriched_ast(position, &ast),
+            Some(Hoverable::Function(HoverableFunction {
+                range: TextRange::new(TextSize::new(7), TextSize::new(22)),
+                name: "add".to_string(),
+                schema: Some("public".to_string()),
+                arg_count: 2,
+            }))
+        );
+    }
+
     #[test]
     fn test_resolve_from_tree_sitter() {
         let input = "select id from contact;";
@@ -6,6 +6,9 @@ use crate::{
     InlayHintsParams,
 };
 
+/// Calls with fewer arguments are easy enough to read without hints
+const MIN_ARGS: usize = 3;
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct FunctionArgHint {
     pub name: Option<String>,
@@ -24,7 +27,7 @@ impl InlayHintsResolver for FunctionArgHint {
 
         ChildrenIterator::new(root.to_owned())
             .filter_map(|n| match n {
-                pg_query_ext::NodeEnum::FuncCall(source_fn) => {
+                pg_query_ext::NodeEnum::FuncCall(source_fn) if source_fn.args.len() >= MIN_ARGS => {
                     if let Some(schema_fn) = pg_type_resolver::resolve_func_call(
                         source_fn.as_ref(),
                         &params.schema_cache,
@@ -93,7 +96,7 @@ mod tests {
 
     #[test]
     fn test_function_args() {
-        let input = "select lower('TEST')";
+        let input = "select lower('TEST'), replace('TEST', 'T', 't')";
 
         let conn_string = std::env::var("DB_CONNECTION_STRING").unwrap();
 
@@ -106,23 +109,22 @@ mod tests {
         let schema_cache = block_on(SchemaCache::load(&pool));
 
         let hints = FunctionArgHint::find_all(InlayHintsParams {
+            text: input,
             ast: Some(&root),
             tree: None,
             schema_cache: &schema_cache,
             enriched_ast: Some(&res.ast),
             cst: Some(&res.cst),
+            parameter_types: None,
         });
 
-        assert_eq!(hints.len(), 1);
-        assert_eq!(
-            hints[0],
-            InlayHint {
-                offset: 13.into(),
-                content: InlayHintContent::FunctionArg(FunctionArgHint {
-                    name: None,
-                    type_name: "text".to_string(),
-                }),
-            }
-        );
+        let hint = |offset: u32| InlayHint {
+            offset: offset.into(),
+            content: InlayHintContent::FunctionArg(FunctionArgHint {
+                name: None,
+                type_name: "text".to_string(),
+            }),
+        };
+        assert_eq!(hints, vec![hint(30), hint(38), hint(43)]);
     }
 }
@@ -0,0 +1,244 @@
+use pg_query_ext::{
+    protobuf::{a_const::Val, AExprKind, Node},
+    ChildrenIterator, NodeEnum,
+};
+use pg_type_resolver::Scope;
+use text_size::TextSize;
+
+use crate::{
+    inlay_hint::{InlayHint, InlayHintContent, InlayHintsResolver},
+    util::name_end,
+    InlayHintsParams,
+};
+
+/// A cast Postgres adds to an operand of a comparison because the types of both sides differ
+#[derive(Debug, PartialEq, Eq)]
+pub struct ImplicitCastHint {
+    pub type_name: String,
+}
+
+/// The builtin types involved in implicit casts by their oid
+const TYPE_OIDS: &[(&str, i64)] = &[
+    ("int2", 21),
+    ("int4", 23),
+    ("int8", 20),
+    ("numeric", 1700),
+    ("float4", 700),
+    ("float8", 701),
+    ("text", 25),
+    ("varchar", 1043),
+    ("bpchar", 1042),
+];
+
+/// Implicit casts between types that do not have cross-type comparison operators, so that one
+/// side is cast to the type of the other, which keeps an index on that side from being used
+const IMPLICIT_CASTS: &[(&str, &str)] = &[
+    ("int2", "numeric"),
+    ("int4", "numeric"),
+    ("int8", "numeric"),
+    ("int2", "float4"),
+    ("int4", "float4"),
+    ("int8", "float4"),
+    ("int2", "float8"),
+    ("int4", "float8"),
+    ("int8", "float8"),
+    ("numeric", "float4"),
+    ("numeric", "float8"),
+    ("varchar", "text"),
+    ("bpchar", "text"),
+];
+
+const COMPARISON_OPERATORS: &[&str] = &["=", "<>", "!=", "<", ">", "<=", ">="];
+
+impl InlayHintsResolver for ImplicitCastHint {
+    fn find_all(params: InlayHintsParams) -> Vec<InlayHint> {
+        let Some(root) = params.ast else {
+            return vec![];
+        };
+        let scope = Scope::from_statement(root);
+
+        let mut hints = Vec::new();
+        for node in ChildrenIterator::new(root.to_owned()) {
+            let NodeEnum::AExpr(expr) = node else {
+                continue;
+            };
+            let is_comparison = expr.name.iter().any(|n| match &n.node {
+                Some(NodeEnum::String(s)) => COMPARISON_OPERATORS.contains(&s.sval.as_str()),
+                _ => false,
+            });
+            if !is_comparison {
+                continue;
+            }
+            let (Some(lexpr), Some(rexpr)) = (expr.lexpr.as_deref(), expr.rexpr.as_deref()) else {
+                continue;
+            };
+
+            let right = if expr.kind == AExprKind::AexprIn as i32 {
+                // `x in (a, b)` compares `x` with every item
+                match &rexpr.node {
+                    Some(NodeEnum::List(l)) => l.items.iter().collect(),
+                    _ => vec![],
+                }
+            } else if expr.kind == AExprKind::AexprOp as i32 {
+                vec![rexpr]
+            } else {
+                vec![]
+            };
+
+            for rexpr in right {
+                let left_type = operand_type(lexpr, &scope, &params);
+                let right_type = operand_type(rexpr, &scope, &params);
+                let (Some(left_type), Some(right_type)) = (left_type, right_type) else {
+                    continue;
+                };
+
+                let cast = if IMPLICIT_CASTS.contains(&(left_type, right_type)) {
+                    Some((lexpr, right_type))
+                } else if IMPLICIT_CASTS.contains(&(right_type, left_type)) {
+                    Some((rexpr, left_type))
+                } else {
+                    None
+                };
+
+                if let Some(hint) = cast.and_then(|(operand, type_name)| {
+                    Some(InlayHint {
+                        offset: operand_end(operand, params.text)?,
+                        content: InlayHintContent::ImplicitCast(ImplicitCastHint {
+                            type_name: display_name(type_name).to_string(),
+                        }),
+                    })
+                }) {
+                    if !hints.contains(&hint) {
+                        hints.push(hint);
+                    }
+                }
+            }
+        }
+
+        hints.sort_by_key(|h| h.offset);
+        hints
+    }
+}
+
+/// Returns the type of an operand, if it is one of the types involved in implicit casts
+///
+/// String literals are left out, since their type is inferred from the other side.
+fn operand_type(node: &Node, scope: &Scope, params: &InlayHintsParams) -> Option<&'static str> {
+    let name = match node.node.as_ref()? {
+        NodeEnum::ColumnRef(c) => {
+            let mut names = c
+                .fields
+                .iter()
+                .map(|f| match &f.node {
+                    Some(NodeEnum::String(s)) => Some(s.sval.clone()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            let name = names.pop()?;
+            let column = scope.resolve_column(params.schema_cache, &names, &name)?;
+            return TYPE_OIDS
+                .iter()
+                .find(|(_, oid)| *oid == column.type_id)
+                .map(|(name, _)| *name);
+        }
+        NodeEnum::AConst(c) => match c.val.as_ref()? {
+            Val::Ival(_) => "int4",
+            // decimal literals are numeric
+            Val::Fval(_) => "numeric",
+            _ => return None,
+        },
+        NodeEnum::TypeCast(c) => match &c.type_name.as_ref()?.names.last()?.node {
+            Some(NodeEnum::String(s)) => s.sval.as_str(),
+            _ => return None,
+        },
+        NodeEnum::ParamRef(p) => params
+            .parameter_types?
+            .get(usize::try_from(p.number - 1).ok()?)?
+            .as_str(),
+        _ => return None,
+    };
+
+    TYPE_OIDS
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(builtin, _)| *builtin)
+}
+
+fn operand_end(node: &Node, text: &str) -> Option<TextSize> {
+    match node.node.as_ref()? {
+        NodeEnum::TypeCast(c) => name_end(text, c.type_name.as_ref()?.location),
+        n => name_end(text, pg_query_ext::get_location(n)?.try_into().ok()?),
+    }
+}
+
+fn display_name(type_name: &str) -> &str {
+    match type_name {
+        "int2" => "smallint",
+        "int4" => "int",
+        "int8" => "bigint",
+        "float4" => "real",
+        "float8" => "double precision",
+        _ => type_name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+
+    use crate::inlay_hint::{InlayHintContent, InlayHintsParams, InlayHintsResolver};
+
+    use super::ImplicitCastHint;
+
+    #[test]
+    fn test_implicit_casts() {
+        let schema_cache = SchemaCache::from_sql(
+            "create table orders (id int, amount numeric, code varchar(10), score float8);",
+        );
+        let parameter_types = vec!["numeric".to_string()];
+
+        let hints = |input: &'static str| {
+            let root = pg_query_ext::parse(input).unwrap();
+            ImplicitCastHint::find_all(InlayHintsParams {
+                text: input,
+                ast: Some(&root),
+                enriched_ast: None,
+                tree: None,
+                cst: None,
+                schema_cache: &schema_cache,
+                parameter_types: Some(&parameter_types),
+            })
+            .into_iter()
+            .map(|hint| match hint.content {
+                InlayHintContent::ImplicitCast(c) => {
+                    (&input[..usize::from(hint.offset)], c.type_name)
+                }
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            hints("select * from orders where id = 1.5"),
+            vec![("select * from orders where id", "numeric".to_string())]
+        );
+        assert_eq!(
+            hints("select * from orders o where o.code = 'a'::text and amount > $1"),
+            vec![("select * from orders o where o.code", "text".to_string())]
+        );
+        assert_eq!(
+            hints("select * from orders where score in (1, 2)"),
+            vec![
+                (
+                    "select * from orders where score in (1",
+                    "double precision".to_string()
+                ),
+                (
+                    "select * from orders where score in (1, 2",
+                    "double precision".to_string()
+                ),
+            ]
+        );
+        assert!(hints("select * from orders where id = 1 and amount = 2.5").is_empty());
+    }
+}
@@ -1,19 +1,32 @@
 use pg_schema_cache::SchemaCache;
 use text_size::TextSize;
 
-use crate::functions_args::FunctionArgHint;
+use crate::{
+    functions_args::FunctionArgHint,
+    implicit_casts::ImplicitCastHint,
+    insert_columns::{CopyColumnsHint, InsertColumnHint},
+    parameters::ParameterTypeHint,
+};
 
+#[derive(Clone, Copy)]
 pub struct InlayHintsParams<'a> {
+    pub text: &'a str,
     pub ast: Option<&'a pg_query_ext::NodeEnum>,
     pub enriched_ast: Option<&'a pg_syntax::AST>,
     pub tree: Option<&'a tree_sitter::Tree>,
     pub cst: Option<&'a pg_syntax::CST>,
     pub schema_cache: &'a SchemaCache,
+    /// The types of the parameters `$1`, `$2`, ... of the statement, if it was typechecked
+    pub parameter_types: Option<&'a [String]>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum InlayHintContent {
     FunctionArg(FunctionArgHint),
+    InsertColumn(InsertColumnHint),
+    CopyColumns(CopyColumnsHint),
+    ParameterType(ParameterTypeHint),
+    ImplicitCast(ImplicitCastHint),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -0,0 +1,187 @@
+use pg_query_ext::{protobuf, ChildrenIterator, NodeEnum};
+use pg_schema_cache::SchemaCache;
+use pg_type_resolver::ScopeRelation;
+use text_size::TextSize;
+
+use crate::{
+    inlay_hint::{InlayHint, InlayHintContent, InlayHintsResolver},
+    util::name_end,
+    InlayHintsParams,
+};
+
+/// The column a value of an `INSERT ... VALUES` tuple is inserted into
+#[derive(Debug, PartialEq, Eq)]
+pub struct InsertColumnHint {
+    pub name: String,
+}
+
+/// The columns a `COPY` without a column list reads or writes, in order
+#[derive(Debug, PartialEq, Eq)]
+pub struct CopyColumnsHint {
+    pub names: Vec<String>,
+}
+
+impl InlayHintsResolver for InsertColumnHint {
+    fn find_all(params: InlayHintsParams) -> Vec<InlayHint> {
+        let Some(root) = params.ast else {
+            return vec![];
+        };
+
+        ChildrenIterator::new(root.to_owned())
+            .filter_map(|n| match n {
+                NodeEnum::InsertStmt(stmt) => Some(insert_hints(&stmt, params.schema_cache)),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    }
+}
+
+impl InlayHintsResolver for CopyColumnsHint {
+    fn find_all(params: InlayHintsParams) -> Vec<InlayHint> {
+        let Some(NodeEnum::CopyStmt(stmt)) = params.ast else {
+            return vec![];
+        };
+        let Some(relation) = stmt.relation.as_ref().filter(|_| stmt.attlist.is_empty()) else {
+            return vec![];
+        };
+
+        let names = columns(relation, params.schema_cache);
+        if names.is_empty() {
+            return vec![];
+        }
+
+        // the hint is shown where the column list would be, right after the relation name
+        name_end(params.text, relation.location)
+            .map(|offset| InlayHint {
+                offset,
+                content: InlayHintContent::CopyColumns(CopyColumnsHint { names }),
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+fn insert_hints(stmt: &protobuf::InsertStmt, schema_cache: &SchemaCache) -> Vec<InlayHint> {
+    let Some(relation) = &stmt.relation else {
+        return vec![];
+    };
+    let Some(NodeEnum::SelectStmt(select)) =
+        stmt.select_stmt.as_ref().and_then(|s| s.node.as_ref())
+    else {
+        return vec![];
+    };
+    if select.values_lists.is_empty() {
+        return vec![];
+    }
+
+    let names = if stmt.cols.is_empty() {
+        columns(relation, schema_cache)
+    } else {
+        stmt.cols
+            .iter()
+            .filter_map(|c| match &c.node {
+                Some(NodeEnum::ResTarget(t)) => Some(t.name.clone()),
+                _ => None,
+            })
+            .collect()
+    };
+
+    select
+        .values_lists
+        .iter()
+        .filter_map(|values| match &values.node {
+            Some(NodeEnum::List(l)) => Some(&l.items),
+            _ => None,
+        })
+        .flat_map(|values| values.iter().zip(&names))
+        .filter_map(|(value, name)| {
+            let location = pg_query_ext::get_location(value.node.as_ref()?)?;
+            Some(InlayHint {
+                offset: TextSize::try_from(location).ok()?,
+                content: InlayHintContent::InsertColumn(InsertColumnHint { name: name.clone() }),
+            })
+        })
+        .collect()
+}
+
+/// Returns the names of the columns of a relation in their order
+fn columns(relation: &protobuf::RangeVar, schema_cache: &SchemaCache) -> Vec<String> {
+    let relation = ScopeRelation {
+        schema: Some(relation.schemaname.clone()).filter(|s| !s.is_empty()),
+        name: relation.relname.clone(),
+        alias: None,
+    };
+
+    relation
+        .resolve(schema_cache)
+        .map(|r| {
+            schema_cache
+                .find_columns(r.id())
+                .into_iter()
+                .map(|c| c.name.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+
+    use crate::inlay_hint::{InlayHintContent, InlayHintsParams, InlayHintsResolver};
+
+    use super::{CopyColumnsHint, InsertColumnHint};
+
+    #[test]
+    fn test_insert_columns() {
+        let schema_cache =
+            SchemaCache::from_sql("create table users (id int, name text, email text);");
+
+        let hints = |input: &str| {
+            let root = pg_query_ext::parse(input).unwrap();
+            let params = InlayHintsParams {
+                text: input,
+                ast: Some(&root),
+                enriched_ast: None,
+                tree: None,
+                cst: None,
+                schema_cache: &schema_cache,
+                parameter_types: None,
+            };
+            InsertColumnHint::find_all(params)
+                .into_iter()
+                .chain(CopyColumnsHint::find_all(params))
+                .map(|hint| {
+                    let label = match hint.content {
+                        InlayHintContent::InsertColumn(c) => c.name,
+                        InlayHintContent::CopyColumns(c) => c.names.join(", "),
+                        _ => unreachable!(),
+                    };
+                    (u32::from(hint.offset), label)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            hints("insert into users values (1, 'a', default), (2, 'b', 'c')"),
+            vec![
+                (26, "id".to_string()),
+                (29, "name".to_string()),
+                (34, "email".to_string()),
+                (45, "id".to_string()),
+                (48, "name".to_string()),
+                (53, "email".to_string()),
+            ]
+        );
+        assert_eq!(
+            hints("insert into users (email, id) values ('c', 3)"),
+            vec![(38, "email".to_string()), (43, "id".to_string())]
+        );
+        assert_eq!(
+            hints("copy public.users from stdin"),
+            vec![(17, "id, name, email".to_string())]
+        );
+        assert!(hints("copy users (id) from stdin").is_empty());
+    }
+}
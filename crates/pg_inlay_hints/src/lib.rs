@@ -1,15 +1,26 @@
 mod functions_args;
+mod implicit_casts;
 mod inlay_hint;
+mod insert_columns;
+mod parameters;
+mod util;
 
 use inlay_hint::InlayHintsResolver;
 
 use crate::functions_args::FunctionArgHint;
+use crate::implicit_casts::ImplicitCastHint;
 pub use crate::inlay_hint::{InlayHint, InlayHintContent, InlayHintsParams};
+use crate::insert_columns::{CopyColumnsHint, InsertColumnHint};
+use crate::parameters::ParameterTypeHint;
 
 pub fn inlay_hints(params: InlayHintsParams) -> Vec<InlayHint> {
     let mut hints = vec![];
 
     hints.extend(FunctionArgHint::find_all(params));
+    hints.extend(InsertColumnHint::find_all(params));
+    hints.extend(CopyColumnsHint::find_all(params));
+    hints.extend(ParameterTypeHint::find_all(params));
+    hints.extend(ImplicitCastHint::find_all(params));
 
     hints
 }
@@ -0,0 +1,78 @@
+use pg_query_ext::{ChildrenIterator, NodeEnum};
+use text_size::TextSize;
+
+use crate::{
+    inlay_hint::{InlayHint, InlayHintContent, InlayHintsResolver},
+    InlayHintsParams,
+};
+
+/// The type the server inferred for a parameter placeholder, e.g. `$1`
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParameterTypeHint {
+    pub type_name: String,
+}
+
+impl InlayHintsResolver for ParameterTypeHint {
+    fn find_all(params: InlayHintsParams) -> Vec<InlayHint> {
+        let (Some(root), Some(parameter_types)) = (params.ast, params.parameter_types) else {
+            return vec![];
+        };
+
+        let mut hints = ChildrenIterator::new(root.to_owned())
+            .filter_map(|n| match n {
+                NodeEnum::ParamRef(p) => {
+                    let type_name = parameter_types.get(usize::try_from(p.number - 1).ok()?)?;
+                    // the hint follows the placeholder
+                    let placeholder = format!("${}", p.number);
+                    let start = TextSize::try_from(usize::try_from(p.location).ok()?).ok()?;
+                    Some(InlayHint {
+                        offset: start + TextSize::of(placeholder.as_str()),
+                        content: InlayHintContent::ParameterType(ParameterTypeHint {
+                            type_name: type_name.clone(),
+                        }),
+                    })
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        hints.sort_by_key(|h| h.offset);
+        hints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+
+    use crate::inlay_hint::{InlayHint, InlayHintContent, InlayHintsParams, InlayHintsResolver};
+
+    use super::ParameterTypeHint;
+
+    #[test]
+    fn test_parameter_types() {
+        let input = "select * from users where id = $1 and name = $2 or id = $1";
+        let root = pg_query_ext::parse(input).unwrap();
+        let parameter_types = vec!["int4".to_string(), "text".to_string()];
+
+        let hints = ParameterTypeHint::find_all(InlayHintsParams {
+            text: input,
+            ast: Some(&root),
+            enriched_ast: None,
+            tree: None,
+            cst: None,
+            schema_cache: &SchemaCache::new(),
+            parameter_types: Some(&parameter_types),
+        });
+
+        let hint = |offset: u32, type_name: &str| InlayHint {
+            offset: offset.into(),
+            content: InlayHintContent::ParameterType(ParameterTypeHint {
+                type_name: type_name.to_string(),
+            }),
+        };
+        assert_eq!(
+            hints,
+            vec![hint(33, "int4"), hint(47, "text"), hint(58, "int4")]
+        );
+    }
+}
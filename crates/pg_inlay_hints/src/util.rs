@@ -0,0 +1,18 @@
+use pg_lexer::SyntaxKind;
+use text_size::TextSize;
+
+/// Returns the end of the possibly qualified name, or of the single token, that starts at
+/// `location`
+pub(crate) fn name_end(text: &str, location: i32) -> Option<TextSize> {
+    let location = TextSize::try_from(usize::try_from(location).ok()?).ok()?;
+    let tokens = pg_lexer::lex(text)
+        .into_iter()
+        .filter(|t| !matches!(t.kind, SyntaxKind::Whitespace | SyntaxKind::Newline))
+        .collect::<Vec<_>>();
+
+    let mut idx = tokens.iter().position(|t| t.span.start() == location)?;
+    while idx + 2 < tokens.len() && tokens[idx + 1].kind == SyntaxKind::Ascii46 {
+        idx += 2;
+    }
+    Some(tokens[idx].span.end())
+}
@@ -108,14 +108,18 @@ fn whitespace_tokens(input: &str) -> VecDeque<Token> {
 ///
 /// The implementation is primarily using libpg_querys `scan` method, and fills in the gaps with tokens that are not parsed by the library, e.g. whitespace.
 pub fn lex(text: &str) -> Vec<Token> {
+    // this _should_ never fail
+    try_lex(text).expect("pg_query::scan failed")
+}
+
+/// Like `lex`, but returns `None` instead of panicking if the text cannot be scanned, e.g. because
+/// of an unterminated string.
+pub fn try_lex(text: &str) -> Option<Vec<Token>> {
+    let _span = tracing::debug_span!("lex").entered();
     let mut whitespace_tokens = whitespace_tokens(text);
 
     // tokens from pg_query.rs
-    let mut pg_query_tokens = match pg_query::scan(text) {
-        Ok(scanned) => VecDeque::from(scanned.tokens),
-        // this _should_ never fail
-        _ => panic!("pg_query::scan failed"),
-    };
+    let mut pg_query_tokens = VecDeque::from(pg_query::scan(text).ok()?.tokens);
 
     // merge the two token lists
     let mut tokens: Vec<Token> = Vec::new();
@@ -167,10 +171,11 @@ pub fn lex(text: &str) -> Vec<Token> {
             continue;
         }
 
-        panic!("No token found at position {}", pos);
+        // no token found at this position
+        return None;
     }
 
-    tokens
+    Some(tokens)
 }
 
 #[cfg(test)]
@@ -228,4 +233,10 @@ mod tests {
         assert_eq!(token.kind, SyntaxKind::Iconst);
         assert_eq!(token.text, "2");
     }
+
+    #[test]
+    fn test_try_lex() {
+        assert_eq!(try_lex("select 1").map(|tokens| tokens.len()), Some(3));
+        assert_eq!(try_lex("select 'unterminated"), None);
+    }
 }
@@ -0,0 +1,93 @@
+//! The columns that the column references of a statement refer to, for the rules that depend on
+//! their types, nullability or indexes
+
+use pg_query_ext::{protobuf::ColumnRef, NodeEnum};
+use pg_schema_cache::{Column, SchemaCache, Table};
+
+use crate::{rules::index_column, tree::NodeTree};
+
+/// The known tables of a statement, by the names they are referenced with
+///
+/// The tables of subqueries are in scope everywhere, so a column that is not qualified only
+/// resolves if a single table of the statement has it.
+pub(crate) struct StatementColumns<'a> {
+    schema_cache: &'a SchemaCache,
+    /// The tables by their alias, or by their name if they have none
+    tables: Vec<(&'a str, &'a Table)>,
+}
+
+impl<'a> StatementColumns<'a> {
+    pub fn new(tree: &'a NodeTree, schema_cache: &'a SchemaCache) -> StatementColumns<'a> {
+        let tables = tree
+            .nodes()
+            .filter_map(|(_, node)| match &node.inner {
+                NodeEnum::RangeVar(relation) => {
+                    let schema =
+                        (!relation.schemaname.is_empty()).then_some(relation.schemaname.as_str());
+                    let table = schema_cache.find_table(&relation.relname, schema)?;
+                    let name = relation
+                        .alias
+                        .as_ref()
+                        .map_or(relation.relname.as_str(), |alias| alias.aliasname.as_str());
+                    Some((name, table))
+                }
+                _ => None,
+            })
+            .collect();
+        StatementColumns {
+            schema_cache,
+            tables,
+        }
+    }
+
+    /// Returns the column a reference refers to, if its table is known and it is not ambiguous
+    pub fn resolve(&self, column: &ColumnRef) -> Option<&'a Column> {
+        let names = column
+            .fields
+            .iter()
+            .map(|field| match &field.node {
+                Some(NodeEnum::String(s)) => Some(s.sval.as_str()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let (qualifier, name) = match names.as_slice() {
+            [name] => (None, *name),
+            [.., qualifier, name] => (Some(*qualifier), *name),
+            [] => return None,
+        };
+
+        let mut columns = self
+            .tables
+            .iter()
+            .filter(|(table_name, _)| qualifier.is_none_or(|q| q == *table_name))
+            .filter_map(|(_, table)| self.schema_cache.find_column(table.id, name));
+        let first = columns.next()?;
+        columns
+            .all(|column| column.table_id == first.table_id)
+            .then_some(first)
+    }
+
+    /// Returns whether a column is the leading key of an index, so that comparisons with the
+    /// column itself can use the index
+    pub fn is_indexed(&self, column: &Column) -> bool {
+        self.schema_cache
+            .find_indexes(column.table_id)
+            .iter()
+            .any(|index| {
+                index
+                    .columns
+                    .first()
+                    .is_some_and(|key| index_column(key) == column.name)
+            })
+    }
+
+    /// Returns whether a column is used by a key of an index on an expression, e.g.
+    /// `lower(email)`, which the expression of a comparison may match
+    pub fn has_expression_index(&self, column: &Column) -> bool {
+        self.schema_cache
+            .find_indexes(column.table_id)
+            .iter()
+            .flat_map(|index| &index.columns)
+            .any(|key| key.contains('(') && key.contains(column.name.as_str()))
+    }
+}
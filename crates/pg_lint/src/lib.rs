@@ -1,38 +1,163 @@
-use lazy_static::lazy_static;
-use text_size::TextSize;
-pub use violations::{RuleViolation, RuleViolationKind, ViolationMessage};
+use std::collections::HashMap;
 
-use crate::rules::ban_drop_column::ban_drop_column;
+use pg_query_ext::NodeEnum;
+use pg_schema_cache::SchemaCache;
+pub use rules::{parse_options, rule, Rule, RuleCategory, RuleMetadata, RULES};
+use serde_json::Value;
+use text_size::{TextRange, TextSize};
+use tree::NodeTree;
+pub use violations::{
+    RuleFix, RuleViolation, RuleViolationKind, SchemaViolation, ViolationMessage,
+};
 
+mod columns;
 mod rules;
+mod tree;
 mod violations;
 
 pub struct LinterParams<'a> {
+    /// The text of the statement, which the locations of the nodes of its AST point into
+    pub sql: &'a str,
     pub ast: &'a pg_query_ext::NodeEnum,
     pub enriched_ast: Option<&'a pg_syntax::AST>,
+    /// The schema of the database the statement is checked against, which is empty if there is
+    /// none
+    pub schema_cache: &'a SchemaCache,
+    /// The options of the rules that are configured, see [`Rule::validate_options`]
+    pub options: &'a HashMap<RuleViolationKind, Value>,
 }
 
-#[derive(Clone)]
-pub struct LintRule {
-    pub name: RuleViolationKind,
-    func: fn(&LinterParams) -> Vec<RuleViolation>,
-    pub messages: Vec<ViolationMessage>,
-}
+impl LinterParams<'_> {
+    /// Returns the range of a node of the AST, if the statement has an enriched AST
+    pub fn enriched_range(&self, node: &pg_query_ext::NodeEnum) -> Option<TextRange> {
+        let ranged = self.enriched_ast?.iter_nodes().find(|n| n.node == *node)?;
+        Some(TextRange::new(ranged.start, ranged.end?))
+    }
+
+    /// Returns whether a table is known not to exist yet, i.e. it is created by the migration
+    /// the statement belongs to
+    ///
+    /// Without a schema, or if its schema is not loaded, every table may exist.
+    pub fn is_new_table(&self, relation: &pg_query_ext::protobuf::RangeVar) -> bool {
+        let schema = (!relation.schemaname.is_empty()).then_some(relation.schemaname.as_str());
+        !self.schema_cache.tables.is_empty()
+            && schema.is_none_or(|schema| self.schema_cache.is_schema_loaded(schema))
+            && self
+                .schema_cache
+                .find_table(&relation.relname, schema)
+                .is_none()
+    }
+
+    /// Returns the range of the name of a table, from its location to the first whitespace or
+    /// parenthesis that is not quoted
+    pub fn relation_range(&self, relation: &pg_query_ext::protobuf::RangeVar) -> Option<TextRange> {
+        self.name_range(relation.location)
+    }
+
+    /// Returns the range of a possibly qualified name at a location, e.g. of a role, which ends
+    /// at the first whitespace, parenthesis, comma, semicolon or operator that is not quoted
+    pub fn name_range(&self, location: i32) -> Option<TextRange> {
+        let start = usize::try_from(location).ok()?;
+        let rest = self.sql.get(start..)?;
+        let mut quoted = false;
+        let len = rest
+            .char_indices()
+            .find(|(_, c)| {
+                if *c == '"' {
+                    quoted = !quoted;
+                }
+                !quoted
+                    && (c.is_whitespace()
+                        || matches!(c, '(' | ')' | ',' | ';' | '=' | '<' | '>' | '!' | ':' | '~'))
+            })
+            .map_or(rest.len(), |(idx, _)| idx);
+        Some(TextRange::new(
+            TextSize::try_from(start).ok()?,
+            TextSize::try_from(start + len).ok()?,
+        ))
+    }
+
+    /// Returns the range from a location to the end of the first string literal after it, e.g.
+    /// of `PASSWORD 'secret'` or of the literal itself
+    pub fn literal_range(&self, location: i32) -> Option<TextRange> {
+        let start = usize::try_from(location).ok()?;
+        let rest = self.sql.get(start..)?;
+        let quote = rest.find('\'')?;
+        let mut chars = rest[quote + 1..].char_indices().peekable();
+        let end = loop {
+            let (idx, c) = chars.next()?;
+            if c == '\'' {
+                // a quote within the literal is escaped by doubling it
+                if chars.next_if(|(_, c)| *c == '\'').is_none() {
+                    break quote + 1 + idx + 1;
+                }
+            }
+        };
+        Some(TextRange::new(
+            TextSize::try_from(start).ok()?,
+            TextSize::try_from(start + end).ok()?,
+        ))
+    }
+
+    /// Returns the range from a location to the end of the first occurrence of a lowercase
+    /// keyword after it in any case, e.g. of `SECURITY DEFINER`
+    pub fn keyword_range(&self, location: i32, keyword: &str) -> Option<TextRange> {
+        let start = usize::try_from(location).ok()?;
+        let end =
+            start + self.sql.get(start..)?.to_ascii_lowercase().find(keyword)? + keyword.len();
+        Some(TextRange::new(
+            TextSize::try_from(start).ok()?,
+            TextSize::try_from(end).ok()?,
+        ))
+    }
 
-lazy_static! {
-    pub static ref RULES: Vec<LintRule> = vec![LintRule {
-        name: RuleViolationKind::BanDropColumn,
-        func: ban_drop_column,
-        messages: vec![ViolationMessage::Note(
-            "Dropping a column may break existing clients.".into()
-        ),],
-    }];
+    /// Returns the tokens of the statement, or none if it cannot be scanned
+    pub fn tokens(&self) -> Vec<pg_lexer::Token> {
+        pg_lexer::try_lex(self.sql)
+            // the ranges of tokens after characters of more than a byte are off, which fixes
+            // must not edit
+            .filter(|tokens| {
+                tokens.iter().all(|token| {
+                    self.sql.get(std::ops::Range::<usize>::from(token.span))
+                        == Some(token.text.as_str())
+                })
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the ranges of the names of the tables and columns that the statement references or
+    /// defines, e.g. `public.users` or `u.email`, but not of aliases
+    pub fn identifier_ranges(&self) -> Vec<TextRange> {
+        let tree = NodeTree::new(self.ast);
+        tree.nodes()
+            .filter_map(|(idx, node)| match &node.inner {
+                NodeEnum::RangeVar(relation) => Some(relation.location),
+                NodeEnum::ColumnRef(column) => Some(column.location),
+                NodeEnum::ColumnDef(column) => Some(column.location),
+                // the columns of `UPDATE ... SET` and `INSERT INTO ... (...)`
+                NodeEnum::ResTarget(target)
+                    if matches!(
+                        tree.ancestors(idx).next(),
+                        Some(NodeEnum::UpdateStmt(_) | NodeEnum::InsertStmt(_))
+                    ) =>
+                {
+                    Some(target.location)
+                }
+                _ => None,
+            })
+            .filter_map(|location| self.name_range(location))
+            .collect()
+    }
 }
 
 pub fn check_sql(params: LinterParams) -> Vec<RuleViolation> {
     let mut errs = vec![];
     for rule in RULES.iter() {
-        errs.extend((rule.func)(&params));
+        let options = params
+            .options
+            .get(&rule.metadata().kind)
+            .unwrap_or(&Value::Null);
+        errs.extend(rule.run(&params, options));
     }
 
     errs.sort_by_key(|v| match v.range {
@@ -42,3 +167,45 @@ pub fn check_sql(params: LinterParams) -> Vec<RuleViolation> {
 
     errs
 }
+
+/// Checks the existing objects of a database against the rules that check the schema, sorted by
+/// object
+pub fn check_schema(
+    schema_cache: &SchemaCache,
+    options: &HashMap<RuleViolationKind, Value>,
+) -> Vec<SchemaViolation> {
+    let mut violations = RULES
+        .iter()
+        .flat_map(|rule| {
+            let options = options.get(&rule.metadata().kind).unwrap_or(&Value::Null);
+            rule.run_schema(schema_cache, options)
+        })
+        .collect::<Vec<_>>();
+    violations.sort_by(|a, b| a.object.cmp(&b.object));
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pg_schema_cache::SchemaCache;
+
+    use crate::{check_sql, LinterParams, RuleViolationKind};
+
+    #[test]
+    fn test_check_sql() {
+        let ast = pg_query_ext::parse("ALTER TABLE users DROP COLUMN email;").unwrap();
+        let violations = check_sql(LinterParams {
+            sql: "ALTER TABLE users DROP COLUMN email;",
+            ast: &ast,
+            enriched_ast: None,
+            schema_cache: &SchemaCache::default(),
+            options: &HashMap::new(),
+        });
+        assert_eq!(
+            violations.iter().map(|v| &v.kind).collect::<Vec<_>>(),
+            vec![&RuleViolationKind::BanDropColumn]
+        );
+    }
+}
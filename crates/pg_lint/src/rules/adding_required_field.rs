@@ -0,0 +1,133 @@
+use pg_query_ext::{
+    protobuf::{AlterTableType, ConstrType},
+    NodeEnum,
+};
+use serde_json::Value;
+
+use crate::{
+    violations::{RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags adding a `NOT NULL` column without a default to an existing table, which fails if the
+/// table has rows
+pub struct AddingRequiredField;
+
+impl Rule for AddingRequiredField {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::AddingRequiredField,
+            category: RuleCategory::Safety,
+            docs: "https://www.postgresql.org/docs/current/sql-altertable.html",
+            messages: vec![
+                ViolationMessage::Note(
+                    "Adding a `NOT NULL` column without a default fails if the table has rows."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Add a default, or add the column as nullable, fill it and set it to `NOT NULL` afterwards."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let NodeEnum::AlterTableStmt(stmt) = params.ast else {
+            return Vec::new();
+        };
+        if stmt
+            .relation
+            .as_ref()
+            .is_some_and(|relation| params.is_new_table(relation))
+        {
+            return Vec::new();
+        }
+
+        stmt.cmds
+            .iter()
+            .filter_map(|cmd| match &cmd.node {
+                Some(NodeEnum::AlterTableCmd(cmd))
+                    if cmd.subtype() == AlterTableType::AtAddColumn =>
+                {
+                    match cmd.def.as_ref()?.node.as_ref()? {
+                        NodeEnum::ColumnDef(column) => Some(column),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+            .filter(|column| {
+                let constraints = column
+                    .constraints
+                    .iter()
+                    .filter_map(|c| match &c.node {
+                        Some(NodeEnum::Constraint(constraint)) => Some(constraint.contype()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                // identity and generated columns are filled by Postgres
+                column.raw_default.is_none()
+                    && constraints
+                        .iter()
+                        .any(|c| matches!(c, ConstrType::ConstrNotnull | ConstrType::ConstrPrimary))
+                    && !constraints.iter().any(|c| {
+                        matches!(
+                            c,
+                            ConstrType::ConstrDefault
+                                | ConstrType::ConstrIdentity
+                                | ConstrType::ConstrGenerated
+                        )
+                    })
+            })
+            .map(|column| {
+                let range = params.enriched_range(&NodeEnum::ColumnDef(column.clone()));
+                RuleViolation::new(RuleViolationKind::AddingRequiredField, range, None)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::Value;
+
+    use crate::rules::run_rule;
+
+    use super::AddingRequiredField;
+
+    #[test]
+    fn test_adding_required_field() {
+        let violations = |sql| {
+            run_rule(
+                &AddingRequiredField,
+                sql,
+                &SchemaCache::default(),
+                Value::Null,
+            )
+            .len()
+        };
+        assert_eq!(
+            violations("alter table users add column email text not null"),
+            1
+        );
+        assert_eq!(
+            violations("alter table users add column id int primary key"),
+            1
+        );
+        assert_eq!(
+            violations("alter table users add column email text, add column name text not null"),
+            1
+        );
+        assert_eq!(violations("alter table users add column email text"), 0);
+        assert_eq!(
+            violations("alter table users add column email text not null default ''"),
+            0
+        );
+        assert_eq!(
+            violations("alter table users add column id int not null generated always as identity"),
+            0
+        );
+    }
+}
@@ -1,43 +1,62 @@
+use serde_json::Value;
+
 use crate::{
-    violations::{RuleViolation, RuleViolationKind},
-    LinterParams,
+    violations::{RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
 };
 
-pub fn ban_drop_column(params: &LinterParams) -> Vec<RuleViolation> {
-    let mut errs: Vec<RuleViolation> = vec![];
+/// Flags dropping a column, which breaks the clients that still read it
+pub struct BanDropColumn;
 
-    if let Some(enriched_ast) = params.enriched_ast {
-        if let pg_query_ext::NodeEnum::AlterTableStmt(_) = &enriched_ast.root_node().node {
-            for node in enriched_ast.iter_nodes() {
-                if let pg_query_ext::NodeEnum::AlterTableCmd(cmd) = &node.node {
-                    if cmd.subtype() == pg_query_ext::protobuf::AlterTableType::AtDropColumn {
-                        errs.push(RuleViolation::new(
-                            RuleViolationKind::BanDropColumn,
-                            Some(node.range()),
-                            None,
-                        ));
-                    }
-                }
-            }
+impl Rule for BanDropColumn {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::BanDropColumn,
+            category: RuleCategory::Safety,
+            docs: "https://www.postgresql.org/docs/current/sql-altertable.html",
+            messages: vec![ViolationMessage::Note(
+                "Dropping a column may break existing clients.".into(),
+            )],
         }
-    } else {
-        match &params.ast {
-            pg_query_ext::NodeEnum::AlterTableStmt(stmt) => {
-                for cmd in &stmt.cmds {
-                    if let Some(pg_query_ext::NodeEnum::AlterTableCmd(cmd)) = &cmd.node {
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let mut errs: Vec<RuleViolation> = vec![];
+
+        if let Some(enriched_ast) = params.enriched_ast {
+            if let pg_query_ext::NodeEnum::AlterTableStmt(_) = &enriched_ast.root_node().node {
+                for node in enriched_ast.iter_nodes() {
+                    if let pg_query_ext::NodeEnum::AlterTableCmd(cmd) = &node.node {
                         if cmd.subtype() == pg_query_ext::protobuf::AlterTableType::AtDropColumn {
                             errs.push(RuleViolation::new(
                                 RuleViolationKind::BanDropColumn,
-                                None,
+                                Some(node.range()),
                                 None,
                             ));
                         }
                     }
                 }
             }
-            _ => {}
+        } else {
+            match &params.ast {
+                pg_query_ext::NodeEnum::AlterTableStmt(stmt) => {
+                    for cmd in &stmt.cmds {
+                        if let Some(pg_query_ext::NodeEnum::AlterTableCmd(cmd)) = &cmd.node {
+                            if cmd.subtype() == pg_query_ext::protobuf::AlterTableType::AtDropColumn
+                            {
+                                errs.push(RuleViolation::new(
+                                    RuleViolationKind::BanDropColumn,
+                                    None,
+                                    None,
+                                ));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
         }
-    }
 
-    errs
+        errs
+    }
 }
@@ -0,0 +1,172 @@
+use pg_query_ext::{protobuf::SubLinkType, NodeEnum};
+use serde::Deserialize;
+use serde_json::Value;
+use text_size::{TextRange, TextSize};
+
+use crate::{
+    parse_options,
+    tree::NodeTree,
+    violations::{RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags `SELECT *`, whose columns change when columns are added to a table and which fetches
+/// columns that are not needed
+///
+/// `count(*)` and stars in the arguments of functions, e.g. `row_to_json(t.*)`, are not
+/// projections and always allowed.
+pub struct BanSelectStar;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+struct BanSelectStarOptions {
+    /// Whether `SELECT *` is allowed in `EXISTS` subqueries, whose columns are not used
+    allow_in_exists: bool,
+    /// Whether `SELECT *` is allowed within common table expressions, whose columns are
+    /// selected again by the statement they belong to
+    allow_in_cte: bool,
+}
+
+impl Default for BanSelectStarOptions {
+    fn default() -> Self {
+        Self {
+            allow_in_exists: true,
+            allow_in_cte: true,
+        }
+    }
+}
+
+impl Rule for BanSelectStar {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::BanSelectStar,
+            category: RuleCategory::Correctness,
+            docs: "https://www.postgresql.org/docs/current/sql-select.html",
+            messages: vec![
+                ViolationMessage::Note(
+                    "`SELECT *` changes its columns when columns are added to a table.".into(),
+                ),
+                ViolationMessage::Help("Select the columns that are needed by name.".into()),
+            ],
+        }
+    }
+
+    fn validate_options(&self, options: &Value) -> Result<(), String> {
+        parse_options::<BanSelectStarOptions>(options).map(|_| ())
+    }
+
+    fn run(&self, params: &LinterParams, options: &Value) -> Vec<RuleViolation> {
+        let options = parse_options::<BanSelectStarOptions>(options).unwrap_or_default();
+        let tree = NodeTree::new(params.ast);
+
+        tree.nodes()
+            .filter_map(|(idx, node)| match &node.inner {
+                NodeEnum::ColumnRef(column) => Some((idx, column)),
+                _ => None,
+            })
+            .filter(|(_, column)| {
+                column
+                    .fields
+                    .iter()
+                    .any(|field| matches!(field.node, Some(NodeEnum::AStar(_))))
+            })
+            .filter(|(idx, _)| {
+                let mut ancestors = tree.ancestors(*idx);
+                // only the stars in the target list of a select are projections
+                matches!(ancestors.next(), Some(NodeEnum::ResTarget(_)))
+                    && matches!(ancestors.next(), Some(NodeEnum::SelectStmt(_)))
+            })
+            .filter(|(idx, _)| {
+                !tree.ancestors(*idx).any(|ancestor| match ancestor {
+                    NodeEnum::SubLink(sub_link) => {
+                        options.allow_in_exists
+                            && sub_link.sub_link_type() == SubLinkType::ExistsSublink
+                    }
+                    NodeEnum::CommonTableExpr(_) => options.allow_in_cte,
+                    _ => false,
+                })
+            })
+            .map(|(_, column)| {
+                RuleViolation::new(
+                    RuleViolationKind::BanSelectStar,
+                    star_range(params.sql, column.location),
+                    None,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Returns the range of a column reference with a star, e.g. `t.*`, from its start to the star
+fn star_range(sql: &str, location: i32) -> Option<TextRange> {
+    let start = usize::try_from(location).ok()?;
+    let star = start + sql.get(start..)?.find('*')?;
+    Some(TextRange::new(
+        TextSize::try_from(start).ok()?,
+        TextSize::try_from(star + 1).ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pg_schema_cache::SchemaCache;
+    use serde_json::{json, Value};
+
+    use crate::{LinterParams, Rule};
+
+    use super::BanSelectStar;
+
+    fn select_stars(sql: &str, options: Value) -> Vec<&str> {
+        let ast = pg_query_ext::parse(sql).unwrap();
+        let params = LinterParams {
+            sql,
+            ast: &ast,
+            enriched_ast: None,
+            schema_cache: &SchemaCache::default(),
+            options: &HashMap::new(),
+        };
+        BanSelectStar
+            .run(&params, &options)
+            .into_iter()
+            .map(|v| &sql[v.range.unwrap()])
+            .collect()
+    }
+
+    #[test]
+    fn test_ban_select_star() {
+        assert_eq!(select_stars("select * from users", Value::Null), vec!["*"]);
+        assert_eq!(
+            select_stars(
+                "select u.*, p.id from users u join posts p on true",
+                Value::Null
+            ),
+            vec!["u.*"]
+        );
+        assert_eq!(
+            select_stars("insert into archive select * from users", Value::Null),
+            vec!["*"]
+        );
+        assert!(select_stars(
+            "select count(*), row_to_json(u.*) from users u",
+            Value::Null
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_ban_select_star_allowances() {
+        let sql = "with recent as (select * from users) \
+            select id from recent where exists (select * from posts)";
+        assert!(select_stars(sql, Value::Null).is_empty());
+        assert_eq!(
+            select_stars(sql, json!({ "allowInExists": false, "allowInCte": false })),
+            vec!["*", "*"]
+        );
+        assert_eq!(select_stars(sql, json!({ "allowInCte": false })), vec!["*"]);
+        assert!(BanSelectStar
+            .validate_options(&json!({ "allowInCtes": false }))
+            .is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use pg_query_ext::{protobuf::AlterTableType, NodeEnum};
+use serde_json::Value;
+
+use crate::{
+    violations::{RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags changing the type of a column, which rewrites the table and its indexes for most
+/// types while holding an exclusive lock, and may break existing clients
+pub struct ChangingColumnType;
+
+impl Rule for ChangingColumnType {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::ChangingColumnType,
+            category: RuleCategory::Safety,
+            docs: "https://www.postgresql.org/docs/current/sql-altertable.html",
+            messages: vec![ViolationMessage::Note(
+                "Changing the type of a column may rewrite the table while locking it, and break existing clients."
+                    .into(),
+            )],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let NodeEnum::AlterTableStmt(stmt) = params.ast else {
+            return Vec::new();
+        };
+
+        stmt.cmds
+            .iter()
+            .filter_map(|cmd| match &cmd.node {
+                Some(NodeEnum::AlterTableCmd(cmd))
+                    if cmd.subtype() == AlterTableType::AtAlterColumnType =>
+                {
+                    Some(cmd)
+                }
+                _ => None,
+            })
+            .map(|cmd| {
+                let range = params.enriched_range(&NodeEnum::AlterTableCmd(cmd.clone()));
+                RuleViolation::new(RuleViolationKind::ChangingColumnType, range, None)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::Value;
+
+    use crate::rules::run_rule;
+
+    use super::ChangingColumnType;
+
+    #[test]
+    fn test_changing_column_type() {
+        let violations = |sql| {
+            run_rule(
+                &ChangingColumnType,
+                sql,
+                &SchemaCache::default(),
+                Value::Null,
+            )
+            .len()
+        };
+        assert_eq!(
+            violations("alter table users alter column id type bigint"),
+            1
+        );
+        assert_eq!(
+            violations("alter table users alter column id set not null"),
+            0
+        );
+    }
+}
@@ -0,0 +1,480 @@
+use pg_lexer::{SyntaxKind, WHITESPACE_TOKENS};
+use pg_query_ext::{
+    protobuf::{a_const::Val, ColumnDef, TypeName},
+    NodeEnum,
+};
+use serde_json::Value;
+use text_size::TextRange;
+
+use crate::{
+    tree::NodeTree,
+    violations::{RuleFix, RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags `varchar(n)` columns, whose limit is arbitrary and can only be raised by changing the
+/// type of the column
+///
+/// The fix of columns that are created changes them to `text` with a check constraint on their
+/// length.
+pub struct PreferTextField;
+
+/// Flags `timestamp` columns without a time zone, which store the local time of whoever wrote
+/// them rather than a point in time
+pub struct PreferTimestamptz;
+
+/// Flags `money` columns, whose fractional precision depends on the `lc_monetary` setting of the
+/// database
+pub struct BanMoneyType;
+
+/// Flags `serial` columns, whose sequences are separate objects with their own privileges,
+/// rather than identity columns
+pub struct PreferIdentity;
+
+/// Flags `char(n)` columns, which pad their values with spaces that comparisons ignore
+pub struct BanCharField;
+
+impl Rule for PreferTextField {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::PreferTextField,
+            category: RuleCategory::Correctness,
+            docs: "https://wiki.postgresql.org/wiki/Don%27t_Do_This#Don.27t_use_varchar.28n.29_by_default",
+            messages: vec![
+                ViolationMessage::Note(
+                    "The limit of a `varchar(n)` column can only be raised by changing its type, which locks the table."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Use `text` with a check constraint on its length, which can be replaced without a lock."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let tree = NodeTree::new(params.ast);
+        column_types(&tree)
+            .into_iter()
+            .filter(|column| column.name == "varchar")
+            .filter_map(|column| Some((typmods(column.type_name).first().copied()?, column)))
+            .map(|(length, column)| {
+                let range = type_range(params, column.type_name);
+                let violation = RuleViolation::new(RuleViolationKind::PreferTextField, range, None);
+                // the type of an existing column is changed without a name to check
+                let name = (!column.def.colname.is_empty())
+                    .then(|| params.name_range(column.def.location))
+                    .flatten();
+                match (range, name) {
+                    (Some(range), Some(name)) if column.type_name.array_bounds.is_empty() => {
+                        let text = &params.sql[range];
+                        let replacement = format!(
+                            "{} ({}({}) <= {})",
+                            match_case(text, "text check"),
+                            match_case(text, "char_length"),
+                            &params.sql[name],
+                            length
+                        );
+                        violation.with_fix(RuleFix {
+                            title: format!("Change `{}` to `{}`", text, replacement),
+                            edits: vec![(range, replacement)],
+                            safe: false,
+                        })
+                    }
+                    _ => violation,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Rule for PreferTimestamptz {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::PreferTimestamptz,
+            category: RuleCategory::Correctness,
+            docs: "https://wiki.postgresql.org/wiki/Don%27t_Do_This#Don.27t_use_timestamp_.28without_time_zone.29",
+            messages: vec![
+                ViolationMessage::Note(
+                    "A `timestamp` without a time zone is a local time that is ambiguous without knowing where it was written."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Use `timestamptz`, which stores a point in time and converts it to the time zone of each session."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let tree = NodeTree::new(params.ast);
+        column_types(&tree)
+            .into_iter()
+            .filter(|column| column.name == "timestamp")
+            .map(|column| {
+                let range = type_range(params, column.type_name);
+                let violation =
+                    RuleViolation::new(RuleViolationKind::PreferTimestamptz, range, None);
+                let Some(range) = range else {
+                    return violation;
+                };
+                let text = &params.sql[range];
+                let precision = match typmods(column.type_name).first() {
+                    Some(precision) => format!("({})", precision),
+                    None => String::new(),
+                };
+                let replacement = match_case(text, &format!("timestamptz{}", precision));
+                violation.with_fix(RuleFix {
+                    title: format!("Change `{}` to `{}`", text, replacement),
+                    edits: vec![(range, replacement)],
+                    // existing values are read as times in the time zone of the session
+                    safe: false,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Rule for BanMoneyType {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::BanMoneyType,
+            category: RuleCategory::Correctness,
+            docs: "https://wiki.postgresql.org/wiki/Don%27t_Do_This#Don.27t_use_money",
+            messages: vec![
+                ViolationMessage::Note(
+                    "The precision of `money` depends on the `lc_monetary` setting of the database, and it has no currency."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Use `numeric`, and store the currency in another column if there are several."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let tree = NodeTree::new(params.ast);
+        column_types(&tree)
+            .into_iter()
+            .filter(|column| column.name == "money")
+            .map(|column| {
+                RuleViolation::new(
+                    RuleViolationKind::BanMoneyType,
+                    type_range(params, column.type_name),
+                    None,
+                )
+            })
+            .collect()
+    }
+}
+
+impl Rule for PreferIdentity {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::PreferIdentity,
+            category: RuleCategory::Correctness,
+            docs: "https://wiki.postgresql.org/wiki/Don%27t_Do_This#Don.27t_use_serial",
+            messages: vec![
+                ViolationMessage::Note(
+                    "The sequence of a `serial` column is a separate object, which needs its own privileges and is not copied with the table."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Use an identity column, e.g. `bigint generated by default as identity`."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let tree = NodeTree::new(params.ast);
+        column_types(&tree)
+            .into_iter()
+            .filter_map(|column| {
+                let integer = match column.name {
+                    "smallserial" | "serial2" => "smallint",
+                    "serial" | "serial4" => "integer",
+                    "bigserial" | "serial8" => "bigint",
+                    _ => return None,
+                };
+                Some((integer, column))
+            })
+            .map(|(integer, column)| {
+                let range = type_range(params, column.type_name);
+                let violation = RuleViolation::new(RuleViolationKind::PreferIdentity, range, None);
+                let Some(range) = range else {
+                    return violation;
+                };
+                let text = &params.sql[range];
+                let replacement = match_case(
+                    text,
+                    &format!("{} generated by default as identity", integer),
+                );
+                violation.with_fix(RuleFix {
+                    title: format!("Change `{}` to `{}`", text, replacement),
+                    edits: vec![(range, replacement)],
+                    // the sequence has another name, which clients may use
+                    safe: false,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Rule for BanCharField {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::BanCharField,
+            category: RuleCategory::Correctness,
+            docs: "https://wiki.postgresql.org/wiki/Don%27t_Do_This#Don.27t_use_char.28n.29",
+            messages: vec![
+                ViolationMessage::Note(
+                    "`char(n)` pads its values with spaces, which comparisons and casts ignore inconsistently."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Use `text`, with a check constraint on its length if it is fixed.".into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let tree = NodeTree::new(params.ast);
+        column_types(&tree)
+            .into_iter()
+            .filter(|column| column.name == "bpchar")
+            .map(|column| {
+                RuleViolation::new(
+                    RuleViolationKind::BanCharField,
+                    type_range(params, column.type_name),
+                    None,
+                )
+            })
+            .collect()
+    }
+}
+
+/// The type of a column that is created or changed
+struct ColumnType<'a> {
+    def: &'a ColumnDef,
+    type_name: &'a TypeName,
+    /// The name of the type without its schema, e.g. `varchar` for `character varying(255)`
+    name: &'a str,
+}
+
+/// Returns the types of the columns of a statement, e.g. of `CREATE TABLE`, `ADD COLUMN` or
+/// `ALTER COLUMN ... TYPE`
+fn column_types(tree: &NodeTree) -> Vec<ColumnType<'_>> {
+    tree.nodes()
+        .filter_map(|(_, node)| match &node.inner {
+            NodeEnum::ColumnDef(def) => {
+                let type_name = def.type_name.as_ref()?;
+                match type_name.names.last()?.node.as_ref()? {
+                    NodeEnum::String(name) => Some(ColumnType {
+                        def,
+                        type_name,
+                        name: name.sval.as_str(),
+                    }),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the integer modifiers of a type, e.g. the length of `varchar(255)`
+fn typmods(type_name: &TypeName) -> Vec<i32> {
+    type_name
+        .typmods
+        .iter()
+        .filter_map(|typmod| match typmod.node.as_ref()? {
+            NodeEnum::AConst(constant) => match constant.val.as_ref()? {
+                Val::Ival(value) => Some(value.ival),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the range of a type from its location, e.g. of `character varying(255)` or of
+/// `timestamp(3) without time zone`, without its array bounds
+fn type_range(params: &LinterParams, type_name: &TypeName) -> Option<TextRange> {
+    let tokens = params
+        .tokens()
+        .into_iter()
+        .filter(|token| !WHITESPACE_TOKENS.contains(&token.kind))
+        .collect::<Vec<_>>();
+    let location = u32::try_from(type_name.location).ok()?;
+    let start = tokens
+        .iter()
+        .position(|token| u32::from(token.span.start()) == location)?;
+
+    let mut end = tokens[start].span.end();
+    let mut depth = 0;
+    let mut after_dot = false;
+    for token in &tokens[start + 1..] {
+        match token.kind {
+            SyntaxKind::Ascii40 => depth += 1,
+            SyntaxKind::Ascii41 if depth > 0 => depth -= 1,
+            _ if depth > 0 => {}
+            // e.g. `pg_catalog.varchar`
+            SyntaxKind::Ascii46 => after_dot = true,
+            _ if after_dot => after_dot = false,
+            // e.g. `character varying` or `timestamp without time zone`
+            SyntaxKind::Varying
+            | SyntaxKind::Without
+            | SyntaxKind::With
+            | SyntaxKind::Time
+            | SyntaxKind::Zone
+            | SyntaxKind::Precision => {}
+            _ => break,
+        }
+        end = token.span.end();
+    }
+    Some(TextRange::new(tokens[start].span.start(), end))
+}
+
+/// Returns the keywords of a replacement for a type in upper case if the type is written in upper
+/// case
+fn match_case(text: &str, keywords: &str) -> String {
+    if text.chars().any(|c| c.is_ascii_uppercase()) && !text.chars().any(|c| c.is_ascii_lowercase())
+    {
+        keywords.to_ascii_uppercase()
+    } else {
+        keywords.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pg_schema_cache::SchemaCache;
+    use serde_json::Value;
+
+    use crate::{rules::run_rule, LinterParams, Rule};
+
+    use super::{BanCharField, BanMoneyType, PreferIdentity, PreferTextField, PreferTimestamptz};
+
+    fn fixes(rule: &dyn Rule, sql: &str) -> Vec<String> {
+        let ast = pg_query_ext::parse(sql).unwrap();
+        let params = LinterParams {
+            sql,
+            ast: &ast,
+            enriched_ast: None,
+            schema_cache: &SchemaCache::default(),
+            options: &HashMap::new(),
+        };
+        rule.run(&params, &Value::Null)
+            .into_iter()
+            .filter_map(|v| v.fix)
+            .flat_map(|fix| {
+                assert!(!fix.safe);
+                fix.edits.into_iter().map(|(_, text)| text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_prefer_text_field() {
+        let sql = "create table users (id int, email varchar(255) not null, tags varchar(20)[], name varchar)";
+        assert_eq!(
+            run_rule(&PreferTextField, sql, &SchemaCache::default(), Value::Null),
+            vec![Some("varchar(255)"), Some("varchar(20)")]
+        );
+        assert_eq!(
+            fixes(&PreferTextField, sql),
+            vec!["text check (char_length(email) <= 255)"]
+        );
+        let sql = "ALTER TABLE users ADD COLUMN \"Name\" CHARACTER VARYING (50)";
+        assert_eq!(
+            run_rule(&PreferTextField, sql, &SchemaCache::default(), Value::Null),
+            vec![Some("CHARACTER VARYING (50)")]
+        );
+        assert_eq!(
+            fixes(&PreferTextField, sql),
+            vec!["TEXT CHECK (CHAR_LENGTH(\"Name\") <= 50)"]
+        );
+        // the type of an existing column is changed without a name for the check
+        assert!(fixes(
+            &PreferTextField,
+            "alter table users alter column email type varchar(320)"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_prefer_timestamptz() {
+        let sql = "create table events (a timestamp, b timestamp(3) without time zone, c timestamptz, d timestamp with time zone)";
+        assert_eq!(
+            run_rule(
+                &PreferTimestamptz,
+                sql,
+                &SchemaCache::default(),
+                Value::Null
+            ),
+            vec![Some("timestamp"), Some("timestamp(3) without time zone")]
+        );
+        assert_eq!(
+            fixes(&PreferTimestamptz, sql),
+            vec!["timestamptz", "timestamptz(3)"]
+        );
+    }
+
+    #[test]
+    fn test_ban_money_type() {
+        assert_eq!(
+            run_rule(
+                &BanMoneyType,
+                "alter table orders add column total money default 0",
+                &SchemaCache::default(),
+                Value::Null
+            ),
+            vec![Some("money")]
+        );
+        assert!(run_rule(
+            &BanMoneyType,
+            "create table orders (total numeric(12, 2))",
+            &SchemaCache::default(),
+            Value::Null
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_prefer_identity() {
+        let sql = "create table users (id bigserial primary key, n serial, m smallint)";
+        assert_eq!(
+            run_rule(&PreferIdentity, sql, &SchemaCache::default(), Value::Null),
+            vec![Some("bigserial"), Some("serial")]
+        );
+        assert_eq!(
+            fixes(&PreferIdentity, sql),
+            vec![
+                "bigint generated by default as identity",
+                "integer generated by default as identity"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ban_char_field() {
+        assert_eq!(
+            run_rule(
+                &BanCharField,
+                "create table countries (code char(2), name character(10), c \"char\", v varchar(3))",
+                &SchemaCache::default(),
+                Value::Null
+            ),
+            vec![Some("char(2)"), Some("character(10)")]
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use pg_query_ext::{protobuf::AlterTableType, NodeEnum};
+use pg_schema_cache::{quote_ident, SchemaCache, Table};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    parse_options,
+    violations::{RuleViolation, RuleViolationKind, SchemaViolation, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags tables in the schemas that are exposed to clients, e.g. by the API of Supabase, which
+/// have no row level security or no policies
+///
+/// Without row level security, every client that is granted access to the table can read and
+/// change all of its rows. With it but without policies, clients can access none of them.
+/// Statements are only checked if they disable row level security, since it is enabled after a
+/// table is created.
+pub struct ExposedTableWithoutRls;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+struct ExposedTableWithoutRlsOptions {
+    /// The schemas that are exposed to clients
+    schemas: Vec<String>,
+}
+
+impl Default for ExposedTableWithoutRlsOptions {
+    fn default() -> Self {
+        Self {
+            schemas: vec!["public".to_string()],
+        }
+    }
+}
+
+impl Rule for ExposedTableWithoutRls {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::ExposedTableWithoutRls,
+            category: RuleCategory::Security,
+            docs: "https://www.postgresql.org/docs/current/ddl-rowsecurity.html",
+            messages: vec![
+                ViolationMessage::Note(
+                    "The table is exposed to clients without row level security, so they can read and change all of its rows."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Enable row level security on the table and add policies for the rows that clients may access."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn validate_options(&self, options: &Value) -> Result<(), String> {
+        parse_options::<ExposedTableWithoutRlsOptions>(options).map(|_| ())
+    }
+
+    fn run(&self, params: &LinterParams, options: &Value) -> Vec<RuleViolation> {
+        let options = parse_options::<ExposedTableWithoutRlsOptions>(options).unwrap_or_default();
+        let NodeEnum::AlterTableStmt(stmt) = params.ast else {
+            return Vec::new();
+        };
+        let Some(relation) = &stmt.relation else {
+            return Vec::new();
+        };
+        let schema = match relation.schemaname.as_str() {
+            "" => params
+                .schema_cache
+                .find_table(&relation.relname, None)
+                .map_or("public", |table| &table.schema),
+            schema => schema,
+        };
+        if !options.schemas.iter().any(|s| s == schema) {
+            return Vec::new();
+        }
+
+        stmt.cmds
+            .iter()
+            .filter(|cmd| {
+                matches!(
+                    &cmd.node,
+                    Some(NodeEnum::AlterTableCmd(cmd))
+                        if cmd.subtype() == AlterTableType::AtDisableRowSecurity
+                )
+            })
+            .map(|_| {
+                RuleViolation::new(
+                    RuleViolationKind::ExposedTableWithoutRls,
+                    params.relation_range(relation),
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    fn run_schema(&self, schema_cache: &SchemaCache, options: &Value) -> Vec<SchemaViolation> {
+        let options = parse_options::<ExposedTableWithoutRlsOptions>(options).unwrap_or_default();
+        schema_cache
+            .tables
+            .iter()
+            .filter(|table| options.schemas.iter().any(|s| *s == *table.schema))
+            .filter_map(|table| {
+                let messages = if !table.rls_enabled {
+                    without_rls(table)
+                } else if schema_cache.find_policies(table.id).is_empty() {
+                    without_policies()
+                } else {
+                    return None;
+                };
+                Some(SchemaViolation::new(
+                    RuleViolationKind::ExposedTableWithoutRls,
+                    format!("{}.{}", table.schema, table.name),
+                    Some(messages),
+                ))
+            })
+            .collect()
+    }
+}
+
+fn without_rls(table: &Table) -> Vec<ViolationMessage> {
+    vec![
+        ViolationMessage::Note(
+            "The table is exposed to clients without row level security, so they can read and change all of its rows."
+                .into(),
+        ),
+        ViolationMessage::Help(format!(
+            "Enable row level security and add policies: `ALTER TABLE {}.{} ENABLE ROW LEVEL SECURITY;`",
+            quote_ident(&table.schema),
+            quote_ident(&table.name)
+        )),
+    ]
+}
+
+fn without_policies() -> Vec<ViolationMessage> {
+    vec![
+        ViolationMessage::Note(
+            "Row level security is enabled on the table, but it has no policies, so clients can access none of its rows."
+                .into(),
+        ),
+        ViolationMessage::Help(
+            "Add policies with `CREATE POLICY`, or revoke the privileges of the clients if they should not access the table."
+                .into(),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::{json, Value};
+
+    use crate::{rules::run_rule, Rule, ViolationMessage};
+
+    use super::ExposedTableWithoutRls;
+
+    const SCHEMA: &str = r#"
+create schema api;
+create schema private;
+create table profiles (id int primary key);
+create table api.posts (id int primary key);
+alter table api.posts enable row level security;
+create policy "public posts" on api.posts for select using (true);
+create table api.drafts (id int primary key);
+alter table api.drafts enable row level security;
+create table private.secrets (id int primary key);
+"#;
+
+    #[test]
+    fn test_exposed_table_without_rls() {
+        let schema_cache = SchemaCache::from_sql(SCHEMA);
+        let violations =
+            |sql, options| run_rule(&ExposedTableWithoutRls, sql, &schema_cache, options);
+        assert_eq!(
+            violations(
+                "alter table profiles disable row level security;",
+                Value::Null
+            ),
+            vec![Some("profiles")]
+        );
+        assert_eq!(
+            violations(
+                "alter table api.posts disable row level security;",
+                json!({ "schemas": ["api"] })
+            ),
+            vec![Some("api.posts")]
+        );
+        assert!(violations(
+            "alter table api.posts disable row level security;",
+            Value::Null
+        )
+        .is_empty());
+        assert!(violations(
+            "alter table profiles enable row level security;",
+            Value::Null
+        )
+        .is_empty());
+        assert!(ExposedTableWithoutRls
+            .validate_options(&json!({ "schema": "api" }))
+            .is_err());
+    }
+
+    #[test]
+    fn test_exposed_table_without_rls_in_schema() {
+        let schema_cache = SchemaCache::from_sql(SCHEMA);
+        let violations = ExposedTableWithoutRls
+            .run_schema(&schema_cache, &json!({ "schemas": ["public", "api"] }));
+        assert_eq!(
+            violations
+                .iter()
+                .map(|v| v.object.as_str())
+                .collect::<Vec<_>>(),
+            vec!["public.profiles", "api.drafts"]
+        );
+        assert_eq!(
+            violations[0].messages[1],
+            ViolationMessage::Help(
+                "Enable row level security and add policies: `ALTER TABLE public.profiles ENABLE ROW LEVEL SECURITY;`"
+                    .into()
+            )
+        );
+        assert!(matches!(
+            &violations[1].messages[0],
+            ViolationMessage::Note(note) if note.contains("no policies")
+        ));
+
+        let violations = ExposedTableWithoutRls.run_schema(&schema_cache, &Value::Null);
+        assert_eq!(violations.len(), 1);
+    }
+}
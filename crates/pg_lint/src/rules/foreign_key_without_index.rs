@@ -0,0 +1,252 @@
+use pg_query_ext::{
+    protobuf::{AlterTableType, ConstrType, Constraint},
+    NodeEnum,
+};
+use pg_schema_cache::{is_system_schema, quote_ident, ForeignKey, SchemaCache};
+use serde_json::Value;
+use text_size::{TextRange, TextSize};
+
+use crate::{
+    rules::index_column,
+    violations::{RuleFix, RuleViolation, RuleViolationKind, SchemaViolation, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags foreign keys whose columns have no index, so that deleting or updating a referenced row
+/// scans the referencing table while holding its locks
+///
+/// Statements are only checked if they add a foreign key to an existing table, since the index of
+/// a new table is usually created after it.
+pub struct ForeignKeyWithoutIndex;
+
+impl Rule for ForeignKeyWithoutIndex {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::ForeignKeyWithoutIndex,
+            category: RuleCategory::Performance,
+            docs: "https://www.postgresql.org/docs/current/ddl-constraints.html#DDL-CONSTRAINTS-FK",
+            messages: vec![
+                ViolationMessage::Note(
+                    "The columns of the foreign key have no index, so deleting or updating a referenced row scans the table while it is locked."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Create an index on the columns with `CREATE INDEX CONCURRENTLY`.".into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let NodeEnum::AlterTableStmt(stmt) = params.ast else {
+            return Vec::new();
+        };
+        let Some(relation) = &stmt.relation else {
+            return Vec::new();
+        };
+        let schema = (!relation.schemaname.is_empty()).then_some(relation.schemaname.as_str());
+        let Some(table) = params.schema_cache.find_table(&relation.relname, schema) else {
+            return Vec::new();
+        };
+
+        stmt.cmds
+            .iter()
+            .filter_map(|cmd| match &cmd.node {
+                Some(NodeEnum::AlterTableCmd(cmd)) => match (cmd.subtype(), &cmd.def.as_ref()?.node)
+                {
+                    (AlterTableType::AtAddConstraint, Some(NodeEnum::Constraint(c)))
+                        if c.contype() == ConstrType::ConstrForeign =>
+                    {
+                        Some(c.fk_attrs.iter().filter_map(string_value).collect())
+                    }
+                    // a new column has no index yet
+                    (AlterTableType::AtAddColumn, Some(NodeEnum::ColumnDef(column)))
+                        if column.constraints.iter().any(
+                            |c| matches!(&c.node, Some(NodeEnum::Constraint(c)) if is_foreign(c)),
+                        ) =>
+                    {
+                        Some(vec![column.colname.clone()])
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .filter(|columns: &Vec<String>| {
+                !columns.is_empty() && !has_index(params.schema_cache, table.id, columns)
+            })
+            .map(|columns| {
+                let table_name = params
+                    .relation_range(relation)
+                    .map_or_else(|| quote_ident(&relation.relname), |r| params.sql[r].into());
+                let statement = create_index(&relation.relname, &table_name, &columns);
+                let end = TextSize::of(params.sql);
+                let separator = if params.sql.trim_end().ends_with(';') {
+                    "\n"
+                } else {
+                    ";\n"
+                };
+                RuleViolation::new(RuleViolationKind::ForeignKeyWithoutIndex, None, None).with_fix(
+                    RuleFix {
+                        title: format!("Add `{}`", statement),
+                        edits: vec![(TextRange::empty(end), format!("{}{}", separator, statement))],
+                        safe: false,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn run_schema(&self, schema_cache: &SchemaCache, _: &Value) -> Vec<SchemaViolation> {
+        schema_cache
+            .foreign_keys
+            .iter()
+            .filter(|key| {
+                !is_system_schema(&key.schema)
+                    && !has_index(schema_cache, key.table_id, &key.columns)
+            })
+            .map(|key| {
+                SchemaViolation::new(
+                    RuleViolationKind::ForeignKeyWithoutIndex,
+                    format!("{}.{}", key.schema, key.table),
+                    Some(messages(key)),
+                )
+            })
+            .collect()
+    }
+}
+
+fn messages(key: &ForeignKey) -> Vec<ViolationMessage> {
+    let table = format!("{}.{}", quote_ident(&key.schema), quote_ident(&key.table));
+    vec![
+        ViolationMessage::Note(format!(
+            "The columns of the foreign key `{}` have no index, so deleting or updating a row of `{}` scans the table while it is locked.",
+            key.name, key.referenced_table
+        )),
+        ViolationMessage::Help(format!(
+            "Create an index on the columns: `{}`",
+            create_index(&key.table, &table, &key.columns)
+        )),
+    ]
+}
+
+/// Returns the statement that creates the index of a foreign key, named like Postgres names an
+/// index without a name
+fn create_index(table: &str, table_name: &str, columns: &[String]) -> String {
+    format!(
+        "CREATE INDEX CONCURRENTLY {} ON {} ({});",
+        quote_ident(&format!("{}_{}_idx", table, columns.join("_"))),
+        table_name,
+        columns
+            .iter()
+            .map(|c| quote_ident(c))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Returns whether a table has an index that covers all rows and whose leading columns are the
+/// columns of a foreign key, in any order
+fn has_index(schema_cache: &SchemaCache, table_id: i64, columns: &[String]) -> bool {
+    schema_cache.find_indexes(table_id).iter().any(|index| {
+        !index.is_partial
+            && index.columns.len() >= columns.len()
+            && index.columns[..columns.len()]
+                .iter()
+                .all(|key| columns.contains(&index_column(key)))
+    })
+}
+
+fn is_foreign(constraint: &Constraint) -> bool {
+    constraint.contype() == ConstrType::ConstrForeign
+}
+
+fn string_value(node: &pg_query_ext::protobuf::Node) -> Option<String> {
+    match &node.node {
+        Some(NodeEnum::String(s)) => Some(s.sval.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pg_schema_cache::SchemaCache;
+    use serde_json::Value;
+
+    use crate::{LinterParams, Rule, ViolationMessage};
+
+    use super::ForeignKeyWithoutIndex;
+
+    const SCHEMA: &str = r#"
+create table teams (id int primary key);
+create table users (id int primary key, team_id int, manager_id int, "Org" int);
+create index users_manager_idx on users (manager_id, team_id);
+create table members (team_id int references teams, user_id int references users);
+create index members_user_team_idx on members (user_id, team_id);
+"#;
+
+    fn fixes(sql: &str) -> Vec<String> {
+        let schema_cache = SchemaCache::from_sql(SCHEMA);
+        let ast = pg_query_ext::parse(sql).unwrap();
+        let params = LinterParams {
+            sql,
+            ast: &ast,
+            enriched_ast: None,
+            schema_cache: &schema_cache,
+            options: &HashMap::new(),
+        };
+        ForeignKeyWithoutIndex
+            .run(&params, &Value::Null)
+            .into_iter()
+            .map(|v| {
+                let fix = v.fix.unwrap();
+                assert!(!fix.safe);
+                fix.edits.into_iter().map(|(_, text)| text).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_foreign_key_without_index() {
+        assert_eq!(
+            fixes("alter table users add foreign key (team_id) references teams;"),
+            vec!["\nCREATE INDEX CONCURRENTLY users_team_id_idx ON users (team_id);"]
+        );
+        assert_eq!(
+            fixes(
+                r#"alter table public.users add constraint org_fk foreign key ("Org") references teams"#
+            ),
+            vec![";\nCREATE INDEX CONCURRENTLY \"users_Org_idx\" ON public.users (\"Org\");"]
+        );
+        assert_eq!(
+            fixes("alter table users add column group_id int references teams;").len(),
+            1
+        );
+        assert!(
+            fixes("alter table users add foreign key (manager_id) references users;").is_empty()
+        );
+        assert!(
+            fixes("alter table users add foreign key (team_id, manager_id) references teams;")
+                .is_empty()
+        );
+        assert!(fixes("alter table users add column email text;").is_empty());
+        // the table is new, so its index is created afterwards
+        assert!(fixes("alter table posts add foreign key (user_id) references users;").is_empty());
+    }
+
+    #[test]
+    fn test_foreign_key_without_index_in_schema() {
+        let schema_cache = SchemaCache::from_sql(SCHEMA);
+        let violations = ForeignKeyWithoutIndex.run_schema(&schema_cache, &Value::Null);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].object, "public.members");
+        assert_eq!(
+            violations[0].messages[1],
+            ViolationMessage::Help(
+                "Create an index on the columns: `CREATE INDEX CONCURRENTLY members_team_id_idx ON public.members (team_id);`"
+                    .into()
+            )
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use pg_query_ext::{
+    protobuf::{ColumnRef, Node},
+    NodeEnum,
+};
+use serde_json::Value;
+
+use crate::{
+    columns::StatementColumns,
+    rules::is_comparison,
+    tree::NodeTree,
+    violations::{RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags comparisons of functions of indexed columns or of indexed columns that are cast, e.g.
+/// `lower(email) = $1` or `created_at::date = $1`, which cannot use the index of the column
+///
+/// Columns that an index on an expression uses are allowed, since the expression may match the
+/// index. Columns are only known to be indexed with a schema.
+pub struct FunctionOnIndexedColumn;
+
+impl Rule for FunctionOnIndexedColumn {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::FunctionOnIndexedColumn,
+            category: RuleCategory::Performance,
+            docs: "https://www.postgresql.org/docs/current/indexes-expressional.html",
+            messages: vec![
+                ViolationMessage::Note(
+                    "The indexed column is passed to a function or cast before it is compared, so its index cannot be used."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Compare the column itself, e.g. with a range of timestamps instead of a date, or create an index on the expression."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let tree = NodeTree::new(params.ast);
+        let columns = StatementColumns::new(&tree, params.schema_cache);
+
+        tree.nodes()
+            .filter_map(|(_, node)| match &node.inner {
+                NodeEnum::AExpr(expr) if is_comparison(expr) => Some(expr),
+                _ => None,
+            })
+            .flat_map(|expr| [expr.lexpr.as_deref(), expr.rexpr.as_deref()])
+            .flatten()
+            .flat_map(wrapped_columns)
+            .filter(|column| {
+                columns
+                    .resolve(column)
+                    .is_some_and(|c| columns.is_indexed(c) && !columns.has_expression_index(c))
+            })
+            .map(|column| {
+                RuleViolation::new(
+                    RuleViolationKind::FunctionOnIndexedColumn,
+                    params.name_range(column.location),
+                    None,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Returns the columns that are passed to a function or cast by an operand of a comparison
+fn wrapped_columns(operand: &Node) -> Vec<&ColumnRef> {
+    let args: Vec<&Node> = match &operand.node {
+        Some(NodeEnum::FuncCall(call)) => call.args.iter().collect(),
+        Some(NodeEnum::TypeCast(cast)) => cast.arg.as_deref().into_iter().collect(),
+        _ => Vec::new(),
+    };
+    args.into_iter()
+        .filter_map(|arg| match &arg.node {
+            Some(NodeEnum::ColumnRef(column)) => Some(column),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::Value;
+
+    use crate::rules::run_rule;
+
+    use super::FunctionOnIndexedColumn;
+
+    #[test]
+    fn test_function_on_indexed_column() {
+        let schema_cache = SchemaCache::from_sql(
+            "create table users (id int primary key, email text, name text, created_at timestamptz);
+            create index users_email_idx on users (email);
+            create index users_created_at_idx on users (created_at);
+            create index users_name_idx on users (name);
+            create index users_lower_name_idx on users (lower(name));",
+        );
+        let violations = |sql| run_rule(&FunctionOnIndexedColumn, sql, &schema_cache, Value::Null);
+        assert_eq!(
+            violations("select id from users where lower(email) = $1"),
+            vec![Some("email")]
+        );
+        assert_eq!(
+            violations("update users u set name = 'x' where u.created_at::date = current_date;"),
+            vec![Some("u.created_at")]
+        );
+        assert_eq!(
+            violations("select id from users where $1 >= date_trunc('day', created_at)"),
+            vec![Some("created_at")]
+        );
+        assert!(violations("select id from users where email = lower($1)").is_empty());
+        assert!(violations("select id from users where lower(name) = $1").is_empty());
+        assert!(violations("select lower(email) from users where id = $1").is_empty());
+    }
+}
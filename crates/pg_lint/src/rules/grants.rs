@@ -0,0 +1,209 @@
+use pg_query_ext::{
+    protobuf::{GrantStmt, ObjectType, RoleSpec, RoleSpecType},
+    NodeEnum,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    parse_options,
+    violations::{RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags granting all privileges to `PUBLIC`, i.e. to every role including those created later,
+/// also as default privileges
+///
+/// Grants on schemas are left to [`BroadSchemaGrant`].
+pub struct BanGrantAllToPublic;
+
+/// Flags granting `USAGE` or `CREATE` on a schema to `PUBLIC` or other roles that many users
+/// have, which lets them create objects that shadow others on the search path
+pub struct BroadSchemaGrant;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+struct BroadSchemaGrantOptions {
+    /// The roles that are too broad, where `public` is every role
+    roles: Vec<String>,
+}
+
+impl Default for BroadSchemaGrantOptions {
+    fn default() -> Self {
+        Self {
+            roles: vec!["public".to_string()],
+        }
+    }
+}
+
+impl Rule for BanGrantAllToPublic {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::BanGrantAllToPublic,
+            category: RuleCategory::Security,
+            docs: "https://www.postgresql.org/docs/current/sql-grant.html",
+            messages: vec![
+                ViolationMessage::Note(
+                    "All privileges are granted to every role, including those that are created later."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Grant the privileges that are needed to the roles that need them.".into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        grants(params.ast)
+            .filter(|grant| grant.objtype() != ObjectType::ObjectSchema)
+            // no privileges are `ALL`
+            .filter(|grant| grant.privileges.is_empty())
+            .flat_map(grantees)
+            .filter(|role| role.roletype() == RoleSpecType::RolespecPublic)
+            .map(|role| {
+                RuleViolation::new(
+                    RuleViolationKind::BanGrantAllToPublic,
+                    params.name_range(role.location),
+                    None,
+                )
+            })
+            .collect()
+    }
+}
+
+impl Rule for BroadSchemaGrant {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::BroadSchemaGrant,
+            category: RuleCategory::Security,
+            docs: "https://www.postgresql.org/docs/current/ddl-schemas.html#DDL-SCHEMAS-PATTERNS",
+            messages: vec![
+                ViolationMessage::Note(
+                    "The schema is granted to a role that many users have, who can then access its objects or create objects that shadow others on the search path."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Grant `USAGE` and `CREATE` on the schema to the roles that need them.".into(),
+                ),
+            ],
+        }
+    }
+
+    fn validate_options(&self, options: &Value) -> Result<(), String> {
+        parse_options::<BroadSchemaGrantOptions>(options).map(|_| ())
+    }
+
+    fn run(&self, params: &LinterParams, options: &Value) -> Vec<RuleViolation> {
+        let options = parse_options::<BroadSchemaGrantOptions>(options).unwrap_or_default();
+        grants(params.ast)
+            .filter(|grant| grant.objtype() == ObjectType::ObjectSchema)
+            .filter(|grant| {
+                grant.privileges.is_empty()
+                    || grant.privileges.iter().any(|privilege| {
+                        matches!(
+                            &privilege.node,
+                            Some(NodeEnum::AccessPriv(p)) if matches!(p.priv_name.as_str(), "usage" | "create")
+                        )
+                    })
+            })
+            .flat_map(grantees)
+            .filter(|role| {
+                options.roles.iter().any(|name| match role.roletype() {
+                    RoleSpecType::RolespecPublic => name.eq_ignore_ascii_case("public"),
+                    _ => *name == role.rolename,
+                })
+            })
+            .map(|role| {
+                RuleViolation::new(
+                    RuleViolationKind::BroadSchemaGrant,
+                    params.name_range(role.location),
+                    None,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Returns the grants of a statement, which are those of `GRANT` and of
+/// `ALTER DEFAULT PRIVILEGES`
+fn grants(ast: &NodeEnum) -> impl Iterator<Item = &GrantStmt> {
+    let grant = match ast {
+        NodeEnum::GrantStmt(grant) => Some(grant),
+        NodeEnum::AlterDefaultPrivilegesStmt(stmt) => stmt.action.as_ref(),
+        _ => None,
+    };
+    grant.filter(|grant| grant.is_grant).into_iter()
+}
+
+fn grantees(grant: &GrantStmt) -> impl Iterator<Item = &RoleSpec> {
+    grant
+        .grantees
+        .iter()
+        .filter_map(|grantee| match &grantee.node {
+            Some(NodeEnum::RoleSpec(role)) => Some(role),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::{json, Value};
+
+    use crate::rules::run_rule;
+
+    use super::{BanGrantAllToPublic, BroadSchemaGrant};
+
+    #[test]
+    fn test_ban_grant_all_to_public() {
+        let violations = |sql| {
+            run_rule(
+                &BanGrantAllToPublic,
+                sql,
+                &SchemaCache::default(),
+                Value::Null,
+            )
+        };
+        assert_eq!(
+            violations("grant all on table users to public;"),
+            vec![Some("public")]
+        );
+        assert_eq!(
+            violations("grant all privileges on all tables in schema api to admin, PUBLIC"),
+            vec![Some("PUBLIC")]
+        );
+        assert_eq!(
+            violations("alter default privileges in schema api grant all on tables to public;"),
+            vec![Some("public")]
+        );
+        assert!(violations("grant select on table users to public;").is_empty());
+        assert!(violations("grant all on table users to admin;").is_empty());
+        assert!(violations("revoke all on table users from public;").is_empty());
+        assert!(violations("grant all on schema api to public;").is_empty());
+    }
+
+    #[test]
+    fn test_broad_schema_grant() {
+        let violations =
+            |sql, options| run_rule(&BroadSchemaGrant, sql, &SchemaCache::default(), options);
+        assert_eq!(
+            violations("grant create on schema api to public;", Value::Null),
+            vec![Some("public")]
+        );
+        assert_eq!(
+            violations("grant all on schema api to public;", Value::Null),
+            vec![Some("public")]
+        );
+        assert_eq!(
+            violations(
+                "grant usage, create on schema api to anon, admin",
+                json!({ "roles": ["anon", "public"] })
+            ),
+            vec![Some("anon")]
+        );
+        assert!(violations("grant usage on schema api to admin;", Value::Null).is_empty());
+        assert!(violations("grant select on table api.users to public;", Value::Null).is_empty());
+        assert!(violations("revoke create on schema public from public;", Value::Null).is_empty());
+    }
+}
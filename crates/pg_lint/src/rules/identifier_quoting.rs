@@ -0,0 +1,155 @@
+use pg_lexer::{SyntaxKind, TokenType};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    parse_options,
+    violations::{RuleFix, RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags identifiers that are quoted inconsistently
+///
+/// By default, identifiers are only quoted if they have to be, i.e. if they have upper case or
+/// special characters or are keywords. With `always`, the names of tables and columns are
+/// always quoted.
+pub struct IdentifierQuoting;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum QuotingStyle {
+    #[default]
+    Minimal,
+    Always,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+struct IdentifierQuotingOptions {
+    style: QuotingStyle,
+}
+
+impl Rule for IdentifierQuoting {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::IdentifierQuoting,
+            category: RuleCategory::Style,
+            docs: "https://www.postgresql.org/docs/current/sql-syntax-lexical.html#SQL-SYNTAX-IDENTIFIERS",
+            messages: vec![
+                ViolationMessage::Note(
+                    "The identifier is not quoted like the others of the project.".into(),
+                ),
+                ViolationMessage::Help(
+                    "Names without quotes are folded to lower case, so quoting a lower case name does not change it."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn validate_options(&self, options: &Value) -> Result<(), String> {
+        parse_options::<IdentifierQuotingOptions>(options).map(|_| ())
+    }
+
+    fn run(&self, params: &LinterParams, options: &Value) -> Vec<RuleViolation> {
+        let options = parse_options::<IdentifierQuotingOptions>(options).unwrap_or_default();
+        let tokens = params.tokens();
+
+        let fixes = match options.style {
+            QuotingStyle::Minimal => tokens
+                .iter()
+                .filter(|token| token.kind == SyntaxKind::Ident && token.text.starts_with('"'))
+                .filter_map(|token| {
+                    let name = &token.text[1..token.text.len() - 1];
+                    is_plain_identifier(name).then(|| (token, name.to_string()))
+                })
+                .collect::<Vec<_>>(),
+            QuotingStyle::Always => {
+                let identifiers = params.identifier_ranges();
+                tokens
+                    .iter()
+                    .filter(|token| {
+                        identifiers
+                            .iter()
+                            .any(|range| range.contains_range(token.span))
+                    })
+                    // names that are not reserved keywords, e.g. `name`, are identifiers there
+                    .filter(|token| {
+                        (token.kind == SyntaxKind::Ident && !token.text.starts_with('"'))
+                            || matches!(
+                                token.token_type,
+                                TokenType::UnreservedKeyword
+                                    | TokenType::ColNameKeyword
+                                    | TokenType::TypeFuncNameKeyword
+                            )
+                    })
+                    .map(|token| (token, format!("\"{}\"", token.text.to_ascii_lowercase())))
+                    .collect()
+            }
+        };
+
+        fixes
+            .into_iter()
+            .map(|(token, text)| {
+                RuleViolation::new(RuleViolationKind::IdentifierQuoting, Some(token.span), None)
+                    .with_fix(RuleFix {
+                        title: format!("Change `{}` to `{}`", token.text, text),
+                        edits: vec![(token.span, text)],
+                        safe: true,
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Returns whether a name means the same without quotes, i.e. it is lower case, has no special
+/// characters and is not a keyword
+fn is_plain_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '$'))
+        && matches!(
+            pg_lexer::try_lex(name).as_deref(),
+            Some([token]) if token.token_type == TokenType::NoKeyword
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::{json, Value};
+
+    use crate::rules::run_rule;
+
+    use super::IdentifierQuoting;
+
+    #[test]
+    fn test_identifier_quoting() {
+        let violations =
+            |sql, options| run_rule(&IdentifierQuoting, sql, &SchemaCache::default(), options);
+        assert_eq!(
+            violations(
+                r#"select "id", "Name", "user", "a""b" from "public"."users""#,
+                Value::Null
+            ),
+            vec![Some(r#""id""#), Some(r#""public""#), Some(r#""users""#)]
+        );
+        assert_eq!(
+            violations(
+                r#"select u.name, count(*) as total from "users" u where "Id" = 1"#,
+                json!({ "style": "always" })
+            ),
+            vec![Some("u"), Some("name")]
+        );
+        assert_eq!(
+            violations(
+                "create table Users (id int primary key)",
+                json!({ "style": "always" })
+            ),
+            vec![Some("Users"), Some("id")]
+        );
+        assert!(violations(r#"select "Id" from "user""#, Value::Null).is_empty());
+    }
+}
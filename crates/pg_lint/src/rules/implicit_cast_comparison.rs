@@ -0,0 +1,185 @@
+use pg_query_ext::{
+    protobuf::{a_const::Val, Node},
+    NodeEnum,
+};
+use serde_json::Value;
+
+use crate::{
+    columns::StatementColumns,
+    rules::is_comparison,
+    tree::NodeTree,
+    violations::{RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags comparisons of indexed columns with values of another type that the column is cast to,
+/// e.g. of an integer column with `1.5` or of a `char(n)` column with `text`, which cannot use
+/// the index of the column
+///
+/// Columns are only known to be indexed with a schema.
+pub struct ImplicitCastComparison;
+
+/// The types whose comparisons with each other cast one side, by the types they are cast to
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TypeFamily {
+    Integer,
+    Numeric,
+    Character,
+    Text,
+}
+
+impl TypeFamily {
+    fn of_type_id(type_id: i64) -> Option<TypeFamily> {
+        match type_id {
+            // int8, int2 and int4
+            20 | 21 | 23 => Some(TypeFamily::Integer),
+            // float4, float8 and numeric
+            700 | 701 | 1700 => Some(TypeFamily::Numeric),
+            // bpchar
+            1042 => Some(TypeFamily::Character),
+            // text and varchar
+            25 | 1043 => Some(TypeFamily::Text),
+            _ => None,
+        }
+    }
+
+    fn of_type_name(name: &str) -> Option<TypeFamily> {
+        match name {
+            "int2" | "int4" | "int8" | "smallint" | "integer" | "int" | "bigint" => {
+                Some(TypeFamily::Integer)
+            }
+            "float4" | "float8" | "real" | "numeric" | "decimal" => Some(TypeFamily::Numeric),
+            "bpchar" | "char" | "character" => Some(TypeFamily::Character),
+            "text" | "varchar" => Some(TypeFamily::Text),
+            _ => None,
+        }
+    }
+
+    /// Returns whether a column of this family is cast when it is compared with a value of
+    /// another
+    fn is_cast_to(self, other: TypeFamily) -> bool {
+        matches!(
+            (self, other),
+            (TypeFamily::Integer, TypeFamily::Numeric) | (TypeFamily::Character, TypeFamily::Text)
+        )
+    }
+}
+
+impl Rule for ImplicitCastComparison {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::ImplicitCastComparison,
+            category: RuleCategory::Performance,
+            docs: "https://www.postgresql.org/docs/current/typeconv-oper.html",
+            messages: vec![
+                ViolationMessage::Note(
+                    "The column is compared with a value of another type, so the column is cast and its index cannot be used."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Compare the column with a value of its own type, e.g. `1` rather than `1.0` for an integer column, or cast the value."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let tree = NodeTree::new(params.ast);
+        let columns = StatementColumns::new(&tree, params.schema_cache);
+
+        tree.nodes()
+            .filter_map(|(_, node)| match &node.inner {
+                NodeEnum::AExpr(expr) if is_comparison(expr) => {
+                    Some((expr.lexpr.as_deref()?, expr.rexpr.as_deref()?))
+                }
+                _ => None,
+            })
+            .flat_map(|(left, right)| [(left, right), (right, left)])
+            .filter_map(|(operand, other)| {
+                let Some(NodeEnum::ColumnRef(column_ref)) = &operand.node else {
+                    return None;
+                };
+                let column = columns.resolve(column_ref)?;
+                let family = TypeFamily::of_type_id(column.type_id)?;
+                let other_family = type_family(other, &columns)?;
+                (family.is_cast_to(other_family) && columns.is_indexed(column))
+                    .then_some(column_ref.location)
+            })
+            .map(|location| {
+                RuleViolation::new(
+                    RuleViolationKind::ImplicitCastComparison,
+                    params.name_range(location),
+                    None,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Returns the type family of an operand, if it is known without a cast to the other operand,
+/// i.e. for numbers, casts and columns but not for strings
+fn type_family(operand: &Node, columns: &StatementColumns) -> Option<TypeFamily> {
+    match operand.node.as_ref()? {
+        NodeEnum::AConst(constant) => match constant.val.as_ref()? {
+            Val::Ival(_) => Some(TypeFamily::Integer),
+            Val::Fval(_) => Some(TypeFamily::Numeric),
+            _ => None,
+        },
+        NodeEnum::TypeCast(cast) => {
+            let type_name = cast.type_name.as_ref()?;
+            if !type_name.array_bounds.is_empty() {
+                return None;
+            }
+            match type_name.names.last()?.node.as_ref()? {
+                NodeEnum::String(name) => TypeFamily::of_type_name(&name.sval),
+                _ => None,
+            }
+        }
+        NodeEnum::ColumnRef(column) => columns
+            .resolve(column)
+            .and_then(|column| TypeFamily::of_type_id(column.type_id)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::Value;
+
+    use crate::rules::run_rule;
+
+    use super::ImplicitCastComparison;
+
+    #[test]
+    fn test_implicit_cast_comparison() {
+        let schema_cache = SchemaCache::from_sql(
+            "create table users (id int primary key, code char(3), score numeric, team_id bigint);
+            create index users_code_idx on users (code);
+            create table teams (id int primary key, code varchar(3), score numeric);",
+        );
+        let violations = |sql| run_rule(&ImplicitCastComparison, sql, &schema_cache, Value::Null);
+        assert_eq!(
+            violations("select code from users where id = 1.0"),
+            vec![Some("id")]
+        );
+        assert_eq!(
+            violations("select u.id from users u where $1::text = u.code;"),
+            vec![Some("u.code")]
+        );
+        assert_eq!(
+            violations("select u.id from users u join teams t on u.code=t.code"),
+            vec![Some("u.code")]
+        );
+        assert_eq!(
+            violations("select t.id from teams t join users u on t.id = u.score"),
+            vec![Some("t.id")]
+        );
+        assert!(violations("select code from users where id = 1").is_empty());
+        assert!(violations("select id from users where code = 'abc'").is_empty());
+        assert!(violations("select id from users where team_id = 1").is_empty());
+        // the column has no index
+        assert!(violations("select id from users where score = 1").is_empty());
+    }
+}
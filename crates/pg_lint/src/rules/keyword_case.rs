@@ -0,0 +1,155 @@
+use pg_lexer::{SyntaxKind, Token, TokenType, WHITESPACE_TOKENS};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    parse_options,
+    violations::{RuleFix, RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags keywords that are not written in the configured case, which is upper case by default
+///
+/// Keywords that name tables, columns or aliases, e.g. a column `name`, are identifiers and
+/// allowed in any case.
+pub struct KeywordCase;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Case {
+    #[default]
+    Upper,
+    Lower,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+struct KeywordCaseOptions {
+    case: Case,
+}
+
+impl Rule for KeywordCase {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::KeywordCase,
+            category: RuleCategory::Style,
+            docs: "https://www.postgresql.org/docs/current/sql-keywords-appendix.html",
+            messages: vec![
+                ViolationMessage::Note("The keyword is not written in the configured case.".into()),
+                ViolationMessage::Help(
+                    "Change the case of the keyword, which does not change its meaning.".into(),
+                ),
+            ],
+        }
+    }
+
+    fn validate_options(&self, options: &Value) -> Result<(), String> {
+        parse_options::<KeywordCaseOptions>(options).map(|_| ())
+    }
+
+    fn run(&self, params: &LinterParams, options: &Value) -> Vec<RuleViolation> {
+        let options = parse_options::<KeywordCaseOptions>(options).unwrap_or_default();
+        let identifiers = params.identifier_ranges();
+        let tokens = params
+            .tokens()
+            .into_iter()
+            .filter(|token| !WHITESPACE_TOKENS.contains(&token.kind))
+            .collect::<Vec<_>>();
+
+        tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| is_keyword(token))
+            .filter(|(_, token)| {
+                !identifiers
+                    .iter()
+                    .any(|range| range.contains_range(token.span))
+            })
+            .filter(|(idx, token)| {
+                let previous = idx.checked_sub(1).and_then(|idx| tokens.get(idx));
+                let next = tokens.get(idx + 1);
+                // e.g. `excluded.name`, or an alias after `AS`, which only reserved keywords
+                // cannot be without quotes
+                previous.is_none_or(|previous| {
+                    previous.kind != SyntaxKind::Ascii46
+                        && (previous.kind != SyntaxKind::As
+                            || token.token_type == TokenType::ReservedKeyword)
+                }) && next.is_none_or(|next| next.kind != SyntaxKind::Ascii46)
+            })
+            .filter_map(|(_, token)| {
+                let text = match options.case {
+                    Case::Upper => token.text.to_ascii_uppercase(),
+                    Case::Lower => token.text.to_ascii_lowercase(),
+                };
+                (text != token.text).then_some((token, text))
+            })
+            .map(|(token, text)| {
+                RuleViolation::new(RuleViolationKind::KeywordCase, Some(token.span), None).with_fix(
+                    RuleFix {
+                        title: format!("Change `{}` to `{}`", token.text, text),
+                        edits: vec![(token.span, text)],
+                        safe: true,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+/// Returns whether a token is a keyword rather than e.g. a name, a literal or an operator
+fn is_keyword(token: &Token) -> bool {
+    matches!(
+        token.token_type,
+        TokenType::UnreservedKeyword
+            | TokenType::ColNameKeyword
+            | TokenType::TypeFuncNameKeyword
+            | TokenType::ReservedKeyword
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::{json, Value};
+
+    use crate::rules::run_rule;
+
+    use super::KeywordCase;
+
+    #[test]
+    fn test_keyword_case() {
+        let violations =
+            |sql, options| run_rule(&KeywordCase, sql, &SchemaCache::default(), options);
+        assert_eq!(
+            violations("select id FROM users where name = 'select'", Value::Null),
+            vec![Some("select"), Some("where")]
+        );
+        assert_eq!(
+            violations(
+                "SELECT u.name AS user FROM users u;",
+                json!({ "case": "lower" })
+            ),
+            vec![Some("SELECT"), Some("AS"), Some("FROM")]
+        );
+        assert_eq!(
+            violations(
+                "UPDATE public.users SET name = excluded.name, level = 1",
+                json!({ "case": "lower" })
+            ),
+            vec![Some("UPDATE"), Some("SET")]
+        );
+        assert!(violations(
+            "CREATE TABLE users (id INT PRIMARY KEY, name TEXT NOT NULL)",
+            Value::Null
+        )
+        .is_empty());
+        assert!(violations("SELECT count(*) AS level FROM users", Value::Null).is_empty());
+    }
+
+    #[test]
+    fn test_keyword_case_options() {
+        let rule: &dyn crate::Rule = &KeywordCase;
+        assert!(rule.validate_options(&json!({ "case": "lower" })).is_ok());
+        assert!(rule.validate_options(&json!({ "case": "title" })).is_err());
+    }
+}
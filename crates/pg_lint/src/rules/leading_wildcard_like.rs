@@ -0,0 +1,110 @@
+use pg_query_ext::{
+    protobuf::{a_const::Val, AExprKind},
+    NodeEnum,
+};
+use serde_json::Value;
+
+use crate::{
+    columns::StatementColumns,
+    tree::NodeTree,
+    violations::{RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags `LIKE` and `ILIKE` patterns that start with a wildcard and are matched against an
+/// indexed column, which a B-tree index cannot be used for
+///
+/// Columns are only known to be indexed with a schema.
+pub struct LeadingWildcardLike;
+
+impl Rule for LeadingWildcardLike {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::LeadingWildcardLike,
+            category: RuleCategory::Performance,
+            docs: "https://www.postgresql.org/docs/current/indexes-types.html#INDEXES-TYPES-BTREE",
+            messages: vec![
+                ViolationMessage::Note(
+                    "The pattern starts with a wildcard, so the index of the column cannot be used and the table is scanned."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Anchor the pattern at its start, or create a trigram index with the `pg_trgm` extension."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let tree = NodeTree::new(params.ast);
+        let columns = StatementColumns::new(&tree, params.schema_cache);
+
+        tree.nodes()
+            .filter_map(|(_, node)| match &node.inner {
+                NodeEnum::AExpr(expr)
+                    if matches!(expr.kind(), AExprKind::AexprLike | AExprKind::AexprIlike) =>
+                {
+                    Some(expr)
+                }
+                _ => None,
+            })
+            .filter(|expr| {
+                matches!(
+                    expr.lexpr.as_ref().and_then(|e| e.node.as_ref()),
+                    Some(NodeEnum::ColumnRef(column))
+                        if columns.resolve(column).is_some_and(|c| columns.is_indexed(c))
+                )
+            })
+            .filter_map(
+                |expr| match expr.rexpr.as_ref().and_then(|e| e.node.as_ref()) {
+                    Some(NodeEnum::AConst(pattern)) => match &pattern.val {
+                        Some(Val::Sval(s)) if s.sval.starts_with(['%', '_']) => {
+                            Some(pattern.location)
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                },
+            )
+            .map(|location| {
+                RuleViolation::new(
+                    RuleViolationKind::LeadingWildcardLike,
+                    params.literal_range(location),
+                    None,
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::Value;
+
+    use crate::rules::run_rule;
+
+    use super::LeadingWildcardLike;
+
+    #[test]
+    fn test_leading_wildcard_like() {
+        let schema_cache = SchemaCache::from_sql(
+            "create table users (id int primary key, email text, name text);
+            create index users_email_idx on users (email);",
+        );
+        let violations = |sql| run_rule(&LeadingWildcardLike, sql, &schema_cache, Value::Null);
+        assert_eq!(
+            violations("select id from users where email like '%@example.com'"),
+            vec![Some("'%@example.com'")]
+        );
+        assert_eq!(
+            violations("select u.id from users u where u.email not ilike '_dmin%';"),
+            vec![Some("'_dmin%'")]
+        );
+        assert!(violations("select id from users where email like 'admin%'").is_empty());
+        // the column has no index, so the table is scanned anyway
+        assert!(violations("select id from users where name like '%smith'").is_empty());
+        assert!(violations("select id from posts where email like '%@example.com'").is_empty());
+    }
+}
@@ -1 +1,279 @@
-pub mod ban_drop_column;
+//! The registry of the lint rules
+//!
+//! A rule is registered by adding it to [`RULES`] and its code to [`RuleViolationKind`]. Codes
+//! are stable, since they are used in settings and in `pglsp-ignore` comments.
+
+mod adding_required_field;
+mod ban_drop_column;
+mod ban_select_star;
+mod changing_column_type;
+mod column_types;
+mod exposed_table_without_rls;
+mod foreign_key_without_index;
+mod function_on_indexed_column;
+mod grants;
+mod hardcoded_password;
+mod identifier_quoting;
+mod implicit_cast_comparison;
+mod keyword_case;
+mod leading_wildcard_like;
+mod naming_convention;
+mod not_equal_operator;
+mod not_in_subquery;
+mod renaming;
+mod require_concurrent_index_creation;
+mod require_trailing_semicolon;
+mod security_definer_without_search_path;
+mod table_without_primary_key;
+
+use lazy_static::lazy_static;
+use pg_schema_cache::SchemaCache;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{LinterParams, RuleViolation, RuleViolationKind, SchemaViolation, ViolationMessage};
+
+use adding_required_field::AddingRequiredField;
+use ban_drop_column::BanDropColumn;
+use ban_select_star::BanSelectStar;
+use changing_column_type::ChangingColumnType;
+use column_types::{
+    BanCharField, BanMoneyType, PreferIdentity, PreferTextField, PreferTimestamptz,
+};
+use exposed_table_without_rls::ExposedTableWithoutRls;
+use foreign_key_without_index::ForeignKeyWithoutIndex;
+use function_on_indexed_column::FunctionOnIndexedColumn;
+use grants::{BanGrantAllToPublic, BroadSchemaGrant};
+use hardcoded_password::HardcodedPassword;
+use identifier_quoting::IdentifierQuoting;
+use implicit_cast_comparison::ImplicitCastComparison;
+use keyword_case::KeywordCase;
+use leading_wildcard_like::LeadingWildcardLike;
+use naming_convention::NamingConvention;
+use not_equal_operator::NotEqualOperator;
+use not_in_subquery::NotInSubquery;
+use renaming::{RenamingColumn, RenamingTable};
+use require_concurrent_index_creation::RequireConcurrentIndexCreation;
+use require_trailing_semicolon::RequireTrailingSemicolon;
+use security_definer_without_search_path::SecurityDefinerWithoutSearchPath;
+use table_without_primary_key::TableWithoutPrimaryKey;
+
+lazy_static! {
+    pub static ref RULES: Vec<Box<dyn Rule>> = vec![
+        Box::new(BanDropColumn),
+        Box::new(BanSelectStar),
+        Box::new(RequireConcurrentIndexCreation),
+        Box::new(AddingRequiredField),
+        Box::new(ChangingColumnType),
+        Box::new(RenamingColumn),
+        Box::new(RenamingTable),
+        Box::new(TableWithoutPrimaryKey),
+        Box::new(ForeignKeyWithoutIndex),
+        Box::new(ExposedTableWithoutRls),
+        Box::new(BanGrantAllToPublic),
+        Box::new(HardcodedPassword),
+        Box::new(SecurityDefinerWithoutSearchPath),
+        Box::new(BroadSchemaGrant),
+        Box::new(LeadingWildcardLike),
+        Box::new(NotInSubquery),
+        Box::new(FunctionOnIndexedColumn),
+        Box::new(ImplicitCastComparison),
+        Box::new(KeywordCase),
+        Box::new(RequireTrailingSemicolon),
+        Box::new(IdentifierQuoting),
+        Box::new(NotEqualOperator),
+        Box::new(NamingConvention),
+        Box::new(PreferTextField),
+        Box::new(PreferTimestamptz),
+        Box::new(BanMoneyType),
+        Box::new(PreferIdentity),
+        Box::new(BanCharField),
+    ];
+}
+
+/// Returns the registered rule of a kind
+pub fn rule(kind: &RuleViolationKind) -> Option<&'static dyn Rule> {
+    RULES
+        .iter()
+        .find(|rule| rule.metadata().kind == *kind)
+        .map(|rule| rule.as_ref())
+}
+
+/// A lint rule, which checks a statement on its own or against the schema of its database
+pub trait Rule: Send + Sync {
+    fn metadata(&self) -> RuleMetadata;
+
+    /// Checks the options of the rule in the settings, which are `null` if there are none
+    ///
+    /// Rules without options reject any.
+    fn validate_options(&self, options: &Value) -> Result<(), String> {
+        match options {
+            Value::Null => Ok(()),
+            _ => Err("The rule has no options".to_string()),
+        }
+    }
+
+    /// Returns the violations of the rule in a statement, with its options if it has any
+    fn run(&self, params: &LinterParams, options: &Value) -> Vec<RuleViolation>;
+
+    /// Returns the violations of the rule in the existing objects of a database, which only the
+    /// rules that check the schema rather than statements have
+    fn run_schema(&self, _schema_cache: &SchemaCache, _options: &Value) -> Vec<SchemaViolation> {
+        Vec::new()
+    }
+}
+
+/// Describes a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleMetadata {
+    /// The kind, whose name is the code of the rule
+    pub kind: RuleViolationKind,
+    pub category: RuleCategory,
+    /// The documentation of what the rule checks for, which is linked from its diagnostics
+    pub docs: &'static str,
+    /// The messages of a violation. Notes are reported with the severity of the rule, and helps
+    /// as hints.
+    pub messages: Vec<ViolationMessage>,
+}
+
+/// The group a rule belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleCategory {
+    /// Statements that are likely wrong
+    Correctness,
+    /// Migrations that lock tables, rewrite them or break clients
+    Safety,
+    /// Statements that are slow, or schemas that make queries slow
+    Performance,
+    /// Statements that expose data or privileges
+    Security,
+    /// Formatting and idioms
+    Style,
+    /// The names of objects
+    Naming,
+}
+
+impl RuleCategory {
+    /// Returns whether the rules of the category are reported if they are not configured, which
+    /// the rules of conventions that differ between projects are not
+    pub fn is_enabled_by_default(self) -> bool {
+        !matches!(self, RuleCategory::Style | RuleCategory::Naming)
+    }
+}
+
+impl std::fmt::Display for RuleCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            serde_plain::to_string(self).map_err(|_| std::fmt::Error)?
+        )
+    }
+}
+
+/// Returns the name of a key column of an index, which is quoted if needed. Keys on expressions
+/// are returned as they are and match no column.
+pub(crate) fn index_column(key: &str) -> String {
+    key.strip_prefix('"')
+        .and_then(|key| key.strip_suffix('"'))
+        .map_or_else(|| key.to_string(), |key| key.replace("\"\"", "\""))
+}
+
+/// Returns whether an expression compares its operands, e.g. with `=` or `<`, so that an index
+/// on one of them may be used
+pub(crate) fn is_comparison(expr: &pg_query_ext::protobuf::AExpr) -> bool {
+    expr.kind() == pg_query_ext::protobuf::AExprKind::AexprOp
+        && matches!(
+            expr.name.last().and_then(|name| name.node.as_ref()),
+            Some(pg_query_ext::NodeEnum::String(op))
+                if matches!(op.sval.as_str(), "=" | "<>" | "<" | ">" | "<=" | ">=")
+        )
+}
+
+/// Parses the options of a rule, which are the defaults if there are none
+pub fn parse_options<T: DeserializeOwned + Default>(options: &Value) -> Result<T, String> {
+    match options {
+        Value::Null => Ok(T::default()),
+        options => serde_json::from_value(options.clone()).map_err(|err| err.to_string()),
+    }
+}
+
+/// Runs a rule on a statement, and returns the text of each violation, or `None` if it covers
+/// the whole statement
+#[cfg(test)]
+pub(crate) fn run_rule<'a>(
+    rule: &dyn Rule,
+    sql: &'a str,
+    schema_cache: &pg_schema_cache::SchemaCache,
+    options: Value,
+) -> Vec<Option<&'a str>> {
+    let ast = pg_query_ext::parse(sql).unwrap();
+    let params = LinterParams {
+        sql,
+        ast: &ast,
+        enriched_ast: None,
+        schema_cache,
+        options: &std::collections::HashMap::new(),
+    };
+    rule.run(&params, &options)
+        .into_iter()
+        .map(|v| v.range.map(|range| &sql[range]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+
+    use crate::RuleViolationKind;
+
+    use super::{parse_options, rule, RULES};
+
+    #[test]
+    fn test_registry() {
+        let codes = RULES
+            .iter()
+            .map(|rule| rule.metadata().kind.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(codes.iter().collect::<HashSet<_>>().len(), codes.len());
+        for code in &codes {
+            let kind = code.parse::<RuleViolationKind>().unwrap();
+            let metadata = rule(&kind).unwrap().metadata();
+            assert_eq!(metadata.kind, kind);
+            assert!(metadata.docs.starts_with("https://"), "{}", metadata.docs);
+        }
+
+        let ban_drop_column = rule(&RuleViolationKind::BanDropColumn).unwrap();
+        assert_eq!(ban_drop_column.metadata().category.to_string(), "safety");
+        assert!(ban_drop_column.validate_options(&Value::Null).is_ok());
+        assert_eq!(
+            ban_drop_column.validate_options(&json!({ "allow": true })),
+            Err("The rule has no options".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_options() {
+        #[derive(Debug, Default, PartialEq, Deserialize)]
+        #[serde(rename_all = "camelCase", deny_unknown_fields)]
+        struct Options {
+            max_length: Option<usize>,
+        }
+
+        assert_eq!(
+            parse_options::<Options>(&Value::Null),
+            Ok(Options::default())
+        );
+        assert_eq!(
+            parse_options::<Options>(&json!({ "maxLength": 63 })),
+            Ok(Options {
+                max_length: Some(63)
+            })
+        );
+        assert!(parse_options::<Options>(&json!({ "length": 63 })).is_err());
+    }
+}
@@ -0,0 +1,468 @@
+use pg_lexer::{SyntaxKind, Token, TokenType};
+use pg_query_ext::{protobuf::ObjectType, NodeEnum};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use text_size::TextSize;
+
+use crate::{
+    parse_options,
+    tree::NodeTree,
+    violations::{RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// The longest name in bytes that postgres keeps, see `NAMEDATALEN`
+const MAX_NAME_LENGTH: usize = 63;
+
+/// Flags the names of tables, columns, indexes, constraints and functions that are created or
+/// renamed and do not follow the conventions of the project, which are snake case by default
+///
+/// Names that are longer than 63 bytes are always flagged, since postgres truncates them.
+pub struct NamingConvention;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+struct NamingConventionOptions {
+    /// The longest name in bytes, which cannot be more than 63
+    max_length: usize,
+    tables: NameConvention,
+    columns: NameConvention,
+    indexes: NameConvention,
+    constraints: NameConvention,
+    functions: NameConvention,
+}
+
+impl Default for NamingConventionOptions {
+    fn default() -> Self {
+        Self {
+            max_length: MAX_NAME_LENGTH,
+            tables: NameConvention::default(),
+            columns: NameConvention::default(),
+            indexes: NameConvention::default(),
+            constraints: NameConvention::default(),
+            functions: NameConvention::default(),
+        }
+    }
+}
+
+impl NamingConventionOptions {
+    fn convention(&self, kind: ObjectKind) -> &NameConvention {
+        match kind {
+            ObjectKind::Table => &self.tables,
+            ObjectKind::Column => &self.columns,
+            ObjectKind::Index => &self.indexes,
+            ObjectKind::Constraint => &self.constraints,
+            ObjectKind::Function => &self.functions,
+        }
+    }
+}
+
+/// The convention of the names of a kind of object
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+struct NameConvention {
+    style: NameStyle,
+    /// The prefix names must start with, e.g. `idx_`
+    prefix: Option<String>,
+    /// A regular expression names must match
+    pattern: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+enum NameStyle {
+    #[default]
+    #[serde(rename = "snake_case")]
+    SnakeCase,
+    #[serde(rename = "camelCase")]
+    CamelCase,
+    #[serde(rename = "PascalCase")]
+    PascalCase,
+    #[serde(rename = "any")]
+    Any,
+}
+
+impl NameStyle {
+    fn matches(self, name: &str) -> bool {
+        let mut chars = name.chars();
+        let Some(first) = chars.next() else {
+            return true;
+        };
+        match self {
+            NameStyle::SnakeCase => {
+                (first.is_ascii_lowercase() || first == '_')
+                    && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+            }
+            NameStyle::CamelCase => {
+                first.is_ascii_lowercase() && chars.all(|c| c.is_ascii_alphanumeric())
+            }
+            NameStyle::PascalCase => {
+                first.is_ascii_uppercase() && chars.all(|c| c.is_ascii_alphanumeric())
+            }
+            NameStyle::Any => true,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            NameStyle::SnakeCase => "snake_case",
+            NameStyle::CamelCase => "camelCase",
+            NameStyle::PascalCase => "PascalCase",
+            NameStyle::Any => "any",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ObjectKind {
+    Table,
+    Column,
+    Index,
+    Constraint,
+    Function,
+}
+
+impl ObjectKind {
+    fn of_object_type(object_type: ObjectType) -> Option<ObjectKind> {
+        match object_type {
+            ObjectType::ObjectTable => Some(ObjectKind::Table),
+            ObjectType::ObjectColumn => Some(ObjectKind::Column),
+            ObjectType::ObjectIndex => Some(ObjectKind::Index),
+            ObjectType::ObjectTabconstraint => Some(ObjectKind::Constraint),
+            ObjectType::ObjectFunction | ObjectType::ObjectProcedure => Some(ObjectKind::Function),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            ObjectKind::Table => "table",
+            ObjectKind::Column => "column",
+            ObjectKind::Index => "index",
+            ObjectKind::Constraint => "constraint",
+            ObjectKind::Function => "function",
+        }
+    }
+}
+
+/// A name of an object that the statement creates or renames
+struct Name<'a> {
+    kind: ObjectKind,
+    /// The name in the AST, which postgres truncated if it is too long
+    name: &'a str,
+    location: Location,
+}
+
+/// Where the name is written in the statement
+enum Location {
+    /// At the first occurrence of the name from a location
+    From(i32),
+    /// At the last occurrence of the name, e.g. the new name of a rename
+    Last,
+}
+
+impl Rule for NamingConvention {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::NamingConvention,
+            category: RuleCategory::Naming,
+            docs: "https://www.postgresql.org/docs/current/sql-syntax-lexical.html#SQL-SYNTAX-IDENTIFIERS",
+            messages: vec![
+                ViolationMessage::Note(
+                    "The name does not follow the naming conventions of the project.".into(),
+                ),
+                ViolationMessage::Help(
+                    "Rename the object, or change the conventions in the options of the rule."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn validate_options(&self, options: &Value) -> Result<(), String> {
+        let options = parse_options::<NamingConventionOptions>(options)?;
+        if options.max_length > MAX_NAME_LENGTH {
+            return Err(format!(
+                "maxLength cannot be more than {}, since postgres truncates longer names",
+                MAX_NAME_LENGTH
+            ));
+        }
+        for convention in [
+            &options.tables,
+            &options.columns,
+            &options.indexes,
+            &options.constraints,
+            &options.functions,
+        ] {
+            if let Some(pattern) = &convention.pattern {
+                Regex::new(pattern).map_err(|err| err.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn run(&self, params: &LinterParams, options: &Value) -> Vec<RuleViolation> {
+        let options = parse_options::<NamingConventionOptions>(options).unwrap_or_default();
+        let tree = NodeTree::new(params.ast);
+        let tokens = params.tokens();
+
+        let names = tree
+            .nodes()
+            .filter_map(|(idx, node)| {
+                let in_table = || {
+                    tree.ancestors(idx).any(|ancestor| {
+                        matches!(
+                            ancestor,
+                            NodeEnum::CreateStmt(_) | NodeEnum::AlterTableStmt(_)
+                        )
+                    })
+                };
+                let (kind, name, location) = match &node.inner {
+                    NodeEnum::CreateStmt(stmt) => {
+                        let relation = stmt.relation.as_ref()?;
+                        (
+                            ObjectKind::Table,
+                            relation.relname.as_str(),
+                            Location::From(relation.location),
+                        )
+                    }
+                    NodeEnum::ColumnDef(column) if in_table() => (
+                        ObjectKind::Column,
+                        column.colname.as_str(),
+                        Location::From(column.location),
+                    ),
+                    NodeEnum::Constraint(constraint) if in_table() => (
+                        ObjectKind::Constraint,
+                        constraint.conname.as_str(),
+                        Location::From(constraint.location),
+                    ),
+                    NodeEnum::IndexStmt(stmt) => {
+                        (ObjectKind::Index, stmt.idxname.as_str(), Location::From(0))
+                    }
+                    NodeEnum::CreateFunctionStmt(stmt) => {
+                        match stmt.funcname.last()?.node.as_ref()? {
+                            NodeEnum::String(name) => {
+                                (ObjectKind::Function, name.sval.as_str(), Location::From(0))
+                            }
+                            _ => return None,
+                        }
+                    }
+                    NodeEnum::RenameStmt(stmt) => (
+                        ObjectKind::of_object_type(stmt.rename_type())?,
+                        stmt.newname.as_str(),
+                        Location::Last,
+                    ),
+                    _ => return None,
+                };
+                (!name.is_empty()).then_some(Name {
+                    kind,
+                    name,
+                    location,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let token = name_token(&tokens, &name);
+                // the name as it is written, which may be longer than the one in the AST
+                let written = token.map_or(name.name.to_string(), unquote);
+                let messages = check_name(&options, name.kind, &written);
+                (!messages.is_empty()).then(|| {
+                    RuleViolation::new(
+                        RuleViolationKind::NamingConvention,
+                        token.map(|token| token.span),
+                        Some(messages),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Returns the messages of the conventions that a name of a kind of object does not follow
+fn check_name(
+    options: &NamingConventionOptions,
+    kind: ObjectKind,
+    name: &str,
+) -> Vec<ViolationMessage> {
+    let convention = options.convention(kind);
+    let kind = kind.as_str();
+
+    let mut notes = Vec::new();
+    if name.len() > options.max_length {
+        notes.push(format!(
+            "The {} name `{}` is {} bytes long, more than {}.",
+            kind,
+            name,
+            name.len(),
+            options.max_length
+        ));
+    }
+    if !convention.style.matches(name) {
+        notes.push(format!(
+            "The {} name `{}` is not {}.",
+            kind,
+            name,
+            convention.style.as_str()
+        ));
+    }
+    if let Some(prefix) = &convention.prefix {
+        if !name.starts_with(prefix.as_str()) {
+            notes.push(format!(
+                "The {} name `{}` does not start with `{}`.",
+                kind, name, prefix
+            ));
+        }
+    }
+    if let Some(pattern) = &convention.pattern {
+        if Regex::new(pattern).is_ok_and(|regex| !regex.is_match(name)) {
+            notes.push(format!(
+                "The {} name `{}` does not match `{}`.",
+                kind, name, pattern
+            ));
+        }
+    }
+    if notes.is_empty() {
+        return Vec::new();
+    }
+
+    let help = if name.len() > MAX_NAME_LENGTH {
+        format!(
+            "Postgres truncates names to {} bytes, so use a shorter name.",
+            MAX_NAME_LENGTH
+        )
+    } else {
+        format!(
+            "Rename the {}, or change the conventions in the options of the rule.",
+            kind
+        )
+    };
+    notes
+        .into_iter()
+        .map(ViolationMessage::Note)
+        .chain([ViolationMessage::Help(help)])
+        .collect()
+}
+
+/// Returns the token of a name, i.e. the first one from its location or the last one of the
+/// statement whose name is the name in the AST
+fn name_token<'a>(tokens: &'a [Token], name: &Name) -> Option<&'a Token> {
+    let mut candidates = tokens.iter().filter(|token| {
+        token.kind == SyntaxKind::Ident || token.token_type != TokenType::NoKeyword
+    });
+    let matching = |token: &&Token| truncate(&unquote(token)) == name.name;
+    match name.location {
+        Location::From(location) => {
+            let from = u32::try_from(location).map_or(TextSize::new(0), TextSize::new);
+            candidates.find(|token| token.span.start() >= from && matching(token))
+        }
+        Location::Last => candidates.rfind(matching),
+    }
+}
+
+/// Returns the name of an identifier token, which is folded to lower case unless it is quoted
+fn unquote(token: &Token) -> String {
+    match token
+        .text
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+    {
+        Some(text) => text.replace("\"\"", "\""),
+        None => token.text.to_ascii_lowercase(),
+    }
+}
+
+/// Truncates a name like postgres does
+fn truncate(name: &str) -> &str {
+    let mut end = name.len().min(MAX_NAME_LENGTH);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    &name[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::{json, Value};
+
+    use crate::{rules::run_rule, Rule};
+
+    use super::NamingConvention;
+
+    #[test]
+    fn test_naming_convention() {
+        let violations =
+            |sql, options| run_rule(&NamingConvention, sql, &SchemaCache::default(), options);
+        assert_eq!(
+            violations(
+                r#"create table "UserAccounts" (id int, "firstName" text, constraint "Pk" primary key (id))"#,
+                Value::Null
+            ),
+            vec![
+                Some(r#""UserAccounts""#),
+                Some(r#""firstName""#),
+                Some(r#""Pk""#)
+            ]
+        );
+        assert_eq!(
+            violations(
+                "alter table users rename column name to \"Name\"",
+                Value::Null
+            ),
+            vec![Some(r#""Name""#)]
+        );
+        let long_name = "a".repeat(64);
+        let sql = format!("alter table users add column {} text", long_name);
+        assert_eq!(
+            violations(&sql, Value::Null),
+            vec![Some(long_name.as_str())]
+        );
+        assert!(violations(
+            "create table users (id int primary key, first_name text)",
+            Value::Null
+        )
+        .is_empty());
+        assert!(violations(
+            "alter table users rename column name to \"Name\" ",
+            json!({ "columns": { "style": "any" } })
+        )
+        .is_empty());
+
+        let options = json!({
+            "indexes": { "prefix": "idx_" },
+            "functions": { "pattern": "^fn_" }
+        });
+        assert_eq!(
+            violations("create index users_email on users (email)", options.clone()),
+            vec![Some("users_email")]
+        );
+        assert!(violations(
+            "create index idx_users_email on users (email)",
+            options.clone()
+        )
+        .is_empty());
+        assert_eq!(
+            violations(
+                "create function public.get_user() returns int language sql as 'select 1'",
+                options
+            ),
+            vec![Some("get_user")]
+        );
+    }
+
+    #[test]
+    fn test_naming_convention_options() {
+        assert!(NamingConvention
+            .validate_options(&json!({ "maxLength": 30, "tables": { "style": "PascalCase" } }))
+            .is_ok());
+        assert!(NamingConvention
+            .validate_options(&json!({ "maxLength": 64 }))
+            .is_err());
+        assert!(NamingConvention
+            .validate_options(&json!({ "tables": { "pattern": "(" } }))
+            .is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use pg_lexer::SyntaxKind;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    parse_options,
+    violations::{RuleFix, RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags the spelling of the not equal operator that is not the configured one, which is the
+/// standard `<>` by default
+pub struct NotEqualOperator;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+enum Operator {
+    #[default]
+    #[serde(rename = "<>")]
+    LessGreater,
+    #[serde(rename = "!=")]
+    BangEquals,
+}
+
+impl Operator {
+    fn as_str(self) -> &'static str {
+        match self {
+            Operator::LessGreater => "<>",
+            Operator::BangEquals => "!=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+struct NotEqualOperatorOptions {
+    operator: Operator,
+}
+
+impl Rule for NotEqualOperator {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::NotEqualOperator,
+            category: RuleCategory::Style,
+            docs: "https://www.postgresql.org/docs/current/functions-comparison.html",
+            messages: vec![
+                ViolationMessage::Note(
+                    "The not equal operator is not spelled as configured.".into(),
+                ),
+                ViolationMessage::Help(
+                    "`<>` and `!=` are the same operator, so either can be used.".into(),
+                ),
+            ],
+        }
+    }
+
+    fn validate_options(&self, options: &Value) -> Result<(), String> {
+        parse_options::<NotEqualOperatorOptions>(options).map(|_| ())
+    }
+
+    fn run(&self, params: &LinterParams, options: &Value) -> Vec<RuleViolation> {
+        let options = parse_options::<NotEqualOperatorOptions>(options).unwrap_or_default();
+        let operator = options.operator.as_str();
+
+        params
+            .tokens()
+            .into_iter()
+            .filter(|token| token.kind == SyntaxKind::NotEquals && token.text != operator)
+            .map(|token| {
+                RuleViolation::new(RuleViolationKind::NotEqualOperator, Some(token.span), None)
+                    .with_fix(RuleFix {
+                        title: format!("Change `{}` to `{}`", token.text, operator),
+                        edits: vec![(token.span, operator.to_string())],
+                        safe: true,
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::{json, Value};
+
+    use crate::rules::run_rule;
+
+    use super::NotEqualOperator;
+
+    #[test]
+    fn test_not_equal_operator() {
+        let violations =
+            |sql, options| run_rule(&NotEqualOperator, sql, &SchemaCache::default(), options);
+        assert_eq!(
+            violations(
+                "select id from users where id != 1 and name <> '!='",
+                Value::Null
+            ),
+            vec![Some("!=")]
+        );
+        assert_eq!(
+            violations(
+                "select id from users where id != 1 and name <> '!='",
+                json!({ "operator": "!=" })
+            ),
+            vec![Some("<>")]
+        );
+        assert!(violations("select id from users where id <> 1", Value::Null).is_empty());
+    }
+}
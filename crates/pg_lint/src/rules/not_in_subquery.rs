@@ -0,0 +1,125 @@
+use pg_query_ext::{
+    protobuf::{BoolExprType, SubLinkType},
+    NodeEnum,
+};
+use serde_json::Value;
+
+use crate::{
+    columns::StatementColumns,
+    tree::NodeTree,
+    violations::{RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags `NOT IN` with a subquery whose column may be null, which is never true if the subquery
+/// returns a null and cannot be planned as an anti join
+///
+/// Subqueries that select a column that is known to be `NOT NULL` are allowed, which requires a
+/// schema.
+pub struct NotInSubquery;
+
+impl Rule for NotInSubquery {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::NotInSubquery,
+            category: RuleCategory::Performance,
+            docs: "https://wiki.postgresql.org/wiki/Don%27t_Do_This#Don.27t_use_NOT_IN",
+            messages: vec![
+                ViolationMessage::Note(
+                    "`NOT IN` is never true if the subquery returns a null, and it cannot be planned as an anti join."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Use `NOT EXISTS` with a subquery that is correlated on the column instead."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let tree = NodeTree::new(params.ast);
+        let columns = StatementColumns::new(&tree, params.schema_cache);
+
+        tree.nodes()
+            .filter_map(|(_, node)| match &node.inner {
+                NodeEnum::BoolExpr(expr) if expr.boolop() == BoolExprType::NotExpr => {
+                    match expr.args.first().and_then(|arg| arg.node.as_ref()) {
+                        // `IN` is `= ANY` without an operator
+                        Some(NodeEnum::SubLink(sub_link))
+                            if sub_link.sub_link_type() == SubLinkType::AnySublink
+                                && sub_link.oper_name.is_empty() =>
+                        {
+                            Some((expr.location, sub_link))
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+            .filter(|(_, sub_link)| {
+                let column = match sub_link.subselect.as_ref().and_then(|s| s.node.as_ref()) {
+                    Some(NodeEnum::SelectStmt(select)) => match select.target_list.as_slice() {
+                        [target] => match target.node.as_ref() {
+                            Some(NodeEnum::ResTarget(target)) => {
+                                match target.val.as_ref().and_then(|v| v.node.as_ref()) {
+                                    Some(NodeEnum::ColumnRef(column)) => columns.resolve(column),
+                                    _ => None,
+                                }
+                            }
+                            _ => None,
+                        },
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                column.is_none_or(|column| column.is_nullable)
+            })
+            .map(|(location, _)| {
+                RuleViolation::new(
+                    RuleViolationKind::NotInSubquery,
+                    params.keyword_range(location, "in"),
+                    None,
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::Value;
+
+    use crate::rules::run_rule;
+
+    use super::NotInSubquery;
+
+    #[test]
+    fn test_not_in_subquery() {
+        let schema_cache = SchemaCache::from_sql(
+            "create table users (id int primary key, team_id int);
+            create table banned (user_id int not null, team_id int);",
+        );
+        let violations = |sql| run_rule(&NotInSubquery, sql, &schema_cache, Value::Null);
+        assert_eq!(
+            violations("select id from users where team_id NOT  IN (select team_id from banned)"),
+            vec![Some("NOT  IN")]
+        );
+        assert_eq!(
+            violations(
+                "delete from users where id not in (select max(id) from users group by team_id);"
+            ),
+            vec![Some("not in")]
+        );
+        assert!(
+            violations("select id from users where id not in (select user_id from banned)")
+                .is_empty()
+        );
+        assert!(
+            violations("select id from users where team_id in (select team_id from banned)")
+                .is_empty()
+        );
+        assert!(violations("select id from users where team_id not in (1, 2)").is_empty());
+    }
+}
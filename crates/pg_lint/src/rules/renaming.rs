@@ -0,0 +1,108 @@
+use pg_query_ext::{protobuf::ObjectType, NodeEnum};
+use serde_json::Value;
+
+use crate::{
+    violations::{RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags renaming a column, which breaks the clients that still use the old name
+pub struct RenamingColumn;
+
+/// Flags renaming a table, which breaks the clients that still use the old name
+pub struct RenamingTable;
+
+impl Rule for RenamingColumn {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::RenamingColumn,
+            category: RuleCategory::Safety,
+            docs: "https://www.postgresql.org/docs/current/sql-altertable.html",
+            messages: vec![
+                ViolationMessage::Note("Renaming a column may break existing clients.".into()),
+                ViolationMessage::Help(
+                    "Add a column with the new name, and drop the old one once no client uses it."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        renames(
+            params,
+            ObjectType::ObjectColumn,
+            RuleViolationKind::RenamingColumn,
+        )
+    }
+}
+
+impl Rule for RenamingTable {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::RenamingTable,
+            category: RuleCategory::Safety,
+            docs: "https://www.postgresql.org/docs/current/sql-altertable.html",
+            messages: vec![
+                ViolationMessage::Note("Renaming a table may break existing clients.".into()),
+                ViolationMessage::Help(
+                    "Create a view with the old name until no client uses it.".into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        renames(
+            params,
+            ObjectType::ObjectTable,
+            RuleViolationKind::RenamingTable,
+        )
+    }
+}
+
+fn renames(
+    params: &LinterParams,
+    object_type: ObjectType,
+    kind: RuleViolationKind,
+) -> Vec<RuleViolation> {
+    match params.ast {
+        NodeEnum::RenameStmt(stmt) if stmt.rename_type() == object_type => {
+            vec![RuleViolation::new(kind, None, None)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::Value;
+
+    use crate::rules::run_rule;
+
+    use super::{RenamingColumn, RenamingTable};
+
+    #[test]
+    fn test_renaming() {
+        let schema_cache = SchemaCache::default();
+        let column = "alter table users rename column email to mail";
+        let table = "alter table users rename to accounts";
+        assert_eq!(
+            run_rule(&RenamingColumn, column, &schema_cache, Value::Null).len(),
+            1
+        );
+        assert_eq!(
+            run_rule(&RenamingColumn, table, &schema_cache, Value::Null).len(),
+            0
+        );
+        assert_eq!(
+            run_rule(&RenamingTable, table, &schema_cache, Value::Null).len(),
+            1
+        );
+        assert_eq!(
+            run_rule(&RenamingTable, column, &schema_cache, Value::Null).len(),
+            0
+        );
+    }
+}
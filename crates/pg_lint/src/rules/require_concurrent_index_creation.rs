@@ -0,0 +1,104 @@
+use pg_query_ext::NodeEnum;
+use serde_json::Value;
+
+use crate::{
+    violations::{RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags creating an index without `CONCURRENTLY`, which blocks writes to the table while the
+/// index is built
+///
+/// Indexes of tables that are created by the same migration are allowed.
+pub struct RequireConcurrentIndexCreation;
+
+impl Rule for RequireConcurrentIndexCreation {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::RequireConcurrentIndexCreation,
+            category: RuleCategory::Safety,
+            docs: "https://www.postgresql.org/docs/current/sql-createindex.html#SQL-CREATEINDEX-CONCURRENTLY",
+            messages: vec![
+                ViolationMessage::Note(
+                    "Creating an index blocks writes to the table until the index is built."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Use `CREATE INDEX CONCURRENTLY`, outside of a transaction.".into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        match params.ast {
+            NodeEnum::IndexStmt(stmt)
+                if !stmt.concurrent
+                    && !stmt
+                        .relation
+                        .as_ref()
+                        .is_some_and(|relation| params.is_new_table(relation)) =>
+            {
+                vec![RuleViolation::new(
+                    RuleViolationKind::RequireConcurrentIndexCreation,
+                    None,
+                    None,
+                )]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::{SchemaCache, Table};
+    use serde_json::Value;
+
+    use crate::rules::run_rule;
+
+    use super::RequireConcurrentIndexCreation;
+
+    #[test]
+    fn test_require_concurrent_index_creation() {
+        let rule = RequireConcurrentIndexCreation;
+        let empty = SchemaCache::default();
+        assert_eq!(
+            run_rule(&rule, "create index on users (email)", &empty, Value::Null),
+            vec![None]
+        );
+        assert!(run_rule(
+            &rule,
+            "create index concurrently on users (email)",
+            &empty,
+            Value::Null
+        )
+        .is_empty());
+
+        // the index of a table that the migration creates is allowed
+        let schema_cache = SchemaCache {
+            tables: vec![Table {
+                schema: "public".into(),
+                name: "users".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            run_rule(
+                &rule,
+                "create index on users (email)",
+                &schema_cache,
+                Value::Null
+            ),
+            vec![None]
+        );
+        assert!(run_rule(
+            &rule,
+            "create index on posts (title)",
+            &schema_cache,
+            Value::Null
+        )
+        .is_empty());
+    }
+}
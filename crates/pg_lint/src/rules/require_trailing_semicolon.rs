@@ -0,0 +1,82 @@
+use pg_lexer::{SyntaxKind, WHITESPACE_TOKENS};
+use serde_json::Value;
+use text_size::TextRange;
+
+use crate::{
+    violations::{RuleFix, RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags statements that are not terminated by a semicolon, which the next statement is appended
+/// to when the file is run with `psql`
+pub struct RequireTrailingSemicolon;
+
+impl Rule for RequireTrailingSemicolon {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::RequireTrailingSemicolon,
+            category: RuleCategory::Style,
+            docs: "https://www.postgresql.org/docs/current/sql-syntax-lexical.html#SQL-SYNTAX-SPECIAL-CHARS",
+            messages: vec![
+                ViolationMessage::Note("The statement is not terminated by a semicolon.".into()),
+                ViolationMessage::Help("Add a semicolon after the statement.".into()),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let Some(last) = params
+            .tokens()
+            .into_iter()
+            .rev()
+            .find(|token| !WHITESPACE_TOKENS.contains(&token.kind))
+        else {
+            return Vec::new();
+        };
+        if last.kind == SyntaxKind::Ascii59 {
+            return Vec::new();
+        }
+
+        // the semicolon is inserted after the last token rather than at the end, so that it does
+        // not end up in a trailing line comment
+        vec![RuleViolation::new(
+            RuleViolationKind::RequireTrailingSemicolon,
+            Some(last.span),
+            None,
+        )
+        .with_fix(RuleFix {
+            title: "Add `;`".to_string(),
+            edits: vec![(TextRange::empty(last.span.end()), ";".to_string())],
+            safe: true,
+        })]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::Value;
+
+    use crate::rules::run_rule;
+
+    use super::RequireTrailingSemicolon;
+
+    #[test]
+    fn test_require_trailing_semicolon() {
+        let violations = |sql| {
+            run_rule(
+                &RequireTrailingSemicolon,
+                sql,
+                &SchemaCache::default(),
+                Value::Null,
+            )
+        };
+        assert_eq!(violations("select id from users"), vec![Some("users")]);
+        assert_eq!(
+            violations("select id from users where id = 1 -- one\n"),
+            vec![Some("1")]
+        );
+        assert!(violations("select id from users;").is_empty());
+        assert!(violations("select id from users; -- all of them").is_empty());
+    }
+}
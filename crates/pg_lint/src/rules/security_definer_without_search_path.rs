@@ -0,0 +1,124 @@
+use pg_query_ext::NodeEnum;
+use serde_json::Value;
+
+use crate::{
+    violations::{RuleViolation, RuleViolationKind, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags functions and procedures that are created with `SECURITY DEFINER` but without setting
+/// their search path, so that a caller can make them run its own objects with the privileges of
+/// the owner
+///
+/// Only `CREATE FUNCTION` is checked, since the search path of an existing function is not
+/// known when it is altered.
+pub struct SecurityDefinerWithoutSearchPath;
+
+impl Rule for SecurityDefinerWithoutSearchPath {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::SecurityDefinerWithoutSearchPath,
+            category: RuleCategory::Security,
+            docs: "https://www.postgresql.org/docs/current/sql-createfunction.html#SQL-CREATEFUNCTION-SECURITY",
+            messages: vec![
+                ViolationMessage::Note(
+                    "The function runs with the privileges of its owner, but resolves names with the search path of the caller, who can put their own objects first."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Pin the search path of the function, e.g. with `SET search_path = ''` and qualified names."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let NodeEnum::CreateFunctionStmt(stmt) = params.ast else {
+            return Vec::new();
+        };
+        let options = stmt.options.iter().filter_map(|option| match &option.node {
+            Some(NodeEnum::DefElem(def)) => Some(def.as_ref()),
+            _ => None,
+        });
+
+        let mut security_definer = None;
+        for def in options {
+            match (
+                def.defname.as_str(),
+                def.arg.as_ref().and_then(|arg| arg.node.as_ref()),
+            ) {
+                ("security", Some(NodeEnum::Boolean(b))) if b.boolval => {
+                    security_definer = Some(def.location)
+                }
+                ("set", Some(NodeEnum::VariableSetStmt(set)))
+                    if set.name.eq_ignore_ascii_case("search_path") =>
+                {
+                    return Vec::new();
+                }
+                _ => {}
+            }
+        }
+
+        security_definer
+            .map(|location| {
+                RuleViolation::new(
+                    RuleViolationKind::SecurityDefinerWithoutSearchPath,
+                    params.keyword_range(location, "definer"),
+                    None,
+                )
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::Value;
+
+    use crate::rules::run_rule;
+
+    use super::SecurityDefinerWithoutSearchPath;
+
+    #[test]
+    fn test_security_definer_without_search_path() {
+        let violations = |sql| {
+            run_rule(
+                &SecurityDefinerWithoutSearchPath,
+                sql,
+                &SchemaCache::default(),
+                Value::Null,
+            )
+        };
+        assert_eq!(
+            violations(
+                "create function is_admin() returns bool language sql SECURITY  DEFINER as 'select true';"
+            ),
+            vec![Some("SECURITY  DEFINER")]
+        );
+        assert_eq!(
+            violations(
+                "create procedure archive() language sql security definer set work_mem = '64MB' as 'select 1'"
+            ),
+            vec![Some("security definer")]
+        );
+        assert!(violations(
+            "create function is_admin() returns bool language sql security definer set search_path = '' as 'select true';"
+        )
+        .is_empty());
+        assert!(violations(
+            "create function is_admin() returns bool language sql security definer set search_path from current as 'select true';"
+        )
+        .is_empty());
+        assert!(violations(
+            "create function is_admin() returns bool language sql security invoker as 'select true';"
+        )
+        .is_empty());
+        assert!(violations(
+            "create function is_admin() returns bool language sql as 'select true';"
+        )
+        .is_empty());
+    }
+}
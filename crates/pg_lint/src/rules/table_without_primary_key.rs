@@ -0,0 +1,216 @@
+use pg_query_ext::{
+    protobuf::{ColumnDef, ConstrType, Constraint},
+    NodeEnum,
+};
+use pg_schema_cache::{is_system_schema, SchemaCache, Table};
+use serde_json::Value;
+
+use crate::{
+    rules::index_column,
+    violations::{RuleViolation, RuleViolationKind, SchemaViolation, ViolationMessage},
+    LinterParams, Rule, RuleCategory, RuleMetadata,
+};
+
+/// Flags tables without a primary key or a unique constraint on columns that are not null,
+/// whose rows cannot be identified by logical replication or `on conflict`
+///
+/// Partitions are skipped, since they get the key of the partitioned table, and so are temporary
+/// tables and tables created with `like`, whose keys are not known.
+pub struct TableWithoutPrimaryKey;
+
+impl Rule for TableWithoutPrimaryKey {
+    fn metadata(&self) -> RuleMetadata {
+        RuleMetadata {
+            kind: RuleViolationKind::TableWithoutPrimaryKey,
+            category: RuleCategory::Correctness,
+            docs: "https://www.postgresql.org/docs/current/ddl-constraints.html#DDL-CONSTRAINTS-PRIMARY-KEYS",
+            messages: vec![
+                ViolationMessage::Note(
+                    "The table has no primary key, so its rows cannot be identified by logical replication or upserts."
+                        .into(),
+                ),
+                ViolationMessage::Help(
+                    "Add a primary key, or a unique constraint on columns that are not null."
+                        .into(),
+                ),
+            ],
+        }
+    }
+
+    fn run(&self, params: &LinterParams, _: &Value) -> Vec<RuleViolation> {
+        let NodeEnum::CreateStmt(stmt) = params.ast else {
+            return Vec::new();
+        };
+        let Some(relation) = &stmt.relation else {
+            return Vec::new();
+        };
+        if stmt.partbound.is_some() || relation.relpersistence == "t" {
+            return Vec::new();
+        }
+
+        let mut columns = Vec::new();
+        let mut constraints = Vec::new();
+        for element in &stmt.table_elts {
+            match &element.node {
+                Some(NodeEnum::ColumnDef(column)) => columns.push(column.as_ref()),
+                Some(NodeEnum::Constraint(constraint)) => constraints.push(constraint.as_ref()),
+                Some(NodeEnum::TableLikeClause(_)) => return Vec::new(),
+                _ => {}
+            }
+        }
+
+        let has_key = columns.iter().any(|column| {
+            column_constraints(column).any(|c| {
+                c.contype() == ConstrType::ConstrPrimary
+                    || (c.contype() == ConstrType::ConstrUnique && is_not_null(column))
+            })
+        }) || constraints
+            .iter()
+            .any(|constraint| match constraint.contype() {
+                ConstrType::ConstrPrimary => true,
+                ConstrType::ConstrUnique => constraint.keys.iter().all(|key| {
+                    let Some(NodeEnum::String(key)) = &key.node else {
+                        return false;
+                    };
+                    columns
+                        .iter()
+                        .any(|column| column.colname == key.sval && is_not_null(column))
+                }),
+                _ => false,
+            });
+        if has_key {
+            return Vec::new();
+        }
+
+        vec![RuleViolation::new(
+            RuleViolationKind::TableWithoutPrimaryKey,
+            params.relation_range(relation),
+            None,
+        )]
+    }
+
+    fn run_schema(&self, schema_cache: &SchemaCache, _: &Value) -> Vec<SchemaViolation> {
+        schema_cache
+            .tables
+            .iter()
+            .filter(|table| {
+                !is_system_schema(&table.schema)
+                    && !table.is_partition()
+                    && !has_key(schema_cache, table)
+            })
+            .map(|table| {
+                SchemaViolation::new(
+                    RuleViolationKind::TableWithoutPrimaryKey,
+                    format!("{}.{}", table.schema, table.name),
+                    None,
+                )
+            })
+            .collect()
+    }
+}
+
+fn column_constraints(column: &ColumnDef) -> impl Iterator<Item = &Constraint> {
+    column.constraints.iter().filter_map(|c| match &c.node {
+        Some(NodeEnum::Constraint(constraint)) => Some(constraint.as_ref()),
+        _ => None,
+    })
+}
+
+fn is_not_null(column: &ColumnDef) -> bool {
+    column.is_not_null
+        || column_constraints(column).any(|c| {
+            matches!(
+                c.contype(),
+                ConstrType::ConstrNotnull | ConstrType::ConstrPrimary | ConstrType::ConstrIdentity
+            )
+        })
+}
+
+/// Returns whether a table of the schema has a primary key, or a unique index on columns that are
+/// not null and that covers all rows
+fn has_key(schema_cache: &SchemaCache, table: &Table) -> bool {
+    let columns = schema_cache.find_columns(table.id);
+    if columns.iter().any(|column| column.is_primary_key) {
+        return true;
+    }
+    schema_cache.find_indexes(table.id).iter().any(|index| {
+        index.is_primary
+            || (index.is_unique
+                && !index.is_partial
+                && index.columns.iter().all(|key| {
+                    let name = index_column(key);
+                    columns
+                        .iter()
+                        .any(|column| column.name == name && !column.is_nullable)
+                }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use serde_json::Value;
+
+    use crate::{rules::run_rule, Rule};
+
+    use super::TableWithoutPrimaryKey;
+
+    #[test]
+    fn test_table_without_primary_key() {
+        let violations = |sql| {
+            run_rule(
+                &TableWithoutPrimaryKey,
+                sql,
+                &SchemaCache::default(),
+                Value::Null,
+            )
+        };
+        assert_eq!(
+            violations("create table public.events (name text, payload jsonb);"),
+            vec![Some("public.events")]
+        );
+        assert_eq!(
+            violations(r#"create table "Events"(name text unique)"#),
+            vec![Some(r#""Events""#)]
+        );
+        assert_eq!(
+            violations("create table events (a int not null, b int, unique (a, b))"),
+            vec![Some("events")]
+        );
+        assert!(violations("create table events (id bigint primary key)").is_empty());
+        assert!(violations("create table events (id int, primary key (id))").is_empty());
+        assert!(violations("create table events (name text not null unique)").is_empty());
+        assert!(violations(
+            "create table events (id int generated always as identity unique, name text)"
+        )
+        .is_empty());
+        assert!(violations("create table events (a int not null, unique (a))").is_empty());
+        assert!(violations("create table events_2024 partition of events default").is_empty());
+        assert!(violations("create temporary table scratch (name text)").is_empty());
+        assert!(violations("create table events_copy (like events)").is_empty());
+    }
+
+    #[test]
+    fn test_table_without_primary_key_in_schema() {
+        let schema_cache = SchemaCache::from_sql(
+            r#"
+create table users (id bigint primary key);
+create table logs (message text);
+create table tags (name text not null);
+create unique index tags_name_idx on tags (name);
+create table slugs ("Slug" text not null, team_id int);
+create unique index slugs_slug_idx on slugs ("Slug");
+create table teams (name text);
+create unique index teams_name_idx on teams (name);
+create table posts (title text not null);
+create unique index posts_title_idx on posts (title) where title <> '';
+"#,
+        );
+        let objects = TableWithoutPrimaryKey
+            .run_schema(&schema_cache, &Value::Null)
+            .into_iter()
+            .map(|v| v.object)
+            .collect::<Vec<_>>();
+        assert_eq!(objects, vec!["public.logs", "public.teams", "public.posts"]);
+    }
+}
@@ -0,0 +1,36 @@
+//! The nodes of a statement with their parents, for the rules that depend on where a node is
+
+use petgraph::{
+    stable_graph::{NodeIndex, StableGraph},
+    Direction,
+};
+use pg_query_ext::{get_nodes, Node, NodeEnum};
+
+pub(crate) struct NodeTree {
+    graph: StableGraph<Node, ()>,
+}
+
+impl NodeTree {
+    pub fn new(ast: &NodeEnum) -> NodeTree {
+        NodeTree {
+            graph: get_nodes(ast),
+        }
+    }
+
+    /// Returns the nodes, parents before their children
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeIndex, &Node)> {
+        self.graph.node_indices().map(|idx| (idx, &self.graph[idx]))
+    }
+
+    /// Returns the parents of a node, from its parent up to the root
+    pub fn ancestors(&self, idx: NodeIndex) -> impl Iterator<Item = &NodeEnum> {
+        std::iter::successors(self.parent(idx), |idx| self.parent(*idx))
+            .map(|idx| &self.graph[idx].inner)
+    }
+
+    fn parent(&self, idx: NodeIndex) -> Option<NodeIndex> {
+        self.graph
+            .neighbors_directed(idx, Direction::Incoming)
+            .next()
+    }
+}
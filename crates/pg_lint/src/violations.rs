@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-use crate::RULES;
+use crate::rule;
 use serde::{Deserialize, Serialize};
 use text_size::TextRange;
 
@@ -8,6 +8,68 @@ use text_size::TextRange;
 pub enum RuleViolationKind {
     #[serde(rename = "ban-drop-column")]
     BanDropColumn,
+    #[serde(rename = "ban-select-star")]
+    BanSelectStar,
+    #[serde(rename = "require-concurrent-index-creation")]
+    RequireConcurrentIndexCreation,
+    #[serde(rename = "adding-required-field")]
+    AddingRequiredField,
+    #[serde(rename = "changing-column-type")]
+    ChangingColumnType,
+    #[serde(rename = "renaming-column")]
+    RenamingColumn,
+    #[serde(rename = "renaming-table")]
+    RenamingTable,
+    #[serde(rename = "table-without-primary-key")]
+    TableWithoutPrimaryKey,
+    #[serde(rename = "foreign-key-without-index")]
+    ForeignKeyWithoutIndex,
+    #[serde(rename = "exposed-table-without-rls")]
+    ExposedTableWithoutRls,
+    #[serde(rename = "ban-grant-all-to-public")]
+    BanGrantAllToPublic,
+    #[serde(rename = "hardcoded-password")]
+    HardcodedPassword,
+    #[serde(rename = "security-definer-without-search-path")]
+    SecurityDefinerWithoutSearchPath,
+    #[serde(rename = "broad-schema-grant")]
+    BroadSchemaGrant,
+    #[serde(rename = "leading-wildcard-like")]
+    LeadingWildcardLike,
+    #[serde(rename = "not-in-subquery")]
+    NotInSubquery,
+    #[serde(rename = "function-on-indexed-column")]
+    FunctionOnIndexedColumn,
+    #[serde(rename = "implicit-cast-comparison")]
+    ImplicitCastComparison,
+    #[serde(rename = "keyword-case")]
+    KeywordCase,
+    #[serde(rename = "require-trailing-semicolon")]
+    RequireTrailingSemicolon,
+    #[serde(rename = "identifier-quoting")]
+    IdentifierQuoting,
+    #[serde(rename = "not-equal-operator")]
+    NotEqualOperator,
+    #[serde(rename = "naming-convention")]
+    NamingConvention,
+    #[serde(rename = "prefer-text-field")]
+    PreferTextField,
+    #[serde(rename = "prefer-timestamptz")]
+    PreferTimestamptz,
+    #[serde(rename = "ban-money-type")]
+    BanMoneyType,
+    #[serde(rename = "prefer-identity")]
+    PreferIdentity,
+    #[serde(rename = "ban-char-field")]
+    BanCharField,
+}
+
+impl RuleViolationKind {
+    /// Returns whether the rule is reported if it is not configured, see
+    /// [`RuleCategory::is_enabled_by_default`](crate::RuleCategory::is_enabled_by_default)
+    pub fn is_enabled_by_default(&self) -> bool {
+        rule(self).is_none_or(|rule| rule.metadata().category.is_enabled_by_default())
+    }
 }
 
 impl std::fmt::Display for RuleViolationKind {
@@ -51,11 +113,47 @@ pub enum ViolationMessage {
     Help(String),
 }
 
+/// Edits that fix a violation, whose ranges are relative to the statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleFix {
+    pub title: String,
+    pub edits: Vec<(TextRange, String)>,
+    /// Whether the fix keeps the meaning of the sql, so that it is applied without review, e.g.
+    /// on save or by `pglsp check --write`
+    pub safe: bool,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct RuleViolation {
     pub kind: RuleViolationKind,
     pub range: Option<TextRange>,
     pub messages: Vec<ViolationMessage>,
+    pub fix: Option<RuleFix>,
+}
+
+/// A violation of a rule by an existing object of the database, e.g. a table
+#[derive(Debug, PartialEq)]
+pub struct SchemaViolation {
+    pub kind: RuleViolationKind,
+    /// The qualified name of the object, e.g. `public.users`
+    pub object: String,
+    pub messages: Vec<ViolationMessage>,
+}
+
+impl SchemaViolation {
+    pub fn new(
+        kind: RuleViolationKind,
+        object: String,
+        messages: Option<Vec<ViolationMessage>>,
+    ) -> Self {
+        let messages = messages
+            .unwrap_or_else(|| rule(&kind).map_or_else(Vec::new, |r| r.metadata().messages));
+        Self {
+            kind,
+            object,
+            messages,
+        }
+    }
 }
 
 impl RuleViolation {
@@ -64,16 +162,20 @@ impl RuleViolation {
         range: Option<TextRange>,
         messages: Option<Vec<ViolationMessage>>,
     ) -> Self {
-        let messages = messages.unwrap_or_else(|| {
-            RULES
-                .iter()
-                .find(|r| r.name == kind)
-                .map_or_else(Vec::new, |x| x.messages.clone())
-        });
+        let messages = messages
+            .unwrap_or_else(|| rule(&kind).map_or_else(Vec::new, |r| r.metadata().messages));
         Self {
             kind,
             range,
             messages,
+            fix: None,
+        }
+    }
+
+    pub fn with_fix(self, fix: RuleFix) -> Self {
+        Self {
+            fix: Some(fix),
+            ..self
         }
     }
 }
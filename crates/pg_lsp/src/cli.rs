@@ -0,0 +1,192 @@
+//! The command line interface of `pglsp`, which runs the language server unless another command
+//! is given
+mod cache;
+pub(crate) mod check;
+mod doctor;
+mod explain;
+pub(crate) mod flags;
+mod generate;
+mod init;
+mod logging;
+mod metrics;
+mod reporter;
+mod schema;
+mod test;
+mod traverse;
+mod vcs;
+
+use std::{ffi::OsString, path::Path, process::ExitCode};
+
+use anyhow::Context;
+use lsp_server::Connection;
+use pg_workspace::{ConnectionRoutes, DEFAULT_CONNECTION};
+
+use crate::{
+    config,
+    server::{options::Options, Server},
+};
+
+pub fn run() -> anyhow::Result<ExitCode> {
+    let args = normalize_args(std::env::args_os().skip(1));
+    // editor integrations validate and complete pglsp.toml with the schema, which is not a
+    // command for users and not listed in the help
+    if args.len() == 1 && args[0] == "config-schema" {
+        println!("{}", serde_json::to_string_pretty(&Options::schema())?);
+        return Ok(ExitCode::SUCCESS);
+    }
+    let flags = flags::Pglsp::from_vec(args.clone()).unwrap_or_else(|err| err.exit());
+    let timings = logging::init(&flags)?;
+    let traced = flags.log_file.is_some() || flags.log_level.is_some() || flags.verbose;
+    let exit_code = match flags.subcommand {
+        flags::PglspCmd::Server(_) => {
+            let (connection, threads) = Connection::stdio();
+            Server::init(connection)?;
+            threads.join()?;
+            Ok(ExitCode::SUCCESS)
+        }
+        flags::PglspCmd::Check(mut flags) => {
+            // the daemon does not log or measure, so a check that is traced runs in this process
+            flags.no_daemon |= traced || flags.metrics.is_some();
+            Ok(check::check(flags, &args, timings.as_deref()))
+        }
+        flags::PglspCmd::Clean(_) => {
+            cache::clean(&std::env::current_dir()?.join(cache::CACHE_DIR))?;
+            Ok(ExitCode::SUCCESS)
+        }
+        flags::PglspCmd::Init(flags) => init::init(flags),
+        flags::PglspCmd::Doctor(flags) => doctor::doctor(flags),
+        flags::PglspCmd::Explain(flags) => explain::explain(flags),
+        flags::PglspCmd::Generate(flags) => generate::generate(flags),
+        flags::PglspCmd::Schema(flags) => schema::schema(flags),
+        flags::PglspCmd::Test(flags) => test::test(flags),
+        #[cfg(unix)]
+        flags::PglspCmd::Daemon(flags) => crate::daemon::command(flags),
+        #[cfg(not(unix))]
+        flags::PglspCmd::Daemon(_) => anyhow::bail!("The daemon is only supported on unix"),
+    };
+    if let Some(timings) = timings.filter(|_| flags.verbose) {
+        eprintln!();
+        timings.write(&mut std::io::stderr())?;
+    }
+    exit_code
+}
+
+/// Reads the options like `pglsp check` does, with the connection flags of a command on top
+fn load_options(
+    root: &Path,
+    config: Option<&Path>,
+    profile: Option<String>,
+    connection: Option<String>,
+) -> anyhow::Result<Options> {
+    let settings = match config {
+        Some(path) => check::read_settings(path)?,
+        None => serde_json::Value::Null,
+    };
+    let settings = config::layer(config::read_config_file(root)?, settings);
+    let (mut options, warnings) = Options::parse(settings).map_err(anyhow::Error::msg)?;
+    for warning in warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    if let Some(profile) = profile {
+        options.profile = Some(profile);
+    }
+    options.apply_profile().map_err(anyhow::Error::msg)?;
+    if let Some(connection_string) = connection {
+        options.db_connection_string = Some(connection_string);
+    }
+    Ok(options)
+}
+
+/// Returns the default connection string, which is resolved from the environment if it is not set
+fn connection_string(root: &Path, options: &Options) -> anyhow::Result<String> {
+    options
+        .db_connection_string
+        .clone()
+        .or_else(|| config::default_connection_string(Some(root)))
+        .context("There is no database, set `dbConnectionString` or use --connection")
+}
+
+/// Returns the connection string of the connection the file is routed to by the `connections`
+/// setting
+fn file_connection_string(root: &Path, options: &Options, path: &Path) -> anyhow::Result<String> {
+    let routes = ConnectionRoutes::new(
+        Some(root),
+        options
+            .connections
+            .iter()
+            .flat_map(|c| c.paths.iter().map(|path| (path.clone(), c.name.clone()))),
+    )
+    .context("Invalid connection path")?;
+    let path = traverse::normalize(&root.join(path));
+    match routes.connection(&path) {
+        DEFAULT_CONNECTION => connection_string(root, options),
+        name => options
+            .connection_strings()
+            .remove(name)
+            .with_context(|| format!("There is no connection named {}", name)),
+    }
+}
+
+/// Rewrites the arguments into the form xflags understands, which neither splits `--flag=value`
+/// nor accepts `-` as a path
+///
+/// A `-` is moved behind a `--`, which makes it a path.
+fn normalize_args(args: impl Iterator<Item = OsString>) -> Vec<OsString> {
+    let mut flags = Vec::new();
+    let mut paths = Vec::new();
+    let mut after_double_dash = false;
+    for arg in args {
+        match arg.to_str() {
+            _ if after_double_dash => paths.push(arg),
+            Some("--") => after_double_dash = true,
+            Some("-") => paths.push(arg),
+            Some(flag) if flag.starts_with("--") && flag.contains('=') => {
+                let (flag, value) = flag.split_once('=').unwrap();
+                flags.push(flag.into());
+                flags.push(value.into());
+            }
+            _ => flags.push(arg),
+        }
+    }
+    if after_double_dash || !paths.is_empty() {
+        flags.push("--".into());
+        flags.extend(paths);
+    }
+    flags
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::normalize_args;
+
+    #[test]
+    fn test_normalize_args() {
+        let normalize = |args: &[&str]| {
+            normalize_args(args.iter().map(OsString::from))
+                .into_iter()
+                .map(|arg| arg.into_string().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            normalize(&["check", "--stdin-file-path=migrations/0001.sql", "-"]),
+            vec![
+                "check",
+                "--stdin-file-path",
+                "migrations/0001.sql",
+                "--",
+                "-"
+            ]
+        );
+        assert_eq!(
+            normalize(&["check", "--", "--a=b.sql", "-"]),
+            vec!["check", "--", "--a=b.sql", "-"]
+        );
+        assert_eq!(
+            normalize(&["check", "-", "--config", "settings.json"]),
+            vec!["check", "--config", "settings.json", "--", "-"]
+        );
+    }
+}
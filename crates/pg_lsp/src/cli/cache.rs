@@ -0,0 +1,128 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use pg_diagnostics::Diagnostic;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// The directory within the working directory the results of `pglsp check` are cached in
+pub const CACHE_DIR: &str = ".pglsp_cache";
+
+/// The diagnostics of a file from a previous run along with the key they are valid for
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    key: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Caches the diagnostics of every checked file, so that files that did not change since the
+/// last run are not checked again
+///
+/// There is one entry per file, which is replaced when the file is checked again.
+#[derive(Debug)]
+pub struct Cache {
+    dir: PathBuf,
+    /// Hashes everything but the content a result depends on
+    context: String,
+}
+
+impl Cache {
+    /// Creates the cache for results of the given settings and schema cache versions, which are
+    /// part of the key of every entry together with the version of pglsp
+    pub fn new(dir: PathBuf, settings: &serde_json::Value, schema_versions: &[String]) -> Cache {
+        let mut hasher = Sha256::new();
+        hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+        hasher.update(b"\n");
+        hasher.update(settings.to_string().as_bytes());
+        for version in schema_versions {
+            hasher.update(b"\n");
+            hasher.update(version.as_bytes());
+        }
+        Cache {
+            dir,
+            context: hex::encode(hasher.finalize()),
+        }
+    }
+
+    /// Returns the key of the results for a file with the given content
+    pub fn key(&self, text: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.context.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(text.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    /// Returns the cached diagnostics of a file if they were cached with the same key
+    pub fn get(&self, file: &Path, key: &str) -> Option<Vec<Diagnostic>> {
+        let text = fs::read_to_string(self.entry_path(file)).ok()?;
+        let entry: Entry = serde_json::from_str(&text).ok()?;
+        (entry.key == key).then_some(entry.diagnostics)
+    }
+
+    pub fn put(&self, file: &Path, key: String, diagnostics: Vec<Diagnostic>) -> io::Result<()> {
+        let entry = Entry { key, diagnostics };
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.entry_path(file), serde_json::to_string(&entry)?)
+    }
+
+    /// Entries are named by the hash of the path of their file
+    fn entry_path(&self, file: &Path) -> PathBuf {
+        let hash = hex::encode(Sha256::digest(file.to_string_lossy().as_bytes()));
+        self.dir.join(hash).with_extension("json")
+    }
+}
+
+/// Removes the cache directory, if there is one
+pub fn clean(dir: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use pg_diagnostics::{Diagnostic, Severity};
+    use text_size::{TextRange, TextSize};
+
+    use super::{clean, Cache};
+
+    #[test]
+    fn test_cache() {
+        let dir = std::env::temp_dir().join(format!("pglsp-cache-{}", std::process::id()));
+        let settings = serde_json::json!({ "lint": { "ban-drop-column": "error" } });
+        let cache = Cache::new(dir.clone(), &settings, &["schema".to_string()]);
+        let file = Path::new("/project/migrations/0001.sql");
+        let diagnostic = Diagnostic {
+            message: "Dropping a column may break existing clients.".to_string(),
+            description: None,
+            code: Some("ban-drop-column".to_string()),
+            severity: Severity::Error,
+            source: "lint".to_string(),
+            range: TextRange::new(TextSize::new(0), TextSize::new(28)),
+        };
+
+        let text = "alter table t drop column c;";
+        let key = cache.key(text);
+        cache.put(file, key.clone(), vec![diagnostic]).unwrap();
+        let cached = cache.get(file, &key);
+        let changed_text = cache.get(file, &cache.key("select 1;"));
+        let other_schema = Cache::new(dir.clone(), &settings, &["other schema".to_string()]);
+        let changed_schema = other_schema.get(file, &other_schema.key(text));
+        let other_file = cache.get(Path::new("/project/migrations/0002.sql"), &key);
+        clean(&dir).unwrap();
+        let cleaned = cache.get(file, &key);
+
+        assert_eq!(cached.unwrap()[0].code.as_deref(), Some("ban-drop-column"));
+        assert!(changed_text.is_none());
+        assert!(changed_schema.is_none());
+        assert!(other_file.is_none());
+        assert!(cleaned.is_none());
+        assert!(clean(&dir).is_ok());
+    }
+}
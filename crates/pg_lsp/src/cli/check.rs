@@ -0,0 +1,657 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsString,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Instant,
+};
+
+use anyhow::Context;
+use pg_base_db::{Change, DocumentChange, PgLspPath};
+use pg_diagnostics::Diagnostic;
+use pg_schema_cache::SchemaCache;
+use pg_workspace::{apply_code_actions, ConnectionRoutes, Workspace, DEFAULT_CONNECTION};
+use sqlx::PgPool;
+use tracing::Instrument;
+
+#[cfg(unix)]
+use crate::daemon;
+use crate::{
+    config,
+    db::{self, ConnectionSettings, Tunnel},
+    server::options::{ExitCodesOptions, Options},
+};
+
+use super::{
+    cache::{Cache, CACHE_DIR},
+    flags,
+    logging::Timings,
+    metrics::{FileMetrics, RunMetrics},
+    reporter::{plural, DiagnosticsPayload, ReportOutputs, Reporter, TraversalSummary},
+    traverse::{collect_files, normalize, read_file, FileFilter},
+    vcs::{changed_files, staged_files, staged_text, DEFAULT_BASE},
+};
+
+/// Checks the files given on the command line and prints their diagnostics
+///
+/// Statements are parsed and linted, and typechecked if there is a connection. Exits with the
+/// code of the errors that were found, or with the internal code if the check failed.
+///
+/// The check runs in the daemon of the working directory if there is one, which has the schema
+/// caches of its connections loaded already.
+pub fn check(flags: flags::Check, args: &[OsString], timings: Option<&Timings>) -> ExitCode {
+    #[cfg(unix)]
+    if !flags.no_daemon && !reads_stdin(&flags) {
+        if let Some(output) = std::env::current_dir()
+            .ok()
+            .and_then(|root| daemon::check(&root, args))
+        {
+            let _ = io::stdout().write_all(output.stdout.as_bytes());
+            let _ = io::stderr().write_all(output.stderr.as_bytes());
+            return ExitCode::from(output.exit_code);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = args;
+
+    let code = run(
+        flags,
+        &mut io::stdout().lock(),
+        &mut io::stderr(),
+        &|options| async_std::task::block_on(connect(options)),
+        timings,
+    );
+    ExitCode::from(code)
+}
+
+/// Checks the files and writes the report to `out` and warnings and errors to `err`, opening the
+/// connections with `connect`, and returns the exit code
+///
+/// The timings of the phases are written to the metrics of `--metrics`, if they are collected.
+pub fn run(
+    flags: flags::Check,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+    connect: &dyn Fn(&Options) -> anyhow::Result<Connections>,
+    timings: Option<&Timings>,
+) -> u8 {
+    let error_on_warnings = flags.error_on_warnings;
+    let exit_code_flags = flags.exit_code.clone();
+    // the exit codes of the flags apply even if the settings file cannot be read
+    let mut exit_codes = ExitCodesOptions::default();
+    for flag in &exit_code_flags {
+        flag.apply(&mut exit_codes);
+    }
+    let summary = check_files(flags, out, err, connect, timings, |options| {
+        exit_codes = options.exit_codes.clone();
+        for flag in &exit_code_flags {
+            flag.apply(&mut exit_codes);
+        }
+    });
+    match summary {
+        Ok(summary) => exit_code(&exit_codes, &summary, error_on_warnings),
+        Err(error) => {
+            let _ = writeln!(err, "Error: {:?}", error);
+            exit_codes.internal
+        }
+    }
+}
+
+/// The open connections of a check, with their schema caches by name
+#[derive(Default)]
+pub struct Connections {
+    pub pools: HashMap<String, PgPool>,
+    pub schema_caches: HashMap<String, SchemaCache>,
+    /// The tunnels the pools connect through, which are closed when they are dropped
+    pub tunnels: Vec<Tunnel>,
+}
+
+impl Connections {
+    /// Without a default connection, builds the schema cache of the default connection from the
+    /// schema dump and files, if any
+    pub fn load_offline_schema_cache(&mut self, options: &Options) -> anyhow::Result<()> {
+        if self.pools.contains_key(DEFAULT_CONNECTION) {
+            return Ok(());
+        }
+        if let Some(schema_cache) = offline_schema_cache(options)? {
+            self.schema_caches
+                .insert(DEFAULT_CONNECTION.to_string(), schema_cache);
+        }
+        Ok(())
+    }
+}
+
+/// Builds a schema cache from the schema dump and files, or returns `None` if there are none
+pub fn offline_schema_cache(options: &Options) -> anyhow::Result<Option<SchemaCache>> {
+    if options.schema_dump.is_none() && options.schema_files.is_empty() {
+        return Ok(None);
+    }
+    let mut schema_cache = match &options.schema_dump {
+        Some(dump) => SchemaCache::from_dump(dump)
+            .with_context(|| format!("Could not read {}", dump.display()))?,
+        None => SchemaCache::new(),
+    };
+    for sql in SchemaCache::read_sql_files(&options.schema_files)? {
+        schema_cache.apply_sql(&sql);
+    }
+    Ok(Some(schema_cache))
+}
+
+/// Returns whether a check reads the file to check from stdin
+fn reads_stdin(flags: &flags::Check) -> bool {
+    flags.stdin_file_path.is_some() || flags.paths.iter().any(|p| p == Path::new("-"))
+}
+
+/// An exit code given on the command line as `outcome=code`, where the outcome is `errors`,
+/// `internal` or a category, e.g. `typecheck=2`
+#[derive(Debug, Clone)]
+pub struct ExitCodeFlag {
+    outcome: String,
+    code: u8,
+}
+
+impl ExitCodeFlag {
+    fn apply(&self, exit_codes: &mut ExitCodesOptions) {
+        match self.outcome.as_str() {
+            "errors" => exit_codes.errors = self.code,
+            "internal" => exit_codes.internal = self.code,
+            category => {
+                exit_codes
+                    .categories
+                    .insert(category.to_string(), self.code);
+            }
+        }
+    }
+}
+
+impl FromStr for ExitCodeFlag {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid exit code `{}`, expected e.g. `typecheck=2`", s);
+        let (outcome, code) = s.split_once('=').ok_or_else(invalid)?;
+        if outcome.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            outcome: outcome.to_string(),
+            code: code.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Returns the exit code of a run, which is the highest code of the categories with errors, or
+/// with warnings if they are errors, and 0 if there are none
+fn exit_code(
+    exit_codes: &ExitCodesOptions,
+    summary: &TraversalSummary,
+    error_on_warnings: bool,
+) -> u8 {
+    summary
+        .categories
+        .iter()
+        .filter(|(_, counts)| counts.errors > 0 || (error_on_warnings && counts.warnings > 0))
+        .map(|(category, _)| {
+            exit_codes
+                .categories
+                .get(category)
+                .copied()
+                .unwrap_or(exit_codes.errors)
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Checks the files and reports their diagnostics, calling `on_options` once the options are
+/// known
+fn check_files(
+    flags: flags::Check,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+    connect: &dyn Fn(&Options) -> anyhow::Result<Connections>,
+    timings: Option<&Timings>,
+    on_options: impl FnOnce(&Options),
+) -> anyhow::Result<TraversalSummary> {
+    let start = Instant::now();
+    let root = std::env::current_dir()?;
+    let stdin = reads_stdin(&flags);
+    let fixes = Fixes::new(&flags, stdin)?;
+
+    let settings = match &flags.config {
+        Some(path) => read_settings(path)?,
+        None => serde_json::Value::Null,
+    };
+    let settings = config::layer(config::read_config_file(&root)?, settings);
+    let (mut options, mut warnings) = Options::parse(settings).map_err(anyhow::Error::msg)?;
+    warnings.extend(config::read_nested_configs(&root, &mut options)?);
+    for warning in warnings {
+        let _ = writeln!(err, "Warning: {}", warning);
+    }
+    on_options(&options);
+    if let Some(profile) = flags.profile {
+        options.profile = Some(profile);
+    }
+    options.apply_profile().map_err(anyhow::Error::msg)?;
+    if let Some(connection_string) = flags.connection {
+        options.db_connection_string = Some(connection_string);
+    }
+    if options.db_connection_string.is_none() {
+        options.db_connection_string = config::default_connection_string(Some(&root));
+    }
+
+    let filter = FileFilter::new(&root, &options.files)
+        .context("Invalid files setting")?
+        .with_no_ignore(flags.no_ignore);
+    let changed = flags.changed || flags.since.is_some();
+    if changed && flags.staged {
+        anyhow::bail!("--changed and --staged cannot be used together");
+    }
+    let files = if stdin {
+        if flags.paths.iter().any(|p| p != Path::new("-")) {
+            anyhow::bail!("Paths cannot be checked together with stdin");
+        }
+        if changed || flags.staged {
+            anyhow::bail!("Changed or staged files cannot be checked together with stdin");
+        }
+        // the virtual path is skipped like a file given explicitly
+        let path = normalize(&root.join(flags.stdin_file_path.unwrap_or("stdin.sql".into())));
+        match filter.is_ignored(&path) {
+            true => Vec::new(),
+            false => vec![path],
+        }
+    } else {
+        let paths = match flags.paths.is_empty() {
+            true => vec![PathBuf::from(".")],
+            false => flags.paths,
+        };
+        let mut files = collect_files(&root, &paths, &filter)?;
+        if changed {
+            let changed_files =
+                changed_files(&root, flags.since.as_deref().unwrap_or(DEFAULT_BASE))?;
+            files.retain(|f| changed_files.binary_search(f).is_ok());
+        } else if flags.staged {
+            let staged_files = staged_files(&root)?;
+            files.retain(|f| staged_files.binary_search(f).is_ok());
+        }
+        files
+    };
+
+    let routes = ConnectionRoutes::new(
+        Some(&root),
+        options
+            .connections
+            .iter()
+            .flat_map(|c| c.paths.iter().map(|path| (path.clone(), c.name.clone()))),
+    )
+    .context("Invalid connection path")?;
+    let Connections {
+        pools,
+        schema_caches,
+        tunnels: _tunnels,
+    } = connect(&options)?;
+
+    let mut schema_versions = schema_caches
+        .iter()
+        .map(|(name, schema_cache)| format!("{}:{}", name, schema_cache.version_hash()))
+        .collect::<Vec<_>>();
+    schema_versions.sort();
+    let cache = Cache::new(
+        root.join(CACHE_DIR),
+        &serde_json::to_value(&options)?,
+        &schema_versions,
+    );
+
+    // every thread checks files in a workspace of its own, because computing a workspace
+    // analyses all of its changed documents
+    let new_workspace = || {
+        let ide = Workspace::new();
+        ide.linter.set_rule_severities(options.rule_severities());
+        ide.linter
+            .set_directory_rule_severities(options.directory_rule_severities());
+        ide.linter.set_rule_options(options.rule_options());
+        ide.set_connection_routes(routes.clone());
+        for (name, schema_cache) in &schema_caches {
+            ide.set_schema_cache(name, schema_cache.clone());
+        }
+        ide
+    };
+    // returns the warning that a file is ignored instead of its text if it is too large or binary
+    let read_text = |file: &Path| -> anyhow::Result<Result<String, Diagnostic>> {
+        if stdin {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            Ok(Ok(text))
+        } else if flags.staged {
+            staged_text(&root, file).map(Ok)
+        } else {
+            read_file(file, options.files.max_size)
+        }
+    };
+
+    tracing::info!("Checking {} files", files.len());
+    let threads = flags
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+        .clamp(1, files.len().max(1));
+    let mut reports = ReportOutputs::new(&flags.reporter, flags.report_file.as_deref(), &mut *out)?;
+    let mut visitor = reports.visitor();
+
+    // the threads send the result of every file as soon as it is checked, and they are reported
+    // in the order of the files, which is independent of the threads
+    let next = AtomicUsize::new(0);
+    let fixed_files = AtomicUsize::new(0);
+    let ignored_files = AtomicUsize::new(0);
+    let (cache_hits, cache_misses) = (AtomicUsize::new(0), AtomicUsize::new(0));
+    let (sender, receiver) = mpsc::channel();
+    let mut diffs = BTreeMap::new();
+    let summary = thread::scope(|s| -> anyhow::Result<TraversalSummary> {
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let (next, files, root) = (&next, &files, &root);
+            let (cache, pools, new_workspace, read_text) =
+                (&cache, &pools, &new_workspace, &read_text);
+            let (fixed_files, ignored_files, cache_hits, cache_misses) =
+                (&fixed_files, &ignored_files, &cache_hits, &cache_misses);
+            s.spawn(move || {
+                let mut ide = None;
+                loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(file) = files.get(idx) else {
+                        return;
+                    };
+                    let file_start = Instant::now();
+                    let path = file.strip_prefix(root).unwrap_or(file).to_path_buf();
+                    let span = tracing::debug_span!("check_file", path = %path.display());
+                    let payload = span.in_scope(|| read_text(file)).and_then(|text| {
+                        let _span = span.enter();
+                        let text = match text {
+                            Ok(text) => text,
+                            // an ignored file is neither checked nor cached
+                            Err(ignored) => {
+                                ignored_files.fetch_add(1, Ordering::Relaxed);
+                                return Ok((
+                                    DiagnosticsPayload {
+                                        path,
+                                        text: String::new(),
+                                        diagnostics: vec![ignored],
+                                        duration: file_start.elapsed(),
+                                    },
+                                    None,
+                                ));
+                            }
+                        };
+                        let key = cache.key(&text);
+                        let (text, diagnostics, diff) = match cache.get(file, &key) {
+                            // fixes are not cached, so files are checked again to fix them
+                            Some(diagnostics) if !flags.force && fixes.is_none() => {
+                                cache_hits.fetch_add(1, Ordering::Relaxed);
+                                (text, diagnostics, None)
+                            }
+                            _ => {
+                                cache_misses.fetch_add(1, Ordering::Relaxed);
+                                let ide = ide.get_or_insert_with(new_workspace);
+                                let (diagnostics, fixed) =
+                                    check_file(ide, pools, file, &text, fixes);
+                                match (fixed, fixes) {
+                                    (Some(fixed), Some(Fixes { diff: false, .. })) => {
+                                        std::fs::write(file, &fixed).with_context(|| {
+                                            format!("Could not write {}", file.display())
+                                        })?;
+                                        fixed_files.fetch_add(1, Ordering::Relaxed);
+                                        // the problems that remain are reported
+                                        let (diagnostics, _) =
+                                            check_file(ide, pools, file, &fixed, None);
+                                        let key = cache.key(&fixed);
+                                        let _ = cache.put(file, key, diagnostics.clone());
+                                        (fixed, diagnostics, None)
+                                    }
+                                    (fixed, _) => {
+                                        // a cache that cannot be written only makes the next
+                                        // run slower
+                                        let _ = cache.put(file, key, diagnostics.clone());
+                                        let diff =
+                                            fixed.map(|fixed| unified_diff(&path, &text, &fixed));
+                                        (text, diagnostics, diff)
+                                    }
+                                }
+                            }
+                        };
+                        Ok((
+                            DiagnosticsPayload {
+                                path,
+                                text,
+                                diagnostics,
+                                duration: file_start.elapsed(),
+                            },
+                            diff,
+                        ))
+                    });
+                    if sender.send((idx, payload)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let mut reporter = Reporter::new(&mut visitor)?.with_max_diagnostics(flags.max_diagnostics);
+        for (idx, payload) in receiver {
+            match payload {
+                Ok((payload, diff)) => {
+                    diffs.extend(diff.map(|diff| (idx, diff)));
+                    tracing::debug_span!("report").in_scope(|| reporter.add(idx, payload))?;
+                }
+                Err(err) => {
+                    // stop the other threads
+                    next.store(files.len(), Ordering::Relaxed);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(tracing::debug_span!("report").in_scope(|| reporter.finish(start.elapsed()))?)
+    })?;
+    drop(visitor);
+    reports.flush()?;
+    drop(reports);
+    // the diffs are written to stdout even if the report is not, so that they can be piped into
+    // `git apply`
+    for diff in diffs.values() {
+        write!(out, "{}", diff)?;
+    }
+    let fixed_files = fixed_files.into_inner();
+    if let Some(path) = &flags.metrics {
+        let files = FileMetrics {
+            checked: summary.files,
+            ignored: ignored_files.into_inner(),
+            fixed: fixed_files,
+        };
+        let cache = (cache_hits.into_inner(), cache_misses.into_inner());
+        RunMetrics::new(&summary, timings, files, cache, pools.len()).write(path)?;
+    }
+    if fixes.is_some_and(|fixes| !fixes.diff) {
+        let _ = writeln!(
+            err,
+            "Fixed {} {}.",
+            fixed_files,
+            plural(fixed_files, "file", "files")
+        );
+    }
+    Ok(summary)
+}
+
+/// The fixes a check applies, see `--write` and `--diff`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fixes {
+    /// Whether the fixes are printed as a diff instead of being written to the files
+    diff: bool,
+    include_unsafe: bool,
+}
+
+impl Fixes {
+    /// Returns the fixes of the flags, or `None` if the check does not fix the files
+    fn new(flags: &flags::Check, stdin: bool) -> anyhow::Result<Option<Fixes>> {
+        if flags.write && flags.diff {
+            anyhow::bail!("--write and --diff cannot be used together");
+        }
+        if flags.write && (stdin || flags.staged) {
+            anyhow::bail!("--write cannot fix stdin or staged files, use --diff instead");
+        }
+        if !flags.write && !flags.diff {
+            if flags.unsafe_fixes {
+                anyhow::bail!("--unsafe-fixes requires --write or --diff");
+            }
+            return Ok(None);
+        }
+        Ok(Some(Fixes {
+            diff: flags.diff,
+            include_unsafe: flags.unsafe_fixes,
+        }))
+    }
+}
+
+/// Returns the changes of the fixes of a file as a unified diff that `git apply` accepts
+fn unified_diff(path: &Path, text: &str, fixed: &str) -> String {
+    let path = path.to_string_lossy();
+    similar::TextDiff::from_lines(text, fixed)
+        .unified_diff()
+        .header(&format!("a/{}", path), &format!("b/{}", path))
+        .to_string()
+}
+
+/// Reads a file with the editor settings
+pub(super) fn read_settings(path: &Path) -> anyhow::Result<serde_json::Value> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("Could not parse {}", path.display()))
+}
+
+/// Opens the connections and loads their schema caches
+async fn connect(options: &Options) -> anyhow::Result<Connections> {
+    let settings = ConnectionSettings::new(options);
+    let mut connections = Connections::default();
+    for (name, connection_string) in options.connection_strings() {
+        let (pool, tunnel) = db::connect(&connection_string, &settings)
+            .await
+            .with_context(|| format!("Could not connect to the {} database", name))?;
+        tracing::info!("Connected to the {} database", name);
+        connections.schema_caches.insert(
+            name.clone(),
+            SchemaCache::load(&pool)
+                .instrument(tracing::debug_span!("schema_load", connection = %name))
+                .await,
+        );
+        connections.pools.insert(name, pool);
+        connections.tunnels.extend(tunnel);
+    }
+    connections.load_offline_schema_cache(options)?;
+    Ok(connections)
+}
+
+/// Checks a file and returns its diagnostics, together with the fixed text if fixes are applied
+/// and change it
+fn check_file(
+    ide: &Workspace,
+    pools: &HashMap<String, PgPool>,
+    file: &Path,
+    text: &str,
+    fixes: Option<Fixes>,
+) -> (Vec<Diagnostic>, Option<String>) {
+    let path = PgLspPath::new(file);
+    ide.apply_change(
+        path.clone(),
+        DocumentChange::new(
+            1,
+            vec![Change {
+                range: None,
+                text: text.to_string(),
+            }],
+        ),
+    );
+    ide.compute(pools);
+    let diagnostics = ide.diagnostics(&path);
+    let fixed = fixes
+        .map(|fixes| apply_code_actions(text, &ide.fixes(&path, fixes.include_unsafe)))
+        .filter(|fixed| fixed != text);
+    ide.remove_document(path);
+    (diagnostics, fixed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        cli::reporter::{SeverityCounts, TraversalSummary},
+        server::options::ExitCodesOptions,
+    };
+
+    use super::{exit_code, ExitCodeFlag};
+
+    #[test]
+    fn test_exit_code() {
+        let mut exit_codes = ExitCodesOptions {
+            errors: 1,
+            categories: HashMap::from([("typecheck".to_string(), 2)]),
+            internal: 3,
+        };
+        let summary = |categories: &[(&str, usize, usize)]| TraversalSummary {
+            categories: categories
+                .iter()
+                .map(|(category, errors, warnings)| {
+                    let counts = SeverityCounts {
+                        errors: *errors,
+                        warnings: *warnings,
+                    };
+                    (category.to_string(), counts)
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(exit_code(&exit_codes, &summary(&[]), false), 0);
+        assert_eq!(
+            exit_code(&exit_codes, &summary(&[("lint", 0, 2)]), false),
+            0
+        );
+        assert_eq!(exit_code(&exit_codes, &summary(&[("lint", 0, 2)]), true), 1);
+        assert_eq!(
+            exit_code(&exit_codes, &summary(&[("lint", 1, 0)]), false),
+            1
+        );
+        assert_eq!(
+            exit_code(
+                &exit_codes,
+                &summary(&[("lint", 1, 0), ("typecheck", 1, 0)]),
+                false
+            ),
+            2
+        );
+
+        "lint=4"
+            .parse::<ExitCodeFlag>()
+            .unwrap()
+            .apply(&mut exit_codes);
+        "internal=5"
+            .parse::<ExitCodeFlag>()
+            .unwrap()
+            .apply(&mut exit_codes);
+        assert_eq!(
+            exit_code(
+                &exit_codes,
+                &summary(&[("lint", 1, 0), ("typecheck", 1, 0)]),
+                false
+            ),
+            4
+        );
+        assert_eq!(exit_codes.internal, 5);
+        assert!("lint".parse::<ExitCodeFlag>().is_err());
+        assert!("lint=256".parse::<ExitCodeFlag>().is_err());
+    }
+}
@@ -0,0 +1,560 @@
+//! `pglsp doctor`, which checks the configuration and the database connections and prints how to
+//! fix the problems it finds
+
+use std::{
+    fmt::Write as _,
+    io::Write,
+    path::Path,
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+use pg_schema_cache::SchemaCache;
+use pg_workspace::{ConnectionRoutes, DEFAULT_CONNECTION};
+
+use crate::{
+    config,
+    db::{self, ConnectionSettings},
+    server::options::Options,
+};
+
+use super::{
+    check::{offline_schema_cache, read_settings},
+    flags,
+    init::without_password,
+    traverse::FileFilter,
+};
+
+/// The oldest server the schema cache queries run on
+const MIN_SERVER_VERSION: i32 = 110000;
+
+/// Round trips above this slow down typechecking noticeably
+const SLOW_ROUND_TRIP: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// The result of a single check, with how to fix it if it failed
+#[derive(Debug, PartialEq, Eq)]
+struct Finding {
+    status: Status,
+    message: String,
+    remediation: Option<String>,
+}
+
+impl Finding {
+    fn ok(message: impl Into<String>) -> Finding {
+        Finding {
+            status: Status::Ok,
+            message: message.into(),
+            remediation: None,
+        }
+    }
+
+    fn warning(message: impl Into<String>, remediation: impl Into<String>) -> Finding {
+        Finding {
+            status: Status::Warning,
+            message: message.into(),
+            remediation: Some(remediation.into()),
+        }
+    }
+
+    fn error(message: impl Into<String>, remediation: impl Into<String>) -> Finding {
+        Finding {
+            status: Status::Error,
+            message: message.into(),
+            remediation: Some(remediation.into()),
+        }
+    }
+}
+
+/// The findings of a part of the setup, e.g. of a connection
+#[derive(Debug)]
+struct Section {
+    title: String,
+    findings: Vec<Finding>,
+}
+
+/// Checks the setup of the working directory and prints the findings
+///
+/// Exits with 1 if there are errors.
+pub fn doctor(flags: flags::Doctor) -> anyhow::Result<ExitCode> {
+    let root = std::env::current_dir()?;
+    let mut sections = Vec::new();
+
+    let (config, options) = check_config(&root, &flags);
+    sections.push(config);
+
+    if let Some(options) = options {
+        let mut connections = options.connection_strings().into_iter().collect::<Vec<_>>();
+        if connections.is_empty() {
+            if let Some(connection_string) = config::default_connection_string(Some(&root)) {
+                connections.push((DEFAULT_CONNECTION.to_string(), connection_string));
+            }
+        }
+        connections.sort();
+        if let Some(connection_string) = &options.execution.connection_string {
+            connections.push(("execution".to_string(), connection_string.clone()));
+        }
+        for (name, connection_string) in connections {
+            sections.extend(async_std::task::block_on(check_connection(
+                &name,
+                &connection_string,
+                &options,
+            )));
+        }
+    }
+
+    let report = format_report(&sections);
+    std::io::stdout().write_all(report.as_bytes())?;
+
+    let has_errors = sections
+        .iter()
+        .flat_map(|s| &s.findings)
+        .any(|f| f.status == Status::Error);
+    Ok(if has_errors {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Checks the settings, and returns the options if they could be read
+fn check_config(root: &Path, flags: &flags::Doctor) -> (Section, Option<Options>) {
+    let mut findings = Vec::new();
+    let options = read_options(root, flags, &mut findings);
+    if let Some(options) = &options {
+        check_options(root, options, &mut findings);
+    }
+    (
+        Section {
+            title: "Configuration".to_string(),
+            findings,
+        },
+        options,
+    )
+}
+
+fn read_options(
+    root: &Path,
+    flags: &flags::Doctor,
+    findings: &mut Vec<Finding>,
+) -> Option<Options> {
+    let project = match config::read_config_file(root) {
+        Ok(Some(project)) => {
+            findings.push(Finding::ok(format!("Read {}", config::CONFIG_FILE)));
+            Some(project)
+        }
+        Ok(None) => {
+            findings.push(Finding::warning(
+                format!("There is no {} in {}", config::CONFIG_FILE, root.display()),
+                "Run `pglsp init` to create one, or run pglsp in the root of the project",
+            ));
+            None
+        }
+        Err(err) => {
+            findings.push(Finding::error(
+                format!("{:#}", err),
+                format!("Fix the syntax of {}", config::CONFIG_FILE),
+            ));
+            return None;
+        }
+    };
+    let settings = match &flags.config {
+        Some(path) => match read_settings(path) {
+            Ok(settings) => settings,
+            Err(err) => {
+                findings.push(Finding::error(
+                    format!("{:#}", err),
+                    "Pass a JSON file with the editor settings to --config",
+                ));
+                return None;
+            }
+        },
+        None => serde_json::Value::Null,
+    };
+
+    let (mut options, warnings) = match Options::parse(config::layer(project, settings)) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            findings.push(Finding::error(
+                err,
+                "Fix the setting, the schema of the settings is printed by `pglsp config-schema`",
+            ));
+            return None;
+        }
+    };
+    let nested_warnings = match config::read_nested_configs(root, &mut options) {
+        Ok(warnings) => warnings,
+        Err(err) => {
+            findings.push(Finding::error(
+                format!("{:#}", err),
+                format!(
+                    "Fix the {} files in the subdirectories",
+                    config::CONFIG_FILE
+                ),
+            ));
+            Vec::new()
+        }
+    };
+    for warning in warnings.into_iter().chain(nested_warnings) {
+        findings.push(Finding::warning(
+            warning,
+            "Remove the setting or fix its name, it is ignored",
+        ));
+    }
+
+    if let Some(profile) = flags.profile.clone() {
+        options.profile = Some(profile);
+    }
+    if let Err(err) = options.apply_profile() {
+        findings.push(Finding::error(
+            err,
+            "Add the profile to `profiles` or select another one",
+        ));
+        return None;
+    }
+    if let Some(connection_string) = flags.connection.clone() {
+        options.db_connection_string = Some(connection_string);
+    }
+    Some(options)
+}
+
+fn check_options(root: &Path, options: &Options, findings: &mut Vec<Finding>) {
+    if let Err(err) = FileFilter::new(root, &options.files) {
+        findings.push(Finding::error(
+            format!("Invalid `files` pattern: {}", err),
+            "Fix the glob in `files.include` or `files.ignore`",
+        ));
+    }
+    let routes = options
+        .connections
+        .iter()
+        .flat_map(|c| c.paths.iter().map(|path| (path.clone(), c.name.clone())));
+    if let Err(err) = ConnectionRoutes::new(Some(root), routes) {
+        findings.push(Finding::error(
+            format!("Invalid connection path: {}", err),
+            "Fix the glob in the `paths` of the connection",
+        ));
+    }
+
+    match offline_schema_cache(options) {
+        Ok(Some(schema_cache)) => findings.push(Finding::ok(format!(
+            "Read {} tables from the schema files",
+            schema_cache.tables.len()
+        ))),
+        Ok(None) => {}
+        Err(err) => findings.push(Finding::error(
+            format!("{:#}", err),
+            "Fix the paths of `schemaFiles` and `schemaDump`",
+        )),
+    }
+
+    if options.connection_strings().is_empty()
+        && config::default_connection_string(Some(root)).is_none()
+    {
+        findings.push(Finding::warning(
+            "There is no database connection, so statements are not typechecked",
+            "Set `dbConnectionString` or a profile, or set `DATABASE_URL`",
+        ));
+    }
+}
+
+/// Checks that a connection works and has the privileges the schema cache needs, and that its
+/// schema cache loads
+async fn check_connection(name: &str, connection_string: &str, options: &Options) -> Vec<Section> {
+    let mut findings = Vec::new();
+    let title = format!(
+        "Connection `{}` ({})",
+        name,
+        without_password(connection_string)
+    );
+
+    let start = Instant::now();
+    let pool = match db::connect(connection_string, &ConnectionSettings::new(options)).await {
+        Ok((pool, tunnel)) => {
+            findings.push(Finding::ok(format!(
+                "Connected in {} ms{}",
+                start.elapsed().as_millis(),
+                if tunnel.is_some() {
+                    " through the SSH tunnel"
+                } else {
+                    ""
+                }
+            )));
+            (pool, tunnel)
+        }
+        Err(err) => {
+            findings.push(Finding::error(
+                format!("Could not connect: {:#}", err),
+                "Check that the database runs and accepts connections from this host, and the \
+                 host, port, user and password of the connection string",
+            ));
+            return vec![Section { title, findings }];
+        }
+    };
+    let (pool, _tunnel) = pool;
+
+    let mut round_trip = Duration::MAX;
+    for _ in 0..3 {
+        let start = Instant::now();
+        if sqlx::query("select 1").execute(&pool).await.is_ok() {
+            round_trip = round_trip.min(start.elapsed());
+        }
+    }
+    if round_trip > SLOW_ROUND_TRIP {
+        findings.push(Finding::warning(
+            format!("Round trips take {} ms", round_trip.as_millis()),
+            "Run `pglsp daemon start` or enable `persistSchemaCache` to avoid reloading the \
+             schema, or use a database closer to this host",
+        ));
+    } else {
+        findings.push(Finding::ok(format!(
+            "Round trips take {:.1} ms",
+            round_trip.as_secs_f64() * 1000.0
+        )));
+    }
+
+    let server = sqlx::query_as::<_, (String, String, i32, bool)>(
+        "select current_user::text, current_setting('server_version'), \
+         current_setting('server_version_num')::int4, \
+         (select rolsuper from pg_roles where rolname = current_user)",
+    )
+    .fetch_one(&pool)
+    .await;
+    match server {
+        Ok((user, version, version_num, superuser)) => {
+            let role = if superuser {
+                format!("{} (superuser)", user)
+            } else {
+                user
+            };
+            if version_num < MIN_SERVER_VERSION {
+                findings.push(Finding::error(
+                    format!(
+                        "Postgres {} is not supported, connected as {}",
+                        version, role
+                    ),
+                    "Upgrade the database to Postgres 11 or newer",
+                ));
+            } else {
+                findings.push(Finding::ok(format!(
+                    "Postgres {}, connected as {}",
+                    version, role
+                )));
+            }
+            if options.install_event_trigger && !superuser {
+                findings.push(Finding::error(
+                    "`installEventTrigger` is enabled, but only superusers can create event \
+                     triggers",
+                    "Connect as a superuser, or disable `installEventTrigger` and set \
+                     `schemaCacheRefreshInterval` instead",
+                ));
+            }
+        }
+        Err(err) => findings.push(Finding::error(
+            format!("Could not query the server version: {}", err),
+            "Check that the user may read `pg_roles`",
+        )),
+    }
+
+    let schemas = sqlx::query_scalar::<_, String>(
+        "select nspname::text from pg_namespace \
+         where not has_schema_privilege(oid, 'usage') \
+         and nspname not like 'pg\\_%' and nspname <> 'information_schema' \
+         order by nspname",
+    )
+    .fetch_all(&pool)
+    .await;
+    match schemas {
+        Ok(schemas) if schemas.is_empty() => {
+            findings.push(Finding::ok("The user may use all schemas"))
+        }
+        Ok(schemas) => findings.push(Finding::warning(
+            format!(
+                "The user may not use the schemas {}, so statements using them fail to typecheck",
+                schemas.join(", ")
+            ),
+            format!(
+                "Run `grant usage on schema {} to current_user` as their owner",
+                schemas.join(", ")
+            ),
+        )),
+        Err(err) => findings.push(Finding::error(
+            format!("Could not query the schema privileges: {}", err),
+            "Check that the user may read `pg_namespace`",
+        )),
+    }
+
+    let mut sections = vec![Section { title, findings }];
+    sections.push(check_schema_cache(name, connection_string, options, &pool).await);
+    sections
+}
+
+/// Checks that the schema cache loads, and whether the persisted one is up to date
+async fn check_schema_cache(
+    name: &str,
+    connection_string: &str,
+    options: &Options,
+    pool: &sqlx::PgPool,
+) -> Section {
+    let mut findings = Vec::new();
+
+    let start = Instant::now();
+    let schema_cache = SchemaCache::load(pool).await;
+    let elapsed = start.elapsed();
+    let user_tables = schema_cache
+        .tables
+        .iter()
+        .filter(|t| !matches!(t.schema.as_str(), "pg_catalog" | "information_schema"))
+        .count();
+    findings.push(Finding::ok(format!(
+        "Loaded {} tables, {} columns, {} functions and {} types in {} ms",
+        schema_cache.tables.len(),
+        schema_cache.columns.len(),
+        schema_cache.functions.len(),
+        schema_cache.types.len(),
+        elapsed.as_millis()
+    )));
+    if user_tables == 0 {
+        findings.push(Finding::warning(
+            "The database has no tables of its own",
+            "Check that the connection string names the database of the project, or run its \
+             migrations",
+        ));
+    }
+    if elapsed > Duration::from_secs(5) && !options.lazy_schema_loading {
+        findings.push(Finding::warning(
+            "Loading the schema cache is slow",
+            "Enable `lazySchemaLoading` to only load the schemas in the `search_path` on start",
+        ));
+    }
+
+    if options.persist_schema_cache {
+        let dir = pg_schema_cache::default_cache_dir().map(|dir| match &options.profile {
+            Some(profile) => dir.join("profiles").join(profile),
+            None => dir,
+        });
+        match dir.and_then(|dir| SchemaCache::read_from_disk(&dir, connection_string)) {
+            Some(persisted) if persisted.version_hash() == schema_cache.version_hash() => {
+                findings.push(Finding::ok("The persisted schema cache is up to date"))
+            }
+            Some(_) => findings.push(Finding::warning(
+                "The persisted schema cache is out of date",
+                "It is refreshed the next time the language server starts",
+            )),
+            None => findings.push(Finding::warning(
+                "There is no persisted schema cache",
+                "It is written the next time the language server starts",
+            )),
+        }
+    }
+
+    Section {
+        title: format!("Schema cache `{}`", name),
+        findings,
+    }
+}
+
+fn format_report(sections: &[Section]) -> String {
+    let mut report = String::new();
+    let mut errors = 0;
+    let mut warnings = 0;
+    for section in sections {
+        let _ = writeln!(report, "{}", section.title);
+        for finding in &section.findings {
+            let status = match finding.status {
+                Status::Ok => "ok",
+                Status::Warning => {
+                    warnings += 1;
+                    "warning"
+                }
+                Status::Error => {
+                    errors += 1;
+                    "error"
+                }
+            };
+            let _ = writeln!(report, "  {:<8} {}", status, finding.message);
+            if let Some(remediation) = &finding.remediation {
+                let _ = writeln!(report, "  {:<8} -> {}", "", remediation);
+            }
+        }
+        report.push('\n');
+    }
+    let plural = |n: usize, word: &str| match n {
+        1 => format!("1 {}", word),
+        n => format!("{} {}s", n, word),
+    };
+    let _ = writeln!(
+        report,
+        "{}, {}",
+        plural(errors, "error"),
+        plural(warnings, "warning")
+    );
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::cli::flags;
+
+    use super::{check_config, format_report, Finding, Section, Status};
+
+    #[test]
+    fn test_check_config() {
+        let dir = std::env::temp_dir().join(format!("pglsp-doctor-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("pglsp.toml"),
+            "dbConnectionString = \"postgres://localhost/app\"\nunknown = 1\n",
+        )
+        .unwrap();
+
+        let flags = flags::Doctor {
+            config: None,
+            connection: None,
+            profile: Some("staging".to_string()),
+        };
+        let (section, options) = check_config(&dir, &flags);
+        assert!(options.is_none());
+        assert_eq!(
+            section
+                .findings
+                .iter()
+                .map(|f| f.status)
+                .collect::<Vec<_>>(),
+            [Status::Ok, Status::Warning, Status::Error]
+        );
+        assert_eq!(section.findings[1].message, "Unknown setting `unknown`");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_format_report() {
+        let sections = [Section {
+            title: "Configuration".to_string(),
+            findings: vec![
+                Finding::ok("Read pglsp.toml"),
+                Finding::warning("Unknown setting `unknown`", "Remove the setting"),
+            ],
+        }];
+
+        assert_eq!(
+            format_report(&sections),
+            "Configuration
+  ok       Read pglsp.toml
+  warning  Unknown setting `unknown`
+           -> Remove the setting
+
+0 errors, 1 warning
+"
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! `pglsp explain`, which prints the plans of the statements of a file
+
+use std::process::ExitCode;
+
+use anyhow::Context;
+use pg_base_db::{Document, PgLspPath};
+use pg_commands::{format_plan, ExecuteStatementCommand, ExplainStatementCommand};
+use serde::Serialize;
+
+use crate::db::{self, ConnectionSettings};
+
+use super::{file_connection_string, flags, load_options};
+
+/// The length statements are shortened to in the headings of their plans
+const HEADING_LENGTH: usize = 60;
+
+/// A statement of the file that is explained
+#[derive(Debug, PartialEq, Eq)]
+struct Statement<'a> {
+    /// The number of the statement within the file, starting at 1
+    number: usize,
+    /// The line the statement starts on, starting at 1
+    line: usize,
+    sql: &'a str,
+}
+
+/// The plan of a statement in the output of `--json`
+#[derive(Serialize)]
+struct ExplainResult<'a> {
+    statement: usize,
+    line: usize,
+    sql: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plan: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Explains the statements of a file on its connection and prints their plans
+///
+/// `--analyze` executes the statements. Their changes are rolled back, but they may still take
+/// locks or have side effects, so it requires `--allow-exec`. Exits with 1 if a statement could
+/// not be explained.
+pub fn explain(flags: flags::Explain) -> anyhow::Result<ExitCode> {
+    if flags.analyze && !flags.allow_exec {
+        anyhow::bail!("--analyze executes the statements, pass --allow-exec to allow it");
+    }
+
+    let root = std::env::current_dir()?;
+    let options = load_options(
+        &root,
+        flags.config.as_deref(),
+        flags.profile,
+        flags.connection,
+    )?;
+    let text = std::fs::read_to_string(&flags.path)
+        .with_context(|| format!("Could not read {}", flags.path.display()))?;
+    let document = Document::new(PgLspPath::new(&flags.path), Some(text));
+    let statements = select_statements(&document, flags.statement)?;
+
+    let connection_string = match &options.execution.connection_string {
+        Some(connection_string) => connection_string.clone(),
+        None => file_connection_string(&root, &options, &flags.path)?,
+    };
+    let settings = ConnectionSettings::new(&options);
+    let (pool, _tunnel) = async_std::task::block_on(db::connect(&connection_string, &settings))
+        .context("Could not connect to the database")?;
+
+    let mut results = Vec::new();
+    for statement in &statements {
+        let command = ExplainStatementCommand::new(statement.sql.to_string(), flags.analyze);
+        let (plan, error) = match async_std::task::block_on(command.plan(&pool)) {
+            Ok(plan) => (Some(plan), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        results.push(ExplainResult {
+            statement: statement.number,
+            line: statement.line,
+            sql: statement.sql,
+            plan,
+            error,
+        });
+    }
+
+    if flags.json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+    } else {
+        for (i, result) in results.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!("{}", heading(result));
+            match (&result.plan, &result.error) {
+                (Some(plan), _) => println!("{}", format_plan(plan)),
+                (_, Some(error)) => println!("Error: {}", error),
+                _ => {}
+            }
+        }
+    }
+
+    Ok(if results.iter().any(|r| r.error.is_some()) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Returns the statements of the document, or only the one with the given number
+fn select_statements(
+    document: &Document,
+    number: Option<usize>,
+) -> anyhow::Result<Vec<Statement<'_>>> {
+    let statements = document
+        .statement_ranges
+        .iter()
+        .enumerate()
+        .map(|(i, range)| Statement {
+            number: i + 1,
+            line: document.line_index.line_col(range.start()).line as usize + 1,
+            sql: &document.text[*range],
+        })
+        .collect::<Vec<_>>();
+
+    match number {
+        None if statements.is_empty() => anyhow::bail!("The file has no statements"),
+        None => Ok(statements),
+        Some(number) => {
+            let count = statements.len();
+            let statement = statements
+                .into_iter()
+                .find(|s| s.number == number)
+                .with_context(|| {
+                    format!(
+                        "There is no statement {}, the file has {} statements",
+                        number, count
+                    )
+                })?;
+            Ok(vec![statement])
+        }
+    }
+}
+
+/// Returns the heading of a plan, e.g. `-- Statement 2, line 5: select * from users`
+fn heading(result: &ExplainResult) -> String {
+    let sql = result.sql.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!(
+        "-- Statement {}, line {}: {}",
+        result.statement,
+        result.line,
+        ExecuteStatementCommand::trim_statement(sql, HEADING_LENGTH)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_base_db::{Document, PgLspPath};
+
+    use super::{select_statements, Statement};
+
+    #[test]
+    fn test_select_statements() {
+        let document = Document::new(
+            PgLspPath::new("queries.sql"),
+            Some("select 1;\n\nselect *\nfrom users;\n".to_string()),
+        );
+
+        assert_eq!(
+            select_statements(&document, None).unwrap(),
+            vec![
+                Statement {
+                    number: 1,
+                    line: 1,
+                    sql: "select 1;"
+                },
+                Statement {
+                    number: 2,
+                    line: 3,
+                    sql: "select *\nfrom users;"
+                },
+            ]
+        );
+        assert_eq!(select_statements(&document, Some(2)).unwrap()[0].line, 3);
+        assert_eq!(
+            select_statements(&document, Some(3))
+                .unwrap_err()
+                .to_string(),
+            "There is no statement 3, the file has 2 statements"
+        );
+    }
+}
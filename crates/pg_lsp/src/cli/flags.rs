@@ -0,0 +1,482 @@
+#![allow(unreachable_pub, clippy::large_enum_variant)]
+
+use std::path::PathBuf;
+
+use tracing::level_filters::LevelFilter;
+
+use super::{check::ExitCodeFlag, reporter::ReporterFlag, schema::LayoutFlag};
+
+xflags::xflags! {
+    src "./src/cli/flags.rs"
+
+    /// The Postgres language server and command line tools.
+    cmd pglsp {
+        /// Write logs to this file. They are written to stderr if only `--log-level` is given.
+        optional --log-file path: PathBuf
+        /// The level of the logs: `error`, `warn`, `info` (the default), `debug` or `trace`.
+        optional --log-level level: LevelFilter
+        /// Print how long the phases of the run took, e.g. loading the schema and typechecking,
+        /// in total and for the slowest files.
+        optional --verbose
+
+        /// Run the language server on stdin and stdout.
+        default cmd server {}
+
+        /// Check sql files for syntax errors, lint violations and, if there is a database
+        /// connection, type errors.
+        cmd check {
+            /// Files, directories or globs to check, or `-` to check stdin. Defaults to the
+            /// working directory.
+            repeated paths: PathBuf
+            /// A settings file in the format of the editor settings, e.g. with `files.ignore`. It
+            /// overrides the `pglsp.toml` of the working directory.
+            optional --config path: PathBuf
+            /// The database to typecheck against instead of the `dbConnectionString` setting.
+            optional --connection connection_string: String
+            /// The connection profile to typecheck against instead of the `profile` setting.
+            optional --profile name: String
+            /// Also read files that git ignores, which are skipped in directories otherwise.
+            optional --no-ignore
+            /// The path stdin is checked as, which decides its connection and is reported in
+            /// diagnostics. Implies checking stdin.
+            optional --stdin-file-path path: PathBuf
+            /// Only check files that changed in git since the base branch, including uncommitted
+            /// and untracked files.
+            optional --changed
+            /// The base of `--changed`, e.g. `main`. Defaults to the default branch of `origin`.
+            /// Implies `--changed`.
+            optional --since ref: String
+            /// Only check files that are staged in git, in their staged state, e.g. in a
+            /// pre-commit hook.
+            optional --staged
+            /// The number of files to check at the same time. Defaults to the number of CPUs.
+            optional --threads count: usize
+            /// Check all files, even those whose results are cached from a previous run.
+            optional --force
+            /// Apply the safe fixes of the problems that are found to the files, and report the
+            /// problems that remain.
+            optional --write
+            /// Print the fixes as a diff after the report instead of applying them.
+            optional --diff
+            /// Also apply fixes that may change the meaning of the sql, e.g. of misspelled
+            /// names. Requires `--write` or `--diff`.
+            optional --unsafe-fixes
+            /// The format of the output: `console` (the default), `checkstyle`, `ndjson`,
+            /// `markdown`, `html`, `tap` or `junit`. Can be repeated to write several formats,
+            /// each but one to a file of its own given as `format=path`, e.g. `junit=junit.xml`.
+            repeated --reporter reporter: ReporterFlag
+            /// Write the output of the reporter without a file of its own to this file instead
+            /// of stdout.
+            optional --report-file path: PathBuf
+            /// Report at most this many diagnostics. All diagnostics are still counted.
+            optional --max-diagnostics count: usize
+            /// Write the metrics of the run as JSON to a file, e.g. the duration of each phase,
+            /// the cache hit rate and the number of database round trips.
+            optional --metrics path: PathBuf
+            /// Fail if there are warnings, not only if there are errors.
+            optional --error-on-warnings
+            /// The exit code of an outcome as `outcome=code`, where the outcome is `errors`,
+            /// `internal` or a category of diagnostics, e.g. `typecheck=2`. Overrides the
+            /// `exitCodes` setting.
+            repeated --exit-code outcome_code: ExitCodeFlag
+            /// Check in this process even if a daemon runs in the working directory.
+            optional --no-daemon
+            /// Reload the schema caches of the daemon before checking instead of using the
+            /// schema as of the end of the previous check. Checks outside the daemon always load
+            /// the schema.
+            optional --reload-schema-cache
+        }
+
+        /// Remove the results of `pglsp check` cached in the working directory.
+        cmd clean {}
+
+        /// Create a pglsp.toml in the working directory, which connects to `DATABASE_URL` and
+        /// checks the migration directories that are found.
+        cmd init {
+            /// Overwrite an existing pglsp.toml.
+            optional --force
+        }
+
+        /// Run a daemon in the working directory, which keeps the connections and schema caches
+        /// of `pglsp check` and the language server warm.
+        cmd daemon {
+            /// Start the daemon in the background.
+            cmd start {
+                /// Run the daemon in the foreground instead.
+                optional --foreground
+            }
+            /// Stop the daemon.
+            cmd stop {}
+            /// Print whether the daemon runs.
+            cmd status {}
+        }
+
+        /// Check the configuration and the database connections, and print how to fix the
+        /// problems that are found.
+        cmd doctor {
+            /// A settings file in the format of the editor settings. It overrides the
+            /// `pglsp.toml` of the working directory.
+            optional --config path: PathBuf
+            /// The database to check instead of the `dbConnectionString` setting.
+            optional --connection connection_string: String
+            /// The connection profile to check instead of the `profile` setting.
+            optional --profile name: String
+        }
+
+        /// Print the plans of the statements of a file, as `EXPLAIN` does.
+        cmd explain {
+            /// The sql file.
+            required path: PathBuf
+            /// Only explain the statement with this number, starting at 1.
+            optional --statement number: usize
+            /// Print the plans in the JSON format of `EXPLAIN`.
+            optional --json
+            /// Execute the statements to report their actual times and row counts. Requires
+            /// `--allow-exec`.
+            optional --analyze
+            /// Allow `--analyze` to execute the statements. Their changes are rolled back, but
+            /// they may still take locks or have side effects.
+            optional --allow-exec
+            /// A settings file in the format of the editor settings. It overrides the
+            /// `pglsp.toml` of the working directory.
+            optional --config path: PathBuf
+            /// The database to explain on instead of the `dbConnectionString` setting.
+            optional --connection connection_string: String
+            /// The connection profile to explain on instead of the `profile` setting.
+            optional --profile name: String
+        }
+
+        /// Run pgTAP tests, each file in a transaction that is rolled back.
+        cmd test {
+            /// Test files or directories, which are searched for `*_test.sql` files. Defaults to
+            /// the working directory.
+            repeated paths: PathBuf
+            /// A settings file in the format of the editor settings. It overrides the
+            /// `pglsp.toml` of the working directory.
+            optional --config path: PathBuf
+            /// The database to run the tests on instead of the `dbConnectionString` setting.
+            optional --connection connection_string: String
+            /// The connection profile to run the tests on instead of the `profile` setting.
+            optional --profile name: String
+            /// Also read files that git ignores, which are skipped in directories otherwise.
+            optional --no-ignore
+            /// The format of the output: `console` (the default), `checkstyle`, `ndjson`,
+            /// `markdown`, `html`, `tap` or `junit`. Can be repeated to write several formats,
+            /// each but one to a file of its own given as `format=path`, e.g. `junit=junit.xml`.
+            repeated --reporter reporter: ReporterFlag
+            /// Write the output of the reporter without a file of its own to this file instead
+            /// of stdout.
+            optional --report-file path: PathBuf
+        }
+
+        /// Generate code from the queries of sql files, which are named with `-- name:`
+        /// comments and described by the database.
+        cmd generate {
+            /// Generate Rust structs and functions for sqlx.
+            cmd rust {
+                /// Sql files or directories with queries. Defaults to the working directory.
+                repeated paths: PathBuf
+                /// The file to write the code to instead of stdout.
+                optional -o, --output path: PathBuf
+                /// A settings file in the format of the editor settings. It overrides the
+                /// `pglsp.toml` of the working directory.
+                optional --config path: PathBuf
+                /// The database to describe the queries on instead of the `dbConnectionString`
+                /// setting.
+                optional --connection connection_string: String
+                /// The connection profile to describe the queries on instead of the `profile`
+                /// setting.
+                optional --profile name: String
+                /// Also read files that git ignores, which are skipped in directories otherwise.
+                optional --no-ignore
+            }
+            /// Generate TypeScript types for the rows and parameters of the queries, and for the
+            /// tables and enums of the schema.
+            cmd ts {
+                /// Sql files or directories with queries. Defaults to the working directory.
+                repeated paths: PathBuf
+                /// The directory to write `schema.ts` and `queries.ts` to.
+                required -o, --output path: PathBuf
+                /// Only generate the types of this schema. Can be given multiple times.
+                repeated --schema name: String
+                /// A settings file in the format of the editor settings. It overrides the
+                /// `pglsp.toml` of the working directory.
+                optional --config path: PathBuf
+                /// The database to describe the queries and schema on instead of the
+                /// `dbConnectionString` setting.
+                optional --connection connection_string: String
+                /// The connection profile to describe the queries and schema on instead of the
+                /// `profile` setting.
+                optional --profile name: String
+                /// Also read files that git ignores, which are skipped in directories otherwise.
+                optional --no-ignore
+            }
+        }
+
+        /// Work with the schema of the database.
+        cmd schema {
+            /// Write the schema of the database as sql files, which can be committed and read
+            /// with the `schemaFiles` setting.
+            cmd dump {
+                /// The directory to write the files to. Defaults to `db/schema`.
+                optional -o, --output path: PathBuf
+                /// `schema` to write a file per schema (the default), or `object` to write a
+                /// file per table, type and function.
+                optional --layout layout: LayoutFlag
+                /// Only dump this schema. Can be given multiple times.
+                repeated --schema name: String
+                /// A settings file in the format of the editor settings. It overrides the
+                /// `pglsp.toml` of the working directory.
+                optional --config path: PathBuf
+                /// The database to dump instead of the `dbConnectionString` setting.
+                optional --connection connection_string: String
+                /// The connection profile to dump instead of the `profile` setting.
+                optional --profile name: String
+            }
+            /// Compare the schema of the database with the expected one, e.g. to detect drift in
+            /// CI. Exits with 1 if they differ.
+            cmd diff {
+                /// Sql files or directories with the expected schema. Defaults to the
+                /// `schemaFiles` and `schemaDump` settings.
+                repeated paths: PathBuf
+                /// The database with the expected schema, instead of sql files.
+                optional --expected connection_string: String
+                /// Only compare this schema. Can be given multiple times.
+                repeated --schema name: String
+                /// Print the differences as JSON.
+                optional --json
+                /// A settings file in the format of the editor settings. It overrides the
+                /// `pglsp.toml` of the working directory.
+                optional --config path: PathBuf
+                /// The database to compare instead of the `dbConnectionString` setting.
+                optional --connection connection_string: String
+                /// The connection profile to compare instead of the `profile` setting.
+                optional --profile name: String
+            }
+            /// Check the existing objects of the database against the lint rules that check the
+            /// schema, e.g. tables without a primary key. Exits with 1 if there are errors.
+            cmd lint {
+                /// Only check this schema. Can be given multiple times.
+                repeated --schema name: String
+                /// A settings file in the format of the editor settings. It overrides the
+                /// `pglsp.toml` of the working directory.
+                optional --config path: PathBuf
+                /// The database to check instead of the `dbConnectionString` setting.
+                optional --connection connection_string: String
+                /// The connection profile to check instead of the `profile` setting.
+                optional --profile name: String
+            }
+        }
+    }
+}
+// generated start
+// The following code is generated by `xflags` macro.
+// Run `env UPDATE_XFLAGS=1 cargo build` to regenerate.
+#[derive(Debug)]
+pub struct Pglsp {
+    pub log_file: Option<PathBuf>,
+    pub log_level: Option<LevelFilter>,
+    pub verbose: bool,
+    pub subcommand: PglspCmd,
+}
+
+#[derive(Debug)]
+pub enum PglspCmd {
+    Server(Server),
+    Check(Check),
+    Clean(Clean),
+    Init(Init),
+    Daemon(Daemon),
+    Doctor(Doctor),
+    Explain(Explain),
+    Test(Test),
+    Generate(Generate),
+    Schema(Schema),
+}
+
+#[derive(Debug)]
+pub struct Server;
+
+#[derive(Debug)]
+pub struct Check {
+    pub paths: Vec<PathBuf>,
+
+    pub config: Option<PathBuf>,
+    pub connection: Option<String>,
+    pub profile: Option<String>,
+    pub no_ignore: bool,
+    pub stdin_file_path: Option<PathBuf>,
+    pub changed: bool,
+    pub since: Option<String>,
+    pub staged: bool,
+    pub threads: Option<usize>,
+    pub force: bool,
+    pub write: bool,
+    pub diff: bool,
+    pub unsafe_fixes: bool,
+    pub reporter: Vec<ReporterFlag>,
+    pub report_file: Option<PathBuf>,
+    pub max_diagnostics: Option<usize>,
+    pub metrics: Option<PathBuf>,
+    pub error_on_warnings: bool,
+    pub exit_code: Vec<ExitCodeFlag>,
+    pub no_daemon: bool,
+    pub reload_schema_cache: bool,
+}
+
+#[derive(Debug)]
+pub struct Clean;
+
+#[derive(Debug)]
+pub struct Init {
+    pub force: bool,
+}
+
+#[derive(Debug)]
+pub struct Daemon {
+    pub subcommand: DaemonCmd,
+}
+
+#[derive(Debug)]
+pub enum DaemonCmd {
+    Start(Start),
+    Stop(Stop),
+    Status(Status),
+}
+
+#[derive(Debug)]
+pub struct Start {
+    pub foreground: bool,
+}
+
+#[derive(Debug)]
+pub struct Stop;
+
+#[derive(Debug)]
+pub struct Status;
+
+#[derive(Debug)]
+pub struct Doctor {
+    pub config: Option<PathBuf>,
+    pub connection: Option<String>,
+    pub profile: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Explain {
+    pub path: PathBuf,
+
+    pub statement: Option<usize>,
+    pub json: bool,
+    pub analyze: bool,
+    pub allow_exec: bool,
+    pub config: Option<PathBuf>,
+    pub connection: Option<String>,
+    pub profile: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Test {
+    pub paths: Vec<PathBuf>,
+
+    pub config: Option<PathBuf>,
+    pub connection: Option<String>,
+    pub profile: Option<String>,
+    pub no_ignore: bool,
+    pub reporter: Vec<ReporterFlag>,
+    pub report_file: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct Generate {
+    pub subcommand: GenerateCmd,
+}
+
+#[derive(Debug)]
+pub enum GenerateCmd {
+    Rust(Rust),
+    Ts(Ts),
+}
+
+#[derive(Debug)]
+pub struct Rust {
+    pub paths: Vec<PathBuf>,
+
+    pub output: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    pub connection: Option<String>,
+    pub profile: Option<String>,
+    pub no_ignore: bool,
+}
+
+#[derive(Debug)]
+pub struct Ts {
+    pub paths: Vec<PathBuf>,
+
+    pub output: PathBuf,
+    pub schema: Vec<String>,
+    pub config: Option<PathBuf>,
+    pub connection: Option<String>,
+    pub profile: Option<String>,
+    pub no_ignore: bool,
+}
+
+#[derive(Debug)]
+pub struct Schema {
+    pub subcommand: SchemaCmd,
+}
+
+#[derive(Debug)]
+pub enum SchemaCmd {
+    Dump(Dump),
+    Diff(Diff),
+    Lint(Lint),
+}
+
+#[derive(Debug)]
+pub struct Dump {
+    pub output: Option<PathBuf>,
+    pub layout: Option<LayoutFlag>,
+    pub schema: Vec<String>,
+    pub config: Option<PathBuf>,
+    pub connection: Option<String>,
+    pub profile: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Diff {
+    pub paths: Vec<PathBuf>,
+
+    pub expected: Option<String>,
+    pub schema: Vec<String>,
+    pub json: bool,
+    pub config: Option<PathBuf>,
+    pub connection: Option<String>,
+    pub profile: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Lint {
+    pub schema: Vec<String>,
+    pub config: Option<PathBuf>,
+    pub connection: Option<String>,
+    pub profile: Option<String>,
+}
+
+impl Pglsp {
+    #[allow(dead_code)]
+    pub fn from_env_or_exit() -> Self {
+        Self::from_env_or_exit_()
+    }
+
+    #[allow(dead_code)]
+    pub fn from_env() -> xflags::Result<Self> {
+        Self::from_env_()
+    }
+
+    #[allow(dead_code)]
+    pub fn from_vec(args: Vec<std::ffi::OsString>) -> xflags::Result<Self> {
+        Self::from_vec_(args)
+    }
+}
+// generated end
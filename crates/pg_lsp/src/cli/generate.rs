@@ -0,0 +1,192 @@
+//! `pglsp generate`, which generates code from the queries of sql files
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::Context;
+use pg_codegen::{
+    generate_rust, generate_typescript, generate_typescript_schema, parse_queries, Query,
+    SCHEMA_MODULE,
+};
+use pg_typecheck::{describe_sql, StatementDescription};
+
+use crate::{
+    db::{self, ConnectionSettings},
+    server::options::Options,
+};
+
+use super::{
+    connection_string, file_connection_string, flags, load_options,
+    schema::{load_schema, retain_schemas},
+    traverse::{collect_files, read_file, FileFilter},
+};
+
+/// The module of the queries in the output of `pglsp generate ts`
+const QUERIES_MODULE: &str = "queries";
+
+pub fn generate(flags: flags::Generate) -> anyhow::Result<ExitCode> {
+    match flags.subcommand {
+        flags::GenerateCmd::Rust(flags) => rust(flags),
+        flags::GenerateCmd::Ts(flags) => ts(flags),
+    }
+}
+
+/// Generates Rust structs and functions for the queries
+fn rust(flags: flags::Rust) -> anyhow::Result<ExitCode> {
+    let root = std::env::current_dir()?;
+    let options = load_options(
+        &root,
+        flags.config.as_deref(),
+        flags.profile,
+        flags.connection,
+    )?;
+    let Some(queries) = describe_queries(&root, &options, &flags.paths, flags.no_ignore)? else {
+        return Ok(ExitCode::FAILURE);
+    };
+    match generate_rust(&queries) {
+        Ok(code) => write_output(flags.output.as_deref(), &code),
+        Err(err) => {
+            eprintln!("{}", err);
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Generates the TypeScript modules of the schema and the queries
+///
+/// The module of the queries is only written if there are queries.
+fn ts(flags: flags::Ts) -> anyhow::Result<ExitCode> {
+    let root = std::env::current_dir()?;
+    let options = load_options(
+        &root,
+        flags.config.as_deref(),
+        flags.profile,
+        flags.connection,
+    )?;
+    let Some(queries) = describe_queries(&root, &options, &flags.paths, flags.no_ignore)? else {
+        return Ok(ExitCode::FAILURE);
+    };
+    let mut cache = load_schema(&connection_string(&root, &options)?, &options)?;
+    if !flags.schema.is_empty() {
+        retain_schemas(&mut cache, &flags.schema);
+    }
+
+    let mut modules = vec![(SCHEMA_MODULE, generate_typescript_schema(&cache))];
+    if !queries.is_empty() {
+        match generate_typescript(&queries, &cache) {
+            Ok(code) => modules.push((QUERIES_MODULE, code)),
+            Err(err) => {
+                eprintln!("{}", err);
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+    for (module, code) in &modules {
+        write_output(Some(&flags.output.join(format!("{}.ts", module))), code)?;
+    }
+
+    println!(
+        "Wrote {} to {}",
+        modules
+            .iter()
+            .map(|(module, _)| format!("{}.ts", module))
+            .collect::<Vec<_>>()
+            .join(" and "),
+        flags.output.display()
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Reads the named queries of the files and describes them on the database the files are routed
+/// to
+///
+/// Returns `None` if a query is invalid, after printing why.
+fn describe_queries(
+    root: &Path,
+    options: &Options,
+    paths: &[PathBuf],
+    no_ignore: bool,
+) -> anyhow::Result<Option<Vec<(Query, StatementDescription)>>> {
+    let filter = FileFilter::new(root, &options.files)
+        .context("Invalid files setting")?
+        .with_no_ignore(no_ignore);
+    let paths = match paths.is_empty() {
+        true => vec![PathBuf::from(".")],
+        false => paths.to_vec(),
+    };
+    let files = collect_files(root, &paths, &filter)?;
+
+    let settings = ConnectionSettings::new(options);
+    let mut pools = HashMap::new();
+    let mut queries: Vec<(Query, StatementDescription)> = Vec::new();
+    let mut errors = Vec::new();
+    for file in files {
+        let path = file
+            .strip_prefix(root)
+            .unwrap_or(&file)
+            .display()
+            .to_string();
+        let text = match read_file(&file, options.files.max_size)? {
+            Ok(text) => text,
+            Err(ignored) => {
+                eprintln!("{}: {}", path, ignored.message);
+                continue;
+            }
+        };
+        let file_queries = match parse_queries(&path, &text) {
+            Ok(file_queries) => file_queries,
+            Err(err) => {
+                errors.push(err.to_string());
+                continue;
+            }
+        };
+        if file_queries.is_empty() {
+            continue;
+        }
+
+        let connection_string = file_connection_string(root, options, &file)?;
+        if !pools.contains_key(&connection_string) {
+            let connection = async_std::task::block_on(db::connect(&connection_string, &settings))
+                .context("Could not connect to the database")?;
+            pools.insert(connection_string.clone(), connection);
+        }
+        let (pool, _tunnel) = &pools[&connection_string];
+
+        for query in file_queries {
+            if let Some((other, _)) = queries.iter().find(|(q, _)| q.name == query.name) {
+                errors.push(format!(
+                    "{}:{}: The name `{}` is already used by the query in {}, line {}",
+                    query.path, query.line, query.name, other.path, other.line
+                ));
+                continue;
+            }
+            match async_std::task::block_on(describe_sql(pool, &query.sql)) {
+                Ok(description) => queries.push((query, description)),
+                Err(err) => errors.push(format!("{}:{}: {}", query.path, query.line, err)),
+            }
+        }
+    }
+
+    for error in &errors {
+        eprintln!("{}", error);
+    }
+    Ok(errors.is_empty().then_some(queries))
+}
+
+/// Writes the code to the output file, or to stdout if there is none
+fn write_output(output: Option<&Path>, code: &str) -> anyhow::Result<ExitCode> {
+    match output {
+        Some(output) => {
+            if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(output, code)
+                .with_context(|| format!("Could not write {}", output.display()))?;
+        }
+        None => print!("{}", code),
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,283 @@
+//! Logging to a file or stderr, and the timing of the phases of a run for `--verbose`
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context as _;
+use tracing::{
+    field::{Field, Visit},
+    level_filters::LevelFilter,
+    span::{Attributes, Id},
+    Subscriber,
+};
+use tracing_subscriber::{
+    filter::filter_fn,
+    fmt::writer::BoxMakeWriter,
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    Layer,
+};
+
+use super::flags;
+
+/// The name of the span of a checked file, whose `path` field is the path of the file
+const FILE_SPAN: &str = "check_file";
+
+/// The number of slowest files whose phases are printed
+const SLOWEST_FILES: usize = 5;
+
+/// Installs the subscriber of the logging flags, and returns the timings that are collected for
+/// `--verbose` and `pglsp check --metrics`
+///
+/// Logs are written to the log file, or to stderr if only a level is given. Nothing is installed
+/// without any of the flags.
+pub fn init(flags: &flags::Pglsp) -> anyhow::Result<Option<Arc<Timings>>> {
+    let log = match (&flags.log_file, flags.log_level) {
+        (None, None) => None,
+        (log_file, level) => {
+            let writer = match log_file {
+                Some(path) => BoxMakeWriter::new(Mutex::new(
+                    File::create(path)
+                        .with_context(|| format!("Could not create {}", path.display()))?,
+                )),
+                None => BoxMakeWriter::new(io::stderr),
+            };
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(writer)
+                    .with_ansi(false)
+                    .with_filter(level.unwrap_or(LevelFilter::INFO)),
+            )
+        }
+    };
+    let metrics =
+        matches!(&flags.subcommand, flags::PglspCmd::Check(check) if check.metrics.is_some());
+    let timings = (flags.verbose || metrics).then(|| Arc::new(Timings::default()));
+    if log.is_none() && timings.is_none() {
+        return Ok(None);
+    }
+
+    tracing_subscriber::registry()
+        .with(log)
+        .with(timings.clone().map(|timings| {
+            // the phases are spans of the crates of pglsp, whose names start with `pg_`
+            TimingLayer(timings)
+                .with_filter(filter_fn(|m| m.is_span() && m.target().starts_with("pg_")))
+        }))
+        .try_init()?;
+    Ok(timings)
+}
+
+/// How often a phase ran, and how long it took in total
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseTiming {
+    pub count: usize,
+    pub total: Duration,
+}
+
+/// How long a file took to check, and how long each phase took within it
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileTiming {
+    path: String,
+    total: Duration,
+    phases: BTreeMap<&'static str, Duration>,
+}
+
+/// The time spent in each phase of a run, e.g. `split` or `typecheck`, which are the spans of
+/// the crates of pglsp
+///
+/// Nested phases are included in the time of the phases they run in, e.g. `lex` in `split`.
+#[derive(Debug, Default)]
+pub struct Timings {
+    phases: Mutex<BTreeMap<&'static str, PhaseTiming>>,
+    files: Mutex<Vec<FileTiming>>,
+}
+
+impl Timings {
+    /// Returns the timing of each phase by name
+    pub fn phases(&self) -> BTreeMap<&'static str, PhaseTiming> {
+        self.phases.lock().unwrap().clone()
+    }
+
+    /// Writes the phases, slowest first, and the phases of the slowest files
+    pub fn write(&self, out: &mut dyn io::Write) -> io::Result<()> {
+        let mut phases = self
+            .phases
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, timing)| (*name, *timing))
+            .collect::<Vec<_>>();
+        phases.sort_by_key(|(_, timing)| std::cmp::Reverse(timing.total));
+        let mut files = self.files.lock().unwrap().clone();
+        files.sort_by_key(|file| std::cmp::Reverse(file.total));
+        files.truncate(SLOWEST_FILES);
+
+        writeln!(out, "Phases (summed over all threads):")?;
+        let width = phases.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        for (name, timing) in &phases {
+            writeln!(
+                out,
+                "  {:width$}  {:>10}  {:>6} {}",
+                name,
+                millis(timing.total),
+                timing.count,
+                if timing.count == 1 { "run" } else { "runs" },
+                width = width
+            )?;
+        }
+
+        if files.is_empty() {
+            return Ok(());
+        }
+        writeln!(out)?;
+        writeln!(out, "Phases of the slowest files:")?;
+        for file in &files {
+            let mut file_phases = file.phases.iter().collect::<Vec<_>>();
+            file_phases.sort_by_key(|(_, duration)| std::cmp::Reverse(**duration));
+            writeln!(
+                out,
+                "  {}  {}: {}",
+                file.path,
+                millis(file.total),
+                file_phases
+                    .iter()
+                    .map(|(name, duration)| format!("{} {}", name, millis(**duration)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+        Ok(())
+    }
+
+    fn add_phase(&self, name: &'static str, duration: Duration) {
+        let mut phases = self.phases.lock().unwrap();
+        let phase = phases.entry(name).or_default();
+        phase.count += 1;
+        phase.total += duration;
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Measures the spans of a run into the timings
+pub(super) struct TimingLayer(pub(super) Arc<Timings>);
+
+/// When a span was created
+struct SpanStart(Instant);
+
+/// The phases of the file of a file span so far
+struct FilePhases {
+    path: String,
+    phases: BTreeMap<&'static str, Duration>,
+}
+
+/// Reads the `path` field of a file span
+#[derive(Default)]
+struct PathVisitor(String);
+
+impl Visit for PathVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "path" {
+            self.0 = format!("{:?}", value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "path" {
+            self.0 = value.to_string();
+        }
+    }
+}
+
+impl<S> Layer<S> for TimingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        extensions.insert(SpanStart(Instant::now()));
+        if attrs.metadata().name() == FILE_SPAN {
+            let mut visitor = PathVisitor::default();
+            attrs.record(&mut visitor);
+            extensions.insert(FilePhases {
+                path: visitor.0,
+                phases: BTreeMap::new(),
+            });
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(duration) = span.extensions().get::<SpanStart>().map(|s| s.0.elapsed()) else {
+            return;
+        };
+        if let Some(file) = span.extensions_mut().remove::<FilePhases>() {
+            self.0.files.lock().unwrap().push(FileTiming {
+                path: file.path,
+                total: duration,
+                phases: file.phases,
+            });
+            return;
+        }
+
+        self.0.add_phase(span.name(), duration);
+        if let Some(file) = span.scope().skip(1).find(|s| s.name() == FILE_SPAN) {
+            if let Some(file_phases) = file.extensions_mut().get_mut::<FilePhases>() {
+                *file_phases.phases.entry(span.name()).or_default() += duration;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+    use super::{TimingLayer, Timings};
+
+    #[test]
+    fn test_timings() {
+        let timings = Arc::new(Timings::default());
+        let subscriber = Registry::default().with(TimingLayer(timings.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            let _file = tracing::debug_span!("check_file", path = "a.sql").entered();
+            for _ in 0..2 {
+                let _split = tracing::debug_span!("split").entered();
+                let _lex = tracing::debug_span!("lex").entered();
+            }
+            tracing::debug_span!("typecheck").in_scope(|| {});
+        });
+
+        let phases = timings.phases.lock().unwrap();
+        assert_eq!(
+            phases
+                .iter()
+                .map(|(name, timing)| (*name, timing.count))
+                .collect::<Vec<_>>(),
+            vec![("lex", 2), ("split", 2), ("typecheck", 1)]
+        );
+        let files = timings.files.lock().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "a.sql");
+        assert_eq!(
+            files[0].phases.keys().copied().collect::<Vec<_>>(),
+            vec!["lex", "split", "typecheck"]
+        );
+    }
+}
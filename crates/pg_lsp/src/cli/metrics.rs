@@ -0,0 +1,189 @@
+//! The metrics of a check for `pglsp check --metrics`, e.g. to track its performance in CI
+
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use super::{logging::Timings, reporter::TraversalSummary};
+
+/// The phases that take one round trip to the database each time they run
+const ROUND_TRIP_PHASES: &[&str] = &["schema_load", "prepare"];
+
+/// The metrics of a check, which are written as JSON
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunMetrics {
+    pub duration_ms: f64,
+    pub files: FileMetrics,
+    pub cache: CacheMetrics,
+    pub diagnostics: DiagnosticMetrics,
+    pub database: DatabaseMetrics,
+    /// The phases by name, e.g. `split` or `typecheck`, summed over all threads
+    pub phases: BTreeMap<&'static str, PhaseMetrics>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileMetrics {
+    /// The files that were reported, including those whose result was cached
+    pub checked: usize,
+    /// The files that were ignored because they are too large or binary
+    pub ignored: usize,
+    pub fixed: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheMetrics {
+    pub hits: usize,
+    pub misses: usize,
+    /// The share of the hits of all lookups, or 0 if there were none
+    pub hit_rate: f64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticMetrics {
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseMetrics {
+    pub connections: usize,
+    /// The queries sent to the databases, i.e. loading a schema and preparing a statement to
+    /// typecheck it
+    pub round_trips: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhaseMetrics {
+    pub count: usize,
+    pub duration_ms: f64,
+}
+
+impl RunMetrics {
+    /// Collects the metrics of a check from its summary and the timings of its phases
+    pub fn new(
+        summary: &TraversalSummary,
+        timings: Option<&Timings>,
+        files: FileMetrics,
+        (hits, misses): (usize, usize),
+        connections: usize,
+    ) -> RunMetrics {
+        let phases = timings.map(Timings::phases).unwrap_or_default();
+        RunMetrics {
+            duration_ms: millis(summary.duration),
+            files,
+            cache: CacheMetrics {
+                hits,
+                misses,
+                hit_rate: match hits + misses {
+                    0 => 0.0,
+                    lookups => hits as f64 / lookups as f64,
+                },
+            },
+            diagnostics: DiagnosticMetrics {
+                errors: summary.errors,
+                warnings: summary.warnings,
+            },
+            database: DatabaseMetrics {
+                connections,
+                round_trips: ROUND_TRIP_PHASES
+                    .iter()
+                    .filter_map(|name| phases.get(name))
+                    .map(|phase| phase.count)
+                    .sum(),
+            },
+            phases: phases
+                .into_iter()
+                .map(|(name, phase)| {
+                    let metrics = PhaseMetrics {
+                        count: phase.count,
+                        duration_ms: millis(phase.total),
+                    };
+                    (name, metrics)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Could not write {}", path.display()))
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1000.0 * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use serde_json::json;
+    use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+    use crate::cli::{
+        logging::{TimingLayer, Timings},
+        reporter::TraversalSummary,
+    };
+
+    use super::{FileMetrics, RunMetrics};
+
+    #[test]
+    fn test_run_metrics() {
+        let timings = Arc::new(Timings::default());
+        let subscriber = Registry::default().with(TimingLayer(timings.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug_span!("schema_load").in_scope(|| {});
+            for _ in 0..2 {
+                let _typecheck = tracing::debug_span!("typecheck").entered();
+                let _prepare = tracing::debug_span!("prepare").entered();
+            }
+        });
+        let summary = TraversalSummary {
+            files: 4,
+            errors: 1,
+            warnings: 2,
+            duration: Duration::from_millis(1500),
+            ..Default::default()
+        };
+
+        let metrics = RunMetrics::new(
+            &summary,
+            Some(&timings),
+            FileMetrics {
+                checked: 4,
+                ignored: 1,
+                fixed: 0,
+            },
+            (1, 2),
+            1,
+        );
+        let mut value = serde_json::to_value(&metrics).unwrap();
+        // the durations of the phases vary
+        for phase in value["phases"].as_object_mut().unwrap().values_mut() {
+            phase["durationMs"] = json!(0.0);
+        }
+        assert_eq!(
+            value,
+            json!({
+                "durationMs": 1500.0,
+                "files": { "checked": 4, "ignored": 1, "fixed": 0 },
+                "cache": { "hits": 1, "misses": 2, "hitRate": 1.0 / 3.0 },
+                "diagnostics": { "errors": 1, "warnings": 2 },
+                "database": { "connections": 1, "roundTrips": 3 },
+                "phases": {
+                    "prepare": { "count": 2, "durationMs": 0.0 },
+                    "schema_load": { "count": 1, "durationMs": 0.0 },
+                    "typecheck": { "count": 2, "durationMs": 0.0 },
+                },
+            })
+        );
+    }
+}
@@ -0,0 +1,463 @@
+mod checkstyle;
+mod console;
+mod html;
+mod junit;
+mod markdown;
+mod ndjson;
+mod tap;
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::Context;
+
+use line_index::LineIndex;
+use pg_diagnostics::{Diagnostic, Severity};
+use text_size::TextSize;
+
+/// The diagnostics of a single file
+#[derive(Debug)]
+pub struct DiagnosticsPayload {
+    /// The path of the file as it is reported, i.e. relative to the working directory if it is
+    /// within it
+    pub path: PathBuf,
+    pub text: String,
+    pub diagnostics: Vec<Diagnostic>,
+    /// How long checking the file took
+    pub duration: Duration,
+}
+
+impl DiagnosticsPayload {
+    /// Returns the 1-based line and column of an offset in the file
+    pub fn line_col(&self, diagnostic: &Diagnostic) -> (u32, u32) {
+        self.position(diagnostic.range.start())
+    }
+
+    /// Returns the 1-based line and column of an offset
+    pub fn position(&self, offset: TextSize) -> (u32, u32) {
+        let line_col = LineIndex::new(&self.text).line_col(offset);
+        (line_col.line + 1, line_col.col + 1)
+    }
+}
+
+/// The number of slowest files that are kept in the summary
+const SLOWEST_FILES: usize = 5;
+
+/// The number of errors and warnings of some diagnostics
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SeverityCounts {
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl SeverityCounts {
+    pub fn total(&self) -> usize {
+        self.errors + self.warnings
+    }
+}
+
+/// The counts of a run over a set of files
+///
+/// Hints and infos are not counted.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TraversalSummary {
+    pub files: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub duration: Duration,
+    /// The counts per rule, see `rule`
+    pub rules: BTreeMap<String, SeverityCounts>,
+    /// The counts per category, i.e. per source of the diagnostics such as `lint`
+    pub categories: BTreeMap<String, SeverityCounts>,
+    /// The files that took longest to check, slowest first
+    pub slowest_files: Vec<(PathBuf, Duration)>,
+    /// The number of diagnostics that were counted but not reported because of the maximum
+    /// number of diagnostics
+    pub not_shown: usize,
+}
+
+impl TraversalSummary {
+    pub fn add(&mut self, payload: &DiagnosticsPayload) {
+        self.files += 1;
+        for diagnostic in &payload.diagnostics {
+            let is_error = match diagnostic.severity {
+                Severity::Error | Severity::Fatal => true,
+                Severity::Warning => false,
+                Severity::Hint | Severity::Information => continue,
+            };
+            let rule = self.rules.entry(rule(diagnostic)).or_default();
+            let category = self
+                .categories
+                .entry(diagnostic.source.clone())
+                .or_default();
+            if is_error {
+                self.errors += 1;
+                rule.errors += 1;
+                category.errors += 1;
+            } else {
+                self.warnings += 1;
+                rule.warnings += 1;
+                category.warnings += 1;
+            }
+        }
+
+        let idx = self
+            .slowest_files
+            .partition_point(|(_, duration)| *duration >= payload.duration);
+        if idx < SLOWEST_FILES {
+            self.slowest_files
+                .insert(idx, (payload.path.clone(), payload.duration));
+            self.slowest_files.truncate(SLOWEST_FILES);
+        }
+    }
+}
+
+/// Returns the rule of a diagnostic, which is its code, or its source if it has none, e.g.
+/// `pg_query` for syntax errors
+fn rule(diagnostic: &Diagnostic) -> String {
+    diagnostic
+        .code
+        .clone()
+        .unwrap_or_else(|| diagnostic.source.clone())
+}
+
+pub use checkstyle::CheckstyleReporter;
+pub use console::ConsoleReporter;
+pub use html::HtmlReporter;
+pub use junit::JunitReporter;
+pub use markdown::MarkdownReporter;
+pub use ndjson::NdjsonReporter;
+pub use tap::TapReporter;
+
+/// The format the result of a run is written in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReporterKind {
+    #[default]
+    Console,
+    Checkstyle,
+    Ndjson,
+    Markdown,
+    Html,
+    Tap,
+    Junit,
+}
+
+impl FromStr for ReporterKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "console" => Ok(ReporterKind::Console),
+            "checkstyle" => Ok(ReporterKind::Checkstyle),
+            "ndjson" => Ok(ReporterKind::Ndjson),
+            "markdown" => Ok(ReporterKind::Markdown),
+            "html" => Ok(ReporterKind::Html),
+            "tap" => Ok(ReporterKind::Tap),
+            "junit" => Ok(ReporterKind::Junit),
+            _ => Err(format!(
+                "unknown reporter `{}`, expected one of console, checkstyle, ndjson, markdown, \
+                 html, tap, junit",
+                s
+            )),
+        }
+    }
+}
+
+impl ReporterKind {
+    /// Returns the visitor that writes the result in this format
+    fn visitor<'a>(self, out: &'a mut dyn io::Write) -> Box<dyn ReporterVisitor + 'a> {
+        match self {
+            ReporterKind::Console => Box::new(ConsoleReporter::new(out)),
+            ReporterKind::Checkstyle => Box::new(CheckstyleReporter::new(out)),
+            ReporterKind::Ndjson => Box::new(NdjsonReporter::new(out)),
+            ReporterKind::Markdown => Box::new(MarkdownReporter::new(out, github_blob_url())),
+            ReporterKind::Html => Box::new(HtmlReporter::new(out)),
+            ReporterKind::Tap => Box::new(TapReporter::new(out)),
+            ReporterKind::Junit => Box::new(JunitReporter::new(out)),
+        }
+    }
+}
+
+/// A reporter of `--reporter`, as `kind` or `kind=path`, e.g. `junit=junit.xml` to write the
+/// result in the JUnit format to a file of its own
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReporterFlag {
+    kind: ReporterKind,
+    file: Option<PathBuf>,
+}
+
+impl FromStr for ReporterFlag {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, file) = match s.split_once('=') {
+            Some((_, "")) => {
+                return Err(format!(
+                    "Invalid reporter `{}`, expected e.g. `junit=junit.xml`",
+                    s
+                ))
+            }
+            Some((kind, file)) => (kind, Some(PathBuf::from(file))),
+            None => (s, None),
+        };
+        Ok(Self {
+            kind: kind.parse()?,
+            file,
+        })
+    }
+}
+
+/// The reporters of a run and the outputs they write to
+///
+/// Reporters write to their own file if they have one, and otherwise to the report file, or to
+/// stdout if there is none. Only one reporter can write to the latter.
+pub struct ReportOutputs<'a> {
+    outputs: Vec<(ReporterKind, Box<dyn io::Write + 'a>)>,
+}
+
+impl<'a> ReportOutputs<'a> {
+    /// Opens the outputs of the reporters, which are the console reporter if none are given
+    pub fn new(
+        reporters: &[ReporterFlag],
+        report_file: Option<&Path>,
+        stdout: impl io::Write + 'a,
+    ) -> anyhow::Result<Self> {
+        let default = [ReporterFlag {
+            kind: ReporterKind::Console,
+            file: None,
+        }];
+        let reporters = match reporters.is_empty() {
+            true => &default[..],
+            false => reporters,
+        };
+        if reporters.iter().filter(|r| r.file.is_none()).count() > 1 {
+            anyhow::bail!(
+                "Only one reporter can write to {}, give the others a file of their own, e.g. \
+                 `--reporter junit=junit.xml`",
+                match report_file {
+                    Some(path) => path.display().to_string(),
+                    None => "stdout".to_string(),
+                }
+            );
+        }
+
+        let mut stdout = Some(stdout);
+        let mut outputs = Vec::new();
+        for reporter in reporters {
+            let output: Box<dyn io::Write + 'a> = match (reporter.file.as_deref(), report_file) {
+                (Some(path), _) | (None, Some(path)) => Box::new(io::BufWriter::new(
+                    File::create(path)
+                        .with_context(|| format!("Could not create {}", path.display()))?,
+                )),
+                (None, None) => {
+                    Box::new(stdout.take().expect("only one reporter writes to stdout"))
+                }
+            };
+            outputs.push((reporter.kind, output));
+        }
+        Ok(Self { outputs })
+    }
+
+    /// Returns the visitor that passes the result to all reporters
+    pub fn visitor(&mut self) -> MultiReporter<'_> {
+        MultiReporter(
+            self.outputs
+                .iter_mut()
+                .map(|(kind, output)| kind.visitor(output.as_mut()))
+                .collect(),
+        )
+    }
+
+    /// Flushes the outputs once the visitor is done
+    pub fn flush(&mut self) -> io::Result<()> {
+        for (_, output) in &mut self.outputs {
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Passes the result of a run to several reporters, which receive the same payloads and summary
+pub struct MultiReporter<'a>(Vec<Box<dyn ReporterVisitor + 'a>>);
+
+impl ReporterVisitor for MultiReporter<'_> {
+    fn report_start(&mut self) -> io::Result<()> {
+        self.0.iter_mut().try_for_each(|r| r.report_start())
+    }
+
+    fn report_diagnostics(&mut self, payload: &DiagnosticsPayload) -> io::Result<()> {
+        self.0
+            .iter_mut()
+            .try_for_each(|r| r.report_diagnostics(payload))
+    }
+
+    fn report_summary(&mut self, summary: &TraversalSummary) -> io::Result<()> {
+        self.0
+            .iter_mut()
+            .try_for_each(|r| r.report_summary(summary))
+    }
+}
+
+/// Returns the url files of the checked commit are linked to in a GitHub Actions workflow
+fn github_blob_url() -> Option<String> {
+    let var = |name| std::env::var(name).ok().filter(|v| !v.is_empty());
+    Some(format!(
+        "{}/{}/blob/{}",
+        var("GITHUB_SERVER_URL")?,
+        var("GITHUB_REPOSITORY")?,
+        var("GITHUB_SHA")?
+    ))
+}
+
+/// Writes the result of a run in some format
+pub trait ReporterVisitor {
+    fn report_start(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn report_diagnostics(&mut self, payload: &DiagnosticsPayload) -> io::Result<()>;
+
+    fn report_summary(&mut self, summary: &TraversalSummary) -> io::Result<()>;
+}
+
+/// Passes the results of a run to a visitor in the order of the files while counting them
+///
+/// Results can be added in any order, e.g. as they are produced by several threads. Each is
+/// reported as soon as the results of all files before it are.
+pub struct Reporter<'a> {
+    visitor: &'a mut dyn ReporterVisitor,
+    pending: BTreeMap<usize, DiagnosticsPayload>,
+    next: usize,
+    summary: TraversalSummary,
+    /// The number of diagnostics that may still be reported, if it is limited
+    remaining: Option<usize>,
+}
+
+impl<'a> Reporter<'a> {
+    pub fn new(visitor: &'a mut dyn ReporterVisitor) -> io::Result<Self> {
+        visitor.report_start()?;
+        Ok(Self {
+            visitor,
+            pending: BTreeMap::new(),
+            next: 0,
+            summary: TraversalSummary::default(),
+            remaining: None,
+        })
+    }
+
+    /// Limits the number of reported diagnostics, e.g. to keep the output of a run with many
+    /// violations readable
+    ///
+    /// The diagnostics over the limit are still counted in the summary.
+    pub fn with_max_diagnostics(mut self, max: Option<usize>) -> Self {
+        self.remaining = max;
+        self
+    }
+
+    /// Adds the result of the file at an index of the checked files
+    pub fn add(&mut self, idx: usize, payload: DiagnosticsPayload) -> io::Result<()> {
+        self.pending.insert(idx, payload);
+        while let Some(mut payload) = self.pending.remove(&self.next) {
+            self.summary.add(&payload);
+            if let Some(remaining) = &mut self.remaining {
+                let shown = payload.diagnostics.len().min(*remaining);
+                self.summary.not_shown += payload.diagnostics.len() - shown;
+                payload.diagnostics.truncate(shown);
+                *remaining -= shown;
+            }
+            self.visitor.report_diagnostics(&payload)?;
+            self.next += 1;
+        }
+        Ok(())
+    }
+
+    /// Reports the summary once the results of all files were added
+    pub fn finish(mut self, duration: Duration) -> io::Result<TraversalSummary> {
+        self.summary.duration = duration;
+        self.visitor.report_summary(&self.summary)?;
+        Ok(self.summary)
+    }
+}
+
+pub(super) fn plural<'a>(count: usize, singular: &'a str, plural: &'a str) -> &'a str {
+    if count == 1 {
+        singular
+    } else {
+        plural
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, time::Duration};
+
+    use super::{DiagnosticsPayload, ReportOutputs, Reporter, ReporterFlag, ReporterKind};
+
+    #[test]
+    fn test_reporter_flag() {
+        assert_eq!(
+            "junit=junit.xml".parse::<ReporterFlag>(),
+            Ok(ReporterFlag {
+                kind: ReporterKind::Junit,
+                file: Some(PathBuf::from("junit.xml"))
+            })
+        );
+        assert_eq!(
+            "tap".parse::<ReporterFlag>(),
+            Ok(ReporterFlag {
+                kind: ReporterKind::Tap,
+                file: None
+            })
+        );
+        assert!("junit=".parse::<ReporterFlag>().is_err());
+        assert!("xml=report.xml".parse::<ReporterFlag>().is_err());
+    }
+
+    #[test]
+    fn test_report_outputs() {
+        let file = std::env::temp_dir().join(format!("pglsp-report-{}.xml", std::process::id()));
+        let reporters = [
+            "tap".parse().unwrap(),
+            format!("junit={}", file.display()).parse().unwrap(),
+        ];
+
+        let mut stdout = Vec::new();
+        let mut reports = ReportOutputs::new(&reporters, None, &mut stdout).unwrap();
+        let mut visitor = reports.visitor();
+        let mut reporter = Reporter::new(&mut visitor).unwrap();
+        reporter
+            .add(
+                0,
+                DiagnosticsPayload {
+                    path: PathBuf::from("a.sql"),
+                    text: "select 1;".to_string(),
+                    diagnostics: Vec::new(),
+                    duration: Duration::ZERO,
+                },
+            )
+            .unwrap();
+        reporter.finish(Duration::ZERO).unwrap();
+        drop(visitor);
+        reports.flush().unwrap();
+        drop(reports);
+        let junit = fs::read_to_string(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+
+        assert!(String::from_utf8(stdout)
+            .unwrap()
+            .starts_with("TAP version 13\nok 1 - a.sql\n"));
+        assert!(junit.contains(r#"<testsuite name="a.sql""#));
+        assert!(ReportOutputs::new(
+            &["tap".parse().unwrap(), "junit".parse().unwrap()],
+            None,
+            Vec::new()
+        )
+        .is_err());
+    }
+}
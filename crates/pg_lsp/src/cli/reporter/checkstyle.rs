@@ -0,0 +1,132 @@
+use std::io;
+
+use pg_diagnostics::Severity;
+
+use super::{DiagnosticsPayload, ReporterVisitor, TraversalSummary};
+
+/// Writes the diagnostics in the checkstyle XML format, with one `file` element per checked
+/// file
+pub struct CheckstyleReporter<W> {
+    out: W,
+}
+
+impl<W: io::Write> CheckstyleReporter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl<W: io::Write> ReporterVisitor for CheckstyleReporter<W> {
+    fn report_start(&mut self) -> io::Result<()> {
+        writeln!(self.out, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+        writeln!(self.out, r#"<checkstyle version="4.3">"#)
+    }
+
+    fn report_diagnostics(&mut self, payload: &DiagnosticsPayload) -> io::Result<()> {
+        writeln!(
+            self.out,
+            r#"  <file name="{}">"#,
+            escape(&payload.path.to_string_lossy())
+        )?;
+        for diagnostic in &payload.diagnostics {
+            let (line, col) = payload.line_col(diagnostic);
+            let severity = match diagnostic.severity {
+                Severity::Hint | Severity::Information => "info",
+                Severity::Warning => "warning",
+                Severity::Error | Severity::Fatal => "error",
+            };
+            let source = match &diagnostic.code {
+                Some(code) => format!("pglsp.{}.{}", diagnostic.source, code),
+                None => format!("pglsp.{}", diagnostic.source),
+            };
+            writeln!(
+                self.out,
+                r#"    <error line="{}" column="{}" severity="{}" message="{}" source="{}" />"#,
+                line,
+                col,
+                severity,
+                escape(&diagnostic.message),
+                escape(&source)
+            )?;
+        }
+        writeln!(self.out, "  </file>")
+    }
+
+    fn report_summary(&mut self, _: &TraversalSummary) -> io::Result<()> {
+        writeln!(self.out, "</checkstyle>")
+    }
+}
+
+/// Escapes text for use in an attribute value
+pub(super) fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            '\n' => result.push_str("&#10;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use pg_diagnostics::{Diagnostic, Severity};
+    use text_size::{TextRange, TextSize};
+
+    use crate::cli::reporter::{DiagnosticsPayload, Reporter};
+
+    use super::CheckstyleReporter;
+
+    #[test]
+    fn test_checkstyle_reporter() {
+        let payloads = vec![
+            DiagnosticsPayload {
+                path: PathBuf::from("migrations/0001.sql"),
+                duration: Duration::ZERO,
+                text: "select 1;\nselect * from \"Users\" where a < 1;".to_string(),
+                diagnostics: vec![Diagnostic {
+                    message: "relation \"Users\" does not exist".to_string(),
+                    description: None,
+                    code: Some("42P01".to_string()),
+                    severity: Severity::Error,
+                    source: "typecheck".to_string(),
+                    range: TextRange::new(TextSize::new(24), TextSize::new(31)),
+                }],
+            },
+            DiagnosticsPayload {
+                path: PathBuf::from("migrations/0002.sql"),
+                duration: Duration::ZERO,
+                text: "select 1;".to_string(),
+                diagnostics: Vec::new(),
+            },
+        ];
+
+        let mut out = Vec::new();
+        let mut visitor = CheckstyleReporter::new(&mut out);
+        let mut reporter = Reporter::new(&mut visitor).unwrap();
+        for (idx, payload) in payloads.into_iter().enumerate() {
+            reporter.add(idx, payload).unwrap();
+        }
+        reporter.finish(Duration::ZERO).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<checkstyle version="4.3">
+  <file name="migrations/0001.sql">
+    <error line="2" column="15" severity="error" message="relation &quot;Users&quot; does not exist" source="pglsp.typecheck.42P01" />
+  </file>
+  <file name="migrations/0002.sql">
+  </file>
+</checkstyle>
+"#
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use std::{cmp::Reverse, collections::BTreeMap, io};
+
+use pg_diagnostics::Severity;
+
+use super::{plural, DiagnosticsPayload, ReporterVisitor, SeverityCounts, TraversalSummary};
+
+/// Writes one line per diagnostic, e.g. `migrations/0001.sql:3:1: warning[ban-drop-column]:
+/// Dropping a column may break existing clients.`, followed by a summary
+pub struct ConsoleReporter<W> {
+    out: W,
+}
+
+impl<W: io::Write> ConsoleReporter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl<W: io::Write> ReporterVisitor for ConsoleReporter<W> {
+    fn report_diagnostics(&mut self, payload: &DiagnosticsPayload) -> io::Result<()> {
+        for diagnostic in &payload.diagnostics {
+            let (line, col) = payload.line_col(diagnostic);
+            let severity = match diagnostic.severity {
+                Severity::Hint => "hint",
+                Severity::Information => "info",
+                Severity::Warning => "warning",
+                Severity::Error | Severity::Fatal => "error",
+            };
+            let code = diagnostic
+                .code
+                .as_ref()
+                .map(|code| format!("[{}]", code))
+                .unwrap_or_default();
+            writeln!(
+                self.out,
+                "{}:{}:{}: {}{}: {}",
+                payload.path.display(),
+                line,
+                col,
+                severity,
+                code,
+                diagnostic.message
+            )?;
+        }
+        Ok(())
+    }
+
+    fn report_summary(&mut self, summary: &TraversalSummary) -> io::Result<()> {
+        if summary.not_shown > 0 {
+            writeln!(
+                self.out,
+                "{} more {} not shown.",
+                summary.not_shown,
+                plural(summary.not_shown, "diagnostic", "diagnostics")
+            )?;
+        }
+        writeln!(
+            self.out,
+            "Checked {} {} in {}ms. Found {} {} and {} {}.",
+            summary.files,
+            plural(summary.files, "file", "files"),
+            summary.duration.as_millis(),
+            summary.errors,
+            plural(summary.errors, "error", "errors"),
+            summary.warnings,
+            plural(summary.warnings, "warning", "warnings"),
+        )?;
+
+        if summary.errors + summary.warnings > 0 {
+            self.write_counts("Rules", &summary.rules)?;
+            self.write_counts("Categories", &summary.categories)?;
+        }
+        if summary.files > 1 {
+            writeln!(self.out)?;
+            writeln!(self.out, "Slowest files:")?;
+            let paths = summary
+                .slowest_files
+                .iter()
+                .map(|(path, duration)| (path.display().to_string(), duration))
+                .collect::<Vec<_>>();
+            let width = paths.iter().map(|(path, _)| path.chars().count()).max();
+            for (path, duration) in &paths {
+                writeln!(
+                    self.out,
+                    "  {:width$}  {}ms",
+                    path,
+                    duration.as_millis(),
+                    width = width.unwrap_or(0)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: io::Write> ConsoleReporter<W> {
+    /// Writes the counts of every rule or category, the most frequent first
+    fn write_counts(
+        &mut self,
+        title: &str,
+        counts: &BTreeMap<String, SeverityCounts>,
+    ) -> io::Result<()> {
+        let mut counts = counts.iter().collect::<Vec<_>>();
+        counts.sort_by_key(|(_, counts)| Reverse(counts.total()));
+
+        writeln!(self.out)?;
+        writeln!(self.out, "{}:", title)?;
+        let width = counts.iter().map(|(name, _)| name.chars().count()).max();
+        for (name, counts) in counts {
+            writeln!(
+                self.out,
+                "  {:width$}  {} {}, {} {}",
+                name,
+                counts.errors,
+                plural(counts.errors, "error", "errors"),
+                counts.warnings,
+                plural(counts.warnings, "warning", "warnings"),
+                width = width.unwrap_or(0)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use pg_diagnostics::{Diagnostic, Severity};
+    use text_size::{TextRange, TextSize};
+
+    use crate::cli::reporter::{DiagnosticsPayload, Reporter};
+
+    use super::ConsoleReporter;
+
+    #[test]
+    fn test_console_reporter() {
+        let payloads = vec![
+            DiagnosticsPayload {
+                path: PathBuf::from("migrations/0001.sql"),
+                duration: Duration::from_millis(3),
+                text: "select 1;\nalter table t drop column c;".to_string(),
+                diagnostics: vec![Diagnostic {
+                    message: "Dropping a column may break existing clients.".to_string(),
+                    description: None,
+                    code: Some("ban-drop-column".to_string()),
+                    severity: Severity::Warning,
+                    source: "lint".to_string(),
+                    range: TextRange::new(TextSize::new(14), TextSize::new(38)),
+                }],
+            },
+            DiagnosticsPayload {
+                path: PathBuf::from("queries/users.sql"),
+                duration: Duration::from_millis(8),
+                text: "select * from u;".to_string(),
+                diagnostics: vec![Diagnostic {
+                    message: "relation \"u\" does not exist".to_string(),
+                    description: None,
+                    code: Some("42P01".to_string()),
+                    severity: Severity::Error,
+                    source: "typecheck".to_string(),
+                    range: TextRange::new(TextSize::new(14), TextSize::new(15)),
+                }],
+            },
+        ];
+
+        let mut out = Vec::new();
+        let mut visitor = ConsoleReporter::new(&mut out);
+        let mut reporter = Reporter::new(&mut visitor).unwrap();
+        for (idx, payload) in payloads.into_iter().enumerate() {
+            reporter.add(idx, payload).unwrap();
+        }
+        reporter.finish(Duration::from_millis(12)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "migrations/0001.sql:2:5: warning[ban-drop-column]: Dropping a column may break existing clients.
+queries/users.sql:1:15: error[42P01]: relation \"u\" does not exist
+Checked 2 files in 12ms. Found 1 error and 1 warning.
+
+Rules:
+  42P01            1 error, 0 warnings
+  ban-drop-column  0 errors, 1 warning
+
+Categories:
+  lint       0 errors, 1 warning
+  typecheck  1 error, 0 warnings
+
+Slowest files:
+  queries/users.sql    8ms
+  migrations/0001.sql  3ms
+"
+        );
+    }
+
+    #[test]
+    fn test_max_diagnostics() {
+        let diagnostic = |start: u32| Diagnostic {
+            message: "Dropping a column may break existing clients.".to_string(),
+            description: None,
+            code: Some("ban-drop-column".to_string()),
+            severity: Severity::Warning,
+            source: "lint".to_string(),
+            range: TextRange::empty(TextSize::new(start)),
+        };
+        let payload = |path: &str| DiagnosticsPayload {
+            path: PathBuf::from(path),
+            duration: Duration::ZERO,
+            text: "alter table t drop column a;\nalter table t drop column b;".to_string(),
+            diagnostics: vec![diagnostic(0), diagnostic(29)],
+        };
+
+        let mut out = Vec::new();
+        let mut visitor = ConsoleReporter::new(&mut out);
+        let mut reporter = Reporter::new(&mut visitor)
+            .unwrap()
+            .with_max_diagnostics(Some(3));
+        reporter.add(0, payload("migrations/0001.sql")).unwrap();
+        reporter.add(1, payload("migrations/0002.sql")).unwrap();
+        reporter.add(2, payload("migrations/0003.sql")).unwrap();
+        let summary = reporter.finish(Duration::ZERO).unwrap();
+        assert_eq!(summary.warnings, 6);
+        assert_eq!(summary.not_shown, 3);
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with(
+            "migrations/0001.sql:1:1: warning[ban-drop-column]: Dropping a column may break existing clients.
+migrations/0001.sql:2:1: warning[ban-drop-column]: Dropping a column may break existing clients.
+migrations/0002.sql:1:1: warning[ban-drop-column]: Dropping a column may break existing clients.
+3 more diagnostics not shown.
+Checked 3 files in 0ms. Found 0 errors and 6 warnings.
+"
+        ));
+    }
+}
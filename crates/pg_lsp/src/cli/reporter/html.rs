@@ -0,0 +1,330 @@
+use std::io;
+
+use pg_diagnostics::Severity;
+use pg_lexer::{SyntaxKind, TokenType};
+
+use super::{plural, DiagnosticsPayload, ReporterVisitor, TraversalSummary};
+
+/// The number of lines shown before and after the lines of a diagnostic
+const CONTEXT_LINES: u32 = 2;
+
+/// The maximum number of lines of a diagnostic that are shown, e.g. of a long statement
+const MAX_DIAGNOSTIC_LINES: u32 = 10;
+
+const STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 0 auto; max-width: 70rem; padding: 1rem; color: #1f2328; }
+header { border-bottom: 1px solid #d0d7de; margin-bottom: 1rem; }
+#facets { display: flex; flex-wrap: wrap; gap: 0.5rem 1.5rem; margin-bottom: 1rem; }
+#facets fieldset { border: 1px solid #d0d7de; border-radius: 6px; }
+#facets label { margin-right: 0.75rem; white-space: nowrap; }
+section.file { margin-bottom: 2rem; }
+section.file h2 { font-family: ui-monospace, monospace; font-size: 1rem; }
+.diagnostic { margin-bottom: 1rem; }
+.diagnostic p { margin: 0.25rem 0; }
+.severity { border-radius: 4px; color: #fff; font-size: 0.8rem; padding: 0 0.4rem; }
+.severity.error { background: #cf222e; }
+.severity.warning { background: #9a6700; }
+.severity.info, .severity.hint { background: #0969da; }
+pre { background: #f6f8fa; border-radius: 6px; margin: 0; overflow-x: auto; padding: 0.5rem 0; }
+.line { display: block; padding: 0 0.5rem; }
+.line.marked { background: #fff8c5; }
+.line-number { color: #6e7781; display: inline-block; margin-right: 1rem; text-align: right; user-select: none; width: 3rem; }
+.keyword { color: #cf222e; }
+.string { color: #0a3069; }
+.number { color: #0550ae; }
+.comment { color: #6e7781; font-style: italic; }
+"#;
+
+/// Hides the diagnostics whose severity or rule is unchecked, and files without visible
+/// diagnostics
+const SCRIPT: &str = r#"
+const diagnostics = Array.from(document.querySelectorAll(".diagnostic"));
+const facets = document.getElementById("facets");
+function facet(title, attribute) {
+  const counts = new Map();
+  for (const d of diagnostics) counts.set(d.dataset[attribute], (counts.get(d.dataset[attribute]) || 0) + 1);
+  const fieldset = document.createElement("fieldset");
+  fieldset.innerHTML = "<legend>" + title + "</legend>";
+  for (const [value, count] of [...counts].sort()) {
+    const label = document.createElement("label");
+    const input = document.createElement("input");
+    input.type = "checkbox";
+    input.checked = true;
+    input.dataset.attribute = attribute;
+    input.value = value;
+    input.addEventListener("change", update);
+    label.append(input, " " + value + " (" + count + ")");
+    fieldset.append(label);
+  }
+  facets.append(fieldset);
+}
+function update() {
+  const hidden = Array.from(facets.querySelectorAll("input:not(:checked)"));
+  for (const d of diagnostics) d.hidden = hidden.some(i => d.dataset[i.dataset.attribute] === i.value);
+  for (const f of document.querySelectorAll("section.file")) f.hidden = !f.querySelector(".diagnostic:not([hidden])");
+}
+facet("Severity", "severity");
+facet("Rule", "rule");
+"#;
+
+/// Writes a standalone HTML page with a section per file that has diagnostics, which shows
+/// every diagnostic with a highlighted excerpt of the code around it
+///
+/// The page can be filtered by severity and rule.
+pub struct HtmlReporter<W> {
+    out: W,
+}
+
+impl<W: io::Write> HtmlReporter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl<W: io::Write> ReporterVisitor for HtmlReporter<W> {
+    fn report_start(&mut self) -> io::Result<()> {
+        writeln!(self.out, "<!DOCTYPE html>")?;
+        writeln!(self.out, r#"<html lang="en">"#)?;
+        writeln!(self.out, r#"<head><meta charset="utf-8">"#)?;
+        writeln!(self.out, "<title>pglsp check</title>")?;
+        writeln!(self.out, "<style>{}</style>", STYLE)?;
+        writeln!(self.out, "</head>")?;
+        writeln!(self.out, "<body>")?;
+        writeln!(self.out, "<header><h1>pglsp check</h1></header>")?;
+        writeln!(self.out, r#"<div id="facets"></div>"#)?;
+        writeln!(self.out, "<main>")
+    }
+
+    fn report_diagnostics(&mut self, payload: &DiagnosticsPayload) -> io::Result<()> {
+        if payload.diagnostics.is_empty() {
+            return Ok(());
+        }
+
+        let lines = highlight(&payload.text);
+        writeln!(self.out, r#"<section class="file">"#)?;
+        writeln!(
+            self.out,
+            "<h2>{}</h2>",
+            escape(&payload.path.to_string_lossy())
+        )?;
+        for diagnostic in &payload.diagnostics {
+            let severity = match diagnostic.severity {
+                Severity::Hint => "hint",
+                Severity::Information => "info",
+                Severity::Warning => "warning",
+                Severity::Error | Severity::Fatal => "error",
+            };
+            let rule = diagnostic.code.as_ref().unwrap_or(&diagnostic.source);
+            let (start_line, start_col) = payload.position(diagnostic.range.start());
+            let (end_line, _) = payload.position(diagnostic.range.end());
+            let end_line = end_line.min(start_line + MAX_DIAGNOSTIC_LINES - 1);
+
+            writeln!(
+                self.out,
+                r#"<div class="diagnostic" data-severity="{}" data-rule="{}">"#,
+                severity,
+                escape(rule)
+            )?;
+            writeln!(
+                self.out,
+                r#"<p><span class="severity {0}">{0}</span> <code>{1}</code> {2}:{3} {4}</p>"#,
+                severity,
+                escape(rule),
+                start_line,
+                start_col,
+                escape(&diagnostic.message)
+            )?;
+            write!(self.out, "<pre><code>")?;
+            let first = start_line.saturating_sub(CONTEXT_LINES).max(1);
+            let last = (end_line + CONTEXT_LINES).min(u32::try_from(lines.len()).unwrap());
+            for line in first..=last {
+                let class = if (start_line..=end_line).contains(&line) {
+                    "line marked"
+                } else {
+                    "line"
+                };
+                write!(
+                    self.out,
+                    r#"<span class="{}"><span class="line-number">{}</span>{}</span>"#,
+                    class,
+                    line,
+                    lines[usize::try_from(line - 1).unwrap()]
+                )?;
+            }
+            writeln!(self.out, "</code></pre>")?;
+            writeln!(self.out, "</div>")?;
+        }
+        writeln!(self.out, "</section>")
+    }
+
+    fn report_summary(&mut self, summary: &TraversalSummary) -> io::Result<()> {
+        writeln!(self.out, "</main>")?;
+        writeln!(
+            self.out,
+            "<footer><p>Checked {} {} in {}ms. Found {} {} and {} {}.</p></footer>",
+            summary.files,
+            plural(summary.files, "file", "files"),
+            summary.duration.as_millis(),
+            summary.errors,
+            plural(summary.errors, "error", "errors"),
+            summary.warnings,
+            plural(summary.warnings, "warning", "warnings"),
+        )?;
+        writeln!(self.out, "<script>{}</script>", SCRIPT)?;
+        writeln!(self.out, "</body>")?;
+        writeln!(self.out, "</html>")
+    }
+}
+
+/// Returns the lines of a file as HTML with the tokens wrapped in spans with their class, or
+/// without highlighting if the file cannot be lexed
+fn highlight(text: &str) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut end = 0;
+    for token in pg_lexer::try_lex(text).unwrap_or_default() {
+        let range = std::ops::Range::<usize>::from(token.span);
+        if range.start != end {
+            break;
+        }
+        let Some(token_text) = text.get(range.clone()) else {
+            break;
+        };
+        pieces.push((token_class(&token.kind, &token.token_type), token_text));
+        end = range.end;
+    }
+    if end != text.len() {
+        pieces = vec![(None, text)];
+    }
+
+    let mut lines = vec![String::new()];
+    for (class, piece) in pieces {
+        for (idx, part) in piece.split('\n').enumerate() {
+            if idx > 0 {
+                lines.push(String::new());
+            }
+            let line = lines.last_mut().unwrap();
+            match class {
+                _ if part.is_empty() => {}
+                Some(class) => line.push_str(&format!(
+                    r#"<span class="{}">{}</span>"#,
+                    class,
+                    escape(part)
+                )),
+                None => line.push_str(&escape(part)),
+            }
+        }
+    }
+    lines
+}
+
+fn token_class(kind: &SyntaxKind, token_type: &TokenType) -> Option<&'static str> {
+    match (kind, token_type) {
+        (SyntaxKind::SqlComment | SyntaxKind::CComment, _) => Some("comment"),
+        (SyntaxKind::Sconst | SyntaxKind::Usconst | SyntaxKind::Bconst | SyntaxKind::Xconst, _) => {
+            Some("string")
+        }
+        (SyntaxKind::Iconst | SyntaxKind::Fconst, _) => Some("number"),
+        (
+            _,
+            TokenType::UnreservedKeyword
+            | TokenType::ColNameKeyword
+            | TokenType::TypeFuncNameKeyword
+            | TokenType::ReservedKeyword,
+        ) => Some("keyword"),
+        _ => None,
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use pg_diagnostics::{Diagnostic, Severity};
+    use text_size::{TextRange, TextSize};
+
+    use crate::cli::reporter::{DiagnosticsPayload, Reporter};
+
+    use super::{highlight, HtmlReporter};
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            highlight("select 'a' -- b\nfrom t where x < 1"),
+            vec![
+                r#"<span class="keyword">select</span> <span class="string">'a'</span> <span class="comment">-- b</span>"#,
+                r#"<span class="keyword">from</span> t <span class="keyword">where</span> x &lt; <span class="number">1</span>"#,
+            ]
+        );
+        assert_eq!(
+            highlight("select '<\nunterminated"),
+            vec!["select '&lt;", "unterminated"]
+        );
+    }
+
+    #[test]
+    fn test_html_reporter() {
+        let text = "select 1;\nselect 2;\nselect 3;\nselect * from \"Users\";\nselect 5;\nselect 6;\nselect 7;";
+        let payloads = vec![
+            DiagnosticsPayload {
+                path: PathBuf::from("migrations/0001.sql"),
+                duration: Duration::ZERO,
+                text: text.to_string(),
+                diagnostics: vec![Diagnostic {
+                    message: "relation \"Users\" does not exist".to_string(),
+                    description: None,
+                    code: Some("42P01".to_string()),
+                    severity: Severity::Error,
+                    source: "typecheck".to_string(),
+                    range: TextRange::new(TextSize::new(44), TextSize::new(51)),
+                }],
+            },
+            DiagnosticsPayload {
+                path: PathBuf::from("migrations/0002.sql"),
+                duration: Duration::ZERO,
+                text: "select 1;".to_string(),
+                diagnostics: Vec::new(),
+            },
+        ];
+
+        let mut out = Vec::new();
+        let mut visitor = HtmlReporter::new(&mut out);
+        let mut reporter = Reporter::new(&mut visitor).unwrap();
+        for (idx, payload) in payloads.into_iter().enumerate() {
+            reporter.add(idx, payload).unwrap();
+        }
+        reporter.finish(Duration::from_millis(3)).unwrap();
+        let html = String::from_utf8(out).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h2>migrations/0001.sql</h2>"));
+        assert!(!html.contains("migrations/0002.sql"));
+        assert!(
+            html.contains(r#"<div class="diagnostic" data-severity="error" data-rule="42P01">"#)
+        );
+        assert!(html.contains(
+            r#"<span class="severity error">error</span> <code>42P01</code> 4:15 relation &quot;Users&quot; does not exist"#
+        ));
+        // the line of the diagnostic with two lines of context before and after it
+        let excerpt = html
+            .split("<pre><code>")
+            .nth(1)
+            .unwrap()
+            .split("</code></pre>")
+            .next()
+            .unwrap();
+        let numbers = excerpt
+            .match_indices(r#"<span class="line-number">"#)
+            .map(|(idx, m)| &excerpt[idx + m.len()..idx + m.len() + 1])
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, vec!["2", "3", "4", "5", "6"]);
+        assert!(excerpt.contains(r#"<span class="line marked"><span class="line-number">4</span>"#));
+        assert!(html.contains("Checked 2 files in 3ms. Found 1 error and 0 warnings."));
+    }
+}
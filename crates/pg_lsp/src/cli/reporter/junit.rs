@@ -0,0 +1,147 @@
+use std::io;
+
+use pg_diagnostics::Severity;
+
+use super::{checkstyle::escape, DiagnosticsPayload, ReporterVisitor, TraversalSummary};
+
+/// Writes the result in the JUnit XML format, with one test suite per file
+///
+/// Every error of a file is a failed test case. A file without errors has a single test case
+/// that passes, so that the checked files show up in test reports. Warnings do not fail a test
+/// case and are not written.
+pub struct JunitReporter<W> {
+    out: W,
+}
+
+impl<W: io::Write> JunitReporter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl<W: io::Write> ReporterVisitor for JunitReporter<W> {
+    fn report_start(&mut self) -> io::Result<()> {
+        writeln!(self.out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(self.out, r#"<testsuites name="pglsp">"#)
+    }
+
+    fn report_diagnostics(&mut self, payload: &DiagnosticsPayload) -> io::Result<()> {
+        let path = escape(&payload.path.to_string_lossy());
+        let errors = payload
+            .diagnostics
+            .iter()
+            .filter(|d| matches!(d.severity, Severity::Error | Severity::Fatal))
+            .collect::<Vec<_>>();
+        writeln!(
+            self.out,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" time="{:.3}">"#,
+            path,
+            errors.len().max(1),
+            errors.len(),
+            payload.duration.as_secs_f64()
+        )?;
+        if errors.is_empty() {
+            writeln!(
+                self.out,
+                r#"    <testcase name="{0}" classname="{0}" />"#,
+                path
+            )?;
+        }
+        for diagnostic in errors {
+            let (line, column) = payload.line_col(diagnostic);
+            let kind = match &diagnostic.code {
+                Some(code) => format!("{}.{}", diagnostic.source, code),
+                None => diagnostic.source.clone(),
+            };
+            let summary = diagnostic.message.lines().next().unwrap_or_default();
+            writeln!(
+                self.out,
+                r#"    <testcase name="{}:{} {}" classname="{}">"#,
+                line,
+                column,
+                escape(summary),
+                path
+            )?;
+            writeln!(
+                self.out,
+                r#"      <failure message="{}" type="{}">{}:{}:{}: {}</failure>"#,
+                escape(summary),
+                escape(&kind),
+                path,
+                line,
+                column,
+                escape(&diagnostic.message)
+            )?;
+            writeln!(self.out, "    </testcase>")?;
+        }
+        writeln!(self.out, "  </testsuite>")
+    }
+
+    fn report_summary(&mut self, _: &TraversalSummary) -> io::Result<()> {
+        writeln!(self.out, "</testsuites>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use pg_diagnostics::{Diagnostic, Severity};
+    use text_size::{TextRange, TextSize};
+
+    use crate::cli::reporter::{DiagnosticsPayload, Reporter};
+
+    use super::JunitReporter;
+
+    #[test]
+    fn test_junit_reporter() {
+        let diagnostic = |message: &str, severity: Severity, start: u32| Diagnostic {
+            message: message.to_string(),
+            description: None,
+            code: None,
+            severity,
+            source: "test".to_string(),
+            range: TextRange::new(TextSize::new(start), TextSize::new(start + 1)),
+        };
+        let payloads = vec![
+            DiagnosticsPayload {
+                path: PathBuf::from("tests/users_test.sql"),
+                duration: Duration::from_millis(12),
+                text: "select plan(2);\nselect ok(false, 'a < b');".to_string(),
+                diagnostics: vec![
+                    diagnostic("not ok 1 - a < b\nFailed test 1", Severity::Error, 16),
+                    diagnostic("a warning", Severity::Warning, 0),
+                ],
+            },
+            DiagnosticsPayload {
+                path: PathBuf::from("tests/posts_test.sql"),
+                duration: Duration::ZERO,
+                text: "select 1;".to_string(),
+                diagnostics: Vec::new(),
+            },
+        ];
+
+        let mut out = Vec::new();
+        let mut visitor = JunitReporter::new(&mut out);
+        let mut reporter = Reporter::new(&mut visitor).unwrap();
+        for (idx, payload) in payloads.into_iter().enumerate() {
+            reporter.add(idx, payload).unwrap();
+        }
+        reporter.finish(Duration::ZERO).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="pglsp">
+  <testsuite name="tests/users_test.sql" tests="1" failures="1" time="0.012">
+    <testcase name="2:1 not ok 1 - a &lt; b" classname="tests/users_test.sql">
+      <failure message="not ok 1 - a &lt; b" type="test">tests/users_test.sql:2:1: not ok 1 - a &lt; b&#10;Failed test 1</failure>
+    </testcase>
+  </testsuite>
+  <testsuite name="tests/posts_test.sql" tests="1" failures="0" time="0.000">
+    <testcase name="tests/posts_test.sql" classname="tests/posts_test.sql" />
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+}
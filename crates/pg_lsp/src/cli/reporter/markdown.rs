@@ -0,0 +1,254 @@
+use std::{collections::BTreeMap, io};
+
+use pg_diagnostics::Severity;
+
+use super::{plural, rule, DiagnosticsPayload, ReporterVisitor, TraversalSummary};
+
+/// Where typecheck errors, whose codes are SQLSTATEs, are documented
+const SQLSTATE_DOCS: &str = "https://www.postgresql.org/docs/current/errcodes-appendix.html";
+
+/// The errors and warnings of a rule, i.e. of diagnostics with the same code
+#[derive(Debug, Default)]
+struct RuleCount {
+    errors: usize,
+    warnings: usize,
+    files: usize,
+}
+
+/// The diagnostics of a rule within a file
+#[derive(Debug)]
+struct FileRow {
+    path: String,
+    rule: String,
+    errors: usize,
+    warnings: usize,
+    lines: Vec<u32>,
+}
+
+/// Writes a Markdown summary with tables of the errors and warnings per rule and per file and
+/// rule, e.g. for `$GITHUB_STEP_SUMMARY` or a pull request comment
+///
+/// Files are linked relative to the working directory, or to a base url such as the blob url of
+/// the checked commit.
+pub struct MarkdownReporter<W> {
+    out: W,
+    link_base: Option<String>,
+    rules: BTreeMap<String, RuleCount>,
+    files: Vec<FileRow>,
+}
+
+impl<W: io::Write> MarkdownReporter<W> {
+    pub fn new(out: W, link_base: Option<String>) -> Self {
+        Self {
+            out,
+            link_base,
+            rules: BTreeMap::new(),
+            files: Vec::new(),
+        }
+    }
+
+    fn link(&self, path: &str, line: Option<u32>) -> String {
+        let mut link = match &self.link_base {
+            Some(base) => format!("{}/{}", base.trim_end_matches('/'), path),
+            None => path.to_string(),
+        };
+        if let Some(line) = line {
+            link.push_str(&format!("#L{}", line));
+        }
+        link.replace(' ', "%20")
+    }
+}
+
+impl<W: io::Write> ReporterVisitor for MarkdownReporter<W> {
+    fn report_diagnostics(&mut self, payload: &DiagnosticsPayload) -> io::Result<()> {
+        let path = payload.path.to_string_lossy().replace('\\', "/");
+        let mut rows: BTreeMap<String, FileRow> = BTreeMap::new();
+        for diagnostic in &payload.diagnostics {
+            let is_error = match diagnostic.severity {
+                Severity::Error | Severity::Fatal => true,
+                Severity::Warning => false,
+                Severity::Hint | Severity::Information => continue,
+            };
+            let rule = rule(diagnostic);
+            let row = rows.entry(rule.clone()).or_insert_with(|| FileRow {
+                path: path.clone(),
+                rule,
+                errors: 0,
+                warnings: 0,
+                lines: Vec::new(),
+            });
+            if is_error {
+                row.errors += 1;
+            } else {
+                row.warnings += 1;
+            }
+            let (line, _) = payload.line_col(diagnostic);
+            if !row.lines.contains(&line) {
+                row.lines.push(line);
+            }
+        }
+
+        for row in rows.into_values() {
+            let count = self.rules.entry(row.rule.clone()).or_default();
+            count.errors += row.errors;
+            count.warnings += row.warnings;
+            count.files += 1;
+            self.files.push(row);
+        }
+        Ok(())
+    }
+
+    fn report_summary(&mut self, summary: &TraversalSummary) -> io::Result<()> {
+        writeln!(self.out, "## pglsp check")?;
+        writeln!(self.out)?;
+        writeln!(
+            self.out,
+            "Checked {} {} in {}ms. Found {} {} and {} {}.",
+            summary.files,
+            plural(summary.files, "file", "files"),
+            summary.duration.as_millis(),
+            summary.errors,
+            plural(summary.errors, "error", "errors"),
+            summary.warnings,
+            plural(summary.warnings, "warning", "warnings"),
+        )?;
+        if self.files.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(self.out)?;
+        writeln!(self.out, "### Rules")?;
+        writeln!(self.out)?;
+        writeln!(self.out, "| Rule | Errors | Warnings | Files |")?;
+        writeln!(self.out, "| --- | ---: | ---: | ---: |")?;
+        for (rule, count) in &self.rules {
+            writeln!(
+                self.out,
+                "| {} | {} | {} | {} |",
+                rule_cell(rule),
+                count.errors,
+                count.warnings,
+                count.files
+            )?;
+        }
+
+        writeln!(self.out)?;
+        writeln!(self.out, "### Files")?;
+        writeln!(self.out)?;
+        writeln!(self.out, "| File | Rule | Errors | Warnings | Lines |")?;
+        writeln!(self.out, "| --- | --- | ---: | ---: | --- |")?;
+        for row in &self.files {
+            let lines = row
+                .lines
+                .iter()
+                .map(|line| format!("[{}]({})", line, self.link(&row.path, Some(*line))))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(
+                self.out,
+                "| [{}]({}) | {} | {} | {} | {} |",
+                row.path.replace('|', "\\|"),
+                self.link(&row.path, None),
+                rule_cell(&row.rule),
+                row.errors,
+                row.warnings,
+                lines
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats a rule as code, linked to the documentation of SQLSTATEs for typecheck errors
+fn rule_cell(rule: &str) -> String {
+    let is_sqlstate = rule.len() == 5
+        && rule
+            .chars()
+            .all(|c| c.is_ascii_digit() || c.is_ascii_uppercase());
+    if is_sqlstate {
+        format!("[`{}`]({})", rule, SQLSTATE_DOCS)
+    } else {
+        format!("`{}`", rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use pg_diagnostics::{Diagnostic, Severity};
+    use text_size::{TextRange, TextSize};
+
+    use crate::cli::reporter::{DiagnosticsPayload, Reporter};
+
+    use super::MarkdownReporter;
+
+    #[test]
+    fn test_markdown_reporter() {
+        let diagnostic = |code: &str, source: &str, severity: Severity, start: u32| Diagnostic {
+            message: String::new(),
+            description: None,
+            code: Some(code.to_string()),
+            severity,
+            source: source.to_string(),
+            range: TextRange::empty(TextSize::new(start)),
+        };
+        let text = "alter table t drop column a;\nalter table t drop column b;\nselect * from u;";
+        let payloads = vec![
+            DiagnosticsPayload {
+                path: PathBuf::from("migrations/0001.sql"),
+                duration: Duration::ZERO,
+                text: text.to_string(),
+                diagnostics: vec![
+                    diagnostic("ban-drop-column", "lint", Severity::Warning, 0),
+                    diagnostic("ban-drop-column", "lint", Severity::Warning, 29),
+                    diagnostic("42P01", "typecheck", Severity::Error, 58),
+                ],
+            },
+            DiagnosticsPayload {
+                path: PathBuf::from("migrations/0002.sql"),
+                duration: Duration::ZERO,
+                text: text.to_string(),
+                diagnostics: vec![diagnostic("ban-drop-column", "lint", Severity::Warning, 0)],
+            },
+        ];
+
+        let mut out = Vec::new();
+        let mut visitor = MarkdownReporter::new(
+            &mut out,
+            Some("https://github.com/org/repo/blob/abc".to_string()),
+        );
+        let mut reporter = Reporter::new(&mut visitor).unwrap();
+        for (idx, payload) in payloads.into_iter().enumerate() {
+            reporter.add(idx, payload).unwrap();
+        }
+        reporter.finish(Duration::from_millis(5)).unwrap();
+
+        let link = "https://github.com/org/repo/blob/abc/migrations";
+        let docs = "https://www.postgresql.org/docs/current/errcodes-appendix.html";
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "## pglsp check
+
+Checked 2 files in 5ms. Found 1 error and 3 warnings.
+
+### Rules
+
+| Rule | Errors | Warnings | Files |
+| --- | ---: | ---: | ---: |
+| [`42P01`]({docs}) | 1 | 0 | 1 |
+| `ban-drop-column` | 0 | 3 | 2 |
+
+### Files
+
+| File | Rule | Errors | Warnings | Lines |
+| --- | --- | ---: | ---: | --- |
+| [migrations/0001.sql]({link}/0001.sql) | [`42P01`]({docs}) | 1 | 0 | [3]({link}/0001.sql#L3) |
+| [migrations/0001.sql]({link}/0001.sql) | `ban-drop-column` | 0 | 2 | [1]({link}/0001.sql#L1), [2]({link}/0001.sql#L2) |
+| [migrations/0002.sql]({link}/0002.sql) | `ban-drop-column` | 0 | 1 | [1]({link}/0002.sql#L1) |
+"
+            )
+        );
+    }
+}
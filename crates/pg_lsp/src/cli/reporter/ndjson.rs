@@ -0,0 +1,102 @@
+use std::io;
+
+use pg_diagnostics::Severity;
+use serde_json::json;
+
+use super::{DiagnosticsPayload, ReporterVisitor, TraversalSummary};
+
+/// Writes one JSON object per line for every diagnostic as soon as its file was checked, so that
+/// tools can process the diagnostics while the run continues
+///
+/// Positions are 1-based and the end of a range is exclusive. Diagnostics carry no fixes, so
+/// there is no `fix` field.
+pub struct NdjsonReporter<W> {
+    out: W,
+}
+
+impl<W: io::Write> NdjsonReporter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl<W: io::Write> ReporterVisitor for NdjsonReporter<W> {
+    fn report_diagnostics(&mut self, payload: &DiagnosticsPayload) -> io::Result<()> {
+        for diagnostic in &payload.diagnostics {
+            let (start_line, start_column) = payload.position(diagnostic.range.start());
+            let (end_line, end_column) = payload.position(diagnostic.range.end());
+            let severity = match diagnostic.severity {
+                Severity::Hint => "hint",
+                Severity::Information => "info",
+                Severity::Warning => "warning",
+                Severity::Error | Severity::Fatal => "error",
+            };
+            let line = json!({
+                "path": payload.path,
+                "range": {
+                    "start": { "line": start_line, "column": start_column },
+                    "end": { "line": end_line, "column": end_column },
+                },
+                "severity": severity,
+                "source": diagnostic.source,
+                "code": diagnostic.code,
+                "message": diagnostic.message,
+            });
+            writeln!(self.out, "{}", line)?;
+        }
+        self.out.flush()
+    }
+
+    fn report_summary(&mut self, _: &TraversalSummary) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use pg_diagnostics::{Diagnostic, Severity};
+    use text_size::{TextRange, TextSize};
+
+    use crate::cli::reporter::{DiagnosticsPayload, Reporter};
+
+    use super::NdjsonReporter;
+
+    #[test]
+    fn test_ndjson_reporter() {
+        let payload = |path: &str| DiagnosticsPayload {
+            path: PathBuf::from(path),
+            duration: Duration::ZERO,
+            text: "select 1;\nalter table t\n  drop column c;".to_string(),
+            diagnostics: vec![Diagnostic {
+                message: "Dropping a column may break existing clients.".to_string(),
+                description: None,
+                code: Some("ban-drop-column".to_string()),
+                severity: Severity::Warning,
+                source: "lint".to_string(),
+                range: TextRange::new(TextSize::new(10), TextSize::new(40)),
+            }],
+        };
+
+        let mut out = Vec::new();
+        let mut visitor = NdjsonReporter::new(&mut out);
+        let mut reporter = Reporter::new(&mut visitor).unwrap();
+        reporter.add(1, payload("migrations/0002.sql")).unwrap();
+        reporter.add(0, payload("migrations/0001.sql")).unwrap();
+        let summary = reporter.finish(Duration::ZERO).unwrap();
+
+        let line = r#""range":{"end":{"column":17,"line":3},"start":{"column":1,"line":2}},"severity":"warning","source":"lint"}"#;
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "{}{}\n{}{}\n",
+                r#"{"code":"ban-drop-column","message":"Dropping a column may break existing clients.","path":"migrations/0001.sql","#,
+                line,
+                r#"{"code":"ban-drop-column","message":"Dropping a column may break existing clients.","path":"migrations/0002.sql","#,
+                line,
+            )
+        );
+        assert_eq!(summary.warnings, 2);
+    }
+}
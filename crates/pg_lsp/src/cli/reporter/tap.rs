@@ -0,0 +1,162 @@
+use std::io;
+
+use pg_diagnostics::Severity;
+
+use super::{plural, DiagnosticsPayload, ReporterVisitor, TraversalSummary};
+
+/// Writes the result in the Test Anything Protocol, with one test point per file that fails if
+/// the file has errors
+///
+/// The diagnostics of a file are listed in a YAML block after its test point. The plan follows
+/// the test points, so that they can be written as soon as their files are checked.
+pub struct TapReporter<W> {
+    out: W,
+    count: usize,
+}
+
+impl<W: io::Write> TapReporter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, count: 0 }
+    }
+}
+
+impl<W: io::Write> ReporterVisitor for TapReporter<W> {
+    fn report_start(&mut self) -> io::Result<()> {
+        writeln!(self.out, "TAP version 13")
+    }
+
+    fn report_diagnostics(&mut self, payload: &DiagnosticsPayload) -> io::Result<()> {
+        self.count += 1;
+        let failed = payload
+            .diagnostics
+            .iter()
+            .any(|d| matches!(d.severity, Severity::Error | Severity::Fatal));
+        // `#` starts a directive in the description, so it is escaped
+        let path = payload.path.to_string_lossy().replace('#', "\\#");
+        if failed {
+            writeln!(self.out, "not ok {} - {}", self.count, path)?;
+        } else {
+            writeln!(self.out, "ok {} - {}", self.count, path)?;
+        }
+
+        if payload.diagnostics.is_empty() {
+            return Ok(());
+        }
+        writeln!(self.out, "  ---")?;
+        writeln!(self.out, "  diagnostics:")?;
+        for diagnostic in &payload.diagnostics {
+            let (line, column) = payload.line_col(diagnostic);
+            let severity = match diagnostic.severity {
+                Severity::Hint => "hint",
+                Severity::Information => "info",
+                Severity::Warning => "warning",
+                Severity::Error | Severity::Fatal => "error",
+            };
+            writeln!(self.out, "    - message: {}", quote(&diagnostic.message))?;
+            writeln!(self.out, "      severity: {}", severity)?;
+            writeln!(self.out, "      source: {}", quote(&diagnostic.source))?;
+            if let Some(code) = &diagnostic.code {
+                writeln!(self.out, "      code: {}", quote(code))?;
+            }
+            writeln!(self.out, "      line: {}", line)?;
+            writeln!(self.out, "      column: {}", column)?;
+        }
+        writeln!(self.out, "  ...")
+    }
+
+    fn report_summary(&mut self, summary: &TraversalSummary) -> io::Result<()> {
+        writeln!(self.out, "1..{}", self.count)?;
+        writeln!(
+            self.out,
+            "# Checked {} {} in {}ms. Found {} {} and {} {}.",
+            summary.files,
+            plural(summary.files, "file", "files"),
+            summary.duration.as_millis(),
+            summary.errors,
+            plural(summary.errors, "error", "errors"),
+            summary.warnings,
+            plural(summary.warnings, "warning", "warnings"),
+        )
+    }
+}
+
+/// Quotes a string for YAML, whose double quoted strings accept the escapes of JSON
+fn quote(text: &str) -> String {
+    serde_json::Value::from(text).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use pg_diagnostics::{Diagnostic, Severity};
+    use text_size::{TextRange, TextSize};
+
+    use crate::cli::reporter::{DiagnosticsPayload, Reporter};
+
+    use super::TapReporter;
+
+    #[test]
+    fn test_tap_reporter() {
+        let payload = |path: &str, severity: Severity| DiagnosticsPayload {
+            path: PathBuf::from(path),
+            duration: Duration::ZERO,
+            text: "select 1;\nalter table t drop column c;".to_string(),
+            diagnostics: vec![Diagnostic {
+                message: "Dropping a column may break existing clients.".to_string(),
+                description: None,
+                code: Some("ban-drop-column".to_string()),
+                severity,
+                source: "lint".to_string(),
+                range: TextRange::new(TextSize::new(14), TextSize::new(38)),
+            }],
+        };
+        let payloads = vec![
+            payload("migrations/0001.sql", Severity::Error),
+            payload("migrations/0002.sql", Severity::Warning),
+            DiagnosticsPayload {
+                path: PathBuf::from("migrations/#3.sql"),
+                duration: Duration::ZERO,
+                text: "select 1;".to_string(),
+                diagnostics: Vec::new(),
+            },
+        ];
+
+        let mut out = Vec::new();
+        let mut visitor = TapReporter::new(&mut out);
+        let mut reporter = Reporter::new(&mut visitor).unwrap();
+        for (idx, payload) in payloads.into_iter().enumerate() {
+            reporter.add(idx, payload).unwrap();
+        }
+        reporter.finish(Duration::from_millis(7)).unwrap();
+
+        let diagnostics = |severity: &str| {
+            format!(
+                r#"  ---
+  diagnostics:
+    - message: "Dropping a column may break existing clients."
+      severity: {}
+      source: "lint"
+      code: "ban-drop-column"
+      line: 2
+      column: 5
+  ...
+"#,
+                severity
+            )
+        };
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "TAP version 13\n\
+                 not ok 1 - migrations/0001.sql\n{}\
+                 ok 2 - migrations/0002.sql\n{}\
+                 ok 3 - migrations/\\#3.sql\n\
+                 1..3\n\
+                 # Checked 3 files in 7ms. Found 1 error and 1 warning.\n",
+                diagnostics("error"),
+                diagnostics("warning")
+            )
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! The `pglsp schema` commands: `dump` writes the schema of the database as sql files that can
+//! be committed and read back with the `schemaFiles` setting, `diff` compares the schema of the
+//! database with them, and `lint` checks its objects against the lint rules
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    str::FromStr,
+};
+
+use anyhow::Context;
+use pg_diagnostics::Severity;
+use pg_lint::{SchemaViolation, ViolationMessage};
+use pg_schema_cache::{SchemaCache, SchemaChange, SqlFile, SqlLayout};
+
+use crate::{
+    db::{self, ConnectionSettings},
+    server::options::Options,
+};
+
+use super::{
+    check::offline_schema_cache, connection_string, flags, load_options, reporter::plural,
+};
+
+/// The first line of every written file, which marks the files that may be overwritten
+const HEADER: &str = "-- Generated by `pglsp schema dump`, do not edit.";
+
+const DEFAULT_OUTPUT: &str = "db/schema";
+
+/// A value of `--layout`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutFlag(pub SqlLayout);
+
+impl FromStr for LayoutFlag {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "schema" => Ok(LayoutFlag(SqlLayout::Schema)),
+            "object" => Ok(LayoutFlag(SqlLayout::Object)),
+            _ => Err(format!(
+                "Unknown layout `{}`, expected `schema` or `object`",
+                s
+            )),
+        }
+    }
+}
+
+pub fn schema(flags: flags::Schema) -> anyhow::Result<ExitCode> {
+    match flags.subcommand {
+        flags::SchemaCmd::Dump(flags) => dump(flags),
+        flags::SchemaCmd::Diff(flags) => diff(flags),
+        flags::SchemaCmd::Lint(flags) => lint(flags),
+    }
+}
+
+/// Writes the schema of the database to the output directory
+///
+/// Files that were written by a previous dump and are not part of this one are removed, other
+/// files in the directory are left alone.
+fn dump(flags: flags::Dump) -> anyhow::Result<ExitCode> {
+    let root = std::env::current_dir()?;
+    let options = load_options(
+        &root,
+        flags.config.as_deref(),
+        flags.profile,
+        flags.connection,
+    )?;
+    let connection_string = connection_string(&root, &options)?;
+
+    let mut cache = load_schema(&connection_string, &options)?;
+    if !flags.schema.is_empty() {
+        retain_schemas(&mut cache, &flags.schema);
+    }
+
+    let layout = flags.layout.map_or(SqlLayout::Schema, |layout| layout.0);
+    let output = flags
+        .output
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_OUTPUT));
+    let files = cache.to_sql_files(layout);
+    write_files(&output, &files)?;
+
+    println!("Wrote {} files to {}", files.len(), output.display());
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints how the schema of the database differs from the expected one, which is that of the
+/// sql files or another database
+///
+/// Exits with 1 if there are differences, like `diff` does.
+fn diff(flags: flags::Diff) -> anyhow::Result<ExitCode> {
+    let root = std::env::current_dir()?;
+    let options = load_options(
+        &root,
+        flags.config.as_deref(),
+        flags.profile,
+        flags.connection,
+    )?;
+
+    let mut expected = match &flags.expected {
+        Some(connection_string) => load_schema(connection_string, &options)?,
+        None if !flags.paths.is_empty() => SchemaCache::from_files(&flags.paths)?,
+        None => offline_schema_cache(&options)?.context(
+            "There is no expected schema, pass sql files or set `schemaFiles` or `schemaDump`",
+        )?,
+    };
+    let mut actual = load_schema(&connection_string(&root, &options)?, &options)?;
+    if !flags.schema.is_empty() {
+        retain_schemas(&mut expected, &flags.schema);
+        retain_schemas(&mut actual, &flags.schema);
+    }
+
+    let changes = expected.diff(&actual);
+    if flags.json {
+        println!("{}", serde_json::to_string_pretty(&changes)?);
+    } else {
+        for change in &changes {
+            println!("{}", format_change(change));
+        }
+        match changes.len() {
+            0 => println!("The schema matches"),
+            1 => println!("Found 1 difference"),
+            n => println!("Found {} differences", n),
+        }
+    }
+
+    Ok(if changes.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn format_change(change: &SchemaChange) -> String {
+    let kind = change.kind().as_str();
+    match change {
+        SchemaChange::Missing { name, .. } => format!("missing {} {}", kind, name),
+        SchemaChange::Extra { name, .. } => format!("extra {} {}", kind, name),
+        SchemaChange::Changed {
+            name,
+            property,
+            expected,
+            actual,
+            ..
+        } => format!(
+            "changed {} {}: {} is `{}`, expected `{}`",
+            kind, name, property, actual, expected
+        ),
+    }
+}
+
+/// Prints the violations of the lint rules that check the existing objects of the database, with
+/// the severities and options of the settings
+///
+/// Exits with 1 if there are errors, like `pglsp check` does.
+fn lint(flags: flags::Lint) -> anyhow::Result<ExitCode> {
+    let root = std::env::current_dir()?;
+    let options = load_options(
+        &root,
+        flags.config.as_deref(),
+        flags.profile,
+        flags.connection,
+    )?;
+
+    let mut cache = load_schema(&connection_string(&root, &options)?, &options)?;
+    if !flags.schema.is_empty() {
+        retain_schemas(&mut cache, &flags.schema);
+    }
+
+    let severities = options.rule_severities();
+    let (mut errors, mut warnings) = (0, 0);
+    for violation in pg_lint::check_schema(&cache, &options.rule_options()) {
+        let severity = match severities.get(&violation.kind) {
+            Some(None) => continue,
+            Some(Some(severity)) => *severity,
+            None if violation.kind.is_enabled_by_default() => Severity::Warning,
+            None => continue,
+        };
+        match severity {
+            Severity::Error | Severity::Fatal => errors += 1,
+            Severity::Warning => warnings += 1,
+            Severity::Information | Severity::Hint => {}
+        }
+        print!("{}", format_violation(&violation, severity));
+    }
+    println!(
+        "Found {} {} and {} {}.",
+        errors,
+        plural(errors, "error", "errors"),
+        warnings,
+        plural(warnings, "warning", "warnings")
+    );
+
+    Ok(if errors > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Formats a violation like `pglsp check` does, with the object instead of the location, e.g.
+/// `public.logs: warning[table-without-primary-key]: The table has no primary key, ...`
+fn format_violation(violation: &SchemaViolation, severity: Severity) -> String {
+    violation
+        .messages
+        .iter()
+        .map(|message| {
+            let (severity, message) = match message {
+                ViolationMessage::Note(message) => (severity, message),
+                ViolationMessage::Help(message) => (Severity::Hint, message),
+            };
+            let severity = match severity {
+                Severity::Hint => "hint",
+                Severity::Information => "info",
+                Severity::Warning => "warning",
+                Severity::Error | Severity::Fatal => "error",
+            };
+            format!(
+                "{}: {}[{}]: {}\n",
+                violation.object, severity, violation.kind, message
+            )
+        })
+        .collect()
+}
+
+/// Loads the schema of all schemas of a database
+pub(super) fn load_schema(
+    connection_string: &str,
+    options: &Options,
+) -> anyhow::Result<SchemaCache> {
+    async_std::task::block_on(async {
+        let (pool, _tunnel) =
+            db::connect(connection_string, &ConnectionSettings::new(options)).await?;
+        anyhow::Ok(SchemaCache::load(&pool).await)
+    })
+    .context("Could not connect to the database")
+}
+
+/// Removes the objects of all other schemas
+pub(super) fn retain_schemas(cache: &mut SchemaCache, schemas: &[String]) {
+    let keep = |schema: &str| schemas.iter().any(|s| s == schema);
+    cache.schemas.retain(|s| keep(&s.name));
+    cache.types.retain(|t| keep(&t.schema));
+    cache.tables.retain(|t| keep(&t.schema));
+    cache.columns.retain(|c| keep(&c.schema));
+    cache.foreign_tables.retain(|t| keep(&t.schema));
+    cache.indexes.retain(|i| keep(&i.schema));
+    cache.foreign_keys.retain(|k| keep(&k.schema));
+    cache.policies.retain(|p| keep(&p.schema));
+    cache
+        .functions
+        .retain(|f| f.schema.as_deref().is_some_and(keep));
+}
+
+/// Writes the files below the output directory and removes stale files of a previous dump
+///
+/// Files whose content did not change are not written, so that their modification time stays.
+fn write_files(output: &Path, files: &[SqlFile]) -> anyhow::Result<()> {
+    fs::create_dir_all(output).with_context(|| format!("Could not create {}", output.display()))?;
+    let mut written = HashSet::new();
+    for file in files {
+        let path = output.join(&file.path);
+        let content = format!("{}\n\n{}", HEADER, file.sql);
+        if fs::read_to_string(&path).ok().as_deref() != Some(content.as_str()) {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, content)
+                .with_context(|| format!("Could not write {}", path.display()))?;
+        }
+        written.insert(path);
+    }
+
+    remove_stale_files(output, &written)?;
+    Ok(())
+}
+
+/// Removes the generated files of a directory that were not written, and returns whether the
+/// directory is empty afterwards
+fn remove_stale_files(dir: &Path, written: &HashSet<PathBuf>) -> anyhow::Result<bool> {
+    let mut empty = true;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if remove_stale_files(&path, written)? {
+                fs::remove_dir(&path)?;
+            } else {
+                empty = false;
+            }
+        } else if !written.contains(&path)
+            && path.extension().is_some_and(|ext| ext == "sql")
+            && fs::read_to_string(&path).is_ok_and(|text| text.starts_with(HEADER))
+        {
+            fs::remove_file(&path)?;
+        } else {
+            empty = false;
+        }
+    }
+    Ok(empty)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, fs, path::PathBuf};
+
+    use pg_diagnostics::Severity;
+    use pg_lint::{RuleViolationKind, SchemaViolation};
+    use pg_schema_cache::SqlFile;
+
+    use super::{format_violation, write_files, HEADER};
+
+    #[test]
+    fn test_format_violation() {
+        let violation = SchemaViolation::new(
+            RuleViolationKind::TableWithoutPrimaryKey,
+            "public.logs".to_string(),
+            None,
+        );
+        assert_eq!(
+            format_violation(&violation, Severity::Error),
+            "public.logs: error[table-without-primary-key]: The table has no primary key, so its rows cannot be identified by logical replication or upserts.
+public.logs: hint[table-without-primary-key]: Add a primary key, or a unique constraint on columns that are not null.
+"
+        );
+    }
+
+    #[test]
+    fn test_write_files() {
+        let dir = std::env::temp_dir().join(format!("pglsp-schema-dump-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("old")).unwrap();
+        fs::write(dir.join("old/table.sql"), format!("{}\n\n", HEADER)).unwrap();
+        fs::write(dir.join("README.md"), "# Schema").unwrap();
+        fs::write(dir.join("seed.sql"), "insert into users default values;").unwrap();
+
+        let files = [SqlFile {
+            path: PathBuf::from("public/00_schema.sql"),
+            sql: "create schema if not exists public;\n".to_string(),
+        }];
+        write_files(&dir, &files).unwrap();
+
+        let mut paths = HashSet::new();
+        for entry in walkdir(&dir) {
+            paths.insert(entry.strip_prefix(&dir).unwrap().to_path_buf());
+        }
+        assert_eq!(
+            paths,
+            ["README.md", "seed.sql", "public/00_schema.sql"]
+                .map(PathBuf::from)
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("public/00_schema.sql")).unwrap(),
+            format!("{}\n\ncreate schema if not exists public;\n", HEADER)
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn walkdir(dir: &std::path::Path) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                paths.extend(walkdir(&path));
+            } else {
+                paths.push(path);
+            }
+        }
+        paths
+    }
+}
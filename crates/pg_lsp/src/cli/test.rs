@@ -0,0 +1,475 @@
+//! `pglsp test`, which runs pgTAP tests against the database
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Instant,
+};
+
+use anyhow::Context;
+use pg_base_db::{Document, PgLspPath};
+use pg_diagnostics::{Diagnostic, Severity};
+use sqlx::{PgConnection, PgPool, Row};
+use text_size::TextRange;
+
+use crate::{
+    db::{self, ConnectionSettings},
+    server::options::FilesOptions,
+};
+
+use super::{
+    file_connection_string, flags, load_options,
+    reporter::{plural, DiagnosticsPayload, ReportOutputs, Reporter},
+    traverse::{collect_files, normalize, read_file, FileFilter},
+};
+
+/// The suffix of the names of test files that are found in directories
+const TEST_FILE_SUFFIX: &str = "_test.sql";
+
+/// The source of the diagnostics of failed tests
+const SOURCE: &str = "test";
+
+/// Runs the test files and reports the failed tests as errors
+///
+/// The statements of a file are executed one by one in a transaction that is rolled back, and
+/// the text they return is read as TAP, which is what the assertions of pgTAP return. Exits with
+/// 1 if a test failed.
+pub fn test(flags: flags::Test) -> anyhow::Result<ExitCode> {
+    let start = Instant::now();
+    let root = std::env::current_dir()?;
+    let options = load_options(
+        &root,
+        flags.config.as_deref(),
+        flags.profile,
+        flags.connection,
+    )?;
+    let files = test_files(&root, &flags.paths, &options.files, flags.no_ignore)?;
+    if files.is_empty() {
+        anyhow::bail!(
+            "No test files found, their names end with `{}`",
+            TEST_FILE_SUFFIX
+        );
+    }
+
+    let settings = ConnectionSettings::new(&options);
+    let mut pools = HashMap::new();
+    let mut reports = ReportOutputs::new(
+        &flags.reporter,
+        flags.report_file.as_deref(),
+        io::stdout().lock(),
+    )?;
+    let mut visitor = reports.visitor();
+    let mut reporter = Reporter::new(&mut visitor)?;
+    let mut counts = TestRun::default();
+    for (idx, file) in files.iter().enumerate() {
+        let file_start = Instant::now();
+        let path = file.strip_prefix(&root).unwrap_or(file).to_path_buf();
+        let text = match read_file(file, options.files.max_size)? {
+            Ok(text) => text,
+            Err(ignored) => {
+                reporter.add(
+                    idx,
+                    DiagnosticsPayload {
+                        path,
+                        text: String::new(),
+                        diagnostics: vec![ignored],
+                        duration: file_start.elapsed(),
+                    },
+                )?;
+                continue;
+            }
+        };
+        let connection_string = match &options.execution.connection_string {
+            Some(connection_string) => connection_string.clone(),
+            None => file_connection_string(&root, &options, file)?,
+        };
+        if !pools.contains_key(&connection_string) {
+            let connection = async_std::task::block_on(db::connect(&connection_string, &settings))
+                .context("Could not connect to the database")?;
+            pools.insert(connection_string.clone(), connection);
+        }
+        let (pool, _tunnel) = &pools[&connection_string];
+
+        let document = Document::new(PgLspPath::new(file), Some(text));
+        let run = async_std::task::block_on(run_file(pool, &document))?;
+        counts.tests += run.tests;
+        counts.failed += run.failed;
+        counts.skipped += run.skipped;
+        reporter.add(
+            idx,
+            DiagnosticsPayload {
+                path,
+                text: document.text,
+                diagnostics: run.diagnostics,
+                duration: file_start.elapsed(),
+            },
+        )?;
+    }
+    let summary = reporter.finish(start.elapsed())?;
+    drop(visitor);
+    reports.flush()?;
+
+    // the output may be a report for another program, so the counts are written to stderr
+    eprintln!(
+        "Ran {} {} in {} {}: {} passed, {} failed, {} skipped.",
+        counts.tests,
+        plural(counts.tests, "test", "tests"),
+        files.len(),
+        plural(files.len(), "file", "files"),
+        counts.tests - counts.failed - counts.skipped,
+        counts.failed,
+        counts.skipped
+    );
+    Ok(if summary.errors > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Returns the test files for the paths given on the command line
+///
+/// Directories are searched for files whose names end with `_test.sql`, while files that are
+/// given explicitly are run whatever their names.
+fn test_files(
+    root: &Path,
+    paths: &[PathBuf],
+    files_options: &FilesOptions,
+    no_ignore: bool,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let filter = FileFilter::new(root, files_options)
+        .context("Invalid files setting")?
+        .with_no_ignore(no_ignore);
+    let paths = match paths.is_empty() {
+        true => vec![PathBuf::from(".")],
+        false => paths.to_vec(),
+    };
+    let explicit = paths
+        .iter()
+        .map(|path| normalize(&root.join(path)))
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    let mut files = collect_files(root, &paths, &filter)?;
+    files.retain(|file| {
+        explicit.contains(file)
+            || file
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with(TEST_FILE_SUFFIX))
+    });
+    Ok(files)
+}
+
+/// The result of running a test file
+#[derive(Debug, Default, PartialEq)]
+struct TestRun {
+    tests: usize,
+    failed: usize,
+    skipped: usize,
+    /// The plan and the statement that printed it
+    plan: Option<(usize, TextRange)>,
+    diagnostics: Vec<Diagnostic>,
+    /// Whether the tests stopped early, because of an error or a bail out
+    stopped: bool,
+}
+
+impl TestRun {
+    /// Adds the TAP output of a statement
+    ///
+    /// The diagnostics of a failed test are reported at the statement that printed it, together
+    /// with the comments that follow it.
+    fn add_output(&mut self, range: TextRange, output: &str) {
+        let mut failure: Option<Diagnostic> = None;
+        for line in output.lines() {
+            match parse_line(line) {
+                TapLine::Plan(count) => self.plan = Some((count, range)),
+                TapLine::Test { ok, directive } => {
+                    self.diagnostics.extend(failure.take());
+                    self.tests += 1;
+                    match directive {
+                        Some(Directive::Skip) => self.skipped += 1,
+                        // a failing test that is still to do is expected to fail
+                        Some(Directive::Todo) => {}
+                        None if !ok => {
+                            self.failed += 1;
+                            failure = Some(error(range, line.to_string(), None));
+                        }
+                        None => {}
+                    }
+                }
+                TapLine::Comment(comment) => {
+                    if let Some(failure) = &mut failure {
+                        failure.message.push('\n');
+                        failure.message.push_str(comment);
+                    }
+                }
+                TapLine::BailOut => {
+                    self.diagnostics.extend(failure.take());
+                    self.diagnostics.push(error(range, line.to_string(), None));
+                    self.stopped = true;
+                    return;
+                }
+                TapLine::Other => {}
+            }
+        }
+        self.diagnostics.extend(failure);
+    }
+
+    /// Reports a statement that failed, which stops the tests of the file
+    fn add_error(&mut self, range: TextRange, err: sqlx::Error) {
+        let code = err
+            .as_database_error()
+            .and_then(|err| err.code())
+            .map(|code| code.to_string());
+        let message = match err.as_database_error() {
+            Some(err) => err.message().to_string(),
+            None => err.to_string(),
+        };
+        self.diagnostics.push(error(range, message, code));
+        self.stopped = true;
+    }
+
+    /// Checks that the tests ran as planned once all statements were executed
+    fn finish(&mut self) {
+        if self.stopped {
+            return;
+        }
+        match self.plan {
+            Some((count, range)) if count != self.tests => {
+                self.diagnostics.push(error(
+                    range,
+                    format!(
+                        "Planned {} {} but ran {}",
+                        count,
+                        plural(count, "test", "tests"),
+                        self.tests
+                    ),
+                    None,
+                ));
+            }
+            None if self.tests == 0 => self.diagnostics.push(Diagnostic {
+                message: "The file ran no tests".to_string(),
+                description: None,
+                code: None,
+                severity: Severity::Warning,
+                source: SOURCE.to_string(),
+                range: TextRange::default(),
+            }),
+            _ => {}
+        }
+    }
+}
+
+fn error(range: TextRange, message: String, code: Option<String>) -> Diagnostic {
+    Diagnostic {
+        message,
+        description: None,
+        code,
+        severity: Severity::Error,
+        source: SOURCE.to_string(),
+        range,
+    }
+}
+
+/// Executes the statements of a test file in a transaction that is rolled back
+///
+/// Statements that start or end a transaction are skipped, because they would end the
+/// transaction the tests run in. Savepoints can still be used.
+async fn run_file(pool: &PgPool, document: &Document) -> anyhow::Result<TestRun> {
+    let mut run = TestRun::default();
+    let mut tx = pool.begin().await?;
+    for range in &document.statement_ranges {
+        let sql = &document.text[*range];
+        if is_transaction_control(sql) {
+            continue;
+        }
+        match statement_output(&mut tx, sql).await {
+            Ok(output) => run.add_output(*range, &output),
+            Err(err) => run.add_error(*range, err),
+        }
+        if run.stopped {
+            break;
+        }
+    }
+    tx.rollback().await?;
+    run.finish();
+    Ok(run)
+}
+
+/// Returns the text the rows of a statement start with, one line per row
+///
+/// Rows that do not start with text, e.g. of a statement that sets up data, are skipped.
+async fn statement_output(conn: &mut PgConnection, sql: &str) -> Result<String, sqlx::Error> {
+    let rows = sqlx::query(sql).persistent(false).fetch_all(conn).await?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| row.try_get::<Option<String>, _>(0).ok().flatten())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Returns whether a statement starts or ends a transaction
+fn is_transaction_control(sql: &str) -> bool {
+    let mut words = sql
+        .split(|c: char| c.is_whitespace() || c == ';')
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase);
+    match words.next().as_deref() {
+        Some("begin" | "start" | "commit" | "end" | "abort") => true,
+        Some("rollback") => words.next().as_deref() != Some("to"),
+        _ => false,
+    }
+}
+
+/// A line of TAP output
+#[derive(Debug, PartialEq, Eq)]
+enum TapLine<'a> {
+    /// `1..N`, the number of tests that are planned
+    Plan(usize),
+    /// `ok N - description` or `not ok N - description`, optionally with a directive
+    Test {
+        ok: bool,
+        directive: Option<Directive>,
+    },
+    /// `# comment`, without the `#`
+    Comment(&'a str),
+    /// `Bail out! reason`, which stops the tests
+    BailOut,
+    Other,
+}
+
+/// A directive of a test, after a `#` in its description
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Directive {
+    Skip,
+    Todo,
+}
+
+fn parse_line(line: &str) -> TapLine<'_> {
+    if let Some(comment) = line.strip_prefix('#') {
+        return TapLine::Comment(comment.strip_prefix(' ').unwrap_or(comment));
+    }
+    if line.starts_with("Bail out!") {
+        return TapLine::BailOut;
+    }
+    if let Some(count) = line.strip_prefix("1..") {
+        let digits = count.split(|c: char| !c.is_ascii_digit()).next();
+        if let Some(count) = digits.and_then(|digits| digits.parse().ok()) {
+            return TapLine::Plan(count);
+        }
+    }
+    let (ok, rest) = if let Some(rest) = line.strip_prefix("not ok") {
+        (false, rest)
+    } else if let Some(rest) = line.strip_prefix("ok") {
+        (true, rest)
+    } else {
+        return TapLine::Other;
+    };
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return TapLine::Other;
+    }
+
+    // a `#` that is not escaped with `\` starts the directive
+    let directive = rest
+        .char_indices()
+        .find(|&(i, c)| c == '#' && !rest[..i].ends_with('\\'))
+        .and_then(|(i, _)| {
+            let directive = rest[i + 1..].trim_start().to_ascii_lowercase();
+            if directive.starts_with("skip") {
+                Some(Directive::Skip)
+            } else if directive.starts_with("todo") {
+                Some(Directive::Todo)
+            } else {
+                None
+            }
+        });
+    TapLine::Test { ok, directive }
+}
+
+#[cfg(test)]
+mod tests {
+    use text_size::{TextRange, TextSize};
+
+    use super::{is_transaction_control, parse_line, Directive, TapLine, TestRun};
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(parse_line("1..3"), TapLine::Plan(3));
+        assert_eq!(parse_line("1..0 # Skipped: no database"), TapLine::Plan(0));
+        assert_eq!(
+            parse_line("ok 1 - users have emails"),
+            TapLine::Test {
+                ok: true,
+                directive: None
+            }
+        );
+        assert_eq!(
+            parse_line("not ok 2 - a \\# b"),
+            TapLine::Test {
+                ok: false,
+                directive: None
+            }
+        );
+        assert_eq!(
+            parse_line("ok 3 # SKIP no posts"),
+            TapLine::Test {
+                ok: true,
+                directive: Some(Directive::Skip)
+            }
+        );
+        assert_eq!(
+            parse_line("not ok 4 - soon # TODO later"),
+            TapLine::Test {
+                ok: false,
+                directive: Some(Directive::Todo)
+            }
+        );
+        assert_eq!(
+            parse_line("# Failed test 2"),
+            TapLine::Comment("Failed test 2")
+        );
+        assert_eq!(parse_line("Bail out! no schema"), TapLine::BailOut);
+        assert_eq!(parse_line("okay"), TapLine::Other);
+    }
+
+    #[test]
+    fn test_run() {
+        let range = |start: u32| TextRange::new(TextSize::new(start), TextSize::new(start + 1));
+        let mut run = TestRun::default();
+        run.add_output(range(0), "1..4");
+        run.add_output(range(10), "ok 1 - a");
+        run.add_output(
+            range(20),
+            "not ok 2 - b\n# Failed test 2: \"b\"\n#   have: 1\n#   want: 2",
+        );
+        run.add_output(range(30), "ok 3 # SKIP c");
+        run.finish();
+
+        assert_eq!((run.tests, run.failed, run.skipped), (3, 1, 1));
+        let diagnostics = run
+            .diagnostics
+            .iter()
+            .map(|d| (d.range, d.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    range(20),
+                    "not ok 2 - b\nFailed test 2: \"b\"\n  have: 1\n  want: 2"
+                ),
+                (range(0), "Planned 4 tests but ran 3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_transaction_control() {
+        assert!(is_transaction_control("BEGIN;"));
+        assert!(is_transaction_control("start transaction"));
+        assert!(is_transaction_control("rollback;"));
+        assert!(!is_transaction_control("rollback to savepoint a;"));
+        assert!(!is_transaction_control("select plan(1);"));
+    }
+}
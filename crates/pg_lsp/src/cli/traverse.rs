@@ -0,0 +1,346 @@
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Context;
+use glob::{Pattern, PatternError};
+use ignore::WalkBuilder;
+use pg_diagnostics::{Diagnostic, Severity};
+use pg_workspace::{is_glob, matches_path, path_pattern};
+
+use crate::{
+    server::options::FilesOptions,
+    utils::{read_sql_file, sql_files, IGNORED_DIRS},
+};
+
+/// The source of the diagnostics of files that are ignored because of their content
+const SOURCE: &str = "files";
+
+/// Decides which of the files found in directories are checked
+#[derive(Debug, Default)]
+pub struct FileFilter {
+    include: Vec<Pattern>,
+    ignore: Vec<Pattern>,
+    force_include: Vec<Pattern>,
+    /// Whether the files that git ignores are skipped in directories
+    gitignore: bool,
+}
+
+impl FileFilter {
+    /// Creates the filter from the `files` settings, whose relative paths are resolved against
+    /// the root
+    pub fn new(root: &Path, options: &FilesOptions) -> Result<FileFilter, PatternError> {
+        let patterns = |paths: &[String]| {
+            paths
+                .iter()
+                .map(|path| path_pattern(Some(root), path))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(FileFilter {
+            include: patterns(&options.include)?,
+            ignore: patterns(&options.ignore)?,
+            force_include: patterns(&options.force_include)?,
+            gitignore: true,
+        })
+    }
+
+    /// Also finds the files that git ignores in directories, see `--no-ignore`
+    pub fn with_no_ignore(mut self, no_ignore: bool) -> FileFilter {
+        self.gitignore = !no_ignore;
+        self
+    }
+
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.ignore
+            .iter()
+            .any(|pattern| matches_path(pattern, path))
+    }
+
+    pub fn is_included(&self, path: &Path) -> bool {
+        self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| matches_path(pattern, path))
+    }
+
+    pub fn is_force_included(&self, path: &Path) -> bool {
+        self.force_include
+            .iter()
+            .any(|pattern| matches_path(pattern, path))
+    }
+}
+
+/// Returns the files to check for the paths given on the command line, sorted and without
+/// duplicates
+///
+/// Directories are searched for sql files that are included and not ignored, skipping the files
+/// git ignores unless they are force included. Files that are given explicitly are checked unless
+/// they are ignored, and so are sql files that match a glob. Relative paths are resolved against
+/// the root.
+pub fn collect_files(
+    root: &Path,
+    paths: &[PathBuf],
+    filter: &FileFilter,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        let path = normalize(&root.join(path));
+        let matches = if is_glob(&path.to_string_lossy()) {
+            glob::glob(&path.to_string_lossy())?
+                .filter(|m| {
+                    m.as_ref()
+                        .is_ok_and(|m| m.is_dir() || m.extension().is_some_and(|ext| ext == "sql"))
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        } else if path.exists() {
+            vec![path]
+        } else {
+            anyhow::bail!("{} does not exist", path.display());
+        };
+
+        for path in matches {
+            if path.is_dir() {
+                let found = dir_files(&path, filter)?;
+                files.extend(found.into_iter().filter(|f| filter.is_included(f)));
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    files.retain(|f| !filter.is_ignored(f));
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Returns the sql files within a directory, skipping hidden and dependency directories, and the
+/// files git ignores if the filter skips them
+///
+/// Like ripgrep, `.gitignore`, `.git/info/exclude`, the global gitignore and `.ignore` files are
+/// respected, including those of parent directories. They are also respected outside of a git
+/// repository, e.g. in a copy of the sources without `.git`.
+fn dir_files(dir: &Path, filter: &FileFilter) -> anyhow::Result<Vec<PathBuf>> {
+    let mut all = Vec::new();
+    if !filter.gitignore || !filter.force_include.is_empty() {
+        sql_files(dir, &mut all)?;
+    }
+    if !filter.gitignore {
+        return Ok(all);
+    }
+
+    let mut files = Vec::new();
+    let walker = WalkBuilder::new(dir)
+        .require_git(false)
+        .filter_entry(|entry| !IGNORED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()))
+        .build();
+    for entry in walker {
+        let entry = entry?;
+        if entry.file_type().is_some_and(|t| t.is_file())
+            && entry.path().extension().is_some_and(|ext| ext == "sql")
+        {
+            files.push(entry.into_path());
+        }
+    }
+    files.extend(all.into_iter().filter(|f| filter.is_force_included(f)));
+    Ok(files)
+}
+
+/// Reads a file to check, or returns the warning that it is ignored because it is larger than the
+/// maximum size or binary
+///
+/// The warning is reported instead of the diagnostics of the file, so that a stray dump neither
+/// slows down nor fails the run but is not skipped silently either.
+pub fn read_file(path: &Path, max_size: u64) -> anyhow::Result<Result<String, Diagnostic>> {
+    let text = read_sql_file(path, max_size)
+        .with_context(|| format!("Could not read {}", path.display()))?;
+    Ok(text.map_err(|skipped| Diagnostic {
+        message: skipped.to_string(),
+        description: None,
+        code: Some("ignored".to_string()),
+        severity: Severity::Warning,
+        source: SOURCE.to_string(),
+        range: Default::default(),
+    }))
+}
+
+/// Removes the `.` components of a path, so that it can be matched against patterns
+pub fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| *c != Component::CurDir)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use crate::server::options::FilesOptions;
+
+    use super::{collect_files, read_file, FileFilter};
+
+    #[test]
+    fn test_collect_files() {
+        let root = std::env::temp_dir().join(format!("pglsp-traverse-{}", std::process::id()));
+        for file in [
+            "migrations/0001.sql",
+            "migrations/0002.sql",
+            "migrations/legacy/0000.sql",
+            "queries/users.sql",
+            "queries/README.md",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "select 1;").unwrap();
+        }
+
+        let filter = FileFilter::new(
+            &root,
+            &FilesOptions {
+                include: vec!["migrations".to_string(), "**/users.sql".to_string()],
+                ignore: vec!["migrations/legacy".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let collect = |paths: &[&str]| {
+            let paths = paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+            collect_files(&root, &paths, &filter).map(|files| {
+                files
+                    .into_iter()
+                    .map(|f| {
+                        f.strip_prefix(&root)
+                            .unwrap()
+                            .to_string_lossy()
+                            .into_owned()
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        let result = (
+            collect(&["."]).unwrap(),
+            collect(&[
+                "queries/*",
+                "migrations/0001.sql",
+                "migrations/legacy/0000.sql",
+            ])
+            .unwrap(),
+            collect(&["missing.sql"]).is_err(),
+        );
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            result.0,
+            vec![
+                "migrations/0001.sql",
+                "migrations/0002.sql",
+                "queries/users.sql"
+            ]
+        );
+        assert_eq!(result.1, vec!["migrations/0001.sql", "queries/users.sql"]);
+        assert!(result.2);
+    }
+
+    #[test]
+    fn test_collect_files_gitignore() {
+        let root = std::env::temp_dir().join(format!("pglsp-gitignore-{}", std::process::id()));
+        for file in [
+            "users.sql",
+            "vendor/extension.sql",
+            "vendor/patched/extension.sql",
+            "db/schema.generated.sql",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "select 1;").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "vendor/\n*.generated.sql\n").unwrap();
+
+        let options = FilesOptions {
+            force_include: vec!["vendor/patched".to_string()],
+            ..Default::default()
+        };
+        let collect = |paths: &[&str], no_ignore: bool| {
+            let filter = FileFilter::new(&root, &options)
+                .unwrap()
+                .with_no_ignore(no_ignore);
+            let paths = paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+            collect_files(&root, &paths, &filter)
+                .unwrap()
+                .into_iter()
+                .map(|f| {
+                    f.strip_prefix(&root)
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let result = (
+            collect(&["."], false),
+            collect(&[".", "vendor/extension.sql"], false),
+            collect(&["."], true),
+        );
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(result.0, vec!["users.sql", "vendor/patched/extension.sql"]);
+        assert_eq!(
+            result.1,
+            vec![
+                "users.sql",
+                "vendor/extension.sql",
+                "vendor/patched/extension.sql"
+            ]
+        );
+        assert_eq!(
+            result.2,
+            vec![
+                "db/schema.generated.sql",
+                "users.sql",
+                "vendor/extension.sql",
+                "vendor/patched/extension.sql"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_file() {
+        let dir = std::env::temp_dir().join(format!("pglsp-read-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, content: &[u8]| {
+            let path = dir.join(name);
+            fs::write(&path, content).unwrap();
+            path
+        };
+        let read = |path: PathBuf| {
+            read_file(&path, 16)
+                .unwrap()
+                .map_err(|diagnostic| diagnostic.message)
+        };
+
+        let result = (
+            read(write("query.sql", b"select 1;")),
+            read(write("dump.sql", b"select 1; select 2; select 3;")),
+            read(write("blob.sql", b"PK\x03\x04\x00\x00")),
+            read(write("latin1.sql", b"select '\xe9';")),
+            read_file(&dir.join("missing.sql"), 16).is_err(),
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result.0, Ok("select 1;".to_string()));
+        assert_eq!(
+            result.1,
+            Err("Ignored because the file has 29 bytes, more than the maximum of 16 bytes of `files.maxSize`".to_string())
+        );
+        assert_eq!(
+            result.2,
+            Err("Ignored because the file is binary".to_string())
+        );
+        assert_eq!(
+            result.3,
+            Err("Ignored because the file is binary".to_string())
+        );
+        assert!(result.4);
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Context;
+
+use super::traverse::normalize;
+
+/// The base of `--changed` if none is given, which is the default branch of the remote
+pub const DEFAULT_BASE: &str = "origin/HEAD";
+
+/// Returns the files within the root that changed since the merge base of a ref and `HEAD`,
+/// including uncommitted and untracked files but not deleted ones
+pub fn changed_files(root: &Path, since: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let base = git(root, &["merge-base", since, "HEAD"])
+        .with_context(|| format!("Could not find the merge base of {} and HEAD", since))?;
+    let changed = git(
+        root,
+        &[
+            "diff",
+            "--name-only",
+            "--relative",
+            "--diff-filter=d",
+            base.trim(),
+        ],
+    )?;
+    let untracked = git(root, &["ls-files", "--others", "--exclude-standard"])?;
+
+    let mut files = changed
+        .lines()
+        .chain(untracked.lines())
+        .map(|path| normalize(&root.join(path)))
+        .collect::<Vec<_>>();
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Returns the files within the root that are staged, except deleted ones
+pub fn staged_files(root: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let staged = git(
+        root,
+        &[
+            "diff",
+            "--cached",
+            "--name-only",
+            "--relative",
+            "--diff-filter=d",
+        ],
+    )?;
+    let mut files = staged
+        .lines()
+        .map(|path| normalize(&root.join(path)))
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+/// Returns the staged content of a file within the root, which may differ from the one in the
+/// working tree
+pub fn staged_text(root: &Path, file: &Path) -> anyhow::Result<String> {
+    let path = file.strip_prefix(root).unwrap_or(file);
+    git(root, &["show", &format!(":./{}", path.display())])
+        .with_context(|| format!("Could not read the staged {}", path.display()))
+}
+
+/// Runs git in a directory and returns its output
+fn git(dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("Could not run git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).context("git returned invalid utf8")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::{changed_files, git, staged_files, staged_text};
+
+    #[test]
+    fn test_changed_files() {
+        let root = std::env::temp_dir().join(format!("pglsp-vcs-{}", std::process::id()));
+        let write = |file: &str| {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "select 1;").unwrap();
+        };
+        let commit = |root: &Path| {
+            git(root, &["add", "-A"]).unwrap();
+            git(
+                root,
+                &[
+                    "-c",
+                    "user.name=test",
+                    "-c",
+                    "user.email=test@example.com",
+                    "commit",
+                    "-q",
+                    "-m",
+                    "test",
+                ],
+            )
+            .unwrap();
+        };
+
+        fs::create_dir_all(&root).unwrap();
+        git(&root, &["init", "-q", "-b", "main"]).unwrap();
+        write("migrations/0001.sql");
+        write("migrations/0002.sql");
+        commit(&root);
+        git(&root, &["checkout", "-q", "-b", "feature"]).unwrap();
+        write("migrations/0003.sql");
+        commit(&root);
+        fs::remove_file(root.join("migrations/0002.sql")).unwrap();
+        write("migrations/0004.sql");
+        write("queries/users.sql");
+
+        let result = changed_files(&root.join("migrations"), "main").map(|files| {
+            files
+                .into_iter()
+                .map(|f| {
+                    f.strip_prefix(&root)
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect::<Vec<_>>()
+        });
+        let unknown_ref = changed_files(&root, "unknown").is_err();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            result.unwrap(),
+            vec!["migrations/0003.sql", "migrations/0004.sql"]
+        );
+        assert!(unknown_ref);
+    }
+
+    #[test]
+    fn test_staged_files() {
+        let root = std::env::temp_dir().join(format!("pglsp-vcs-staged-{}", std::process::id()));
+        let migrations = root.join("migrations");
+        fs::create_dir_all(&migrations).unwrap();
+        git(&root, &["init", "-q"]).unwrap();
+        fs::write(migrations.join("0001.sql"), "select 1;").unwrap();
+        fs::write(migrations.join("0002.sql"), "select 2;").unwrap();
+        fs::write(root.join("seed.sql"), "select 3;").unwrap();
+        git(&root, &["add", "migrations/0001.sql", "seed.sql"]).unwrap();
+        fs::write(migrations.join("0001.sql"), "select from;").unwrap();
+
+        let files = staged_files(&migrations);
+        let text = staged_text(&migrations, &migrations.join("0001.sql"));
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(files.unwrap(), vec![migrations.join("0001.sql")]);
+        assert_eq!(text.unwrap(), "select 1;");
+    }
+}
@@ -2,6 +2,7 @@ pub mod client_flags;
 
 use std::{
     collections::HashMap,
+    path::Path,
     sync::{
         atomic::{AtomicI32, Ordering},
         Arc, Mutex,
@@ -14,7 +15,7 @@ use lsp_server::{ErrorCode, Message, Request, RequestId, Response};
 use lsp_types::{notification::ShowMessage, MessageType, ShowMessageParams};
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::server::options::Options;
+use crate::{config, server::options::Options};
 
 #[derive(Debug)]
 struct RawClient {
@@ -97,18 +98,71 @@ impl LspClient {
         Ok(())
     }
 
-    pub fn parse_options(&self, mut value: serde_json::Value) -> Result<Options> {
+    /// Parses the settings of the client, layered over the `pglsp.toml` in the root of the
+    /// workspace, together with the `pglsp.toml` files of its subdirectories
+    pub fn parse_options(
+        &self,
+        root: Option<&Path>,
+        mut value: serde_json::Value,
+    ) -> Result<Options> {
         // if there are multiple servers, we need to extract the options for pglsp first
         let options = match value.get_mut("pglsp") {
             Some(section) => section.take(),
             None => value,
         };
 
-        let options = match serde_json::from_value(options) {
-            Ok(new_options) => new_options,
+        let project = match root.map_or(Ok(None), config::read_config_file) {
+            Ok(project) => project,
             Err(why) => {
                 let message = format!(
-                    "The texlab configuration is invalid; using the default settings instead.\nDetails: {why}"
+                    "The {} of the workspace is ignored.\nDetails: {why:#}",
+                    config::CONFIG_FILE
+                );
+                let typ = MessageType::WARNING;
+                self.send_notification::<ShowMessage>(ShowMessageParams { message, typ })?;
+                None
+            }
+        };
+        let options = config::layer(project, options);
+
+        let options = Options::parse(options).and_then(|(mut options, warnings)| {
+            options.apply_profile()?;
+            Ok((options, warnings))
+        });
+        let options = match options {
+            Ok((mut new_options, mut warnings)) => {
+                if new_options.db_connection_string.is_none() {
+                    new_options.db_connection_string = config::default_connection_string(root);
+                }
+                if let Some(root) = root {
+                    match config::read_nested_configs(root, &mut new_options) {
+                        Ok(nested_warnings) => warnings.extend(nested_warnings),
+                        Err(why) => {
+                            let message = format!(
+                                "The {} files of the subdirectories are ignored.\nDetails: {why:#}",
+                                config::CONFIG_FILE
+                            );
+                            let typ = MessageType::WARNING;
+                            self.send_notification::<ShowMessage>(ShowMessageParams {
+                                message,
+                                typ,
+                            })?;
+                        }
+                    }
+                }
+                if !warnings.is_empty() {
+                    let message = format!(
+                        "Some settings of the postgres_lsp configuration are ignored.\nDetails: {}",
+                        warnings.join(", ")
+                    );
+                    let typ = MessageType::WARNING;
+                    self.send_notification::<ShowMessage>(ShowMessageParams { message, typ })?;
+                }
+                Some(new_options)
+            }
+            Err(why) => {
+                let message = format!(
+                    "The postgres_lsp configuration is invalid; using the default settings instead.\nDetails: {why}"
                 );
                 let typ = MessageType::WARNING;
                 self.send_notification::<ShowMessage>(ShowMessageParams { message, typ })?;
@@ -7,4 +7,16 @@ pub struct ClientFlags {
 
     /// If `true`, the client notifies the server when the configuration changes.
     pub configuration_push: bool,
+
+    /// If `true`, the client pulls diagnostics instead of waiting for them to be published.
+    pub diagnostics_pull: bool,
+
+    /// If `true`, the server can ask the client to pull the diagnostics again.
+    pub diagnostics_refresh: bool,
+
+    /// If `true`, the server can show the progress of long running operations.
+    pub work_done_progress: bool,
+
+    /// If `true`, the server can ask the client to watch files.
+    pub watched_files_registration: bool,
 }
@@ -0,0 +1,270 @@
+//! The project configuration in `pglsp.toml`, which has the format of the editor settings and is
+//! read by both the language server and `pglsp check`
+//!
+//! The settings are layered, from the lowest to the highest precedence:
+//!
+//! 1. the defaults
+//! 2. `pglsp.toml` in the root of the workspace, or in the working directory of `pglsp check`
+//! 3. the environment, i.e. `PGLSP_DB_CONNECTION_STRING`
+//! 4. the settings of the editor, or the settings file given with `pglsp check --config`
+//! 5. the flags of `pglsp check`, e.g. `--connection`
+//!
+//! Tables are merged key by key, while other values, including lists, replace those of lower
+//! layers.
+//!
+//! A `pglsp.toml` in a subdirectory overrides the connection and the lint rules for the files
+//! within it, e.g. to relax the rules for `legacy/`.
+//!
+//! Without a `dbConnectionString` in any layer, the default connection is resolved from the
+//! environment like psql does.
+mod connection;
+mod toml;
+
+use std::{io, path::Path};
+
+use anyhow::Context;
+use serde_json::{Map, Value};
+
+use crate::{
+    server::options::{ConnectionOptions, DirectoryOptions, Options},
+    utils::dirs_with_file,
+};
+
+pub use connection::{connection_environment, default_connection_string};
+
+/// The name of the project configuration file
+pub const CONFIG_FILE: &str = "pglsp.toml";
+
+/// The settings that a `pglsp.toml` in a subdirectory may contain
+const DIRECTORY_SETTINGS: &[&str] = &["dbConnectionString", "lint"];
+
+/// Reads the settings of `pglsp.toml` in a directory, if there is one
+pub fn read_config_file(dir: &Path) -> anyhow::Result<Option<Value>> {
+    let path = dir.join(CONFIG_FILE);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Could not read {}", path.display()));
+        }
+    };
+    let settings = toml::parse(&text)
+        .map_err(anyhow::Error::msg)
+        .with_context(|| format!("Could not parse {}", path.display()))?;
+    Ok(Some(settings))
+}
+
+/// Returns the settings given by environment variables
+pub fn env_settings() -> Value {
+    let mut settings = Map::new();
+    if let Some(connection_string) = std::env::var("PGLSP_DB_CONNECTION_STRING")
+        .ok()
+        .filter(|v| !v.is_empty())
+    {
+        settings.insert("dbConnectionString".into(), connection_string.into());
+    }
+    Value::Object(settings)
+}
+
+/// Layers the project configuration and the environment under settings
+pub fn layer(project: Option<Value>, settings: Value) -> Value {
+    let mut layered = Value::Object(Map::new());
+    merge(&mut layered, project.unwrap_or(Value::Null));
+    merge(&mut layered, env_settings());
+    merge(&mut layered, settings);
+    layered
+}
+
+/// Reads the `pglsp.toml` files in the subdirectories of the root into the options, and returns
+/// warnings about the settings that are ignored
+///
+/// The connection string of a directory is added as a connection named after its file, which
+/// takes precedence over the connections of the root. The lint rules of a directory are merged
+/// with those of the directories above it.
+pub fn read_nested_configs(root: &Path, options: &mut Options) -> anyhow::Result<Vec<String>> {
+    let mut dirs = Vec::new();
+    dirs_with_file(root, CONFIG_FILE, &mut dirs)
+        .with_context(|| format!("Could not search {} for {}", root.display(), CONFIG_FILE))?;
+    dirs.retain(|dir| dir != root);
+    // parents come before their subdirectories
+    dirs.sort();
+
+    let mut warnings = Vec::new();
+    let mut directories: Vec<DirectoryOptions> = Vec::new();
+    let mut connections = Vec::new();
+    for dir in dirs {
+        let Some(Value::Object(mut settings)) = read_config_file(&dir)? else {
+            continue;
+        };
+        let name = dir
+            .strip_prefix(root)
+            .unwrap_or(&dir)
+            .join(CONFIG_FILE)
+            .to_string_lossy()
+            .into_owned();
+        for key in settings.keys() {
+            if !DIRECTORY_SETTINGS.contains(&key.as_str()) {
+                warnings.push(format!(
+                    "{}: Setting `{}` only applies in the root {}",
+                    name, key, CONFIG_FILE
+                ));
+            }
+        }
+        settings.retain(|key, _| DIRECTORY_SETTINGS.contains(&key.as_str()));
+        let (own, own_warnings) = Options::parse(Value::Object(settings))
+            .map_err(anyhow::Error::msg)
+            .with_context(|| format!("Invalid {}", name))?;
+        warnings.extend(own_warnings.into_iter().map(|w| format!("{}: {}", name, w)));
+
+        let mut lint = directories
+            .iter()
+            .rev()
+            .find(|parent| dir.starts_with(&parent.dir))
+            .map_or(&options.lint, |parent| &parent.lint)
+            .clone();
+        lint.extend(own.lint);
+        if let Some(connection_string) = own.db_connection_string {
+            connections.push(ConnectionOptions {
+                name,
+                connection_string,
+                paths: vec![dir.to_string_lossy().into_owned()],
+            });
+        }
+        directories.push(DirectoryOptions { dir, lint });
+    }
+
+    // the routes of subdirectories come first, since the first route that matches a file applies
+    connections.reverse();
+    options.connections.splice(0..0, connections);
+    options.directories = directories;
+    Ok(warnings)
+}
+
+/// Merges the settings of a higher layer into those of a lower one. `null` keeps the value of the
+/// lower layer.
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (_, Value::Null) => {}
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay.into_iter().filter(|(_, value)| !value.is_null()) {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pg_diagnostics::Severity;
+    use serde_json::json;
+
+    use crate::server::options::{ConnectionOptions, Options, RuleConfig, RuleSeverity};
+
+    use super::{merge, read_nested_configs};
+
+    #[test]
+    fn test_merge() {
+        let mut settings = json!({
+            "dbConnectionString": "postgres://localhost/app",
+            "files": { "include": ["migrations"], "ignore": ["migrations/old"] },
+            "lint": { "ban-drop-column": "error" }
+        });
+        merge(
+            &mut settings,
+            json!({
+                "files": { "ignore": [] },
+                "lint": { "ban-drop-table": "off" },
+                "schemaDump": null
+            }),
+        );
+        assert_eq!(
+            settings,
+            json!({
+                "dbConnectionString": "postgres://localhost/app",
+                "files": { "include": ["migrations"], "ignore": [] },
+                "lint": { "ban-drop-column": "error", "ban-drop-table": "off" }
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_nested_configs() {
+        let root = std::env::temp_dir().join(format!("pglsp-config-{}", std::process::id()));
+        let write = |dir: &str, text: &str| {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(root.join(dir).join("pglsp.toml"), text).unwrap();
+        };
+        write("", "[lint]\nban-drop-column = \"error\"");
+        write(
+            "legacy",
+            "dbConnectionString = \"postgres://localhost/legacy\"\n\
+             schemaFiles = [\"structure.sql\"]\n\
+             [lint]\nban-drop-column = \"warning\"",
+        );
+        write("legacy/old", "[lint]\n");
+        write(
+            "services",
+            "dbConnectionString = \"postgres://localhost/services\"",
+        );
+
+        let mut options = Options {
+            lint: HashMap::from([(
+                "ban-drop-column".to_string(),
+                RuleConfig::Severity(RuleSeverity::Error),
+            )]),
+            connections: vec![ConnectionOptions {
+                name: "billing".to_string(),
+                connection_string: "postgres://localhost/billing".to_string(),
+                paths: vec!["services/billing".to_string()],
+            }],
+            ..Default::default()
+        };
+        let warnings = read_nested_configs(&root, &mut options).unwrap();
+        assert_eq!(
+            warnings,
+            vec!["legacy/pglsp.toml: Setting `schemaFiles` only applies in the root pglsp.toml"]
+        );
+
+        let connections = options
+            .connections
+            .iter()
+            .map(|c| {
+                (
+                    c.name.as_str(),
+                    c.paths[0].strip_prefix(root.to_str().unwrap()),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            connections,
+            vec![
+                ("services/pglsp.toml", Some("/services")),
+                ("legacy/pglsp.toml", Some("/legacy")),
+                ("billing", None)
+            ]
+        );
+        let directories = options
+            .directories
+            .iter()
+            .map(|d| {
+                (
+                    d.dir.strip_prefix(&root).unwrap().to_str().unwrap(),
+                    d.lint["ban-drop-column"].severity(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            directories,
+            vec![
+                ("legacy", Some(Severity::Warning)),
+                ("legacy/old", Some(Severity::Warning)),
+                ("services", Some(Severity::Error))
+            ]
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,264 @@
+//! Resolves the default connection the way psql does if none is configured, so that a project
+//! with a `DATABASE_URL` or `PG*` variables is typechecked without any settings
+//!
+//! The sources are, from the highest to the lowest precedence:
+//!
+//! 1. `DATABASE_URL`
+//! 2. the service of `PGSERVICE` in the pg_service file, i.e. `PGSERVICEFILE`,
+//!    `~/.pg_service.conf` or `pg_service.conf` in `PGSYSCONFDIR`
+//! 3. `PGHOST`, `PGPORT`, `PGUSER`, `PGDATABASE`, `PGPASSWORD` and `PGSSLMODE`
+//!
+//! Variables of the environment take precedence over those of a `.env` file in the root. A
+//! password that is not given is looked up in `~/.pgpass` or `PGPASSFILE` when connecting.
+
+use std::path::{Path, PathBuf};
+
+use lsp_types::Url;
+
+/// The environment variables of the connection parameters, with their keys in a connection
+/// string
+const PARAMETER_VARS: &[(&str, &str)] = &[
+    ("PGHOST", "host"),
+    ("PGPORT", "port"),
+    ("PGUSER", "user"),
+    ("PGDATABASE", "dbname"),
+    ("PGPASSWORD", "password"),
+    ("PGSSLMODE", "sslmode"),
+];
+
+/// The parameters of a service that are passed on to the connection string
+const SERVICE_PARAMETERS: &[&str] = &[
+    "host",
+    "hostaddr",
+    "port",
+    "user",
+    "dbname",
+    "password",
+    "sslmode",
+    "sslrootcert",
+    "sslcert",
+    "sslkey",
+    "application_name",
+    "options",
+];
+
+/// Returns the connection string of the default connection from the environment, or `None` if
+/// nothing names a database to connect to
+pub fn default_connection_string(root: Option<&Path>) -> Option<String> {
+    let dotenv = root
+        .and_then(|root| std::fs::read_to_string(root.join(".env")).ok())
+        .map(|text| parse_dotenv(&text))
+        .unwrap_or_default();
+    let var = |name: &str| {
+        std::env::var(name)
+            .ok()
+            .or_else(|| {
+                dotenv
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.clone())
+            })
+            .filter(|value| !value.is_empty())
+    };
+    resolve(&var)
+}
+
+/// Returns the variables of the environment that connections are resolved from, i.e.
+/// `DATABASE_URL` and the `PG*` variables, sorted by name
+pub fn connection_environment() -> Vec<(String, String)> {
+    let mut vars: Vec<_> = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(name, _)| name == "DATABASE_URL" || name.starts_with("PG"))
+        .collect();
+    vars.sort();
+    vars
+}
+
+fn resolve(var: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+    if let Some(url) = var("DATABASE_URL") {
+        return Some(url);
+    }
+
+    // the parameters of the service take precedence over the variables, as with libpq
+    let mut parameters = var("PGSERVICE")
+        .and_then(|service| {
+            service_files(var)
+                .iter()
+                .find_map(|file| read_service(file, &service))
+        })
+        .unwrap_or_default();
+    for (name, key) in PARAMETER_VARS {
+        if parameters.iter().all(|(k, _)| k != key) {
+            if let Some(value) = var(name) {
+                parameters.push((key.to_string(), value));
+            }
+        }
+    }
+    // a password or a ssl mode alone does not name a database
+    if parameters
+        .iter()
+        .all(|(key, _)| key == "password" || key == "sslmode")
+    {
+        return None;
+    }
+
+    let mut url = Url::parse("postgresql://").unwrap();
+    url.query_pairs_mut().extend_pairs(parameters);
+    Some(url.to_string())
+}
+
+/// Returns the pg_service files in the order they are searched
+fn service_files(var: &dyn Fn(&str) -> Option<String>) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    match var("PGSERVICEFILE") {
+        Some(file) => files.push(PathBuf::from(file)),
+        None => {
+            if let Some(home) = dirs::home_dir() {
+                files.push(home.join(".pg_service.conf"));
+            }
+        }
+    }
+    let sysconfdir = var("PGSYSCONFDIR").unwrap_or_else(|| "/etc/postgresql-common".to_string());
+    files.push(Path::new(&sysconfdir).join("pg_service.conf"));
+    files
+}
+
+/// Returns the parameters of a service in a pg_service file, which is an INI file with a section
+/// per service
+fn read_service(file: &Path, service: &str) -> Option<Vec<(String, String)>> {
+    let text = std::fs::read_to_string(file).ok()?;
+    parse_service(&text, service)
+}
+
+fn parse_service(text: &str, service: &str) -> Option<Vec<(String, String)>> {
+    let mut parameters = None;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if parameters.is_some() {
+                break;
+            }
+            if section.trim() == service {
+                parameters = Some(Vec::new());
+            }
+        } else if let (Some(parameters), Some((key, value))) =
+            (parameters.as_mut(), line.split_once('='))
+        {
+            let key = key.trim();
+            if SERVICE_PARAMETERS.contains(&key) {
+                parameters.push((key.to_string(), value.trim().to_string()));
+            }
+        }
+    }
+    parameters
+}
+
+/// Parses the variables of a `.env` file, e.g. `export DATABASE_URL="postgres://..."`
+fn parse_dotenv(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+            let value = match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default(),
+                _ => value.split(" #").next().unwrap_or_default().trim_end(),
+            };
+            Some((key.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{parse_dotenv, parse_service, resolve};
+
+    #[test]
+    fn test_resolve() {
+        let dir = std::env::temp_dir().join(format!("pglsp-connection-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let service_file = dir.join("pg_service.conf");
+        std::fs::write(
+            &service_file,
+            "[staging]\nhost=db.staging\n# a comment\ndbname = app\nconnect_timeout=10\n\n\
+             [local]\nhost=/var/run/postgresql\n",
+        )
+        .unwrap();
+
+        let resolve_with = |vars: &[(&str, &str)]| {
+            let mut vars: HashMap<String, String> = vars
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            vars.insert(
+                "PGSERVICEFILE".to_string(),
+                service_file.to_string_lossy().into_owned(),
+            );
+            vars.insert(
+                "PGSYSCONFDIR".to_string(),
+                dir.to_string_lossy().into_owned(),
+            );
+            resolve(&|name| vars.get(name).cloned())
+        };
+
+        assert_eq!(resolve_with(&[]), None);
+        assert_eq!(resolve_with(&[("PGPASSWORD", "secret")]), None);
+        assert_eq!(
+            resolve_with(&[
+                ("DATABASE_URL", "postgres://localhost/app"),
+                ("PGHOST", "db")
+            ]),
+            Some("postgres://localhost/app".to_string())
+        );
+        assert_eq!(
+            resolve_with(&[("PGHOST", "db"), ("PGUSER", "app user"), ("PGPORT", "6543")]),
+            Some("postgresql://?host=db&port=6543&user=app+user".to_string())
+        );
+        assert_eq!(
+            resolve_with(&[
+                ("PGSERVICE", "staging"),
+                ("PGHOST", "db"),
+                ("PGUSER", "app")
+            ]),
+            Some("postgresql://?host=db.staging&dbname=app&user=app".to_string())
+        );
+        assert_eq!(
+            resolve_with(&[("PGSERVICE", "missing"), ("PGDATABASE", "app")]),
+            Some("postgresql://?dbname=app".to_string())
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse_dotenv(
+                "# the database\nexport DATABASE_URL=\"postgres://localhost/app\" # local\n\
+                 PGUSER=app # the user\nPGPASSWORD='se#cret'\n"
+            ),
+            vec![
+                (
+                    "DATABASE_URL".to_string(),
+                    "postgres://localhost/app".to_string()
+                ),
+                ("PGUSER".to_string(), "app".to_string()),
+                ("PGPASSWORD".to_string(), "se#cret".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse_service("[a]\nhost=a\n[b]\nhost = b\nport=1\n", "b"),
+            Some(vec![
+                ("host".to_string(), "b".to_string()),
+                ("port".to_string(), "1".to_string())
+            ])
+        );
+        assert_eq!(parse_service("[a]\nhost=a\n", "b"), None);
+    }
+}
@@ -0,0 +1,381 @@
+//! A parser for the subset of TOML that `pglsp.toml` needs, which converts a document into the
+//! JSON value of the settings
+//!
+//! Tables, arrays of tables, dotted and quoted keys, single-line strings, integers, floats,
+//! booleans, arrays and inline tables are supported. Multi-line strings and dates are not.
+
+use serde_json::{Map, Number, Value};
+
+/// Parses a TOML document, or returns an error with the line it occurred in
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut parser = Parser { text, pos: 0 };
+    parser
+        .document()
+        .map(Value::Object)
+        .map_err(|err| format!("line {}: {}", parser.line(), err))
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn document(&mut self) -> Result<Map<String, Value>, String> {
+        let mut root = Map::new();
+        let mut current = Vec::new();
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None => return Ok(root),
+                Some('[') if self.rest().starts_with("[[") => {
+                    self.pos += 2;
+                    let mut keys = self.key()?;
+                    self.expect("]]")?;
+                    let last = keys.pop().unwrap();
+                    let parent = table_mut(&mut root, &keys)?;
+                    match parent
+                        .entry(last.clone())
+                        .or_insert(Value::Array(Vec::new()))
+                    {
+                        Value::Array(tables) => tables.push(Value::Object(Map::new())),
+                        _ => return Err(format!("`{}` is not an array of tables", last)),
+                    }
+                    keys.push(last);
+                    current = keys;
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    let keys = self.key()?;
+                    self.expect("]")?;
+                    table_mut(&mut root, &keys)?;
+                    current = keys;
+                }
+                Some(_) => {
+                    let table = table_mut(&mut root, &current)?;
+                    self.key_value(table)?;
+                }
+            }
+            self.skip_whitespace();
+            self.skip_comment();
+            match self.peek() {
+                None => {}
+                Some('\n') => self.pos += 1,
+                Some('\r') if self.rest().starts_with("\r\n") => self.pos += 2,
+                Some(c) => return Err(format!("Expected the end of the line, found `{}`", c)),
+            }
+        }
+    }
+
+    /// Parses `key = value` into a table, where a dotted key creates the tables it names
+    fn key_value(&mut self, table: &mut Map<String, Value>) -> Result<(), String> {
+        let mut keys = self.key()?;
+        self.expect("=")?;
+        self.skip_whitespace();
+        let value = self.value()?;
+        let last = keys.pop().unwrap();
+        let table = table_mut(table, &keys)?;
+        if table.contains_key(&last) {
+            return Err(format!("Duplicate key `{}`", last));
+        }
+        table.insert(last, value);
+        Ok(())
+    }
+
+    /// Parses a key and returns its parts, e.g. `a`, `b` and `c` of `a."b".c`
+    fn key(&mut self) -> Result<Vec<String>, String> {
+        let mut keys = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let key = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let len = self
+                        .rest()
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                        .unwrap_or(self.rest().len());
+                    if len == 0 {
+                        return Err("Expected a key".to_string());
+                    }
+                    self.pos += len;
+                    self.text[self.pos - len..self.pos].to_string()
+                }
+            };
+            keys.push(key);
+            self.skip_whitespace();
+            if self.peek() != Some('.') {
+                return Ok(keys);
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') if self.rest().starts_with("\"\"\"") => {
+                Err("Multi-line strings are not supported".to_string())
+            }
+            Some('\'') if self.rest().starts_with("'''") => {
+                Err("Multi-line strings are not supported".to_string())
+            }
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            _ => self.scalar(),
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut values = Vec::new();
+        loop {
+            self.skip_trivia();
+            if self.peek() == Some(']') {
+                break;
+            }
+            values.push(self.value()?);
+            self.skip_trivia();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => break,
+                _ => return Err("Expected `,` or `]` in array".to_string()),
+            }
+        }
+        self.pos += 1;
+        Ok(Value::Array(values))
+    }
+
+    fn inline_table(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut table = Map::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Value::Object(table));
+        }
+        loop {
+            self.key_value(&mut table)?;
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => break,
+                _ => return Err("Expected `,` or `}` in inline table".to_string()),
+            }
+        }
+        self.pos += 1;
+        Ok(Value::Object(table))
+    }
+
+    /// Parses a boolean or a number
+    fn scalar(&mut self) -> Result<Value, String> {
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '.')))
+            .unwrap_or(self.rest().len());
+        let token = &self.text[self.pos..self.pos + len];
+        let value = match token {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => {
+                let number = token.replace('_', "");
+                if let Ok(int) = number.parse::<i64>() {
+                    Value::from(int)
+                } else if let Some(float) = number
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|_| number.contains(|c: char| c.is_ascii_digit()))
+                    .and_then(Number::from_f64)
+                {
+                    Value::Number(float)
+                } else if token.is_empty() {
+                    return Err("Expected a value".to_string());
+                } else {
+                    return Err(format!("Invalid value `{}`", token));
+                }
+            }
+        };
+        self.pos += len;
+        Ok(value)
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut result = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += idx + 1;
+                    return Ok(result);
+                }
+                '\\' => {
+                    let escaped = match chars.next().map(|(_, c)| c) {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some(u @ ('u' | 'U')) => {
+                            let len = if u == 'u' { 4 } else { 8 };
+                            let hex = chars.by_ref().take(len).map(|(_, c)| c);
+                            u32::from_str_radix(&hex.collect::<String>(), 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or("Invalid unicode escape")?
+                        }
+                        _ => return Err("Invalid escape in string".to_string()),
+                    };
+                    result.push(escaped);
+                }
+                '\n' => break,
+                c => result.push(c),
+            }
+        }
+        Err("Unterminated string".to_string())
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        match self.rest().find(['\'', '\n']) {
+            Some(len) if self.rest()[len..].starts_with('\'') => {
+                let result = self.rest()[..len].to_string();
+                self.pos += len + 1;
+                Ok(result)
+            }
+            _ => Err("Unterminated string".to_string()),
+        }
+    }
+
+    /// Skips whitespace, comments and newlines
+    fn skip_trivia(&mut self) {
+        loop {
+            self.skip_whitespace();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n' | '\r') => self.pos += 1,
+                _ => return,
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t']).len();
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            self.pos += self.rest().find('\n').unwrap_or(self.rest().len());
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        self.skip_whitespace();
+        if !self.rest().starts_with(token) {
+            return Err(format!("Expected `{}`", token));
+        }
+        self.pos += token.len();
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    /// The 1-based line of the current position
+    fn line(&self) -> usize {
+        self.text[..self.pos].matches('\n').count() + 1
+    }
+}
+
+/// Returns the table at a path of keys, creating the tables that do not exist. The last table of
+/// an array of tables is used.
+fn table_mut<'a>(
+    mut table: &'a mut Map<String, Value>,
+    keys: &[String],
+) -> Result<&'a mut Map<String, Value>, String> {
+    for key in keys {
+        let value = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+        table = match value {
+            Value::Object(table) => table,
+            Value::Array(tables) => match tables.last_mut() {
+                Some(Value::Object(table)) => table,
+                _ => return Err(format!("`{}` is not a table", key)),
+            },
+            _ => return Err(format!("`{}` is not a table", key)),
+        };
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::parse;
+
+    #[test]
+    fn test_parse() {
+        let text = r#"
+# the connection of all files without one of their own
+dbConnectionString = "postgres://postgres@localhost:5432/postgres"
+schemaFiles = [
+    'db/structure.sql', # a comment
+    "db\\seeds.sql",
+]
+inlayHints = { functionArgs = false, parameterTypes = true }
+diagnostics.idleDelay = 1_000
+
+[lint]
+"ban-drop-column" = "error"
+
+[[connections]]
+name = "billing"
+paths = ["services/billing"]
+
+[[connections]]
+name = "audit"
+
+[execution]
+timeout = 2.5
+"#;
+        assert_eq!(
+            parse(text).unwrap(),
+            json!({
+                "dbConnectionString": "postgres://postgres@localhost:5432/postgres",
+                "schemaFiles": ["db/structure.sql", "db\\seeds.sql"],
+                "inlayHints": { "functionArgs": false, "parameterTypes": true },
+                "diagnostics": { "idleDelay": 1000 },
+                "lint": { "ban-drop-column": "error" },
+                "connections": [
+                    { "name": "billing", "paths": ["services/billing"] },
+                    { "name": "audit" }
+                ],
+                "execution": { "timeout": 2.5 }
+            })
+        );
+
+        assert_eq!(
+            parse("a = 1\na = 2"),
+            Err("line 2: Duplicate key `a`".to_string())
+        );
+        assert_eq!(
+            parse("[files]\ninclude = [\"migrations\"\nignore = []"),
+            Err("line 3: Expected `,` or `]` in array".to_string())
+        );
+        assert_eq!(
+            parse("a = \"b\" c"),
+            Err("line 1: Expected the end of the line, found `c`".to_string())
+        );
+    }
+}
@@ -0,0 +1,610 @@
+//! A long-running process per working directory that keeps the connections and schema caches of
+//! `pglsp check` warm, so that neither checks nor language servers start cold
+//!
+//! The daemon listens on a unix socket in a directory that only its user can access, since the
+//! requests contain connection strings. Every request is a line of JSON on a connection of its
+//! own, which is answered with a line of JSON. Checks run in the daemon only if the connection
+//! variables of the environment, e.g. `DATABASE_URL`, are those the daemon was started with, and
+//! their output is sent back to the command that requested them.
+//!
+//! The schema caches are reloaded in the background whenever they are used, so that a check sees
+//! the schema as of the end of the previous one. `pglsp check --reload-schema-cache` reloads them
+//! before checking instead.
+
+use std::{
+    ffi::OsString,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::{
+        fs::{DirBuilderExt, MetadataExt},
+        net::{UnixListener, UnixStream},
+        process::CommandExt,
+    },
+    path::{Path, PathBuf},
+    process::{Command, ExitCode, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use async_std::task;
+use pg_schema_cache::SchemaCache;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::{
+    cli::{
+        check::{self, Connections},
+        flags,
+    },
+    config::connection_environment,
+    db::{self, ConnectionSettings, Tunnel},
+    server::options::Options,
+};
+
+/// How long `pglsp daemon start` waits for the daemon to listen
+const START_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the language server waits for a schema cache of the daemon before it loads its own
+const SCHEMA_CACHE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Request {
+    Status,
+    Stop,
+    /// Runs `pglsp check` with the arguments and the connection variables of the command line
+    Check {
+        args: Vec<String>,
+        env: Vec<(String, String)>,
+    },
+    /// Returns the schema cache of a connection if the daemon has it loaded
+    SchemaCache {
+        connection_string: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Response {
+    Status {
+        pid: u32,
+        uptime: u64,
+        connections: usize,
+        checks: usize,
+    },
+    Stopped,
+    Check(CheckOutput),
+    SchemaCache {
+        schema_cache: Option<Box<SchemaCache>>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// The output of a check that ran in the daemon
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: u8,
+}
+
+/// Runs `pglsp daemon`
+pub fn command(flags: flags::Daemon) -> anyhow::Result<ExitCode> {
+    let root = std::env::current_dir()?;
+    match flags.subcommand {
+        flags::DaemonCmd::Start(flags) if flags.foreground => {
+            serve(root)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        flags::DaemonCmd::Start(_) => start(&root),
+        flags::DaemonCmd::Stop(_) => match request(&root, &Request::Stop, None) {
+            Ok(_) => {
+                println!("Stopped the daemon in {}", root.display());
+                Ok(ExitCode::SUCCESS)
+            }
+            Err(_) => {
+                println!("No daemon runs in {}", root.display());
+                Ok(ExitCode::FAILURE)
+            }
+        },
+        flags::DaemonCmd::Status(_) => match request(&root, &Request::Status, None) {
+            Ok(Response::Status {
+                pid,
+                uptime,
+                connections,
+                checks,
+            }) => {
+                println!(
+                    "The daemon in {} runs with pid {} since {}s",
+                    root.display(),
+                    pid,
+                    uptime
+                );
+                println!("Connections: {}", connections);
+                println!("Checks: {}", checks);
+                Ok(ExitCode::SUCCESS)
+            }
+            _ => {
+                println!("No daemon runs in {}", root.display());
+                Ok(ExitCode::FAILURE)
+            }
+        },
+    }
+}
+
+/// Runs a check in the daemon of the root, or returns `None` if there is no daemon or the check
+/// could not be run in it, e.g. since it would connect to another database than in this process
+pub fn check(root: &Path, args: &[OsString]) -> Option<CheckOutput> {
+    let args = args
+        .iter()
+        .map(|arg| arg.to_str().map(String::from))
+        .collect::<Option<Vec<_>>>()?;
+    let env = connection_environment();
+    match request(root, &Request::Check { args, env }, None) {
+        Ok(Response::Check(output)) => Some(output),
+        _ => None,
+    }
+}
+
+/// Returns the schema cache of a connection from the daemon of the root, if it has it loaded
+pub fn schema_cache(root: &Path, connection_string: &str) -> Option<SchemaCache> {
+    let request = Request::SchemaCache {
+        connection_string: connection_string.to_string(),
+    };
+    match self::request(root, &request, Some(SCHEMA_CACHE_TIMEOUT)) {
+        Ok(Response::SchemaCache {
+            schema_cache: Some(mut schema_cache),
+        }) => {
+            schema_cache.intern();
+            Some(*schema_cache)
+        }
+        _ => None,
+    }
+}
+
+/// The socket of the daemon of a root, which is named after a hash of the root since the length
+/// of socket paths is limited
+fn socket_path(root: &Path) -> PathBuf {
+    let hash = hex::encode(Sha256::digest(root.to_string_lossy().as_bytes()));
+    socket_dir().join(format!("{}.sock", &hash[..16]))
+}
+
+/// The directory of the sockets of the user, within the runtime directory or, since the temporary
+/// directory is shared with other users, a directory of the user within it
+fn socket_dir() -> PathBuf {
+    match dirs::runtime_dir() {
+        Some(dir) => dir.join("pglsp"),
+        None => std::env::temp_dir().join(format!("pglsp-{}", uid())),
+    }
+}
+
+fn uid() -> u32 {
+    // SAFETY: getuid cannot fail
+    unsafe { libc::getuid() }
+}
+
+/// Fails unless the path is owned by the user and, if `private` is set, only they can access it,
+/// since another user could otherwise replace the socket and receive the connection strings
+fn check_owner(path: &Path, private: bool) -> io::Result<()> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    if metadata.uid() != uid() || (private && metadata.mode() & 0o077 != 0) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is accessible to other users", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+fn request(root: &Path, request: &Request, timeout: Option<Duration>) -> io::Result<Response> {
+    let path = socket_path(root);
+    check_owner(&socket_dir(), true)?;
+    check_owner(&path, false)?;
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(timeout)?;
+    serde_json::to_writer(&mut stream, request)?;
+    stream.write_all(b"\n")?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// Starts the daemon of the root in the background and waits until it listens
+fn start(root: &Path) -> anyhow::Result<ExitCode> {
+    if request(root, &Request::Status, None).is_ok() {
+        println!("The daemon already runs in {}", root.display());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut child = Command::new(std::env::current_exe()?)
+        .args(["daemon", "start", "--foreground"])
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // the daemon keeps running when the terminal is closed
+        .process_group(0)
+        .spawn()
+        .context("Could not start the daemon")?;
+
+    let start = Instant::now();
+    while start.elapsed() < START_TIMEOUT {
+        if let Some(status) = child.try_wait()? {
+            anyhow::bail!("The daemon exited with {}", status);
+        }
+        if request(root, &Request::Status, None).is_ok() {
+            println!(
+                "Started the daemon in {} with pid {}",
+                root.display(),
+                child.id()
+            );
+            return Ok(ExitCode::SUCCESS);
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    anyhow::bail!("Timed out starting the daemon")
+}
+
+/// A connection whose pool is kept open and whose schema cache is kept loaded
+struct WarmConnection {
+    connection_string: String,
+    settings: ConnectionSettings,
+    pool: PgPool,
+    schema_cache: SchemaCache,
+    reloading: bool,
+    /// Counts the reloads that were requested, so that a reload that finishes after a newer one
+    /// does not replace its schema cache
+    reloads: u64,
+    _tunnel: Option<Tunnel>,
+}
+
+/// A lock per connection that is held while it is opened, so that it is only opened once
+/// without holding the lock of all connections
+type ConnectingLock = (String, ConnectionSettings, Arc<Mutex<()>>);
+
+struct Daemon {
+    started: Instant,
+    connections: Mutex<Vec<WarmConnection>>,
+    connecting: Mutex<Vec<ConnectingLock>>,
+    checks: AtomicUsize,
+}
+
+/// Runs the daemon of the root until it is stopped
+fn serve(root: PathBuf) -> anyhow::Result<()> {
+    let path = socket_path(&root);
+    if UnixStream::connect(&path).is_ok() {
+        anyhow::bail!("A daemon already runs in {}", root.display());
+    }
+    let dir = socket_dir();
+    match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(err) if err.kind() != io::ErrorKind::AlreadyExists => {
+            return Err(err).with_context(|| format!("Could not create {}", dir.display()));
+        }
+        _ => {}
+    }
+    check_owner(&dir, true)?;
+
+    // the socket of a daemon that did not stop cleanly
+    let _ = std::fs::remove_file(&path);
+    // the socket is created without access for other users, instead of restricting it after
+    // SAFETY: umask cannot fail
+    let umask = unsafe { libc::umask(0o077) };
+    let listener = UnixListener::bind(&path);
+    unsafe { libc::umask(umask) };
+    let listener = listener.with_context(|| format!("Could not bind {}", path.display()))?;
+
+    let daemon = Arc::new(Daemon::new());
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let daemon = daemon.clone();
+        let path = path.clone();
+        thread::spawn(move || {
+            let _ = daemon.handle(stream, &path);
+        });
+    }
+    Ok(())
+}
+
+impl Daemon {
+    fn new() -> Daemon {
+        Daemon {
+            started: Instant::now(),
+            connections: Mutex::new(Vec::new()),
+            connecting: Mutex::new(Vec::new()),
+            checks: AtomicUsize::new(0),
+        }
+    }
+
+    fn handle(self: &Arc<Self>, stream: UnixStream, path: &Path) -> anyhow::Result<()> {
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line)?;
+        let request: Request = serde_json::from_str(&line)?;
+        let stop = matches!(request, Request::Stop);
+        let response = match request {
+            Request::Status => Response::Status {
+                pid: std::process::id(),
+                uptime: self.started.elapsed().as_secs(),
+                connections: self.connections.lock().unwrap().len(),
+                checks: self.checks.load(Ordering::Relaxed),
+            },
+            Request::Stop => Response::Stopped,
+            Request::Check { args, env } => match self.check(args, env) {
+                Ok(output) => Response::Check(output),
+                Err(err) => Response::Error {
+                    message: format!("{:#}", err),
+                },
+            },
+            Request::SchemaCache { connection_string } => {
+                let mut connections = self.connections.lock().unwrap();
+                let connection = connections
+                    .iter_mut()
+                    .find(|c| c.connection_string == connection_string);
+                Response::SchemaCache {
+                    schema_cache: connection.map(|connection| {
+                        self.reload(connection);
+                        Box::new(connection.schema_cache.clone())
+                    }),
+                }
+            }
+        };
+
+        let mut writer = &stream;
+        serde_json::to_writer(&mut writer, &response)?;
+        writer.write_all(b"\n")?;
+        if stop {
+            // the tunnels are closed when the connections are dropped
+            self.connections.lock().unwrap().clear();
+            let _ = std::fs::remove_file(path);
+            std::process::exit(0);
+        }
+        Ok(())
+    }
+
+    fn check(
+        self: &Arc<Self>,
+        args: Vec<String>,
+        env: Vec<(String, String)>,
+    ) -> anyhow::Result<CheckOutput> {
+        if env != connection_environment() {
+            anyhow::bail!("The connection variables differ from those of the daemon");
+        }
+        let args = args.into_iter().map(OsString::from).collect();
+        let flags::PglspCmd::Check(flags) = flags::Pglsp::from_vec(args)?.subcommand else {
+            anyhow::bail!("Only checks run in the daemon");
+        };
+        self.checks.fetch_add(1, Ordering::Relaxed);
+        let reload = flags.reload_schema_cache;
+
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        // checks with metrics do not run in the daemon, which collects no timings
+        let exit_code = check::run(
+            flags,
+            &mut stdout,
+            &mut stderr,
+            &|options| self.connect(options, reload),
+            None,
+        );
+        Ok(CheckOutput {
+            stdout: String::from_utf8_lossy(&stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+            exit_code,
+        })
+    }
+
+    /// Returns the warm connections of a check, opening those that are not open yet and reloading
+    /// the schema caches of the others first if `reload` is set
+    fn connect(self: &Arc<Self>, options: &Options, reload: bool) -> anyhow::Result<Connections> {
+        let settings = ConnectionSettings::new(options);
+        let mut connections = Connections::default();
+        for (name, connection_string) in options.connection_strings() {
+            let (pool, schema_cache) = self
+                .warm_connection(&connection_string, &settings, reload)
+                .with_context(|| format!("Could not connect to the {} database", name))?;
+            connections.schema_caches.insert(name.clone(), schema_cache);
+            connections.pools.insert(name, pool);
+        }
+        connections.load_offline_schema_cache(options)?;
+        Ok(connections)
+    }
+
+    fn warm_connection(
+        self: &Arc<Self>,
+        connection_string: &str,
+        settings: &ConnectionSettings,
+        reload: bool,
+    ) -> anyhow::Result<(PgPool, SchemaCache)> {
+        if let Some(warm) = self.open_connection(connection_string, settings, reload) {
+            return Ok(warm);
+        }
+
+        let lock = {
+            let mut connecting = self.connecting.lock().unwrap();
+            match connecting
+                .iter()
+                .find(|(c, s, _)| c == connection_string && s == settings)
+            {
+                Some((_, _, lock)) => lock.clone(),
+                None => {
+                    let lock = Arc::new(Mutex::new(()));
+                    connecting.push((
+                        connection_string.to_string(),
+                        settings.clone(),
+                        lock.clone(),
+                    ));
+                    lock
+                }
+            }
+        };
+        let _connecting = lock.lock().unwrap();
+        // the schema of a connection that was opened while waiting is fresh already
+        if let Some(warm) = self.open_connection(connection_string, settings, false) {
+            return Ok(warm);
+        }
+
+        let (pool, tunnel) = task::block_on(db::connect(connection_string, settings))?;
+        let schema_cache = task::block_on(SchemaCache::load(&pool));
+        self.connections.lock().unwrap().push(WarmConnection {
+            connection_string: connection_string.to_string(),
+            settings: settings.clone(),
+            pool: pool.clone(),
+            schema_cache: schema_cache.clone(),
+            reloading: false,
+            reloads: 0,
+            _tunnel: tunnel,
+        });
+        Ok((pool, schema_cache))
+    }
+
+    /// Returns the pool and schema cache of a connection if it is open, reloading its schema
+    /// cache first if `reload` is set and in the background otherwise
+    fn open_connection(
+        self: &Arc<Self>,
+        connection_string: &str,
+        settings: &ConnectionSettings,
+        reload: bool,
+    ) -> Option<(PgPool, SchemaCache)> {
+        let mut connections = self.connections.lock().unwrap();
+        let connection = connections
+            .iter_mut()
+            .find(|c| c.connection_string == connection_string && &c.settings == settings)?;
+        if !reload {
+            self.reload(connection);
+            return Some((connection.pool.clone(), connection.schema_cache.clone()));
+        }
+
+        // other checks keep using the connection while its schema is loaded
+        connection.reloads += 1;
+        let reloads = connection.reloads;
+        let pool = connection.pool.clone();
+        drop(connections);
+        let schema_cache = task::block_on(SchemaCache::load(&pool));
+        let mut connections = self.connections.lock().unwrap();
+        if let Some(connection) = connections
+            .iter_mut()
+            .find(|c| c.connection_string == connection_string && &c.settings == settings)
+            .filter(|c| c.reloads == reloads)
+        {
+            connection.schema_cache = schema_cache.clone();
+        }
+        Some((pool, schema_cache))
+    }
+
+    /// Reloads the schema cache of a connection in the background, unless it is being reloaded
+    fn reload(self: &Arc<Self>, connection: &mut WarmConnection) {
+        if connection.reloading {
+            return;
+        }
+        connection.reloading = true;
+        connection.reloads += 1;
+        let reloads = connection.reloads;
+
+        let daemon = self.clone();
+        let pool = connection.pool.clone();
+        let connection_string = connection.connection_string.clone();
+        let settings = connection.settings.clone();
+        task::spawn(async move {
+            let schema_cache = SchemaCache::load(&pool).await;
+            let mut connections = daemon.connections.lock().unwrap();
+            if let Some(connection) = connections
+                .iter_mut()
+                .find(|c| c.connection_string == connection_string && c.settings == settings)
+            {
+                if connection.reloads == reloads {
+                    connection.schema_cache = schema_cache;
+                }
+                connection.reloading = false;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::fs::PermissionsExt, path::Path};
+
+    use async_std::task;
+
+    use super::{check_owner, socket_dir, socket_path, Daemon, Ordering};
+    use crate::db::ConnectionSettings;
+
+    #[test]
+    fn test_socket_path() {
+        let path = socket_path(Path::new("/home/user/projects/app"));
+        assert_eq!(path, socket_path(Path::new("/home/user/projects/app")));
+        assert_ne!(path, socket_path(Path::new("/home/user/projects/other")));
+        assert!(path.to_str().unwrap().len() < 108);
+        assert_eq!(path.parent(), Some(socket_dir().as_path()));
+    }
+
+    #[test]
+    fn test_check_owner() {
+        let dir = std::env::temp_dir().join(format!("pglsp-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let permissions = |mode| std::fs::Permissions::from_mode(mode);
+
+        std::fs::set_permissions(&dir, permissions(0o755)).unwrap();
+        assert!(check_owner(&dir, false).is_ok());
+        assert!(check_owner(&dir, true).is_err());
+        std::fs::set_permissions(&dir, permissions(0o700)).unwrap();
+        assert!(check_owner(&dir, true).is_ok());
+        assert!(check_owner(&dir.join("missing.sock"), false).is_err());
+
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_environment() {
+        let daemon = std::sync::Arc::new(Daemon::new());
+        let args = vec!["check".to_string(), "--no-daemon".to_string()];
+        let env = vec![(
+            "DATABASE_URL".to_string(),
+            "postgresql://other@localhost/other".to_string(),
+        )];
+        let err = daemon.check(args, env).unwrap_err();
+        assert!(err.to_string().contains("connection variables"));
+        assert_eq!(daemon.checks.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_warm_connection_reload() {
+        let connection_string = std::env::var("DB_CONNECTION_STRING").unwrap();
+        let settings = ConnectionSettings::default();
+        let daemon = std::sync::Arc::new(Daemon::new());
+        let has_table = |reload: bool| {
+            let (_, schema_cache) = daemon
+                .warm_connection(&connection_string, &settings, reload)
+                .unwrap();
+            schema_cache
+                .tables
+                .iter()
+                .any(|t| t.name == "daemon_reload_test")
+        };
+
+        let (pool, _) = daemon
+            .warm_connection(&connection_string, &settings, false)
+            .unwrap();
+        task::block_on(async {
+            sqlx::query("drop table if exists daemon_reload_test")
+                .execute(&pool)
+                .await
+                .unwrap();
+            sqlx::query("create table daemon_reload_test (id int)")
+                .execute(&pool)
+                .await
+                .unwrap();
+        });
+        assert!(has_table(true));
+
+        task::block_on(sqlx::query("drop table daemon_reload_test").execute(&pool)).unwrap();
+        assert!(!has_table(true));
+    }
+}
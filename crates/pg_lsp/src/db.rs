@@ -0,0 +1,201 @@
+//! Opens the database connections of the language server and `pglsp check` with the TLS settings
+//! of the options, and through their SSH tunnel if there is one
+//!
+//! The tunnel is a `ssh -L` process that forwards a local port to the database. It lives as long
+//! as the connection that opened it.
+
+use std::{
+    io::Read,
+    net::TcpListener,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use async_std::{net::TcpStream, task};
+use sqlx::{
+    postgres::{PgConnectOptions, PgSslMode},
+    PgPool,
+};
+
+use crate::server::options::{Options, SshTunnelOptions, SslMode};
+
+/// How long to wait for `ssh` to forward the local port
+const TUNNEL_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// The settings all connections are opened with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectionSettings {
+    pub ssl_mode: Option<SslMode>,
+    pub ssl_root_cert: Option<PathBuf>,
+    pub ssh_tunnel: Option<SshTunnelOptions>,
+}
+
+impl ConnectionSettings {
+    pub fn new(options: &Options) -> ConnectionSettings {
+        ConnectionSettings {
+            ssl_mode: options.ssl_mode,
+            ssl_root_cert: options.ssl_root_cert.clone(),
+            ssh_tunnel: options.ssh_tunnel.clone(),
+        }
+    }
+}
+
+/// An open SSH tunnel, which is closed when it is dropped
+#[derive(Debug)]
+pub struct Tunnel {
+    child: Child,
+}
+
+impl Drop for Tunnel {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Opens a connection pool, together with the tunnel it connects through
+pub async fn connect(
+    connection_string: &str,
+    settings: &ConnectionSettings,
+) -> anyhow::Result<(PgPool, Option<Tunnel>)> {
+    let mut options = PgConnectOptions::from_str(connection_string)?;
+    if let Some(ssl_mode) = settings.ssl_mode {
+        options = options.ssl_mode(pg_ssl_mode(ssl_mode));
+    }
+    if let Some(cert) = &settings.ssl_root_cert {
+        options = options.ssl_root_cert(cert);
+    }
+
+    let tunnel = match &settings.ssh_tunnel {
+        Some(ssh_tunnel) => {
+            let host = options.get_host().to_string();
+            if host.starts_with('/') {
+                anyhow::bail!("A connection over a unix socket cannot use an SSH tunnel");
+            }
+            // the tunnel connects to 127.0.0.1, which the certificate of the database is not for
+            if matches!(options.get_ssl_mode(), PgSslMode::VerifyFull) {
+                anyhow::bail!(
+                    "A connection through an SSH tunnel cannot verify the host name of the \
+                     database, use the sslMode verify-ca instead of verify-full"
+                );
+            }
+            let (tunnel, local_port) = open_tunnel(ssh_tunnel, &host, options.get_port()).await?;
+            options = options.host("127.0.0.1").port(local_port);
+            Some(tunnel)
+        }
+        None => None,
+    };
+
+    let pool = PgPool::connect_with(options).await?;
+    Ok((pool, tunnel))
+}
+
+fn pg_ssl_mode(ssl_mode: SslMode) -> PgSslMode {
+    match ssl_mode {
+        SslMode::Disable => PgSslMode::Disable,
+        SslMode::Allow => PgSslMode::Allow,
+        SslMode::Prefer => PgSslMode::Prefer,
+        SslMode::Require => PgSslMode::Require,
+        SslMode::VerifyCa => PgSslMode::VerifyCa,
+        SslMode::VerifyFull => PgSslMode::VerifyFull,
+    }
+}
+
+/// Starts `ssh` to forward a free local port to the database, and returns once the port accepts
+/// connections
+async fn open_tunnel(
+    options: &SshTunnelOptions,
+    host: &str,
+    port: u16,
+) -> anyhow::Result<(Tunnel, u16)> {
+    let local_port = TcpListener::bind(("127.0.0.1", 0))?.local_addr()?.port();
+
+    let mut command = Command::new("ssh");
+    command
+        .args([
+            "-N",
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            "ExitOnForwardFailure=yes",
+        ])
+        .arg("-L")
+        .arg(format!("127.0.0.1:{}:{}:{}", local_port, host, port))
+        .arg("-p")
+        .arg(options.port.to_string());
+    if let Some(key) = &options.key {
+        command.arg("-i").arg(key);
+    }
+    command.arg(match &options.user {
+        Some(user) => format!("{}@{}", user, options.host),
+        None => options.host.clone(),
+    });
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Could not start ssh")?;
+    let mut tunnel = Tunnel { child };
+
+    let start = Instant::now();
+    loop {
+        if let Some(status) = tunnel.child.try_wait()? {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = tunnel.child.stderr.take() {
+                let _ = pipe.read_to_string(&mut stderr);
+            }
+            anyhow::bail!(
+                "The SSH tunnel to {} failed ({}): {}",
+                options.host,
+                status,
+                stderr.trim()
+            );
+        }
+        if TcpStream::connect(("127.0.0.1", local_port)).await.is_ok() {
+            return Ok((tunnel, local_port));
+        }
+        if start.elapsed() > TUNNEL_TIMEOUT {
+            anyhow::bail!("Timed out opening the SSH tunnel to {}", options.host);
+        }
+        task::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::task;
+
+    use crate::server::options::{SshTunnelOptions, SslMode};
+
+    use super::{connect, ConnectionSettings};
+
+    #[test]
+    fn test_tunnel_verify_full() {
+        let settings = ConnectionSettings {
+            ssl_mode: Some(SslMode::VerifyFull),
+            ssh_tunnel: Some(SshTunnelOptions {
+                host: "bastion.internal".to_string(),
+                port: 22,
+                user: None,
+                key: None,
+            }),
+            ..Default::default()
+        };
+        let error = task::block_on(connect("postgres://db.internal/app", &settings)).unwrap_err();
+        assert!(error.to_string().contains("verify-ca"));
+
+        let error = task::block_on(connect(
+            "postgres://db.internal/app?sslmode=verify-full",
+            &ConnectionSettings {
+                ssl_mode: None,
+                ..settings
+            },
+        ))
+        .unwrap_err();
+        assert!(error.to_string().contains("verify-ca"));
+    }
+}
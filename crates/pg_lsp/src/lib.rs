@@ -1,3 +1,8 @@
+pub mod cli;
 mod client;
+mod config;
+#[cfg(unix)]
+mod daemon;
+mod db;
 pub mod server;
 mod utils;
@@ -1,10 +1,5 @@
-use lsp_server::Connection;
-use pg_lsp::server::Server;
+use std::process::ExitCode;
 
-fn main() -> anyhow::Result<()> {
-    let (connection, threads) = Connection::stdio();
-    Server::init(connection)?;
-    threads.join()?;
-
-    Ok(())
+fn main() -> anyhow::Result<ExitCode> {
+    pg_lsp::cli::run()
 }
@@ -1,69 +1,134 @@
+mod cancellation;
+mod connection_status;
 mod debouncer;
 mod dispatch;
+mod embedded;
 pub mod options;
+mod progress;
+mod schema_refreshes;
 
 use async_std::task::{self};
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use lsp_server::{Connection, ErrorCode, Message, RequestId};
 use lsp_types::{
     notification::{
-        DidChangeConfiguration, DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
-        DidSaveTextDocument, Notification as _, PublishDiagnostics, ShowMessage,
+        Cancel, DidChangeConfiguration, DidChangeTextDocument, DidChangeWatchedFiles,
+        DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument, Notification as _,
+        PublishDiagnostics, ShowMessage, WorkDoneProgressCancel,
     },
     request::{
-        CodeActionRequest, Completion, ExecuteCommand, HoverRequest, InlayHintRequest,
-        RegisterCapability, WorkspaceConfiguration,
+        CodeActionRequest, CodeLensRequest, Completion, DocumentDiagnosticRequest,
+        DocumentSymbolRequest, ExecuteCommand, FoldingRangeRequest, GotoDefinition, HoverRequest,
+        InlayHintRequest, LinkedEditingRange, PrepareRenameRequest, References, RegisterCapability,
+        Rename, SelectionRangeRequest, SemanticTokensFullDeltaRequest, SemanticTokensFullRequest,
+        SemanticTokensRangeRequest, UnregisterCapability, WorkspaceConfiguration,
+        WorkspaceDiagnosticRefresh, WorkspaceDiagnosticRequest, WorkspaceSymbolRequest,
     },
     CompletionList, CompletionOptions, ConfigurationItem, ConfigurationParams,
-    DidChangeConfigurationParams, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
-    DidOpenTextDocumentParams, DidSaveTextDocumentParams, ExecuteCommandOptions,
-    ExecuteCommandParams, HoverProviderCapability, InitializeParams, InitializeResult,
-    PublishDiagnosticsParams, Registration, RegistrationParams, SaveOptions, ServerCapabilities,
-    ServerInfo, ShowMessageParams, TextDocumentSyncCapability, TextDocumentSyncKind,
-    TextDocumentSyncOptions, TextDocumentSyncSaveOptions,
+    DidChangeConfigurationParams, DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
+    DidChangeWatchedFilesRegistrationOptions, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DidSaveTextDocumentParams, DocumentSymbolParams,
+    DocumentSymbolResponse, ExecuteCommandOptions, ExecuteCommandParams, FileChangeType,
+    FileSystemWatcher, GlobPattern, GotoDefinitionParams, GotoDefinitionResponse,
+    HoverProviderCapability, InitializeParams, InitializeResult, PublishDiagnosticsParams,
+    ReferenceParams, Registration, RegistrationParams, RenameOptions, RenameParams, SaveOptions,
+    ServerCapabilities, ServerInfo, ShowMessageParams, TextDocumentPositionParams,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
+    TextDocumentSyncSaveOptions, TextEdit, Unregistration, UnregistrationParams, WorkspaceEdit,
+    WorkspaceSymbolParams, WorkspaceSymbolResponse,
+};
+use pg_base_db::{Change, Document, DocumentChange, PgLspPath};
+use pg_commands::{
+    Command, CommandType, ExecuteOptions, ExecuteResult, ExecuteStatementCommand,
+    ExplainStatementCommand,
 };
-use pg_base_db::{Change, DocumentChange};
-use pg_commands::{Command, CommandType, ExecuteStatementCommand};
 use pg_completions::CompletionParams;
 use pg_hover::HoverParams;
-use pg_schema_cache::SchemaCache;
-use pg_workspace::Workspace;
+use pg_schema_cache::{SchemaCache, SchemaCacheClass, SchemaCacheUpdate};
+use pg_workspace::{ConnectionRoutes, FoldingKind, Workspace, DEFAULT_CONNECTION};
 use serde::{de::DeserializeOwned, Serialize};
-use std::{collections::HashSet, sync::Arc, time::Duration};
-use text_size::TextSize;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use text_size::{TextRange, TextSize};
 use threadpool::ThreadPool;
 
 use crate::{
     client::{client_flags::ClientFlags, LspClient},
-    utils::{file_path, from_proto, line_index_ext::LineIndexExt, normalize_uri, to_proto},
+    config::CONFIG_FILE,
+    db::{self, ConnectionSettings, Tunnel},
+    utils::{
+        file_path, from_proto,
+        line_index_ext::{set_position_encoding, LineIndexExt, PositionEncoding},
+        normalize_uri, read_sql_file,
+        semantic_tokens::{self, SemanticTokensCache},
+        sql_files, to_proto,
+    },
 };
 
-use self::{debouncer::EventDebouncer, options::Options};
-use sqlx::{
-    postgres::{PgListener, PgPool},
-    Executor,
+use self::{
+    cancellation::{cancellable, CancellationToken, PendingRequests},
+    connection_status::{
+        backoff, ConnectionState, ConnectionStatus, ConnectionStatusParams, MAX_RECONNECT_ATTEMPTS,
+    },
+    debouncer::EventDebouncer,
+    embedded::{
+        AnalyzeEmbedded, AnalyzeEmbeddedParams, AnalyzeEmbeddedResult, EmbeddedSnippetResult,
+        SnippetMapping,
+    },
+    options::{Options, TypecheckTrigger},
+    progress::ProgressReporter,
+    schema_refreshes::{RefreshedSchemaCache, SchemaRefreshes},
 };
+use sqlx::postgres::{PgListener, PgPool};
+
+/// The number of statements from which the progress of a check is shown
+const PROGRESS_MIN_STATEMENTS: usize = 50;
 
 #[derive(Debug)]
 enum InternalMessage {
     PublishDiagnostics(lsp_types::Url),
-    SetOptions(Options),
-    RefreshSchemaCache,
-    SetSchemaCache(SchemaCache),
+    SetOptions(Box<Options>),
+    /// Refreshes the schema cache of a connection, or of all connections if none is given
+    RefreshSchemaCache(Option<String>),
+    RefreshSchemaCacheClasses(String, HashSet<SchemaCacheClass>),
+    /// Replaces the schema cache of a connection, along with the sequence number of the refresh
+    /// that loaded it, if any
+    SetSchemaCache(String, Option<u64>, Box<SchemaCache>),
+    /// Adds the objects of schemas that were loaded on demand to the schema cache of a connection
+    MergeSchemas(String, Box<SchemaCacheUpdate>),
+    /// Replaces the objects of the classes that a refresh with the given sequence number loaded
+    MergeSchemaCacheClasses(String, u64, Box<SchemaCacheUpdate>),
+    /// A connection that failed before was established by a retry
+    Connected(String, DbConnection),
+    /// The connection was lost, e.g. because the database restarted
+    ConnectionLost(String),
+    /// Closes and opens all connections again
+    Reconnect,
 }
 
 #[derive(Debug)]
 struct DbConnection {
     pub pool: PgPool,
     connection_string: String,
+    /// The SSH tunnel the pool connects through, which is closed with the connection
+    _tunnel: Option<Tunnel>,
 }
 
 impl DbConnection {
-    pub async fn new(connection_string: &str) -> Result<Self, sqlx::Error> {
-        let pool = PgPool::connect(connection_string).await?;
+    pub async fn new(
+        connection_string: &str,
+        settings: &ConnectionSettings,
+    ) -> anyhow::Result<Self> {
+        let (pool, tunnel) = db::connect(connection_string, settings).await?;
         Ok(Self {
             pool,
             connection_string: connection_string.to_owned(),
+            _tunnel: tunnel,
         })
     }
 }
@@ -76,8 +141,32 @@ pub struct Server {
     pool: Arc<ThreadPool>,
     client_flags: Arc<ClientFlags>,
     ide: Arc<Workspace>,
-    db_conn: Option<DbConnection>,
-    compute_debouncer: EventDebouncer<Option<PgPool>>,
+    /// The open database connections by name
+    db_conns: HashMap<String, DbConnection>,
+    /// The connection statements are executed on, if not the one of their file
+    execution_conn: Option<DbConnection>,
+    /// The root of the workspace, which relative connection paths are resolved against
+    root: Option<PathBuf>,
+    refresh_task: Option<task::JoinHandle<()>>,
+    /// The connections that are retried in the background, along with their connection string
+    reconnect_tasks: HashMap<String, (String, task::JoinHandle<()>)>,
+    options: Options,
+    /// The settings the client sent last, which are layered over the project configuration again
+    /// when it changes
+    settings: serde_json::Value,
+    /// The connections and schemas that are currently being loaded
+    loading_schemas: Arc<Mutex<HashSet<(String, String)>>>,
+    schema_refreshes: SchemaRefreshes,
+    /// The connections whose documents are checked again once their schema cache is reloaded,
+    /// even if the schema did not change
+    revalidate_on_reload: HashSet<String>,
+    compute_debouncer: EventDebouncer<HashMap<String, PgPool>>,
+    semantic_tokens: Arc<SemanticTokensCache>,
+    /// The requests that are queued or being processed, which the client may cancel
+    pending_requests: PendingRequests,
+    progress: ProgressReporter,
+    /// Whether the client was asked to watch the files of the workspace
+    watching_files: bool,
 }
 
 impl Server {
@@ -89,8 +178,17 @@ impl Server {
         let (id, params) = connection.initialize_start()?;
         let params: InitializeParams = serde_json::from_value(params)?;
 
+        let position_encoding = PositionEncoding::negotiate(
+            params
+                .capabilities
+                .general
+                .as_ref()
+                .and_then(|general| general.position_encodings.as_deref()),
+        );
+        set_position_encoding(position_encoding);
+
         let result = InitializeResult {
-            capabilities: Self::capabilities(),
+            capabilities: Self::capabilities(position_encoding),
             server_info: Some(ServerInfo {
                 name: "Postgres LSP".to_owned(),
                 version: Some(env!("CARGO_PKG_VERSION").to_owned()),
@@ -99,8 +197,18 @@ impl Server {
 
         connection.initialize_finish(id, serde_json::to_value(result)?)?;
 
+        let root = params.root_uri.and_then(|uri| uri.to_file_path().ok());
+
+        // the settings of the workspace configuration take precedence once they are pulled, and
+        // the project configuration applies even without settings
+        let settings = params.initialization_options.unwrap_or_default();
+        let options = client.parse_options(root.as_deref(), settings.clone())?;
+        internal_tx.send(InternalMessage::SetOptions(Box::new(options)))?;
+
         let client_flags = Arc::new(from_proto::client_flags(params.capabilities));
 
+        let progress = ProgressReporter::new(client.clone(), client_flags.work_done_progress);
+
         let pool = Arc::new(threadpool::Builder::new().build());
 
         let ide = Arc::new(Workspace::new());
@@ -116,11 +224,24 @@ impl Server {
             internal_tx,
             client,
             client_flags,
-            db_conn: None,
+            db_conns: HashMap::new(),
+            execution_conn: None,
+            root,
+            refresh_task: None,
+            reconnect_tasks: HashMap::new(),
+            options: Options::default(),
+            settings,
+            loading_schemas: Arc::new(Mutex::new(HashSet::new())),
+            schema_refreshes: SchemaRefreshes::default(),
+            revalidate_on_reload: HashSet::new(),
             ide,
+            semantic_tokens: Arc::new(SemanticTokensCache::default()),
+            pending_requests: PendingRequests::default(),
+            progress,
+            watching_files: false,
             compute_debouncer: EventDebouncer::new(
                 Duration::from_millis(500),
-                move |conn: Option<PgPool>| {
+                move |conns: HashMap<String, PgPool>| {
                     let inner_cloned_ide = cloned_ide.clone();
                     let inner_cloned_tx = cloned_tx.clone();
                     let inner_cloned_client = cloned_client.clone();
@@ -128,19 +249,11 @@ impl Server {
                         inner_cloned_client
                             .send_notification::<ShowMessage>(ShowMessageParams {
                                 typ: lsp_types::MessageType::INFO,
-                                message: format!("Computing debounced {}", conn.is_some()),
-                            })
-                            .unwrap();
-                        let r =
-                            async_std::task::block_on(conn.as_ref().unwrap().execute("SELECT 1"));
-                        inner_cloned_client
-                            .send_notification::<ShowMessage>(ShowMessageParams {
-                                typ: lsp_types::MessageType::INFO,
-                                message: format!("res {:?}", r.unwrap()),
+                                message: format!("Computing debounced {}", !conns.is_empty()),
                             })
                             .unwrap();
 
-                        let changed = inner_cloned_ide.compute(conn);
+                        let changed = inner_cloned_ide.compute_typecheck(&conns);
 
                         let urls = HashSet::<&str>::from_iter(
                             changed.iter().map(|f| f.document_url.to_str().unwrap()),
@@ -162,11 +275,20 @@ impl Server {
         Ok(())
     }
 
+    /// Returns the pools of all open connections by name
+    fn pools(&self) -> HashMap<String, PgPool> {
+        self.db_conns
+            .iter()
+            .map(|(name, conn)| (name.clone(), conn.pool.clone()))
+            .collect()
+    }
+
     fn compute_now(&self) {
-        let conn = self.db_conn.as_ref().map(|p| p.pool.clone());
+        let conns = self.pools();
         let cloned_ide = self.ide.clone();
         let cloned_tx = self.internal_tx.clone();
         let client = self.client.clone();
+        let progress = self.progress.clone();
 
         self.compute_debouncer.clear();
 
@@ -174,27 +296,45 @@ impl Server {
             client
                 .send_notification::<ShowMessage>(ShowMessageParams {
                     typ: lsp_types::MessageType::INFO,
-                    message: format!("Computing now {}", conn.is_some()),
+                    message: format!("Computing now {}", !conns.is_empty()),
                 })
                 .unwrap();
 
-            if conn.is_some() {
-                client
-                    .send_notification::<ShowMessage>(ShowMessageParams {
-                        typ: lsp_types::MessageType::INFO,
-                        message: format!("pool closed {}", conn.as_ref().unwrap().is_closed()),
-                    })
-                    .unwrap();
+            // only checks of many statements, e.g. after the schema changed, are shown
+            let mut check_progress = None;
+            let changed = cloned_ide.compute_with_progress(&conns, |done, total| {
+                if total < PROGRESS_MIN_STATEMENTS {
+                    return true;
+                }
+                let progress =
+                    check_progress.get_or_insert_with(|| progress.begin("Checking", true));
+                progress.report(format!("{}/{} statements", done, total), done, total);
+                !progress.cancellation().is_cancelled()
+            });
+            drop(check_progress);
 
-                let r = async_std::task::block_on(conn.as_ref().unwrap().execute("SELECT 1"));
-                client
-                    .send_notification::<ShowMessage>(ShowMessageParams {
-                        typ: lsp_types::MessageType::INFO,
-                        message: format!("res {:?}", r.unwrap()),
-                    })
+            let urls = HashSet::<&str>::from_iter(
+                changed.iter().map(|f| f.document_url.to_str().unwrap()),
+            );
+
+            for url in urls {
+                cloned_tx
+                    .send(InternalMessage::PublishDiagnostics(
+                        lsp_types::Url::from_file_path(url).unwrap(),
+                    ))
                     .unwrap();
             }
-            let changed = cloned_ide.compute(conn);
+        });
+    }
+
+    /// Parses and lints the changed statements right away, and schedules their typecheck
+    /// according to the diagnostics options
+    fn compute_syntax(&self) {
+        let cloned_ide = self.ide.clone();
+        let cloned_tx = self.internal_tx.clone();
+
+        self.pool.execute(move || {
+            let changed = cloned_ide.compute_syntax();
             let urls = HashSet::<&str>::from_iter(
                 changed.iter().map(|f| f.document_url.to_str().unwrap()),
             );
@@ -207,32 +347,48 @@ impl Server {
                     .unwrap();
             }
         });
+
+        if self.options.diagnostics.typecheck == TypecheckTrigger::Idle {
+            self.compute_debouncer.put(self.pools());
+        }
     }
 
-    fn start_listening(&self) {
-        if self.db_conn.is_none() {
+    fn start_listening(&self, name: &str) {
+        let Some(conn) = self.db_conns.get(name) else {
             return;
-        }
+        };
 
-        let pool = self.db_conn.as_ref().unwrap().pool.clone();
+        let pool = conn.pool.clone();
         let tx = self.internal_tx.clone();
+        let name = name.to_string();
 
         task::spawn(async move {
             let mut listener = PgListener::connect_with(&pool).await.unwrap();
             listener
-                .listen_all(["postgres_lsp", "pgrst"])
+                .listen_all([pg_schema_cache::NOTIFY_CHANNEL, "pgrst"])
                 .await
                 .unwrap();
 
             loop {
                 match listener.recv().await {
                     Ok(notification) => {
-                        if notification.payload().to_string() == "reload schema" {
-                            tx.send(InternalMessage::RefreshSchemaCache).unwrap();
+                        let payload = notification.payload();
+                        if payload == "reload schema" {
+                            tx.send(InternalMessage::RefreshSchemaCache(Some(name.clone())))
+                                .unwrap();
+                        } else if let Some(classes) =
+                            pg_schema_cache::parse_ddl_notification(payload)
+                        {
+                            tx.send(InternalMessage::RefreshSchemaCacheClasses(
+                                name.clone(),
+                                classes,
+                            ))
+                            .unwrap();
                         }
                     }
                     Err(e) => {
                         eprintln!("Listener error: {}", e);
+                        tx.send(InternalMessage::ConnectionLost(name)).unwrap();
                         break;
                     }
                 }
@@ -240,44 +396,345 @@ impl Server {
         });
     }
 
-    async fn update_db_connection(&mut self, connection_string: Option<String>) {
-        if connection_string == self.db_conn.as_ref().map(|c| c.connection_string.clone()) {
-            return;
-        }
-        if let Some(conn) = self.db_conn.take() {
-            conn.pool.close().await;
+    /// Opens the given connections by name and closes all others
+    ///
+    /// Connections that cannot be established are retried in the background.
+    async fn update_db_connections(&mut self, connection_strings: HashMap<String, String>) {
+        let stale: Vec<String> = self
+            .db_conns
+            .iter()
+            .filter(|(name, conn)| connection_strings.get(*name) != Some(&conn.connection_string))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        for name in stale {
+            if let Some(conn) = self.db_conns.remove(&name) {
+                conn.pool.close().await;
+            }
+            if !connection_strings.contains_key(&name) {
+                self.send_connection_status(&name, ConnectionState::Offline, None, None);
+            }
         }
 
-        if connection_string.is_none() {
-            return;
+        let stale_retries: Vec<String> = self
+            .reconnect_tasks
+            .iter()
+            .filter(|(name, (connection_string, _))| {
+                connection_strings.get(*name) != Some(connection_string)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        for name in stale_retries {
+            if let Some((_, task)) = self.reconnect_tasks.remove(&name) {
+                task.cancel().await;
+            }
         }
 
-        let new_conn = DbConnection::new(connection_string.unwrap().as_str()).await;
+        for (name, connection_string) in connection_strings {
+            if self.db_conns.contains_key(&name) || self.reconnect_tasks.contains_key(&name) {
+                continue;
+            }
 
-        if new_conn.is_err() {
-            return;
+            let settings = ConnectionSettings::new(&self.options);
+            match DbConnection::new(connection_string.as_str(), &settings).await {
+                Ok(new_conn) => self.connected(name, new_conn),
+                Err(e) => self.reconnect(name, connection_string, format!("{:#}", e)),
+            }
+        }
+    }
+
+    /// Closes all connections and stops their retries to open them again right away
+    async fn reconnect_all(&mut self) {
+        for (_, (_, task)) in self.reconnect_tasks.drain() {
+            task.cancel().await;
         }
+        for (_, conn) in self.db_conns.drain() {
+            conn.pool.close().await;
+        }
+
+        self.update_db_connections(self.options.connection_strings())
+            .await;
+    }
 
-        self.db_conn = Some(new_conn.unwrap());
+    fn connected(&mut self, name: String, conn: DbConnection) {
+        self.db_conns.insert(name.clone(), conn);
 
         self.client
             .send_notification::<ShowMessage>(ShowMessageParams {
                 typ: lsp_types::MessageType::INFO,
-                message: "Connection to database established".to_string(),
+                message: format!("Connection to database {} established", name),
+            })
+            .unwrap();
+        self.send_connection_status(&name, ConnectionState::Connected, None, None);
+
+        self.restore_schema_cache(&name);
+
+        self.refresh_schema_cache(&name);
+
+        if self.options.install_event_trigger {
+            self.install_event_trigger(&name);
+        }
+
+        self.start_listening(&name);
+    }
+
+    /// Retries to open a connection in the background with increasing delays, until it is
+    /// established or `MAX_RECONNECT_ATTEMPTS` attempts failed
+    fn reconnect(&mut self, name: String, connection_string: String, error: String) {
+        self.send_connection_status(
+            &name,
+            ConnectionState::Reconnecting,
+            Some(error),
+            Some(backoff(0)),
+        );
+
+        let client = self.client.clone();
+        let tx = self.internal_tx.clone();
+        let task_name = name.clone();
+        let task_connection_string = connection_string.clone();
+        let settings = ConnectionSettings::new(&self.options);
+
+        let task = task::spawn(async move {
+            for attempt in 0..MAX_RECONNECT_ATTEMPTS {
+                task::sleep(backoff(attempt)).await;
+
+                match DbConnection::new(task_connection_string.as_str(), &settings).await {
+                    Ok(conn) => {
+                        tx.send(InternalMessage::Connected(task_name, conn))
+                            .unwrap();
+                        return;
+                    }
+                    Err(e) => {
+                        let last = attempt + 1 == MAX_RECONNECT_ATTEMPTS;
+                        client
+                            .send_notification::<ConnectionStatus>(ConnectionStatusParams {
+                                connection: task_name.clone(),
+                                state: if last {
+                                    ConnectionState::Offline
+                                } else {
+                                    ConnectionState::Reconnecting
+                                },
+                                message: Some(format!("{:#}", e)),
+                                retry_in: (!last).then(|| backoff(attempt + 1).as_secs()),
+                            })
+                            .unwrap();
+                    }
+                }
+            }
+        });
+
+        self.reconnect_tasks.insert(name, (connection_string, task));
+    }
+
+    fn send_connection_status(
+        &self,
+        name: &str,
+        state: ConnectionState,
+        message: Option<String>,
+        retry_in: Option<Duration>,
+    ) {
+        self.client
+            .send_notification::<ConnectionStatus>(ConnectionStatusParams {
+                connection: name.to_string(),
+                state,
+                message,
+                retry_in: retry_in.map(|d| d.as_secs()),
             })
             .unwrap();
+    }
+
+    /// Opens the connection statements are executed on, if it is not the one of their file
+    async fn update_execution_connection(&mut self, connection_string: Option<String>) {
+        if self.execution_conn.as_ref().map(|c| &c.connection_string) == connection_string.as_ref()
+        {
+            return;
+        }
+
+        if let Some(conn) = self.execution_conn.take() {
+            conn.pool.close().await;
+        }
+
+        let Some(connection_string) = connection_string else {
+            return;
+        };
+
+        let settings = ConnectionSettings::new(&self.options);
+        match DbConnection::new(connection_string.as_str(), &settings).await {
+            Ok(conn) => self.execution_conn = Some(conn),
+            Err(e) => {
+                self.client
+                    .send_notification::<ShowMessage>(ShowMessageParams {
+                        typ: lsp_types::MessageType::WARNING,
+                        message: format!("Failed to connect to the execution database: {:#}", e),
+                    })
+                    .unwrap();
+            }
+        }
+    }
+
+    /// Installs the event trigger that notifies about schema changes on a connection
+    fn install_event_trigger(&self, name: &str) {
+        let Some(conn) = self.db_conns.get(name) else {
+            return;
+        };
+        let pool = conn.pool.clone();
+        let client = self.client.clone();
+        let name = name.to_string();
+
+        task::spawn(async move {
+            if let Err(e) = pg_schema_cache::install_event_trigger(&pool).await {
+                client
+                    .send_notification::<ShowMessage>(ShowMessageParams {
+                        typ: lsp_types::MessageType::WARNING,
+                        message: format!("Failed to install event trigger on {}: {}", name, e),
+                    })
+                    .unwrap();
+            }
+        });
+    }
 
-        self.refresh_schema_cache();
+    /// (Re)starts the task that periodically refreshes the schema cache
+    fn update_refresh_interval(&mut self, interval: Option<Duration>) {
+        if let Some(task) = self.refresh_task.take() {
+            async_std::task::block_on(task.cancel());
+        }
+
+        let Some(interval) = interval.filter(|i| !i.is_zero()) else {
+            return;
+        };
 
-        self.start_listening();
+        let tx = self.internal_tx.clone();
+
+        self.refresh_task = Some(task::spawn(async move {
+            loop {
+                task::sleep(interval).await;
+                if tx.send(InternalMessage::RefreshSchemaCache(None)).is_err() {
+                    break;
+                }
+            }
+        }));
     }
 
+    /// Applies the options, and checks the open documents again if options they depend on
+    /// changed
     fn update_options(&mut self, options: Options) {
-        async_std::task::block_on(self.update_db_connection(options.db_connection_string));
+        let previous = std::mem::replace(&mut self.options, options.clone());
+        let connections_changed = previous.db_connection_string != options.db_connection_string
+            || previous.connections != options.connections;
+        let lint_changed =
+            previous.lint != options.lint || previous.directories != options.directories;
+        let schema_dump_changed = previous.schema_dump != options.schema_dump;
+        let settings_changed =
+            ConnectionSettings::new(&previous) != ConnectionSettings::new(&options);
+
+        self.compute_debouncer
+            .set_delay(Duration::from_millis(options.diagnostics.idle_delay));
+
+        let routes = ConnectionRoutes::new(
+            self.root.as_deref(),
+            options
+                .connections
+                .iter()
+                .flat_map(|c| c.paths.iter().map(|path| (path.clone(), c.name.clone()))),
+        );
+        match routes {
+            Ok(routes) => self.ide.set_connection_routes(routes),
+            Err(e) => {
+                self.client
+                    .send_notification::<ShowMessage>(ShowMessageParams {
+                        typ: lsp_types::MessageType::WARNING,
+                        message: format!("Invalid connection path: {}", e),
+                    })
+                    .unwrap();
+            }
+        }
+
+        // connections that are opened from now on install it when they are established
+        if options.install_event_trigger && !previous.install_event_trigger {
+            for name in self.db_conns.keys() {
+                self.install_event_trigger(name);
+            }
+        }
+
+        if settings_changed {
+            async_std::task::block_on(async {
+                if let Some(conn) = self.execution_conn.take() {
+                    conn.pool.close().await;
+                }
+                self.reconnect_all().await;
+            });
+        } else {
+            async_std::task::block_on(
+                self.update_db_connections(self.options.connection_strings()),
+            );
+        }
+
+        async_std::task::block_on(
+            self.update_execution_connection(options.execution.connection_string.clone()),
+        );
+
+        if !self.db_conns.contains_key(DEFAULT_CONNECTION)
+            && (options.schema_dump.is_some() || !options.schema_files.is_empty())
+        {
+            self.load_offline_schema_cache(options.schema_dump, options.schema_files);
+        }
+
+        self.update_refresh_interval(
+            options
+                .schema_cache_refresh_interval
+                .map(Duration::from_secs),
+        );
+
+        let rule_options_changed = lint_changed && self.update_lint_rules();
+
+        if schema_dump_changed {
+            self.register_file_watchers();
+        }
+
+        // the results of the typecheck depend on the connection a document is checked against,
+        // while lints are only reported with another severity and can be published right away
+        // while the options of the rules change the violations themselves
+        if connections_changed || rule_options_changed {
+            if connections_changed {
+                self.ide.invalidate_typecheck();
+            }
+            if rule_options_changed {
+                self.ide.invalidate_lint();
+            }
+            self.compute_now();
+        } else if lint_changed {
+            self.publish_open_documents_diagnostics();
+        }
+    }
+
+    /// Sets the severity of the lint rules, for the whole workspace and for the directories with
+    /// a `pglsp.toml`, and their options, and returns whether the options changed
+    fn update_lint_rules(&self) -> bool {
+        self.ide
+            .linter
+            .set_rule_severities(self.options.rule_severities());
+        self.ide
+            .linter
+            .set_directory_rule_severities(self.options.directory_rule_severities());
+        self.ide
+            .linter
+            .set_rule_options(self.options.rule_options())
+    }
+
+    fn publish_open_documents_diagnostics(&self) {
+        for doc in self.ide.documents.iter() {
+            if let Ok(uri) = lsp_types::Url::from_file_path(doc.key().as_path()) {
+                self.internal_tx
+                    .send(InternalMessage::PublishDiagnostics(uri))
+                    .unwrap();
+            }
+        }
     }
 
-    fn capabilities() -> ServerCapabilities {
+    fn capabilities(position_encoding: PositionEncoding) -> ServerCapabilities {
         ServerCapabilities {
+            position_encoding: Some(position_encoding.kind()),
             text_document_sync: Some(TextDocumentSyncCapability::Options(
                 TextDocumentSyncOptions {
                     open_close: Some(true),
@@ -293,19 +750,79 @@ impl Server {
             execute_command_provider: Some(ExecuteCommandOptions {
                 commands: CommandType::ALL
                     .iter()
-                    .map(|c| c.id().to_string())
+                    .map(|c| format!("pglsp.{}", c.id()))
                     .collect(),
                 ..Default::default()
             }),
             inlay_hint_provider: Some(lsp_types::OneOf::Left(true)),
-            code_action_provider: Some(lsp_types::CodeActionProviderCapability::Simple(true)),
+            diagnostic_provider: Some(lsp_types::DiagnosticServerCapabilities::Options(
+                lsp_types::DiagnosticOptions {
+                    identifier: Some("pglsp".to_string()),
+                    inter_file_dependencies: false,
+                    workspace_diagnostics: true,
+                    work_done_progress_options: Default::default(),
+                },
+            )),
+            code_action_provider: Some(lsp_types::CodeActionProviderCapability::Options(
+                lsp_types::CodeActionOptions {
+                    code_action_kinds: Some(
+                        [
+                            pg_workspace::CodeActionKind::QuickFix,
+                            pg_workspace::CodeActionKind::RefactorRewrite,
+                            pg_workspace::CodeActionKind::SourceFixAll,
+                        ]
+                        .into_iter()
+                        .map(to_proto::code_action_kind)
+                        .collect(),
+                    ),
+                    ..Default::default()
+                },
+            )),
+            code_lens_provider: Some(lsp_types::CodeLensOptions {
+                resolve_provider: Some(false),
+            }),
             completion_provider: Some(CompletionOptions::default()),
+            definition_provider: Some(lsp_types::OneOf::Left(true)),
+            references_provider: Some(lsp_types::OneOf::Left(true)),
+            linked_editing_range_provider: Some(
+                lsp_types::LinkedEditingRangeServerCapabilities::Simple(true),
+            ),
+            document_symbol_provider: Some(lsp_types::OneOf::Left(true)),
+            folding_range_provider: Some(lsp_types::FoldingRangeProviderCapability::Simple(true)),
+            selection_range_provider: Some(lsp_types::SelectionRangeProviderCapability::Simple(
+                true,
+            )),
+            workspace_symbol_provider: Some(lsp_types::OneOf::Left(true)),
+            semantic_tokens_provider: Some(
+                lsp_types::SemanticTokensOptions {
+                    legend: semantic_tokens::legend(),
+                    range: Some(true),
+                    full: Some(lsp_types::SemanticTokensFullOptions::Delta { delta: Some(true) }),
+                    work_done_progress_options: Default::default(),
+                }
+                .into(),
+            ),
+            rename_provider: Some(lsp_types::OneOf::Right(RenameOptions {
+                prepare_provider: Some(true),
+                work_done_progress_options: Default::default(),
+            })),
             ..ServerCapabilities::default()
         }
     }
 
     // TODO allow option url and publish diagnostics for all files
     fn publish_diagnostics(&self, uri: lsp_types::Url) -> anyhow::Result<()> {
+        // clients that pull diagnostics are asked to pull them again instead
+        if self.client_flags.diagnostics_pull {
+            if self.client_flags.diagnostics_refresh {
+                let client = self.client.clone();
+                self.pool.execute(move || {
+                    let _ = client.send_request::<WorkspaceDiagnosticRefresh>(());
+                });
+            }
+            return Ok(());
+        }
+
         let mut url = uri.clone();
         normalize_uri(&mut url);
 
@@ -343,6 +860,125 @@ impl Server {
         Ok(())
     }
 
+    fn document_diagnostic(
+        &self,
+        id: RequestId,
+        params: lsp_types::DocumentDiagnosticParams,
+    ) -> anyhow::Result<()> {
+        self.run_query(id, move |ide| {
+            let mut uri = params.text_document.uri;
+            normalize_uri(&mut uri);
+
+            let diagnostics = Self::document_diagnostics(ide, &file_path(&uri)).unwrap_or_default();
+            let result_id = Self::diagnostics_result_id(&diagnostics);
+
+            let report = if params.previous_result_id.as_ref() == Some(&result_id) {
+                lsp_types::DocumentDiagnosticReport::Unchanged(
+                    lsp_types::RelatedUnchangedDocumentDiagnosticReport {
+                        related_documents: None,
+                        unchanged_document_diagnostic_report:
+                            lsp_types::UnchangedDocumentDiagnosticReport { result_id },
+                    },
+                )
+            } else {
+                lsp_types::DocumentDiagnosticReport::Full(
+                    lsp_types::RelatedFullDocumentDiagnosticReport {
+                        related_documents: None,
+                        full_document_diagnostic_report: lsp_types::FullDocumentDiagnosticReport {
+                            result_id: Some(result_id),
+                            items: diagnostics,
+                        },
+                    },
+                )
+            };
+
+            lsp_types::DocumentDiagnosticReportResult::Report(report)
+        });
+
+        Ok(())
+    }
+
+    /// Reports the diagnostics of all open documents
+    fn workspace_diagnostic(
+        &self,
+        id: RequestId,
+        params: lsp_types::WorkspaceDiagnosticParams,
+    ) -> anyhow::Result<()> {
+        self.run_query(id, move |ide| {
+            let paths = ide
+                .documents
+                .iter()
+                .map(|doc| doc.key().clone())
+                .collect::<Vec<_>>();
+
+            let items = paths
+                .into_iter()
+                .filter_map(|path| {
+                    let uri = lsp_types::Url::from_file_path(path.as_path()).ok()?;
+                    let diagnostics = Self::document_diagnostics(ide, &path)?;
+                    let result_id = Self::diagnostics_result_id(&diagnostics);
+
+                    let unchanged = params
+                        .previous_result_ids
+                        .iter()
+                        .any(|previous| previous.uri == uri && previous.value == result_id);
+
+                    Some(if unchanged {
+                        lsp_types::WorkspaceDocumentDiagnosticReport::Unchanged(
+                            lsp_types::WorkspaceUnchangedDocumentDiagnosticReport {
+                                uri,
+                                version: None,
+                                unchanged_document_diagnostic_report:
+                                    lsp_types::UnchangedDocumentDiagnosticReport { result_id },
+                            },
+                        )
+                    } else {
+                        lsp_types::WorkspaceDocumentDiagnosticReport::Full(
+                            lsp_types::WorkspaceFullDocumentDiagnosticReport {
+                                uri,
+                                version: None,
+                                full_document_diagnostic_report:
+                                    lsp_types::FullDocumentDiagnosticReport {
+                                        result_id: Some(result_id),
+                                        items: diagnostics,
+                                    },
+                            },
+                        )
+                    })
+                })
+                .collect();
+
+            lsp_types::WorkspaceDiagnosticReportResult::Report(
+                lsp_types::WorkspaceDiagnosticReport { items },
+            )
+        });
+
+        Ok(())
+    }
+
+    fn document_diagnostics(
+        ide: &Workspace,
+        path: &PgLspPath,
+    ) -> Option<Vec<lsp_types::Diagnostic>> {
+        let doc = ide.documents.get(path)?;
+        Some(
+            ide.diagnostics(path)
+                .iter()
+                .map(|d| to_proto::diagnostic(&doc, d))
+                .collect(),
+        )
+    }
+
+    /// Identifies a set of diagnostics by its content, so that the client can be told that the
+    /// diagnostics it pulled before did not change
+    fn diagnostics_result_id(diagnostics: &[lsp_types::Diagnostic]) -> String {
+        let mut hasher = DefaultHasher::new();
+        serde_json::to_string(diagnostics)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        format!("{:x}", hasher.finish())
+    }
+
     fn did_open(&self, params: DidOpenTextDocumentParams) -> anyhow::Result<()> {
         let mut uri = params.text_document.uri;
 
@@ -351,7 +987,7 @@ impl Server {
         let path = file_path(&uri);
 
         self.ide.apply_change(
-            path,
+            path.clone(),
             DocumentChange::new(
                 params.text_document.version,
                 vec![Change {
@@ -361,6 +997,8 @@ impl Server {
             ),
         );
 
+        self.load_referenced_schemas(&path);
+
         self.compute_now();
 
         Ok(())
@@ -381,12 +1019,13 @@ impl Server {
         let changes = from_proto::content_changes(&document.unwrap(), params.content_changes);
 
         self.ide.apply_change(
-            path,
+            path.clone(),
             DocumentChange::new(params.text_document.version, changes),
         );
 
-        let conn = self.db_conn.as_ref().map(|p| p.pool.clone());
-        self.compute_debouncer.put(conn);
+        self.load_referenced_schemas(&path);
+
+        self.compute_syntax();
 
         Ok(())
     }
@@ -412,38 +1051,203 @@ impl Server {
         normalize_uri(&mut uri);
         let path = file_path(&uri);
 
-        self.ide.remove_document(path);
+        self.ide.remove_document(path.clone());
+        self.semantic_tokens.remove(&path);
+
+        // the file is indexed again from disk, since its unsaved changes were discarded
+        if let Ok(text) = std::fs::read_to_string(path.as_path()) {
+            self.ide.objects.index_file(path, text);
+        }
 
         Ok(())
     }
 
-    fn code_actions(
-        &self,
-        id: RequestId,
-        params: lsp_types::CodeActionParams,
+    /// Updates the index of the sql files that changed on disk, e.g. by checking out another
+    /// branch, and rebuilds the offline schema cache if it is built from one of the changed files
+    fn did_change_watched_files(
+        &mut self,
+        params: DidChangeWatchedFilesParams,
     ) -> anyhow::Result<()> {
-        let db_conn = self.db_conn.as_ref().map(|p| p.pool.clone());
-        self.run_query(id, move |ide| {
-            let mut actions = Vec::<lsp_types::CodeAction>::new();
-
-            if db_conn.is_none() {
-                return actions;
-            }
-
-            let mut uri = params.text_document.uri;
+        let mut config_changed = false;
+        let mut schema_changed = false;
+        for change in params.changes {
+            let mut uri = change.uri;
             normalize_uri(&mut uri);
             let path = file_path(&uri);
 
-            let doc = ide.documents.get(&path);
+            // the configuration of the root or of a subdirectory
+            config_changed |= path.file_name().is_some_and(|name| name == CONFIG_FILE)
+                && self
+                    .root
+                    .as_ref()
+                    .is_some_and(|root| path.starts_with(root));
+            schema_changed |= self.is_schema_file(path.as_path());
+
+            // open documents are indexed as they change
+            if path.extension().is_none_or(|ext| ext != "sql")
+                || self.ide.documents.contains_key(&path)
+            {
+                continue;
+            }
 
-            if doc.is_none() {
-                return actions;
+            if change.typ == FileChangeType::DELETED {
+                self.ide.objects.remove_file(&path);
+            } else if let Ok(text) = std::fs::read_to_string(path.as_path()) {
+                self.ide.objects.index_file(path, text);
             }
+        }
 
-            let doc = doc.unwrap();
+        if schema_changed && !self.db_conns.contains_key(DEFAULT_CONNECTION) {
+            self.load_offline_schema_cache(
+                self.options.schema_dump.clone(),
+                self.options.schema_files.clone(),
+            );
+        }
+
+        if config_changed {
+            if self.client_flags.configuration_pull {
+                self.pull_options();
+            } else {
+                let options = self
+                    .client
+                    .parse_options(self.root.as_deref(), self.settings.clone())?;
+                self.update_options(options);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the offline schema cache is built from the file, i.e. it is the schema dump or
+    /// one of the schema files or within one of their directories
+    fn is_schema_file(&self, path: &Path) -> bool {
+        let resolve = |p: &PathBuf| match &self.root {
+            Some(root) if p.is_relative() => root.join(p),
+            _ => p.clone(),
+        };
+        self.options
+            .schema_dump
+            .iter()
+            .chain(self.options.schema_files.iter())
+            .any(|p| path.starts_with(resolve(p)))
+    }
+
+    /// Asks the client to notify the server about changes to the sql files of the workspace, to
+    /// the schema dump, which are not open in the client, and to the project configuration
+    fn register_file_watchers(&mut self) {
+        if !self.client_flags.watched_files_registration {
+            return;
+        }
+
+        let mut watchers = vec![
+            FileSystemWatcher {
+                glob_pattern: GlobPattern::String("**/*.sql".to_string()),
+                kind: None,
+            },
+            FileSystemWatcher {
+                glob_pattern: GlobPattern::String(format!("**/{}", CONFIG_FILE)),
+                kind: None,
+            },
+        ];
+        if let Some(dump) = &self.options.schema_dump {
+            watchers.push(FileSystemWatcher {
+                glob_pattern: GlobPattern::String(dump.to_string_lossy().to_string()),
+                kind: None,
+            });
+        }
+
+        let registration = Registration {
+            id: "watch-files".to_string(),
+            method: DidChangeWatchedFiles::METHOD.to_string(),
+            register_options: Some(
+                serde_json::to_value(DidChangeWatchedFilesRegistrationOptions { watchers })
+                    .unwrap(),
+            ),
+        };
+        // the watchers of a previous registration are replaced
+        let unregistration =
+            std::mem::replace(&mut self.watching_files, true).then(|| UnregistrationParams {
+                unregisterations: vec![Unregistration {
+                    id: registration.id.clone(),
+                    method: registration.method.clone(),
+                }],
+            });
+
+        let client = self.client.clone();
+        self.pool.execute(move || {
+            if let Some(params) = unregistration {
+                let _ = client.send_request::<UnregisterCapability>(params);
+            }
+            let _ = client.send_request::<RegisterCapability>(RegistrationParams {
+                registrations: vec![registration],
+            });
+        });
+    }
+
+    fn code_actions(
+        &self,
+        id: RequestId,
+        params: lsp_types::CodeActionParams,
+    ) -> anyhow::Result<()> {
+        let connections: HashSet<String> = self.db_conns.keys().cloned().collect();
+        self.run_query(id, move |ide| {
+            let mut actions = Vec::<lsp_types::CodeAction>::new();
+
+            let mut uri = params.text_document.uri;
+            normalize_uri(&mut uri);
+            let path = file_path(&uri);
+
+            let doc = ide.documents.get(&path);
+
+            if doc.is_none() {
+                return actions;
+            }
+
+            let doc = doc.unwrap();
 
             let range = doc.line_index.offset_lsp_range(params.range).unwrap();
 
+            // e.g. requested by `editor.codeActionsOnSave`, which only asks for source actions
+            let fix_all = to_proto::code_action_kind(pg_workspace::CodeActionKind::SourceFixAll);
+            let fix_all_requested = params.context.only.as_ref().is_some_and(|only| {
+                only.iter().any(|kind| {
+                    fix_all.as_str() == kind.as_str()
+                        || fix_all.as_str().starts_with(&format!("{}.", kind.as_str()))
+                })
+            });
+
+            actions.extend(
+                ide.code_actions(&path, range)
+                    .into_iter()
+                    .chain(ide.fix_all(&path).filter(|_| fix_all_requested))
+                    .map(|action| lsp_types::CodeAction {
+                        title: action.title,
+                        kind: Some(to_proto::code_action_kind(action.kind)),
+                        is_preferred: (action.kind == pg_workspace::CodeActionKind::QuickFix)
+                            .then_some(true),
+                        edit: Some(WorkspaceEdit {
+                            changes: Some(HashMap::from([(
+                                uri.clone(),
+                                action
+                                    .edits
+                                    .into_iter()
+                                    .map(|(range, new_text)| lsp_types::TextEdit {
+                                        range: doc.line_index.line_col_lsp_range(range).unwrap(),
+                                        new_text,
+                                    })
+                                    .collect(),
+                            )])),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+            );
+
+            // statements can only be executed on a connection
+            if !connections.contains(&ide.connection(&path)) {
+                return actions;
+            }
+
             actions.extend(doc.statements_at_range(&range).iter().map(|stmt| {
                 let cmd = ExecuteStatementCommand::command_type();
                 let title = format!(
@@ -457,7 +1261,10 @@ impl Server {
                     command: Some(lsp_types::Command {
                         title,
                         command: format!("pglsp.{}", cmd.id()),
-                        arguments: Some(vec![serde_json::to_value(stmt.text.clone()).unwrap()]),
+                        arguments: Some(vec![
+                            serde_json::to_value(stmt.text.clone()).unwrap(),
+                            serde_json::to_value(&uri).unwrap(),
+                        ]),
                     }),
                     diagnostics: None,
                     is_preferred: None,
@@ -472,6 +1279,55 @@ impl Server {
         Ok(())
     }
 
+    /// Returns lenses to run and explain every statement of a document on its connection
+    fn code_lens(&self, id: RequestId, params: lsp_types::CodeLensParams) -> anyhow::Result<()> {
+        let connections: HashSet<String> = self.db_conns.keys().cloned().collect();
+        self.run_query(id, move |ide| {
+            let mut uri = params.text_document.uri;
+            normalize_uri(&mut uri);
+            let path = file_path(&uri);
+
+            // statements can only be executed on a connection
+            if !connections.contains(&ide.connection(&path)) {
+                return Vec::new();
+            }
+
+            let Some(doc) = ide.documents.get(&path) else {
+                return Vec::new();
+            };
+
+            doc.statement_refs_with_range()
+                .into_iter()
+                .flat_map(|(range, stmt)| {
+                    let range = doc
+                        .line_index
+                        .line_col_lsp_range(TextRange::empty(range.start()))
+                        .unwrap();
+                    let arguments = vec![
+                        serde_json::to_value(stmt.text.clone()).unwrap(),
+                        serde_json::to_value(&uri).unwrap(),
+                    ];
+                    [
+                        ("\u{25b6} Run", ExecuteStatementCommand::command_type()),
+                        ("Explain", ExplainStatementCommand::command_type()),
+                    ]
+                    .into_iter()
+                    .map(move |(title, cmd)| lsp_types::CodeLens {
+                        range,
+                        command: Some(lsp_types::Command {
+                            title: title.to_string(),
+                            command: format!("pglsp.{}", cmd.id()),
+                            arguments: Some(arguments.clone()),
+                        }),
+                        data: None,
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+
+        Ok(())
+    }
+
     fn inlay_hint(
         &self,
         id: RequestId,
@@ -480,6 +1336,7 @@ impl Server {
         normalize_uri(&mut params.text_document.uri);
 
         let c = self.client.clone();
+        let options = self.options.inlay_hints.clone();
 
         self.run_query(id, move |ide| {
             let path = file_path(&params.text_document.uri);
@@ -494,7 +1351,7 @@ impl Server {
 
             let range = doc.line_index.offset_lsp_range(params.range).unwrap();
 
-            let schema_cache = ide.schema_cache.read().unwrap();
+            let schema_cache = ide.document_schema_cache(&path);
 
             c.send_notification::<ShowMessage>(ShowMessageParams {
                 typ: lsp_types::MessageType::INFO,
@@ -502,10 +1359,13 @@ impl Server {
             })
             .unwrap();
 
-            doc.statements_at_range(&range)
-                .iter()
-                .flat_map(|stmt| {
+            // hints are relative to their statement
+            doc.statement_refs_with_range()
+                .into_iter()
+                .filter(|(stmt_range, _)| stmt_range.intersect(range).is_some())
+                .flat_map(|(stmt_range, stmt)| {
                     ::pg_inlay_hints::inlay_hints(::pg_inlay_hints::InlayHintsParams {
+                        text: &stmt.text,
                         ast: ide.pg_query.ast(&stmt).as_ref().map(|x| x.as_ref()),
                         enriched_ast: ide
                             .pg_query
@@ -515,22 +1375,52 @@ impl Server {
                         tree: ide.tree_sitter.tree(&stmt).as_ref().map(|x| x.as_ref()),
                         cst: ide.pg_query.cst(&stmt).as_ref().map(|x| x.as_ref()),
                         schema_cache: &schema_cache,
+                        parameter_types: ide
+                            .typechecker
+                            .parameter_types(&stmt)
+                            .as_ref()
+                            .map(|x| x.as_slice()),
                     })
+                    .into_iter()
+                    .map(move |hint| (stmt_range.start() + hint.offset, hint.content))
                 })
-                .map(|hint| lsp_types::InlayHint {
-                    position: doc.line_index.line_col_lsp(hint.offset).unwrap(),
-                    label: match hint.content {
-                        pg_inlay_hints::InlayHintContent::FunctionArg(arg) => {
-                            lsp_types::InlayHintLabel::String(match arg.name {
-                                Some(name) => format!("{} ({})", name, arg.type_name),
-                                None => arg.type_name.clone(),
-                            })
+                .filter(|(_, content)| match content {
+                    pg_inlay_hints::InlayHintContent::FunctionArg(_) => options.function_args,
+                    pg_inlay_hints::InlayHintContent::ParameterType(_) => options.parameter_types,
+                    pg_inlay_hints::InlayHintContent::ImplicitCast(_) => options.implicit_casts,
+                    pg_inlay_hints::InlayHintContent::InsertColumn(_)
+                    | pg_inlay_hints::InlayHintContent::CopyColumns(_) => options.insert_columns,
+                })
+                .map(|(offset, content)| lsp_types::InlayHint {
+                    position: doc.line_index.line_col_lsp(offset).unwrap(),
+                    label: lsp_types::InlayHintLabel::String(match &content {
+                        pg_inlay_hints::InlayHintContent::FunctionArg(arg) => match &arg.name {
+                            Some(name) => format!("{} ({})", name, arg.type_name),
+                            None => arg.type_name.clone(),
+                        },
+                        pg_inlay_hints::InlayHintContent::InsertColumn(column) => {
+                            format!("{}:", column.name)
                         }
-                    },
-                    kind: match hint.content {
-                        pg_inlay_hints::InlayHintContent::FunctionArg(_) => {
+                        pg_inlay_hints::InlayHintContent::CopyColumns(columns) => {
+                            format!("({})", columns.names.join(", "))
+                        }
+                        pg_inlay_hints::InlayHintContent::ParameterType(parameter) => {
+                            format!(": {}", parameter.type_name)
+                        }
+                        pg_inlay_hints::InlayHintContent::ImplicitCast(cast) => {
+                            format!("::{}", cast.type_name)
+                        }
+                    }),
+                    kind: match content {
+                        pg_inlay_hints::InlayHintContent::FunctionArg(_)
+                        | pg_inlay_hints::InlayHintContent::InsertColumn(_)
+                        | pg_inlay_hints::InlayHintContent::CopyColumns(_) => {
                             Some(lsp_types::InlayHintKind::PARAMETER)
                         }
+                        pg_inlay_hints::InlayHintContent::ParameterType(_)
+                        | pg_inlay_hints::InlayHintContent::ImplicitCast(_) => {
+                            Some(lsp_types::InlayHintKind::TYPE)
+                        }
                     },
                     text_edits: None,
                     tooltip: None,
@@ -561,43 +1451,116 @@ impl Server {
                 .offset_lsp(params.text_document_position.position)
                 .unwrap();
 
-            let (range, stmt) = doc.statement_at_offset_with_range(&pos)?;
+            let schema = ide.document_schema_cache(&path);
 
-            let schema = ide.schema_cache.read().unwrap();
+            Self::complete(ide, &doc, pos, &schema)
+        });
 
-            Some(CompletionList {
-                is_incomplete: false,
-                items: pg_completions::complete(&CompletionParams {
-                    position: pos - range.start() - TextSize::from(1),
-                    text: stmt.text.as_str(),
-                    tree: ide.tree_sitter.tree(&stmt).as_ref().map(|x| x.as_ref()),
-                    schema: &schema,
-                })
-                .items
-                .iter()
-                .map(|i| lsp_types::CompletionItem {
-                    // TODO: add more data
-                    label: i.data.label().to_string(),
-                    label_details: None,
-                    kind: Some(lsp_types::CompletionItemKind::CLASS),
-                    detail: None,
-                    documentation: None,
-                    deprecated: None,
-                    preselect: None,
-                    sort_text: None,
-                    filter_text: None,
-                    insert_text: None,
-                    insert_text_format: None,
-                    insert_text_mode: None,
-                    text_edit: None,
-                    additional_text_edits: None,
-                    commit_characters: None,
-                    data: None,
-                    tags: None,
-                    command: None,
-                })
-                .collect(),
+        Ok(())
+    }
+
+    /// Returns the completions at an offset of a document
+    fn complete(
+        ide: &Workspace,
+        doc: &Document,
+        pos: TextSize,
+        schema: &SchemaCache,
+    ) -> Option<CompletionList> {
+        let (range, stmt) = doc.statement_at_offset_with_range(&pos)?;
+
+        Some(CompletionList {
+            is_incomplete: false,
+            items: pg_completions::complete(&CompletionParams {
+                position: pos - range.start() - TextSize::from(1),
+                text: stmt.text.as_str(),
+                tree: ide.tree_sitter.tree(&stmt).as_ref().map(|x| x.as_ref()),
+                ast: ide.pg_query.ast(&stmt).as_ref().map(|x| x.as_ref()),
+                schema,
             })
+            .items
+            .iter()
+            .map(|i| lsp_types::CompletionItem {
+                // TODO: add more data
+                label: i.data.label().to_string(),
+                label_details: None,
+                kind: Some(to_proto::completion_item_kind(&i.data)),
+                detail: None,
+                documentation: None,
+                deprecated: None,
+                preselect: None,
+                sort_text: None,
+                filter_text: None,
+                insert_text: None,
+                insert_text_format: None,
+                insert_text_mode: None,
+                text_edit: None,
+                additional_text_edits: None,
+                commit_characters: None,
+                data: None,
+                tags: None,
+                command: None,
+            })
+            .collect(),
+        })
+    }
+
+    /// Returns the diagnostics and completions of sql embedded in a document of another
+    /// language, with positions in that document
+    fn analyze_embedded(
+        &self,
+        id: RequestId,
+        mut params: AnalyzeEmbeddedParams,
+    ) -> anyhow::Result<()> {
+        normalize_uri(&mut params.host_uri);
+        let conns = self.pools();
+
+        self.run_cancellable_query(id, move |ide, token| {
+            let host = file_path(&params.host_uri);
+            let schema = ide.document_schema_cache(&host);
+            let is_cancelled = || token.is_cancelled();
+
+            // the result of a cancelled request is not sent, so the remaining snippets are skipped
+            let snippets = params
+                .snippets
+                .into_iter()
+                .take_while(|_| !is_cancelled())
+                .map(|snippet| {
+                    let mapping = SnippetMapping {
+                        start: snippet.start,
+                    };
+                    let path = file_path(&snippet.uri);
+
+                    ide.analyse_embedded(
+                        path,
+                        &host,
+                        snippet.text,
+                        &conns,
+                        &is_cancelled,
+                        |doc, diagnostics| {
+                            let completions = snippet
+                                .completion_position
+                                .and_then(|p| mapping.snippet_position(p))
+                                .and_then(|p| doc.line_index.offset_lsp(p))
+                                .and_then(|pos| Self::complete(ide, doc, pos, &schema));
+
+                            EmbeddedSnippetResult {
+                                uri: snippet.uri.clone(),
+                                diagnostics: diagnostics
+                                    .iter()
+                                    .map(|d| {
+                                        let mut diagnostic = to_proto::diagnostic(doc, d);
+                                        diagnostic.range = mapping.host_range(diagnostic.range);
+                                        diagnostic
+                                    })
+                                    .collect(),
+                                completions,
+                            }
+                        },
+                    )
+                })
+                .collect();
+
+            AnalyzeEmbeddedResult { snippets }
         });
 
         Ok(())
@@ -626,7 +1589,12 @@ impl Server {
                     .as_ref()
                     .map(|x| x.as_ref()),
                 tree: ide.tree_sitter.tree(&stmt).as_ref().map(|x| x.as_ref()),
-                schema_cache: ide.schema_cache.read().unwrap().clone(),
+                schema_cache: &ide.document_schema_cache(&path),
+                result_columns: ide
+                    .typechecker
+                    .result_columns(&stmt)
+                    .as_ref()
+                    .map(|x| x.as_slice()),
             })
             .map(|hover| lsp_types::Hover {
                 contents: lsp_types::HoverContents::Scalar(lsp_types::MarkedString::String(
@@ -639,34 +1607,526 @@ impl Server {
         Ok(())
     }
 
-    fn execute_command(&self, id: RequestId, params: ExecuteCommandParams) -> anyhow::Result<()> {
+    fn definition(&self, id: RequestId, mut params: GotoDefinitionParams) -> anyhow::Result<()> {
+        normalize_uri(&mut params.text_document_position_params.text_document.uri);
+
+        self.run_query(id, move |ide| {
+            let path = file_path(&params.text_document_position_params.text_document.uri);
+
+            let pos = ide
+                .documents
+                .get(&path)?
+                .line_index
+                .offset_lsp(params.text_document_position_params.position)?;
+
+            let locations = ide
+                .find_definitions(&path, pos)
+                .into_iter()
+                .filter_map(|(path, range)| {
+                    to_proto::location(&path, &ide.line_index(&path)?, range)
+                })
+                .collect::<Vec<_>>();
+
+            if locations.is_empty() {
+                None
+            } else {
+                Some(GotoDefinitionResponse::Array(locations))
+            }
+        });
+
+        Ok(())
+    }
+
+    fn references(&self, id: RequestId, mut params: ReferenceParams) -> anyhow::Result<()> {
+        normalize_uri(&mut params.text_document_position.text_document.uri);
+
+        self.run_query(id, move |ide| {
+            let path = file_path(&params.text_document_position.text_document.uri);
+
+            let pos = ide
+                .documents
+                .get(&path)?
+                .line_index
+                .offset_lsp(params.text_document_position.position)?;
+
+            let locations = ide
+                .find_references(&path, pos)
+                .into_iter()
+                .filter_map(|(path, range)| {
+                    to_proto::location(&path, &ide.line_index(&path)?, range)
+                })
+                .collect::<Vec<_>>();
+
+            Some(locations)
+        });
+
+        Ok(())
+    }
+
+    fn folding_ranges(
+        &self,
+        id: RequestId,
+        mut params: lsp_types::FoldingRangeParams,
+    ) -> anyhow::Result<()> {
+        normalize_uri(&mut params.text_document.uri);
+
+        self.run_query(id, move |ide| {
+            let path = file_path(&params.text_document.uri);
+
+            ide.folding_ranges(&path)
+                .into_iter()
+                .map(|range| lsp_types::FoldingRange {
+                    start_line: range.start_line,
+                    end_line: range.end_line,
+                    kind: Some(match range.kind {
+                        FoldingKind::Comment => lsp_types::FoldingRangeKind::Comment,
+                        FoldingKind::Region => lsp_types::FoldingRangeKind::Region,
+                    }),
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>()
+        });
+
+        Ok(())
+    }
+
+    fn selection_ranges(
+        &self,
+        id: RequestId,
+        mut params: lsp_types::SelectionRangeParams,
+    ) -> anyhow::Result<()> {
+        normalize_uri(&mut params.text_document.uri);
+
+        self.run_query(id, move |ide| {
+            let path = file_path(&params.text_document.uri);
+            let line_index = ide.line_index(&path)?;
+
+            // the response needs one selection range per position
+            let ranges = params
+                .positions
+                .into_iter()
+                .map(|position| {
+                    let ranges = line_index
+                        .offset_lsp(position)
+                        .map(|offset| ide.selection_ranges(&path, offset))
+                        .unwrap_or_default();
+
+                    ranges
+                        .into_iter()
+                        .rev()
+                        .filter_map(|range| line_index.line_col_lsp_range(range))
+                        .fold(None, |parent, range| {
+                            Some(lsp_types::SelectionRange {
+                                range,
+                                parent: parent.map(Box::new),
+                            })
+                        })
+                        .unwrap_or(lsp_types::SelectionRange {
+                            range: lsp_types::Range::new(position, position),
+                            parent: None,
+                        })
+                })
+                .collect::<Vec<_>>();
+
+            Some(ranges)
+        });
+
+        Ok(())
+    }
+
+    fn document_symbols(
+        &self,
+        id: RequestId,
+        mut params: DocumentSymbolParams,
+    ) -> anyhow::Result<()> {
+        normalize_uri(&mut params.text_document.uri);
+
+        self.run_query(id, move |ide| {
+            let path = file_path(&params.text_document.uri);
+            let line_index = ide.line_index(&path)?;
+
+            let symbols = ide
+                .document_symbols(&path)
+                .into_iter()
+                .filter_map(|symbol| {
+                    let range = line_index.line_col_lsp_range(symbol.range)?;
+                    #[allow(deprecated)]
+                    Some(lsp_types::DocumentSymbol {
+                        name: symbol.name,
+                        detail: None,
+                        kind: to_proto::symbol_kind(symbol.kind),
+                        tags: None,
+                        deprecated: None,
+                        range,
+                        selection_range: range,
+                        children: None,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            Some(DocumentSymbolResponse::Nested(symbols))
+        });
+
+        Ok(())
+    }
+
+    fn workspace_symbols(
+        &self,
+        id: RequestId,
+        params: WorkspaceSymbolParams,
+    ) -> anyhow::Result<()> {
+        // objects that are not defined in a workspace file can only point to the workspace itself
+        let root = self
+            .root
+            .as_ref()
+            .and_then(|root| lsp_types::Url::from_directory_path(root).ok());
+
+        self.run_query(id, move |ide| {
+            let symbols = ide
+                .workspace_symbols(&params.query)
+                .into_iter()
+                .filter_map(|symbol| {
+                    let location = match &symbol.location {
+                        Some((path, range)) => lsp_types::OneOf::Left(to_proto::location(
+                            path,
+                            &ide.line_index(path)?,
+                            *range,
+                        )?),
+                        None => lsp_types::OneOf::Right(lsp_types::WorkspaceLocation {
+                            uri: root.clone()?,
+                        }),
+                    };
+                    Some(lsp_types::WorkspaceSymbol {
+                        name: symbol.name,
+                        kind: to_proto::symbol_kind(Some(symbol.kind)),
+                        tags: None,
+                        container_name: Some(symbol.schema),
+                        location,
+                        data: None,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            Some(WorkspaceSymbolResponse::Nested(symbols))
+        });
+
+        Ok(())
+    }
+
+    fn semantic_tokens_full(
+        &self,
+        id: RequestId,
+        mut params: lsp_types::SemanticTokensParams,
+    ) -> anyhow::Result<()> {
+        normalize_uri(&mut params.text_document.uri);
+        let cache = self.semantic_tokens.clone();
+
+        self.run_query(id, move |ide| {
+            let path = file_path(&params.text_document.uri);
+            let (tokens, _) = cache.update(ide, &path)?;
+
+            Some(lsp_types::SemanticTokensResult::Tokens(
+                lsp_types::SemanticTokens {
+                    result_id: Some(tokens.result_id.clone()),
+                    data: semantic_tokens::encode(&tokens.tokens),
+                },
+            ))
+        });
+
+        Ok(())
+    }
+
+    fn semantic_tokens_full_delta(
+        &self,
+        id: RequestId,
+        mut params: lsp_types::SemanticTokensDeltaParams,
+    ) -> anyhow::Result<()> {
+        normalize_uri(&mut params.text_document.uri);
+        let cache = self.semantic_tokens.clone();
+
+        self.run_query(id, move |ide| {
+            let path = file_path(&params.text_document.uri);
+            let (tokens, previous) = cache.update(ide, &path)?;
+            let data = semantic_tokens::encode(&tokens.tokens);
+
+            // the full tokens are sent if the client's tokens are not the ones we sent last
+            match previous.filter(|p| p.result_id == params.previous_result_id) {
+                Some(previous) => Some(lsp_types::SemanticTokensFullDeltaResult::TokensDelta(
+                    lsp_types::SemanticTokensDelta {
+                        result_id: Some(tokens.result_id.clone()),
+                        edits: semantic_tokens::diff(
+                            &semantic_tokens::encode(&previous.tokens),
+                            &data,
+                        ),
+                    },
+                )),
+                None => Some(lsp_types::SemanticTokensFullDeltaResult::Tokens(
+                    lsp_types::SemanticTokens {
+                        result_id: Some(tokens.result_id.clone()),
+                        data,
+                    },
+                )),
+            }
+        });
+
+        Ok(())
+    }
+
+    fn semantic_tokens_range(
+        &self,
+        id: RequestId,
+        mut params: lsp_types::SemanticTokensRangeParams,
+    ) -> anyhow::Result<()> {
+        normalize_uri(&mut params.text_document.uri);
+        let cache = self.semantic_tokens.clone();
+
+        self.run_query(id, move |ide| {
+            let path = file_path(&params.text_document.uri);
+            let line_index = ide.line_index(&path)?;
+            let range = line_index.offset_lsp_range(params.range)?;
+            let (tokens, _) = cache.update(ide, &path)?;
+
+            Some(lsp_types::SemanticTokensRangeResult::Tokens(
+                lsp_types::SemanticTokens {
+                    result_id: None,
+                    data: semantic_tokens::encode(&semantic_tokens::tokens_in_range(
+                        &tokens.tokens,
+                        &line_index,
+                        range,
+                    )),
+                },
+            ))
+        });
+
+        Ok(())
+    }
+
+    fn linked_editing_ranges(
+        &self,
+        id: RequestId,
+        mut params: lsp_types::LinkedEditingRangeParams,
+    ) -> anyhow::Result<()> {
+        normalize_uri(&mut params.text_document_position_params.text_document.uri);
+
+        self.run_query(id, move |ide| {
+            let path = file_path(&params.text_document_position_params.text_document.uri);
+            let doc = ide.documents.get(&path)?;
+
+            let pos = doc
+                .line_index
+                .offset_lsp(params.text_document_position_params.position)?;
+
+            let ranges = ide
+                .linked_editing_ranges(&path, pos)
+                .into_iter()
+                .filter_map(|range| doc.line_index.line_col_lsp_range(range))
+                .collect::<Vec<_>>();
+
+            if ranges.is_empty() {
+                None
+            } else {
+                Some(lsp_types::LinkedEditingRanges {
+                    ranges,
+                    // unquoted identifiers, so that e.g. typing a space ends linked editing
+                    word_pattern: Some("[A-Za-z_][A-Za-z0-9_$]*".to_string()),
+                })
+            }
+        });
+
+        Ok(())
+    }
+
+    fn prepare_rename(
+        &self,
+        id: RequestId,
+        mut params: TextDocumentPositionParams,
+    ) -> anyhow::Result<()> {
+        normalize_uri(&mut params.text_document.uri);
+
+        self.run_query(id, move |ide| {
+            let path = file_path(&params.text_document.uri);
+            let doc = ide.documents.get(&path)?;
+
+            let pos = doc.line_index.offset_lsp(params.position)?;
+            let range = ide.prepare_rename(&path, pos)?;
+
+            Some(lsp_types::PrepareRenameResponse::Range(
+                doc.line_index.line_col_lsp_range(range)?,
+            ))
+        });
+
+        Ok(())
+    }
+
+    fn rename(&self, id: RequestId, mut params: RenameParams) -> anyhow::Result<()> {
+        normalize_uri(&mut params.text_document_position.text_document.uri);
+
+        self.run_query(id, move |ide| {
+            let uri = params.text_document_position.text_document.uri;
+            let path = file_path(&uri);
+            let doc = ide.documents.get(&path)?;
+
+            let pos = doc
+                .line_index
+                .offset_lsp(params.text_document_position.position)?;
+
+            let edits = ide
+                .rename(&path, pos, &params.new_name)
+                .into_iter()
+                .filter_map(|(range, new_text)| {
+                    Some(TextEdit {
+                        range: doc.line_index.line_col_lsp_range(range)?,
+                        new_text,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            if edits.is_empty() {
+                None
+            } else {
+                Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri, edits)])),
+                    ..Default::default()
+                })
+            }
+        });
+
+        Ok(())
+    }
+
+    fn execute_command(
+        &mut self,
+        id: RequestId,
+        params: ExecuteCommandParams,
+    ) -> anyhow::Result<()> {
         match CommandType::from_id(params.command.replace("pglsp.", "").as_str()) {
             Some(CommandType::ExecuteStatement) => {
+                let connection = self.command_connection(&params.arguments);
+
+                // the optional third argument confirms a statement that modifies the database
+                let confirmed = params
+                    .arguments
+                    .get(2)
+                    .and_then(|options| options.get("confirmed"))
+                    .and_then(|confirmed| confirmed.as_bool())
+                    .unwrap_or(false);
+
                 let stmt = self.parse_command_params::<String>(params.arguments)?;
 
-                let command = ExecuteStatementCommand::new(stmt);
+                let options = &self.options.execution;
+                let command = ExecuteStatementCommand::new(
+                    stmt,
+                    ExecuteOptions {
+                        row_limit: options.row_limit,
+                        timeout: Some(Duration::from_secs(options.timeout))
+                            .filter(|t| !t.is_zero()),
+                        confirmed: confirmed || !options.confirm_writes,
+                    },
+                );
 
-                let conn = self.db_conn.as_ref().map(|p| p.pool.clone());
+                let conn = match &self.execution_conn {
+                    Some(conn) => Some(conn.pool.clone()),
+                    None => self.db_conns.get(&connection).map(|p| p.pool.clone()),
+                };
 
                 let client = self.client.clone();
 
-                self.run_fallible(id, move || {
-                    // todo return the rows and do something with them
-                    // maybe store them and add the table to the hover output?
-                    let res = async_std::task::block_on(command.run(conn))?;
+                self.run_fallible(id, move |token| {
+                    let res = async_std::task::block_on(cancellable(token, command.run(conn)))
+                        .ok_or_else(|| anyhow::anyhow!("Request cancelled"))??;
 
                     // todo if its a ddl statement, recompute schema cache
 
+                    if let ExecuteResult::Completed(result) = &res {
+                        client
+                            .send_notification::<ShowMessage>(ShowMessageParams {
+                                typ: lsp_types::MessageType::INFO,
+                                message: format!(
+                                    "Success! Rows: {}, affected rows: {}",
+                                    result.rows.len(),
+                                    result.rows_affected
+                                ),
+                            })
+                            .unwrap();
+                    }
+
+                    Ok(res)
+                });
+            }
+            Some(CommandType::ExplainStatement) => {
+                let connection = self.command_connection(&params.arguments);
+
+                // the optional third argument requests to analyze the statement
+                let analyze = params
+                    .arguments
+                    .get(2)
+                    .and_then(|options| options.get("analyze"))
+                    .and_then(|analyze| analyze.as_bool())
+                    .unwrap_or(false);
+                if analyze && !self.options.execution.allow_explain_analyze {
+                    self.client
+                        .send_error(
+                            id,
+                            ErrorCode::InvalidRequest,
+                            "EXPLAIN ANALYZE is disabled by the execution options".to_string(),
+                        )
+                        .unwrap();
+                    return Ok(());
+                }
+
+                let stmt = self.parse_command_params::<String>(params.arguments)?;
+
+                let command = ExplainStatementCommand::new(stmt, analyze);
+
+                let conn = match &self.execution_conn {
+                    Some(conn) => Some(conn.pool.clone()),
+                    None => self.db_conns.get(&connection).map(|p| p.pool.clone()),
+                };
+
+                let client = self.client.clone();
+
+                self.run_fallible(id, move |token| {
+                    let plan = async_std::task::block_on(cancellable(token, command.run(conn)))
+                        .ok_or_else(|| anyhow::anyhow!("Request cancelled"))??;
+
                     client
                         .send_notification::<ShowMessage>(ShowMessageParams {
                             typ: lsp_types::MessageType::INFO,
-                            message: format!("Success! Affected rows: {}", res.rows_affected()),
+                            message: plan.clone(),
                         })
                         .unwrap();
 
-                    Ok(())
+                    Ok(plan)
                 });
             }
+            Some(CommandType::Reconnect) => {
+                self.internal_tx.send(InternalMessage::Reconnect).unwrap();
+
+                self.client
+                    .send_response(lsp_server::Response::new_ok(id, ()))
+                    .unwrap();
+            }
+            Some(CommandType::ReloadSchemaCache) => {
+                if self.db_conns.is_empty() {
+                    self.client
+                        .send_error(
+                            id,
+                            ErrorCode::RequestFailed,
+                            "No connection to database".to_string(),
+                        )
+                        .unwrap();
+                } else {
+                    // e.g. functions or data that statements are checked against may have
+                    // changed without changing the schema cache
+                    self.revalidate_on_reload
+                        .extend(self.db_conns.keys().cloned());
+                    self.refresh_all_schema_caches();
+
+                    self.client
+                        .send_response(lsp_server::Response::new_ok(id, ()))
+                        .unwrap();
+                }
+            }
             None => {
                 self.client
                     .send_error(
@@ -681,25 +2141,66 @@ impl Server {
         Ok(())
     }
 
+    /// Returns the connection of the document passed as the optional second argument of a
+    /// statement command, i.e. the document the statement is from
+    fn command_connection(&self, arguments: &[serde_json::Value]) -> String {
+        arguments
+            .get(1)
+            .and_then(|uri| serde_json::from_value::<lsp_types::Url>(uri.clone()).ok())
+            .map(|mut uri| {
+                normalize_uri(&mut uri);
+                self.ide.connection(&file_path(&uri))
+            })
+            .unwrap_or_else(|| DEFAULT_CONNECTION.to_string())
+    }
+
+    /// Runs a request that may fail on the thread pool
+    ///
+    /// The query is passed the token that tells whether the client cancelled the request, so that
+    /// it can stop e.g. database queries early.
     fn run_fallible<R, Q>(&self, id: RequestId, query: Q)
     where
         R: Serialize,
-        Q: FnOnce() -> anyhow::Result<R> + Send + 'static,
+        Q: FnOnce(&CancellationToken) -> anyhow::Result<R> + Send + 'static,
     {
         let client = self.client.clone();
-        self.pool.execute(move || match query() {
-            Ok(result) => {
-                let response = lsp_server::Response::new_ok(id, result);
-                client.send_response(response).unwrap();
+        let pending = self.pending_requests.clone();
+        let token = pending.register(id.clone());
+        self.pool.execute(move || {
+            // requests cancelled while they were queued are not processed at all
+            if token.is_cancelled() {
+                Self::send_cancelled(&client, id);
+                return;
             }
-            Err(why) => {
-                client
-                    .send_error(id, ErrorCode::InternalError, why.to_string())
-                    .unwrap();
+
+            let result = query(&token);
+            pending.complete(&id);
+
+            match result {
+                _ if token.is_cancelled() => Self::send_cancelled(&client, id),
+                Ok(result) => {
+                    let response = lsp_server::Response::new_ok(id, result);
+                    client.send_response(response).unwrap();
+                }
+                Err(why) => {
+                    client
+                        .send_error(id, ErrorCode::InternalError, why.to_string())
+                        .unwrap();
+                }
             }
         });
     }
 
+    fn send_cancelled(client: &LspClient, id: RequestId) {
+        client
+            .send_error(
+                id,
+                ErrorCode::RequestCanceled,
+                "Request cancelled".to_string(),
+            )
+            .unwrap();
+    }
+
     fn parse_command_params<T: DeserializeOwned>(
         &self,
         params: Vec<serde_json::Value>,
@@ -717,35 +2218,345 @@ impl Server {
     where
         R: Serialize,
         Q: FnOnce(&Workspace) -> R + Send + 'static,
+    {
+        self.run_cancellable_query(id, move |ide, _| query(ide));
+    }
+
+    /// Like `run_query`, but passes the query the token that tells whether the client cancelled
+    /// the request, so that it can stop between statements it checks against the database
+    fn run_cancellable_query<R, Q>(&self, id: RequestId, query: Q)
+    where
+        R: Serialize,
+        Q: FnOnce(&Workspace, &CancellationToken) -> R + Send + 'static,
     {
         let client = self.client.clone();
         let ide = Arc::clone(&self.ide);
+        let pending = self.pending_requests.clone();
+        let token = pending.register(id.clone());
 
         self.pool.execute(move || {
-            let response = lsp_server::Response::new_ok(id, query(&ide));
+            // requests cancelled while they were queued are not processed at all
+            if token.is_cancelled() {
+                Self::send_cancelled(&client, id);
+                return;
+            }
+
+            let result = query(&ide, &token);
+            pending.complete(&id);
+
+            if token.is_cancelled() {
+                Self::send_cancelled(&client, id);
+                return;
+            }
+
+            let response = lsp_server::Response::new_ok(id, result);
             client.send_response(response).unwrap();
         });
     }
 
-    fn refresh_schema_cache(&self) {
-        if self.db_conn.is_none() {
+    /// Cancels a long running operation whose progress is shown, e.g. loading a schema cache
+    fn cancel_progress(
+        &self,
+        params: lsp_types::WorkDoneProgressCancelParams,
+    ) -> anyhow::Result<()> {
+        self.progress.cancel(&params.token);
+        Ok(())
+    }
+
+    /// Cancels a request the client is no longer interested in
+    fn cancel(&self, params: lsp_types::CancelParams) -> anyhow::Result<()> {
+        let id = match params.id {
+            lsp_types::NumberOrString::Number(id) => RequestId::from(id),
+            lsp_types::NumberOrString::String(id) => RequestId::from(id),
+        };
+        self.pending_requests.cancel(&id);
+        Ok(())
+    }
+
+    /// Indexes the objects defined and referenced in the sql files of the workspace, e.g.
+    /// migrations, so that references can be resolved to them and found within them
+    fn index_workspace_files(&self) {
+        let Some(root) = self.root.clone() else {
             return;
+        };
+        let ide = self.ide.clone();
+        let progress = self.progress.clone();
+        let max_size = self.options.files.max_size;
+
+        self.pool.execute(move || {
+            let mut files = Vec::new();
+            if sql_files(&root, &mut files).is_err() {
+                return;
+            }
+
+            let progress = progress.begin("Indexing workspace", true);
+            let total = files.len();
+            for (idx, file) in files.into_iter().enumerate() {
+                if progress.cancellation().is_cancelled() {
+                    break;
+                }
+                progress.report(format!("{}/{} files", idx, total), idx, total);
+
+                let path = PgLspPath::new(file);
+                // open documents are indexed as they change
+                if ide.documents.contains_key(&path) {
+                    continue;
+                }
+                if let Ok(Ok(text)) = read_sql_file(path.as_path(), max_size) {
+                    ide.objects.index_file(path, text);
+                }
+            }
+        });
+    }
+
+    /// Builds the schema cache from a schema dump and sql files for when there is no database
+    /// connection. The files are applied on top of the dump.
+    fn load_offline_schema_cache(&self, dump: Option<PathBuf>, files: Vec<PathBuf>) {
+        let tx = self.internal_tx.clone();
+        let client = self.client.clone();
+        let progress = self.progress.clone();
+
+        self.pool.execute(move || {
+            let _progress = progress.begin("Loading schema files", false);
+            let schema_cache = dump
+                .map(|dump| SchemaCache::from_dump(&dump))
+                .transpose()
+                .and_then(|schema_cache| {
+                    let mut schema_cache = schema_cache.unwrap_or_else(SchemaCache::new);
+                    for sql in SchemaCache::read_sql_files(&files)? {
+                        schema_cache.apply_sql(&sql);
+                    }
+                    Ok(schema_cache)
+                });
+
+            match schema_cache {
+                Ok(schema_cache) => {
+                    tx.send(InternalMessage::SetSchemaCache(
+                        DEFAULT_CONNECTION.to_string(),
+                        None,
+                        Box::new(schema_cache),
+                    ))
+                    .unwrap();
+                }
+                Err(e) => {
+                    client
+                        .send_notification::<ShowMessage>(ShowMessageParams {
+                            typ: lsp_types::MessageType::WARNING,
+                            message: format!("Failed to read schema files: {}", e),
+                        })
+                        .unwrap();
+                }
+            }
+        });
+    }
+
+    /// Returns the directory and connection string the schema cache of a connection is persisted
+    /// for, if enabled
+    fn persisted_schema_cache_location(&self, name: &str) -> Option<(PathBuf, String)> {
+        if !self.options.persist_schema_cache {
+            return None;
+        }
+
+        let mut dir = pg_schema_cache::default_cache_dir()?;
+        if let Some(profile) = &self.options.profile {
+            dir = dir.join("profiles").join(profile);
+        }
+        let conn = self.db_conns.get(name)?;
+
+        Some((dir, conn.connection_string.clone()))
+    }
+
+    /// Sets the schema cache of the daemon of the root, or the one persisted in a previous
+    /// session, so that it is available while the cache is being reloaded
+    fn restore_schema_cache(&self, name: &str) {
+        let from_daemon = || {
+            #[cfg(unix)]
+            {
+                let root = self.root.as_deref()?;
+                let conn = self.db_conns.get(name)?;
+                crate::daemon::schema_cache(root, &conn.connection_string)
+            }
+            #[cfg(not(unix))]
+            None
+        };
+        let from_disk = || {
+            let (dir, connection_string) = self.persisted_schema_cache_location(name)?;
+            SchemaCache::read_from_disk(&dir, &connection_string)
+        };
+
+        if let Some(schema_cache) = from_daemon().or_else(from_disk) {
+            self.internal_tx
+                .send(InternalMessage::SetSchemaCache(
+                    name.to_string(),
+                    None,
+                    Box::new(schema_cache),
+                ))
+                .unwrap();
         }
+    }
+
+    fn refresh_all_schema_caches(&mut self) {
+        for name in self.db_conns.keys().cloned().collect::<Vec<_>>() {
+            self.refresh_schema_cache(&name);
+        }
+    }
+
+    fn refresh_schema_cache(&mut self, name: &str) {
+        let sequence = self.schema_refreshes.start(name);
+        let Some(conn) = self.db_conns.get(name) else {
+            return;
+        };
 
         let tx = self.internal_tx.clone();
-        let conn = self.db_conn.as_ref().unwrap().pool.clone();
+        let conn = conn.pool.clone();
         let client = self.client.clone();
+        let lazy = self.options.lazy_schema_loading;
+        // the schemas that were loaded on demand are loaded again
+        let loaded_schemas: Vec<String> = self
+            .ide
+            .schema_cache(name)
+            .loaded_schemas
+            .iter()
+            .flatten()
+            .cloned()
+            .collect();
+        let persist = self.persisted_schema_cache_location(name);
+        let current_version = self.ide.schema_version(name);
+        let name = name.to_string();
+        let progress = self.progress.clone();
+
+        // the progress is created on the thread pool, since creating it waits for the client
+        self.pool.execute(move || {
+            if !progress.enabled() {
+                client
+                    .send_notification::<ShowMessage>(ShowMessageParams {
+                        typ: lsp_types::MessageType::INFO,
+                        message: format!("Refreshing schema cache of {}...", name),
+                    })
+                    .unwrap();
+            }
+            let progress = progress.begin(&format!("Loading schema cache of {}", name), true);
+
+            let load = async {
+                if lazy {
+                    SchemaCache::load_lazy(&conn, &loaded_schemas).await
+                } else {
+                    SchemaCache::load(&conn).await
+                }
+            };
+            let Some(schema_cache) =
+                async_std::task::block_on(cancellable(progress.cancellation(), load))
+            else {
+                progress.end(Some("Cancelled".to_string()));
+                return;
+            };
+
+            // the persisted cache is still up to date if the schema did not change
+            let persist = persist.filter(|_| schema_cache.version_hash() != current_version);
+
+            if let Some((dir, connection_string)) = persist {
+                if let Err(e) = schema_cache.write_to_disk(&dir, &connection_string) {
+                    client
+                        .send_notification::<ShowMessage>(ShowMessageParams {
+                            typ: lsp_types::MessageType::WARNING,
+                            message: format!("Failed to persist schema cache: {}", e),
+                        })
+                        .unwrap();
+                }
+            }
+            tx.send(InternalMessage::SetSchemaCache(
+                name,
+                Some(sequence),
+                Box::new(schema_cache),
+            ))
+            .unwrap();
+        });
+    }
+
+    /// Reloads the given classes of objects in the background. Only the reloaded classes are
+    /// replaced once they are loaded, so that loads which finish in the meantime are kept.
+    fn refresh_schema_cache_classes(&mut self, name: String, classes: HashSet<SchemaCacheClass>) {
+        let sequence = self.schema_refreshes.start(&name);
+        let Some(conn) = self.db_conns.get(&name) else {
+            return;
+        };
+
+        let tx = self.internal_tx.clone();
+        let conn = conn.pool.clone();
+        let loaded_schemas = self.ide.schema_cache(&name).loaded_schemas.clone();
 
         async_std::task::spawn(async move {
-            client
-                .send_notification::<ShowMessage>(ShowMessageParams {
-                    typ: lsp_types::MessageType::INFO,
-                    message: "Refreshing schema cache...".to_string(),
-                })
-                .unwrap();
-            let schema_cache = SchemaCache::load(&conn).await;
-            tx.send(InternalMessage::SetSchemaCache(schema_cache))
-                .unwrap();
+            let update = SchemaCacheUpdate::load_classes(&conn, classes, loaded_schemas).await;
+            tx.send(InternalMessage::MergeSchemaCacheClasses(
+                name,
+                sequence,
+                Box::new(update),
+            ))
+            .unwrap();
+        });
+    }
+
+    /// Sets the schema cache of a connection and checks the documents against it again
+    fn set_schema_cache(&mut self, name: &str, schema_cache: SchemaCache) {
+        self.ide.set_schema_cache(name, schema_cache);
+        if self.revalidate_on_reload.remove(name) {
+            self.ide.invalidate_connection(name);
+        }
+        self.compute_now();
+    }
+
+    /// Applies objects that were loaded in the background to the schema cache as it is now
+    fn merge_schema_cache(&mut self, name: &str, update: SchemaCacheUpdate) {
+        let mut schema_cache = SchemaCache::clone(&self.ide.schema_cache(name));
+        schema_cache.apply_update(update);
+        self.set_schema_cache(name, schema_cache);
+    }
+
+    /// Loads the schemas that are referenced in the document but were not loaded yet
+    fn load_referenced_schemas(&self, path: &PgLspPath) {
+        let name = self.ide.connection(path);
+
+        let Some(conn) = self.db_conns.get(&name) else {
+            return;
+        };
+
+        let Some(doc) = self.ide.documents.get(path) else {
+            return;
+        };
+
+        let schema_cache = self.ide.schema_cache(&name);
+
+        let schemas: Vec<String> = {
+            let mut loading_schemas = self.loading_schemas.lock().unwrap();
+
+            schema_cache
+                .unloaded_schema_references(&doc.text)
+                .into_iter()
+                .filter(|s| loading_schemas.insert((name.clone(), s.clone())))
+                .collect()
+        };
+
+        if schemas.is_empty() {
+            return;
+        }
+
+        let tx = self.internal_tx.clone();
+        let conn = conn.pool.clone();
+        let loading_schemas = self.loading_schemas.clone();
+
+        // only the loaded objects are sent, since other loads may change the cache meanwhile
+        async_std::task::spawn(async move {
+            let update = SchemaCacheUpdate::load_schemas(&conn, &schemas).await;
+            tx.send(InternalMessage::MergeSchemas(
+                name.clone(),
+                Box::new(update),
+            ))
+            .unwrap();
+
+            let mut loading_schemas = loading_schemas.lock().unwrap();
+            for schema in schemas {
+                loading_schemas.remove(&(name.clone(), schema));
+            }
         });
     }
 
@@ -756,7 +2567,10 @@ impl Server {
         if self.client_flags.configuration_pull {
             self.pull_options();
         } else {
-            let options = self.client.parse_options(params.settings)?;
+            self.settings = params.settings;
+            let options = self
+                .client
+                .parse_options(self.root.as_deref(), self.settings.clone())?;
             self.update_options(options);
         }
 
@@ -776,6 +2590,36 @@ impl Server {
                             if let Some(response) = dispatch::RequestDispatcher::new(request)
                                 .on::<InlayHintRequest, _>(|id, params| self.inlay_hint(id, params))?
                                 .on::<HoverRequest, _>(|id, params| self.hover(id, params))?
+                                .on::<GotoDefinition, _>(|id, params| self.definition(id, params))?
+                                .on::<References, _>(|id, params| self.references(id, params))?
+                                .on::<FoldingRangeRequest, _>(|id, params| {
+                                    self.folding_ranges(id, params)
+                                })?
+                                .on::<SelectionRangeRequest, _>(|id, params| {
+                                    self.selection_ranges(id, params)
+                                })?
+                                .on::<DocumentSymbolRequest, _>(|id, params| {
+                                    self.document_symbols(id, params)
+                                })?
+                                .on::<WorkspaceSymbolRequest, _>(|id, params| {
+                                    self.workspace_symbols(id, params)
+                                })?
+                                .on::<SemanticTokensFullRequest, _>(|id, params| {
+                                    self.semantic_tokens_full(id, params)
+                                })?
+                                .on::<SemanticTokensFullDeltaRequest, _>(|id, params| {
+                                    self.semantic_tokens_full_delta(id, params)
+                                })?
+                                .on::<SemanticTokensRangeRequest, _>(|id, params| {
+                                    self.semantic_tokens_range(id, params)
+                                })?
+                                .on::<LinkedEditingRange, _>(|id, params| {
+                                    self.linked_editing_ranges(id, params)
+                                })?
+                                .on::<PrepareRenameRequest, _>(|id, params| {
+                                    self.prepare_rename(id, params)
+                                })?
+                                .on::<Rename, _>(|id, params| self.rename(id, params))?
                                 .on::<ExecuteCommand,_>(|id, params| self.execute_command(id, params))?
                                 .on::<Completion, _>(|id, params| {
                                     self.completion(id, params)
@@ -783,6 +2627,18 @@ impl Server {
                                 .on::<CodeActionRequest, _>(|id, params| {
                                     self.code_actions(id, params)
                                 })?
+                                .on::<DocumentDiagnosticRequest, _>(|id, params| {
+                                    self.document_diagnostic(id, params)
+                                })?
+                                .on::<WorkspaceDiagnosticRequest, _>(|id, params| {
+                                    self.workspace_diagnostic(id, params)
+                                })?
+                                .on::<AnalyzeEmbedded, _>(|id, params| {
+                                    self.analyze_embedded(id, params)
+                                })?
+                                .on::<CodeLensRequest, _>(|id, params| {
+                                    self.code_lens(id, params)
+                                })?
                                 .default()
                             {
                                 self.client.send_response(response)?;
@@ -790,10 +2646,17 @@ impl Server {
                         }
                         Message::Notification(notification) => {
                             dispatch::NotificationDispatcher::new(notification)
+                                .on::<Cancel, _>(|params| self.cancel(params))?
+                                .on::<WorkDoneProgressCancel, _>(|params| {
+                                    self.cancel_progress(params)
+                                })?
                                 .on::<DidChangeConfiguration, _>(|params| {
                                     self.did_change_configuration(params)
                                 })?
                                 .on::<DidCloseTextDocument, _>(|params| self.did_close(params))?
+                                .on::<DidChangeWatchedFiles, _>(|params| {
+                                    self.did_change_watched_files(params)
+                                })?
                                 .on::<DidOpenTextDocument, _>(|params| self.did_open(params))?
                                 .on::<DidChangeTextDocument, _>(|params| self.did_change(params))?
                                 .on::<DidSaveTextDocument, _>(|params| self.did_save(params))?
@@ -807,18 +2670,81 @@ impl Server {
                 },
                 recv(&self.internal_rx) -> msg => {
                     match msg? {
-                        InternalMessage::SetSchemaCache(c) => {
-                            self.ide.set_schema_cache(c);
-                            self.compute_now();
+                        InternalMessage::SetSchemaCache(name, None, c) => {
+                            self.set_schema_cache(&name, *c);
+                        }
+                        InternalMessage::SetSchemaCache(name, Some(sequence), c) => {
+                            // only the classes that no newer refresh replaced in the meantime
+                            // are applied
+                            match self.schema_refreshes.finish_all(&name, sequence, *c) {
+                                Some(RefreshedSchemaCache::All(c)) => {
+                                    self.set_schema_cache(&name, c);
+                                    // schemas may have been loaded on demand while it reloaded
+                                    let paths: Vec<_> = self
+                                        .ide
+                                        .documents
+                                        .iter()
+                                        .map(|doc| doc.key().clone())
+                                        .filter(|path| self.ide.connection(path) == name)
+                                        .collect();
+                                    for path in paths {
+                                        self.load_referenced_schemas(&path);
+                                    }
+                                }
+                                Some(RefreshedSchemaCache::Classes(update)) => {
+                                    self.merge_schema_cache(&name, update);
+                                }
+                                None => {}
+                            }
+                        }
+                        InternalMessage::MergeSchemas(name, update) => {
+                            self.merge_schema_cache(&name, *update);
+                        }
+                        InternalMessage::MergeSchemaCacheClasses(name, sequence, mut update) => {
+                            self.schema_refreshes
+                                .finish(&name, sequence, &mut update.classes);
+                            if !update.classes.is_empty() {
+                                self.merge_schema_cache(&name, *update);
+                            }
+                        }
+                        InternalMessage::RefreshSchemaCache(Some(name)) => {
+                            self.refresh_schema_cache(&name);
                         }
-                        InternalMessage::RefreshSchemaCache => {
-                            self.refresh_schema_cache();
+                        InternalMessage::RefreshSchemaCache(None) => {
+                            self.refresh_all_schema_caches();
+                        }
+                        InternalMessage::RefreshSchemaCacheClasses(name, classes) => {
+                            self.refresh_schema_cache_classes(name, classes);
                         }
                         InternalMessage::PublishDiagnostics(uri) => {
                             self.publish_diagnostics(uri)?;
                         }
                         InternalMessage::SetOptions(options) => {
-                            self.update_options(options);
+                            self.update_options(*options);
+                        }
+                        InternalMessage::Connected(name, conn) => {
+                            // the connection may have been removed or changed in the meantime
+                            match self.reconnect_tasks.remove(&name) {
+                                Some((connection_string, _))
+                                    if connection_string == conn.connection_string =>
+                                {
+                                    self.connected(name, conn);
+                                }
+                                _ => async_std::task::block_on(conn.pool.close()),
+                            }
+                        }
+                        InternalMessage::ConnectionLost(name) => {
+                            if let Some(conn) = self.db_conns.remove(&name) {
+                                async_std::task::block_on(conn.pool.close());
+                                self.reconnect(
+                                    name,
+                                    conn.connection_string,
+                                    "Connection lost".to_string(),
+                                );
+                            }
+                        }
+                        InternalMessage::Reconnect => {
+                            async_std::task::block_on(self.reconnect_all());
                         }
                     };
                 }
@@ -840,17 +2766,23 @@ impl Server {
 
         let client = self.client.clone();
         let sender = self.internal_tx.clone();
+        let root = self.root.clone();
         self.pool.execute(move || {
             match client.send_request::<WorkspaceConfiguration>(params) {
                 Ok(mut json) => {
                     let options = client
-                        .parse_options(json.pop().expect("invalid configuration request"))
+                        .parse_options(
+                            root.as_deref(),
+                            json.pop().expect("invalid configuration request"),
+                        )
                         .unwrap();
 
-                    sender.send(InternalMessage::SetOptions(options)).unwrap();
+                    sender
+                        .send(InternalMessage::SetOptions(Box::new(options)))
+                        .unwrap();
                 }
-                Err(_why) => {
-                    // log::error!("Retrieving configuration failed: {}", why);
+                Err(why) => {
+                    tracing::error!("Retrieving configuration failed: {}", why);
                 }
             };
         });
@@ -870,12 +2802,12 @@ impl Server {
 
             let client = self.client.clone();
             self.pool.execute(move || {
-                if let Err(_why) = client.send_request::<RegisterCapability>(params) {
-                    // log::error!(
-                    //     "Failed to register \"{}\" notification: {}",
-                    //     DidChangeConfiguration::METHOD,
-                    //     why
-                    // );
+                if let Err(why) = client.send_request::<RegisterCapability>(params) {
+                    tracing::error!(
+                        "Failed to register \"{}\" notification: {}",
+                        DidChangeConfiguration::METHOD,
+                        why
+                    );
                 }
             });
         }
@@ -883,7 +2815,9 @@ impl Server {
 
     pub fn run(mut self) -> anyhow::Result<()> {
         self.register_configuration();
+        self.register_file_watchers();
         self.pull_options();
+        self.index_workspace_files();
         self.process_messages()?;
         self.pool.join();
         Ok(())
@@ -0,0 +1,95 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use lsp_server::RequestId;
+
+/// Signals that the client is no longer interested in the result of a request
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Runs a future until it completes or the token is cancelled, in which case it is dropped and
+/// `None` is returned
+pub async fn cancellable<F: Future>(token: &CancellationToken, future: F) -> Option<F::Output> {
+    let mut future = Box::pin(future);
+    loop {
+        match async_std::future::timeout(Duration::from_millis(50), &mut future).await {
+            Ok(output) => return Some(output),
+            Err(_) if token.is_cancelled() => return None,
+            Err(_) => {}
+        }
+    }
+}
+
+/// The requests that are queued or being processed, by id
+#[derive(Debug, Clone, Default)]
+pub struct PendingRequests(Arc<Mutex<HashMap<RequestId, CancellationToken>>>);
+
+impl PendingRequests {
+    pub fn register(&self, id: RequestId) -> CancellationToken {
+        let token = CancellationToken::default();
+        self.0.lock().unwrap().insert(id, token.clone());
+        token
+    }
+
+    pub fn complete(&self, id: &RequestId) {
+        self.0.lock().unwrap().remove(id);
+    }
+
+    /// Cancels a request, if it is still pending
+    pub fn cancel(&self, id: &RequestId) {
+        if let Some(token) = self.0.lock().unwrap().remove(id) {
+            token.cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_std::task::block_on;
+    use lsp_server::RequestId;
+
+    use super::{cancellable, PendingRequests};
+
+    #[test]
+    fn test_cancel_request() {
+        let pending = PendingRequests::default();
+
+        let first = pending.register(RequestId::from(1));
+        let second = pending.register(RequestId::from(2));
+
+        pending.cancel(&RequestId::from(1));
+        pending.complete(&RequestId::from(2));
+        pending.cancel(&RequestId::from(2));
+
+        assert!(first.is_cancelled());
+        assert!(!second.is_cancelled());
+
+        assert_eq!(block_on(cancellable(&second, async { 1 })), Some(1));
+        assert_eq!(
+            block_on(cancellable(
+                &first,
+                async_std::task::sleep(Duration::from_secs(10))
+            )),
+            None
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// The number of failed attempts after which a connection is considered offline and no longer
+/// retried until the client asks to reconnect
+pub const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+
+/// The state of a database connection, e.g. to be shown in the status bar of the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConnectionState {
+    Connected,
+    /// The connection failed or was lost and is retried
+    Reconnecting,
+    /// The connection was removed or could not be established after all retries
+    Offline,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStatusParams {
+    /// The name of the connection, which is `default` for the connection used for all files
+    /// that are not mapped to another one
+    pub connection: String,
+    pub state: ConnectionState,
+    /// The error of the last attempt to connect
+    pub message: Option<String>,
+    /// Seconds until the next attempt to connect
+    pub retry_in: Option<u64>,
+}
+
+/// Sent whenever the state of a database connection changes
+pub enum ConnectionStatus {}
+
+impl lsp_types::notification::Notification for ConnectionStatus {
+    type Params = ConnectionStatusParams;
+    const METHOD: &'static str = "pglsp/connectionStatus";
+}
+
+/// Returns the delay before an attempt to connect, which doubles with every failed attempt up
+/// to a minute
+pub fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs(2u64.saturating_pow(attempt).min(60))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::backoff;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(0), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(8));
+        assert_eq!(backoff(6), Duration::from_secs(60));
+        assert_eq!(backoff(100), Duration::from_secs(60));
+    }
+}
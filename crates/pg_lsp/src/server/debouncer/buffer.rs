@@ -58,6 +58,11 @@ impl<T> EventBuffer<T> {
     pub fn clear(&mut self) {
         self.events.clear();
     }
+
+    /// Changes the delay of the events put from now on
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay;
+    }
 }
 
 impl<T> Get for EventBuffer<T> {
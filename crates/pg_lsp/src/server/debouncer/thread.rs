@@ -69,6 +69,10 @@ impl<T> EventDebouncer<T> {
         self.0.mutex.lock().unwrap().clear();
     }
 
+    pub fn set_delay(&self, delay: Duration) {
+        self.0.mutex.lock().unwrap().set_delay(delay);
+    }
+
     /// Signals the debouncer thread to quit and returns a
     /// [std::thread::JoinHandle] which can be `.join()`ed in the consumer
     /// thread. The common idiom is: `debouncer.stop().join().unwrap();`
@@ -35,8 +35,8 @@ impl NotificationDispatcher {
     }
 
     pub fn default(self) {
-        if let Some(_not) = &self.not {
-            // log::warn!("Unknown notification: {}", not.method);
+        if let Some(not) = &self.not {
+            tracing::warn!("Unknown notification: {}", not.method);
         }
     }
 }
@@ -75,7 +75,7 @@ impl RequestDispatcher {
 
     pub fn default(self) -> Option<Response> {
         self.req.map(|req| {
-            // log::warn!("Unknown request: {}", req.method);
+            tracing::warn!("Unknown request: {}", req.method);
             Response::new_err(
                 req.id,
                 ErrorCode::MethodNotFound as i32,
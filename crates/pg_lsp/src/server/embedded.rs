@@ -0,0 +1,117 @@
+use lsp_types::{CompletionList, Diagnostic, Position, Range, Url};
+use serde::{Deserialize, Serialize};
+
+/// Analyses sql embedded in a document of another language, e.g. in `sqlx::query!` macros or
+/// template strings, which the client extracted from the document
+pub enum AnalyzeEmbedded {}
+
+impl lsp_types::request::Request for AnalyzeEmbedded {
+    type Params = AnalyzeEmbeddedParams;
+    type Result = AnalyzeEmbeddedResult;
+    const METHOD: &'static str = "pglsp/analyzeEmbedded";
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyzeEmbeddedParams {
+    /// The document the snippets are embedded in, whose connection they are checked against
+    pub host_uri: Url,
+    pub snippets: Vec<EmbeddedSnippet>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddedSnippet {
+    /// A virtual uri that identifies the snippet, e.g. `embedded:///src/main.rs/3.sql`
+    pub uri: Url,
+    /// The sql as it appears in the host document
+    pub text: String,
+    /// The position of the first character of the snippet in the host document
+    pub start: Position,
+    /// A position in the host document within the snippet to return completions for
+    pub completion_position: Option<Position>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyzeEmbeddedResult {
+    pub snippets: Vec<EmbeddedSnippetResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddedSnippetResult {
+    pub uri: Url,
+    /// The diagnostics of the snippet with ranges in the host document
+    pub diagnostics: Vec<Diagnostic>,
+    pub completions: Option<CompletionList>,
+}
+
+/// Maps positions between a snippet and the host document, which contains the text of the
+/// snippet verbatim starting at `start`
+#[derive(Debug, Clone, Copy)]
+pub struct SnippetMapping {
+    pub start: Position,
+}
+
+impl SnippetMapping {
+    pub fn host_position(&self, position: Position) -> Position {
+        if position.line == 0 {
+            Position::new(self.start.line, self.start.character + position.character)
+        } else {
+            Position::new(self.start.line + position.line, position.character)
+        }
+    }
+
+    pub fn host_range(&self, range: Range) -> Range {
+        Range::new(
+            self.host_position(range.start),
+            self.host_position(range.end),
+        )
+    }
+
+    /// Returns the position within the snippet, or `None` if the position is before it
+    pub fn snippet_position(&self, position: Position) -> Option<Position> {
+        if position.line == self.start.line {
+            let character = position.character.checked_sub(self.start.character)?;
+            Some(Position::new(0, character))
+        } else {
+            let line = position.line.checked_sub(self.start.line)?;
+            Some(Position::new(line, position.character))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Position;
+
+    use super::SnippetMapping;
+
+    #[test]
+    fn test_snippet_mapping() {
+        let mapping = SnippetMapping {
+            start: Position::new(4, 20),
+        };
+
+        assert_eq!(
+            mapping.host_position(Position::new(0, 7)),
+            Position::new(4, 27)
+        );
+        assert_eq!(
+            mapping.host_position(Position::new(2, 3)),
+            Position::new(6, 3)
+        );
+
+        assert_eq!(
+            mapping.snippet_position(Position::new(4, 27)),
+            Some(Position::new(0, 7))
+        );
+        assert_eq!(
+            mapping.snippet_position(Position::new(6, 3)),
+            Some(Position::new(2, 3))
+        );
+        assert_eq!(mapping.snippet_position(Position::new(4, 2)), None);
+        assert_eq!(mapping.snippet_position(Position::new(3, 30)), None);
+    }
+}
@@ -1,8 +1,556 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use pg_diagnostics::Severity;
+use pg_lint::RuleViolationKind;
+use pg_workspace::DEFAULT_CONNECTION;
+use schemars::{schema::RootSchema, JsonSchema};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 #[serde(default)]
 pub struct Options {
+    /// The connection used for all files that are not mapped to one of the `connections`
     pub db_connection_string: Option<String>,
+    /// Additional named connections, each with its own schema cache
+    pub connections: Vec<ConnectionOptions>,
+    /// Connection profiles by name, e.g. `local`, `staging` and `ci`, of which `profile` selects
+    /// one
+    pub profiles: HashMap<String, ProfileOptions>,
+    /// The profile whose connection is used instead of `dbConnectionString`. The schema caches
+    /// of each profile are persisted separately.
+    pub profile: Option<String>,
+    /// The TLS mode of all connections, which overrides the `sslmode` of their connection strings
+    pub ssl_mode: Option<SslMode>,
+    /// The certificate authority the certificate of the server is verified against, e.g. with
+    /// `verify-ca`
+    pub ssl_root_cert: Option<PathBuf>,
+    /// An SSH tunnel all connections are opened through, e.g. to a database that can only be
+    /// reached through a bastion host
+    pub ssh_tunnel: Option<SshTunnelOptions>,
+    /// Install an event trigger that notifies the server about DDL changes, so that only the
+    /// affected parts of the schema cache are refreshed
+    pub install_event_trigger: bool,
+    /// Interval in seconds in which the schema cache is reloaded. Disabled if not set or zero.
+    pub schema_cache_refresh_interval: Option<u64>,
+    /// Only load the schemas in the `search_path` on startup and load other schemas once they are
+    /// referenced
+    pub lazy_schema_loading: bool,
+    /// Persist the schema cache to disk, so that it is available immediately on the next start
+    /// while it is revalidated against the database in the background
+    pub persist_schema_cache: bool,
+    /// A `pg_dump --schema-only` output in plain or custom format to build the schema cache from
+    /// if there is no database connection
+    pub schema_dump: Option<PathBuf>,
+    /// Sql files or directories of sql files, e.g. a `structure.sql` or a migrations directory,
+    /// to build the schema cache from if there is no database connection. They are applied on
+    /// top of the schema dump, if any.
+    pub schema_files: Vec<PathBuf>,
+    pub files: FilesOptions,
+    pub exit_codes: ExitCodesOptions,
+    pub inlay_hints: InlayHintsOptions,
+    pub execution: ExecutionOptions,
+    pub diagnostics: DiagnosticsOptions,
+    /// The lint rules by name, with their severity, e.g. `{ "ban-drop-column": "error" }`, or
+    /// with their severity and options, e.g. `{ "level": "error", "options": { ... } }`. Options
+    /// apply to the whole workspace, even in a `pglsp.toml` of a subdirectory.
+    pub lint: HashMap<String, RuleConfig>,
+    /// The settings of the `pglsp.toml` files in subdirectories of the workspace, which are read
+    /// from the files rather than given as settings
+    #[serde(skip_deserializing)]
+    #[schemars(skip)]
+    pub directories: Vec<DirectoryOptions>,
+}
+
+impl Options {
+    /// Parses the options from the settings of the client and returns them together with
+    /// warnings about the settings that are ignored, e.g. misspelled ones
+    ///
+    /// Invalid values are rejected with the path of the setting, e.g. `inlayHints.functionArgs`.
+    pub fn parse(value: serde_json::Value) -> Result<(Options, Vec<String>), String> {
+        let mut warnings = Vec::new();
+        let mut ignored =
+            |path: serde_ignored::Path| warnings.push(format!("Unknown setting `{}`", path));
+        let deserializer = serde_ignored::Deserializer::new(value, &mut ignored);
+        let options: Options = serde_path_to_error::deserialize(deserializer)
+            .map_err(|e| format!("Invalid setting `{}`: {}", e.path(), e.inner()))?;
+
+        let mut rule_warnings = options
+            .lint
+            .iter()
+            .filter_map(|(name, config)| match name.parse::<RuleViolationKind>() {
+                Err(_) => Some(format!("Unknown lint rule `{}`", name)),
+                Ok(kind) => {
+                    let rule = pg_lint::rule(&kind)?;
+                    let err = rule.validate_options(config.options()).err()?;
+                    Some(format!("Invalid options of lint rule `{}`: {}", name, err))
+                }
+            })
+            .collect::<Vec<_>>();
+        rule_warnings.sort();
+        warnings.extend(rule_warnings);
+
+        Ok((options, warnings))
+    }
+
+    /// Replaces the connection string with that of the selected profile, or returns an error if
+    /// there is no profile of that name
+    pub fn apply_profile(&mut self) -> Result<(), String> {
+        let Some(name) = &self.profile else {
+            return Ok(());
+        };
+        match self.profiles.get(name) {
+            Some(profile) => {
+                self.db_connection_string = Some(profile.db_connection_string.clone());
+                Ok(())
+            }
+            None => {
+                let mut names = self.profiles.keys().map(String::as_str).collect::<Vec<_>>();
+                names.sort();
+                Err(format!(
+                    "Unknown profile `{}`, the profiles are: {}",
+                    name,
+                    names.join(", ")
+                ))
+            }
+        }
+    }
+
+    /// Returns the connection strings of the named connections and of the default one by name,
+    /// which the server and the CLI connect to
+    pub fn connection_strings(&self) -> HashMap<String, String> {
+        let mut connection_strings: HashMap<String, String> = self
+            .connections
+            .iter()
+            .map(|c| (c.name.clone(), c.connection_string.clone()))
+            .collect();
+        if let Some(connection_string) = &self.db_connection_string {
+            connection_strings.insert(DEFAULT_CONNECTION.to_string(), connection_string.clone());
+        }
+        connection_strings
+    }
+
+    /// Returns the severities of the lint rules by kind, where `None` disables a rule. Unknown
+    /// rules are skipped, since `parse` warns about them.
+    pub fn rule_severities(&self) -> HashMap<RuleViolationKind, Option<Severity>> {
+        rule_severities(&self.lint)
+    }
+
+    /// Returns the options of the lint rules that have any by kind
+    pub fn rule_options(&self) -> HashMap<RuleViolationKind, serde_json::Value> {
+        self.lint
+            .iter()
+            .filter(|(_, config)| !config.options().is_null())
+            .filter_map(|(name, config)| Some((name.parse().ok()?, config.options().clone())))
+            .collect()
+    }
+
+    /// Returns the severities of the lint rules of the directories with a `pglsp.toml`
+    pub fn directory_rule_severities(
+        &self,
+    ) -> Vec<(PathBuf, HashMap<RuleViolationKind, Option<Severity>>)> {
+        self.directories
+            .iter()
+            .map(|directory| (directory.dir.clone(), rule_severities(&directory.lint)))
+            .collect()
+    }
+
+    /// Returns the JSON schema of the options, which editors can use to validate and complete
+    /// the settings
+    pub fn schema() -> RootSchema {
+        schemars::schema_for!(Options)
+    }
+}
+
+fn rule_severities(
+    lint: &HashMap<String, RuleConfig>,
+) -> HashMap<RuleViolationKind, Option<Severity>> {
+    lint.iter()
+        .filter_map(|(name, config)| Some((name.parse().ok()?, config.severity())))
+        .collect()
+}
+
+/// The settings of a `pglsp.toml` in a subdirectory of the workspace, which apply to the files
+/// within it
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryOptions {
+    pub dir: PathBuf,
+    /// The lint rules, merged with those of the directories above
+    pub lint: HashMap<String, RuleConfig>,
+}
+
+/// The configuration of a lint rule, which is its severity or its severity and options
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum RuleConfig {
+    Severity(RuleSeverity),
+    WithOptions(RuleWithOptions),
+}
+
+impl RuleConfig {
+    /// Returns the severity of the diagnostics of the rule, or `None` if it is disabled
+    pub fn severity(&self) -> Option<Severity> {
+        match self {
+            RuleConfig::Severity(severity) => severity.severity(),
+            RuleConfig::WithOptions(rule) => rule.level.severity(),
+        }
+    }
+
+    /// Returns the options of the rule, which are `null` if there are none
+    pub fn options(&self) -> &serde_json::Value {
+        match self {
+            RuleConfig::Severity(_) => &serde_json::Value::Null,
+            RuleConfig::WithOptions(rule) => &rule.options,
+        }
+    }
+}
+
+/// A lint rule with options
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleWithOptions {
+    pub level: RuleSeverity,
+    /// The options of the rule, which depend on the rule
+    #[serde(default)]
+    pub options: serde_json::Value,
+}
+
+/// The severity a lint rule is reported with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum RuleSeverity {
+    Off,
+    Hint,
+    Information,
+    Warning,
+    Error,
+}
+
+impl RuleSeverity {
+    /// Returns the severity of the diagnostics of the rule, or `None` if it is disabled
+    pub fn severity(self) -> Option<Severity> {
+        match self {
+            RuleSeverity::Off => None,
+            RuleSeverity::Hint => Some(Severity::Hint),
+            RuleSeverity::Information => Some(Severity::Information),
+            RuleSeverity::Warning => Some(Severity::Warning),
+            RuleSeverity::Error => Some(Severity::Error),
+        }
+    }
+}
+
+/// When statements are typechecked against the database. Parse errors and lints are reported on
+/// every change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum TypecheckTrigger {
+    /// Once the document was not changed for `idleDelay` milliseconds, and on save
+    #[default]
+    Idle,
+    /// Only when the document is saved
+    Save,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct DiagnosticsOptions {
+    pub typecheck: TypecheckTrigger,
+    pub idle_delay: u64,
+}
+
+impl Default for DiagnosticsOptions {
+    fn default() -> Self {
+        Self {
+            typecheck: TypecheckTrigger::Idle,
+            idle_delay: 500,
+        }
+    }
+}
+
+/// How statements are executed with the `executeStatement` command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ExecutionOptions {
+    /// The connection statements are executed on instead of the connection of their file, e.g.
+    /// one with a role that may only read
+    pub connection_string: Option<String>,
+    /// The maximum number of rows returned
+    pub row_limit: usize,
+    /// Seconds after which the execution is aborted. Disabled if zero.
+    pub timeout: u64,
+    /// Statements that modify the database are only executed once the client confirmed them
+    pub confirm_writes: bool,
+    /// Allow the `explainStatement` command to execute the statement with `EXPLAIN ANALYZE`.
+    /// Its changes are rolled back, but it may still take locks or call functions with side
+    /// effects.
+    pub allow_explain_analyze: bool,
+}
+
+impl Default for ExecutionOptions {
+    fn default() -> Self {
+        Self {
+            connection_string: None,
+            row_limit: 1000,
+            timeout: 30,
+            confirm_writes: true,
+            allow_explain_analyze: false,
+        }
+    }
+}
+
+/// The files `pglsp check` checks within the directories it is given
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct FilesOptions {
+    /// Directories or file globs, e.g. `migrations` or `**/queries/*.sql`, to check. All sql
+    /// files are checked if empty. Relative paths are resolved against the working directory.
+    pub include: Vec<String>,
+    /// Directories or file globs to skip, even if they are included
+    pub ignore: Vec<String>,
+    /// Directories or file globs to check even if git ignores them, e.g. generated migrations.
+    /// Files that git ignores are skipped otherwise, unless `pglsp check --no-ignore` is given.
+    pub force_include: Vec<String>,
+    /// The size in bytes above which a file is ignored rather than read, e.g. a database dump
+    /// with a `.sql` extension. Binary files are always ignored.
+    pub max_size: u64,
+}
+
+impl Default for FilesOptions {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            ignore: Vec::new(),
+            force_include: Vec::new(),
+            max_size: 10 * 1024 * 1024,
+        }
+    }
+}
+
+/// The exit codes of `pglsp check`, e.g. to tell findings from a failed run in a script. It exits
+/// with 0 if nothing was found.
+///
+/// If errors of several categories were found, the highest of their codes is used.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ExitCodesOptions {
+    /// The code for errors of a category without a code of its own
+    pub errors: u8,
+    /// The code by category of the errors, e.g. `{ "lint": 1, "typecheck": 2 }`. The categories
+    /// are `pg_query` for syntax errors, `lint`, `typecheck` and `schema`.
+    pub categories: HashMap<String, u8>,
+    /// The code if the check itself failed, e.g. because a file could not be read or the
+    /// database could not be reached
+    pub internal: u8,
+}
+
+impl Default for ExitCodesOptions {
+    fn default() -> Self {
+        Self {
+            errors: 1,
+            categories: HashMap::new(),
+            internal: 1,
+        }
+    }
+}
+
+/// Toggles each kind of inlay hint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct InlayHintsOptions {
+    /// Names and types of the arguments of function calls with three or more arguments
+    pub function_args: bool,
+    /// Types the server inferred for parameter placeholders, e.g. `$1`
+    pub parameter_types: bool,
+    /// Target columns of `INSERT ... VALUES` values and of `COPY` without a column list
+    pub insert_columns: bool,
+    /// Casts Postgres adds to one side of a comparison, e.g. of an integer column compared to a
+    /// decimal, which keep indexes on that side from being used
+    pub implicit_casts: bool,
+}
+
+impl Default for InlayHintsOptions {
+    fn default() -> Self {
+        Self {
+            function_args: true,
+            parameter_types: true,
+            insert_columns: true,
+            implicit_casts: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ConnectionOptions {
+    pub name: String,
+    pub connection_string: String,
+    /// Directories or file globs, e.g. `services/billing` or `**/billing/*.sql`, whose files are
+    /// checked against this connection. Relative paths are resolved against the workspace root.
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum SslMode {
+    Disable,
+    Allow,
+    Prefer,
+    Require,
+    VerifyCa,
+    /// Also verifies that the host name matches the certificate, which is not possible through
+    /// an SSH tunnel
+    VerifyFull,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SshTunnelOptions {
+    /// The host to connect to with `ssh`, which forwards the connections to the database
+    pub host: String,
+    #[serde(default = "default_ssh_port")]
+    pub port: u16,
+    /// The user to log in as. Defaults to the one of the ssh configuration.
+    pub user: Option<String>,
+    /// The private key to log in with. Defaults to the keys of the ssh agent and configuration.
+    pub key: Option<PathBuf>,
+}
+
+fn default_ssh_port() -> u16 {
+    22
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileOptions {
+    pub db_connection_string: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pg_diagnostics::Severity;
+    use pg_lint::RuleViolationKind;
+    use serde_json::json;
+
+    use super::Options;
+
+    const SCHEMA_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../docs/options.schema.json"
+    );
+
+    #[test]
+    fn test_parse() {
+        let (options, warnings) = Options::parse(json!({
+            "inlayHints": { "functionArgs": false, "paramterTypes": false },
+            "lint": {
+                "ban-drop-column": { "level": "error", "options": { "allow": true } },
+                "ban-drop-table": "off"
+            }
+        }))
+        .unwrap();
+        assert!(!options.inlay_hints.function_args);
+        assert_eq!(
+            warnings,
+            vec![
+                "Unknown setting `inlayHints.paramterTypes`",
+                "Invalid options of lint rule `ban-drop-column`: The rule has no options",
+                "Unknown lint rule `ban-drop-table`"
+            ]
+        );
+        assert_eq!(
+            options.rule_severities(),
+            HashMap::from([(RuleViolationKind::BanDropColumn, Some(Severity::Error))])
+        );
+        assert_eq!(
+            options.rule_options(),
+            HashMap::from([(RuleViolationKind::BanDropColumn, json!({ "allow": true }))])
+        );
+
+        assert_eq!(
+            Options::parse(json!({ "execution": { "rowLimit": "all" } })),
+            Err(
+                "Invalid setting `execution.rowLimit`: invalid type: string \"all\", expected usize"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_apply_profile() {
+        let parse = |value| Options::parse(value).unwrap().0;
+        let profiles = json!({
+            "local": { "dbConnectionString": "postgres://localhost/app" },
+            "ci": { "dbConnectionString": "postgres://ci/app" }
+        });
+
+        let mut options = parse(json!({
+            "dbConnectionString": "postgres://localhost/other",
+            "profiles": profiles,
+            "profile": "ci"
+        }));
+        options.apply_profile().unwrap();
+        assert_eq!(
+            options.db_connection_string.as_deref(),
+            Some("postgres://ci/app")
+        );
+
+        let mut options = parse(json!({ "profiles": profiles }));
+        options.apply_profile().unwrap();
+        assert_eq!(options.db_connection_string, None);
+
+        let mut options = parse(json!({ "profiles": profiles, "profile": "staging" }));
+        assert_eq!(
+            options.apply_profile(),
+            Err("Unknown profile `staging`, the profiles are: ci, local".to_string())
+        );
+    }
+
+    #[test]
+    fn test_connection_strings() {
+        let (options, _) = Options::parse(json!({
+            "dbConnectionString": "postgres://localhost/app",
+            "connections": [
+                { "name": "billing", "connectionString": "postgres://localhost/billing" }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            options.connection_strings(),
+            HashMap::from([
+                (
+                    "default".to_string(),
+                    "postgres://localhost/app".to_string()
+                ),
+                (
+                    "billing".to_string(),
+                    "postgres://localhost/billing".to_string()
+                ),
+            ])
+        );
+        assert!(Options::default().connection_strings().is_empty());
+    }
+
+    /// The schema is checked in for editor clients. Run `env UPDATE_SCHEMA=1 cargo test -p
+    /// pg_lsp` to regenerate it.
+    #[test]
+    fn test_schema_is_up_to_date() {
+        let schema = serde_json::to_string_pretty(&Options::schema()).unwrap() + "\n";
+        if std::env::var_os("UPDATE_SCHEMA").is_some() {
+            std::fs::write(SCHEMA_PATH, &schema).unwrap();
+        }
+        let checked_in = std::fs::read_to_string(SCHEMA_PATH).unwrap_or_default();
+        assert!(
+            checked_in == schema,
+            "docs/options.schema.json is out of date, run `env UPDATE_SCHEMA=1 cargo test -p pg_lsp`"
+        );
+    }
 }
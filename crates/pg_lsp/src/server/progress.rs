@@ -0,0 +1,163 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use lsp_types::{
+    notification::Progress as ProgressNotification, request::WorkDoneProgressCreate,
+    NumberOrString, ProgressParams, ProgressParamsValue, WorkDoneProgress, WorkDoneProgressBegin,
+    WorkDoneProgressCreateParams, WorkDoneProgressEnd, WorkDoneProgressReport,
+};
+
+use crate::client::LspClient;
+
+use super::cancellation::CancellationToken;
+
+/// Reports the progress of long running operations to the client with `$/progress`, if the
+/// client supports it
+#[derive(Debug, Clone)]
+pub struct ProgressReporter {
+    client: LspClient,
+    enabled: bool,
+    next_id: Arc<AtomicI32>,
+    /// The operations in progress, by token
+    running: Arc<Mutex<HashMap<NumberOrString, CancellationToken>>>,
+}
+
+impl ProgressReporter {
+    pub fn new(client: LspClient, enabled: bool) -> Self {
+        Self {
+            client,
+            enabled,
+            next_id: Arc::new(AtomicI32::new(1)),
+            running: Default::default(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Asks the client to show the progress of an operation
+    ///
+    /// Blocks until the client responded, so it must not be called on the main loop.
+    pub fn begin(&self, title: &str, cancellable: bool) -> Progress {
+        let cancellation = CancellationToken::default();
+        if !self.enabled {
+            return Progress {
+                reporter: self.clone(),
+                token: None,
+                cancellation,
+            };
+        }
+
+        let token = NumberOrString::String(format!(
+            "pglsp/{}",
+            self.next_id.fetch_add(1, Ordering::SeqCst)
+        ));
+        let created = self
+            .client
+            .send_request::<WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                token: token.clone(),
+            })
+            .is_ok();
+        if !created {
+            return Progress {
+                reporter: self.clone(),
+                token: None,
+                cancellation,
+            };
+        }
+
+        self.running
+            .lock()
+            .unwrap()
+            .insert(token.clone(), cancellation.clone());
+        self.notify(
+            &token,
+            WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: title.to_string(),
+                cancellable: Some(cancellable),
+                message: None,
+                percentage: Some(0),
+            }),
+        );
+
+        Progress {
+            reporter: self.clone(),
+            token: Some(token),
+            cancellation,
+        }
+    }
+
+    /// Cancels an operation on request of the client, if it is still in progress
+    pub fn cancel(&self, token: &NumberOrString) {
+        if let Some(cancellation) = self.running.lock().unwrap().remove(token) {
+            cancellation.cancel();
+        }
+    }
+
+    fn notify(&self, token: &NumberOrString, value: WorkDoneProgress) {
+        self.client
+            .send_notification::<ProgressNotification>(ProgressParams {
+                token: token.clone(),
+                value: ProgressParamsValue::WorkDone(value),
+            })
+            .unwrap();
+    }
+}
+
+/// The progress of a single operation, which ends once it is dropped
+pub struct Progress {
+    reporter: ProgressReporter,
+    /// The token of the progress shown by the client, or `None` if it is not shown
+    token: Option<NumberOrString>,
+    cancellation: CancellationToken,
+}
+
+impl Progress {
+    /// Reports that `done` of `total` steps are completed
+    pub fn report(&self, message: String, done: usize, total: usize) {
+        let Some(token) = &self.token else {
+            return;
+        };
+        let percentage = (done * 100).checked_div(total).unwrap_or(100);
+        self.reporter.notify(
+            token,
+            WorkDoneProgress::Report(WorkDoneProgressReport {
+                cancellable: None,
+                message: Some(message),
+                percentage: Some(percentage as u32),
+            }),
+        );
+    }
+
+    /// The token that tells whether the user cancelled the operation
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    pub fn end(mut self, message: Option<String>) {
+        self.finish(message);
+    }
+
+    fn finish(&mut self, message: Option<String>) {
+        let Some(token) = self.token.take() else {
+            return;
+        };
+        self.reporter.running.lock().unwrap().remove(&token);
+        self.reporter.notify(
+            &token,
+            WorkDoneProgress::End(WorkDoneProgressEnd { message }),
+        );
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.finish(None);
+    }
+}
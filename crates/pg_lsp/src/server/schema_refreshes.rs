@@ -0,0 +1,131 @@
+use std::collections::{HashMap, HashSet};
+
+use pg_schema_cache::{SchemaCache, SchemaCacheClass, SchemaCacheUpdate};
+
+/// Orders the refreshes of the schema caches, which run concurrently and may finish in any order,
+/// so that a refresh does not overwrite the classes of objects of a refresh that started later
+#[derive(Debug, Default)]
+pub struct SchemaRefreshes {
+    /// The sequence number of the last refresh that was started, by connection
+    started: HashMap<String, u64>,
+    /// The sequence number of the refresh whose objects are in the cache, by connection and class
+    applied: HashMap<(String, SchemaCacheClass), u64>,
+}
+
+impl SchemaRefreshes {
+    /// Returns the sequence number of a refresh of the schema cache of a connection that starts
+    pub fn start(&mut self, connection: &str) -> u64 {
+        let sequence = self.started.entry(connection.to_string()).or_default();
+        *sequence += 1;
+        *sequence
+    }
+
+    /// Removes the classes of a finished refresh that a refresh which started later has already
+    /// replaced, and records the remaining ones as applied
+    pub fn finish(
+        &mut self,
+        connection: &str,
+        sequence: u64,
+        classes: &mut HashSet<SchemaCacheClass>,
+    ) {
+        classes.retain(|class| {
+            let applied = self
+                .applied
+                .entry((connection.to_string(), *class))
+                .or_default();
+            if *applied > sequence {
+                return false;
+            }
+            *applied = sequence;
+            true
+        });
+    }
+
+    /// Returns what applies of a finished refresh of all classes: the whole cache if no refresh
+    /// that started later replaced any class, the classes that were not replaced otherwise, or
+    /// `None` if all were
+    pub fn finish_all(
+        &mut self,
+        connection: &str,
+        sequence: u64,
+        schema_cache: SchemaCache,
+    ) -> Option<RefreshedSchemaCache> {
+        let mut classes: HashSet<_> = SchemaCacheClass::ALL.iter().copied().collect();
+        self.finish(connection, sequence, &mut classes);
+        if classes.len() == SchemaCacheClass::ALL.len() {
+            Some(RefreshedSchemaCache::All(schema_cache))
+        } else if !classes.is_empty() {
+            Some(RefreshedSchemaCache::Classes(
+                SchemaCacheUpdate::from_cache(schema_cache, classes),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// The part of a refresh of all classes that is still up to date when it finishes
+#[derive(Debug)]
+pub enum RefreshedSchemaCache {
+    /// Replaces the whole cache
+    All(SchemaCache),
+    /// Replaces only the classes of the update, since newer refreshes replaced the others
+    Classes(SchemaCacheUpdate),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use pg_schema_cache::{SchemaCache, SchemaCacheClass};
+
+    use super::{RefreshedSchemaCache, SchemaRefreshes};
+
+    #[test]
+    fn test_schema_refreshes() {
+        let mut refreshes = SchemaRefreshes::default();
+        let first = refreshes.start("default");
+        let second = refreshes.start("default");
+        let other = refreshes.start("billing");
+
+        let mut classes = HashSet::from([SchemaCacheClass::Table, SchemaCacheClass::Policy]);
+        refreshes.finish("default", second, &mut classes);
+        assert_eq!(classes.len(), 2);
+
+        // the first refresh finishes last, so only its classes the second one did not load apply
+        let mut classes = HashSet::from([SchemaCacheClass::Table, SchemaCacheClass::Column]);
+        refreshes.finish("default", first, &mut classes);
+        assert_eq!(classes, HashSet::from([SchemaCacheClass::Column]));
+
+        let mut classes = HashSet::from([SchemaCacheClass::Table]);
+        refreshes.finish("billing", other, &mut classes);
+        assert_eq!(classes, HashSet::from([SchemaCacheClass::Table]));
+    }
+
+    #[test]
+    fn test_finish_all() {
+        let mut refreshes = SchemaRefreshes::default();
+        let full = refreshes.start("default");
+        let tables = refreshes.start("default");
+
+        // the full refresh finishes after a newer one of the tables, whose tables are kept
+        let mut classes = HashSet::from([SchemaCacheClass::Table]);
+        refreshes.finish("default", tables, &mut classes);
+        match refreshes.finish_all("default", full, SchemaCache::new()) {
+            Some(RefreshedSchemaCache::Classes(update)) => {
+                assert!(!update.classes.contains(&SchemaCacheClass::Table));
+                assert_eq!(update.classes.len(), SchemaCacheClass::ALL.len() - 1);
+            }
+            other => panic!("expected the classes of the update, got {:?}", other),
+        }
+
+        let newer = refreshes.start("default");
+        assert!(matches!(
+            refreshes.finish_all("default", newer, SchemaCache::new()),
+            Some(RefreshedSchemaCache::All(_))
+        ));
+        assert!(refreshes
+            .finish_all("default", full, SchemaCache::new())
+            .is_none());
+    }
+}
@@ -1,8 +1,12 @@
 pub mod from_proto;
 pub mod line_index_ext;
+pub mod semantic_tokens;
 pub mod to_proto;
 
-use std::path::PathBuf;
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
 
 use lsp_types;
 use pg_base_db::PgLspPath;
@@ -21,6 +25,93 @@ pub(crate) fn file_path(url: &lsp_types::Url) -> PgLspPath {
     PgLspPath::new(path_to_file)
 }
 
+/// Directories that never contain sql files of the workspace itself
+pub(crate) const IGNORED_DIRS: &[&str] = &["node_modules", "target"];
+
+/// Collects the sql files within a directory, skipping hidden and dependency directories
+pub(crate) fn sql_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        if name.starts_with('.') || IGNORED_DIRS.contains(&name) {
+            continue;
+        }
+        if path.is_dir() {
+            sql_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "sql") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The number of bytes at the start of a file that are searched for a NUL byte to detect a binary
+/// file, as git does
+const BINARY_DETECTION_LEN: usize = 8000;
+
+/// Why a sql file is not read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SkippedFile {
+    /// The file has more bytes than the maximum size
+    TooLarge { size: u64, max_size: u64 },
+    /// The file contains a NUL byte, or is not valid UTF-8
+    Binary,
+}
+
+impl fmt::Display for SkippedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkippedFile::TooLarge { size, max_size } => write!(
+                f,
+                "Ignored because the file has {} bytes, more than the maximum of {} bytes of `files.maxSize`",
+                size, max_size
+            ),
+            SkippedFile::Binary => write!(f, "Ignored because the file is binary"),
+        }
+    }
+}
+
+/// Reads a sql file, unless it is larger than the maximum size or binary
+///
+/// Such files end up in directories by accident, e.g. a database dump, and reading them would
+/// only slow down or fail whatever reads them.
+pub(crate) fn read_sql_file(path: &Path, max_size: u64) -> io::Result<Result<String, SkippedFile>> {
+    let size = fs::metadata(path)?.len();
+    if size > max_size {
+        return Ok(Err(SkippedFile::TooLarge { size, max_size }));
+    }
+    let bytes = fs::read(path)?;
+    if bytes[..bytes.len().min(BINARY_DETECTION_LEN)].contains(&0) {
+        return Ok(Err(SkippedFile::Binary));
+    }
+    Ok(String::from_utf8(bytes).map_err(|_| SkippedFile::Binary))
+}
+
+/// Collects the directory and the directories within it that contain a file with a name,
+/// skipping hidden and dependency directories
+pub(crate) fn dirs_with_file(dir: &Path, name: &str, dirs: &mut Vec<PathBuf>) -> io::Result<()> {
+    if dir.join(name).is_file() {
+        dirs.push(dir.to_path_buf());
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        if file_name.starts_with('.') || IGNORED_DIRS.contains(&file_name) {
+            continue;
+        }
+        if path.is_dir() {
+            dirs_with_file(&path, name, dirs)?;
+        }
+    }
+    Ok(())
+}
+
 pub fn normalize_uri(uri: &mut lsp_types::Url) {
     if let Some(mut segments) = uri.path_segments() {
         if let Some(mut path) = segments.next().and_then(fix_drive_letter) {
@@ -32,8 +32,38 @@ pub fn client_flags(capabilities: lsp_types::ClientCapabilities) -> ClientFlags
         .and_then(|cap| cap.dynamic_registration)
         .unwrap_or(false);
 
+    let diagnostics_pull = capabilities
+        .text_document
+        .as_ref()
+        .and_then(|cap| cap.diagnostic.as_ref())
+        .is_some();
+
+    let diagnostics_refresh = capabilities
+        .workspace
+        .as_ref()
+        .and_then(|cap| cap.diagnostic.as_ref())
+        .and_then(|cap| cap.refresh_support)
+        .unwrap_or(false);
+
+    let work_done_progress = capabilities
+        .window
+        .as_ref()
+        .and_then(|cap| cap.work_done_progress)
+        .unwrap_or(false);
+
+    let watched_files_registration = capabilities
+        .workspace
+        .as_ref()
+        .and_then(|cap| cap.did_change_watched_files)
+        .and_then(|cap| cap.dynamic_registration)
+        .unwrap_or(false);
+
     ClientFlags {
         configuration_pull,
         configuration_push,
+        diagnostics_pull,
+        diagnostics_refresh,
+        work_done_progress,
+        watched_files_registration,
     }
 }
@@ -1,7 +1,60 @@
-use line_index::{LineCol, LineColUtf16, LineIndex};
-use lsp_types::{Position, Range};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use line_index::{LineCol, LineIndex, WideEncoding, WideLineCol};
+use lsp_types::{Position, PositionEncodingKind, Range};
 use text_size::{TextRange, TextSize};
 
+/// The encoding the columns of positions exchanged with the client are counted in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionEncoding {
+    Utf8,
+    Wide(WideEncoding),
+}
+
+impl PositionEncoding {
+    /// Picks the cheapest of the encodings the client supports, which is UTF-16 if it does not
+    /// state any
+    pub fn negotiate(supported: Option<&[PositionEncodingKind]>) -> PositionEncoding {
+        let supported = supported.unwrap_or_default();
+        if supported.contains(&PositionEncodingKind::UTF8) {
+            PositionEncoding::Utf8
+        } else if supported.contains(&PositionEncodingKind::UTF32) {
+            PositionEncoding::Wide(WideEncoding::Utf32)
+        } else {
+            PositionEncoding::Wide(WideEncoding::Utf16)
+        }
+    }
+
+    pub fn kind(&self) -> PositionEncodingKind {
+        match self {
+            PositionEncoding::Utf8 => PositionEncodingKind::UTF8,
+            PositionEncoding::Wide(WideEncoding::Utf16) => PositionEncodingKind::UTF16,
+            PositionEncoding::Wide(WideEncoding::Utf32) => PositionEncodingKind::UTF32,
+        }
+    }
+}
+
+/// The encoding negotiated with the client. There is only one client per server process, so it
+/// is set once on initialization instead of being passed to every conversion.
+static POSITION_ENCODING: AtomicU8 = AtomicU8::new(1);
+
+pub fn set_position_encoding(encoding: PositionEncoding) {
+    let value = match encoding {
+        PositionEncoding::Utf8 => 0,
+        PositionEncoding::Wide(WideEncoding::Utf16) => 1,
+        PositionEncoding::Wide(WideEncoding::Utf32) => 2,
+    };
+    POSITION_ENCODING.store(value, Ordering::Relaxed);
+}
+
+pub fn position_encoding() -> PositionEncoding {
+    match POSITION_ENCODING.load(Ordering::Relaxed) {
+        0 => PositionEncoding::Utf8,
+        2 => PositionEncoding::Wide(WideEncoding::Utf32),
+        _ => PositionEncoding::Wide(WideEncoding::Utf16),
+    }
+}
+
 pub trait LineIndexExt {
     fn offset_lsp(&self, line_col: Position) -> Option<TextSize>;
 
@@ -14,12 +67,20 @@ pub trait LineIndexExt {
 
 impl LineIndexExt for LineIndex {
     fn offset_lsp(&self, line_col: Position) -> Option<TextSize> {
-        let line_col = LineColUtf16 {
-            line: line_col.line,
-            col: line_col.character,
+        let line_col = match position_encoding() {
+            PositionEncoding::Utf8 => LineCol {
+                line: line_col.line,
+                col: line_col.character,
+            },
+            PositionEncoding::Wide(enc) => self.to_utf8(
+                enc,
+                WideLineCol {
+                    line: line_col.line,
+                    col: line_col.character,
+                },
+            )?,
         };
 
-        let line_col = self.to_utf8(line_col)?;
         self.offset(line_col)
     }
 
@@ -31,8 +92,13 @@ impl LineIndexExt for LineIndex {
 
     fn line_col_lsp(&self, offset: TextSize) -> Option<Position> {
         let line_col = self.line_col(offset);
-        let line_col = self.to_utf16(line_col)?;
-        Some(Position::new(line_col.line, line_col.col))
+        match position_encoding() {
+            PositionEncoding::Utf8 => Some(Position::new(line_col.line, line_col.col)),
+            PositionEncoding::Wide(enc) => {
+                let line_col = self.to_wide(enc, line_col)?;
+                Some(Position::new(line_col.line, line_col.col))
+            }
+        }
     }
 
     fn line_col_lsp_range(&self, offset: TextRange) -> Option<Range> {
@@ -57,7 +123,10 @@ mod tests {
     use pg_base_db::{Document, PgLspPath};
     use text_size::{TextRange, TextSize};
 
-    use crate::utils::line_index_ext::LineIndexExt;
+    use line_index::{LineCol, LineIndex, WideEncoding};
+    use lsp_types::PositionEncodingKind;
+
+    use crate::utils::line_index_ext::{LineIndexExt, PositionEncoding};
 
     #[test]
     fn test_line_col_lsp_range() {
@@ -74,4 +143,37 @@ mod tests {
                 .line_col_lsp_range(TextRange::new(TextSize::new(52), TextSize::new(66)))
         );
     }
+
+    #[test]
+    fn test_negotiate_position_encoding() {
+        assert_eq!(
+            PositionEncoding::negotiate(None),
+            PositionEncoding::Wide(WideEncoding::Utf16)
+        );
+        assert_eq!(
+            PositionEncoding::negotiate(Some(&[
+                PositionEncodingKind::UTF16,
+                PositionEncodingKind::UTF8
+            ])),
+            PositionEncoding::Utf8
+        );
+        assert_eq!(
+            PositionEncoding::negotiate(Some(&[PositionEncodingKind::UTF32])),
+            PositionEncoding::Wide(WideEncoding::Utf32)
+        );
+
+        // "é" is two bytes and one UTF-16 code unit, "𝔸" four bytes and two UTF-16 code units
+        let index = LineIndex::new("select 'é𝔸', id");
+        let line_col = LineCol { line: 0, col: 15 };
+        assert_eq!(
+            index.to_wide(WideEncoding::Utf16, line_col).unwrap().col,
+            12
+        );
+        assert_eq!(
+            index.to_wide(WideEncoding::Utf32, line_col).unwrap().col,
+            11
+        );
+        let wide = index.to_wide(WideEncoding::Utf32, line_col).unwrap();
+        assert_eq!(index.to_utf8(WideEncoding::Utf32, wide), Some(line_col));
+    }
 }
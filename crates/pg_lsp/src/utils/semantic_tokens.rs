@@ -0,0 +1,297 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use dashmap::DashMap;
+use line_index::{LineCol, LineIndex};
+use lsp_types::{SemanticToken, SemanticTokenType, SemanticTokensEdit, SemanticTokensLegend};
+use pg_base_db::PgLspPath;
+use pg_workspace::{SemanticTokenKind, Workspace};
+use text_size::{TextRange, TextSize};
+
+use super::line_index_ext::LineIndexExt;
+
+const TOKEN_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::NAMESPACE,
+    SemanticTokenType::CLASS,
+    SemanticTokenType::PROPERTY,
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::TYPE,
+    SemanticTokenType::PARAMETER,
+    SemanticTokenType::STRING,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::COMMENT,
+    SemanticTokenType::OPERATOR,
+];
+
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: Vec::new(),
+    }
+}
+
+fn token_type(kind: SemanticTokenKind) -> u32 {
+    match kind {
+        SemanticTokenKind::Keyword => 0,
+        SemanticTokenKind::Namespace => 1,
+        SemanticTokenKind::Relation => 2,
+        SemanticTokenKind::Column => 3,
+        SemanticTokenKind::Function => 4,
+        SemanticTokenKind::Type => 5,
+        SemanticTokenKind::Parameter => 6,
+        SemanticTokenKind::String => 7,
+        SemanticTokenKind::Number => 8,
+        SemanticTokenKind::Comment => 9,
+        SemanticTokenKind::Operator => 10,
+    }
+}
+
+/// A token positioned by line and column, which always lies within a single line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbsoluteToken {
+    pub line: u32,
+    pub start: u32,
+    pub length: u32,
+    pub token_type: u32,
+}
+
+/// The tokens of a document version along with the id they were sent with
+#[derive(Debug)]
+pub struct CachedTokens {
+    pub version: i32,
+    pub result_id: String,
+    pub tokens: Vec<AbsoluteToken>,
+}
+
+/// Keeps the last tokens computed for every document
+///
+/// Tokens are only computed once per document version, and the last tokens sent are the base for
+/// the delta of the next request.
+#[derive(Default)]
+pub struct SemanticTokensCache {
+    documents: DashMap<PgLspPath, Arc<CachedTokens>>,
+    next_id: AtomicU64,
+}
+
+impl SemanticTokensCache {
+    /// Returns the tokens of the current version of a document together with the tokens they
+    /// replaced, if any
+    pub fn update(
+        &self,
+        ide: &Workspace,
+        path: &PgLspPath,
+    ) -> Option<(Arc<CachedTokens>, Option<Arc<CachedTokens>>)> {
+        let version = ide.documents.get(path)?.version;
+        let previous = self.documents.get(path).map(|entry| entry.clone());
+        if let Some(previous) = previous.as_ref().filter(|p| p.version == version) {
+            return Some((previous.clone(), Some(previous.clone())));
+        }
+
+        let (version, tokens) = ide.semantic_tokens(path)?;
+        let line_index = ide.line_index(path)?;
+        let current = Arc::new(CachedTokens {
+            version,
+            result_id: self.next_id.fetch_add(1, Ordering::Relaxed).to_string(),
+            tokens: absolute_tokens(&tokens, &line_index),
+        });
+        self.documents.insert(path.clone(), current.clone());
+
+        Some((current, previous))
+    }
+
+    pub fn remove(&self, path: &PgLspPath) {
+        self.documents.remove(path);
+    }
+}
+
+/// Positions tokens by line and column, splitting tokens that span multiple lines
+pub fn absolute_tokens(
+    tokens: &[pg_workspace::SemanticToken],
+    line_index: &LineIndex,
+) -> Vec<AbsoluteToken> {
+    let mut result = Vec::new();
+    for token in tokens {
+        let start_line = line_index.line_col(token.range.start()).line;
+        let end_line = line_index.line_col(token.range.end()).line;
+        for line in start_line..=end_line {
+            let start = if line == start_line {
+                token.range.start()
+            } else {
+                line_index.offset(LineCol { line, col: 0 }).unwrap()
+            };
+            // the line break is not part of the token
+            let end = if line == end_line {
+                token.range.end()
+            } else {
+                line_index
+                    .offset(LineCol {
+                        line: line + 1,
+                        col: 0,
+                    })
+                    .unwrap()
+                    - TextSize::from(1)
+            };
+            let (Some(start), Some(end)) =
+                (line_index.line_col_lsp(start), line_index.line_col_lsp(end))
+            else {
+                continue;
+            };
+            if end.character > start.character {
+                result.push(AbsoluteToken {
+                    line,
+                    start: start.character,
+                    length: end.character - start.character,
+                    token_type: token_type(token.kind),
+                });
+            }
+        }
+    }
+    result
+}
+
+/// Returns the tokens that lie within the range
+pub fn tokens_in_range(
+    tokens: &[AbsoluteToken],
+    line_index: &LineIndex,
+    range: TextRange,
+) -> Vec<AbsoluteToken> {
+    let (Some(start), Some(end)) = (
+        line_index.line_col_lsp(range.start()),
+        line_index.line_col_lsp(range.end()),
+    ) else {
+        return Vec::new();
+    };
+
+    tokens
+        .iter()
+        .filter(|t| {
+            (t.line, t.start + t.length) > (start.line, start.character)
+                && (t.line, t.start) < (end.line, end.character)
+        })
+        .copied()
+        .collect()
+}
+
+/// Encodes tokens relative to their predecessor, as the protocol requires
+pub fn encode(tokens: &[AbsoluteToken]) -> Vec<SemanticToken> {
+    let mut previous = (0, 0);
+    tokens
+        .iter()
+        .map(|t| {
+            let delta_line = t.line - previous.0;
+            let delta_start = if delta_line == 0 {
+                t.start - previous.1
+            } else {
+                t.start
+            };
+            previous = (t.line, t.start);
+            SemanticToken {
+                delta_line,
+                delta_start,
+                length: t.length,
+                token_type: t.token_type,
+                token_modifiers_bitset: 0,
+            }
+        })
+        .collect()
+}
+
+/// Returns a single edit that replaces the tokens between the common prefix and suffix of the
+/// old and new tokens
+pub fn diff(old: &[SemanticToken], new: &[SemanticToken]) -> Vec<SemanticTokensEdit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+    if old.is_empty() && new.is_empty() {
+        return Vec::new();
+    }
+
+    // edits address the flattened array of five integers per token
+    vec![SemanticTokensEdit {
+        start: 5 * prefix as u32,
+        delete_count: 5 * old.len() as u32,
+        data: Some(new.to_vec()),
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use line_index::LineIndex;
+    use pg_workspace::{SemanticToken, SemanticTokenKind};
+    use text_size::TextRange;
+
+    use super::{absolute_tokens, diff, encode, AbsoluteToken};
+
+    #[test]
+    fn test_absolute_tokens() {
+        let text = "/* a\nb */ select";
+        let tokens = [
+            SemanticToken {
+                range: TextRange::new(0.into(), 9.into()),
+                kind: SemanticTokenKind::Comment,
+            },
+            SemanticToken {
+                range: TextRange::new(10.into(), 16.into()),
+                kind: SemanticTokenKind::Keyword,
+            },
+        ];
+
+        let absolute = absolute_tokens(&tokens, &LineIndex::new(text));
+        assert_eq!(
+            absolute,
+            vec![
+                AbsoluteToken {
+                    line: 0,
+                    start: 0,
+                    length: 4,
+                    token_type: 9
+                },
+                AbsoluteToken {
+                    line: 1,
+                    start: 0,
+                    length: 4,
+                    token_type: 9
+                },
+                AbsoluteToken {
+                    line: 1,
+                    start: 5,
+                    length: 6,
+                    token_type: 0
+                },
+            ]
+        );
+
+        let encoded = encode(&absolute);
+        assert_eq!((encoded[1].delta_line, encoded[1].delta_start), (1, 0));
+        assert_eq!((encoded[2].delta_line, encoded[2].delta_start), (0, 5));
+    }
+
+    #[test]
+    fn test_diff() {
+        let token = |line: u32, token_type: u32| AbsoluteToken {
+            line,
+            start: 0,
+            length: 1,
+            token_type,
+        };
+        let old = encode(&[token(0, 0), token(1, 1), token(2, 2)]);
+        let new = encode(&[token(0, 0), token(1, 3), token(1, 4), token(2, 2)]);
+
+        let edits = diff(&old, &new);
+        assert_eq!(edits.len(), 1);
+        assert_eq!((edits[0].start, edits[0].delete_count), (5, 5));
+        assert_eq!(edits[0].data.as_ref().unwrap().len(), 2);
+
+        assert!(diff(&old, &old).is_empty());
+    }
+}
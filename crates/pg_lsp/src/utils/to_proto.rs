@@ -1,5 +1,10 @@
-use pg_base_db::Document;
+use line_index::LineIndex;
+use pg_base_db::{Document, PgLspPath};
+use pg_completions::CompletionItemData;
 use pg_diagnostics::Diagnostic;
+use pg_workspace::{CodeActionKind, ObjectKind};
+
+use text_size::TextRange;
 
 use super::line_index_ext::LineIndexExt;
 
@@ -17,9 +22,63 @@ pub fn diagnostic(document: &Document, diagnostic: &Diagnostic) -> lsp_types::Di
         .line_col_lsp_range(diagnostic.range)
         .unwrap();
 
+    // lint diagnostics link to the documentation of their rule
+    let code_description = diagnostic
+        .code
+        .as_deref()
+        .filter(|_| diagnostic.source == "lint")
+        .and_then(|code| pg_lint::rule(&code.parse().ok()?))
+        .and_then(|rule| lsp_types::Url::parse(rule.metadata().docs).ok())
+        .map(|href| lsp_types::CodeDescription { href });
+
     lsp_types::Diagnostic {
         severity: Some(severity),
         source: Some(diagnostic.source.clone()),
+        code: diagnostic
+            .code
+            .clone()
+            .map(lsp_types::NumberOrString::String),
+        code_description,
         ..lsp_types::Diagnostic::new_simple(range, diagnostic.message.clone())
     }
 }
+
+pub fn completion_item_kind(data: &CompletionItemData) -> lsp_types::CompletionItemKind {
+    match data {
+        CompletionItemData::Table(_) => lsp_types::CompletionItemKind::CLASS,
+        CompletionItemData::ForeignTable(_) => lsp_types::CompletionItemKind::INTERFACE,
+        CompletionItemData::Column(_) => lsp_types::CompletionItemKind::FIELD,
+    }
+}
+
+pub fn symbol_kind(kind: Option<ObjectKind>) -> lsp_types::SymbolKind {
+    match kind {
+        Some(ObjectKind::Schema) => lsp_types::SymbolKind::NAMESPACE,
+        Some(ObjectKind::Table) => lsp_types::SymbolKind::CLASS,
+        Some(ObjectKind::View | ObjectKind::MaterializedView | ObjectKind::ForeignTable) => {
+            lsp_types::SymbolKind::INTERFACE
+        }
+        Some(ObjectKind::Function | ObjectKind::Procedure) => lsp_types::SymbolKind::FUNCTION,
+        Some(ObjectKind::Type) => lsp_types::SymbolKind::STRUCT,
+        None => lsp_types::SymbolKind::OBJECT,
+    }
+}
+
+pub fn code_action_kind(kind: CodeActionKind) -> lsp_types::CodeActionKind {
+    match kind {
+        CodeActionKind::QuickFix => lsp_types::CodeActionKind::QUICKFIX,
+        CodeActionKind::RefactorRewrite => lsp_types::CodeActionKind::REFACTOR_REWRITE,
+        CodeActionKind::SourceFixAll => lsp_types::CodeActionKind::new("source.fixAll.pglsp"),
+    }
+}
+
+pub fn location(
+    path: &PgLspPath,
+    line_index: &LineIndex,
+    range: TextRange,
+) -> Option<lsp_types::Location> {
+    Some(lsp_types::Location {
+        uri: lsp_types::Url::from_file_path(path.as_path()).ok()?,
+        range: line_index.line_col_lsp_range(range)?,
+    })
+}
@@ -30,7 +30,17 @@ pub fn get_location_mod(proto_file: &ProtoFile) -> proc_macro2::TokenStream {
                         let loc_b = get_location_internal(&b.node.as_ref().unwrap());
                         loc_a.cmp(&loc_b)
                     });
-                    get_location_internal(&a.unwrap().node.as_ref().unwrap())
+                    let loc = get_location_internal(&a.unwrap().node.as_ref().unwrap());
+                    // `not` is before its argument, except for e.g. `a not in (select ...)`
+                    if n.boolop() == protobuf::BoolExprType::NotExpr && n.location >= 0 {
+                        loc.map(|loc| loc.min(n.location)).or(Some(n.location))
+                    } else {
+                        loc
+                    }
+                },
+                NodeEnum::SubLink(n) => match &n.testexpr {
+                    Some(testexpr) => get_location_internal(&testexpr.node.as_ref().unwrap()),
+                    None => Some(n.location),
                 },
                 NodeEnum::AExpr(n) => get_location_internal(&n.lexpr.as_ref().unwrap().node.as_ref().unwrap()),
                 NodeEnum::WindowDef(n) => {
@@ -92,6 +102,7 @@ fn manual_node_names() -> Vec<&'static str> {
         "ColumnDef",
         "NullTest",
         "PublicationObjSpec",
+        "SubLink",
     ]
 }
 
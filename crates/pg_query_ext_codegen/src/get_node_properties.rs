@@ -137,6 +137,92 @@ pub fn get_node_properties_mod(proto_file: &ProtoFile) -> proc_macro2::TokenStre
             tokens
         }
 
+        fn role_spec_properties(n: &protobuf::RoleSpec) -> Vec<TokenProperty> {
+            match n.roletype() {
+                protobuf::RoleSpecType::RolespecPublic => vec![TokenProperty::from("public".to_string())],
+                protobuf::RoleSpecType::RolespecCurrentRole => vec![TokenProperty::from(Token::CurrentRole)],
+                protobuf::RoleSpecType::RolespecCurrentUser => vec![TokenProperty::from(Token::CurrentUser)],
+                protobuf::RoleSpecType::RolespecSessionUser => vec![TokenProperty::from(Token::SessionUser)],
+                _ if n.rolename.len() > 0 => vec![TokenProperty::from(n.rolename.to_owned())],
+                _ => Vec::new(),
+            }
+        }
+
+        /// Returns the properties of a grant or revoke, which is also the action of
+        /// `alter default privileges`
+        fn grant_properties(n: &protobuf::GrantStmt) -> Vec<TokenProperty> {
+            let mut tokens: Vec<TokenProperty> = Vec::new();
+            if n.is_grant {
+                tokens.push(TokenProperty::from(Token::Grant));
+                tokens.push(TokenProperty::from(Token::To));
+                if n.grant_option {
+                    tokens.push(TokenProperty::from(Token::With));
+                    tokens.push(TokenProperty::from(Token::Grant));
+                    tokens.push(TokenProperty::from(Token::Option));
+                }
+            } else {
+                tokens.push(TokenProperty::from(Token::Revoke));
+                tokens.push(TokenProperty::from(Token::From));
+                if n.grant_option {
+                    tokens.push(TokenProperty::from(Token::Grant));
+                    tokens.push(TokenProperty::from(Token::Option));
+                    tokens.push(TokenProperty::from(Token::For));
+                }
+            }
+            if n.privileges.len() == 0 {
+                // `privileges` is optional, and left over if missing
+                tokens.push(TokenProperty::from(Token::All));
+                tokens.push(TokenProperty::from(Token::Privileges));
+            }
+            tokens.push(TokenProperty::from(Token::On));
+
+            let target = n.targtype();
+            let plural = target != protobuf::GrantTargetType::AclTargetObject;
+            if target == protobuf::GrantTargetType::AclTargetAllInSchema {
+                tokens.push(TokenProperty::from(Token::All));
+                tokens.push(TokenProperty::from(Token::InP));
+                tokens.push(TokenProperty::from(Token::Schema));
+            }
+            let object_tokens: &[Token] = match (n.objtype(), plural) {
+                (protobuf::ObjectType::ObjectTable, false) => &[Token::Table],
+                (protobuf::ObjectType::ObjectTable, true) => &[Token::Tables],
+                (protobuf::ObjectType::ObjectSequence, false) => &[Token::Sequence],
+                (protobuf::ObjectType::ObjectSequence, true) => &[Token::Sequences],
+                (protobuf::ObjectType::ObjectFunction, false) => &[Token::Function],
+                (protobuf::ObjectType::ObjectFunction, true) => &[Token::Functions],
+                (protobuf::ObjectType::ObjectProcedure, false) => &[Token::Procedure],
+                (protobuf::ObjectType::ObjectProcedure, true) => &[Token::Procedures],
+                (protobuf::ObjectType::ObjectRoutine, false) => &[Token::Routine],
+                (protobuf::ObjectType::ObjectRoutine, true) => &[Token::Routines],
+                (protobuf::ObjectType::ObjectType, false) => &[Token::TypeP],
+                (protobuf::ObjectType::ObjectType, true) => &[Token::TypesP],
+                (protobuf::ObjectType::ObjectSchema, false) => &[Token::Schema],
+                (protobuf::ObjectType::ObjectSchema, true) => &[Token::Schemas],
+                (protobuf::ObjectType::ObjectDomain, _) => &[Token::DomainP],
+                (protobuf::ObjectType::ObjectDatabase, _) => &[Token::Database],
+                (protobuf::ObjectType::ObjectLanguage, _) => &[Token::Language],
+                (protobuf::ObjectType::ObjectTablespace, _) => &[Token::Tablespace],
+                (protobuf::ObjectType::ObjectForeignServer, _) => &[Token::Foreign, Token::Server],
+                (protobuf::ObjectType::ObjectFdw, _) => &[Token::Foreign, Token::DataP, Token::Wrapper],
+                (protobuf::ObjectType::ObjectLargeobject, _) => &[Token::LargeP, Token::ObjectP],
+                _ => &[],
+            };
+            tokens.extend(object_tokens.iter().map(|t| TokenProperty::from(*t)));
+
+            if let Some(grantor) = &n.grantor {
+                tokens.push(TokenProperty::from(Token::Granted));
+                tokens.push(TokenProperty::from(Token::By));
+                tokens.extend(role_spec_properties(grantor));
+            }
+            match n.behavior {
+                // DropRestrict
+                1 => tokens.push(TokenProperty::from(Token::Restrict)),
+                // DropCascade
+                2 => tokens.push(TokenProperty::from(Token::Cascade)),
+                _ => {}
+            }
+            tokens
+        }
     }
 }
 
@@ -201,6 +287,23 @@ fn custom_handlers(node: &Node) -> TokenStream {
                 _ => panic!("Unknown BoolExpr {:#?}", n.boolop()),
             }
         },
+        "SubLink" => quote! {
+            match n.sub_link_type() {
+                protobuf::SubLinkType::ExistsSublink => tokens.push(TokenProperty::from(Token::Exists)),
+                protobuf::SubLinkType::AllSublink => tokens.push(TokenProperty::from(Token::All)),
+                // `in` is `= any` without an operator
+                protobuf::SubLinkType::AnySublink if n.oper_name.len() == 0 => {
+                    // the `not` of `a not in (select ...)` is between the column and the
+                    // subquery of the sublink, so it is matched here rather than by its parent,
+                    // and left over otherwise
+                    tokens.push(TokenProperty::from(Token::Not));
+                    tokens.push(TokenProperty::from(Token::InP));
+                },
+                protobuf::SubLinkType::AnySublink => tokens.push(TokenProperty::from(Token::Any)),
+                protobuf::SubLinkType::ArraySublink => tokens.push(TokenProperty::from(Token::Array)),
+                _ => {}
+            }
+        },
         "JoinExpr" => quote! {
             tokens.push(TokenProperty::from(Token::Join));
             tokens.push(TokenProperty::from(Token::On));
@@ -216,6 +319,10 @@ fn custom_handlers(node: &Node) -> TokenStream {
         "ResTarget" => quote! {
             if n.name.len() > 0 {
                 tokens.push(TokenProperty::from(Token::As));
+                // the `=` of the targets of an UPDATE, which have a name and a value
+                if n.val.is_some() {
+                    tokens.push(TokenProperty::from(Token::Ascii61));
+                }
             }
         },
         "Integer" => quote! {
@@ -233,6 +340,53 @@ fn custom_handlers(node: &Node) -> TokenStream {
                 "owner" => {
                     tokens.push(TokenProperty::from(Token::Owner));
                 }
+                // the options of roles, whose keywords differ from their names
+                "password" => {
+                    // `encrypted` is optional, and left over if missing
+                    tokens.push(TokenProperty::from(Token::Encrypted));
+                    if n.arg.is_none() {
+                        tokens.push(TokenProperty::from(Token::NullP));
+                    }
+                },
+                "canlogin" | "superuser" | "createdb" | "createrole" | "inherit" | "isreplication" | "bypassrls" => {
+                    let keyword = match n.defname.as_str() {
+                        "canlogin" => "login",
+                        "isreplication" => "replication",
+                        name => name,
+                    };
+                    let enabled = matches!(
+                        n.arg.as_ref().and_then(|arg| arg.node.as_ref()),
+                        Some(NodeEnum::Boolean(b)) if b.boolval
+                    );
+                    tokens.push(TokenProperty::from(if enabled {
+                        keyword.to_string()
+                    } else {
+                        format!("no{}", keyword)
+                    }));
+                },
+                "validUntil" => {
+                    tokens.push(TokenProperty::from(Token::Valid));
+                    tokens.push(TokenProperty::from(Token::Until));
+                },
+                "connectionlimit" => {
+                    tokens.push(TokenProperty::from(Token::Connection));
+                    tokens.push(TokenProperty::from(Token::Limit));
+                },
+                "addroleto" => {
+                    tokens.push(TokenProperty::from(Token::InP));
+                    tokens.push(TokenProperty::from(Token::Role));
+                },
+                "rolemembers" => tokens.push(TokenProperty::from(Token::Role)),
+                "adminmembers" => tokens.push(TokenProperty::from(Token::Admin)),
+                // the options of `alter default privileges`
+                "schemas" => {
+                    tokens.push(TokenProperty::from(Token::InP));
+                    tokens.push(TokenProperty::from(Token::Schema));
+                },
+                "roles" => {
+                    tokens.push(TokenProperty::from(Token::For));
+                    tokens.push(TokenProperty::from(Token::Role));
+                },
                 _ => {}
             }
             match n.defaction() {
@@ -250,7 +404,42 @@ fn custom_handlers(node: &Node) -> TokenStream {
             match n.kind() {
                 protobuf::AExprKind::AexprOp => {}, // do nothing
                 protobuf::AExprKind::AexprOpAny => tokens.push(TokenProperty::from(Token::Any)),
-                protobuf::AExprKind::AexprIn => tokens.push(TokenProperty::from(Token::InP)),
+                protobuf::AExprKind::AexprIn => {
+                    // `not in` is `<>` of all values
+                    let negated = n.name.iter().any(|name| {
+                        matches!(&name.node, Some(NodeEnum::String(s)) if s.sval == "<>")
+                    });
+                    if negated {
+                        tokens.push(TokenProperty::from(Token::Not));
+                    }
+                    tokens.push(TokenProperty::from(Token::InP));
+                },
+                protobuf::AExprKind::AexprOpAll => tokens.push(TokenProperty::from(Token::All)),
+                protobuf::AExprKind::AexprLike | protobuf::AExprKind::AexprIlike => {
+                    // the operator is negated for `not like`, e.g. `!~~`
+                    let negated = n.name.iter().any(|name| {
+                        matches!(&name.node, Some(NodeEnum::String(s)) if s.sval.starts_with('!'))
+                    });
+                    if negated {
+                        tokens.push(TokenProperty::from(Token::Not));
+                    }
+                    if n.kind() == protobuf::AExprKind::AexprLike {
+                        tokens.push(TokenProperty::from(Token::Like));
+                    } else {
+                        tokens.push(TokenProperty::from(Token::Ilike));
+                    }
+                },
+                protobuf::AExprKind::AexprDistinct => {
+                    tokens.push(TokenProperty::from(Token::Is));
+                    tokens.push(TokenProperty::from(Token::Distinct));
+                    tokens.push(TokenProperty::from(Token::From));
+                },
+                protobuf::AExprKind::AexprNotDistinct => {
+                    tokens.push(TokenProperty::from(Token::Is));
+                    tokens.push(TokenProperty::from(Token::Not));
+                    tokens.push(TokenProperty::from(Token::Distinct));
+                    tokens.push(TokenProperty::from(Token::From));
+                },
                 _ => panic!("Unknown AExpr kind {:#?}", n.kind()),
             }
         },
@@ -293,6 +482,15 @@ fn custom_handlers(node: &Node) -> TokenStream {
             match n.op() {
                 protobuf::SqlValueFunctionOp::SvfopCurrentRole => tokens.push(TokenProperty::from(Token::CurrentRole)),
                 protobuf::SqlValueFunctionOp::SvfopCurrentUser => tokens.push(TokenProperty::from(Token::CurrentUser)),
+                protobuf::SqlValueFunctionOp::SvfopUser => tokens.push(TokenProperty::from(Token::User)),
+                protobuf::SqlValueFunctionOp::SvfopSessionUser => tokens.push(TokenProperty::from(Token::SessionUser)),
+                protobuf::SqlValueFunctionOp::SvfopCurrentCatalog => tokens.push(TokenProperty::from(Token::CurrentCatalog)),
+                protobuf::SqlValueFunctionOp::SvfopCurrentSchema => tokens.push(TokenProperty::from(Token::CurrentSchema)),
+                protobuf::SqlValueFunctionOp::SvfopCurrentDate => tokens.push(TokenProperty::from(Token::CurrentDate)),
+                protobuf::SqlValueFunctionOp::SvfopCurrentTime => tokens.push(TokenProperty::from(Token::CurrentTime)),
+                protobuf::SqlValueFunctionOp::SvfopCurrentTimestamp => tokens.push(TokenProperty::from(Token::CurrentTimestamp)),
+                protobuf::SqlValueFunctionOp::SvfopLocaltime => tokens.push(TokenProperty::from(Token::Localtime)),
+                protobuf::SqlValueFunctionOp::SvfopLocaltimestamp => tokens.push(TokenProperty::from(Token::Localtimestamp)),
                 _ => panic!("Unknown SqlvalueFunction {:#?}", n.op()),
             }
         },
@@ -309,6 +507,12 @@ fn custom_handlers(node: &Node) -> TokenStream {
             if n.isnull {
                 tokens.push(TokenProperty::from(Token::NullP));
             }
+            // the empty string has no string property
+            if let Some(protobuf::a_const::Val::Sval(s)) = &n.val {
+                if s.sval.is_empty() {
+                    tokens.push(TokenProperty::new(Some(String::new()), Some(SyntaxKind::Sconst)));
+                }
+            }
         },
         "AlterTableStmt" => quote! {
             tokens.push(TokenProperty::from(Token::Alter));
@@ -323,6 +527,11 @@ fn custom_handlers(node: &Node) -> TokenStream {
                     tokens.push(TokenProperty::from(Token::Default));
                 },
                 protobuf::AlterTableType::AtAddConstraint => tokens.push(TokenProperty::from(Token::AddP)),
+                // `column` is optional, e.g. `alter table t add c int`, and left over if missing
+                protobuf::AlterTableType::AtAddColumn => {
+                    tokens.push(TokenProperty::from(Token::AddP));
+                    tokens.push(TokenProperty::from(Token::Column));
+                },
                 protobuf::AlterTableType::AtAlterColumnType => {
                     tokens.push(TokenProperty::from(Token::Alter));
                     tokens.push(TokenProperty::from(Token::Column));
@@ -332,13 +541,34 @@ fn custom_handlers(node: &Node) -> TokenStream {
                     tokens.push(TokenProperty::from(Token::Drop));
                     tokens.push(TokenProperty::from(Token::Column));
                 },
+                protobuf::AlterTableType::AtEnableRowSecurity
+                | protobuf::AlterTableType::AtDisableRowSecurity
+                | protobuf::AlterTableType::AtForceRowSecurity
+                | protobuf::AlterTableType::AtNoForceRowSecurity => {
+                    match n.subtype() {
+                        protobuf::AlterTableType::AtEnableRowSecurity => tokens.push(TokenProperty::from(Token::EnableP)),
+                        protobuf::AlterTableType::AtDisableRowSecurity => tokens.push(TokenProperty::from(Token::DisableP)),
+                        protobuf::AlterTableType::AtForceRowSecurity => tokens.push(TokenProperty::from(Token::Force)),
+                        _ => {
+                            tokens.push(TokenProperty::from(Token::No));
+                            tokens.push(TokenProperty::from(Token::Force));
+                        }
+                    }
+                    tokens.push(TokenProperty::from(Token::Row));
+                    tokens.push(TokenProperty::from(Token::Level));
+                    tokens.push(TokenProperty::from(Token::Security));
+                },
                 _ => panic!("Unknown AlterTableCmd {:#?}", n.subtype()),
             }
         },
         "VariableSetStmt" => quote! {
             tokens.push(TokenProperty::from(Token::Set));
             match n.kind() {
-                protobuf::VariableSetKind::VarSetValue => tokens.push(TokenProperty::from(Token::To)),
+                protobuf::VariableSetKind::VarSetValue => {
+                    // `to` and `=` are the same, so one of them is left over
+                    tokens.push(TokenProperty::from(Token::To));
+                    tokens.push(TokenProperty::from(Token::Ascii61));
+                },
                 _ => panic!("Unknown VariableSetStmt {:#?}", n.kind()),
             }
         },
@@ -363,11 +593,32 @@ fn custom_handlers(node: &Node) -> TokenStream {
         },
         "RenameStmt" => quote! {
             tokens.push(TokenProperty::from(Token::Alter));
-            tokens.push(TokenProperty::from(Token::Table));
+            match n.rename_type() {
+                protobuf::ObjectType::ObjectIndex => tokens.push(TokenProperty::from(Token::Index)),
+                protobuf::ObjectType::ObjectFunction => tokens.push(TokenProperty::from(Token::Function)),
+                protobuf::ObjectType::ObjectProcedure => tokens.push(TokenProperty::from(Token::Procedure)),
+                // `column` is optional, which leaves the property unused
+                protobuf::ObjectType::ObjectColumn => {
+                    tokens.push(TokenProperty::from(Token::Table));
+                    tokens.push(TokenProperty::from(Token::Column));
+                },
+                protobuf::ObjectType::ObjectTabconstraint => {
+                    tokens.push(TokenProperty::from(Token::Table));
+                    tokens.push(TokenProperty::from(Token::Constraint));
+                },
+                _ => tokens.push(TokenProperty::from(Token::Table)),
+            }
+            if n.missing_ok {
+                tokens.push(TokenProperty::from(Token::IfP));
+                tokens.push(TokenProperty::from(Token::Exists));
+            }
             tokens.push(TokenProperty::from(Token::Rename));
             tokens.push(TokenProperty::from(Token::To));
         },
         "Constraint" => quote! {
+            if n.conname.len() > 0 {
+                tokens.push(TokenProperty::from(Token::Constraint));
+            }
             match n.contype() {
                 protobuf::ConstrType::ConstrNotnull => {
                     tokens.push(TokenProperty::from(Token::Not));
@@ -379,8 +630,26 @@ fn custom_handlers(node: &Node) -> TokenStream {
                     tokens.push(TokenProperty::from(Token::Primary));
                     tokens.push(TokenProperty::from(Token::Key));
                 },
-                protobuf::ConstrType::ConstrForeign => tokens.push(TokenProperty::from(Token::References)),
+                // `foreign key` is only part of table constraints, which list their columns
+                protobuf::ConstrType::ConstrForeign => {
+                    if n.fk_attrs.len() > 0 {
+                        tokens.push(TokenProperty::from(Token::Foreign));
+                        tokens.push(TokenProperty::from(Token::Key));
+                    }
+                    tokens.push(TokenProperty::from(Token::References));
+                },
                 protobuf::ConstrType::ConstrUnique => tokens.push(TokenProperty::from(Token::Unique)),
+                protobuf::ConstrType::ConstrIdentity => {
+                    tokens.push(TokenProperty::from(Token::Generated));
+                    if n.generated_when == "a" {
+                        tokens.push(TokenProperty::from(Token::Always));
+                    } else {
+                        tokens.push(TokenProperty::from(Token::By));
+                        tokens.push(TokenProperty::from(Token::Default));
+                    }
+                    tokens.push(TokenProperty::from(Token::As));
+                    tokens.push(TokenProperty::from(Token::IdentityP));
+                },
                 _ => panic!("Unknown Constraint {:#?}", n.contype()),
             };
             if n.options.len() > 0 {
@@ -405,6 +674,19 @@ fn custom_handlers(node: &Node) -> TokenStream {
                 tokens.push(TokenProperty::from(Token::Using));
             }
         },
+        "UpdateStmt" => quote! {
+            tokens.push(TokenProperty::from(Token::Update));
+            tokens.push(TokenProperty::from(Token::Set));
+            if n.from_clause.len() > 0 {
+                tokens.push(TokenProperty::from(Token::From));
+            }
+            if n.where_clause.is_some() {
+                tokens.push(TokenProperty::from(Token::Where));
+            }
+            if n.returning_list.len() > 0 {
+                tokens.push(TokenProperty::from(Token::Returning));
+            }
+        },
         "ViewStmt" => quote! {
             tokens.push(TokenProperty::from(Token::Create));
             tokens.push(TokenProperty::from(Token::View));
@@ -552,6 +834,17 @@ fn custom_handlers(node: &Node) -> TokenStream {
                             }
                         }
                     }
+                    if node.defname == "security" {
+                        if let Some(NodeEnum::Boolean(node)) =
+                            node.arg.as_ref().and_then(|arg| arg.node.as_ref())
+                        {
+                            tokens.push(TokenProperty::from(if node.boolval {
+                                Token::Definer
+                            } else {
+                                Token::Invoker
+                            }));
+                        }
+                    }
                 }
             }
         },
@@ -979,6 +1272,96 @@ fn custom_handlers(node: &Node) -> TokenStream {
                 _ => {}
             }
         },
+        "CreateRoleStmt" => quote! {
+            tokens.push(TokenProperty::from(Token::Create));
+            match n.stmt_type() {
+                protobuf::RoleStmtType::RolestmtRole => tokens.push(TokenProperty::from(Token::Role)),
+                protobuf::RoleStmtType::RolestmtUser => tokens.push(TokenProperty::from(Token::User)),
+                protobuf::RoleStmtType::RolestmtGroup => tokens.push(TokenProperty::from(Token::GroupP)),
+                _ => panic!("Unknown CreateRoleStmt {:#?}", n.stmt_type()),
+            }
+            // `with` is optional, and left over if missing
+            tokens.push(TokenProperty::from(Token::With));
+        },
+        "AlterRoleStmt" => quote! {
+            tokens.push(TokenProperty::from(Token::Alter));
+            // `alter role` and `alter user` are the same, so one of them is left over
+            tokens.push(TokenProperty::from(Token::Role));
+            tokens.push(TokenProperty::from(Token::User));
+            tokens.push(TokenProperty::from(Token::With));
+            if let Some(role) = &n.role {
+                tokens.extend(role_spec_properties(role));
+            }
+        },
+        "CreateUserMappingStmt" => quote! {
+            tokens.push(TokenProperty::from(Token::Create));
+            tokens.push(TokenProperty::from(Token::User));
+            tokens.push(TokenProperty::from(Token::Mapping));
+            tokens.push(TokenProperty::from(Token::For));
+            tokens.push(TokenProperty::from(Token::Server));
+            if let Some(user) = &n.user {
+                tokens.extend(role_spec_properties(user));
+            }
+            if n.options.len() > 0 {
+                tokens.push(TokenProperty::from(Token::Options));
+            }
+        },
+        "AlterUserMappingStmt" => quote! {
+            tokens.push(TokenProperty::from(Token::Alter));
+            tokens.push(TokenProperty::from(Token::User));
+            tokens.push(TokenProperty::from(Token::Mapping));
+            tokens.push(TokenProperty::from(Token::For));
+            tokens.push(TokenProperty::from(Token::Server));
+            if let Some(user) = &n.user {
+                tokens.extend(role_spec_properties(user));
+            }
+            tokens.push(TokenProperty::from(Token::Options));
+        },
+        "AlterDefaultPrivilegesStmt" => quote! {
+            tokens.push(TokenProperty::from(Token::Alter));
+            tokens.push(TokenProperty::from(Token::Default));
+            tokens.push(TokenProperty::from(Token::Privileges));
+            if let Some(action) = &n.action {
+                tokens.extend(grant_properties(action));
+            }
+        },
+        "GrantStmt" => quote! {
+            tokens.extend(grant_properties(n));
+        },
+        "GrantRoleStmt" => quote! {
+            if n.is_grant {
+                tokens.push(TokenProperty::from(Token::Grant));
+                tokens.push(TokenProperty::from(Token::To));
+                if n.admin_opt {
+                    tokens.push(TokenProperty::from(Token::With));
+                    tokens.push(TokenProperty::from(Token::Admin));
+                    tokens.push(TokenProperty::from(Token::Option));
+                }
+            } else {
+                tokens.push(TokenProperty::from(Token::Revoke));
+                tokens.push(TokenProperty::from(Token::From));
+                if n.admin_opt {
+                    tokens.push(TokenProperty::from(Token::Admin));
+                    tokens.push(TokenProperty::from(Token::Option));
+                    tokens.push(TokenProperty::from(Token::For));
+                }
+            }
+            if let Some(grantor) = &n.grantor {
+                tokens.push(TokenProperty::from(Token::Granted));
+                tokens.push(TokenProperty::from(Token::By));
+                tokens.extend(role_spec_properties(grantor));
+            }
+            match n.behavior {
+                // DropRestrict
+                1 => tokens.push(TokenProperty::from(Token::Restrict)),
+                // DropCascade
+                2 => tokens.push(TokenProperty::from(Token::Cascade)),
+                _ => {}
+            }
+        },
+        "RoleSpec" => quote! {
+            tokens.extend(role_spec_properties(n));
+        },
         _ => quote! {},
     }
 }
@@ -0,0 +1,132 @@
+//! Loads the schema cache with a single query
+//!
+//! The query of every object class is wrapped into a subquery that aggregates its rows into a
+//! json array, so that the whole cache can be fetched in one round trip instead of one query per
+//! object class.
+
+use serde::de::DeserializeOwned;
+use sqlx::{postgres::PgRow, types::JsonValue, PgPool, Row};
+
+use crate::schema_cache::SchemaCache;
+
+macro_rules! json_agg {
+    ($file:literal, $name:literal) => {
+        concat!(
+            "  (select coalesce(json_agg(q), '[]') from (\n",
+            include_str!($file),
+            "  ) q) as ",
+            $name
+        )
+    };
+}
+
+const BATCH_QUERY: &str = concat!(
+    "select\n",
+    json_agg!("queries/schemas.sql", "schemas"),
+    ",\n",
+    json_agg!("queries/tables.sql", "tables"),
+    ",\n",
+    json_agg!("queries/columns.sql", "columns"),
+    ",\n",
+    json_agg!("queries/functions.sql", "functions"),
+    ",\n",
+    json_agg!("queries/types.sql", "types"),
+    ",\n",
+    json_agg!("queries/foreign_tables.sql", "foreign_tables"),
+    ",\n",
+    json_agg!("queries/foreign_servers.sql", "foreign_servers"),
+    ",\n",
+    json_agg!("queries/indexes.sql", "indexes"),
+    ",\n",
+    json_agg!("queries/foreign_keys.sql", "foreign_keys"),
+    ",\n",
+    json_agg!("queries/policies.sql", "policies"),
+    ",\n",
+    json_agg!("queries/publications.sql", "publications"),
+    ",\n",
+    json_agg!("queries/subscriptions.sql", "subscriptions"),
+    ",\n",
+    "  current_schemas(false) as search_path\n",
+);
+
+/// Loads all object classes, restricting the schema-scoped classes to the given schemas if any
+pub(crate) async fn load(pool: &PgPool, schemas: Option<&[String]>) -> SchemaCache {
+    let row = sqlx::query(BATCH_QUERY)
+        .bind(schemas)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+
+    let mut cache = SchemaCache {
+        schemas: decode(&row, "schemas"),
+        tables: decode(&row, "tables"),
+        columns: decode(&row, "columns"),
+        functions: decode(&row, "functions"),
+        types: decode(&row, "types"),
+        foreign_tables: decode(&row, "foreign_tables"),
+        foreign_servers: decode(&row, "foreign_servers"),
+        indexes: decode(&row, "indexes"),
+        foreign_keys: decode(&row, "foreign_keys"),
+        policies: decode(&row, "policies"),
+        publications: decode(&row, "publications"),
+        subscriptions: decode(&row, "subscriptions"),
+        loaded_schemas: schemas.map(|s| s.iter().cloned().collect()),
+        search_path: row.get("search_path"),
+    };
+    cache.intern();
+    cache
+}
+
+fn decode<T: DeserializeOwned>(row: &PgRow, column: &str) -> Vec<T> {
+    let JsonValue::Array(items) = row.get::<JsonValue, _>(column) else {
+        panic!("Expected a json array for {}", column);
+    };
+
+    items
+        .into_iter()
+        .map(|item| serde_json::from_value(strip_overrides(item)).unwrap())
+        .collect()
+}
+
+/// Removes the sqlx type and nullability overrides, e.g. the `!` in `"id!"` or the `: _` in
+/// `"schema: _"`, from the keys
+fn strip_overrides(item: JsonValue) -> JsonValue {
+    match item {
+        JsonValue::Object(obj) => JsonValue::Object(
+            obj.into_iter()
+                .map(|(k, v)| {
+                    let name = k.split(':').next().unwrap_or_default();
+                    (name.trim_end().trim_end_matches(['!', '?']).to_string(), v)
+                })
+                .collect(),
+        ),
+        v => v,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use crate::schema_cache::SchemaCacheItem;
+    use crate::{Function, Table};
+
+    #[test]
+    fn test_batch_matches_single_queries() {
+        let conn_string = std::env::var("DB_CONNECTION_STRING").unwrap();
+
+        let pool = async_std::task::block_on(PgPool::connect(conn_string.as_str())).unwrap();
+
+        let mut cache = async_std::task::block_on(super::load(&pool, None));
+        let mut tables = async_std::task::block_on(Table::load(&pool));
+
+        // neither query orders the tables
+        cache.tables.sort_by_key(|t| t.id);
+        tables.sort_by_key(|t| t.id);
+        assert_eq!(cache.tables, tables);
+        assert_eq!(
+            cache.functions.len(),
+            async_std::task::block_on(Function::load(&pool)).len()
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::ident::Ident;
+use crate::schema_cache::{SchemaCacheItem, SchemaScopedCacheItem};
+
+/// A column of a table, view, materialized view or foreign table
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Column {
+    pub table_id: i64,
+    pub schema: Ident,
+    pub table: String,
+    pub name: String,
+    /// The 1-based position of the column within its table
+    pub position: i64,
+    pub type_id: i64,
+    /// The type including modifiers, e.g. `character varying(255)`
+    pub type_name: Ident,
+    pub is_nullable: bool,
+    pub default_value: Option<String>,
+    /// Whether the column is generated by an identity sequence
+    pub is_identity: bool,
+    pub is_primary_key: bool,
+    pub comment: Option<String>,
+}
+
+impl SchemaCacheItem for Column {
+    type Item = Column;
+
+    async fn load(pool: &PgPool) -> Vec<Column> {
+        Column::query(pool, None).await
+    }
+}
+
+impl SchemaScopedCacheItem for Column {
+    async fn load_in_schemas(pool: &PgPool, schemas: &[String]) -> Vec<Column> {
+        Column::query(pool, Some(schemas)).await
+    }
+}
+
+impl Column {
+    async fn query(pool: &PgPool, schemas: Option<&[String]>) -> Vec<Column> {
+        sqlx::query_file_as!(Column, "src/queries/columns.sql", schemas)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+}
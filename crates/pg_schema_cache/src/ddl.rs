@@ -0,0 +1,1754 @@
+//! Builds a schema cache from DDL statements without a database connection
+//!
+//! This enables completions and checks in CI or for users who never connect to a live database.
+//! Only what can be derived from the statements themselves is populated, so there are no
+//! statistics and no built-in objects other than the ids of the most common built-in types.
+
+use std::{
+    fs, io,
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use pg_query_ext::{protobuf, NodeEnum};
+
+use crate::{
+    columns::Column,
+    export::{qualified, quote_ident},
+    foreign_keys::ForeignKey,
+    functions::{Behavior, Function, FunctionArg, FunctionArgs},
+    ident::Ident,
+    indexes::Index,
+    policies::Policy,
+    schemas::Schema,
+    types::{Enums, PostgresType, PostgresTypeAttribute, TypeAttributes},
+    ForeignServer, ForeignTable, SchemaCache, Table,
+};
+
+/// Objects that are created offline get ids above the range reserved for built-in objects
+const FIRST_OFFLINE_ID: i64 = 16384;
+
+const DEFAULT_SCHEMA: &str = "public";
+
+/// The ids of common built-in types, which are stable across Postgres versions
+const BUILTIN_TYPE_IDS: &[(&str, i64)] = &[
+    ("bool", 16),
+    ("bytea", 17),
+    ("char", 18),
+    ("name", 19),
+    ("int8", 20),
+    ("int2", 21),
+    ("int4", 23),
+    ("text", 25),
+    ("oid", 26),
+    ("json", 114),
+    ("xml", 142),
+    ("float4", 700),
+    ("float8", 701),
+    ("money", 790),
+    ("bpchar", 1042),
+    ("varchar", 1043),
+    ("date", 1082),
+    ("time", 1083),
+    ("timestamp", 1114),
+    ("timestamptz", 1184),
+    ("interval", 1186),
+    ("timetz", 1266),
+    ("numeric", 1700),
+    ("record", 2249),
+    ("void", 2278),
+    ("trigger", 2279),
+    ("uuid", 2950),
+    ("jsonb", 3802),
+];
+
+impl SchemaCache {
+    /// Builds a schema cache by applying all statements of the given sql source
+    pub fn from_sql(sql: &str) -> SchemaCache {
+        let mut cache = SchemaCache::new();
+        cache.apply_sql(sql);
+        cache
+    }
+
+    /// Builds a schema cache from sql files
+    ///
+    /// See [`SchemaCache::read_sql_files`] for the order in which the files are applied.
+    pub fn from_files(paths: &[PathBuf]) -> io::Result<SchemaCache> {
+        let mut cache = SchemaCache::new();
+        for sql in SchemaCache::read_sql_files(paths)? {
+            cache.apply_sql(&sql);
+        }
+        Ok(cache)
+    }
+
+    /// Reads sql files
+    ///
+    /// Directories are searched recursively for `.sql` files. The files are returned in the
+    /// order of their path, so that timestamp-prefixed migrations are applied in the right order.
+    pub fn read_sql_files(paths: &[PathBuf]) -> io::Result<Vec<String>> {
+        let mut files = Vec::new();
+        for path in paths {
+            collect_sql_files(path, &mut files)?;
+        }
+        files.sort();
+
+        files.into_iter().map(fs::read_to_string).collect()
+    }
+
+    /// Applies all statements of the given sql source. Statements that cannot be parsed are
+    /// skipped.
+    pub fn apply_sql(&mut self, sql: &str) {
+        // the parser splits exactly, but fails on the first syntax error in the source
+        let statements = pg_query::split_with_parser(sql).unwrap_or_else(|_| {
+            pg_statement_splitter::split(sql)
+                .ranges
+                .into_iter()
+                .map(|range| &sql[range])
+                .collect()
+        });
+
+        let mut builder = DdlBuilder::new(self);
+        for statement in statements {
+            if let Ok(stmt) = pg_query_ext::parse(statement) {
+                builder.apply(&stmt, DEFAULT_SCHEMA);
+            }
+        }
+
+        self.intern();
+    }
+
+    /// Applies a DDL statement to the cache
+    ///
+    /// For example, `create table` will add the table to the cache if it does not exist yet.
+    /// Statements that do not change the catalog are ignored.
+    pub fn mutate(&mut self, stmt: &NodeEnum) {
+        DdlBuilder::new(self).apply(stmt, DEFAULT_SCHEMA);
+    }
+}
+
+/// Applies DDL statements to a schema cache, counting up the ids of the objects they create
+struct DdlBuilder<'a> {
+    cache: &'a mut SchemaCache,
+    /// The id of the next object that is created, which starts above those of the cache
+    next_offline_id: i64,
+}
+
+impl<'a> DdlBuilder<'a> {
+    fn new(cache: &'a mut SchemaCache) -> DdlBuilder<'a> {
+        let next_offline_id = first_free_id(cache);
+        DdlBuilder {
+            cache,
+            next_offline_id,
+        }
+    }
+}
+
+impl Deref for DdlBuilder<'_> {
+    type Target = SchemaCache;
+
+    fn deref(&self) -> &SchemaCache {
+        self.cache
+    }
+}
+
+impl DerefMut for DdlBuilder<'_> {
+    fn deref_mut(&mut self) -> &mut SchemaCache {
+        self.cache
+    }
+}
+
+impl DdlBuilder<'_> {
+    fn apply(&mut self, stmt: &NodeEnum, default_schema: &str) {
+        match stmt {
+            NodeEnum::CreateSchemaStmt(s) => {
+                if s.schemaname.is_empty() {
+                    return;
+                }
+                if !self
+                    .schemas
+                    .iter()
+                    .any(|schema| schema.name == s.schemaname)
+                {
+                    let id = self.next_id();
+                    self.schemas.push(Schema {
+                        id,
+                        name: s.schemaname.clone(),
+                        owner: Ident::default(),
+                    });
+                }
+                for elt in &s.schema_elts {
+                    if let Some(node) = &elt.node {
+                        self.apply(node, &s.schemaname);
+                    }
+                }
+            }
+            NodeEnum::CreateStmt(s) => self.create_table(s, default_schema),
+            NodeEnum::CreateForeignTableStmt(s) => {
+                let Some(relation) = s.base_stmt.as_ref().and_then(|b| b.relation.as_ref()) else {
+                    return;
+                };
+                let schema = schema_or(&relation.schemaname, default_schema);
+                if self
+                    .find_foreign_table(&relation.relname, Some(schema))
+                    .is_some()
+                {
+                    return;
+                }
+                let id = self.next_id();
+                let server_id = self
+                    .foreign_servers
+                    .iter()
+                    .find(|server| server.name == s.servername)
+                    .map_or(0, |server| server.id);
+                self.foreign_tables.push(ForeignTable {
+                    id,
+                    schema: schema.into(),
+                    name: relation.relname.clone(),
+                    server_id,
+                    server_name: s.servername.as_str().into(),
+                    options: options(&s.options),
+                    comment: None,
+                });
+                if let Some(base) = &s.base_stmt {
+                    self.add_table_elements(
+                        id,
+                        schema,
+                        &relation.relname,
+                        &base.table_elts,
+                        default_schema,
+                    );
+                }
+            }
+            NodeEnum::CreateForeignServerStmt(s) => {
+                if self
+                    .foreign_servers
+                    .iter()
+                    .any(|server| server.name == s.servername)
+                {
+                    return;
+                }
+                let id = self.next_id();
+                self.foreign_servers.push(ForeignServer {
+                    id,
+                    name: s.servername.clone(),
+                    foreign_data_wrapper: s.fdwname.as_str().into(),
+                    server_type: non_empty(&s.servertype),
+                    server_version: non_empty(&s.version),
+                    options: options(&s.options),
+                    comment: None,
+                });
+            }
+            NodeEnum::CreateEnumStmt(s) => {
+                let (schema, name) = qualified_name(&s.type_name, default_schema);
+                let values = s.vals.iter().filter_map(string_value).collect();
+                self.create_type(schema, name, Enums { values }, TypeAttributes::default());
+            }
+            NodeEnum::CompositeTypeStmt(s) => {
+                let Some(typevar) = &s.typevar else {
+                    return;
+                };
+                let attrs = s
+                    .coldeflist
+                    .iter()
+                    .filter_map(|n| match &n.node {
+                        Some(NodeEnum::ColumnDef(c)) => Some(PostgresTypeAttribute {
+                            name: c.colname.clone(),
+                            type_id: self.type_id(c.type_name.as_ref()),
+                        }),
+                        _ => None,
+                    })
+                    .collect();
+                self.create_type(
+                    schema_or(&typevar.schemaname, default_schema).to_string(),
+                    typevar.relname.clone(),
+                    Enums::default(),
+                    TypeAttributes { attrs },
+                );
+            }
+            NodeEnum::CreateDomainStmt(s) => {
+                let (schema, name) = qualified_name(&s.domainname, default_schema);
+                self.create_type(schema, name, Enums::default(), TypeAttributes::default());
+            }
+            NodeEnum::CreateFunctionStmt(s) => self.create_function(s, default_schema),
+            NodeEnum::DropStmt(s) => self.drop(s, default_schema),
+            NodeEnum::RenameStmt(s) => self.rename(s, default_schema),
+            NodeEnum::CommentStmt(s) => self.comment(s, default_schema),
+            NodeEnum::AlterTableStmt(s) => self.alter_table(s, default_schema),
+            NodeEnum::IndexStmt(s) => self.create_index(s, default_schema),
+            NodeEnum::CreatePolicyStmt(s) => self.create_policy(s, default_schema),
+            _ => {}
+        }
+    }
+
+    fn next_id(&mut self) -> i64 {
+        let id = self.next_offline_id;
+        self.next_offline_id += 1;
+        id
+    }
+
+    fn type_id(&self, type_name: Option<&protobuf::TypeName>) -> i64 {
+        let Some(type_name) = type_name else {
+            return 0;
+        };
+        if !type_name.array_bounds.is_empty() {
+            return 0;
+        }
+        let (schema, name) = qualified_name(&type_name.names, DEFAULT_SCHEMA);
+        BUILTIN_TYPE_IDS
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, id)| *id)
+            .or_else(|| {
+                self.types
+                    .iter()
+                    .find(|t| t.name == name && t.schema == schema)
+                    .map(|t| t.id)
+            })
+            .unwrap_or(0)
+    }
+
+    fn create_table(&mut self, stmt: &protobuf::CreateStmt, default_schema: &str) {
+        let Some(relation) = &stmt.relation else {
+            return;
+        };
+        let schema = schema_or(&relation.schemaname, default_schema);
+        if self
+            .tables
+            .iter()
+            .any(|t| t.name == relation.relname && t.schema == schema)
+        {
+            return;
+        }
+
+        let partition_of = if stmt.partbound.is_some() {
+            stmt.inh_relations
+                .iter()
+                .find_map(|n| match &n.node {
+                    Some(NodeEnum::RangeVar(parent)) => Some(parent),
+                    _ => None,
+                })
+                .and_then(|parent| {
+                    let parent_schema = schema_or(&parent.schemaname, default_schema);
+                    self.tables
+                        .iter()
+                        .find(|t| t.name == parent.relname && t.schema == parent_schema)
+                })
+                .map(|parent| parent.id)
+        } else {
+            None
+        };
+
+        let partition_key = stmt.partspec.as_ref().map(|spec| {
+            let params = spec
+                .part_params
+                .iter()
+                .filter_map(|n| match &n.node {
+                    Some(NodeEnum::PartitionElem(e)) if !e.name.is_empty() => Some(e.name.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            format!("{} ({})", spec.strategy.to_uppercase(), params.join(", "))
+        });
+
+        let id = self.next_id();
+        self.tables.push(Table {
+            id,
+            schema: schema.into(),
+            name: relation.relname.clone(),
+            is_partitioned: partition_key.is_some(),
+            partition_key,
+            partition_of,
+            ..Default::default()
+        });
+
+        // partitions have the columns of their parent
+        if let Some(parent_id) = partition_of {
+            let inherited = self
+                .columns
+                .iter()
+                .filter(|c| c.table_id == parent_id)
+                .map(|c| Column {
+                    table_id: id,
+                    schema: schema.into(),
+                    table: relation.relname.clone(),
+                    comment: None,
+                    ..c.clone()
+                })
+                .collect::<Vec<_>>();
+            self.columns.extend(inherited);
+        }
+        self.add_table_elements(
+            id,
+            schema,
+            &relation.relname,
+            &stmt.table_elts,
+            default_schema,
+        );
+    }
+
+    /// Adds the columns, keys and foreign keys of a `create table` statement
+    fn add_table_elements(
+        &mut self,
+        table_id: i64,
+        schema: &str,
+        table: &str,
+        elements: &[protobuf::Node],
+        default_schema: &str,
+    ) {
+        for element in elements {
+            match &element.node {
+                Some(NodeEnum::ColumnDef(def)) => {
+                    self.add_column(table_id, schema, table, def, default_schema)
+                }
+                Some(NodeEnum::Constraint(c)) => self.add_constraint(table_id, c, default_schema),
+                _ => {}
+            }
+        }
+    }
+
+    fn add_column(
+        &mut self,
+        table_id: i64,
+        schema: &str,
+        table: &str,
+        def: &protobuf::ColumnDef,
+        default_schema: &str,
+    ) {
+        if let Some(column) = self
+            .columns
+            .iter_mut()
+            .find(|c| c.table_id == table_id && c.name == def.colname)
+        {
+            // e.g. the constraints of a partition column, which exists on the parent already
+            apply_column_constraints(column, &def.constraints);
+            return;
+        }
+
+        let position = self
+            .columns
+            .iter()
+            .filter(|c| c.table_id == table_id)
+            .map(|c| c.position)
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        let mut column = Column {
+            table_id,
+            schema: schema.into(),
+            table: table.to_string(),
+            name: def.colname.clone(),
+            position,
+            type_id: self.type_id(def.type_name.as_ref()),
+            type_name: def
+                .type_name
+                .as_ref()
+                .map(format_type)
+                .unwrap_or_default()
+                .into(),
+            is_nullable: !def.is_not_null,
+            default_value: def.raw_default.as_deref().and_then(deparse_expr),
+            is_identity: false,
+            is_primary_key: false,
+            comment: None,
+        };
+
+        // `serial` is shorthand for an integer column that defaults to the next value of a sequence
+        if let Some((type_name, type_id)) = serial_type(&column.type_name) {
+            column.type_name = type_name.into();
+            column.type_id = type_id;
+            column.is_nullable = false;
+            column.default_value = Some(format!(
+                "nextval('{}_{}_seq'::regclass)",
+                table, column.name
+            ));
+        }
+
+        apply_column_constraints(&mut column, &def.constraints);
+        self.columns.push(column);
+
+        for constraint in &def.constraints {
+            if let Some(NodeEnum::Constraint(c)) = &constraint.node {
+                self.add_key_index(table_id, c, vec![def.colname.clone()]);
+                self.add_foreign_key(table_id, c, vec![def.colname.clone()], default_schema);
+            }
+        }
+    }
+
+    /// Applies a table constraint, of which only primary keys, unique constraints and foreign
+    /// keys are tracked
+    fn add_constraint(
+        &mut self,
+        table_id: i64,
+        constraint: &protobuf::Constraint,
+        default_schema: &str,
+    ) {
+        let keys = constraint
+            .keys
+            .iter()
+            .filter_map(string_value)
+            .collect::<Vec<_>>();
+        if protobuf::ConstrType::from_i32(constraint.contype)
+            == Some(protobuf::ConstrType::ConstrPrimary)
+        {
+            for column in self
+                .columns
+                .iter_mut()
+                .filter(|c| c.table_id == table_id && keys.contains(&c.name))
+            {
+                column.is_primary_key = true;
+                column.is_nullable = false;
+            }
+        }
+        self.add_key_index(table_id, constraint, keys);
+        let columns = constraint
+            .fk_attrs
+            .iter()
+            .filter_map(string_value)
+            .collect();
+        self.add_foreign_key(table_id, constraint, columns, default_schema);
+    }
+
+    /// Adds the index of a primary key or unique constraint on the given columns
+    fn add_key_index(
+        &mut self,
+        table_id: i64,
+        constraint: &protobuf::Constraint,
+        columns: Vec<String>,
+    ) {
+        let (is_primary, suffix) = match protobuf::ConstrType::from_i32(constraint.contype) {
+            Some(protobuf::ConstrType::ConstrPrimary) => (true, "pkey"),
+            Some(protobuf::ConstrType::ConstrUnique) => (false, "key"),
+            _ => return,
+        };
+        let id = self.next_id();
+        let Some(table) = self.tables.iter().find(|t| t.id == table_id) else {
+            return;
+        };
+        let name = match non_empty(&constraint.conname) {
+            Some(name) => name,
+            None if is_primary => format!("{}_{}", table.name, suffix),
+            None => format!("{}_{}_{}", table.name, columns.join("_"), suffix),
+        };
+        let definition = format!(
+            "CREATE UNIQUE INDEX {} ON {} USING btree ({})",
+            quote_ident(&name),
+            qualified(&table.schema, &table.name),
+            columns
+                .iter()
+                .map(|c| quote_ident(c))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let index = Index {
+            id,
+            schema: table.schema.clone(),
+            table_id,
+            table: table.name.clone(),
+            name,
+            columns,
+            is_unique: true,
+            is_primary,
+            is_partial: false,
+            definition,
+        };
+        self.add_index(index);
+    }
+
+    /// Adds a foreign key constraint on the given columns, which are named like Postgres does if
+    /// the constraint has no name
+    fn add_foreign_key(
+        &mut self,
+        table_id: i64,
+        constraint: &protobuf::Constraint,
+        columns: Vec<String>,
+        default_schema: &str,
+    ) {
+        if protobuf::ConstrType::from_i32(constraint.contype)
+            != Some(protobuf::ConstrType::ConstrForeign)
+        {
+            return;
+        }
+        let id = self.next_id();
+        let (Some(table), Some(referenced)) = (
+            self.tables.iter().find(|t| t.id == table_id),
+            &constraint.pktable,
+        ) else {
+            return;
+        };
+        let referenced_schema = schema_or(&referenced.schemaname, default_schema);
+        let referenced_table_id = self
+            .relation_id(&referenced.relname, referenced_schema)
+            .unwrap_or(0);
+        let mut referenced_columns = constraint
+            .pk_attrs
+            .iter()
+            .filter_map(string_value)
+            .collect::<Vec<_>>();
+        // without columns, the primary key of the referenced table is referenced
+        if referenced_columns.is_empty() {
+            referenced_columns = self
+                .indexes
+                .iter()
+                .find(|i| i.table_id == referenced_table_id && i.is_primary)
+                .map(|i| i.columns.clone())
+                .unwrap_or_default();
+        }
+
+        let foreign_key = ForeignKey {
+            id,
+            schema: table.schema.clone(),
+            table_id,
+            table: table.name.clone(),
+            name: non_empty(&constraint.conname)
+                .unwrap_or_else(|| format!("{}_{}_fkey", table.name, columns.join("_"))),
+            columns,
+            referenced_table_id,
+            referenced_schema: referenced_schema.into(),
+            referenced_table: referenced.relname.clone(),
+            referenced_columns,
+        };
+        self.foreign_keys.push(foreign_key);
+    }
+
+    fn create_index(&mut self, stmt: &protobuf::IndexStmt, default_schema: &str) {
+        let Some(relation) = &stmt.relation else {
+            return;
+        };
+        let schema = schema_or(&relation.schemaname, default_schema);
+        let Some(table_id) = self.relation_id(&relation.relname, schema) else {
+            return;
+        };
+
+        let columns = stmt
+            .index_params
+            .iter()
+            .filter_map(|param| match &param.node {
+                Some(NodeEnum::IndexElem(elem)) => match &elem.expr {
+                    Some(expr) => deparse_expr(expr),
+                    None => Some(elem.name.clone()),
+                },
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // unnamed indexes are named like Postgres does, which names an expression after its
+        // function, or `expr` otherwise
+        let name = non_empty(&stmt.idxname).unwrap_or_else(|| {
+            let names = stmt
+                .index_params
+                .iter()
+                .filter_map(|param| match &param.node {
+                    Some(NodeEnum::IndexElem(elem)) => Some(match &elem.expr {
+                        Some(expr) => match &expr.node {
+                            Some(NodeEnum::FuncCall(call)) => call
+                                .funcname
+                                .last()
+                                .and_then(|n| match &n.node {
+                                    Some(NodeEnum::String(s)) => Some(s.sval.clone()),
+                                    _ => None,
+                                })
+                                .unwrap_or_else(|| "expr".to_string()),
+                            _ => "expr".to_string(),
+                        },
+                        None => elem.name.clone(),
+                    }),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            format!("{}_{}_idx", relation.relname, names.join("_"))
+        });
+        let definition = NodeEnum::IndexStmt(Box::new(protobuf::IndexStmt {
+            idxname: name.clone(),
+            ..stmt.clone()
+        }))
+        .deparse()
+        .unwrap_or_default();
+
+        let index = Index {
+            id: self.next_id(),
+            schema: schema.into(),
+            table_id,
+            table: relation.relname.clone(),
+            name,
+            columns,
+            is_unique: stmt.unique || stmt.primary,
+            is_primary: stmt.primary,
+            is_partial: stmt.where_clause.is_some(),
+            definition,
+        };
+        self.add_index(index);
+    }
+
+    /// Adds an index unless there is one with the same name already, e.g. for
+    /// `create index if not exists`
+    fn add_index(&mut self, index: Index) {
+        if !self
+            .indexes
+            .iter()
+            .any(|i| i.name == index.name && i.schema == index.schema)
+        {
+            self.indexes.push(index);
+        }
+    }
+
+    fn create_policy(&mut self, stmt: &protobuf::CreatePolicyStmt, default_schema: &str) {
+        let Some(relation) = &stmt.table else {
+            return;
+        };
+        let schema = schema_or(&relation.schemaname, default_schema);
+        let Some(table_id) = self.relation_id(&relation.relname, schema) else {
+            return;
+        };
+        if self
+            .policies
+            .iter()
+            .any(|p| p.table_id == table_id && p.name == stmt.policy_name)
+        {
+            return;
+        }
+
+        let roles = stmt
+            .roles
+            .iter()
+            .filter_map(|role| match &role.node {
+                Some(NodeEnum::RoleSpec(role)) => {
+                    Some(match protobuf::RoleSpecType::from_i32(role.roletype) {
+                        Some(protobuf::RoleSpecType::RolespecPublic) => "public".to_string(),
+                        Some(protobuf::RoleSpecType::RolespecCurrentRole) => {
+                            "current_role".to_string()
+                        }
+                        Some(protobuf::RoleSpecType::RolespecCurrentUser) => {
+                            "current_user".to_string()
+                        }
+                        Some(protobuf::RoleSpecType::RolespecSessionUser) => {
+                            "session_user".to_string()
+                        }
+                        _ => role.rolename.clone(),
+                    })
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let policy = Policy {
+            id: self.next_id(),
+            schema: schema.into(),
+            table_id,
+            table: relation.relname.clone(),
+            name: stmt.policy_name.clone(),
+            command: stmt.cmd_name.to_uppercase(),
+            is_permissive: stmt.permissive,
+            roles: if roles.is_empty() {
+                vec!["public".to_string()]
+            } else {
+                roles
+            },
+            using_expr: stmt.qual.as_deref().and_then(deparse_expr),
+            check_expr: stmt.with_check.as_deref().and_then(deparse_expr),
+        };
+        self.policies.push(policy);
+    }
+
+    /// Returns the id of the table or foreign table with the given name
+    fn relation_id(&self, name: &str, schema: &str) -> Option<i64> {
+        self.tables
+            .iter()
+            .find(|t| t.name == name && t.schema == schema)
+            .map(|t| t.id)
+            .or_else(|| {
+                self.foreign_tables
+                    .iter()
+                    .find(|t| t.name == name && t.schema == schema)
+                    .map(|t| t.id)
+            })
+    }
+
+    fn create_type(
+        &mut self,
+        schema: String,
+        name: String,
+        enums: Enums,
+        attributes: TypeAttributes,
+    ) {
+        if self
+            .types
+            .iter()
+            .any(|t| t.name == name && t.schema == schema)
+        {
+            return;
+        }
+        let id = self.next_id();
+        self.types.push(Arc::new(PostgresType {
+            id,
+            format: name.clone(),
+            name,
+            schema: schema.into(),
+            enums,
+            attributes,
+            comment: None,
+        }));
+    }
+
+    fn create_function(&mut self, stmt: &protobuf::CreateFunctionStmt, default_schema: &str) {
+        if stmt.is_procedure {
+            return;
+        }
+
+        let (schema, name) = qualified_name(&stmt.funcname, default_schema);
+
+        let params = stmt
+            .parameters
+            .iter()
+            .filter_map(|n| match &n.node {
+                Some(NodeEnum::FunctionParameter(p)) => Some(p.as_ref()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let args = params
+            .iter()
+            .map(|p| FunctionArg {
+                mode: parameter_mode(p.mode).into(),
+                name: p.name.clone(),
+                type_id: self.type_id(p.arg_type.as_ref()),
+                has_default: Some(p.defexpr.is_some()),
+            })
+            .collect::<Vec<_>>();
+
+        let format_param = |p: &&protobuf::FunctionParameter| {
+            let type_name = p.arg_type.as_ref().map(format_type).unwrap_or_default();
+            if p.name.is_empty() {
+                type_name
+            } else {
+                format!("{} {}", p.name, type_name)
+            }
+        };
+
+        let identity_argument_types = params
+            .iter()
+            .filter(|p| !matches!(parameter_mode(p.mode), "out" | "table"))
+            .map(format_param)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        // `create or replace` replaces the function with the same identity arguments
+        self.functions.retain(|f| {
+            f.schema.as_deref() != Some(schema.as_str())
+                || f.name.as_deref() != Some(name.as_str())
+                || f.identity_argument_types.as_deref() != Some(identity_argument_types.as_str())
+        });
+
+        let mut language = None;
+        let mut definition = None;
+        let mut behavior = Behavior::Volatile;
+        let mut security_definer = false;
+        for option in &stmt.options {
+            let Some(NodeEnum::DefElem(def)) = &option.node else {
+                continue;
+            };
+            let arg = def.arg.as_ref().and_then(|a| a.node.as_ref());
+            match (def.defname.as_str(), arg) {
+                ("language", Some(NodeEnum::String(s))) => language = Some(s.sval.as_str().into()),
+                ("as", Some(NodeEnum::List(l))) => {
+                    definition = l.items.first().and_then(string_value)
+                }
+                ("volatility", Some(NodeEnum::String(s))) => {
+                    behavior = Behavior::from(Some(s.sval.to_uppercase()))
+                }
+                ("security", Some(NodeEnum::Boolean(b))) => security_definer = b.boolval,
+                _ => {}
+            }
+        }
+
+        let id = self.next_id();
+        let return_type_id = self.type_id(stmt.return_type.as_ref());
+        self.functions.push(Function {
+            id: Some(id),
+            schema: Some(schema.into()),
+            name: Some(name),
+            language,
+            definition,
+            complete_statement: None,
+            args: FunctionArgs { args },
+            argument_types: Some(
+                params
+                    .iter()
+                    .map(format_param)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            identity_argument_types: Some(identity_argument_types),
+            return_type_id: Some(return_type_id),
+            return_type: stmt.return_type.as_ref().map(|t| format_type(t).into()),
+            return_type_relation_id: None,
+            is_set_returning_function: stmt.return_type.as_ref().is_some_and(|t| t.setof),
+            behavior,
+            security_definer,
+        });
+    }
+
+    fn drop(&mut self, stmt: &protobuf::DropStmt, default_schema: &str) {
+        let object_type = protobuf::ObjectType::from_i32(stmt.remove_type)
+            .unwrap_or(protobuf::ObjectType::Undefined);
+
+        for object in &stmt.objects {
+            match (object_type, &object.node) {
+                (protobuf::ObjectType::ObjectSchema, Some(NodeEnum::String(s))) => {
+                    let schema = s.sval.as_str();
+                    self.schemas.retain(|s| s.name != schema);
+                    self.tables.retain(|t| t.schema != schema);
+                    self.types.retain(|t| t.schema != schema);
+                    self.functions
+                        .retain(|f| f.schema.as_deref() != Some(schema));
+                    self.foreign_tables.retain(|t| t.schema != schema);
+                    self.columns.retain(|c| c.schema != schema);
+                    self.indexes.retain(|i| i.schema != schema);
+                    self.foreign_keys
+                        .retain(|k| k.schema != schema && k.referenced_schema != schema);
+                    self.policies.retain(|p| p.schema != schema);
+                }
+                (
+                    protobuf::ObjectType::ObjectTable | protobuf::ObjectType::ObjectForeignTable,
+                    Some(NodeEnum::List(l)),
+                ) => {
+                    let (schema, name) = qualified_name(&l.items, default_schema);
+                    if let Some(id) = self.relation_id(&name, &schema) {
+                        self.tables.retain(|t| t.id != id);
+                        self.foreign_tables.retain(|t| t.id != id);
+                        self.columns.retain(|c| c.table_id != id);
+                        self.indexes.retain(|i| i.table_id != id);
+                        // the foreign keys that reference the table are dropped by `cascade`
+                        self.foreign_keys
+                            .retain(|k| k.table_id != id && k.referenced_table_id != id);
+                        self.policies.retain(|p| p.table_id != id);
+                    }
+                }
+                (protobuf::ObjectType::ObjectPolicy, Some(NodeEnum::List(l))) => {
+                    // the name of the policy follows the name of its table
+                    let Some((policy, table)) = l.items.split_last() else {
+                        continue;
+                    };
+                    let (schema, name) = qualified_name(table, default_schema);
+                    let (Some(policy), Some(id)) =
+                        (string_value(policy), self.relation_id(&name, &schema))
+                    else {
+                        continue;
+                    };
+                    self.policies
+                        .retain(|p| p.table_id != id || p.name != policy);
+                }
+                (protobuf::ObjectType::ObjectIndex, Some(NodeEnum::List(l))) => {
+                    let (schema, name) = qualified_name(&l.items, default_schema);
+                    self.indexes
+                        .retain(|i| i.name != name || i.schema != schema);
+                }
+                (
+                    protobuf::ObjectType::ObjectType | protobuf::ObjectType::ObjectDomain,
+                    Some(NodeEnum::TypeName(t)),
+                ) => {
+                    let (schema, name) = qualified_name(&t.names, default_schema);
+                    self.types.retain(|t| t.name != name || t.schema != schema);
+                }
+                (protobuf::ObjectType::ObjectFunction, Some(NodeEnum::ObjectWithArgs(f))) => {
+                    let (schema, name) = qualified_name(&f.objname, default_schema);
+                    self.functions.retain(|f| {
+                        f.schema.as_deref() != Some(schema.as_str())
+                            || f.name.as_deref() != Some(name.as_str())
+                    });
+                }
+                (protobuf::ObjectType::ObjectForeignServer, Some(NodeEnum::String(s))) => {
+                    self.foreign_servers.retain(|server| server.name != s.sval);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn alter_table(&mut self, stmt: &protobuf::AlterTableStmt, default_schema: &str) {
+        let Some(relation) = &stmt.relation else {
+            return;
+        };
+        let schema = schema_or(&relation.schemaname, default_schema);
+        let Some(table_id) = self.relation_id(&relation.relname, schema) else {
+            return;
+        };
+
+        for cmd in &stmt.cmds {
+            let Some(NodeEnum::AlterTableCmd(cmd)) = &cmd.node else {
+                continue;
+            };
+            let def = cmd.def.as_ref().and_then(|d| d.node.as_ref());
+            let column = self
+                .columns
+                .iter_mut()
+                .find(|c| c.table_id == table_id && c.name == cmd.name);
+
+            match (protobuf::AlterTableType::from_i32(cmd.subtype), def) {
+                (
+                    Some(
+                        subtype @ (protobuf::AlterTableType::AtAttachPartition
+                        | protobuf::AlterTableType::AtDetachPartition),
+                    ),
+                    Some(NodeEnum::PartitionCmd(partition)),
+                ) => {
+                    let Some(name) = &partition.name else {
+                        continue;
+                    };
+                    let partition_schema = schema_or(&name.schemaname, default_schema);
+                    if let Some(table) = self
+                        .tables
+                        .iter_mut()
+                        .find(|t| t.name == name.relname && t.schema == partition_schema)
+                    {
+                        table.partition_of = match subtype {
+                            protobuf::AlterTableType::AtAttachPartition => Some(table_id),
+                            _ => None,
+                        };
+                    }
+                }
+                (Some(protobuf::AlterTableType::AtAddColumn), Some(NodeEnum::ColumnDef(def))) => {
+                    self.add_column(table_id, schema, &relation.relname, def, default_schema);
+                }
+                (Some(protobuf::AlterTableType::AtDropColumn), _) => {
+                    self.columns
+                        .retain(|c| c.table_id != table_id || c.name != cmd.name);
+                    // the indexes of a column are dropped with it
+                    self.indexes
+                        .retain(|i| i.table_id != table_id || !i.columns.contains(&cmd.name));
+                    self.foreign_keys.retain(|k| {
+                        (k.table_id != table_id || !k.columns.contains(&cmd.name))
+                            && (k.referenced_table_id != table_id
+                                || !k.referenced_columns.contains(&cmd.name))
+                    });
+                }
+                (Some(protobuf::AlterTableType::AtDropConstraint), _) => {
+                    self.indexes
+                        .retain(|i| i.table_id != table_id || i.name != cmd.name);
+                    self.foreign_keys
+                        .retain(|k| k.table_id != table_id || k.name != cmd.name);
+                }
+                (
+                    Some(
+                        subtype @ (protobuf::AlterTableType::AtEnableRowSecurity
+                        | protobuf::AlterTableType::AtDisableRowSecurity
+                        | protobuf::AlterTableType::AtForceRowSecurity
+                        | protobuf::AlterTableType::AtNoForceRowSecurity),
+                    ),
+                    _,
+                ) => {
+                    if let Some(table) = self.tables.iter_mut().find(|t| t.id == table_id) {
+                        match subtype {
+                            protobuf::AlterTableType::AtEnableRowSecurity => {
+                                table.rls_enabled = true
+                            }
+                            protobuf::AlterTableType::AtDisableRowSecurity => {
+                                table.rls_enabled = false
+                            }
+                            protobuf::AlterTableType::AtForceRowSecurity => table.rls_forced = true,
+                            _ => table.rls_forced = false,
+                        }
+                    }
+                }
+                (Some(protobuf::AlterTableType::AtSetNotNull), _) => {
+                    if let Some(column) = column {
+                        column.is_nullable = false;
+                    }
+                }
+                (Some(protobuf::AlterTableType::AtDropNotNull), _) => {
+                    if let Some(column) = column {
+                        column.is_nullable = true;
+                    }
+                }
+                (Some(protobuf::AlterTableType::AtAddIdentity), _) => {
+                    if let Some(column) = column {
+                        column.is_identity = true;
+                    }
+                }
+                (Some(protobuf::AlterTableType::AtDropIdentity), _) => {
+                    if let Some(column) = column {
+                        column.is_identity = false;
+                    }
+                }
+                (Some(protobuf::AlterTableType::AtColumnDefault), def) => {
+                    if let Some(column) = column {
+                        column.default_value = def.and_then(|d| {
+                            deparse_expr(&protobuf::Node {
+                                node: Some(d.clone()),
+                            })
+                        });
+                    }
+                }
+                (
+                    Some(protobuf::AlterTableType::AtAlterColumnType),
+                    Some(NodeEnum::ColumnDef(def)),
+                ) => {
+                    let type_id = self.type_id(def.type_name.as_ref());
+                    if let Some(column) = self
+                        .columns
+                        .iter_mut()
+                        .find(|c| c.table_id == table_id && c.name == cmd.name)
+                    {
+                        column.type_id = type_id;
+                        column.type_name = def
+                            .type_name
+                            .as_ref()
+                            .map(format_type)
+                            .unwrap_or_default()
+                            .into();
+                    }
+                }
+                (
+                    Some(protobuf::AlterTableType::AtAddConstraint),
+                    Some(NodeEnum::Constraint(c)),
+                ) => self.add_constraint(table_id, c, default_schema),
+                _ => {}
+            }
+        }
+    }
+
+    fn rename(&mut self, stmt: &protobuf::RenameStmt, default_schema: &str) {
+        match protobuf::ObjectType::from_i32(stmt.rename_type) {
+            Some(protobuf::ObjectType::ObjectTable) => {
+                let Some(relation) = &stmt.relation else {
+                    return;
+                };
+                let schema = schema_or(&relation.schemaname, default_schema);
+                if let Some(table) = self
+                    .tables
+                    .iter_mut()
+                    .find(|t| t.name == relation.relname && t.schema == schema)
+                {
+                    table.name = stmt.newname.clone();
+                    let id = table.id;
+                    self.columns
+                        .iter_mut()
+                        .filter(|c| c.table_id == id)
+                        .for_each(|c| c.table = stmt.newname.clone());
+                    self.indexes
+                        .iter_mut()
+                        .filter(|i| i.table_id == id)
+                        .for_each(|i| i.table = stmt.newname.clone());
+                    self.policies
+                        .iter_mut()
+                        .filter(|p| p.table_id == id)
+                        .for_each(|p| p.table = stmt.newname.clone());
+                    for foreign_key in self.foreign_keys.iter_mut() {
+                        if foreign_key.table_id == id {
+                            foreign_key.table = stmt.newname.clone();
+                        }
+                        if foreign_key.referenced_table_id == id {
+                            foreign_key.referenced_table = stmt.newname.clone();
+                        }
+                    }
+                }
+            }
+            Some(protobuf::ObjectType::ObjectColumn) => {
+                let Some(relation) = &stmt.relation else {
+                    return;
+                };
+                let schema = schema_or(&relation.schemaname, default_schema);
+                let Some(table_id) = self.relation_id(&relation.relname, schema) else {
+                    return;
+                };
+                if let Some(column) = self
+                    .columns
+                    .iter_mut()
+                    .find(|c| c.table_id == table_id && c.name == stmt.subname)
+                {
+                    column.name = stmt.newname.clone();
+                }
+                for column in self
+                    .indexes
+                    .iter_mut()
+                    .filter(|i| i.table_id == table_id)
+                    .flat_map(|i| i.columns.iter_mut())
+                    .filter(|c| **c == stmt.subname)
+                {
+                    *column = stmt.newname.clone();
+                }
+                for column in self
+                    .foreign_keys
+                    .iter_mut()
+                    .flat_map(|k| {
+                        let columns = (k.table_id == table_id).then_some(k.columns.iter_mut());
+                        let referenced = (k.referenced_table_id == table_id)
+                            .then_some(k.referenced_columns.iter_mut());
+                        columns
+                            .into_iter()
+                            .flatten()
+                            .chain(referenced.into_iter().flatten())
+                    })
+                    .filter(|c| **c == stmt.subname)
+                {
+                    *column = stmt.newname.clone();
+                }
+            }
+            Some(protobuf::ObjectType::ObjectPolicy) => {
+                let Some(relation) = &stmt.relation else {
+                    return;
+                };
+                let schema = schema_or(&relation.schemaname, default_schema);
+                let Some(table_id) = self.relation_id(&relation.relname, schema) else {
+                    return;
+                };
+                if let Some(policy) = self
+                    .policies
+                    .iter_mut()
+                    .find(|p| p.table_id == table_id && p.name == stmt.subname)
+                {
+                    policy.name = stmt.newname.clone();
+                }
+            }
+            Some(protobuf::ObjectType::ObjectSchema) => {
+                let (old, new) = (stmt.subname.as_str(), stmt.newname.as_str());
+                self.schemas
+                    .iter_mut()
+                    .filter(|s| s.name == old)
+                    .for_each(|s| s.name = new.to_string());
+                self.tables
+                    .iter_mut()
+                    .filter(|t| t.schema == old)
+                    .for_each(|t| t.schema = new.into());
+                self.types
+                    .iter_mut()
+                    .filter(|t| t.schema == old)
+                    .for_each(|t| Arc::make_mut(t).schema = new.into());
+                self.functions
+                    .iter_mut()
+                    .filter(|f| f.schema.as_deref() == Some(old))
+                    .for_each(|f| f.schema = Some(new.into()));
+                self.foreign_tables
+                    .iter_mut()
+                    .filter(|t| t.schema == old)
+                    .for_each(|t| t.schema = new.into());
+                self.columns
+                    .iter_mut()
+                    .filter(|c| c.schema == old)
+                    .for_each(|c| c.schema = new.into());
+                self.indexes
+                    .iter_mut()
+                    .filter(|i| i.schema == old)
+                    .for_each(|i| i.schema = new.into());
+                self.policies
+                    .iter_mut()
+                    .filter(|p| p.schema == old)
+                    .for_each(|p| p.schema = new.into());
+                for foreign_key in self.foreign_keys.iter_mut() {
+                    if foreign_key.schema == old {
+                        foreign_key.schema = new.into();
+                    }
+                    if foreign_key.referenced_schema == old {
+                        foreign_key.referenced_schema = new.into();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn comment(&mut self, stmt: &protobuf::CommentStmt, default_schema: &str) {
+        let comment = non_empty(&stmt.comment);
+        let object = stmt.object.as_ref().and_then(|o| o.node.as_ref());
+
+        match (protobuf::ObjectType::from_i32(stmt.objtype), object) {
+            (Some(protobuf::ObjectType::ObjectTable), Some(NodeEnum::List(l))) => {
+                let (schema, name) = qualified_name(&l.items, default_schema);
+                if let Some(table) = self
+                    .tables
+                    .iter_mut()
+                    .find(|t| t.name == name && t.schema == schema)
+                {
+                    table.comment = comment;
+                }
+            }
+            (Some(protobuf::ObjectType::ObjectForeignTable), Some(NodeEnum::List(l))) => {
+                let (schema, name) = qualified_name(&l.items, default_schema);
+                if let Some(table) = self
+                    .foreign_tables
+                    .iter_mut()
+                    .find(|t| t.name == name && t.schema == schema)
+                {
+                    table.comment = comment;
+                }
+            }
+            (Some(protobuf::ObjectType::ObjectColumn), Some(NodeEnum::List(l))) => {
+                let Some((column, relation)) = l.items.split_last() else {
+                    return;
+                };
+                let (schema, name) = qualified_name(relation, default_schema);
+                let (Some(table_id), Some(column)) =
+                    (self.relation_id(&name, &schema), string_value(column))
+                else {
+                    return;
+                };
+                if let Some(column) = self
+                    .columns
+                    .iter_mut()
+                    .find(|c| c.table_id == table_id && c.name == column)
+                {
+                    column.comment = comment;
+                }
+            }
+            (
+                Some(protobuf::ObjectType::ObjectType | protobuf::ObjectType::ObjectDomain),
+                Some(NodeEnum::TypeName(t)),
+            ) => {
+                let (schema, name) = qualified_name(&t.names, default_schema);
+                if let Some(t) = self
+                    .types
+                    .iter_mut()
+                    .find(|t| t.name == name && t.schema == schema)
+                {
+                    Arc::make_mut(t).comment = comment;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the first id above those of the objects of the cache and the built-in objects
+fn first_free_id(cache: &SchemaCache) -> i64 {
+    cache
+        .schemas
+        .iter()
+        .map(|s| s.id)
+        .chain(cache.tables.iter().map(|t| t.id))
+        .chain(cache.types.iter().map(|t| t.id))
+        .chain(cache.functions.iter().filter_map(|f| f.id))
+        .chain(cache.foreign_tables.iter().map(|t| t.id))
+        .chain(cache.foreign_servers.iter().map(|s| s.id))
+        .chain(cache.indexes.iter().map(|i| i.id))
+        .chain(cache.foreign_keys.iter().map(|k| k.id))
+        .chain(cache.policies.iter().map(|p| p.id))
+        .max()
+        .map_or(FIRST_OFFLINE_ID, |id| (id + 1).max(FIRST_OFFLINE_ID))
+}
+
+fn collect_sql_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            collect_sql_files(&entry?.path(), files)?;
+        }
+    } else if path.extension().is_some_and(|ext| ext == "sql") {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+fn string_value(node: &protobuf::Node) -> Option<String> {
+    match &node.node {
+        Some(NodeEnum::String(s)) => Some(s.sval.clone()),
+        _ => None,
+    }
+}
+
+/// Formats generic options, e.g. of foreign tables, as `key=value` pairs like the catalog does
+fn options(options: &[protobuf::Node]) -> Vec<String> {
+    options
+        .iter()
+        .filter_map(|n| match &n.node {
+            Some(NodeEnum::DefElem(def)) => Some(format!(
+                "{}={}",
+                def.defname,
+                def.arg
+                    .as_ref()
+                    .and_then(|a| string_value(a))
+                    .unwrap_or_default()
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s.to_string())
+    }
+}
+
+fn schema_or<'a>(schema: &'a str, default_schema: &'a str) -> &'a str {
+    if schema.is_empty() {
+        default_schema
+    } else {
+        schema
+    }
+}
+
+/// Splits a possibly qualified name, e.g. `public.users`, into schema and name
+fn qualified_name(names: &[protobuf::Node], default_schema: &str) -> (String, String) {
+    let names = names.iter().filter_map(string_value).collect::<Vec<_>>();
+    match names.as_slice() {
+        [.., schema, name] => (schema.clone(), name.clone()),
+        [name] => (default_schema.to_string(), name.clone()),
+        [] => (default_schema.to_string(), String::new()),
+    }
+}
+
+fn format_type(type_name: &protobuf::TypeName) -> String {
+    let names = type_name
+        .names
+        .iter()
+        .filter_map(string_value)
+        .filter(|n| n != "pg_catalog")
+        .collect::<Vec<_>>();
+    let mut formatted = names.join(".");
+    let typmods = type_name
+        .typmods
+        .iter()
+        .filter_map(|n| match &n.node {
+            Some(NodeEnum::AConst(protobuf::AConst {
+                val: Some(protobuf::a_const::Val::Ival(i)),
+                ..
+            })) => Some(i.ival.to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if !typmods.is_empty() {
+        formatted = format!("{}({})", formatted, typmods.join(", "));
+    }
+    if type_name.setof {
+        formatted = format!("SETOF {}", formatted);
+    }
+    for _ in &type_name.array_bounds {
+        formatted.push_str("[]");
+    }
+    formatted
+}
+
+/// Applies the constraints of a column definition, e.g. `not null` or `primary key`
+fn apply_column_constraints(column: &mut Column, constraints: &[protobuf::Node]) {
+    for constraint in constraints {
+        let Some(NodeEnum::Constraint(c)) = &constraint.node else {
+            continue;
+        };
+        match protobuf::ConstrType::from_i32(c.contype) {
+            Some(protobuf::ConstrType::ConstrNotnull) => column.is_nullable = false,
+            Some(protobuf::ConstrType::ConstrIdentity) => {
+                column.is_nullable = false;
+                column.is_identity = true;
+            }
+            Some(protobuf::ConstrType::ConstrNull) => column.is_nullable = true,
+            Some(protobuf::ConstrType::ConstrPrimary) => {
+                column.is_primary_key = true;
+                column.is_nullable = false;
+            }
+            Some(protobuf::ConstrType::ConstrDefault) => {
+                column.default_value = c.raw_expr.as_deref().and_then(deparse_expr)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the integer type and its id that a `serial` pseudo-type stands for
+fn serial_type(type_name: &str) -> Option<(&'static str, i64)> {
+    match type_name {
+        "smallserial" | "serial2" => Some(("int2", 21)),
+        "serial" | "serial4" => Some(("int4", 23)),
+        "bigserial" | "serial8" => Some(("int8", 20)),
+        _ => None,
+    }
+}
+
+/// Formats an expression, e.g. a column default, as sql
+fn deparse_expr(expr: &protobuf::Node) -> Option<String> {
+    let select = NodeEnum::SelectStmt(Box::new(protobuf::SelectStmt {
+        target_list: vec![protobuf::Node {
+            node: Some(NodeEnum::ResTarget(Box::new(protobuf::ResTarget {
+                val: Some(Box::new(expr.clone())),
+                ..Default::default()
+            }))),
+        }],
+        op: protobuf::SetOperation::SetopNone as i32,
+        limit_option: protobuf::LimitOption::Default as i32,
+        ..Default::default()
+    }));
+    select
+        .deparse()
+        .ok()?
+        .strip_prefix("SELECT ")
+        .map(str::to_string)
+}
+
+/// Maps a parameter mode to the names used by the functions query
+fn parameter_mode(mode: i32) -> &'static str {
+    match protobuf::FunctionParameterMode::from_i32(mode) {
+        Some(protobuf::FunctionParameterMode::FuncParamOut) => "out",
+        Some(protobuf::FunctionParameterMode::FuncParamInout) => "inout",
+        Some(protobuf::FunctionParameterMode::FuncParamVariadic) => "variadic",
+        Some(protobuf::FunctionParameterMode::FuncParamTable) => "table",
+        _ => "in",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::SchemaCache;
+
+    #[test]
+    fn test_ids() {
+        let mut cache = SchemaCache::from_sql(
+            "create table users (id int primary key);\ncreate index on users (id);",
+        );
+        // the ids of later statements continue after those of the objects that exist
+        cache.apply_sql("create table teams (id int primary key, owner int references users);");
+        cache.mutate(&pg_query_ext::parse("create schema billing").unwrap());
+
+        let ids: Vec<i64> = cache
+            .schemas
+            .iter()
+            .map(|s| s.id)
+            .chain(cache.tables.iter().map(|t| t.id))
+            .chain(cache.indexes.iter().map(|i| i.id))
+            .chain(cache.foreign_keys.iter().map(|k| k.id))
+            .collect();
+        assert_eq!(ids.len(), 7);
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+        assert!(ids.iter().all(|id| *id >= super::FIRST_OFFLINE_ID));
+    }
+
+    #[test]
+    fn test_from_sql() {
+        let cache = SchemaCache::from_sql(
+            r#"
+create schema analytics;
+
+create table public.users (id serial primary key, email text);
+alter table users add column name varchar(100) not null default 'anonymous';
+alter table users rename column email to address;
+comment on column users.address is 'Where to send mail';
+create table analytics.events (id bigint, created_at timestamptz) partition by range (created_at);
+create table analytics.events_2024 partition of analytics.events
+    for values from ('2024-01-01') to ('2025-01-01');
+create table obsolete (id int);
+
+create type mood as enum ('happy', 'sad');
+
+create function add(a int4, b int4 default 1) returns int4 language sql immutable as 'select a + b';
+
+comment on table users is 'All users';
+alter table obsolete rename to archived;
+drop table archived;
+"#,
+        );
+
+        assert!(cache.schemas.iter().any(|s| s.name == "analytics"));
+
+        let users = cache.find_table("users", None).unwrap();
+        assert_eq!(users.schema, "public");
+        assert_eq!(users.comment.as_deref(), Some("All users"));
+
+        let columns = cache.find_columns(users.id);
+        assert_eq!(columns.len(), 3);
+        assert_eq!(columns[0].name, "id");
+        assert_eq!(columns[0].type_name, "int4");
+        assert!(columns[0].is_primary_key && !columns[0].is_nullable);
+        assert_eq!(
+            columns[0].default_value.as_deref(),
+            Some("nextval('users_id_seq'::regclass)")
+        );
+        assert_eq!(columns[1].name, "address");
+        assert!(columns[1].is_nullable);
+        assert_eq!(columns[1].comment.as_deref(), Some("Where to send mail"));
+        assert_eq!(columns[2].type_name, "varchar(100)");
+        assert_eq!(columns[2].default_value.as_deref(), Some("'anonymous'"));
+
+        let events = cache.find_table("events", Some("analytics")).unwrap();
+        assert_eq!(events.partition_key.as_deref(), Some("RANGE (created_at)"));
+        let partitions = cache.find_partitions(events);
+        assert_eq!(partitions.len(), 1);
+        assert_eq!(partitions[0].name, "events_2024");
+        assert_eq!(cache.find_columns(partitions[0].id).len(), 2);
+
+        assert!(!cache
+            .tables
+            .iter()
+            .any(|t| t.name == "archived" || t.name == "obsolete"));
+
+        let mood = cache.find_type("mood", None).unwrap();
+        assert_eq!(mood.enums.values, vec!["happy", "sad"]);
+
+        let add = &cache.functions[0];
+        assert_eq!(add.name.as_deref(), Some("add"));
+        assert_eq!(add.args.args.len(), 2);
+        assert_eq!(add.args.args[0].type_id, 23);
+        assert_eq!(add.args.args[1].has_default, Some(true));
+        assert_eq!(add.language.as_deref(), Some("sql"));
+        assert_eq!(add.return_type.as_deref(), Some("int4"));
+    }
+
+    #[test]
+    fn test_indexes() {
+        let cache = SchemaCache::from_sql(
+            r#"
+create table users (id int primary key, email text unique, name text, team_id int);
+create table members (team_id int, user_id int, constraint members_key unique (team_id, user_id));
+create index on users (lower(name));
+create index if not exists users_team_idx on users (team_id) where team_id is not null;
+create index users_team_idx on users (name);
+alter table users rename column team_id to group_id;
+create index obsolete_idx on users (name);
+drop index obsolete_idx;
+"#,
+        );
+
+        let users = cache.find_table("users", None).unwrap();
+        let indexes = cache
+            .find_indexes(users.id)
+            .into_iter()
+            .map(|i| {
+                (
+                    i.name.as_str(),
+                    i.columns.clone(),
+                    i.is_unique,
+                    i.is_partial,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            indexes,
+            vec![
+                ("users_pkey", vec!["id".to_string()], true, false),
+                ("users_email_key", vec!["email".to_string()], true, false),
+                (
+                    "users_lower_idx",
+                    vec!["lower(name)".to_string()],
+                    false,
+                    false
+                ),
+                ("users_team_idx", vec!["group_id".to_string()], false, true),
+            ]
+        );
+        assert!(cache.find_indexes(users.id)[0].is_primary);
+        assert_eq!(
+            cache.find_indexes(users.id)[1].definition,
+            "CREATE UNIQUE INDEX users_email_key ON public.users USING btree (email)"
+        );
+
+        let members = cache.find_table("members", None).unwrap();
+        let members_key = cache.find_indexes(members.id)[0];
+        assert_eq!(members_key.name, "members_key");
+        assert_eq!(members_key.columns, vec!["team_id", "user_id"]);
+    }
+
+    #[test]
+    fn test_foreign_keys() {
+        let cache = SchemaCache::from_sql(
+            r#"
+create table teams (id int primary key);
+create table users (id int primary key, team_id int references teams);
+create table members (
+    team_id int,
+    user_id int,
+    constraint members_user_fk foreign key (user_id) references users (id)
+);
+alter table members add foreign key (team_id) references teams (id);
+alter table users rename column team_id to group_id;
+alter table teams rename to groups;
+create table obsolete (user_id int references users);
+drop table obsolete;
+alter table members drop constraint members_user_fk;
+"#,
+        );
+
+        let keys = cache
+            .foreign_keys
+            .iter()
+            .map(|k| {
+                (
+                    k.name.as_str(),
+                    k.table.as_str(),
+                    k.columns.clone(),
+                    k.referenced_table.as_str(),
+                    k.referenced_columns.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                (
+                    "users_team_id_fkey",
+                    "users",
+                    vec!["group_id".to_string()],
+                    "groups",
+                    vec!["id".to_string()]
+                ),
+                (
+                    "members_team_id_fkey",
+                    "members",
+                    vec!["team_id".to_string()],
+                    "groups",
+                    vec!["id".to_string()]
+                ),
+            ]
+        );
+        let groups = cache.find_table("groups", None).unwrap();
+        assert_eq!(cache.foreign_keys[0].referenced_table_id, groups.id);
+    }
+
+    #[test]
+    fn test_policies() {
+        let cache = SchemaCache::from_sql(
+            r#"
+create table posts (id int primary key, author text, published bool);
+alter table posts enable row level security;
+alter table posts force row level security;
+create policy "read published" on posts for select to anon, authenticated using (published);
+create policy own_posts on public.posts as restrictive with check (author = current_user);
+create policy obsolete on posts;
+drop policy obsolete on posts;
+alter policy own_posts on posts rename to author_posts;
+create table drafts (id int);
+create policy own_drafts on drafts;
+alter table drafts enable row level security;
+alter table drafts disable row level security;
+drop table drafts;
+"#,
+        );
+
+        let posts = cache.find_table("posts", None).unwrap();
+        assert!(posts.rls_enabled && posts.rls_forced);
+        let policies = cache
+            .find_policies(posts.id)
+            .into_iter()
+            .map(|p| {
+                (
+                    p.name.as_str(),
+                    p.command.as_str(),
+                    p.is_permissive,
+                    p.roles.clone(),
+                    p.using_expr.as_deref(),
+                    p.check_expr.as_deref(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            policies,
+            vec![
+                (
+                    "read published",
+                    "SELECT",
+                    true,
+                    vec!["anon".to_string(), "authenticated".to_string()],
+                    Some("published"),
+                    None
+                ),
+                (
+                    "author_posts",
+                    "ALL",
+                    false,
+                    vec!["public".to_string()],
+                    None,
+                    Some("author = current_user")
+                ),
+            ]
+        );
+        assert_eq!(cache.policies.len(), 2);
+    }
+}
@@ -0,0 +1,409 @@
+//! Compares two schema caches, e.g. the one built from the sql files of a project with the one
+//! of the database it is deployed to
+//!
+//! Objects are matched by their qualified name, and functions by their argument types as well.
+//! Type names are compared in a canonical form, because the database and the sql files spell
+//! the same type differently, e.g. `character varying(100)` and `varchar(100)`.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{export::is_system_schema, Function, SchemaCache};
+
+/// The kind of a schema object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectKind {
+    Schema,
+    Type,
+    Table,
+    ForeignTable,
+    Column,
+    Function,
+}
+
+impl ObjectKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectKind::Schema => "schema",
+            ObjectKind::Type => "type",
+            ObjectKind::Table => "table",
+            ObjectKind::ForeignTable => "foreign table",
+            ObjectKind::Column => "column",
+            ObjectKind::Function => "function",
+        }
+    }
+}
+
+/// A difference between the expected and the actual schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum SchemaChange {
+    /// The object is expected but does not exist
+    Missing { kind: ObjectKind, name: String },
+    /// The object exists but is not expected
+    Extra { kind: ObjectKind, name: String },
+    /// A property of the object differs, e.g. the `type` of a column
+    Changed {
+        kind: ObjectKind,
+        name: String,
+        property: &'static str,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// The compared properties of an object by name
+type Properties = BTreeMap<&'static str, String>;
+
+/// The objects of a schema cache with their parent, which is not reported if it is missing or
+/// extra itself
+type Objects = BTreeMap<(ObjectKind, String), (Option<(ObjectKind, String)>, Properties)>;
+
+impl SchemaCache {
+    /// Returns how the `actual` schema differs from this one, ordered by name
+    ///
+    /// Only objects outside of the schemas of Postgres itself are compared. Columns of a missing
+    /// or extra table are not reported on their own.
+    pub fn diff(&self, actual: &SchemaCache) -> Vec<SchemaChange> {
+        let expected = self.diff_objects();
+        let actual = actual.diff_objects();
+
+        let exists_in_both =
+            |key: &(ObjectKind, String)| expected.contains_key(key) && actual.contains_key(key);
+        let reported =
+            |parent: &Option<(ObjectKind, String)>| parent.as_ref().is_none_or(&exists_in_both);
+
+        let mut changes = Vec::new();
+        for ((kind, name), (parent, properties)) in &expected {
+            match actual.get(&(*kind, name.clone())) {
+                None if reported(parent) => changes.push(SchemaChange::Missing {
+                    kind: *kind,
+                    name: name.clone(),
+                }),
+                None => {}
+                Some((_, actual_properties)) => {
+                    for (property, value) in properties {
+                        let actual_value = actual_properties.get(property);
+                        if actual_value != Some(value) {
+                            changes.push(SchemaChange::Changed {
+                                kind: *kind,
+                                name: name.clone(),
+                                property,
+                                expected: value.clone(),
+                                actual: actual_value.cloned().unwrap_or_default(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        for ((kind, name), (parent, _)) in &actual {
+            if !expected.contains_key(&(*kind, name.clone())) && reported(parent) {
+                changes.push(SchemaChange::Extra {
+                    kind: *kind,
+                    name: name.clone(),
+                });
+            }
+        }
+
+        changes.sort_by(|a, b| a.key().cmp(&b.key()));
+        changes
+    }
+
+    fn diff_objects(&self) -> Objects {
+        let mut objects = Objects::new();
+
+        for schema in self.user_schemas() {
+            objects.insert(
+                (ObjectKind::Schema, schema.to_string()),
+                (None, Properties::new()),
+            );
+        }
+
+        for t in self.types.iter().filter(|t| !is_system_schema(&t.schema)) {
+            let mut properties = Properties::new();
+            if !t.enums.values.is_empty() {
+                properties.insert("values", t.enums.values.join(", "));
+            } else if !t.attributes.attrs.is_empty() {
+                let attributes = t.attributes.attrs.iter().map(|a| a.name.as_str());
+                properties.insert("attributes", attributes.collect::<Vec<_>>().join(", "));
+            } else {
+                // array and base types cannot be defined in sql files
+                continue;
+            }
+            objects.insert(
+                (ObjectKind::Type, format!("{}.{}", t.schema, t.name)),
+                (None, properties),
+            );
+        }
+
+        let tables = self
+            .tables
+            .iter()
+            .map(|t| (ObjectKind::Table, t.id, &t.schema, &t.name, None))
+            .chain(self.foreign_tables.iter().map(|t| {
+                let server = Some(t.server_name.to_string());
+                (ObjectKind::ForeignTable, t.id, &t.schema, &t.name, server)
+            }))
+            .filter(|(_, _, schema, _, _)| !is_system_schema(schema));
+        for (kind, id, schema, name, server) in tables {
+            let table = (kind, format!("{}.{}", schema, name));
+            let mut properties = Properties::new();
+            if let Some(server) = server {
+                properties.insert("server", server);
+            }
+            objects.insert(table.clone(), (None, properties));
+
+            for column in self.find_columns(id) {
+                let mut properties = Properties::new();
+                properties.insert("type", canonical_type(&column.type_name));
+                properties.insert("nullable", column.is_nullable.to_string());
+                objects.insert(
+                    (ObjectKind::Column, format!("{}.{}", table.1, column.name)),
+                    (Some(table.clone()), properties),
+                );
+            }
+        }
+
+        for f in &self.functions {
+            let (Some(schema), Some(name)) = (&f.schema, &f.name) else {
+                continue;
+            };
+            if is_system_schema(schema) {
+                continue;
+            }
+            let mut properties = Properties::new();
+            if let Some(return_type) = &f.return_type {
+                properties.insert("returns", canonical_type(return_type));
+            }
+            objects.insert(
+                (
+                    ObjectKind::Function,
+                    format!("{}.{}({})", schema, name, identity_types(f).join(", ")),
+                ),
+                (None, properties),
+            );
+        }
+
+        objects
+    }
+}
+
+impl SchemaChange {
+    pub fn kind(&self) -> ObjectKind {
+        match self {
+            SchemaChange::Missing { kind, .. }
+            | SchemaChange::Extra { kind, .. }
+            | SchemaChange::Changed { kind, .. } => *kind,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            SchemaChange::Missing { name, .. }
+            | SchemaChange::Extra { name, .. }
+            | SchemaChange::Changed { name, .. } => name,
+        }
+    }
+
+    fn key(&self) -> (&str, ObjectKind) {
+        (self.name(), self.kind())
+    }
+}
+
+/// Returns the canonical types of the identity arguments of a function, without their modes and
+/// names
+fn identity_types(f: &Function) -> Vec<String> {
+    let Some(identity_argument_types) = f.identity_argument_types.as_deref() else {
+        return Vec::new();
+    };
+    let names = f
+        .args
+        .args
+        .iter()
+        .filter(|a| matches!(a.mode.as_str(), "in" | "inout" | "variadic"))
+        .map(|a| a.name.as_str())
+        .collect::<Vec<_>>();
+    // the database lists `out` arguments as well, although they do not identify the function
+    let arguments = split_arguments(identity_argument_types)
+        .into_iter()
+        .map(|argument| {
+            let argument = argument.trim();
+            for mode in ["inout ", "in ", "variadic ", "out "] {
+                if argument.len() > mode.len() && argument[..mode.len()].eq_ignore_ascii_case(mode)
+                {
+                    return (mode.trim_end(), &argument[mode.len()..]);
+                }
+            }
+            ("in", argument)
+        })
+        .filter(|(mode, _)| *mode != "out")
+        .map(|(_, argument)| argument)
+        .collect::<Vec<_>>();
+    let names_match = names.len() == arguments.len();
+    arguments
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut argument)| {
+            if names_match && !names[i].is_empty() {
+                argument = argument
+                    .strip_prefix(names[i])
+                    .or_else(|| argument.strip_prefix(&format!("\"{}\"", names[i])))
+                    .unwrap_or(argument);
+            }
+            canonical_type(argument)
+        })
+        .collect()
+}
+
+/// Splits arguments at the commas that are not within parentheses, e.g. of `numeric(10, 2)`
+fn split_arguments(arguments: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in arguments.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&arguments[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !arguments[start..].trim().is_empty() {
+        parts.push(&arguments[start..]);
+    }
+    parts
+}
+
+/// Returns the internal name of a type with its modifiers, e.g. `varchar(100)` for
+/// `character varying(100)`
+pub fn canonical_type(name: &str) -> String {
+    let mut name = name.trim().to_lowercase().replace('"', "");
+    let setof = match name.strip_prefix("setof ") {
+        Some(rest) => {
+            name = rest.to_string();
+            "setof "
+        }
+        None => "",
+    };
+    let mut arrays = 0;
+    while let Some(rest) = name.trim_end().strip_suffix("[]") {
+        name = rest.to_string();
+        arrays += 1;
+    }
+
+    // the modifiers may be in the middle, e.g. `timestamp(3) with time zone`
+    let (base, modifiers) = match (name.find('('), name.find(')')) {
+        (Some(start), Some(end)) if start < end => (
+            format!("{} {}", &name[..start], &name[end + 1..]),
+            name[start..=end].replace(' ', ""),
+        ),
+        _ => (name.clone(), String::new()),
+    };
+    let base = base.split_whitespace().collect::<Vec<_>>().join(" ");
+    let base = base
+        .strip_prefix("pg_catalog.")
+        .or_else(|| base.strip_prefix("public."))
+        .unwrap_or(&base);
+    let base = match base {
+        "int" | "integer" => "int4",
+        "smallint" => "int2",
+        "bigint" => "int8",
+        "real" => "float4",
+        "float" | "double precision" => "float8",
+        "boolean" => "bool",
+        "character varying" => "varchar",
+        "character" | "char" => "bpchar",
+        "decimal" => "numeric",
+        "timestamp with time zone" => "timestamptz",
+        "timestamp without time zone" => "timestamp",
+        "time with time zone" => "timetz",
+        "time without time zone" => "time",
+        "bit varying" => "varbit",
+        base => base,
+    };
+    format!("{}{}{}{}", setof, base, modifiers, "[]".repeat(arrays))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SchemaCache;
+
+    use super::{canonical_type, ObjectKind, SchemaChange};
+
+    #[test]
+    fn test_diff() {
+        let expected = SchemaCache::from_sql(
+            r#"
+create table users (id serial primary key, email varchar(100) not null);
+create table orders (id bigint);
+create type mood as enum ('happy', 'sad');
+create function add(a integer, b integer) returns integer language sql as 'select a + b';
+create function stats(in a int4, out total int8) language sql as 'select 1::int8';
+"#,
+        );
+        let actual = SchemaCache::from_sql(
+            r#"
+create table public.users (id int4 not null, email text not null, nickname text);
+create type mood as enum ('happy', 'sad', 'neutral');
+create function add(x int4, y int4) returns int4 language sql as 'select x + y';
+create function stats(a integer, out total bigint) language sql as 'select 1::int8';
+create table audit.events (id bigint);
+"#,
+        );
+
+        assert_eq!(
+            expected.diff(&actual),
+            vec![
+                SchemaChange::Extra {
+                    kind: ObjectKind::Schema,
+                    name: "audit".to_string()
+                },
+                SchemaChange::Extra {
+                    kind: ObjectKind::Table,
+                    name: "audit.events".to_string()
+                },
+                SchemaChange::Changed {
+                    kind: ObjectKind::Type,
+                    name: "public.mood".to_string(),
+                    property: "values",
+                    expected: "happy, sad".to_string(),
+                    actual: "happy, sad, neutral".to_string()
+                },
+                SchemaChange::Missing {
+                    kind: ObjectKind::Table,
+                    name: "public.orders".to_string()
+                },
+                SchemaChange::Changed {
+                    kind: ObjectKind::Column,
+                    name: "public.users.email".to_string(),
+                    property: "type",
+                    expected: "varchar(100)".to_string(),
+                    actual: "text".to_string()
+                },
+                SchemaChange::Extra {
+                    kind: ObjectKind::Column,
+                    name: "public.users.nickname".to_string()
+                },
+            ]
+        );
+        assert!(actual.diff(&actual).is_empty());
+    }
+
+    #[test]
+    fn test_canonical_type() {
+        assert_eq!(canonical_type("character varying(100)"), "varchar(100)");
+        assert_eq!(
+            canonical_type("timestamp(3) with time zone"),
+            "timestamptz(3)"
+        );
+        assert_eq!(canonical_type("pg_catalog.int4[]"), "int4[]");
+        assert_eq!(canonical_type("numeric(10, 2)"), "numeric(10,2)");
+        assert_eq!(canonical_type("SETOF integer"), "setof int4");
+    }
+}
@@ -0,0 +1,307 @@
+//! Builds a schema cache from the output of `pg_dump --schema-only`
+//!
+//! Both the plain format, which is a sql script, and the custom format (`-Fc`) are supported. The
+//! custom format is an archive whose table of contents holds the definition of every dumped
+//! object as sql, so the objects are applied to the cache the same way as for a plain dump.
+
+use std::{
+    fs,
+    io::{self, Error, ErrorKind},
+    path::Path,
+};
+
+use crate::SchemaCache;
+
+const MAGIC: &[u8] = b"PGDMP";
+
+/// The format byte of custom format archives
+const FORMAT_CUSTOM: u8 = 1;
+
+const fn archive_version(major: u8, minor: u8) -> u32 {
+    (major as u32) << 16 | (minor as u32) << 8
+}
+
+impl SchemaCache {
+    /// Builds a schema cache from a plain or custom format schema dump
+    pub fn from_dump(path: &Path) -> io::Result<SchemaCache> {
+        let bytes = fs::read(path)?;
+
+        let mut cache = SchemaCache::new();
+
+        if bytes.starts_with(MAGIC) {
+            for definition in read_archive_definitions(&bytes)? {
+                cache.apply_sql(&definition);
+            }
+        } else {
+            let sql =
+                String::from_utf8(bytes).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+            cache.apply_sql(&strip_meta_commands(&sql));
+        }
+
+        Ok(cache)
+    }
+}
+
+/// Removes psql meta-commands, e.g. `\connect`, which are not valid sql
+fn strip_meta_commands(sql: &str) -> String {
+    sql.lines()
+        .filter(|line| !line.starts_with('\\'))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reads the object definitions from the table of contents of a custom format archive
+///
+/// See `ReadHead` and `ReadToc` in `pg_backup_archiver.c` for the layout.
+fn read_archive_definitions(bytes: &[u8]) -> io::Result<Vec<String>> {
+    let mut reader = ArchiveReader {
+        bytes,
+        pos: MAGIC.len(),
+        int_size: 0,
+        offset_size: 0,
+    };
+
+    let major = reader.byte()?;
+    let minor = reader.byte()?;
+    let _revision = reader.byte()?;
+    let version = archive_version(major, minor);
+
+    if version < archive_version(1, 10) {
+        return Err(invalid_data("unsupported archive version"));
+    }
+
+    reader.int_size = reader.byte()? as usize;
+    reader.offset_size = reader.byte()? as usize;
+
+    if reader.byte()? != FORMAT_CUSTOM {
+        return Err(invalid_data(
+            "only plain and custom format dumps are supported",
+        ));
+    }
+
+    if version >= archive_version(1, 15) {
+        // compression algorithm
+        reader.byte()?;
+    } else {
+        // compression level
+        reader.int()?;
+    }
+
+    // creation time
+    for _ in 0..7 {
+        reader.int()?;
+    }
+
+    // database name, server version and pg_dump version
+    for _ in 0..3 {
+        reader.string()?;
+    }
+
+    let entries = reader.int()?;
+    let mut definitions = Vec::new();
+
+    for _ in 0..entries {
+        // dump id and whether it had one
+        reader.int()?;
+        reader.int()?;
+        // catalog id
+        reader.string()?;
+        reader.string()?;
+        // tag and description
+        reader.string()?;
+        reader.string()?;
+        if version >= archive_version(1, 11) {
+            // section
+            reader.int()?;
+        }
+
+        if let Some(definition) = reader.string()? {
+            definitions.push(definition);
+        }
+
+        // drop and copy statement, namespace and tablespace
+        for _ in 0..4 {
+            reader.string()?;
+        }
+
+        if version >= archive_version(1, 14) {
+            // table access method
+            reader.string()?;
+        }
+
+        if version >= archive_version(1, 16) {
+            // relkind
+            reader.int()?;
+        }
+
+        // owner and the obsolete "with oids" flag
+        reader.string()?;
+        reader.string()?;
+
+        // dependencies, terminated by a null string
+        while reader.string()?.is_some() {}
+
+        // data offset
+        reader.byte()?;
+        reader.take(reader.offset_size)?;
+    }
+
+    Ok(definitions)
+}
+
+struct ArchiveReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    int_size: usize,
+    offset_size: usize,
+}
+
+impl<'a> ArchiveReader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let slice = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid_data("unexpected end of archive"))?;
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Integers are stored as a sign byte followed by the little endian magnitude
+    fn int(&mut self) -> io::Result<i64> {
+        let negative = self.byte()? != 0;
+        let magnitude = self
+            .take(self.int_size)?
+            .iter()
+            .rev()
+            .fold(0i64, |acc, b| acc << 8 | *b as i64);
+        Ok(if negative { -magnitude } else { magnitude })
+    }
+
+    /// Strings are stored as their length followed by the bytes, with a length of -1 for null
+    fn string(&mut self) -> io::Result<Option<String>> {
+        let len = self.int()?;
+        if len < 0 {
+            return Ok(None);
+        }
+        let bytes = self.take(len as usize)?;
+        Ok(Some(String::from_utf8_lossy(bytes).into_owned()))
+    }
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::SchemaCache;
+
+    use super::{read_archive_definitions, strip_meta_commands, FORMAT_CUSTOM, MAGIC};
+
+    /// Writes a custom format archive of the given version with one entry per definition
+    fn archive(minor: u8, definitions: &[&str]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend([1, minor, 0, 4, 8, FORMAT_CUSTOM]);
+        let int = |bytes: &mut Vec<u8>, value: i32| {
+            bytes.push((value < 0) as u8);
+            bytes.extend(value.unsigned_abs().to_le_bytes());
+        };
+        let string = |bytes: &mut Vec<u8>, value: Option<&str>| match value {
+            Some(value) => {
+                int(bytes, value.len() as i32);
+                bytes.extend(value.as_bytes());
+            }
+            None => int(bytes, -1),
+        };
+
+        // compression level, creation time, database name, server and pg_dump version
+        for _ in 0..8 {
+            int(&mut bytes, 0);
+        }
+        for _ in 0..3 {
+            string(&mut bytes, Some("16.0"));
+        }
+
+        int(&mut bytes, definitions.len() as i32);
+        for (id, definition) in definitions.iter().enumerate() {
+            int(&mut bytes, id as i32 + 1);
+            int(&mut bytes, 0);
+            string(&mut bytes, Some("1259"));
+            string(&mut bytes, Some("16384"));
+            string(&mut bytes, Some("t"));
+            string(&mut bytes, Some("TABLE"));
+            if minor >= 11 {
+                // the section of the pre-data
+                int(&mut bytes, 1);
+            }
+            string(&mut bytes, Some(definition));
+            for _ in 0..4 {
+                string(&mut bytes, Some(""));
+            }
+            // owner and the "with oids" flag
+            string(&mut bytes, Some("postgres"));
+            string(&mut bytes, Some("false"));
+            string(&mut bytes, None);
+            bytes.push(0);
+            bytes.extend([0; 8]);
+        }
+        bytes
+    }
+
+    fn assert_dumped_schema(cache: &SchemaCache) {
+        assert!(cache.schemas.iter().any(|s| s.name == "app"));
+
+        let users = cache.find_table("users", None).unwrap();
+        assert_eq!(users.schema, "app");
+        assert_eq!(users.comment.as_deref(), Some("All users"));
+
+        let events = cache
+            .tables
+            .iter()
+            .find(|t| t.name == "events" && t.schema == "app")
+            .unwrap();
+        assert_eq!(events.partition_key.as_deref(), Some("RANGE (created_at)"));
+        assert_eq!(cache.find_partitions(events).len(), 1);
+
+        assert!(cache.find_type("mood", Some("app")).is_some());
+        assert_eq!(cache.functions[0].name.as_deref(), Some("add"));
+    }
+
+    #[test]
+    fn test_from_plain_dump() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/schema.sql");
+        assert_dumped_schema(&SchemaCache::from_dump(&path).unwrap());
+    }
+
+    #[test]
+    fn test_from_custom_dump() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/schema.dump");
+        assert_dumped_schema(&SchemaCache::from_dump(&path).unwrap());
+    }
+
+    #[test]
+    fn test_archive_versions() {
+        let definitions = ["CREATE TABLE a (id int);", "CREATE TABLE b (id int);"];
+        for minor in [10, 11] {
+            assert_eq!(
+                read_archive_definitions(&archive(minor, &definitions)).unwrap(),
+                definitions
+            );
+        }
+        assert!(read_archive_definitions(&archive(9, &definitions)).is_err());
+    }
+
+    #[test]
+    fn test_strip_meta_commands() {
+        assert_eq!(
+            strip_meta_commands("\\restrict abc\nSET lock_timeout = 0;\n\\connect db"),
+            "SET lock_timeout = 0;"
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Incremental schema cache refresh
+//!
+//! The event trigger installed by [`install_event_trigger`] sends a notification on the
+//! [`NOTIFY_CHANNEL`] channel after every DDL command. The payload lists the object types that
+//! were affected, so that the consumer can reload only the affected object classes via
+//! [`SchemaCache::reload`](crate::SchemaCache::reload).
+
+use std::collections::HashSet;
+
+use serde::Deserialize;
+use sqlx::{Executor, PgPool};
+
+use crate::schema_cache::SchemaCacheClass;
+
+pub const NOTIFY_CHANNEL: &str = "postgres_lsp";
+
+const EVENT_TRIGGER_SQL: &str = r#"
+create or replace function public.postgres_lsp_notify_ddl() returns event_trigger
+language plpgsql as $$
+declare
+  object_types text[];
+begin
+  if tg_event = 'sql_drop' then
+    select array_agg(distinct object_type) into object_types from pg_event_trigger_dropped_objects();
+  else
+    select array_agg(distinct object_type) into object_types from pg_event_trigger_ddl_commands();
+  end if;
+
+  if object_types is not null then
+    perform pg_notify(
+      'postgres_lsp',
+      json_build_object('type', 'ddl', 'object_types', object_types)::text
+    );
+  end if;
+end;
+$$;
+
+do $$
+begin
+  if not exists (select 1 from pg_event_trigger where evtname = 'postgres_lsp_ddl_command_end') then
+    create event trigger postgres_lsp_ddl_command_end on ddl_command_end
+      execute function public.postgres_lsp_notify_ddl();
+  end if;
+
+  if not exists (select 1 from pg_event_trigger where evtname = 'postgres_lsp_sql_drop') then
+    create event trigger postgres_lsp_sql_drop on sql_drop
+      execute function public.postgres_lsp_notify_ddl();
+  end if;
+end;
+$$;
+"#;
+
+/// Installs the event trigger that notifies about DDL changes
+///
+/// Creating event triggers requires superuser privileges. Calling this multiple times is safe.
+pub async fn install_event_trigger(pool: &PgPool) -> Result<(), sqlx::Error> {
+    pool.execute(EVENT_TRIGGER_SQL).await.map(|_| ())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Notification {
+    Ddl { object_types: Vec<String> },
+}
+
+/// Parses the payload of a notification sent by the event trigger into the object classes that
+/// need to be reloaded
+///
+/// Returns `None` if the payload was not sent by the event trigger.
+pub fn parse_ddl_notification(payload: &str) -> Option<HashSet<SchemaCacheClass>> {
+    match serde_json::from_str::<Notification>(payload).ok()? {
+        Notification::Ddl { object_types } => Some(
+            object_types
+                .iter()
+                .flat_map(|t| SchemaCacheClass::from_object_type(t))
+                .copied()
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::schema_cache::SchemaCacheClass;
+
+    use super::parse_ddl_notification;
+
+    #[test]
+    fn test_parse_ddl_notification() {
+        assert_eq!(
+            parse_ddl_notification(
+                r#"{"type" : "ddl", "object_types" : ["table column","function","index"]}"#
+            ),
+            Some(HashSet::from([
+                SchemaCacheClass::Column,
+                SchemaCacheClass::Function,
+                SchemaCacheClass::Index,
+                SchemaCacheClass::ForeignKey
+            ]))
+        );
+        assert_eq!(parse_ddl_notification("reload schema"), None);
+    }
+}
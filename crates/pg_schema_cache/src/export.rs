@@ -0,0 +1,587 @@
+//! Writes the user-defined objects of a schema cache as canonical sql, which
+//! [`SchemaCache::apply_sql`] reads back
+//!
+//! The output is stable: schemas and the objects within them are ordered by name, and every
+//! object is written the same way regardless of how it was created, so that a dump that is
+//! committed to a repository only changes with the schema.
+
+use std::path::PathBuf;
+
+use pg_query::protobuf::KeywordKind;
+
+use crate::{
+    functions::Behavior, Column, ForeignServer, ForeignTable, Function, Policy, SchemaCache, Table,
+};
+
+/// The schemas of Postgres itself, which are not exported
+const SYSTEM_SCHEMAS: &[&str] = &["pg_catalog", "information_schema", "pg_toast"];
+
+/// How the exported objects are split into files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlLayout {
+    /// A file per schema, e.g. `public.sql`
+    Schema,
+    /// A file per object within a directory per schema and kind, e.g. `public/tables/users.sql`
+    Object,
+}
+
+/// An exported sql file, with its path relative to the output directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlFile {
+    pub path: PathBuf,
+    pub sql: String,
+}
+
+impl SchemaCache {
+    /// Returns the user-defined objects as sql files, whose paths sort in the order the files
+    /// have to be applied in
+    ///
+    /// Only the objects the schema cache knows about are exported: schemas, enum and composite
+    /// types, tables with their columns, primary keys, indexes, partitions and row level security
+    /// policies, foreign servers, foreign tables and functions.
+    pub fn to_sql_files(&self, layout: SqlLayout) -> Vec<SqlFile> {
+        let mut files = Vec::new();
+
+        let mut servers = self.foreign_servers.iter().collect::<Vec<_>>();
+        servers.sort_by(|a, b| a.name.cmp(&b.name));
+        let servers = servers
+            .into_iter()
+            .map(|server| (server.name.clone(), foreign_server_sql(server)))
+            .collect::<Vec<_>>();
+        add_files(&mut files, layout, "00_foreign_servers", servers);
+
+        for schema in self.user_schemas() {
+            let mut objects = vec![(
+                "00_schema".to_string(),
+                format!("create schema if not exists {};\n", quote_ident(schema)),
+            )];
+            objects.extend(
+                self.schema_objects(schema)
+                    .into_iter()
+                    .map(|(kind, name, sql)| (format!("{}/{}", kind, name), sql)),
+            );
+            match layout {
+                SqlLayout::Schema => {
+                    let sql = objects
+                        .into_iter()
+                        .map(|(_, sql)| sql)
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    files.push(SqlFile {
+                        path: PathBuf::from(format!("{}.sql", file_name(schema))),
+                        sql,
+                    });
+                }
+                SqlLayout::Object => {
+                    for (name, sql) in objects {
+                        files.push(SqlFile {
+                            path: PathBuf::from(file_name(schema)).join(format!("{}.sql", name)),
+                            sql,
+                        });
+                    }
+                }
+            }
+        }
+
+        files
+    }
+
+    /// Returns the names of the schemas that are not part of Postgres itself, in order
+    pub fn user_schemas(&self) -> Vec<&str> {
+        // `public` and schemas of objects that were not loaded are not in the list of schemas
+        let mut schemas = self
+            .schemas
+            .iter()
+            .map(|s| s.name.as_str())
+            .chain(self.types.iter().map(|t| &*t.schema))
+            .chain(self.tables.iter().map(|t| &*t.schema))
+            .chain(self.foreign_tables.iter().map(|t| &*t.schema))
+            .chain(self.functions.iter().filter_map(|f| f.schema.as_deref()))
+            .filter(|s| !is_system_schema(s))
+            .collect::<Vec<_>>();
+        schemas.sort();
+        schemas.dedup();
+        schemas
+    }
+
+    /// Returns the objects of a schema as their kind directory, file name and sql, in the
+    /// order they are applied in
+    fn schema_objects(&self, schema: &str) -> Vec<(&'static str, String, String)> {
+        let mut objects = Vec::new();
+
+        let mut types = self
+            .types
+            .iter()
+            .filter(|t| t.schema == schema)
+            .collect::<Vec<_>>();
+        types.sort_by(|a, b| a.name.cmp(&b.name));
+        for t in types {
+            let name = qualified(schema, &t.name);
+            let mut sql = if !t.enums.values.is_empty() {
+                let values = t
+                    .enums
+                    .values
+                    .iter()
+                    .map(|v| quote_literal(v))
+                    .collect::<Vec<_>>();
+                format!("create type {} as enum ({});\n", name, values.join(", "))
+            } else if !t.attributes.attrs.is_empty() {
+                let attrs = t
+                    .attributes
+                    .attrs
+                    .iter()
+                    .map(|a| {
+                        format!(
+                            "    {} {}",
+                            quote_ident(&a.name),
+                            self.type_format(a.type_id)
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                format!("create type {} as (\n{}\n);\n", name, attrs.join(",\n"))
+            } else {
+                // base types, domains and ranges cannot be derived from the cache
+                continue;
+            };
+            push_comment(&mut sql, "type", &name, t.comment.as_deref());
+            objects.push(("01_types", file_name(&t.name), sql));
+        }
+
+        // partitions come after the tables they are partitions of
+        let mut tables = self
+            .tables
+            .iter()
+            .filter(|t| t.schema == schema)
+            .collect::<Vec<_>>();
+        tables.sort_by(|a, b| {
+            (a.partition_of.is_some(), &a.name).cmp(&(b.partition_of.is_some(), &b.name))
+        });
+        for table in tables {
+            let kind = match table.partition_of {
+                Some(_) => "03_partitions",
+                None => "02_tables",
+            };
+            objects.push((kind, file_name(&table.name), self.table_sql(table)));
+        }
+
+        let mut foreign_tables = self
+            .foreign_tables
+            .iter()
+            .filter(|t| t.schema == schema)
+            .collect::<Vec<_>>();
+        foreign_tables.sort_by(|a, b| a.name.cmp(&b.name));
+        for table in foreign_tables {
+            objects.push((
+                "04_foreign_tables",
+                file_name(&table.name),
+                self.foreign_table_sql(table),
+            ));
+        }
+
+        // overloads share the file of their name
+        let mut functions = self
+            .functions
+            .iter()
+            .filter(|f| f.schema.as_deref() == Some(schema) && f.name.is_some())
+            .filter(|f| f.language.as_deref() != Some("internal"))
+            .collect::<Vec<_>>();
+        functions.sort_by(|a, b| {
+            (&a.name, &a.identity_argument_types).cmp(&(&b.name, &b.identity_argument_types))
+        });
+        for function in functions {
+            let name = file_name(function.name.as_deref().unwrap());
+            let sql = function_sql(schema, function);
+            match objects.last_mut() {
+                Some(("05_functions", last, last_sql)) if *last == name => {
+                    last_sql.push('\n');
+                    last_sql.push_str(&sql);
+                }
+                _ => objects.push(("05_functions", name, sql)),
+            }
+        }
+
+        objects
+    }
+
+    fn table_sql(&self, table: &Table) -> String {
+        let name = qualified(&table.schema, &table.name);
+        let columns = self.sorted_columns(table.id);
+
+        let mut sql = match table
+            .partition_of
+            .and_then(|id| self.tables.iter().find(|t| t.id == id))
+        {
+            Some(parent) => format!(
+                "create table {} partition of {}\n    {};\n",
+                name,
+                qualified(&parent.schema, &parent.name),
+                table.partition_bound.as_deref().unwrap_or("DEFAULT")
+            ),
+            None => {
+                let mut elements = columns
+                    .iter()
+                    .map(|c| format!("    {}", column_sql(c)))
+                    .collect::<Vec<_>>();
+                let primary_key = columns
+                    .iter()
+                    .filter(|c| c.is_primary_key)
+                    .map(|c| quote_ident(&c.name))
+                    .collect::<Vec<_>>();
+                if !primary_key.is_empty() {
+                    elements.push(format!("    primary key ({})", primary_key.join(", ")));
+                }
+                let mut sql = format!("create table {} (\n{}\n)", name, elements.join(",\n"));
+                if let Some(key) = &table.partition_key {
+                    sql.push_str(&format!(" partition by {}", key));
+                }
+                sql.push_str(";\n");
+                sql
+            }
+        };
+
+        // the indexes of partitions are created with those of their parent
+        if table.partition_of.is_none() {
+            let mut indexes = self
+                .find_indexes(table.id)
+                .into_iter()
+                .filter(|i| !i.is_primary)
+                .collect::<Vec<_>>();
+            indexes.sort_by(|a, b| a.name.cmp(&b.name));
+            for index in indexes {
+                sql.push_str(&format!("{};\n", index.definition));
+            }
+        }
+
+        if table.rls_enabled {
+            sql.push_str(&format!(
+                "alter table {} enable row level security;\n",
+                name
+            ));
+        }
+        if table.rls_forced {
+            sql.push_str(&format!("alter table {} force row level security;\n", name));
+        }
+        let mut policies = self.find_policies(table.id);
+        policies.sort_by(|a, b| a.name.cmp(&b.name));
+        for policy in policies {
+            sql.push_str(&policy_sql(&name, policy));
+        }
+
+        push_comment(&mut sql, "table", &name, table.comment.as_deref());
+        for column in columns {
+            let column_name = format!("{}.{}", name, quote_ident(&column.name));
+            push_comment(&mut sql, "column", &column_name, column.comment.as_deref());
+        }
+        sql
+    }
+
+    fn foreign_table_sql(&self, table: &ForeignTable) -> String {
+        let name = qualified(&table.schema, &table.name);
+        let columns = self.sorted_columns(table.id);
+        let elements = columns
+            .iter()
+            .map(|c| format!("    {}", column_sql(c)))
+            .collect::<Vec<_>>();
+        let mut sql = format!(
+            "create foreign table {} (\n{}\n) server {}{};\n",
+            name,
+            elements.join(",\n"),
+            quote_ident(&table.server_name),
+            options_sql(&table.options)
+        );
+        push_comment(&mut sql, "foreign table", &name, table.comment.as_deref());
+        for column in columns {
+            let column_name = format!("{}.{}", name, quote_ident(&column.name));
+            push_comment(&mut sql, "column", &column_name, column.comment.as_deref());
+        }
+        sql
+    }
+
+    fn sorted_columns(&self, table_id: i64) -> Vec<&Column> {
+        let mut columns = self.find_columns(table_id);
+        columns.sort_by_key(|c| c.position);
+        columns
+    }
+
+    /// Returns the name of a type as it is written in sql, e.g. `integer`
+    fn type_format(&self, type_id: i64) -> String {
+        self.types
+            .iter()
+            .find(|t| t.id == type_id)
+            .map_or_else(|| "unknown".to_string(), |t| t.format.clone())
+    }
+}
+
+fn column_sql(column: &Column) -> String {
+    let mut sql = format!(
+        "{} {}",
+        quote_ident(&column.name),
+        column.type_name.as_str()
+    );
+    if column.is_identity {
+        sql.push_str(" generated by default as identity");
+    } else if let Some(default) = &column.default_value {
+        sql.push_str(&format!(" default {}", default));
+    }
+    if !column.is_nullable && !column.is_primary_key && !column.is_identity {
+        sql.push_str(" not null");
+    }
+    sql
+}
+
+fn foreign_server_sql(server: &ForeignServer) -> String {
+    let mut sql = format!("create server {}", quote_ident(&server.name));
+    if let Some(server_type) = &server.server_type {
+        sql.push_str(&format!(" type {}", quote_literal(server_type)));
+    }
+    if let Some(version) = &server.server_version {
+        sql.push_str(&format!(" version {}", quote_literal(version)));
+    }
+    sql.push_str(&format!(
+        " foreign data wrapper {}{};\n",
+        quote_ident(&server.foreign_data_wrapper),
+        options_sql(&server.options)
+    ));
+    push_comment(
+        &mut sql,
+        "server",
+        &quote_ident(&server.name),
+        server.comment.as_deref(),
+    );
+    sql
+}
+
+/// Formats generic options, which the catalog stores as `key=value`
+fn options_sql(options: &[String]) -> String {
+    if options.is_empty() {
+        return String::new();
+    }
+    let options = options
+        .iter()
+        .map(|option| {
+            let (key, value) = option.split_once('=').unwrap_or((option, ""));
+            format!("{} {}", quote_ident(key), quote_literal(value))
+        })
+        .collect::<Vec<_>>();
+    format!(" options ({})", options.join(", "))
+}
+
+/// Returns the definition of a function, which is the one of the database if it was loaded from
+/// one
+fn function_sql(schema: &str, function: &Function) -> String {
+    if let Some(statement) = &function.complete_statement {
+        return format!("{};\n", statement.trim_end().trim_end_matches(';'));
+    }
+
+    let behavior = match function.behavior {
+        Behavior::Immutable => "immutable",
+        Behavior::Stable => "stable",
+        Behavior::Volatile => "volatile",
+    };
+    format!(
+        "create or replace function {}({})\n returns {}\n language {}\n {}{}\nas $function${}$function$;\n",
+        qualified(schema, function.name.as_deref().unwrap_or_default()),
+        function.argument_types.as_deref().unwrap_or_default(),
+        function.return_type.as_deref().unwrap_or("void"),
+        function.language.as_deref().unwrap_or("sql"),
+        behavior,
+        if function.security_definer {
+            " security definer"
+        } else {
+            ""
+        },
+        function.definition.as_deref().unwrap_or_default()
+    )
+}
+
+fn policy_sql(table: &str, policy: &Policy) -> String {
+    let mut sql = format!("create policy {} on {}", quote_ident(&policy.name), table);
+    if !policy.is_permissive {
+        sql.push_str(" as restrictive");
+    }
+    sql.push_str(&format!(" for {}", policy.command.to_lowercase()));
+    if policy.roles.iter().any(|r| r != "public") {
+        let roles = policy
+            .roles
+            .iter()
+            .map(|r| quote_ident(r))
+            .collect::<Vec<_>>();
+        sql.push_str(&format!(" to {}", roles.join(", ")));
+    }
+    if let Some(using) = &policy.using_expr {
+        sql.push_str(&format!(" using ({})", using));
+    }
+    if let Some(check) = &policy.check_expr {
+        sql.push_str(&format!(" with check ({})", check));
+    }
+    sql.push_str(";\n");
+    sql
+}
+
+fn push_comment(sql: &mut String, kind: &str, name: &str, comment: Option<&str>) {
+    if let Some(comment) = comment {
+        sql.push_str(&format!(
+            "comment on {} {} is {};\n",
+            kind,
+            name,
+            quote_literal(comment)
+        ));
+    }
+}
+
+/// Returns whether a schema belongs to Postgres, whose objects are not part of a project
+pub fn is_system_schema(schema: &str) -> bool {
+    SYSTEM_SCHEMAS.contains(&schema)
+        || schema.starts_with("pg_temp_")
+        || schema.starts_with("pg_toast_temp_")
+}
+
+pub(crate) fn qualified(schema: &str, name: &str) -> String {
+    format!("{}.{}", quote_ident(schema), quote_ident(name))
+}
+
+/// Quotes an identifier unless it is read back as the same name without quotes
+/// Quotes an identifier if it is not a valid identifier on its own, e.g. because it has upper case
+/// letters or is a reserved keyword
+pub fn quote_ident(name: &str) -> String {
+    let plain = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '$');
+    // unreserved keywords, e.g. `name`, are valid identifiers as well
+    let keyword = pg_query::scan(name).ok().is_some_and(|scan| {
+        scan.tokens.iter().any(|token| {
+            !matches!(
+                KeywordKind::from_i32(token.keyword_kind),
+                Some(KeywordKind::NoKeyword | KeywordKind::UnreservedKeyword)
+            )
+        })
+    });
+    if plain && !keyword {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Returns a file name for an object, which replaces the characters that are not safe in paths
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+fn add_files(
+    files: &mut Vec<SqlFile>,
+    layout: SqlLayout,
+    dir: &str,
+    objects: Vec<(String, String)>,
+) {
+    if objects.is_empty() {
+        return;
+    }
+    match layout {
+        SqlLayout::Schema => files.push(SqlFile {
+            path: PathBuf::from(format!("{}.sql", dir)),
+            sql: objects
+                .into_iter()
+                .map(|(_, sql)| sql)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }),
+        SqlLayout::Object => files.extend(objects.into_iter().map(|(name, sql)| SqlFile {
+            path: PathBuf::from(dir).join(format!("{}.sql", file_name(&name))),
+            sql,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::SchemaCache;
+
+    use super::{quote_ident, SqlLayout};
+
+    const SQL: &str = r#"
+create schema analytics;
+create table public.users (id serial primary key, "Email" text not null, "user" text);
+comment on column users."Email" is 'Where to send mail';
+alter table users enable row level security;
+create policy "own user" on users for update to authenticated using ("user" = current_user);
+create table analytics.events (id bigint, created_at timestamptz) partition by range (created_at);
+create table analytics.events_2024 partition of analytics.events
+    for values from ('2024-01-01') to ('2025-01-01');
+create type mood as enum ('happy', 'it''s complicated');
+create function add(a int4, b int4) returns int4 language sql immutable as 'select a + b';
+"#;
+
+    #[test]
+    fn test_to_sql_files() {
+        let cache = SchemaCache::from_sql(SQL);
+
+        let files = cache.to_sql_files(SqlLayout::Object);
+        let paths = files.iter().map(|f| f.path.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                "analytics/00_schema.sql",
+                "analytics/02_tables/events.sql",
+                "analytics/03_partitions/events_2024.sql",
+                "public/00_schema.sql",
+                "public/01_types/mood.sql",
+                "public/02_tables/users.sql",
+                "public/05_functions/add.sql",
+            ]
+            .map(PathBuf::from)
+        );
+        assert_eq!(
+            files[5].sql,
+            "create table public.users (
+    id int4 default nextval('users_id_seq'::regclass),
+    \"Email\" text not null,
+    \"user\" text,
+    primary key (id)
+);
+alter table public.users enable row level security;
+create policy \"own user\" on public.users for update to authenticated using (\"user\" = current_user);
+comment on column public.users.\"Email\" is 'Where to send mail';
+"
+        );
+
+        // the files are read back into the same objects
+        let schema_files = cache.to_sql_files(SqlLayout::Schema);
+        let mut restored = SchemaCache::new();
+        for file in &schema_files {
+            restored.apply_sql(&file.sql);
+        }
+        assert_eq!(restored.to_sql_files(SqlLayout::Schema), schema_files);
+        assert_eq!(
+            schema_files
+                .iter()
+                .map(|f| f.path.to_str().unwrap())
+                .collect::<Vec<_>>(),
+            ["analytics.sql", "public.sql"]
+        );
+    }
+
+    #[test]
+    fn test_quote_ident() {
+        assert_eq!(quote_ident("users"), "users");
+        assert_eq!(quote_ident("name"), "name");
+        assert_eq!(quote_ident("user"), "\"user\"");
+        assert_eq!(quote_ident("Email"), "\"Email\"");
+        assert_eq!(quote_ident("a\"b"), "\"a\"\"b\"");
+    }
+}
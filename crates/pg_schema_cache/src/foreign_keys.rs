@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::ident::Ident;
+use crate::schema_cache::{SchemaCacheItem, SchemaScopedCacheItem};
+
+/// A foreign key constraint of a table
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForeignKey {
+    pub id: i64,
+    pub schema: Ident,
+    pub table_id: i64,
+    pub table: String,
+    pub name: String,
+    /// The referencing columns in order
+    pub columns: Vec<String>,
+    /// The id of the referenced table, or 0 if it is not known, e.g. offline
+    pub referenced_table_id: i64,
+    pub referenced_schema: Ident,
+    pub referenced_table: String,
+    /// The referenced columns in the order of `columns`, which is empty for a reference to the
+    /// primary key of a table that is not known
+    pub referenced_columns: Vec<String>,
+}
+
+impl SchemaCacheItem for ForeignKey {
+    type Item = ForeignKey;
+
+    async fn load(pool: &PgPool) -> Vec<ForeignKey> {
+        ForeignKey::query(pool, None).await
+    }
+}
+
+impl SchemaScopedCacheItem for ForeignKey {
+    async fn load_in_schemas(pool: &PgPool, schemas: &[String]) -> Vec<ForeignKey> {
+        ForeignKey::query(pool, Some(schemas)).await
+    }
+}
+
+impl ForeignKey {
+    async fn query(pool: &PgPool, schemas: Option<&[String]>) -> Vec<ForeignKey> {
+        sqlx::query_file_as!(ForeignKey, "src/queries/foreign_keys.sql", schemas)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::ident::Ident;
+use crate::schema_cache::SchemaCacheItem;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForeignServer {
+    pub id: i64,
+    pub name: String,
+    pub foreign_data_wrapper: Ident,
+    pub server_type: Option<String>,
+    pub server_version: Option<String>,
+    /// The options of the server as `key=value` pairs
+    pub options: Vec<String>,
+    pub comment: Option<String>,
+}
+
+impl SchemaCacheItem for ForeignServer {
+    type Item = ForeignServer;
+
+    async fn load(pool: &PgPool) -> Vec<ForeignServer> {
+        sqlx::query_file_as!(ForeignServer, "src/queries/foreign_servers.sql")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::ident::Ident;
+use crate::schema_cache::{SchemaCacheItem, SchemaScopedCacheItem};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForeignTable {
+    pub id: i64,
+    pub schema: Ident,
+    pub name: String,
+    pub server_id: i64,
+    pub server_name: Ident,
+    /// The options of the foreign table as `key=value` pairs
+    pub options: Vec<String>,
+    pub comment: Option<String>,
+}
+
+impl SchemaCacheItem for ForeignTable {
+    type Item = ForeignTable;
+
+    async fn load(pool: &PgPool) -> Vec<ForeignTable> {
+        ForeignTable::query(pool, None).await
+    }
+}
+
+impl SchemaScopedCacheItem for ForeignTable {
+    async fn load_in_schemas(pool: &PgPool, schemas: &[String]) -> Vec<ForeignTable> {
+        ForeignTable::query(pool, Some(schemas)).await
+    }
+}
+
+impl ForeignTable {
+    async fn query(pool: &PgPool, schemas: Option<&[String]>) -> Vec<ForeignTable> {
+        sqlx::query_file_as!(ForeignTable, "src/queries/foreign_tables.sql", schemas)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+}
@@ -0,0 +1,166 @@
+//! Shared identifiers
+//!
+//! Large schemas repeat the same few strings over and over, e.g. the schema name of every table or
+//! the language of every function. These are stored as [`Ident`]s, which are reference counted,
+//! and deduplicated with an [`Interner`] so that every distinct string is allocated once.
+
+use std::{borrow::Borrow, collections::HashSet, fmt, ops::Deref, sync::Arc};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::{
+    error::BoxDynError,
+    postgres::{PgTypeInfo, PgValueRef},
+    Decode, Postgres, Type,
+};
+
+/// An immutable string that is cheap to clone
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ident(Arc<str>);
+
+impl Ident {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether both identifiers share the same allocation
+    pub fn ptr_eq(&self, other: &Ident) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for Ident {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Ident {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Ident {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Ident {
+    fn from(s: &str) -> Self {
+        Ident(s.into())
+    }
+}
+
+impl From<String> for Ident {
+    fn from(s: String) -> Self {
+        Ident(s.into())
+    }
+}
+
+impl PartialEq<str> for Ident {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Ident {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Ident {
+    fn eq(&self, other: &String) -> bool {
+        &*self.0 == other.as_str()
+    }
+}
+
+impl fmt::Debug for Ident {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Ident {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl Serialize for Ident {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Ident {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Ident::from)
+    }
+}
+
+impl Type<Postgres> for Ident {
+    fn type_info() -> PgTypeInfo {
+        <&str as Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <&str as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Ident {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        <&str as Decode<Postgres>>::decode(value).map(Ident::from)
+    }
+}
+
+/// Deduplicates identifiers so that equal identifiers share one allocation
+#[derive(Debug, Default)]
+pub struct Interner {
+    idents: HashSet<Ident>,
+}
+
+impl Interner {
+    pub fn intern(&mut self, ident: &mut Ident) {
+        match self.idents.get(ident.as_str()) {
+            Some(interned) => *ident = interned.clone(),
+            None => {
+                self.idents.insert(ident.clone());
+            }
+        }
+    }
+
+    pub fn intern_opt(&mut self, ident: &mut Option<Ident>) {
+        if let Some(ident) = ident {
+            self.intern(ident);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ident, Interner};
+
+    #[test]
+    fn test_intern() {
+        let mut interner = Interner::default();
+
+        let mut a = Ident::from("public");
+        let mut b = Ident::from("public".to_string());
+        let mut c = Ident::from("app");
+
+        assert!(!a.ptr_eq(&b));
+
+        interner.intern(&mut a);
+        interner.intern(&mut b);
+        interner.intern(&mut c);
+
+        assert!(a.ptr_eq(&b));
+        assert!(!a.ptr_eq(&c));
+        assert_eq!(a, "public");
+    }
+}
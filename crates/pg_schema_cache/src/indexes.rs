@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::ident::Ident;
+use crate::schema_cache::{SchemaCacheItem, SchemaScopedCacheItem};
+
+/// An index of a table or materialized view, including those of primary keys and unique
+/// constraints
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Index {
+    pub id: i64,
+    pub schema: Ident,
+    pub table_id: i64,
+    pub table: String,
+    pub name: String,
+    /// The key columns in order, or the expression for a key on an expression, e.g.
+    /// `lower(email)`
+    pub columns: Vec<String>,
+    pub is_unique: bool,
+    pub is_primary: bool,
+    /// Whether the index has a `where` clause, so that it only covers some rows
+    pub is_partial: bool,
+    /// The statement that creates the index, e.g.
+    /// `CREATE INDEX users_email_idx ON public.users USING btree (email)`
+    pub definition: String,
+}
+
+impl SchemaCacheItem for Index {
+    type Item = Index;
+
+    async fn load(pool: &PgPool) -> Vec<Index> {
+        Index::query(pool, None).await
+    }
+}
+
+impl SchemaScopedCacheItem for Index {
+    async fn load_in_schemas(pool: &PgPool, schemas: &[String]) -> Vec<Index> {
+        Index::query(pool, Some(schemas)).await
+    }
+}
+
+impl Index {
+    async fn query(pool: &PgPool, schemas: Option<&[String]>) -> Vec<Index> {
+        sqlx::query_file_as!(Index, "src/queries/indexes.sql", schemas)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+}
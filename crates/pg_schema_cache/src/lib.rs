@@ -3,16 +3,49 @@
 #![allow(dead_code)]
 #![feature(future_join)]
 
+mod batch;
+mod columns;
+mod ddl;
+mod diff;
+mod dump;
+mod event_trigger;
+mod export;
+mod foreign_keys;
+mod foreign_servers;
+mod foreign_tables;
 mod functions;
+mod ident;
+mod indexes;
+mod persist;
+mod policies;
+mod publications;
 mod schema_cache;
 mod schemas;
+mod search_path;
+mod subscriptions;
 mod tables;
 mod types;
+mod version;
 
 use sqlx::postgres::PgPool;
 
+pub use columns::Column;
+pub use diff::{canonical_type, ObjectKind, SchemaChange};
+pub use event_trigger::{install_event_trigger, parse_ddl_notification, NOTIFY_CHANNEL};
+pub use export::{is_system_schema, quote_ident, SqlFile, SqlLayout};
+pub use foreign_keys::ForeignKey;
+pub use foreign_servers::ForeignServer;
+pub use foreign_tables::ForeignTable;
 pub use functions::{Behavior, Function, FunctionArg, FunctionArgs};
-pub use schema_cache::SchemaCache;
+pub use ident::Ident;
+pub use indexes::Index;
+pub use persist::default_cache_dir;
+pub use policies::Policy;
+pub use publications::Publication;
+pub use schema_cache::{SchemaCache, SchemaCacheClass, SchemaCacheUpdate};
+pub use schemas::Schema;
+pub use search_path::Relation;
+pub use subscriptions::Subscription;
 pub use tables::{ReplicaIdentity, Table};
 
 #[derive(Debug, Clone)]
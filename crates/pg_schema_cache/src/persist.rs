@@ -0,0 +1,110 @@
+//! Persists the schema cache to disk, so that it is available immediately on the next startup
+//! while the cache is revalidated against the database in the background.
+
+use std::{
+    fs,
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::SchemaCache;
+
+/// Bump whenever the serialized structure of the schema cache changes
+const FORMAT_VERSION: u32 = 7;
+
+#[derive(Serialize)]
+struct PersistedSchemaCacheRef<'a> {
+    version: u32,
+    cache: &'a SchemaCache,
+}
+
+#[derive(Deserialize)]
+struct PersistedSchemaCache {
+    version: u32,
+    cache: SchemaCache,
+}
+
+/// The default directory to persist schema caches in, e.g. `~/.cache/postgres_lsp` on Linux
+pub fn default_cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("postgres_lsp"))
+}
+
+/// The file name is derived from a hash of the connection string, so that credentials are not
+/// leaked into the file system
+fn cache_file(dir: &Path, connection_string: &str) -> PathBuf {
+    let hash = hex::encode(Sha256::digest(connection_string.as_bytes()));
+    dir.join(format!("schema-cache-{}.json", hash))
+}
+
+impl SchemaCache {
+    /// Reads the schema cache persisted for the given connection
+    ///
+    /// Returns `None` if there is no persisted cache or if it was written in another format
+    /// version.
+    pub fn read_from_disk(dir: &Path, connection_string: &str) -> Option<SchemaCache> {
+        let file = fs::File::open(cache_file(dir, connection_string)).ok()?;
+
+        let persisted: PersistedSchemaCache = serde_json::from_reader(BufReader::new(file)).ok()?;
+
+        if persisted.version != FORMAT_VERSION {
+            return None;
+        }
+
+        let mut cache = persisted.cache;
+        cache.intern();
+        Some(cache)
+    }
+
+    /// Persists the schema cache for the given connection
+    pub fn write_to_disk(&self, dir: &Path, connection_string: &str) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+
+        // write to a temporary file first so that concurrent readers never see a partial file
+        let path = cache_file(dir, connection_string);
+        let tmp_path = path.with_extension("json.tmp");
+
+        let file = fs::File::create(&tmp_path)?;
+        serde_json::to_writer(
+            BufWriter::new(file),
+            &PersistedSchemaCacheRef {
+                version: FORMAT_VERSION,
+                cache: self,
+            },
+        )?;
+
+        fs::rename(tmp_path, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use crate::SchemaCache;
+
+    #[test]
+    fn test_persist_schema_cache() {
+        let conn_string = std::env::var("DB_CONNECTION_STRING").unwrap();
+
+        let pool = async_std::task::block_on(PgPool::connect(conn_string.as_str())).unwrap();
+
+        let cache = async_std::task::block_on(SchemaCache::load(&pool));
+
+        let dir = std::env::temp_dir().join(format!("pg_schema_cache_{}", std::process::id()));
+
+        assert!(SchemaCache::read_from_disk(&dir, &conn_string).is_none());
+
+        cache.write_to_disk(&dir, &conn_string).unwrap();
+
+        let restored = SchemaCache::read_from_disk(&dir, &conn_string).unwrap();
+
+        assert_eq!(restored.tables, cache.tables);
+        assert_eq!(restored.functions.len(), cache.functions.len());
+        assert!(SchemaCache::read_from_disk(&dir, "postgresql://other").is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::ident::Ident;
+use crate::schema_cache::{SchemaCacheItem, SchemaScopedCacheItem};
+
+/// A row level security policy of a table
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Policy {
+    pub id: i64,
+    pub schema: Ident,
+    pub table_id: i64,
+    pub table: String,
+    pub name: String,
+    /// The command the policy applies to, i.e. `ALL`, `SELECT`, `INSERT`, `UPDATE` or `DELETE`
+    pub command: String,
+    /// Whether the policy is combined with the other permissive policies with `OR`, rather than
+    /// with `AND` like a restrictive one
+    pub is_permissive: bool,
+    /// The roles the policy applies to, which is `public` for all roles
+    pub roles: Vec<String>,
+    /// The expression that rows must satisfy to be visible
+    pub using_expr: Option<String>,
+    /// The expression that new rows must satisfy
+    pub check_expr: Option<String>,
+}
+
+impl SchemaCacheItem for Policy {
+    type Item = Policy;
+
+    async fn load(pool: &PgPool) -> Vec<Policy> {
+        Policy::query(pool, None).await
+    }
+}
+
+impl SchemaScopedCacheItem for Policy {
+    async fn load_in_schemas(pool: &PgPool, schemas: &[String]) -> Vec<Policy> {
+        Policy::query(pool, Some(schemas)).await
+    }
+}
+
+impl Policy {
+    async fn query(pool: &PgPool, schemas: Option<&[String]>) -> Vec<Policy> {
+        sqlx::query_file_as!(Policy, "src/queries/policies.sql", schemas)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::schema_cache::SchemaCacheItem;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Publication {
+    pub id: i64,
+    pub name: String,
+    /// Whether the publication was created with `FOR ALL TABLES`
+    pub all_tables: bool,
+    pub publishes_insert: bool,
+    pub publishes_update: bool,
+    pub publishes_delete: bool,
+    pub publishes_truncate: bool,
+    /// The ids of all tables that are part of the publication, including those added via
+    /// `FOR ALL TABLES` or `FOR TABLES IN SCHEMA`
+    pub table_ids: Vec<i64>,
+}
+
+impl Publication {
+    pub fn contains_table(&self, table_id: i64) -> bool {
+        self.table_ids.contains(&table_id)
+    }
+}
+
+impl SchemaCacheItem for Publication {
+    type Item = Publication;
+
+    async fn load(pool: &PgPool) -> Vec<Publication> {
+        sqlx::query_file_as!(Publication, "src/queries/publications.sql")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+}
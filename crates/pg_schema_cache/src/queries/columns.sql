@@ -0,0 +1,38 @@
+select
+  c.oid :: int8 as "table_id!",
+  n.nspname as "schema!",
+  c.relname as "table!",
+  a.attname as "name!",
+  a.attnum :: int8 as "position!",
+  a.atttypid :: int8 as "type_id!",
+  format_type(a.atttypid, a.atttypmod) as "type_name!",
+  not a.attnotnull as "is_nullable!",
+  pg_get_expr(d.adbin, d.adrelid) as default_value,
+  a.attidentity <> '' as "is_identity!",
+  exists (
+    select
+      1
+    from
+      pg_index i
+    where
+      i.indrelid = c.oid
+      and i.indisprimary
+      and a.attnum = any(i.indkey)
+  ) as "is_primary_key!",
+  col_description(c.oid, a.attnum) as comment
+from
+  pg_attribute a
+  join pg_class c on c.oid = a.attrelid
+  join pg_namespace n on n.oid = c.relnamespace
+  left join pg_attrdef d on d.adrelid = a.attrelid
+  and d.adnum = a.attnum
+where
+  c.relkind in ('r', 'p', 'v', 'm', 'f')
+  and a.attnum > 0
+  and not a.attisdropped
+  and ($1::text[] is null or n.nspname = any($1))
+  and not pg_is_other_temp_schema(n.oid)
+  and (
+    pg_has_role(c.relowner, 'USAGE')
+    or has_column_privilege(c.oid, a.attnum, 'SELECT, INSERT, UPDATE, REFERENCES')
+  )
@@ -0,0 +1,48 @@
+select
+  con.oid :: int8 as "id!",
+  n.nspname as "schema!",
+  c.oid :: int8 as "table_id!",
+  c.relname as "table!",
+  con.conname as "name!",
+  array(
+    select
+      a.attname :: text
+    from
+      unnest(con.conkey) with ordinality k(attnum, ord)
+      join pg_attribute a on a.attrelid = con.conrelid
+      and a.attnum = k.attnum
+    order by
+      k.ord
+  ) as "columns!",
+  rc.oid :: int8 as "referenced_table_id!",
+  rn.nspname as "referenced_schema!",
+  rc.relname as "referenced_table!",
+  array(
+    select
+      a.attname :: text
+    from
+      unnest(con.confkey) with ordinality k(attnum, ord)
+      join pg_attribute a on a.attrelid = con.confrelid
+      and a.attnum = k.attnum
+    order by
+      k.ord
+  ) as "referenced_columns!"
+from
+  pg_constraint con
+  join pg_class c on c.oid = con.conrelid
+  join pg_namespace n on n.oid = c.relnamespace
+  join pg_class rc on rc.oid = con.confrelid
+  join pg_namespace rn on rn.oid = rc.relnamespace
+where
+  con.contype = 'f'
+  -- the foreign keys of partitions are clones of that of the partitioned table
+  and con.conparentid = 0
+  and ($1::text[] is null or n.nspname = any($1))
+  and not pg_is_other_temp_schema(n.oid)
+  and (
+    pg_has_role(c.relowner, 'USAGE')
+    or has_table_privilege(
+      c.oid,
+      'SELECT, INSERT, UPDATE, DELETE, TRUNCATE, REFERENCES, TRIGGER'
+    )
+  )
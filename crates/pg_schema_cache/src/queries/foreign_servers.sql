@@ -0,0 +1,11 @@
+select
+  s.oid :: int8 as "id!",
+  s.srvname as "name!",
+  w.fdwname as "foreign_data_wrapper!",
+  s.srvtype as server_type,
+  s.srvversion as server_version,
+  coalesce(s.srvoptions, '{}') as "options!",
+  obj_description(s.oid, 'pg_foreign_server') as comment
+from
+  pg_foreign_server s
+  join pg_foreign_data_wrapper w on w.oid = s.srvfdw
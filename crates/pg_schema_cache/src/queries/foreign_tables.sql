@@ -0,0 +1,24 @@
+select
+  c.oid :: int8 as "id!",
+  n.nspname as "schema!",
+  c.relname as "name!",
+  s.oid :: int8 as "server_id!",
+  s.srvname as "server_name!",
+  coalesce(ft.ftoptions, '{}') as "options!",
+  obj_description(c.oid) as comment
+from
+  pg_foreign_table ft
+  join pg_class c on c.oid = ft.ftrelid
+  join pg_namespace n on n.oid = c.relnamespace
+  join pg_foreign_server s on s.oid = ft.ftserver
+where
+  ($1::text[] is null or n.nspname = any($1))
+  and not pg_is_other_temp_schema(n.oid)
+  and (
+    pg_has_role(c.relowner, 'USAGE')
+    or has_table_privilege(
+      c.oid,
+      'SELECT, INSERT, UPDATE, DELETE, TRUNCATE, REFERENCES, TRIGGER'
+    )
+    or has_any_column_privilege(c.oid, 'SELECT, INSERT, UPDATE, REFERENCES')
+  )
@@ -0,0 +1,34 @@
+select
+  i.indexrelid :: int8 as "id!",
+  n.nspname as "schema!",
+  c.oid :: int8 as "table_id!",
+  c.relname as "table!",
+  ic.relname as "name!",
+  array(
+    select
+      pg_get_indexdef(i.indexrelid, k, true)
+    from
+      generate_series(1, i.indnkeyatts) k
+    order by
+      k
+  ) as "columns!",
+  i.indisunique as "is_unique!",
+  i.indisprimary as "is_primary!",
+  i.indpred is not null as "is_partial!",
+  pg_get_indexdef(i.indexrelid) as "definition!"
+from
+  pg_index i
+  join pg_class ic on ic.oid = i.indexrelid
+  join pg_class c on c.oid = i.indrelid
+  join pg_namespace n on n.oid = c.relnamespace
+where
+  c.relkind in ('r', 'p', 'm')
+  and ($1::text[] is null or n.nspname = any($1))
+  and not pg_is_other_temp_schema(n.oid)
+  and (
+    pg_has_role(c.relowner, 'USAGE')
+    or has_table_privilege(
+      c.oid,
+      'SELECT, INSERT, UPDATE, DELETE, TRUNCATE, REFERENCES, TRIGGER'
+    )
+  )
@@ -0,0 +1,44 @@
+select
+  p.oid :: int8 as "id!",
+  n.nspname as "schema!",
+  c.oid :: int8 as "table_id!",
+  c.relname as "table!",
+  p.polname as "name!",
+  case
+    p.polcmd
+    when 'r' then 'SELECT'
+    when 'a' then 'INSERT'
+    when 'w' then 'UPDATE'
+    when 'd' then 'DELETE'
+    else 'ALL'
+  end as "command!",
+  p.polpermissive as "is_permissive!",
+  case
+    when p.polroles = '{0}' then array['public']
+    else array(
+      select
+        r.rolname :: text
+      from
+        pg_roles r
+      where
+        r.oid = any(p.polroles)
+      order by
+        r.rolname
+    )
+  end as "roles!",
+  pg_get_expr(p.polqual, p.polrelid) as using_expr,
+  pg_get_expr(p.polwithcheck, p.polrelid) as check_expr
+from
+  pg_policy p
+  join pg_class c on c.oid = p.polrelid
+  join pg_namespace n on n.oid = c.relnamespace
+where
+  ($1::text[] is null or n.nspname = any($1))
+  and not pg_is_other_temp_schema(n.oid)
+  and (
+    pg_has_role(c.relowner, 'USAGE')
+    or has_table_privilege(
+      c.oid,
+      'SELECT, INSERT, UPDATE, DELETE, TRUNCATE, REFERENCES, TRIGGER'
+    )
+  )
@@ -0,0 +1,24 @@
+select
+  p.oid :: int8 as "id!",
+  p.pubname as "name!",
+  p.puballtables as "all_tables!",
+  p.pubinsert as "publishes_insert!",
+  p.pubupdate as "publishes_update!",
+  p.pubdelete as "publishes_delete!",
+  p.pubtruncate as "publishes_truncate!",
+  coalesce(
+    (
+      select
+        array_agg(c.oid :: int8)
+      from
+        pg_publication_tables pt
+        join pg_namespace n on n.nspname = pt.schemaname
+        join pg_class c on c.relnamespace = n.oid
+        and c.relname = pt.tablename
+      where
+        pt.pubname = p.pubname
+    ),
+    '{}'
+  ) as "table_ids!"
+from
+  pg_publication p
@@ -0,0 +1,10 @@
+select
+  s.oid :: int8 as "id!",
+  s.subname as "name!",
+  s.subenabled as "enabled!",
+  s.subpublications as "publications!"
+from
+  pg_subscription s
+  join pg_database d on d.oid = s.subdbid
+where
+  d.datname = current_database()
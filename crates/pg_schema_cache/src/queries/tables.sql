@@ -0,0 +1,47 @@
+SELECT
+  c.oid :: int8 AS "id!",
+  nc.nspname AS schema,
+  c.relname AS name,
+  c.relrowsecurity AS rls_enabled,
+  c.relforcerowsecurity AS rls_forced,
+  CASE
+    WHEN c.relreplident = 'd' THEN 'DEFAULT'
+    WHEN c.relreplident = 'i' THEN 'INDEX'
+    WHEN c.relreplident = 'f' THEN 'FULL'
+    ELSE 'NOTHING'
+  END AS "replica_identity!",
+  pg_total_relation_size(format('%I.%I', nc.nspname, c.relname)) :: int8 AS "bytes!",
+  pg_size_pretty(
+    pg_total_relation_size(format('%I.%I', nc.nspname, c.relname))
+  ) AS "size!",
+  pg_stat_get_live_tuples(c.oid) AS "live_rows_estimate!",
+  pg_stat_get_dead_tuples(c.oid) AS "dead_rows_estimate!",
+  c.reltuples :: int8 AS "row_estimate!",
+  obj_description(c.oid) AS comment,
+  c.relkind = 'p' AS "is_partitioned!",
+  pg_get_partkeydef(c.oid) AS partition_key,
+  (
+    SELECT
+      i.inhparent :: int8
+    FROM
+      pg_inherits i
+    WHERE
+      i.inhrelid = c.oid
+      AND c.relispartition
+  ) AS partition_of,
+  pg_get_expr(c.relpartbound, c.oid) AS partition_bound
+FROM
+  pg_namespace nc
+  JOIN pg_class c ON nc.oid = c.relnamespace
+WHERE
+  c.relkind IN ('r', 'p')
+  AND ($1 :: text[] IS NULL OR nc.nspname = ANY($1))
+  AND NOT pg_is_other_temp_schema(nc.oid)
+  AND (
+    pg_has_role(c.relowner, 'USAGE')
+    OR has_table_privilege(
+      c.oid,
+      'SELECT, INSERT, UPDATE, DELETE, TRUNCATE, REFERENCES, TRIGGER'
+    )
+    OR has_any_column_privilege(c.oid, 'SELECT, INSERT, UPDATE, REFERENCES')
+  )
@@ -1,81 +1,705 @@
+use std::collections::HashSet;
 use std::future::join;
+use std::sync::Arc;
 
+use pg_lexer::{SyntaxKind, Token, TokenType, WHITESPACE_TOKENS};
+use serde::{Deserialize, Serialize};
 use sqlx::postgres::PgPool;
 
+use crate::batch;
+use crate::columns::Column;
+use crate::foreign_keys::ForeignKey;
+use crate::foreign_servers::ForeignServer;
+use crate::foreign_tables::ForeignTable;
 use crate::functions::Function;
+use crate::ident::Interner;
+use crate::indexes::Index;
+use crate::policies::Policy;
+use crate::publications::Publication;
 use crate::schemas::Schema;
+use crate::subscriptions::Subscription;
 use crate::tables::Table;
 use crate::types::PostgresType;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SchemaCache {
     pub schemas: Vec<Schema>,
     pub tables: Vec<Table>,
+    pub columns: Vec<Column>,
     pub functions: Vec<Function>,
-    pub types: Vec<PostgresType>,
+    /// Types are shared so that objects referencing a type can hold on to it
+    pub types: Vec<Arc<PostgresType>>,
+    pub foreign_tables: Vec<ForeignTable>,
+    pub foreign_servers: Vec<ForeignServer>,
+    pub indexes: Vec<Index>,
+    pub foreign_keys: Vec<ForeignKey>,
+    pub policies: Vec<Policy>,
+    pub publications: Vec<Publication>,
+    pub subscriptions: Vec<Subscription>,
+    /// The schemas whose objects have been loaded, or `None` if all schemas are loaded
+    pub loaded_schemas: Option<HashSet<String>>,
+    /// The `search_path` used to resolve unqualified names, see [`SchemaCache::resolve`]
+    pub search_path: Vec<String>,
 }
 
 impl SchemaCache {
+    /// Creates an empty cache with the default `search_path` of Postgres
     pub fn new() -> SchemaCache {
-        SchemaCache::default()
+        SchemaCache {
+            search_path: vec!["$user".to_string(), "public".to_string()],
+            ..SchemaCache::default()
+        }
     }
 
     pub async fn load(pool: &PgPool) -> SchemaCache {
-        let (schemas, tables, functions, types) = join!(
-            Schema::load(pool),
-            Table::load(pool),
-            Function::load(pool),
-            PostgresType::load(pool)
-        )
-        .await;
+        batch::load(pool, None).await
+    }
 
-        SchemaCache {
-            schemas,
-            tables,
-            functions,
-            types,
+    /// Loads the objects of the schemas in the `search_path` and of the given schemas only, e.g.
+    /// of those that were loaded on demand before a reload
+    ///
+    /// Objects of other schemas are loaded on demand via [`SchemaCache::load_schemas`].
+    pub async fn load_lazy(pool: &PgPool, loaded_schemas: &[String]) -> SchemaCache {
+        // includes implicitly searched schemas such as pg_catalog
+        let mut schemas = sqlx::query_scalar!(r#"select unnest(current_schemas(true)) as "name!""#)
+            .fetch_all(pool)
+            .await
+            .unwrap();
+        for schema in loaded_schemas {
+            if !schemas.contains(schema) {
+                schemas.push(schema.clone());
+            }
+        }
+
+        batch::load(pool, Some(&schemas)).await
+    }
+
+    /// Loads the objects of the given schemas if they have not been loaded yet
+    pub async fn load_schemas(&mut self, pool: &PgPool, schemas: &[String]) {
+        let Some(loaded_schemas) = self.loaded_schemas.as_ref() else {
+            return;
+        };
+
+        let schemas: Vec<String> = schemas
+            .iter()
+            .filter(|s| !loaded_schemas.contains(*s))
+            .cloned()
+            .collect();
+
+        if schemas.is_empty() {
+            return;
+        }
+
+        self.apply_update(SchemaCacheUpdate::load_schemas(pool, &schemas).await);
+    }
+    pub fn is_schema_loaded(&self, name: &str) -> bool {
+        match &self.loaded_schemas {
+            Some(loaded_schemas) => loaded_schemas.contains(name),
+            None => true,
+        }
+    }
+
+    /// Returns the existing schemas that are used as a qualifier in the given sql, e.g.
+    /// `analytics` in `select * from analytics.events`, but whose objects are not loaded yet
+    pub fn unloaded_schema_references(&self, sql: &str) -> Vec<String> {
+        let mut references = Vec::new();
+
+        for qualifier in qualifiers(sql) {
+            if !self.is_schema_loaded(&qualifier)
+                && !references.contains(&qualifier)
+                && self.schemas.iter().any(|s| s.name == qualifier)
+            {
+                references.push(qualifier);
+            }
         }
+
+        references
     }
 
-    /// Applies an AST node to the repository
+    /// Replaces the objects of the classes of an update in the schemas it loaded, leaving the
+    /// rest of the cache untouched
     ///
-    /// For example, alter table add column will add the column to the table if it does not exist
-    /// yet
-    pub fn mutate(&mut self) {
-        unimplemented!();
+    /// Objects of schemas that are not loaded are skipped, e.g. if the cache was reloaded with
+    /// fewer schemas while the update was loaded.
+    pub fn apply_update(&mut self, update: SchemaCacheUpdate) {
+        let SchemaCacheUpdate {
+            classes,
+            schemas,
+            loads_schemas,
+            objects,
+        } = update;
+
+        if let (true, Some(loaded_schemas), Some(schemas)) =
+            (loads_schemas, self.loaded_schemas.as_mut(), &schemas)
+        {
+            loaded_schemas.extend(schemas.iter().cloned());
+        }
+
+        let loaded_schemas = self.loaded_schemas.clone();
+        let replaced = |schema: &str| schemas.as_ref().is_none_or(|s| s.contains(schema));
+        let kept = |schema: &str| loaded_schemas.as_ref().is_none_or(|s| s.contains(schema));
+        let SchemaCache {
+            mut schemas,
+            mut tables,
+            mut columns,
+            mut functions,
+            mut types,
+            mut foreign_tables,
+            mut foreign_servers,
+            mut indexes,
+            mut foreign_keys,
+            mut policies,
+            mut publications,
+            mut subscriptions,
+            ..
+        } = objects;
+
+        for class in classes {
+            match class {
+                SchemaCacheClass::Schema => self.schemas = std::mem::take(&mut schemas),
+                SchemaCacheClass::Table => merge_scoped(
+                    &mut self.tables,
+                    std::mem::take(&mut tables),
+                    |t| &t.schema,
+                    replaced,
+                    kept,
+                ),
+                SchemaCacheClass::Column => merge_scoped(
+                    &mut self.columns,
+                    std::mem::take(&mut columns),
+                    |c| &c.schema,
+                    replaced,
+                    kept,
+                ),
+                SchemaCacheClass::Function => merge_scoped(
+                    &mut self.functions,
+                    std::mem::take(&mut functions),
+                    |f| f.schema.as_deref().unwrap_or_default(),
+                    replaced,
+                    kept,
+                ),
+                SchemaCacheClass::Type => merge_scoped(
+                    &mut self.types,
+                    std::mem::take(&mut types),
+                    |t| &t.schema,
+                    replaced,
+                    kept,
+                ),
+                SchemaCacheClass::ForeignTable => merge_scoped(
+                    &mut self.foreign_tables,
+                    std::mem::take(&mut foreign_tables),
+                    |t| &t.schema,
+                    replaced,
+                    kept,
+                ),
+                SchemaCacheClass::ForeignServer => {
+                    self.foreign_servers = std::mem::take(&mut foreign_servers)
+                }
+                SchemaCacheClass::Index => merge_scoped(
+                    &mut self.indexes,
+                    std::mem::take(&mut indexes),
+                    |i| &i.schema,
+                    replaced,
+                    kept,
+                ),
+                SchemaCacheClass::ForeignKey => merge_scoped(
+                    &mut self.foreign_keys,
+                    std::mem::take(&mut foreign_keys),
+                    |k| &k.schema,
+                    replaced,
+                    kept,
+                ),
+                SchemaCacheClass::Policy => merge_scoped(
+                    &mut self.policies,
+                    std::mem::take(&mut policies),
+                    |p| &p.schema,
+                    replaced,
+                    kept,
+                ),
+                SchemaCacheClass::Publication => {
+                    self.publications = std::mem::take(&mut publications)
+                }
+                SchemaCacheClass::Subscription => {
+                    self.subscriptions = std::mem::take(&mut subscriptions)
+                }
+            }
+        }
+        self.intern();
+    }
+
+    /// Deduplicates the identifiers that repeat across objects, e.g. schema names, so that
+    /// every distinct identifier is only kept in memory once
+    pub fn intern(&mut self) {
+        let mut interner = Interner::default();
+
+        for schema in self.schemas.iter_mut() {
+            interner.intern(&mut schema.owner);
+        }
+        for table in self.tables.iter_mut() {
+            interner.intern(&mut table.schema);
+        }
+        for column in self.columns.iter_mut() {
+            interner.intern(&mut column.schema);
+            interner.intern(&mut column.type_name);
+        }
+        for function in self.functions.iter_mut() {
+            interner.intern_opt(&mut function.schema);
+            interner.intern_opt(&mut function.language);
+            interner.intern_opt(&mut function.return_type);
+            for arg in function.args.args.iter_mut() {
+                interner.intern(&mut arg.mode);
+            }
+        }
+        for t in self.types.iter_mut() {
+            interner.intern(&mut Arc::make_mut(t).schema);
+        }
+        for table in self.foreign_tables.iter_mut() {
+            interner.intern(&mut table.schema);
+            interner.intern(&mut table.server_name);
+        }
+        for server in self.foreign_servers.iter_mut() {
+            interner.intern(&mut server.foreign_data_wrapper);
+        }
+        for index in self.indexes.iter_mut() {
+            interner.intern(&mut index.schema);
+        }
+        for foreign_key in self.foreign_keys.iter_mut() {
+            interner.intern(&mut foreign_key.schema);
+            interner.intern(&mut foreign_key.referenced_schema);
+        }
+        for policy in self.policies.iter_mut() {
+            interner.intern(&mut policy.schema);
+        }
     }
 
     pub fn find_table(&self, name: &str, schema: Option<&str>) -> Option<&Table> {
         self.tables
             .iter()
-            .find(|t| t.name == name && schema.is_none() || Some(t.schema.as_str()) == schema)
+            .find(|t| t.name == name && schema.is_none_or(|s| t.schema == s))
+    }
+
+    /// Returns the columns of a table, view or foreign table ordered by their position
+    pub fn find_columns(&self, table_id: i64) -> Vec<&Column> {
+        let mut columns: Vec<&Column> = self
+            .columns
+            .iter()
+            .filter(|c| c.table_id == table_id)
+            .collect();
+        columns.sort_by_key(|c| c.position);
+        columns
+    }
+
+    pub fn find_column(&self, table_id: i64, name: &str) -> Option<&Column> {
+        self.columns
+            .iter()
+            .find(|c| c.table_id == table_id && c.name == name)
+    }
+
+    /// Returns the indexes of a table, including those of its primary key and unique constraints
+    pub fn find_indexes(&self, table_id: i64) -> Vec<&Index> {
+        self.indexes
+            .iter()
+            .filter(|i| i.table_id == table_id)
+            .collect()
+    }
+
+    /// Returns the foreign keys of a table, i.e. those that reference other tables
+    pub fn find_foreign_keys(&self, table_id: i64) -> Vec<&ForeignKey> {
+        self.foreign_keys
+            .iter()
+            .filter(|k| k.table_id == table_id)
+            .collect()
+    }
+
+    /// Returns the row level security policies of a table
+    pub fn find_policies(&self, table_id: i64) -> Vec<&Policy> {
+        self.policies
+            .iter()
+            .filter(|p| p.table_id == table_id)
+            .collect()
+    }
+
+    /// Returns the direct child partitions of a partitioned table
+    pub fn find_partitions(&self, table: &Table) -> Vec<&Table> {
+        self.tables
+            .iter()
+            .filter(|t| t.partition_of == Some(table.id))
+            .collect()
+    }
+
+    /// Returns the parent of a partition
+    pub fn find_partition_parent(&self, table: &Table) -> Option<&Table> {
+        table
+            .partition_of
+            .and_then(|id| self.tables.iter().find(|t| t.id == id))
+    }
+
+    pub fn find_foreign_table(&self, name: &str, schema: Option<&str>) -> Option<&ForeignTable> {
+        self.foreign_tables
+            .iter()
+            .find(|t| t.name == name && schema.is_none_or(|s| t.schema == s))
+    }
+
+    /// Returns the server a foreign table maps to
+    pub fn find_foreign_server(&self, table: &ForeignTable) -> Option<&ForeignServer> {
+        self.foreign_servers
+            .iter()
+            .find(|s| s.id == table.server_id)
+    }
+
+    /// Returns all publications that replicate the given table
+    pub fn find_publications(&self, table: &Table) -> Vec<&Publication> {
+        self.publications
+            .iter()
+            .filter(|p| p.contains_table(table.id))
+            .collect()
     }
 
     pub fn find_type(&self, name: &str, schema: Option<&str>) -> Option<&PostgresType> {
         self.types
             .iter()
-            .find(|t| t.name == name && schema.is_none() || Some(t.schema.as_str()) == schema)
+            .find(|t| t.name == name && schema.is_none_or(|s| t.schema == s))
+            .map(|t| t.as_ref())
     }
 
     pub fn find_types(&self, name: &str, schema: Option<&str>) -> Vec<&PostgresType> {
         self.types
             .iter()
-            .filter(|t| t.name == name && schema.is_none() || Some(t.schema.as_str()) == schema)
+            .filter(|t| t.name == name && schema.is_none_or(|s| t.schema == s))
+            .map(|t| t.as_ref())
             .collect()
     }
 }
 
+/// The classes of objects stored in the schema cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SchemaCacheClass {
+    Schema,
+    Table,
+    Column,
+    Function,
+    Type,
+    ForeignTable,
+    ForeignServer,
+    Index,
+    ForeignKey,
+    Policy,
+    Publication,
+    Subscription,
+}
+
+impl SchemaCacheClass {
+    pub const ALL: &'static [SchemaCacheClass] = &[
+        SchemaCacheClass::Schema,
+        SchemaCacheClass::Table,
+        SchemaCacheClass::Column,
+        SchemaCacheClass::Function,
+        SchemaCacheClass::Type,
+        SchemaCacheClass::ForeignTable,
+        SchemaCacheClass::ForeignServer,
+        SchemaCacheClass::Index,
+        SchemaCacheClass::ForeignKey,
+        SchemaCacheClass::Policy,
+        SchemaCacheClass::Publication,
+        SchemaCacheClass::Subscription,
+    ];
+
+    /// Maps an object type as reported by `pg_event_trigger_ddl_commands()` and
+    /// `pg_event_trigger_dropped_objects()` to the classes that have to be reloaded
+    pub fn from_object_type(object_type: &str) -> &'static [SchemaCacheClass] {
+        match object_type {
+            "schema" => &[SchemaCacheClass::Schema],
+            "table" => &[
+                SchemaCacheClass::Table,
+                SchemaCacheClass::Column,
+                SchemaCacheClass::Index,
+                SchemaCacheClass::ForeignKey,
+                SchemaCacheClass::Policy,
+            ],
+            "materialized view" => &[
+                SchemaCacheClass::Table,
+                SchemaCacheClass::Column,
+                SchemaCacheClass::Index,
+            ],
+            "view" => &[SchemaCacheClass::Table, SchemaCacheClass::Column],
+            // dropping a column drops its indexes and foreign keys
+            "table column" | "table constraint" => &[
+                SchemaCacheClass::Column,
+                SchemaCacheClass::Index,
+                SchemaCacheClass::ForeignKey,
+            ],
+            "index" => &[SchemaCacheClass::Column, SchemaCacheClass::Index],
+            "policy" => &[SchemaCacheClass::Policy],
+            "function" | "procedure" | "aggregate" => &[SchemaCacheClass::Function],
+            "type" | "domain" => &[SchemaCacheClass::Type],
+            "foreign table" => &[SchemaCacheClass::ForeignTable, SchemaCacheClass::Column],
+            "server" | "foreign-data wrapper" => &[SchemaCacheClass::ForeignServer],
+            "publication" | "publication relation" | "publication namespace" => {
+                &[SchemaCacheClass::Publication]
+            }
+            "subscription" => &[SchemaCacheClass::Subscription],
+            "extension" => SchemaCacheClass::ALL,
+            _ => &[],
+        }
+    }
+}
+
+/// Objects of some classes that were loaded from the database, which are applied to the cache as
+/// it is once they are loaded, see [`SchemaCache::apply_update`]
+///
+/// Loads run concurrently on snapshots of the cache, so applying only what they loaded keeps the
+/// changes that other loads made in the meantime.
+#[derive(Debug, Default)]
+pub struct SchemaCacheUpdate {
+    /// The classes that were loaded, whose objects replace those of the cache
+    pub classes: HashSet<SchemaCacheClass>,
+    /// The schemas whose objects were loaded, or `None` if they were loaded for all schemas
+    schemas: Option<HashSet<String>>,
+    /// Whether the schemas were not loaded before and are added to the loaded schemas
+    loads_schemas: bool,
+    objects: SchemaCache,
+}
+
+impl SchemaCacheUpdate {
+    /// The classes of objects that belong to a schema and are loaded on demand
+    const SCOPED_CLASSES: [SchemaCacheClass; 8] = [
+        SchemaCacheClass::Table,
+        SchemaCacheClass::Column,
+        SchemaCacheClass::Function,
+        SchemaCacheClass::Type,
+        SchemaCacheClass::ForeignTable,
+        SchemaCacheClass::Index,
+        SchemaCacheClass::ForeignKey,
+        SchemaCacheClass::Policy,
+    ];
+
+    /// Loads the objects of schemas that are not loaded yet, see [`SchemaCache::load_lazy`]
+    pub async fn load_schemas(pool: &PgPool, schemas: &[String]) -> SchemaCacheUpdate {
+        let (tables, columns, functions, types, foreign_tables, indexes, foreign_keys, policies) =
+            join!(
+                Table::load_in_schemas(pool, schemas),
+                Column::load_in_schemas(pool, schemas),
+                Function::load_in_schemas(pool, schemas),
+                PostgresType::load_in_schemas(pool, schemas),
+                ForeignTable::load_in_schemas(pool, schemas),
+                Index::load_in_schemas(pool, schemas),
+                ForeignKey::load_in_schemas(pool, schemas),
+                Policy::load_in_schemas(pool, schemas)
+            )
+            .await;
+
+        SchemaCacheUpdate {
+            classes: HashSet::from(Self::SCOPED_CLASSES),
+            schemas: Some(schemas.iter().cloned().collect()),
+            loads_schemas: true,
+            objects: SchemaCache {
+                tables,
+                columns,
+                functions,
+                types: types.into_iter().map(Arc::new).collect(),
+                foreign_tables,
+                indexes,
+                foreign_keys,
+                policies,
+                ..SchemaCache::default()
+            },
+        }
+    }
+
+    /// Takes the objects of the given classes from a loaded cache, e.g. of a refresh whose other
+    /// classes were replaced by a newer one in the meantime
+    pub fn from_cache(
+        objects: SchemaCache,
+        classes: HashSet<SchemaCacheClass>,
+    ) -> SchemaCacheUpdate {
+        SchemaCacheUpdate {
+            classes,
+            schemas: objects.loaded_schemas.clone(),
+            loads_schemas: false,
+            objects,
+        }
+    }
+
+    /// Loads the objects of the given classes in the schemas that are loaded, or in all schemas
+    /// if `loaded_schemas` is `None`
+    pub async fn load_classes(
+        pool: &PgPool,
+        classes: HashSet<SchemaCacheClass>,
+        loaded_schemas: Option<HashSet<String>>,
+    ) -> SchemaCacheUpdate {
+        let mut objects = SchemaCache::default();
+        for class in &classes {
+            match class {
+                SchemaCacheClass::Schema => objects.schemas = Schema::load(pool).await,
+                SchemaCacheClass::Table => {
+                    objects.tables = load_scoped::<Table>(pool, &loaded_schemas).await
+                }
+                SchemaCacheClass::Column => {
+                    objects.columns = load_scoped::<Column>(pool, &loaded_schemas).await
+                }
+                SchemaCacheClass::Function => {
+                    objects.functions = load_scoped::<Function>(pool, &loaded_schemas).await
+                }
+                SchemaCacheClass::Type => {
+                    objects.types = load_scoped::<PostgresType>(pool, &loaded_schemas)
+                        .await
+                        .into_iter()
+                        .map(Arc::new)
+                        .collect()
+                }
+                SchemaCacheClass::ForeignTable => {
+                    objects.foreign_tables =
+                        load_scoped::<ForeignTable>(pool, &loaded_schemas).await
+                }
+                SchemaCacheClass::ForeignServer => {
+                    objects.foreign_servers = ForeignServer::load(pool).await
+                }
+                SchemaCacheClass::Index => {
+                    objects.indexes = load_scoped::<Index>(pool, &loaded_schemas).await
+                }
+                SchemaCacheClass::ForeignKey => {
+                    objects.foreign_keys = load_scoped::<ForeignKey>(pool, &loaded_schemas).await
+                }
+                SchemaCacheClass::Policy => {
+                    objects.policies = load_scoped::<Policy>(pool, &loaded_schemas).await
+                }
+                SchemaCacheClass::Publication => {
+                    objects.publications = Publication::load(pool).await
+                }
+                SchemaCacheClass::Subscription => {
+                    objects.subscriptions = Subscription::load(pool).await
+                }
+            }
+        }
+
+        SchemaCacheUpdate {
+            classes,
+            schemas: loaded_schemas,
+            loads_schemas: false,
+            objects,
+        }
+    }
+}
+
+/// Replaces the objects of a class in the replaced schemas with the loaded ones of the schemas
+/// that are kept
+fn merge_scoped<T>(
+    items: &mut Vec<T>,
+    loaded: Vec<T>,
+    schema: impl Fn(&T) -> &str,
+    replaced: impl Fn(&str) -> bool,
+    kept: impl Fn(&str) -> bool,
+) {
+    items.retain(|item| !replaced(schema(item)));
+    items.extend(loaded.into_iter().filter(|item| kept(schema(item))));
+}
+
 pub trait SchemaCacheItem {
     type Item;
 
     async fn load(pool: &PgPool) -> Vec<Self::Item>;
 }
 
+/// An object class whose objects belong to a schema and can therefore be loaded per schema
+pub trait SchemaScopedCacheItem: SchemaCacheItem {
+    async fn load_in_schemas(pool: &PgPool, schemas: &[String]) -> Vec<Self::Item>;
+}
+
+async fn load_scoped<T: SchemaScopedCacheItem>(
+    pool: &PgPool,
+    loaded_schemas: &Option<HashSet<String>>,
+) -> Vec<T::Item> {
+    match loaded_schemas {
+        Some(schemas) => {
+            T::load_in_schemas(pool, &schemas.iter().cloned().collect::<Vec<_>>()).await
+        }
+        None => T::load(pool).await,
+    }
+}
+
+/// Returns all identifiers that are followed by a `.` token, but not those within string
+/// literals or comments. Sql that cannot be scanned, e.g. with an unterminated string while it
+/// is typed, has none.
+fn qualifiers(sql: &str) -> Vec<String> {
+    let tokens = pg_lexer::try_lex(sql)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|token| !WHITESPACE_TOKENS.contains(&token.kind))
+        .collect::<Vec<_>>();
+
+    tokens
+        .windows(2)
+        .filter(|pair| pair[1].kind == SyntaxKind::Ascii46)
+        .filter_map(|pair| identifier(&pair[0]))
+        .collect()
+}
+
+/// Returns the name of an identifier token, which is folded to lower case unless it is quoted.
+/// Keywords that are not reserved can be names as well, e.g. `data`.
+fn identifier(token: &Token) -> Option<String> {
+    match token.kind {
+        SyntaxKind::Ident => match token
+            .text
+            .strip_prefix('"')
+            .and_then(|text| text.strip_suffix('"'))
+        {
+            Some(quoted) => Some(quoted.replace("\"\"", "\"")),
+            None => Some(token.text.to_lowercase()),
+        },
+        _ if matches!(
+            token.token_type,
+            TokenType::UnreservedKeyword
+                | TokenType::ColNameKeyword
+                | TokenType::TypeFuncNameKeyword
+        ) =>
+        {
+            Some(token.text.to_lowercase())
+        }
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use sqlx::PgPool;
 
-    use crate::SchemaCache;
+    use crate::schemas::Schema;
+    use crate::{SchemaCache, SchemaCacheClass, SchemaCacheUpdate, Table};
+
+    fn tables_update(
+        schemas: Option<&[&str]>,
+        loads_schemas: bool,
+        tables: &[(&str, &str)],
+    ) -> SchemaCacheUpdate {
+        SchemaCacheUpdate {
+            classes: HashSet::from([SchemaCacheClass::Table]),
+            schemas: schemas.map(|s| s.iter().map(|s| s.to_string()).collect()),
+            loads_schemas,
+            objects: SchemaCache {
+                tables: tables
+                    .iter()
+                    .map(|(schema, name)| Table {
+                        schema: (*schema).into(),
+                        name: name.to_string(),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn table_names(cache: &SchemaCache) -> Vec<String> {
+        let mut names = cache
+            .tables
+            .iter()
+            .map(|t| format!("{}.{}", t.schema, t.name))
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
 
     #[test]
     fn test_schema_cache() {
@@ -87,4 +711,113 @@ mod tests {
 
         assert!(true);
     }
+
+    #[test]
+    fn test_load_lazy() {
+        let conn_string = std::env::var("DB_CONNECTION_STRING").unwrap();
+
+        let pool = async_std::task::block_on(PgPool::connect(conn_string.as_str())).unwrap();
+
+        let mut cache = async_std::task::block_on(SchemaCache::load_lazy(&pool, &[]));
+
+        assert!(cache.is_schema_loaded("public"));
+        assert!(!cache.is_schema_loaded("information_schema"));
+        assert!(cache
+            .find_table("sql_features", Some("information_schema"))
+            .is_none());
+
+        async_std::task::block_on(cache.load_schemas(&pool, &["information_schema".to_string()]));
+
+        assert!(cache.is_schema_loaded("information_schema"));
+        assert!(cache
+            .find_table("sql_features", Some("information_schema"))
+            .is_some());
+
+        // a reload keeps the schemas that were loaded on demand
+        let loaded_schemas: Vec<String> = cache.loaded_schemas.iter().flatten().cloned().collect();
+        let cache = async_std::task::block_on(SchemaCache::load_lazy(&pool, &loaded_schemas));
+        assert!(cache.is_schema_loaded("public"));
+        assert!(cache
+            .find_table("sql_features", Some("information_schema"))
+            .is_some());
+    }
+
+    #[test]
+    fn test_apply_updates() {
+        let mut cache = SchemaCache {
+            loaded_schemas: Some(HashSet::from(["public".to_string()])),
+            ..Default::default()
+        };
+        cache.apply_update(tables_update(
+            Some(&["public"]),
+            false,
+            &[("public", "users")],
+        ));
+
+        // both loads start from the same snapshot, and the second one finishes first
+        let analytics = tables_update(Some(&["analytics"]), true, &[("analytics", "events")]);
+        let sales = tables_update(Some(&["sales"]), true, &[("sales", "orders")]);
+        cache.apply_update(sales);
+        cache.apply_update(analytics);
+        assert_eq!(
+            table_names(&cache),
+            vec!["analytics.events", "public.users", "sales.orders"]
+        );
+        assert!(cache.is_schema_loaded("analytics") && cache.is_schema_loaded("sales"));
+
+        // a refresh of the schemas that were loaded when it started keeps the others
+        cache.apply_update(tables_update(
+            Some(&["public"]),
+            false,
+            &[("public", "accounts")],
+        ));
+        assert_eq!(
+            table_names(&cache),
+            vec!["analytics.events", "public.accounts", "sales.orders"]
+        );
+
+        // objects of schemas that are not loaded are skipped
+        cache.loaded_schemas = Some(HashSet::from(["public".to_string()]));
+        cache.apply_update(tables_update(
+            None,
+            false,
+            &[("public", "accounts"), ("analytics", "events")],
+        ));
+        assert_eq!(table_names(&cache), vec!["public.accounts"]);
+    }
+
+    #[test]
+    fn test_unloaded_schema_references() {
+        let schema = |name: &str| Schema {
+            name: name.to_string(),
+            ..Default::default()
+        };
+
+        let cache = SchemaCache {
+            schemas: vec![schema("public"), schema("analytics"), schema("Sales")],
+            loaded_schemas: Some(HashSet::from(["public".to_string()])),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            cache.unloaded_schema_references(
+                "select * from public.users u join analytics.events e on e.user_id = u.id join \"Sales\".orders using (id) join Analytics.sessions using (id) join unknown.x using (id)"
+            ),
+            vec!["analytics".to_string(), "Sales".to_string()]
+        );
+
+        // names within string literals and comments are not qualifiers
+        assert!(cache
+            .unloaded_schema_references(
+                "select 'analytics.x', $$ Sales.orders $$ from public.users -- see analytics.events\n/* analytics.sessions */"
+            )
+            .is_empty());
+        assert_eq!(
+            cache.unloaded_schema_references("select * from analytics . events"),
+            vec!["analytics".to_string()]
+        );
+        assert!(cache
+            .unloaded_schema_references("select 'analytics.x from analytics.events")
+            .is_empty());
+    }
 }
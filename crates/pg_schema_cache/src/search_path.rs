@@ -0,0 +1,235 @@
+//! Resolves unqualified names the way Postgres does
+//!
+//! The rules follow `recomputeNamespacePath` in `namespace.c`: the schemas of the `search_path` are
+//! searched in order and the first match wins. `pg_catalog` is searched before the path unless it
+//! is listed explicitly, and so is the session's temporary schema, except for functions, which are
+//! never looked up in the temporary schema.
+
+use crate::{types::PostgresType, ForeignTable, Function, SchemaCache, Table};
+
+/// The `search_path` element that refers to the temporary schema of the session
+const TEMP_SCHEMA_ALIAS: &str = "pg_temp";
+
+const TEMP_SCHEMA_PREFIX: &str = "pg_temp_";
+
+const CATALOG_SCHEMA: &str = "pg_catalog";
+
+/// A relation, i.e. an object stored in `pg_class`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Relation<'a> {
+    Table(&'a Table),
+    ForeignTable(&'a ForeignTable),
+}
+
+impl<'a> Relation<'a> {
+    pub fn id(&self) -> i64 {
+        match self {
+            Relation::Table(t) => t.id,
+            Relation::ForeignTable(t) => t.id,
+        }
+    }
+
+    pub fn name(&self) -> &'a str {
+        match self {
+            Relation::Table(t) => &t.name,
+            Relation::ForeignTable(t) => &t.name,
+        }
+    }
+
+    pub fn schema(&self) -> &'a str {
+        match self {
+            Relation::Table(t) => &t.schema,
+            Relation::ForeignTable(t) => &t.schema,
+        }
+    }
+}
+
+impl SchemaCache {
+    /// Returns the schemas that are searched for unqualified names, in search order
+    ///
+    /// `$user` is skipped because the cache does not know the current user. Paths read from a
+    /// session via `current_schemas` have it expanded already.
+    pub fn effective_search_path<'a>(
+        &'a self,
+        search_path: &'a [String],
+        include_temp: bool,
+    ) -> Vec<&'a str> {
+        let temp_schema = self.temp_schema();
+        let mut path: Vec<&str> = Vec::new();
+
+        for schema in search_path {
+            let schema = match schema.as_str() {
+                "$user" => continue,
+                TEMP_SCHEMA_ALIAS => match temp_schema {
+                    Some(temp_schema) => temp_schema,
+                    None => continue,
+                },
+                schema => schema,
+            };
+
+            if schema.starts_with(TEMP_SCHEMA_PREFIX) && !include_temp {
+                continue;
+            }
+
+            if !path.contains(&schema) {
+                path.push(schema);
+            }
+        }
+
+        if !path.contains(&CATALOG_SCHEMA) {
+            path.insert(0, CATALOG_SCHEMA);
+        }
+
+        if let Some(temp_schema) = temp_schema {
+            if include_temp && !path.contains(&temp_schema) {
+                path.insert(0, temp_schema);
+            }
+        }
+
+        path
+    }
+
+    /// Resolves an unqualified relation name, returning the first match along the search path
+    pub fn resolve(&self, name: &str, search_path: &[String]) -> Option<Relation<'_>> {
+        self.effective_search_path(search_path, true)
+            .into_iter()
+            .find_map(|schema| {
+                self.find_table(name, Some(schema))
+                    .map(Relation::Table)
+                    .or_else(|| {
+                        self.find_foreign_table(name, Some(schema))
+                            .map(Relation::ForeignTable)
+                    })
+            })
+    }
+
+    /// Resolves an unqualified type name, returning the first match along the search path
+    pub fn resolve_type(&self, name: &str, search_path: &[String]) -> Option<&PostgresType> {
+        self.effective_search_path(search_path, true)
+            .into_iter()
+            .find_map(|schema| self.find_type(name, Some(schema)))
+    }
+
+    /// Resolves an unqualified function name, returning all overloads that are visible
+    ///
+    /// An overload is hidden by an overload with the same argument types in a schema that comes
+    /// earlier in the search path.
+    pub fn resolve_functions(&self, name: &str, search_path: &[String]) -> Vec<&Function> {
+        let mut functions: Vec<&Function> = Vec::new();
+
+        for schema in self.effective_search_path(search_path, false) {
+            for function in self
+                .functions
+                .iter()
+                .filter(|f| f.name.as_deref() == Some(name) && f.schema.as_deref() == Some(schema))
+            {
+                if !functions
+                    .iter()
+                    .any(|f| input_type_ids(f) == input_type_ids(function))
+                {
+                    functions.push(function);
+                }
+            }
+        }
+
+        functions
+    }
+
+    /// Returns the temporary schema of the session the cache was loaded with, if it has any
+    /// objects
+    ///
+    /// The temporary schemas of other sessions are never loaded into the cache.
+    fn temp_schema(&self) -> Option<&str> {
+        self.tables
+            .iter()
+            .map(|t| t.schema.as_str())
+            .chain(self.foreign_tables.iter().map(|t| t.schema.as_str()))
+            .chain(self.types.iter().map(|t| t.schema.as_str()))
+            .find(|s| s.starts_with(TEMP_SCHEMA_PREFIX))
+    }
+}
+
+/// The types of the arguments that identify a function, i.e. all but its output arguments
+fn input_type_ids(function: &Function) -> Vec<i64> {
+    function
+        .args
+        .args
+        .iter()
+        .filter(|a| !matches!(a.mode.as_str(), "out" | "table"))
+        .map(|a| a.type_id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Relation, SchemaCache};
+
+    fn path(schemas: &[&str]) -> Vec<String> {
+        schemas.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_effective_search_path() {
+        let cache = SchemaCache::from_sql(
+            "create table pg_temp_3.scratch (id int); create table public.users (id int);",
+        );
+
+        assert_eq!(
+            cache.effective_search_path(&path(&["$user", "public"]), true),
+            vec!["pg_temp_3", "pg_catalog", "public"]
+        );
+        assert_eq!(
+            cache.effective_search_path(&path(&["public", "pg_temp", "pg_catalog"]), true),
+            vec!["public", "pg_temp_3", "pg_catalog"]
+        );
+        assert_eq!(
+            cache.effective_search_path(&path(&["public", "pg_temp"]), false),
+            vec!["pg_catalog", "public"]
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let cache = SchemaCache::from_sql(
+            "
+            create schema app;
+            create table app.users (id int);
+            create table public.users (id int);
+            create table pg_temp_3.users (id int);
+            create table app.events (id int);
+            create type app.mood as enum ('happy');
+            create type public.mood as enum ('sad');
+            create function app.add(a int, b int) returns int language sql as 'select a + b';
+            create function public.add(a int, b int) returns int language sql as 'select a + b';
+            create function public.add(a int) returns int language sql as 'select a';
+            ",
+        );
+
+        let resolved = cache.resolve("users", &path(&["app", "public"])).unwrap();
+        assert_eq!(resolved.schema(), "pg_temp_3");
+
+        let resolved = cache
+            .resolve("users", &path(&["app", "pg_temp", "public"]))
+            .unwrap();
+        assert_eq!(resolved.schema(), "app");
+
+        assert!(matches!(
+            cache.resolve("events", &path(&["public", "app"])),
+            Some(Relation::Table(t)) if t.schema == "app"
+        ));
+        assert!(cache.resolve("events", &path(&["public"])).is_none());
+
+        assert_eq!(
+            cache
+                .resolve_type("mood", &path(&["public", "app"]))
+                .unwrap()
+                .schema,
+            "public"
+        );
+
+        let functions = cache.resolve_functions("add", &path(&["app", "public"]));
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].schema.as_deref(), Some("app"));
+        assert_eq!(functions[1].args.args.len(), 1);
+    }
+}
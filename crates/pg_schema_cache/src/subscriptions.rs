@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::schema_cache::SchemaCacheItem;
+
+/// A logical replication subscription of the current database
+///
+/// The connection info is not loaded since it may contain credentials and is only readable by
+/// superusers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Subscription {
+    pub id: i64,
+    pub name: String,
+    pub enabled: bool,
+    /// The names of the publications on the publisher the subscription is subscribed to
+    pub publications: Vec<String>,
+}
+
+impl SchemaCacheItem for Subscription {
+    type Item = Subscription;
+
+    async fn load(pool: &PgPool) -> Vec<Subscription> {
+        sqlx::query_file_as!(Subscription, "src/queries/subscriptions.sql")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+}
@@ -1,8 +1,11 @@
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
-use crate::schema_cache::SchemaCacheItem;
+use crate::ident::Ident;
+use crate::schema_cache::{SchemaCacheItem, SchemaScopedCacheItem};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
 pub enum ReplicaIdentity {
     Default,
     Index,
@@ -28,10 +31,10 @@ impl From<String> for ReplicaIdentity {
     }
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Table {
     pub id: i64,
-    pub schema: String,
+    pub schema: Ident,
     pub name: String,
     pub rls_enabled: bool,
     pub rls_forced: bool,
@@ -40,58 +43,45 @@ pub struct Table {
     pub size: String,
     pub live_rows_estimate: i64,
     pub dead_rows_estimate: i64,
+    /// The planner's estimate of the number of rows, or -1 if the table was never analyzed
+    pub row_estimate: i64,
     pub comment: Option<String>,
+    /// Whether the table is a partitioned table (relkind `p`)
+    pub is_partitioned: bool,
+    /// The partition key definition, e.g. `RANGE (created_at)`, if the table is partitioned
+    pub partition_key: Option<String>,
+    /// The id of the parent table if the table is a partition
+    pub partition_of: Option<i64>,
+    /// The partition bound expression, e.g. `FOR VALUES FROM (...) TO (...)`, if the table is a
+    /// partition
+    pub partition_bound: Option<String>,
+}
+
+impl Table {
+    pub fn is_partition(&self) -> bool {
+        self.partition_of.is_some()
+    }
 }
 
 impl SchemaCacheItem for Table {
     type Item = Table;
 
     async fn load(pool: &PgPool) -> Vec<Table> {
-        sqlx::query_as!(
-            Table,
-            r#"SELECT
-  c.oid :: int8 AS "id!",
-  nc.nspname AS schema,
-  c.relname AS name,
-  c.relrowsecurity AS rls_enabled,
-  c.relforcerowsecurity AS rls_forced,
-  CASE
-    WHEN c.relreplident = 'd' THEN 'DEFAULT'
-    WHEN c.relreplident = 'i' THEN 'INDEX'
-    WHEN c.relreplident = 'f' THEN 'FULL'
-    ELSE 'NOTHING'
-  END AS "replica_identity!",
-  pg_total_relation_size(format('%I.%I', nc.nspname, c.relname)) :: int8 AS "bytes!",
-  pg_size_pretty(
-    pg_total_relation_size(format('%I.%I', nc.nspname, c.relname))
-  ) AS "size!",
-  pg_stat_get_live_tuples(c.oid) AS "live_rows_estimate!",
-  pg_stat_get_dead_tuples(c.oid) AS "dead_rows_estimate!",
-  obj_description(c.oid) AS comment
-FROM
-  pg_namespace nc
-  JOIN pg_class c ON nc.oid = c.relnamespace
-WHERE
-  c.relkind IN ('r', 'p')
-  AND NOT pg_is_other_temp_schema(nc.oid)
-  AND (
-    pg_has_role(c.relowner, 'USAGE')
-    OR has_table_privilege(
-      c.oid,
-      'SELECT, INSERT, UPDATE, DELETE, TRUNCATE, REFERENCES, TRIGGER'
-    )
-    OR has_any_column_privilege(c.oid, 'SELECT, INSERT, UPDATE, REFERENCES')
-  )
-group by
-  c.oid,
-  c.relname,
-  c.relrowsecurity,
-  c.relforcerowsecurity,
-  c.relreplident,
-  nc.nspname"#
-        )
-        .fetch_all(pool)
-        .await
-        .unwrap()
+        Table::query(pool, None).await
+    }
+}
+
+impl SchemaScopedCacheItem for Table {
+    async fn load_in_schemas(pool: &PgPool, schemas: &[String]) -> Vec<Table> {
+        Table::query(pool, Some(schemas)).await
+    }
+}
+
+impl Table {
+    async fn query(pool: &PgPool, schemas: Option<&[String]>) -> Vec<Table> {
+        sqlx::query_file_as!(Table, "src/queries/tables.sql", schemas)
+            .fetch_all(pool)
+            .await
+            .unwrap()
     }
 }
@@ -1,18 +1,20 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sqlx::types::JsonValue;
 use sqlx::PgPool;
 
-use crate::schema_cache::SchemaCacheItem;
+use crate::ident::Ident;
+use crate::schema_cache::{SchemaCacheItem, SchemaScopedCacheItem};
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct TypeAttributes {
-    attrs: Vec<PostgresTypeAttribute>,
+    pub attrs: Vec<PostgresTypeAttribute>,
 }
 
-#[derive(Debug, Clone, Default, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PostgresTypeAttribute {
-    name: String,
-    type_id: i64,
+    pub name: String,
+    pub type_id: i64,
 }
 
 impl From<Option<JsonValue>> for TypeAttributes {
@@ -23,7 +25,8 @@ impl From<Option<JsonValue>> for TypeAttributes {
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Enums {
     pub values: Vec<String>,
 }
@@ -36,11 +39,11 @@ impl From<Option<JsonValue>> for Enums {
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PostgresType {
     pub id: i64,
     pub name: String,
-    pub schema: String,
+    pub schema: Ident,
     pub format: String,
     pub enums: Enums,
     pub attributes: TypeAttributes,
@@ -51,58 +54,21 @@ impl SchemaCacheItem for PostgresType {
     type Item = PostgresType;
 
     async fn load(pool: &PgPool) -> Vec<PostgresType> {
-        sqlx::query_as!(
-            PostgresType,
-            r#"select
-  t.oid::int8 as "id!",
-  t.typname as name,
-  n.nspname as "schema!",
-  format_type (t.oid, null) as "format!",
-  coalesce(t_enums.enums, '[]') as enums,
-  coalesce(t_attributes.attributes, '[]') as attributes,
-  obj_description (t.oid, 'pg_type') as comment
-from
-  pg_type t
-  left join pg_namespace n on n.oid = t.typnamespace
-  left join (
-    select
-      enumtypid,
-      jsonb_agg(enumlabel order by enumsortorder) as enums
-    from
-      pg_enum
-    group by
-      enumtypid
-  ) as t_enums on t_enums.enumtypid = t.oid
-  left join (
-    select
-      oid,
-      jsonb_agg(
-        jsonb_build_object('name', a.attname, 'type_id', a.atttypid::int8)
-        order by a.attnum asc
-      ) as attributes
-    from
-      pg_class c
-      join pg_attribute a on a.attrelid = c.oid
-    where
-      c.relkind = 'c' and not a.attisdropped
-    group by
-      c.oid
-  ) as t_attributes on t_attributes.oid = t.typrelid
-where
-  (
-    t.typrelid = 0
-    or (
-      select
-        c.relkind = 'c'
-      from
-        pg_class c
-      where
-        c.oid = t.typrelid
-    )
-  )"#
-        )
-        .fetch_all(pool)
-        .await
-        .unwrap()
+        PostgresType::query(pool, None).await
+    }
+}
+
+impl SchemaScopedCacheItem for PostgresType {
+    async fn load_in_schemas(pool: &PgPool, schemas: &[String]) -> Vec<PostgresType> {
+        PostgresType::query(pool, Some(schemas)).await
+    }
+}
+
+impl PostgresType {
+    async fn query(pool: &PgPool, schemas: Option<&[String]>) -> Vec<PostgresType> {
+        sqlx::query_file_as!(PostgresType, "src/queries/types.sql", schemas)
+            .fetch_all(pool)
+            .await
+            .unwrap()
     }
 }
@@ -0,0 +1,83 @@
+//! A hash of the catalog state held by the schema cache
+//!
+//! Consumers that derive results from the schema, e.g. the typechecker, can store the hash next
+//! to their results and drop them only when the hash changes.
+
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::SchemaCache;
+
+/// Fields that change without the schema changing
+const STATISTICS: &[&str] = &[
+    "bytes",
+    "size",
+    "live_rows_estimate",
+    "dead_rows_estimate",
+    "row_estimate",
+];
+
+impl SchemaCache {
+    /// Returns a hex encoded hash of the objects in the cache
+    ///
+    /// The hash does not depend on the order in which objects were loaded and ignores statistics
+    /// such as table sizes and row estimates, so it only changes when the schema changes.
+    pub fn version_hash(&self) -> String {
+        let mut entries = Vec::new();
+
+        add_entries(&mut entries, "schema", &self.schemas);
+        add_entries(&mut entries, "table", &self.tables);
+        add_entries(&mut entries, "column", &self.columns);
+        add_entries(&mut entries, "function", &self.functions);
+        add_entries(&mut entries, "type", &self.types);
+        add_entries(&mut entries, "foreign_table", &self.foreign_tables);
+        add_entries(&mut entries, "foreign_server", &self.foreign_servers);
+        add_entries(&mut entries, "index", &self.indexes);
+        add_entries(&mut entries, "foreign_key", &self.foreign_keys);
+        add_entries(&mut entries, "policy", &self.policies);
+        add_entries(&mut entries, "publication", &self.publications);
+        add_entries(&mut entries, "subscription", &self.subscriptions);
+        add_entries(&mut entries, "search_path", &[&self.search_path]);
+
+        entries.sort_unstable();
+
+        let mut hasher = Sha256::new();
+        for entry in entries {
+            hasher.update(entry.as_bytes());
+            hasher.update(b"\n");
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+fn add_entries<T: Serialize>(entries: &mut Vec<String>, class: &str, items: &[T]) {
+    entries.extend(items.iter().map(|item| {
+        let mut value = serde_json::to_value(item).unwrap();
+        if let Value::Object(obj) = &mut value {
+            for key in STATISTICS {
+                obj.remove(*key);
+            }
+        }
+        format!("{}:{}", class, value)
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SchemaCache;
+
+    #[test]
+    fn test_version_hash() {
+        let mut cache =
+            SchemaCache::from_sql("create table users (id int); create table events (id int);");
+        let hash = cache.version_hash();
+
+        cache.tables.reverse();
+        cache.tables[0].live_rows_estimate = 42;
+        assert_eq!(cache.version_hash(), hash);
+
+        cache.apply_sql("comment on table users is 'All users';");
+        assert_ne!(cache.version_hash(), hash);
+    }
+}
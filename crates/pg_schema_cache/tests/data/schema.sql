@@ -0,0 +1,150 @@
+--
+-- PostgreSQL database dump
+--
+
+\restrict flYZ14gjfAYq4zougWBmR7KAVodJldbAymcjFsU9VRVVUTlPhDRwXb64iPgnG1E
+
+-- Dumped from database version 15.18 (Debian 15.18-0+deb12u1)
+-- Dumped by pg_dump version 15.18 (Debian 15.18-0+deb12u1)
+
+SET statement_timeout = 0;
+SET lock_timeout = 0;
+SET idle_in_transaction_session_timeout = 0;
+SET client_encoding = 'SQL_ASCII';
+SET standard_conforming_strings = on;
+SELECT pg_catalog.set_config('search_path', '', false);
+SET check_function_bodies = false;
+SET xmloption = content;
+SET client_min_messages = warning;
+SET row_security = off;
+
+--
+-- Name: app; Type: SCHEMA; Schema: -; Owner: postgres
+--
+
+CREATE SCHEMA app;
+
+
+ALTER SCHEMA app OWNER TO postgres;
+
+--
+-- Name: mood; Type: TYPE; Schema: app; Owner: postgres
+--
+
+CREATE TYPE app.mood AS ENUM (
+    'happy',
+    'sad'
+);
+
+
+ALTER TYPE app.mood OWNER TO postgres;
+
+--
+-- Name: add(integer, integer); Type: FUNCTION; Schema: app; Owner: postgres
+--
+
+CREATE FUNCTION app.add(a integer, b integer DEFAULT 1) RETURNS integer
+    LANGUAGE sql IMMUTABLE
+    AS $$select a + b$$;
+
+
+ALTER FUNCTION app.add(a integer, b integer) OWNER TO postgres;
+
+SET default_tablespace = '';
+
+--
+-- Name: events; Type: TABLE; Schema: app; Owner: postgres
+--
+
+CREATE TABLE app.events (
+    id bigint,
+    created_at timestamp with time zone
+)
+PARTITION BY RANGE (created_at);
+
+
+ALTER TABLE app.events OWNER TO postgres;
+
+SET default_table_access_method = heap;
+
+--
+-- Name: events_2024; Type: TABLE; Schema: app; Owner: postgres
+--
+
+CREATE TABLE app.events_2024 (
+    id bigint,
+    created_at timestamp with time zone
+);
+
+
+ALTER TABLE app.events_2024 OWNER TO postgres;
+
+--
+-- Name: users; Type: TABLE; Schema: app; Owner: postgres
+--
+
+CREATE TABLE app.users (
+    id integer NOT NULL,
+    email text NOT NULL
+);
+
+
+ALTER TABLE app.users OWNER TO postgres;
+
+--
+-- Name: TABLE users; Type: COMMENT; Schema: app; Owner: postgres
+--
+
+COMMENT ON TABLE app.users IS 'All users';
+
+
+--
+-- Name: users_id_seq; Type: SEQUENCE; Schema: app; Owner: postgres
+--
+
+CREATE SEQUENCE app.users_id_seq
+    AS integer
+    START WITH 1
+    INCREMENT BY 1
+    NO MINVALUE
+    NO MAXVALUE
+    CACHE 1;
+
+
+ALTER TABLE app.users_id_seq OWNER TO postgres;
+
+--
+-- Name: users_id_seq; Type: SEQUENCE OWNED BY; Schema: app; Owner: postgres
+--
+
+ALTER SEQUENCE app.users_id_seq OWNED BY app.users.id;
+
+
+--
+-- Name: events_2024; Type: TABLE ATTACH; Schema: app; Owner: postgres
+--
+
+ALTER TABLE ONLY app.events ATTACH PARTITION app.events_2024 FOR VALUES FROM ('2024-01-01 00:00:00+00') TO ('2025-01-01 00:00:00+00');
+
+
+--
+-- Name: users id; Type: DEFAULT; Schema: app; Owner: postgres
+--
+
+ALTER TABLE ONLY app.users ALTER COLUMN id SET DEFAULT nextval('app.users_id_seq'::regclass);
+
+
+--
+-- Name: users users_pkey; Type: CONSTRAINT; Schema: app; Owner: postgres
+--
+
+ALTER TABLE ONLY app.users
+    ADD CONSTRAINT users_pkey PRIMARY KEY (id);
+
+
+--
+-- PostgreSQL database dump complete
+--
+
+\unrestrict flYZ14gjfAYq4zougWBmR7KAVodJldbAymcjFsU9VRVVUTlPhDRwXb64iPgnG1E
+
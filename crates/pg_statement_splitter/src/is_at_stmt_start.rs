@@ -134,6 +134,18 @@ pub static STATEMENT_START_TOKEN_MAPS: LazyLock<Vec<HashMap<SyntaxKind, Vec<Toke
             ],
         ));
 
+        m.push((
+            SyntaxKind::RenameStmt,
+            &[
+                SyntaxToken::Required(SyntaxKind::Alter),
+                SyntaxToken::Required(SyntaxKind::Index),
+                SyntaxToken::Optional(SyntaxKind::IfP),
+                SyntaxToken::Optional(SyntaxKind::Exists),
+                SyntaxToken::Required(SyntaxKind::Ident),
+                SyntaxToken::Required(SyntaxKind::Rename),
+            ],
+        ));
+
         m.push((
             SyntaxKind::AlterDomainStmt,
             &[
@@ -151,6 +163,17 @@ pub static STATEMENT_START_TOKEN_MAPS: LazyLock<Vec<HashMap<SyntaxKind, Vec<Toke
             ],
         ));
 
+        // GRANT and REVOKE of roles start like those of privileges, which is enough to split them
+        m.push((
+            SyntaxKind::GrantStmt,
+            &[SyntaxToken::Required(SyntaxKind::Grant)],
+        ));
+
+        m.push((
+            SyntaxKind::GrantStmt,
+            &[SyntaxToken::Required(SyntaxKind::Revoke)],
+        ));
+
         m.push((
             SyntaxKind::ClusterStmt,
             &[SyntaxToken::Required(SyntaxKind::Cluster)],
@@ -959,8 +982,7 @@ pub static STATEMENT_START_TOKEN_MAPS: LazyLock<Vec<HashMap<SyntaxKind, Vec<Toke
 // SetOperationStmt,
 //
 // TODO: parsing ambiguity, check docs for solution
-// GrantStmt(super::GrantStmt),
-// GrantRoleStmt(super::GrantRoleStmt),
+// GrantRoleStmt(super::GrantRoleStmt), which is split as a GrantStmt
 // ClosePortalStmt,
 // CreatePlangStmt,
 // AlterRoleSetStmt,
@@ -979,24 +1001,39 @@ pub static STATEMENT_START_TOKEN_MAPS: LazyLock<Vec<HashMap<SyntaxKind, Vec<Toke
 
 /// Returns the statement at which the parser is currently at, if any
 pub fn is_at_stmt_start(parser: &mut Parser) -> Option<SyntaxKind> {
-    let mut options = Vec::new();
+    let mut options: Vec<TokenStatement> = Vec::new();
     for i in 0..STATEMENT_START_TOKEN_MAPS.len() {
         // important, else infinite loop: only ignore whitespaces after first token
         let token = parser.nth(i, i != 0).kind;
-        if let Some(result) = STATEMENT_START_TOKEN_MAPS[i].get(&token) {
-            if i == 0 {
-                options = result.clone();
-            } else {
-                options = result
-                    .iter()
-                    .filter(|o| options.contains(o))
-                    .cloned()
-                    .collect();
+        let result = STATEMENT_START_TOKEN_MAPS[i].get(&token).map(|result| {
+            result
+                .iter()
+                .filter(|o| i == 0 || options.contains(*o))
+                .cloned()
+                .collect::<Vec<_>>()
+        });
+        match result {
+            Some(mut result) if !result.is_empty() || i == 0 => {
+                // a statement with several start sequences, e.g. `ALTER TABLE x RENAME` and
+                // `ALTER INDEX x RENAME`, is a single option
+                let mut seen: Vec<TokenStatement> = Vec::new();
+                result.retain(|o| {
+                    let is_new = !seen
+                        .iter()
+                        .any(|s| s.is_eos() == o.is_eos() && s.kind() == o.kind());
+                    if is_new {
+                        seen.push(o.clone());
+                    }
+                    is_new
+                });
+                options = result;
+            }
+            _ => {
+                // no statement continues with the token, so filter the options for all
+                // statements that are complete at this point, e.g. `set role` is a `set`, and
+                // `security definer` within a function is not a `security label`
+                options.retain(|o| o.is_eos());
             }
-        } else if options.len() > 1 {
-            // no result is found, and there is currently more than one option
-            // filter the options for all statements that are complete at this point
-            options.retain(|o| o.is_eos());
         }
 
         if options.len() == 0 {
@@ -20,6 +20,7 @@ use parser::{Parse, Parser};
 use pg_lexer::{lex, SyntaxKind};
 
 pub fn split(sql: &str) -> Parse {
+    let _span = tracing::debug_span!("split").entered();
     let mut parser = Parser::new(lex(sql));
 
     while !parser.eof() {
@@ -52,6 +53,15 @@ pub fn split(sql: &str) -> Parse {
                 let mut is_sub_stmt = 0;
                 let mut is_sub_trx = 0;
                 let mut ignore_next_non_whitespace = false;
+                let is_update = stmt == SyntaxKind::UpdateStmt;
+                let is_function = matches!(
+                    stmt,
+                    SyntaxKind::CreateFunctionStmt | SyntaxKind::AlterFunctionStmt
+                );
+                let is_grant = matches!(
+                    stmt,
+                    SyntaxKind::GrantStmt | SyntaxKind::AlterDefaultPrivilegesStmt
+                );
                 while !parser.at(SyntaxKind::Ascii59) && !parser.eof() {
                     match parser.nth(0, false).kind {
                         SyntaxKind::All => {
@@ -77,6 +87,11 @@ pub fn split(sql: &str) -> Parse {
                             is_sub_stmt -= 1;
                             parser.advance();
                         }
+                        SyntaxKind::Set if is_update || is_function => {
+                            // the SET of an UPDATE or of the options of a function is not a SET
+                            // statement
+                            parser.advance();
+                        }
                         SyntaxKind::As
                         | SyntaxKind::Union
                         | SyntaxKind::Intersect
@@ -87,8 +102,10 @@ pub fn split(sql: &str) -> Parse {
                         }
                         _ => {
                             // if another stmt FIRST is encountered, break
-                            // ignore if parsing sub stmt
+                            // ignore if parsing sub stmt, or a grant whose privileges, e.g.
+                            // SELECT, and actions, e.g. GRANT, look like statements
                             if ignore_next_non_whitespace == false
+                                && !is_grant
                                 && is_sub_stmt == 0
                                 && is_sub_trx == 0
                                 && is_at_stmt_start(&mut parser).is_some()
@@ -134,4 +151,78 @@ mod tests {
             input[res.ranges[2]].to_string()
         );
     }
+
+    #[test]
+    fn test_splitter_update() {
+        let input = "update contact set username = $1 where id = $2;\nset search_path = public;";
+
+        let res = split(input);
+        assert_eq!(res.ranges.len(), 2);
+        assert_eq!(
+            "update contact set username = $1 where id = $2;",
+            input[res.ranges[0]].to_string()
+        );
+        assert_eq!(
+            "set search_path = public;",
+            input[res.ranges[1]].to_string()
+        );
+    }
+
+    #[test]
+    fn test_splitter_function_options() {
+        let input = "create function f() returns int language sql security definer set search_path = '' as 'select 1';\nalter function f() set search_path = public;\nset role admin;";
+
+        let res = split(input);
+        assert_eq!(res.ranges.len(), 3);
+        assert_eq!(
+            "create function f() returns int language sql security definer set search_path = '' as 'select 1';",
+            input[res.ranges[0]].to_string()
+        );
+        assert_eq!(
+            "alter function f() set search_path = public;",
+            input[res.ranges[1]].to_string()
+        );
+        assert_eq!("set role admin;", input[res.ranges[2]].to_string());
+    }
+
+    #[test]
+    fn test_splitter_grants() {
+        let input = "grant select on users to app with grant option;
+revoke grant option for select on users from app;
+alter default privileges in schema api revoke all on tables from public;
+grant admin to app;";
+
+        let res = split(input);
+        assert_eq!(res.ranges.len(), 4);
+        assert_eq!(
+            "grant select on users to app with grant option;",
+            input[res.ranges[0]].to_string()
+        );
+        assert_eq!(
+            "revoke grant option for select on users from app;",
+            input[res.ranges[1]].to_string()
+        );
+        assert_eq!(
+            "alter default privileges in schema api revoke all on tables from public;",
+            input[res.ranges[2]].to_string()
+        );
+        assert_eq!("grant admin to app;", input[res.ranges[3]].to_string());
+    }
+
+    #[test]
+    fn test_splitter_renames() {
+        let input = "alter index users_email_idx rename to accounts_email_idx;
+alter table users rename to accounts;";
+
+        let res = split(input);
+        assert_eq!(res.ranges.len(), 2);
+        assert_eq!(
+            "alter index users_email_idx rename to accounts_email_idx;",
+            input[res.ranges[0]].to_string()
+        );
+        assert_eq!(
+            "alter table users rename to accounts;",
+            input[res.ranges[1]].to_string()
+        );
+    }
 }
@@ -385,7 +385,7 @@ impl<'p> StatementParser<'p> {
 
 /// list of aliases from https://www.postgresql.org/docs/current/datatype.html
 /// NOTE: support for multi-word alias (e.g. time with time zone) requires parser change
-const ALIASES: [&[&str]; 10] = [
+const ALIASES: [&[&str]; 11] = [
     &["bigint", "int8"],
     &["bigserial", "serial8"],
     &["boolean", "bool"],
@@ -396,6 +396,8 @@ const ALIASES: [&[&str]; 10] = [
     &["smallint", "int2"],
     &["smallserial", "serial2"],
     &["serial", "serial4"],
+    // the parser turns `!=` into `<>`
+    &["<>", "!="],
 ];
 
 fn cmp_tokens(p: &TokenProperty, token: &Token) -> bool {
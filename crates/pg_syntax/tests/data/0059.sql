@@ -0,0 +1,8 @@
+CREATE ROLE app LOGIN PASSWORD 'secret';
+ALTER ROLE app WITH PASSWORD NULL;
+CREATE USER MAPPING FOR app SERVER remote OPTIONS (user 'app', password 'secret');
+ALTER DEFAULT PRIVILEGES IN SCHEMA api GRANT ALL ON TABLES TO PUBLIC;
+GRANT CREATE ON SCHEMA api TO PUBLIC;
+CREATE FUNCTION is_admin() RETURNS bool LANGUAGE sql SECURITY DEFINER SET search_path = '' AS 'select true';
+GRANT SELECT, INSERT ON TABLE users TO app WITH GRANT OPTION;
+REVOKE admin FROM app CASCADE;
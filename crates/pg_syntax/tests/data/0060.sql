@@ -0,0 +1,6 @@
+SELECT id FROM users WHERE email LIKE '%@example.com';
+SELECT id FROM users WHERE name NOT ILIKE 'a%';
+SELECT id FROM users WHERE team_id NOT IN (SELECT team_id FROM banned);
+SELECT id FROM users WHERE created_at::date = current_date;
+SELECT id FROM users WHERE team_id IS DISTINCT FROM 1;
+SELECT id FROM users WHERE team_id NOT IN (1, 2);
@@ -0,0 +1,2 @@
+SELECT id FROM users WHERE id != 1;
+SELECT id FROM users WHERE id <> 1 AND name != $1;
@@ -0,0 +1,5 @@
+ALTER TABLE users RENAME COLUMN email TO "Email";
+ALTER TABLE IF EXISTS users RENAME TO accounts;
+ALTER TABLE users RENAME CONSTRAINT users_pkey TO accounts_pkey;
+ALTER INDEX users_email_idx RENAME TO accounts_email_idx;
+ALTER FUNCTION get_user(int) RENAME TO get_account;
@@ -0,0 +1,2 @@
+CREATE TABLE orders (id bigint GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY, note text CHECK (char_length(note) <= 200));
+ALTER TABLE orders ADD COLUMN position integer GENERATED ALWAYS AS IDENTITY;
@@ -1,5 +1,7 @@
 mod functions;
+mod scope;
 mod types;
 mod util;
 
 pub use functions::resolve_func_call;
+pub use scope::{Scope, ScopeRelation};
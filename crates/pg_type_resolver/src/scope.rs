@@ -0,0 +1,204 @@
+use pg_query_ext::{protobuf, NodeEnum};
+use pg_schema_cache::{Column, Relation, SchemaCache};
+
+/// A relation that a statement reads from or writes to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeRelation {
+    pub schema: Option<String>,
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+impl ScopeRelation {
+    fn from_range_var(range_var: &protobuf::RangeVar) -> ScopeRelation {
+        ScopeRelation {
+            schema: non_empty(&range_var.schemaname),
+            name: range_var.relname.clone(),
+            alias: range_var
+                .alias
+                .as_ref()
+                .and_then(|alias| non_empty(&alias.aliasname)),
+        }
+    }
+
+    /// Whether a column qualifier, e.g. `u` in `u.email`, refers to this relation
+    ///
+    /// Like in Postgres, an aliased relation can only be referred to by its alias.
+    pub fn matches(&self, schema: Option<&str>, name: &str) -> bool {
+        match &self.alias {
+            Some(alias) => schema.is_none() && alias == name,
+            None => {
+                self.name == name
+                    && schema.is_none_or(|schema| self.schema.as_deref() == Some(schema))
+            }
+        }
+    }
+
+    /// Looks up the relation in the schema cache
+    pub fn resolve<'a>(&self, schema_cache: &'a SchemaCache) -> Option<Relation<'a>> {
+        match &self.schema {
+            Some(schema) => schema_cache
+                .find_table(&self.name, Some(schema))
+                .map(Relation::Table)
+                .or_else(|| {
+                    schema_cache
+                        .find_foreign_table(&self.name, Some(schema))
+                        .map(Relation::ForeignTable)
+                }),
+            None => schema_cache.resolve(&self.name, &schema_cache.search_path),
+        }
+    }
+}
+
+/// The relations whose columns can be referenced in a statement
+///
+/// Only the relations of the outermost query are collected, i.e. its `FROM` clause including
+/// joins, and the target of an `UPDATE` or `DELETE` together with its `FROM` or `USING` clause.
+/// Subqueries and common table expressions are not resolved.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Scope {
+    pub relations: Vec<ScopeRelation>,
+}
+
+impl Scope {
+    pub fn from_statement(stmt: &NodeEnum) -> Scope {
+        let mut relations = Vec::new();
+
+        match stmt {
+            NodeEnum::SelectStmt(s) => collect_select(s, &mut relations),
+            NodeEnum::UpdateStmt(s) => {
+                relations.extend(s.relation.as_ref().map(ScopeRelation::from_range_var));
+                collect_from_clause(&s.from_clause, &mut relations);
+            }
+            NodeEnum::DeleteStmt(s) => {
+                relations.extend(s.relation.as_ref().map(ScopeRelation::from_range_var));
+                collect_from_clause(&s.using_clause, &mut relations);
+            }
+            NodeEnum::InsertStmt(s) => {
+                relations.extend(s.relation.as_ref().map(ScopeRelation::from_range_var));
+            }
+            _ => {}
+        }
+
+        Scope { relations }
+    }
+
+    /// Resolves a possibly qualified column reference, e.g. `email`, `u.email` or
+    /// `public.users.email`
+    ///
+    /// Unqualified columns resolve to the first relation in scope that has a column of that name.
+    pub fn resolve_column<'a>(
+        &self,
+        schema_cache: &'a SchemaCache,
+        qualifier: &[String],
+        name: &str,
+    ) -> Option<&'a Column> {
+        let (schema, relation) = match qualifier {
+            [] => (None, None),
+            [relation] => (None, Some(relation.as_str())),
+            [.., schema, relation] => (Some(schema.as_str()), Some(relation.as_str())),
+        };
+
+        self.relations
+            .iter()
+            .filter(|r| relation.is_none_or(|relation| r.matches(schema, relation)))
+            .filter_map(|r| r.resolve(schema_cache))
+            .find_map(|r| schema_cache.find_column(r.id(), name))
+    }
+
+    /// Returns the columns of all relations in scope
+    pub fn columns<'a>(&self, schema_cache: &'a SchemaCache) -> Vec<&'a Column> {
+        self.relations
+            .iter()
+            .filter_map(|r| r.resolve(schema_cache))
+            .flat_map(|r| schema_cache.find_columns(r.id()))
+            .collect()
+    }
+}
+
+fn collect_select(stmt: &protobuf::SelectStmt, relations: &mut Vec<ScopeRelation>) {
+    // the columns of a set operation are those of its first query
+    match &stmt.larg {
+        Some(larg) => collect_select(larg, relations),
+        None => collect_from_clause(&stmt.from_clause, relations),
+    }
+}
+
+fn collect_from_clause(from_clause: &[protobuf::Node], relations: &mut Vec<ScopeRelation>) {
+    for node in from_clause {
+        collect_from_item(node, relations);
+    }
+}
+
+fn collect_from_item(node: &protobuf::Node, relations: &mut Vec<ScopeRelation>) {
+    match &node.node {
+        Some(NodeEnum::RangeVar(r)) => relations.push(ScopeRelation::from_range_var(r)),
+        Some(NodeEnum::JoinExpr(j)) => {
+            if let Some(larg) = &j.larg {
+                collect_from_item(larg, relations);
+            }
+            if let Some(rarg) = &j.rarg {
+                collect_from_item(rarg, relations);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+
+    use super::{Scope, ScopeRelation};
+
+    #[test]
+    fn test_resolve_column() {
+        let schema_cache = SchemaCache::from_sql(
+            "
+            create schema app;
+            create table app.users (id int, email text);
+            create table public.events (id int, user_id int);
+            ",
+        );
+
+        let stmt =
+            pg_query_ext::parse("select * from app.users u join events on events.user_id = u.id")
+                .unwrap();
+        let scope = Scope::from_statement(&stmt);
+        assert_eq!(
+            scope.relations[0],
+            ScopeRelation {
+                schema: Some("app".to_string()),
+                name: "users".to_string(),
+                alias: Some("u".to_string()),
+            }
+        );
+
+        let qualifier = |q: &[&str]| q.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let email = scope
+            .resolve_column(&schema_cache, &qualifier(&[]), "email")
+            .unwrap();
+        assert_eq!(email.table, "users");
+
+        let id = scope
+            .resolve_column(&schema_cache, &qualifier(&["events"]), "id")
+            .unwrap();
+        assert_eq!(id.table, "events");
+
+        // aliased relations can not be referenced by name
+        assert!(scope
+            .resolve_column(&schema_cache, &qualifier(&["users"]), "id")
+            .is_none());
+
+        assert_eq!(scope.columns(&schema_cache).len(), 4);
+    }
+}
@@ -1,9 +1,10 @@
-use sqlx::postgres::PgDatabaseError;
 pub use sqlx::postgres::PgSeverity;
-use sqlx::Executor;
+use sqlx::postgres::{PgDatabaseError, PgTypeInfo, PgTypeKind};
 use sqlx::PgPool;
+use sqlx::{Column, Either, Executor, Statement, TypeInfo};
 use text_size::TextRange;
 use text_size::TextSize;
+use tracing::Instrument;
 
 pub struct TypecheckerParams<'a> {
     pub conn: &'a PgPool,
@@ -25,7 +26,61 @@ pub struct TypeError {
     pub constraint: Option<String>,
 }
 
-pub async fn check_sql<'a>(params: TypecheckerParams<'a>) -> Vec<TypeError> {
+/// A column of the rows a statement returns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResultColumn {
+    pub name: String,
+    pub type_name: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TypecheckResult {
+    pub errors: Vec<TypeError>,
+    /// The columns of the result, as described by the server when preparing the statement
+    pub columns: Vec<ResultColumn>,
+    /// The types the server inferred for the parameters `$1`, `$2`, ... of the statement
+    pub parameters: Vec<String>,
+}
+
+/// The result columns and parameters of a statement, as the query macros of sqlx see them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatementDescription {
+    pub columns: Vec<ResultColumn>,
+    /// Whether each column may be null, or `None` if the server cannot tell, e.g. for most
+    /// expressions
+    pub nullable: Vec<Option<bool>>,
+    pub parameters: Vec<String>,
+}
+
+/// Describes a statement for code generation
+///
+/// Unlike [`check_sql`], this also infers whether the columns may be null from the catalog and
+/// the plan of the statement, which takes additional round trips.
+pub async fn describe_sql(conn: &PgPool, sql: &str) -> Result<StatementDescription, sqlx::Error> {
+    let describe = conn.describe(sql).await?;
+
+    let columns = describe
+        .columns()
+        .iter()
+        .map(|c| ResultColumn {
+            name: c.name().to_string(),
+            type_name: type_name(c.type_info()),
+        })
+        .collect::<Vec<_>>();
+    let nullable = (0..columns.len()).map(|i| describe.nullable(i)).collect();
+    let parameters = match describe.parameters() {
+        Some(Either::Left(types)) => types.iter().map(type_name).collect(),
+        _ => vec![],
+    };
+
+    Ok(StatementDescription {
+        columns,
+        nullable,
+        parameters,
+    })
+}
+
+pub async fn check_sql<'a>(params: TypecheckerParams<'a>) -> TypecheckResult {
     let mut errs = vec![];
 
     // prpeared statements work only for select, insert, update, delete, and cte
@@ -37,10 +92,31 @@ pub async fn check_sql<'a>(params: TypecheckerParams<'a>) -> Vec<TypeError> {
         pg_query_ext::NodeEnum::CommonTableExpr(_) => false,
         _ => true,
     } {
-        return errs;
+        return TypecheckResult::default();
     }
 
-    let res = params.conn.prepare(params.sql).await;
+    let res = params
+        .conn
+        .prepare(params.sql)
+        .instrument(tracing::debug_span!("prepare"))
+        .await;
+
+    let columns = match res.as_ref() {
+        Ok(stmt) => stmt
+            .columns()
+            .iter()
+            .map(|c| ResultColumn {
+                name: c.name().to_string(),
+                type_name: type_name(c.type_info()),
+            })
+            .collect(),
+        Err(_) => vec![],
+    };
+
+    let parameters = match res.as_ref().map(|stmt| stmt.parameters()) {
+        Ok(Some(Either::Left(types))) => types.iter().map(type_name).collect(),
+        _ => vec![],
+    };
 
     if res.is_err() {
         if let sqlx::Error::Database(err) = res.as_ref().unwrap_err() {
@@ -79,7 +155,21 @@ pub async fn check_sql<'a>(params: TypecheckerParams<'a>) -> Vec<TypeError> {
         }
     }
 
-    errs
+    TypecheckResult {
+        errors: errs,
+        columns,
+        parameters,
+    }
+}
+
+fn type_name(type_info: &PgTypeInfo) -> String {
+    match type_info.kind() {
+        // built-in types are named in upper case, e.g. `INT4`
+        PgTypeKind::Simple => type_info.name().to_lowercase(),
+        // arrays are named `INT4[]` if they are built-in and `_mood` otherwise
+        PgTypeKind::Array(element) => format!("{}[]", type_name(element)),
+        _ => type_info.name().to_string(),
+    }
 }
 
 #[cfg(test)]
@@ -87,7 +177,7 @@ mod tests {
     use async_std::task::block_on;
     use sqlx::PgPool;
 
-    use crate::{check_sql, TypecheckerParams};
+    use crate::{check_sql, describe_sql, ResultColumn, TypecheckerParams};
 
     #[test]
     fn test_check_sql() {
@@ -105,7 +195,8 @@ mod tests {
             sql: input,
             ast: &root,
             enriched_ast: Some(&ast),
-        }));
+        }))
+        .errors;
 
         assert_eq!(errs.len(), 1);
 
@@ -113,4 +204,78 @@ mod tests {
 
         assert_eq!(&input[e.range.unwrap()], "unknown");
     }
+
+    #[test]
+    fn test_result_columns() {
+        let input = "select id, username as name from contact;";
+
+        let conn_string = std::env::var("DB_CONNECTION_STRING").unwrap();
+
+        let pool = block_on(PgPool::connect(conn_string.as_str())).unwrap();
+
+        let root = pg_query_ext::parse(input).unwrap();
+
+        let result = block_on(check_sql(TypecheckerParams {
+            conn: &pool,
+            sql: input,
+            ast: &root,
+            enriched_ast: None,
+        }));
+
+        assert!(result.errors.is_empty());
+        assert_eq!(
+            result.columns,
+            vec![
+                ResultColumn {
+                    name: "id".to_string(),
+                    type_name: "int4".to_string(),
+                },
+                ResultColumn {
+                    name: "name".to_string(),
+                    type_name: "text".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parameters() {
+        let input = "select id from contact where id = $1 and username = $2;";
+
+        let conn_string = std::env::var("DB_CONNECTION_STRING").unwrap();
+
+        let pool = block_on(PgPool::connect(conn_string.as_str())).unwrap();
+
+        let root = pg_query_ext::parse(input).unwrap();
+
+        let result = block_on(check_sql(TypecheckerParams {
+            conn: &pool,
+            sql: input,
+            ast: &root,
+            enriched_ast: None,
+        }));
+
+        assert_eq!(result.parameters, vec!["int4", "text"]);
+    }
+
+    #[test]
+    fn test_describe_sql() {
+        let conn_string = std::env::var("DB_CONNECTION_STRING").unwrap();
+
+        let pool = block_on(PgPool::connect(conn_string.as_str())).unwrap();
+
+        let description = block_on(describe_sql(
+            &pool,
+            "select id, count(*) over () from contact where username = $1",
+        ))
+        .unwrap();
+
+        assert_eq!(description.columns.len(), 2);
+        assert_eq!(description.nullable[0], Some(false));
+        assert_eq!(description.parameters, vec!["text"]);
+
+        let description =
+            block_on(describe_sql(&pool, "select array[id] as ids from contact")).unwrap();
+        assert_eq!(description.columns[0].type_name, "int4[]");
+    }
 }
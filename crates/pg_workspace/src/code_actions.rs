@@ -0,0 +1,751 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use pg_lexer::SyntaxKind;
+use pg_query_ext::{protobuf, ChildrenIterator, NodeEnum};
+use pg_schema_cache::SchemaCache;
+use pg_type_resolver::{Scope, ScopeRelation};
+use pg_typecheck::TypeError;
+use text_size::{TextRange, TextSize};
+
+use crate::{
+    did_you_mean::did_you_mean,
+    rename::{identifier, quote_identifier},
+};
+
+const UNDEFINED_COLUMN: &str = "42703";
+const UNDEFINED_TABLE: &str = "42P01";
+const AMBIGUOUS_COLUMN: &str = "42702";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeActionKind {
+    QuickFix,
+    RefactorRewrite,
+    /// Applies all fixes that are safe to apply without review, e.g. on save
+    SourceFixAll,
+}
+
+/// An action that edits the statement it is offered for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeAction {
+    pub title: String,
+    pub kind: CodeActionKind,
+    pub edits: Vec<(TextRange, String)>,
+}
+
+/// Offers to replace a `*` or `alias.*` in the target list of a query that intersects the range
+/// with the columns it stands for
+///
+/// The columns are listed in the order of the schema cache. They are qualified with the alias or
+/// name of their relation if the star is qualified or the query selects from multiple relations.
+pub fn expand_star(
+    sql: &str,
+    root: &NodeEnum,
+    range: TextRange,
+    schema_cache: &SchemaCache,
+) -> Vec<CodeAction> {
+    let NodeEnum::SelectStmt(select) = root else {
+        return Vec::new();
+    };
+    let scope = Scope::from_statement(root);
+
+    select
+        .target_list
+        .iter()
+        .filter_map(|target| match &target.node {
+            Some(NodeEnum::ResTarget(t)) => match t.val.as_ref()?.node.as_ref()? {
+                NodeEnum::ColumnRef(c) => Some(c),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter_map(|column_ref| {
+            let qualifier = star_qualifier(column_ref)?;
+            let star_range = star_range(sql, column_ref.location)?;
+            star_range.intersect(range)?;
+
+            let relations = match qualifier.as_slice() {
+                [] => scope.relations.iter().collect::<Vec<_>>(),
+                [.., relation] => {
+                    let schema = qualifier
+                        .len()
+                        .checked_sub(2)
+                        .map(|i| qualifier[i].as_str());
+                    scope
+                        .relations
+                        .iter()
+                        .filter(|r| r.matches(schema, relation))
+                        .collect()
+                }
+            };
+            let qualify = !qualifier.is_empty() || relations.len() > 1;
+
+            let columns = relations
+                .into_iter()
+                .filter_map(|r| Some((r, r.resolve(schema_cache)?)))
+                .flat_map(|(r, resolved)| {
+                    schema_cache
+                        .find_columns(resolved.id())
+                        .into_iter()
+                        .map(move |c| match qualify {
+                            true => format!("{}.{}", qualifier_of(r), quote_identifier(&c.name)),
+                            false => quote_identifier(&c.name),
+                        })
+                })
+                .collect::<Vec<_>>();
+            if columns.is_empty() {
+                return None;
+            }
+
+            Some(CodeAction {
+                title: format!("Expand `{}`", &sql[star_range]),
+                kind: CodeActionKind::RefactorRewrite,
+                edits: vec![(star_range, columns.join(", "))],
+            })
+        })
+        .collect()
+}
+
+/// Offers to insert the columns of each relation of the statement at the cursor, one per line,
+/// e.g. while writing the column list of an `INSERT` or the target list of a `SELECT`
+///
+/// Every relation gets an action with and without the types of the columns as trailing comments.
+/// The columns are qualified like in [`expand_star`] if the statement reads from multiple
+/// relations. Nothing is offered for a selection or if the cursor is within a token.
+pub fn insert_column_list(
+    sql: &str,
+    root: &NodeEnum,
+    range: TextRange,
+    schema_cache: &SchemaCache,
+) -> Vec<CodeAction> {
+    if !range.is_empty()
+        || pg_lexer::lex(sql)
+            .iter()
+            .filter(|t| !matches!(t.kind, SyntaxKind::Whitespace | SyntaxKind::Newline))
+            .any(|t| t.span.start() < range.start() && range.start() < t.span.end())
+    {
+        return Vec::new();
+    }
+
+    let line_start = sql[..usize::from(range.start())]
+        .rfind('\n')
+        .map_or(0, |idx| idx + 1);
+    let indent = sql[line_start..]
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect::<String>();
+
+    let scope = Scope::from_statement(root);
+    let qualify = scope.relations.len() > 1;
+
+    scope
+        .relations
+        .iter()
+        .filter_map(|r| Some((r, r.resolve(schema_cache)?)))
+        .flat_map(|(r, resolved)| {
+            let columns = schema_cache
+                .find_columns(resolved.id())
+                .into_iter()
+                .map(|c| {
+                    let name = match qualify {
+                        true => format!("{}.{}", qualifier_of(r), quote_identifier(&c.name)),
+                        false => quote_identifier(&c.name),
+                    };
+                    (name, c.type_name.to_string())
+                })
+                .collect::<Vec<_>>();
+            if columns.is_empty() {
+                return Vec::new();
+            }
+
+            let separator = format!("\n{}", indent);
+            let plain = columns
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(&format!(",{}", separator));
+            let typed = columns
+                .iter()
+                .enumerate()
+                .map(|(idx, (name, type_name))| match idx + 1 == columns.len() {
+                    true => format!("{} -- {}", name, type_name),
+                    false => format!("{}, -- {}", name, type_name),
+                })
+                .collect::<Vec<_>>()
+                .join(&separator);
+
+            let relation = qualifier_of(r);
+            vec![
+                CodeAction {
+                    title: format!("Insert column list of `{}`", relation),
+                    kind: CodeActionKind::RefactorRewrite,
+                    edits: vec![(range, plain)],
+                },
+                CodeAction {
+                    title: format!("Insert column list of `{}` with types", relation),
+                    kind: CodeActionKind::RefactorRewrite,
+                    edits: vec![(range, typed)],
+                },
+            ]
+        })
+        .collect()
+}
+
+/// Offers to wrap the statements of a document that intersect a selection in a transaction or a
+/// savepoint
+///
+/// The wrappers are placed at the boundaries of the first and last selected statement and take
+/// over the indentation of the first one. Nothing is offered if the selection already contains a
+/// transaction control statement.
+pub fn wrap_in_transaction(
+    text: &str,
+    statements: &[(TextRange, Option<Arc<NodeEnum>>)],
+    range: TextRange,
+) -> Vec<CodeAction> {
+    if range.is_empty() {
+        return Vec::new();
+    }
+    let selected = statements
+        .iter()
+        .filter(|(stmt_range, _)| {
+            stmt_range
+                .intersect(range)
+                .is_some_and(|intersection| !intersection.is_empty())
+        })
+        .collect::<Vec<_>>();
+    let (Some((first, _)), Some((last, _))) = (selected.first(), selected.last()) else {
+        return Vec::new();
+    };
+    if selected
+        .iter()
+        .any(|(_, root)| matches!(root.as_deref(), Some(NodeEnum::TransactionStmt(_))))
+    {
+        return Vec::new();
+    }
+
+    let line_start = text[..usize::from(first.start())]
+        .rfind('\n')
+        .map_or(0, |idx| idx + 1);
+    let indent = text[line_start..usize::from(first.start())]
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect::<String>();
+
+    [
+        ("Wrap in transaction", "BEGIN;", "COMMIT;"),
+        (
+            "Wrap in savepoint",
+            "SAVEPOINT savepoint;",
+            "RELEASE SAVEPOINT savepoint;",
+        ),
+    ]
+    .into_iter()
+    .map(|(title, open, close)| CodeAction {
+        title: title.to_string(),
+        kind: CodeActionKind::RefactorRewrite,
+        edits: vec![
+            (
+                TextRange::empty(first.start()),
+                format!("{}\n{}", open, indent),
+            ),
+            (
+                TextRange::empty(last.end()),
+                format!("\n{}{}", indent, close),
+            ),
+        ],
+    })
+    .collect()
+}
+
+/// Offers to replace a misspelled column or table name the typecheck reported as undefined with
+/// the closest name in scope
+///
+/// Columns are looked up in the relations the statement reads from, tables in the schema they
+/// are qualified with or else in the schemas of the `search_path`.
+pub fn fix_misspelled_identifiers(
+    sql: &str,
+    root: &NodeEnum,
+    errors: &[TypeError],
+    range: TextRange,
+    schema_cache: &SchemaCache,
+) -> Vec<CodeAction> {
+    errors
+        .iter()
+        .filter(|e| e.code == UNDEFINED_COLUMN || e.code == UNDEFINED_TABLE)
+        .filter_map(|e| {
+            let parts = name_parts(sql, e.position?);
+            let (name, name_range) = parts.last()?.clone();
+            let qualifier = parts[..parts.len() - 1]
+                .iter()
+                .map(|(part, _)| part.as_str())
+                .collect::<Vec<_>>();
+            TextRange::new(parts[0].1.start(), name_range.end()).intersect(range)?;
+
+            let candidates: Vec<&str> = if e.code == UNDEFINED_COLUMN {
+                let (schema, relation) = match qualifier.as_slice() {
+                    [] => (None, None),
+                    [relation] => (None, Some(*relation)),
+                    [.., schema, relation] => (Some(*schema), Some(*relation)),
+                };
+                Scope::from_statement(root)
+                    .relations
+                    .iter()
+                    .filter(|r| relation.is_none_or(|relation| r.matches(schema, relation)))
+                    .filter_map(|r| r.resolve(schema_cache))
+                    .flat_map(|r| schema_cache.find_columns(r.id()))
+                    .map(|c| c.name.as_str())
+                    .collect()
+            } else {
+                let schemas = match qualifier.last() {
+                    Some(schema) => vec![*schema],
+                    None => schema_cache.effective_search_path(&schema_cache.search_path, true),
+                };
+                schema_cache
+                    .tables
+                    .iter()
+                    .filter(|t| schemas.contains(&t.schema.as_str()))
+                    .map(|t| t.name.as_str())
+                    .chain(
+                        schema_cache
+                            .foreign_tables
+                            .iter()
+                            .filter(|t| schemas.contains(&t.schema.as_str()))
+                            .map(|t| t.name.as_str()),
+                    )
+                    .collect()
+            };
+
+            let suggestion = did_you_mean(&name, candidates)?;
+            Some(CodeAction {
+                title: format!("Change to `{}`", suggestion),
+                kind: CodeActionKind::QuickFix,
+                edits: vec![(name_range, quote_identifier(suggestion))],
+            })
+        })
+        .collect()
+}
+
+/// Offers to qualify a column the typecheck reported as ambiguous with each relation in scope that
+/// has a column of that name
+///
+/// All unqualified references to the column in the statement are qualified, since they are all
+/// ambiguous.
+pub fn qualify_ambiguous_columns(
+    sql: &str,
+    root: &NodeEnum,
+    errors: &[TypeError],
+    range: TextRange,
+    schema_cache: &SchemaCache,
+) -> Vec<CodeAction> {
+    let Some((name, name_range)) = errors
+        .iter()
+        .filter(|e| e.code == AMBIGUOUS_COLUMN)
+        .find_map(|e| match name_parts(sql, e.position?).as_slice() {
+            [(name, name_range)] if name_range.intersect(range).is_some() => {
+                Some((name.clone(), *name_range))
+            }
+            _ => None,
+        })
+    else {
+        return Vec::new();
+    };
+
+    let occurrences = ChildrenIterator::new(root.to_owned())
+        .filter_map(|n| match n {
+            NodeEnum::ColumnRef(c) => match c.fields.as_slice() {
+                [protobuf::Node {
+                    node: Some(NodeEnum::String(s)),
+                }] if s.sval == name => TextSize::try_from(usize::try_from(c.location).ok()?).ok(),
+                _ => None,
+            },
+            _ => None,
+        })
+        .chain(std::iter::once(name_range.start()))
+        .collect::<BTreeSet<_>>();
+
+    Scope::from_statement(root)
+        .relations
+        .iter()
+        .filter(|r| {
+            r.resolve(schema_cache)
+                .is_some_and(|resolved| schema_cache.find_column(resolved.id(), &name).is_some())
+        })
+        .map(|r| {
+            let qualifier = qualifier_of(r);
+            CodeAction {
+                title: format!("Qualify `{}` with `{}`", name, qualifier),
+                kind: CodeActionKind::QuickFix,
+                edits: occurrences
+                    .iter()
+                    .map(|offset| (TextRange::empty(*offset), format!("{}.", qualifier)))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Applies the edits of actions to a text, e.g. to fix a file from the command line
+///
+/// An action whose edits overlap those of an action before it is skipped, so that the text stays
+/// valid. It can be applied once the text is analysed again.
+pub fn apply_code_actions(text: &str, actions: &[CodeAction]) -> String {
+    let mut edits: Vec<&(TextRange, String)> = Vec::new();
+    for action in actions {
+        let overlaps = action.edits.iter().any(|(range, _)| {
+            edits.iter().any(|(other, _)| {
+                range.intersect(*other).is_some_and(|i| !i.is_empty())
+                    || (range.is_empty() && range.start() == other.start())
+                    || (other.is_empty() && other.start() == range.start())
+            })
+        });
+        if !overlaps {
+            edits.extend(&action.edits);
+        }
+    }
+    edits.sort_by_key(|(range, _)| range.start());
+
+    let mut result = String::with_capacity(text.len());
+    let mut offset = 0;
+    for (range, new_text) in edits {
+        result.push_str(&text[offset..usize::from(range.start())]);
+        result.push_str(new_text);
+        offset = usize::from(range.end());
+    }
+    result.push_str(&text[offset..]);
+    result
+}
+
+/// Returns the normalised parts of the possibly qualified name at an offset together with their
+/// ranges, e.g. `u` and `usernme` for `u.usernme`
+fn name_parts(sql: &str, offset: usize) -> Vec<(String, TextRange)> {
+    let Ok(offset) = TextSize::try_from(offset) else {
+        return Vec::new();
+    };
+    let tokens = pg_lexer::lex(sql)
+        .into_iter()
+        .filter(|t| !matches!(t.kind, SyntaxKind::Whitespace | SyntaxKind::Newline))
+        .skip_while(|t| t.span.start() < offset)
+        .collect::<Vec<_>>();
+
+    let mut parts = Vec::new();
+    for (idx, token) in tokens.iter().enumerate().step_by(2) {
+        let Some(name) = identifier(token) else {
+            break;
+        };
+        parts.push((name, token.span));
+        if tokens
+            .get(idx + 1)
+            .is_none_or(|t| t.kind != SyntaxKind::Ascii46)
+        {
+            break;
+        }
+    }
+    parts
+}
+
+/// Returns the qualifier of a column reference that ends with a star, e.g. `["u"]` for `u.*`
+fn star_qualifier(column_ref: &protobuf::ColumnRef) -> Option<Vec<String>> {
+    let (last, qualifier) = column_ref.fields.split_last()?;
+    if !matches!(last.node, Some(NodeEnum::AStar(_))) {
+        return None;
+    }
+    qualifier
+        .iter()
+        .map(|f| match &f.node {
+            Some(NodeEnum::String(s)) => Some(s.sval.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the range of a possibly qualified star that starts at `location`
+fn star_range(sql: &str, location: i32) -> Option<TextRange> {
+    let start = TextSize::try_from(usize::try_from(location).ok()?).ok()?;
+    let tokens = pg_lexer::lex(sql)
+        .into_iter()
+        .filter(|t| !matches!(t.kind, SyntaxKind::Whitespace | SyntaxKind::Newline))
+        .skip_while(|t| t.span.start() < start)
+        .collect::<Vec<_>>();
+
+    let star = tokens.iter().find(|t| t.kind == SyntaxKind::Ascii42)?;
+    Some(TextRange::new(start, star.span.end()))
+}
+
+/// Returns the name a relation is referred to by within its query
+fn qualifier_of(relation: &ScopeRelation) -> String {
+    match (&relation.alias, &relation.schema) {
+        (Some(alias), _) => quote_identifier(alias),
+        (None, Some(schema)) => format!(
+            "{}.{}",
+            quote_identifier(schema),
+            quote_identifier(&relation.name)
+        ),
+        (None, None) => quote_identifier(&relation.name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use pg_schema_cache::SchemaCache;
+    use pg_typecheck::{PgSeverity, TypeError};
+    use text_size::{TextRange, TextSize};
+
+    use super::{
+        apply_code_actions, expand_star, fix_misspelled_identifiers, insert_column_list,
+        qualify_ambiguous_columns, wrap_in_transaction, CodeAction, CodeActionKind,
+    };
+
+    #[test]
+    fn test_expand_star() {
+        let schema_cache = SchemaCache::from_sql(
+            "create table users (id int, name text, \"order\" int);
+            create table orders (id int, user_id int);",
+        );
+
+        let expand = |sql: &str, offset: u32| {
+            let root = pg_query_ext::parse(sql).unwrap();
+            let actions = expand_star(
+                sql,
+                &root,
+                TextRange::empty(TextSize::new(offset)),
+                &schema_cache,
+            );
+            let mut sql = sql.to_string();
+            for action in actions {
+                for (range, text) in action.edits.into_iter().rev() {
+                    sql.replace_range(std::ops::Range::<usize>::from(range), &text);
+                }
+            }
+            sql
+        };
+
+        assert_eq!(
+            expand("select * from users", 7),
+            "select id, name, \"order\" from users"
+        );
+        assert_eq!(
+            expand(
+                "select u.*, o.id from users u join orders o on o.user_id = u.id",
+                8
+            ),
+            "select u.id, u.name, u.\"order\", o.id from users u join orders o on o.user_id = u.id"
+        );
+        assert_eq!(
+            expand("select * from users u, public.orders", 7),
+            "select u.id, u.name, u.\"order\", public.orders.id, public.orders.user_id from users u, public.orders"
+        );
+        assert_eq!(expand("select * from users", 16), "select * from users");
+    }
+
+    #[test]
+    fn test_fix_misspelled_identifiers() {
+        let schema_cache = SchemaCache::from_sql(
+            "create table users (id int, username text);
+            create table app.orders (id int, user_id int);",
+        );
+        let error = |code: &str, position: usize| TypeError {
+            message: String::new(),
+            code: code.to_string(),
+            severity: PgSeverity::Error,
+            position: Some(position),
+            range: None,
+            table: None,
+            column: None,
+            data_type: None,
+            constraint: None,
+        };
+
+        let fix = |sql: &'static str, error: TypeError| {
+            let root = pg_query_ext::parse(sql).unwrap();
+            fix_misspelled_identifiers(
+                sql,
+                &root,
+                &[error],
+                TextRange::up_to(TextSize::of(sql)),
+                &schema_cache,
+            )
+            .into_iter()
+            .flat_map(|action| action.edits)
+            .map(|(range, text)| (&sql[range], text))
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            fix("select u.usernme from users u", error("42703", 7)),
+            vec![("usernme", "username".to_string())]
+        );
+        assert_eq!(
+            fix("select id from usrs", error("42P01", 15)),
+            vec![("usrs", "users".to_string())]
+        );
+        assert_eq!(
+            fix("select id from app.ordes", error("42P01", 15)),
+            vec![("ordes", "orders".to_string())]
+        );
+        assert!(fix("select phone from users", error("42703", 7)).is_empty());
+    }
+
+    #[test]
+    fn test_qualify_ambiguous_columns() {
+        let schema_cache = SchemaCache::from_sql(
+            "create table users (id int, name text);
+            create table orders (id int, user_id int);",
+        );
+        let sql = "select id, name from users u join orders on user_id = u.id where id > 1";
+        let root = pg_query_ext::parse(sql).unwrap();
+        let error = TypeError {
+            message: "column reference \"id\" is ambiguous".to_string(),
+            code: "42702".to_string(),
+            severity: PgSeverity::Error,
+            position: Some(7),
+            range: None,
+            table: None,
+            column: None,
+            data_type: None,
+            constraint: None,
+        };
+
+        let actions = qualify_ambiguous_columns(
+            sql,
+            &root,
+            &[error],
+            TextRange::empty(TextSize::new(8)),
+            &schema_cache,
+        );
+
+        let apply = |edits: &[(TextRange, String)]| {
+            let mut sql = sql.to_string();
+            for (range, text) in edits.iter().rev() {
+                sql.replace_range(std::ops::Range::<usize>::from(*range), text);
+            }
+            sql
+        };
+        assert_eq!(
+            actions.iter().map(|a| a.title.as_str()).collect::<Vec<_>>(),
+            vec!["Qualify `id` with `u`", "Qualify `id` with `orders`"]
+        );
+        assert_eq!(
+            apply(&actions[1].edits),
+            "select orders.id, name from users u join orders on user_id = u.id where orders.id > 1"
+        );
+    }
+
+    #[test]
+    fn test_apply_code_actions() {
+        let action = |edits: &[(u32, u32, &str)]| CodeAction {
+            title: String::new(),
+            kind: CodeActionKind::QuickFix,
+            edits: edits
+                .iter()
+                .map(|(start, end, text)| {
+                    (
+                        TextRange::new(TextSize::from(*start), TextSize::from(*end)),
+                        text.to_string(),
+                    )
+                })
+                .collect(),
+        };
+
+        assert_eq!(
+            apply_code_actions(
+                "select nme from usrs\nselect 2",
+                &[
+                    action(&[(20, 20, ";"), (29, 29, ";")]),
+                    action(&[(7, 10, "name"), (16, 20, "users")]),
+                    // overlaps the previous action
+                    action(&[(15, 20, "users")]),
+                ]
+            ),
+            "select name from users;\nselect 2;"
+        );
+    }
+
+    #[test]
+    fn test_insert_column_list() {
+        let schema_cache = SchemaCache::from_sql(
+            "create table users (id int, name text);
+            create table orders (id int, user_id int);",
+        );
+
+        let insert = |sql: &str, offset: u32| {
+            let root = pg_query_ext::parse(sql).unwrap();
+            insert_column_list(
+                sql,
+                &root,
+                TextRange::empty(TextSize::from(offset)),
+                &schema_cache,
+            )
+            .into_iter()
+            .map(|a| (a.title, a.edits[0].1.clone()))
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            insert("select\n    \nfrom users", 11),
+            vec![
+                (
+                    "Insert column list of `users`".to_string(),
+                    "id,\n    name".to_string()
+                ),
+                (
+                    "Insert column list of `users` with types".to_string(),
+                    "id, -- int4\n    name -- text".to_string()
+                ),
+            ]
+        );
+
+        let actions = insert("select  from users u join orders o on o.user_id = u.id", 7);
+        assert_eq!(actions.len(), 4);
+        assert_eq!(actions[2].1, "o.id,\no.user_id");
+
+        // within a token
+        assert!(insert("select  from users", 16).is_empty());
+    }
+
+    #[test]
+    fn test_wrap_in_transaction() {
+        let wrap = |text: &str, range: TextRange| {
+            // statements are separated by newlines in these tests
+            let mut offset = 0;
+            let statements = text
+                .split_inclusive('\n')
+                .map(|line| {
+                    let start = offset + (line.len() - line.trim_start().len());
+                    offset += line.len();
+                    let range = TextRange::new(
+                        TextSize::try_from(start).unwrap(),
+                        TextSize::try_from(offset - (line.len() - line.trim_end().len())).unwrap(),
+                    );
+                    (range, pg_query_ext::parse(&text[range]).ok().map(Arc::new))
+                })
+                .collect::<Vec<_>>();
+            wrap_in_transaction(text, &statements, range)
+                .into_iter()
+                .map(|action| {
+                    let mut text = text.to_string();
+                    for (range, new_text) in action.edits.iter().rev() {
+                        text.replace_range(std::ops::Range::<usize>::from(*range), new_text);
+                    }
+                    text
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let text = "select 1;\n  insert into t values (1);\n  delete from t;\nselect 2;";
+        assert_eq!(
+            wrap(text, TextRange::new(TextSize::from(14), TextSize::from(45))),
+            vec![
+                "select 1;\n  BEGIN;\n  insert into t values (1);\n  delete from t;\n  COMMIT;\nselect 2;",
+                "select 1;\n  SAVEPOINT savepoint;\n  insert into t values (1);\n  delete from t;\n  RELEASE SAVEPOINT savepoint;\nselect 2;",
+            ]
+        );
+
+        assert!(wrap(text, TextRange::empty(TextSize::from(14))).is_empty());
+        assert!(wrap(
+            "begin;\nselect 1;",
+            TextRange::new(TextSize::from(0), TextSize::from(10))
+        )
+        .is_empty());
+    }
+}
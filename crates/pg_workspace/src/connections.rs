@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern, PatternError};
+
+/// The name of the connection used for files that are not routed to any other connection
+pub const DEFAULT_CONNECTION: &str = "default";
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Maps files to the name of the database connection they are checked against
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConnectionRoutes {
+    routes: Vec<(Pattern, String)>,
+}
+
+impl ConnectionRoutes {
+    /// Creates routes from `(path, connection)` pairs
+    ///
+    /// A path is either a directory, which matches all files within it, or a file glob. Relative
+    /// paths are resolved against the workspace root.
+    pub fn new(
+        root: Option<&Path>,
+        routes: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<ConnectionRoutes, PatternError> {
+        let routes = routes
+            .into_iter()
+            .map(|(path, connection)| {
+                path_pattern(root, &path).map(|pattern| (pattern, connection))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ConnectionRoutes { routes })
+    }
+
+    /// Returns the connection of the first route that matches the path
+    pub fn connection(&self, path: &Path) -> &str {
+        self.routes
+            .iter()
+            .find(|(pattern, _)| matches_path(pattern, path))
+            .map(|(_, connection)| connection.as_str())
+            .unwrap_or(DEFAULT_CONNECTION)
+    }
+}
+
+/// Returns the pattern that matches the files a directory or file glob stands for. Relative
+/// paths are resolved against the root.
+pub fn path_pattern(root: Option<&Path>, path: &str) -> Result<Pattern, PatternError> {
+    let mut path = match root {
+        Some(root) if Path::new(path).is_relative() => root.join(path),
+        _ => PathBuf::from(path),
+    };
+    if !is_glob(&path.to_string_lossy()) {
+        path.push("**");
+    }
+    Pattern::new(&path.to_string_lossy())
+}
+
+/// Returns whether a path contains glob metacharacters
+pub fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// Matches a path against a pattern of `path_pattern`, where `*` does not match `/`
+pub fn matches_path(pattern: &Pattern, path: &Path) -> bool {
+    pattern.matches_path_with(path, MATCH_OPTIONS)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{ConnectionRoutes, DEFAULT_CONNECTION};
+
+    #[test]
+    fn test_connection_routes() {
+        let routes = ConnectionRoutes::new(
+            Some(Path::new("/repo")),
+            [
+                ("services/billing".to_string(), "billing".to_string()),
+                ("**/auth/*.sql".to_string(), "auth".to_string()),
+                ("/shared/*.sql".to_string(), "shared".to_string()),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            routes.connection(Path::new("/repo/services/billing/migrations/001.sql")),
+            "billing"
+        );
+        assert_eq!(
+            routes.connection(Path::new("/repo/services/auth/users.sql")),
+            "auth"
+        );
+        assert_eq!(
+            routes.connection(Path::new("/repo/services/auth/nested/users.sql")),
+            DEFAULT_CONNECTION
+        );
+        assert_eq!(routes.connection(Path::new("/shared/util.sql")), "shared");
+        assert_eq!(
+            routes.connection(Path::new("/repo/other.sql")),
+            DEFAULT_CONNECTION
+        );
+    }
+}
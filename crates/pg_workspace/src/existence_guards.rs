@@ -0,0 +1,147 @@
+use pg_lexer::{SyntaxKind, Token};
+use pg_query_ext::{protobuf, NodeEnum};
+use text_size::{TextRange, TextSize};
+
+use crate::{
+    code_actions::{CodeAction, CodeActionKind},
+    rename::identifier,
+};
+
+/// Offers to add `IF NOT EXISTS` to a `CREATE` or `IF EXISTS` to a `DROP` statement that lacks
+/// it, so that the statement can be run again, e.g. as part of a migration
+///
+/// The guard is inserted before the name of the created object or the first dropped object.
+/// Statements that create an object without a name, e.g. `CREATE INDEX ON t (a)`, cannot have a
+/// guard.
+pub fn add_existence_guard(sql: &str, root: &NodeEnum) -> Option<CodeAction> {
+    let tokens = pg_lexer::lex(sql)
+        .into_iter()
+        .filter(|t| {
+            !matches!(
+                t.kind,
+                SyntaxKind::Whitespace
+                    | SyntaxKind::Newline
+                    | SyntaxKind::SqlComment
+                    | SyntaxKind::CComment
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let (guard, start) = match root {
+        NodeEnum::CreateStmt(s) if !s.if_not_exists => {
+            ("if not exists", range_var_start(s.relation.as_ref()?)?)
+        }
+        NodeEnum::CreateSeqStmt(s) if !s.if_not_exists => {
+            ("if not exists", range_var_start(s.sequence.as_ref()?)?)
+        }
+        NodeEnum::CreateTableAsStmt(s) if !s.if_not_exists && !s.is_select_into => (
+            "if not exists",
+            range_var_start(s.into.as_ref()?.rel.as_ref()?)?,
+        ),
+        NodeEnum::IndexStmt(s) if !s.if_not_exists && !s.idxname.is_empty() => {
+            ("if not exists", name_start(&tokens, &s.idxname)?)
+        }
+        NodeEnum::CreateSchemaStmt(s) if !s.if_not_exists && !s.schemaname.is_empty() => {
+            ("if not exists", name_start(&tokens, &s.schemaname)?)
+        }
+        NodeEnum::CreateExtensionStmt(s) if !s.if_not_exists => {
+            ("if not exists", name_start(&tokens, &s.extname)?)
+        }
+        NodeEnum::DropStmt(s) if !s.missing_ok => (
+            "if exists",
+            name_start(&tokens, &first_name(s.objects.first()?)?)?,
+        ),
+        _ => return None,
+    };
+
+    // follow the case of the keywords of the statement
+    let first = &sql[tokens.first()?.span];
+    let guard = if first.chars().all(|c| c.is_uppercase()) {
+        guard.to_uppercase()
+    } else {
+        guard.to_string()
+    };
+
+    Some(CodeAction {
+        title: format!("Add `{}`", guard),
+        kind: CodeActionKind::RefactorRewrite,
+        edits: vec![(TextRange::empty(start), format!("{} ", guard))],
+    })
+}
+
+fn range_var_start(relation: &protobuf::RangeVar) -> Option<TextSize> {
+    TextSize::try_from(usize::try_from(relation.location).ok()?).ok()
+}
+
+/// Returns the start of the first token after the leading keywords that is the given name
+fn name_start(tokens: &[Token], name: &str) -> Option<TextSize> {
+    tokens
+        .iter()
+        .skip(1)
+        .find(|t| identifier(t).is_some_and(|i| i == name))
+        .map(|t| t.span.start())
+}
+
+/// Returns the first part of the possibly qualified name of a dropped object, e.g. `public` for
+/// `public.users`
+fn first_name(object: &protobuf::Node) -> Option<String> {
+    let names = match object.node.as_ref()? {
+        NodeEnum::String(s) => return Some(s.sval.clone()),
+        NodeEnum::List(l) => &l.items,
+        NodeEnum::TypeName(t) => &t.names,
+        NodeEnum::ObjectWithArgs(o) => &o.objname,
+        _ => return None,
+    };
+    match names.first()?.node.as_ref()? {
+        NodeEnum::String(s) => Some(s.sval.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::add_existence_guard;
+
+    fn rewrite(sql: &str) -> Option<String> {
+        let root = pg_query_ext::parse(sql).unwrap();
+        let action = add_existence_guard(sql, &root)?;
+        let mut sql = sql.to_string();
+        for (range, text) in action.edits.into_iter().rev() {
+            sql.replace_range(std::ops::Range::<usize>::from(range), &text);
+        }
+        Some(sql)
+    }
+
+    #[test]
+    fn test_add_existence_guard() {
+        assert_eq!(
+            rewrite("create table public.users (id int)").as_deref(),
+            Some("create table if not exists public.users (id int)")
+        );
+        assert_eq!(
+            rewrite("CREATE UNIQUE INDEX CONCURRENTLY users_email ON users (email)").as_deref(),
+            Some("CREATE UNIQUE INDEX CONCURRENTLY IF NOT EXISTS users_email ON users (email)")
+        );
+        assert_eq!(
+            rewrite("create materialized view stats as select 1").as_deref(),
+            Some("create materialized view if not exists stats as select 1")
+        );
+        assert_eq!(
+            rewrite("create extension pg_trgm").as_deref(),
+            Some("create extension if not exists pg_trgm")
+        );
+        assert_eq!(
+            rewrite("drop table \"Users\", orders cascade").as_deref(),
+            Some("drop table if exists \"Users\", orders cascade")
+        );
+        assert_eq!(
+            rewrite("drop function app.touch(int)").as_deref(),
+            Some("drop function if exists app.touch(int)")
+        );
+
+        assert_eq!(rewrite("create table if not exists users (id int)"), None);
+        assert_eq!(rewrite("drop table if exists users"), None);
+        assert_eq!(rewrite("create index on users (email)"), None);
+        assert_eq!(rewrite("select 1"), None);
+    }
+}
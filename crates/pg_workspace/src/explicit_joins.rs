@@ -0,0 +1,294 @@
+use pg_lexer::{SyntaxKind, Token};
+use pg_query_ext::{protobuf, ChildrenIterator, NodeEnum};
+use text_size::{TextRange, TextSize};
+
+use crate::code_actions::{CodeAction, CodeActionKind};
+
+/// Offers to rewrite a `FROM` clause that lists relations separated by commas into explicit
+/// joins, e.g. `FROM a, b WHERE a.id = b.a_id` into `FROM a JOIN b ON a.id = b.a_id`
+///
+/// Only the conditions of the `WHERE` clause that compare columns of two or more of the relations
+/// are moved, each one into the join of the last relation it refers to. A relation without such
+/// a condition becomes a `CROSS JOIN`. Conditions with unqualified columns are kept, since it is
+/// not known which relation they refer to.
+pub fn explicit_joins(sql: &str, root: &NodeEnum, range: TextRange) -> Option<CodeAction> {
+    let tokens = pg_lexer::lex(sql)
+        .into_iter()
+        .filter(|t| {
+            !matches!(
+                t.kind,
+                SyntaxKind::Whitespace
+                    | SyntaxKind::Newline
+                    | SyntaxKind::SqlComment
+                    | SyntaxKind::CComment
+            )
+        })
+        .collect::<Vec<_>>();
+
+    ChildrenIterator::new(root.to_owned())
+        .filter_map(|n| match n {
+            NodeEnum::SelectStmt(s) => CommaJoin::new(&s, &tokens),
+            _ => None,
+        })
+        .filter(|join| join.range().contains_range(range))
+        .min_by_key(|join| join.range().len())
+        .and_then(|join| join.rewrite(sql, &tokens))
+}
+
+struct CommaJoin {
+    relations: Vec<protobuf::RangeVar>,
+    /// The ranges of the items of the `FROM` clause
+    items: Vec<TextRange>,
+    conditions: Vec<protobuf::Node>,
+    /// The ranges of the conditions of the `WHERE` clause
+    condition_ranges: Vec<TextRange>,
+    /// The index of the `WHERE` token
+    where_idx: usize,
+}
+
+impl CommaJoin {
+    fn new(stmt: &protobuf::SelectStmt, tokens: &[Token]) -> Option<CommaJoin> {
+        if stmt.from_clause.len() < 2 {
+            return None;
+        }
+        let relations = stmt
+            .from_clause
+            .iter()
+            .map(|n| match &n.node {
+                Some(NodeEnum::RangeVar(r)) => Some(r.clone()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let conditions = match stmt.where_clause.as_ref()?.node.as_ref()? {
+            NodeEnum::BoolExpr(b) if b.boolop() == protobuf::BoolExprType::AndExpr => {
+                b.args.clone()
+            }
+            _ => vec![*stmt.where_clause.clone()?],
+        };
+
+        let start = TextSize::try_from(usize::try_from(relations[0].location).ok()?).ok()?;
+        let first = tokens.iter().position(|t| t.span.start() == start)?;
+        let (items, end) = split(tokens, first, SyntaxKind::Ascii44);
+        if tokens.get(end)?.kind != SyntaxKind::Where || items.len() != relations.len() {
+            return None;
+        }
+        let locations_match = items.iter().zip(&relations).all(|(item, relation)| {
+            usize::try_from(relation.location).is_ok_and(|l| usize::from(item.start()) == l)
+        });
+        if !locations_match {
+            return None;
+        }
+
+        // e.g. `BETWEEN ... AND ...` is not split the same way as the expression
+        let (condition_ranges, _) = split(tokens, end + 1, SyntaxKind::And);
+        if condition_ranges.len() != conditions.len() {
+            return None;
+        }
+
+        Some(CommaJoin {
+            relations,
+            items,
+            conditions,
+            condition_ranges,
+            where_idx: end,
+        })
+    }
+
+    fn range(&self) -> TextRange {
+        TextRange::new(
+            self.items[0].start(),
+            self.condition_ranges.last().unwrap().end(),
+        )
+    }
+
+    fn rewrite(&self, sql: &str, tokens: &[Token]) -> Option<CodeAction> {
+        let mut joins = vec![Vec::new(); self.relations.len()];
+        let mut remaining = Vec::new();
+        for (condition, range) in self.conditions.iter().zip(&self.condition_ranges) {
+            match self.relations_of(condition) {
+                Some(relations) if relations.len() > 1 => {
+                    joins[*relations.last().unwrap()].push(&sql[*range])
+                }
+                _ => remaining.push(&sql[*range]),
+            }
+        }
+        if joins.iter().all(|j| j.is_empty()) {
+            return None;
+        }
+
+        // follow the case of the keywords of the statement
+        let keyword = |k: &str| {
+            if sql[tokens[self.where_idx].span]
+                .chars()
+                .all(|c| c.is_uppercase())
+            {
+                k.to_uppercase()
+            } else {
+                k.to_string()
+            }
+        };
+
+        let mut from = sql[self.items[0]].to_string();
+        for (item, conditions) in self.items.iter().zip(&joins).skip(1) {
+            if conditions.is_empty() {
+                from.push_str(&format!(" {} {}", keyword("cross join"), &sql[*item]));
+            } else {
+                from.push_str(&format!(
+                    " {} {} {} {}",
+                    keyword("join"),
+                    &sql[*item],
+                    keyword("on"),
+                    conditions.join(&format!(" {} ", keyword("and")))
+                ));
+            }
+        }
+
+        let conditions = TextRange::new(
+            self.condition_ranges[0].start(),
+            self.condition_ranges.last().unwrap().end(),
+        );
+        let where_edit = if remaining.is_empty() {
+            // remove the whole clause including the whitespace before it
+            let start = tokens[self.where_idx - 1].span.end();
+            (TextRange::new(start, conditions.end()), String::new())
+        } else {
+            (conditions, remaining.join(&format!(" {} ", keyword("and"))))
+        };
+
+        Some(CodeAction {
+            title: "Convert to explicit joins".to_string(),
+            kind: CodeActionKind::RefactorRewrite,
+            edits: vec![
+                (
+                    TextRange::new(self.items[0].start(), self.items.last().unwrap().end()),
+                    from,
+                ),
+                where_edit,
+            ],
+        })
+    }
+
+    /// Returns the indices of the relations a condition refers to in ascending order, or `None`
+    /// if it refers to columns that cannot be attributed to one of them
+    fn relations_of(&self, condition: &protobuf::Node) -> Option<Vec<usize>> {
+        let mut relations = Vec::new();
+        for node in ChildrenIterator::new(condition.node.clone()?) {
+            match node {
+                NodeEnum::SubLink(_) => return None,
+                NodeEnum::ColumnRef(c) => {
+                    let names = c
+                        .fields
+                        .iter()
+                        .map(|f| match &f.node {
+                            Some(NodeEnum::String(s)) => Some(s.sval.as_str()),
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    let idx = match names.as_slice() {
+                        [relation, _] => self.relations.iter().position(|r| match &r.alias {
+                            Some(alias) => alias.aliasname == *relation,
+                            None => r.relname == *relation,
+                        }),
+                        [schema, relation, _] => self.relations.iter().position(|r| {
+                            r.alias.is_none() && r.schemaname == *schema && r.relname == *relation
+                        }),
+                        _ => None,
+                    }?;
+                    if !relations.contains(&idx) {
+                        relations.push(idx);
+                    }
+                }
+                _ => {}
+            }
+        }
+        relations.sort();
+        Some(relations)
+    }
+}
+
+/// Splits the tokens starting at `start` at the top-level separators until the end of the
+/// clause, and returns the ranges of the parts and the index of the token after the last one
+fn split(tokens: &[Token], start: usize, separator: SyntaxKind) -> (Vec<TextRange>, usize) {
+    let mut parts = Vec::new();
+    let mut part_start = start;
+    let mut depth = 0;
+    let mut idx = start;
+    while let Some(token) = tokens.get(idx) {
+        match token.kind {
+            SyntaxKind::Ascii40 => depth += 1,
+            SyntaxKind::Ascii41 if depth == 0 => break,
+            SyntaxKind::Ascii41 => depth -= 1,
+            kind if depth == 0 && kind == separator => {
+                if idx > part_start {
+                    parts.push(TextRange::new(
+                        tokens[part_start].span.start(),
+                        tokens[idx - 1].span.end(),
+                    ));
+                }
+                part_start = idx + 1;
+            }
+            SyntaxKind::Where
+            | SyntaxKind::GroupP
+            | SyntaxKind::Having
+            | SyntaxKind::Window
+            | SyntaxKind::Order
+            | SyntaxKind::Limit
+            | SyntaxKind::Offset
+            | SyntaxKind::Fetch
+            | SyntaxKind::For
+            | SyntaxKind::Union
+            | SyntaxKind::Intersect
+            | SyntaxKind::Except
+            | SyntaxKind::Ascii59
+                if depth == 0 =>
+            {
+                break
+            }
+            _ => {}
+        }
+        idx += 1;
+    }
+    if idx > part_start {
+        parts.push(TextRange::new(
+            tokens[part_start].span.start(),
+            tokens[idx - 1].span.end(),
+        ));
+    }
+    (parts, idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use text_size::{TextRange, TextSize};
+
+    use super::explicit_joins;
+
+    fn rewrite(sql: &str) -> Option<String> {
+        let root = pg_query_ext::parse(sql).unwrap();
+        let action = explicit_joins(sql, &root, TextRange::empty(TextSize::new(16)))?;
+        let mut sql = sql.to_string();
+        for (range, text) in action.edits.into_iter().rev() {
+            sql.replace_range(std::ops::Range::<usize>::from(range), &text);
+        }
+        Some(sql)
+    }
+
+    #[test]
+    fn test_explicit_joins() {
+        assert_eq!(
+            rewrite("select * from users u, orders o where u.id = o.user_id").as_deref(),
+            Some("select * from users u join orders o on u.id = o.user_id")
+        );
+        assert_eq!(
+            rewrite(
+                "SELECT * FROM users u, orders o, items i, tags WHERE o.id = i.order_id AND u.active AND u.id = o.user_id ORDER BY 1"
+            )
+            .as_deref(),
+            Some("SELECT * FROM users u JOIN orders o ON u.id = o.user_id JOIN items i ON o.id = i.order_id CROSS JOIN tags WHERE u.active ORDER BY 1")
+        );
+        assert_eq!(
+            rewrite("select * from users u, orders o where id = user_id"),
+            None
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use pg_lexer::{SyntaxKind, Token};
+use pg_query_ext::{ChildrenIterator, NodeEnum};
+use text_size::{TextRange, TextSize};
+
+use crate::code_actions::{CodeAction, CodeActionKind};
+
+/// Offers to move the innermost subquery that contains the range into a common table expression
+/// and to refer to it by name instead
+///
+/// A subquery in a `FROM` clause is replaced with the name of the CTE and keeps its alias, a
+/// subquery within an expression with a query that selects from the CTE. The CTE is added after
+/// the existing ones, or before the one that contains the subquery, so that it can refer to the
+/// same CTEs as before. Subqueries that refer to relations of the surrounding query, i.e.
+/// `LATERAL` subqueries and correlated subqueries, are not offered, since they cannot be moved.
+pub fn extract_cte(sql: &str, root: &NodeEnum, range: TextRange) -> Option<CodeAction> {
+    let with_clause = match root {
+        NodeEnum::SelectStmt(s) => s.with_clause.as_ref(),
+        NodeEnum::InsertStmt(s) => s.with_clause.as_ref(),
+        NodeEnum::UpdateStmt(s) => s.with_clause.as_ref(),
+        NodeEnum::DeleteStmt(s) => s.with_clause.as_ref(),
+        _ => return None,
+    };
+
+    let tokens = pg_lexer::lex(sql)
+        .into_iter()
+        .filter(|t| {
+            !matches!(
+                t.kind,
+                SyntaxKind::Whitespace
+                    | SyntaxKind::Newline
+                    | SyntaxKind::SqlComment
+                    | SyntaxKind::CComment
+            )
+        })
+        .collect::<Vec<_>>();
+    let groups = paren_groups(&tokens);
+    let group_range = |(open, close): (usize, usize)| {
+        TextRange::new(tokens[open].span.start(), tokens[close].span.end())
+    };
+
+    let (open, close) = groups
+        .iter()
+        .copied()
+        .filter(|(open, _)| {
+            tokens.get(open + 1).is_some_and(|t| {
+                matches!(
+                    t.kind,
+                    SyntaxKind::Select | SyntaxKind::With | SyntaxKind::Values
+                )
+            })
+        })
+        .filter(|group| group_range(*group).contains_range(range))
+        .min_by_key(|group| group_range(*group).len())?;
+
+    // the bodies of the existing CTEs are query groups as well
+    let ctes = with_clause
+        .map(|w| {
+            w.ctes
+                .iter()
+                .filter_map(|c| match &c.node {
+                    Some(NodeEnum::CommonTableExpr(cte)) => {
+                        let start = TextSize::try_from(usize::try_from(cte.location).ok()?).ok()?;
+                        let body = cte_body(&tokens, &groups, start)?;
+                        Some((start, body))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if ctes.iter().any(|(_, body)| *body == (open, close)) {
+        return None;
+    }
+
+    let subquery_range = TextRange::new(tokens[open + 1].span.start(), tokens[close].span.start());
+    let subquery = sql[subquery_range].trim_end();
+    let name = unique_name(root, "subquery");
+
+    let replacement = match preceding_clause(&tokens, &groups, open) {
+        Clause::From => name.clone(),
+        Clause::Lateral => return None,
+        Clause::Expression => {
+            if refers_to_outer_relations(subquery) {
+                return None;
+            }
+            format!("(select * from {})", name)
+        }
+    };
+
+    let cte = format!("{} as ({})", name, subquery);
+    let insertion = match ctes
+        .iter()
+        .find(|(_, body)| group_range(*body).contains_range(group_range((open, close))))
+    {
+        Some((start, _)) => (TextRange::empty(*start), format!("{},\n", cte)),
+        None => match ctes.last() {
+            Some((_, body)) => (
+                TextRange::empty(group_range(*body).end()),
+                format!(",\n{}", cte),
+            ),
+            None => (TextRange::empty(0.into()), format!("with {}\n", cte)),
+        },
+    };
+
+    let mut edits = vec![insertion, (group_range((open, close)), replacement)];
+    edits.sort_by_key(|(range, _)| range.start());
+
+    Some(CodeAction {
+        title: "Extract subquery into a CTE".to_string(),
+        kind: CodeActionKind::RefactorRewrite,
+        edits,
+    })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Clause {
+    From,
+    Lateral,
+    Expression,
+}
+
+/// Returns the indices of the opening and closing token of every pair of parentheses
+fn paren_groups(tokens: &[Token]) -> Vec<(usize, usize)> {
+    let mut open = Vec::new();
+    let mut groups = Vec::new();
+    for (idx, token) in tokens.iter().enumerate() {
+        match token.kind {
+            SyntaxKind::Ascii40 => open.push(idx),
+            SyntaxKind::Ascii41 => {
+                if let Some(start) = open.pop() {
+                    groups.push((start, idx));
+                }
+            }
+            _ => {}
+        }
+    }
+    groups
+}
+
+/// Returns the clause a parenthesised query is part of by walking back to the keyword that
+/// starts it, skipping other parenthesised groups, e.g. the other items of a `FROM` clause
+fn preceding_clause(tokens: &[Token], groups: &[(usize, usize)], open: usize) -> Clause {
+    if open > 0 && tokens[open - 1].kind == SyntaxKind::LateralP {
+        return Clause::Lateral;
+    }
+
+    let mut idx = open;
+    while idx > 0 {
+        idx -= 1;
+        match tokens[idx].kind {
+            SyntaxKind::Ascii41 => {
+                if let Some((start, _)) = groups.iter().find(|(_, end)| *end == idx) {
+                    idx = *start;
+                }
+            }
+            SyntaxKind::From | SyntaxKind::Join => return Clause::From,
+            SyntaxKind::Ascii40
+            | SyntaxKind::Select
+            | SyntaxKind::Where
+            | SyntaxKind::On
+            | SyntaxKind::Having
+            | SyntaxKind::GroupP
+            | SyntaxKind::Order
+            | SyntaxKind::Set
+            | SyntaxKind::Returning
+            | SyntaxKind::Using
+            | SyntaxKind::Values => return Clause::Expression,
+            _ => {}
+        }
+    }
+    Clause::Expression
+}
+
+/// Returns the parentheses around the query of the CTE whose name starts at `start`
+fn cte_body(
+    tokens: &[Token],
+    groups: &[(usize, usize)],
+    start: TextSize,
+) -> Option<(usize, usize)> {
+    let as_idx = tokens
+        .iter()
+        .position(|t| t.span.start() >= start && t.kind == SyntaxKind::As)?;
+    groups
+        .iter()
+        .copied()
+        .filter(|(open, _)| *open > as_idx)
+        .min_by_key(|(open, _)| *open)
+}
+
+/// Whether a subquery qualifies a column with a relation it does not select from itself
+fn refers_to_outer_relations(subquery: &str) -> bool {
+    let Ok(root) = pg_query_ext::parse(subquery) else {
+        return true;
+    };
+
+    let mut names = Vec::new();
+    let mut qualifiers = Vec::new();
+    for node in ChildrenIterator::new(root) {
+        match node {
+            NodeEnum::RangeVar(r) => match &r.alias {
+                Some(alias) => names.push(alias.aliasname.clone()),
+                None => names.push(r.relname.clone()),
+            },
+            NodeEnum::RangeSubselect(r) => names.extend(r.alias.map(|a| a.aliasname)),
+            NodeEnum::RangeFunction(r) => names.extend(r.alias.map(|a| a.aliasname)),
+            NodeEnum::CommonTableExpr(c) => names.push(c.ctename.clone()),
+            NodeEnum::ColumnRef(c) if c.fields.len() > 1 => {
+                let relation = &c.fields[c.fields.len() - 2];
+                if let Some(NodeEnum::String(s)) = &relation.node {
+                    qualifiers.push(s.sval.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    qualifiers.iter().any(|q| !names.contains(q))
+}
+
+/// Returns a name that no relation, alias or CTE of the statement has
+fn unique_name(root: &NodeEnum, base: &str) -> String {
+    let names = ChildrenIterator::new(root.to_owned())
+        .flat_map(|n| match n {
+            NodeEnum::RangeVar(r) => vec![Some(r.relname), r.alias.map(|a| a.aliasname)],
+            NodeEnum::RangeSubselect(r) => vec![r.alias.map(|a| a.aliasname)],
+            NodeEnum::CommonTableExpr(c) => vec![Some(c.ctename)],
+            _ => vec![],
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+
+    std::iter::once(base.to_string())
+        .chain((2..).map(|i| format!("{}_{}", base, i)))
+        .find(|name| !names.contains(name))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use text_size::{TextRange, TextSize};
+
+    use super::extract_cte;
+
+    fn extract(sql: &str, offset: u32) -> Option<String> {
+        let root = pg_query_ext::parse(sql).unwrap();
+        let action = extract_cte(sql, &root, TextRange::empty(TextSize::new(offset)))?;
+        let mut sql = sql.to_string();
+        for (range, text) in action.edits.into_iter().rev() {
+            sql.replace_range(std::ops::Range::<usize>::from(range), &text);
+        }
+        Some(sql)
+    }
+
+    #[test]
+    fn test_extract_from_subquery() {
+        assert_eq!(
+            extract(
+                "select * from users u join (select user_id from orders) o on o.user_id = u.id",
+                30
+            )
+            .as_deref(),
+            Some("with subquery as (select user_id from orders)\nselect * from users u join subquery o on o.user_id = u.id")
+        );
+    }
+
+    #[test]
+    fn test_extract_expression_subquery() {
+        assert_eq!(
+            extract(
+                "with subquery as (select 1)\nselect * from users where id in (select user_id from orders)",
+                65
+            )
+            .as_deref(),
+            Some("with subquery as (select 1),\nsubquery_2 as (select user_id from orders)\nselect * from users where id in (select * from subquery_2)")
+        );
+    }
+
+    #[test]
+    fn test_extract_from_cte() {
+        assert_eq!(
+            extract(
+                "with a as (select * from (select 1 as x) s)\nselect * from a",
+                27
+            )
+            .as_deref(),
+            Some("with subquery as (select 1 as x),\na as (select * from subquery s)\nselect * from a")
+        );
+    }
+
+    #[test]
+    fn test_skip_correlated_subquery() {
+        assert_eq!(
+            extract(
+                "select * from users u where exists (select 1 from orders o where o.user_id = u.id)",
+                40
+            ),
+            None
+        );
+        assert_eq!(
+            extract(
+                "select * from users u, lateral (select * from orders o where o.user_id = u.id) o",
+                35
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use line_index::LineIndex;
+use pg_lexer::SyntaxKind;
+use text_size::TextRange;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldingKind {
+    Comment,
+    Region,
+}
+
+/// A range of lines that can be folded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoldingRange {
+    pub start_line: u32,
+    pub end_line: u32,
+    pub kind: FoldingKind,
+}
+
+/// Returns the foldable ranges of a document
+///
+/// Parenthesised queries, i.e. CTE bodies and subqueries, and `CASE` expressions are matched on
+/// the tokens of the document rather than on the concrete syntax trees of its statements, since
+/// those are not built for statements with CTEs or subqueries yet. Comment blocks and
+/// dollar-quoted strings, e.g. function bodies, fold as well. Only ranges that span multiple lines
+/// are returned.
+pub fn folding_ranges(text: &str, line_index: &LineIndex) -> Vec<FoldingRange> {
+    let mut ranges = Vec::new();
+    let mut push = |range: TextRange, kind: FoldingKind| {
+        let start_line = line_index.line_col(range.start()).line;
+        let end_line = line_index.line_col(range.end()).line;
+        if end_line > start_line {
+            ranges.push(FoldingRange {
+                start_line,
+                end_line,
+                kind,
+            });
+        }
+    };
+
+    let tokens = pg_lexer::lex(text)
+        .into_iter()
+        .filter(|t| !matches!(t.kind, SyntaxKind::Whitespace | SyntaxKind::Newline))
+        .collect::<Vec<_>>();
+
+    // open parentheses, with whether they enclose a query, and `CASE` keywords
+    let mut open: Vec<(SyntaxKind, bool, TextRange)> = Vec::new();
+    // consecutive line comments are folded as one block
+    let mut comment_block: Option<TextRange> = None;
+
+    for (idx, token) in tokens.iter().enumerate() {
+        if token.kind == SyntaxKind::SqlComment {
+            comment_block = Some(match comment_block {
+                Some(block) => block.cover(token.span),
+                None => token.span,
+            });
+            continue;
+        }
+        if let Some(block) = comment_block.take() {
+            push(block, FoldingKind::Comment);
+        }
+
+        match token.kind {
+            SyntaxKind::CComment => push(token.span, FoldingKind::Comment),
+            SyntaxKind::Sconst if token.text.starts_with('$') => {
+                push(token.span, FoldingKind::Region)
+            }
+            SyntaxKind::Ascii40 => {
+                let is_query = tokens[idx + 1..]
+                    .iter()
+                    .find(|t| !matches!(t.kind, SyntaxKind::SqlComment | SyntaxKind::CComment))
+                    .is_some_and(|t| {
+                        matches!(
+                            t.kind,
+                            SyntaxKind::Select | SyntaxKind::With | SyntaxKind::Values
+                        )
+                    });
+                open.push((SyntaxKind::Ascii40, is_query, token.span));
+            }
+            SyntaxKind::Ascii41 => {
+                while let Some((kind, is_query, start)) = open.pop() {
+                    if kind == SyntaxKind::Ascii40 {
+                        if is_query {
+                            push(start.cover(token.span), FoldingKind::Region);
+                        }
+                        break;
+                    }
+                }
+            }
+            SyntaxKind::Case => open.push((SyntaxKind::Case, false, token.span)),
+            SyntaxKind::EndP
+                if open
+                    .last()
+                    .is_some_and(|(kind, _, _)| *kind == SyntaxKind::Case) =>
+            {
+                let (_, _, start) = open.pop().unwrap();
+                push(start.cover(token.span), FoldingKind::Region);
+            }
+            // unbalanced parentheses do not leak into the next statement
+            SyntaxKind::Ascii59 => open.clear(),
+            _ => {}
+        }
+    }
+    if let Some(block) = comment_block {
+        push(block, FoldingKind::Comment);
+    }
+
+    ranges.sort_by_key(|r| (r.start_line, r.end_line));
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use line_index::LineIndex;
+
+    use super::{folding_ranges, FoldingKind, FoldingRange};
+
+    #[test]
+    fn test_folding_ranges() {
+        let text = "-- users that
+-- ordered recently
+with recent as (
+  select user_id from orders
+  where created_at > now() - interval '1 day'
+)
+select case
+    when id in (select user_id from recent) then 'recent'
+    else 'old'
+  end
+from users;
+/*
+ * helpers
+ */
+create function one() returns int as $$
+  select 1;
+$$ language sql;
+";
+        let region = |start_line, end_line| FoldingRange {
+            start_line,
+            end_line,
+            kind: FoldingKind::Region,
+        };
+        let comment = |start_line, end_line| FoldingRange {
+            start_line,
+            end_line,
+            kind: FoldingKind::Comment,
+        };
+
+        assert_eq!(
+            folding_ranges(text, &LineIndex::new(text)),
+            vec![
+                comment(0, 1),
+                region(2, 5),
+                region(6, 9),
+                comment(11, 13),
+                region(14, 16),
+            ]
+        );
+    }
+}
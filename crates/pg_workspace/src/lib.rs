@@ -1,42 +1,78 @@
+mod code_actions;
+mod connections;
+mod did_you_mean;
+mod existence_guards;
+mod explicit_joins;
+mod extract_cte;
+mod folding;
 mod lint;
+mod object_index;
 mod pg_query;
+mod rename;
+mod required_columns;
+mod selection;
+mod semantic_tokens;
+mod suppressions;
+mod symbols;
 mod tree_sitter;
 mod typecheck;
 
-use std::sync::{RwLock, RwLockWriteGuard};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+};
 
 use dashmap::{DashMap, DashSet};
+use line_index::LineIndex;
 use lint::Linter;
+use object_index::{statement_definitions, ObjectIndex, ObjectReference};
 use pg_base_db::{Document, DocumentChange, PgLspPath, StatementRef};
 use pg_query::PgQueryParser;
 use pg_schema_cache::SchemaCache;
 use sqlx::PgPool;
+use text_size::{TextRange, TextSize};
 use tree_sitter::TreeSitterParser;
 use typecheck::Typechecker;
 
+pub use code_actions::{apply_code_actions, CodeAction, CodeActionKind};
+pub use connections::{is_glob, matches_path, path_pattern, ConnectionRoutes, DEFAULT_CONNECTION};
+pub use folding::{FoldingKind, FoldingRange};
+pub use object_index::{ObjectDefinition, ObjectKind, ReferenceKind};
+pub use semantic_tokens::{SemanticToken, SemanticTokenKind};
+pub use symbols::{ObjectSymbol, StatementSymbol};
+
 pub struct Workspace {
     pub documents: DashMap<PgLspPath, Document>,
     // Stores the statements that have changed since the last analysis
     changed_stmts: DashSet<StatementRef>,
-    pub schema_cache: RwLock<SchemaCache>,
+    /// Statements that were parsed but not yet typechecked
+    unchecked_stmts: DashSet<StatementRef>,
+    // The schema cache of every connection along with its version hash, see
+    // `SchemaCache::version_hash`
+    schema_caches: DashMap<String, (Arc<SchemaCache>, String)>,
+    connection_routes: RwLock<ConnectionRoutes>,
 
     pub tree_sitter: TreeSitterParser,
     pub pg_query: PgQueryParser,
     pub linter: Linter,
     pub typechecker: Typechecker,
+    pub objects: ObjectIndex,
 }
 
 impl Workspace {
     pub fn new() -> Workspace {
         Workspace {
             documents: DashMap::new(),
-            schema_cache: RwLock::new(SchemaCache::new()),
+            schema_caches: DashMap::new(),
+            connection_routes: RwLock::new(ConnectionRoutes::default()),
             changed_stmts: DashSet::new(),
+            unchecked_stmts: DashSet::new(),
 
             tree_sitter: TreeSitterParser::new(),
             pg_query: PgQueryParser::new(),
             linter: Linter::new(),
             typechecker: Typechecker::new(),
+            objects: ObjectIndex::new(),
         }
     }
 
@@ -68,6 +104,7 @@ impl Workspace {
                     self.typechecker.clear_statement_errors(s);
 
                     self.changed_stmts.insert(s.to_owned());
+                    self.unchecked_stmts.remove(s);
                 }
                 pg_base_db::StatementChange::Modified(s) => {
                     self.tree_sitter.modify_statement(s);
@@ -76,10 +113,444 @@ impl Workspace {
                     self.typechecker.clear_statement_errors(&s.statement);
 
                     self.changed_stmts.remove(&s.statement);
+                    self.unchecked_stmts.remove(&s.statement);
                     self.changed_stmts.insert(s.new_statement().to_owned());
                 }
             }
         }
+
+        let definitions = doc
+            .statement_refs_with_range()
+            .into_iter()
+            .filter_map(|(range, stmt)| {
+                self.pg_query
+                    .ast(&stmt)
+                    .map(|ast| statement_definitions(&ast, range))
+            })
+            .flatten()
+            .collect();
+        self.objects
+            .set_definitions(doc.url.clone(), doc.line_index.clone(), definitions);
+    }
+
+    /// Returns the line index of an open document or an indexed workspace file
+    pub fn line_index(&self, url: &PgLspPath) -> Option<LineIndex> {
+        self.documents
+            .get(url)
+            .map(|doc| doc.line_index.clone())
+            .or_else(|| self.objects.line_index(url))
+    }
+
+    /// Returns the statements that create the object referenced at the offset of a document
+    pub fn find_definitions(
+        &self,
+        url: &PgLspPath,
+        offset: TextSize,
+    ) -> Vec<(PgLspPath, TextRange)> {
+        let Some(doc) = self.documents.get(url) else {
+            return Vec::new();
+        };
+        let Some((range, stmt)) = doc.statement_at_offset_with_range(&offset) else {
+            return Vec::new();
+        };
+        let Some(ast) = self.pg_query.enriched_ast(&stmt) else {
+            return Vec::new();
+        };
+
+        ObjectReference::at(&ast, offset - range.start())
+            .map(|reference| self.objects.find(&reference))
+            .unwrap_or_default()
+    }
+
+    /// Returns all references to the table, column or function referenced at the offset of a
+    /// document, both in open documents and in the other files of the workspace
+    pub fn find_references(
+        &self,
+        url: &PgLspPath,
+        offset: TextSize,
+    ) -> Vec<(PgLspPath, TextRange)> {
+        let reference = {
+            let Some(doc) = self.documents.get(url) else {
+                return Vec::new();
+            };
+            let Some((range, stmt)) = doc.statement_at_offset_with_range(&offset) else {
+                return Vec::new();
+            };
+            let Some(ast) = self.statement_ast(&stmt) else {
+                return Vec::new();
+            };
+            match ObjectReference::at(&ast, offset - range.start()) {
+                Some(r) if r.kind != ReferenceKind::Type => r,
+                _ => return Vec::new(),
+            }
+        };
+
+        let mut references = Vec::new();
+        for doc in self.documents.iter() {
+            for (range, stmt) in doc.statement_refs_with_range() {
+                let Some(ast) = self.statement_ast(&stmt) else {
+                    continue;
+                };
+                references.extend(
+                    ObjectReference::collect(&ast)
+                        .into_iter()
+                        .filter(|r| r.refers_to_same(&reference))
+                        .map(|r| (doc.url.clone(), r.range + range.start())),
+                );
+            }
+        }
+        references.extend(
+            self.objects
+                .find_references(&reference, |path| self.documents.contains_key(path)),
+        );
+        references
+    }
+
+    /// Returns the foldable ranges of a document
+    pub fn folding_ranges(&self, url: &PgLspPath) -> Vec<FoldingRange> {
+        self.documents
+            .get(url)
+            .map(|doc| folding::folding_ranges(&doc.text, &doc.line_index))
+            .unwrap_or_default()
+    }
+
+    /// Returns the ranges to expand the selection through at the offset of a document, innermost
+    /// first
+    pub fn selection_ranges(&self, url: &PgLspPath, offset: TextSize) -> Vec<TextRange> {
+        let Some(doc) = self.documents.get(url) else {
+            return Vec::new();
+        };
+        let Some((range, stmt)) = doc.statement_at_offset_with_range(&offset) else {
+            return Vec::new();
+        };
+        let Some(ast) = self.statement_ast(&stmt) else {
+            return Vec::new();
+        };
+
+        selection::selection_ranges(&stmt.text, &ast, offset - range.start())
+            .into_iter()
+            .map(|r| r + range.start())
+            .collect()
+    }
+
+    /// Returns one symbol per statement of a document
+    pub fn document_symbols(&self, url: &PgLspPath) -> Vec<StatementSymbol> {
+        let Some(doc) = self.documents.get(url) else {
+            return Vec::new();
+        };
+
+        doc.statement_refs_with_range()
+            .into_iter()
+            .map(|(range, stmt)| {
+                let root = self.pg_query.ast(&stmt);
+                symbols::statement_symbol(&stmt.text, root.as_deref(), range)
+            })
+            .collect()
+    }
+
+    /// Returns the classified tokens of a document together with its version
+    pub fn semantic_tokens(&self, url: &PgLspPath) -> Option<(i32, Vec<SemanticToken>)> {
+        let doc = self.documents.get(url)?;
+
+        let statements = doc
+            .statement_refs_with_range()
+            .into_iter()
+            .map(|(range, stmt)| (range, self.pg_query.ast(&stmt).map(|ast| (*ast).clone())))
+            .collect::<Vec<_>>();
+
+        Some((
+            doc.version,
+            semantic_tokens::semantic_tokens(&doc.text, &statements),
+        ))
+    }
+
+    /// Returns the tables, foreign tables and functions whose name matches the query
+    pub fn workspace_symbols(&self, query: &str) -> Vec<ObjectSymbol> {
+        let schema_caches = self
+            .schema_caches
+            .iter()
+            .map(|entry| entry.0.clone())
+            .collect::<Vec<_>>();
+
+        symbols::object_symbols(
+            query,
+            self.objects.definitions(),
+            schema_caches.iter().map(|cache| cache.as_ref()),
+        )
+    }
+
+    /// Returns the range of the alias or CTE name at the offset of a document, if it can be renamed
+    pub fn prepare_rename(&self, url: &PgLspPath, offset: TextSize) -> Option<TextRange> {
+        let doc = self.documents.get(url)?;
+        let (range, stmt) = doc.statement_at_offset_with_range(&offset)?;
+        let root = self.pg_query.ast(&stmt)?;
+
+        rename::rename_range(&stmt.text, &root, offset - range.start()).map(|r| r + range.start())
+    }
+
+    /// Returns the edits that rename the alias or CTE name at the offset of a document within its
+    /// statement
+    pub fn rename(
+        &self,
+        url: &PgLspPath,
+        offset: TextSize,
+        new_name: &str,
+    ) -> Vec<(TextRange, String)> {
+        let Some(doc) = self.documents.get(url) else {
+            return Vec::new();
+        };
+        let Some((range, stmt)) = doc.statement_at_offset_with_range(&offset) else {
+            return Vec::new();
+        };
+        let Some(root) = self.pg_query.ast(&stmt) else {
+            return Vec::new();
+        };
+
+        let new_name = rename::quote_identifier(new_name);
+        rename::rename_occurrences(&stmt.text, &root, offset - range.start())
+            .into_iter()
+            .map(|r| (r + range.start(), new_name.clone()))
+            .collect()
+    }
+
+    /// Returns the occurrences of the alias or CTE name at the offset of a document that are
+    /// edited together
+    pub fn linked_editing_ranges(&self, url: &PgLspPath, offset: TextSize) -> Vec<TextRange> {
+        let Some(doc) = self.documents.get(url) else {
+            return Vec::new();
+        };
+        let Some((range, stmt)) = doc.statement_at_offset_with_range(&offset) else {
+            return Vec::new();
+        };
+        let Some(root) = self.pg_query.ast(&stmt) else {
+            return Vec::new();
+        };
+
+        rename::rename_occurrences(&stmt.text, &root, offset - range.start())
+            .into_iter()
+            .map(|r| r + range.start())
+            .collect()
+    }
+
+    /// Returns the actions that edit the statements of a document intersecting the range
+    pub fn code_actions(&self, url: &PgLspPath, range: TextRange) -> Vec<CodeAction> {
+        let Some(doc) = self.documents.get(url) else {
+            return Vec::new();
+        };
+        let schema_cache = self.document_schema_cache(url);
+
+        let mut actions = Vec::new();
+        for (stmt_range, stmt) in doc.statement_refs_with_range() {
+            let Some(intersection) = stmt_range.intersect(range) else {
+                continue;
+            };
+            let Some(root) = self.pg_query.ast(&stmt) else {
+                continue;
+            };
+            let relative = intersection - stmt_range.start();
+
+            let errors = self.typechecker.errors(&stmt).unwrap_or_default();
+
+            actions.extend(
+                code_actions::expand_star(&stmt.text, &root, relative, &schema_cache)
+                    .into_iter()
+                    .chain(code_actions::insert_column_list(
+                        &stmt.text,
+                        &root,
+                        relative,
+                        &schema_cache,
+                    ))
+                    .chain(code_actions::fix_misspelled_identifiers(
+                        &stmt.text,
+                        &root,
+                        &errors,
+                        relative,
+                        &schema_cache,
+                    ))
+                    .chain(code_actions::qualify_ambiguous_columns(
+                        &stmt.text,
+                        &root,
+                        &errors,
+                        relative,
+                        &schema_cache,
+                    ))
+                    .chain(required_columns::add_missing_columns(
+                        &stmt.text,
+                        &root,
+                        relative,
+                        &schema_cache,
+                    ))
+                    .chain(extract_cte::extract_cte(&stmt.text, &root, relative))
+                    .chain(explicit_joins::explicit_joins(&stmt.text, &root, relative))
+                    .chain(existence_guards::add_existence_guard(&stmt.text, &root))
+                    .chain(
+                        self.lint_fixes(&doc.text, stmt_range, &stmt)
+                            .into_iter()
+                            .filter(|(range, _)| range.intersect(relative).is_some())
+                            .map(|(_, fix)| CodeAction {
+                                title: fix.title,
+                                kind: CodeActionKind::QuickFix,
+                                edits: fix.edits,
+                            }),
+                    )
+                    .map(|action| CodeAction {
+                        edits: action
+                            .edits
+                            .into_iter()
+                            .map(|(r, text)| (r + stmt_range.start(), text))
+                            .collect(),
+                        ..action
+                    }),
+            );
+        }
+
+        let statements = doc
+            .statement_refs_with_range()
+            .into_iter()
+            .map(|(stmt_range, stmt)| (stmt_range, self.pg_query.ast(&stmt)))
+            .collect::<Vec<_>>();
+        actions.extend(code_actions::wrap_in_transaction(
+            &doc.text,
+            &statements,
+            range,
+        ));
+
+        let mut suppressions = HashSet::new();
+        for diagnostic in self.document_diagnostics(&doc, &schema_cache) {
+            let Some(code) = diagnostic.code else {
+                continue;
+            };
+            if diagnostic.range.intersect(range).is_none() {
+                continue;
+            }
+            let edit = suppressions::suppress(&doc.text, diagnostic.range.start(), &code);
+            if !suppressions.insert((edit.0, code.clone())) {
+                continue;
+            }
+            actions.push(CodeAction {
+                title: format!("Suppress with `-- pglsp-ignore {}`", code),
+                kind: CodeActionKind::QuickFix,
+                edits: vec![edit],
+            });
+        }
+
+        actions
+    }
+
+    /// Returns a single action that applies the safe fixes of the enabled lint rules to a
+    /// document, or `None` if there is nothing to fix
+    ///
+    /// Only statements that parse are fixed, so that statements that are still being written are
+    /// left alone.
+    pub fn fix_all(&self, url: &PgLspPath) -> Option<CodeAction> {
+        let doc = self.documents.get(url)?;
+
+        let edits = doc
+            .statement_refs_with_range()
+            .into_iter()
+            .filter(|(_, stmt)| self.pg_query.ast(stmt).is_some())
+            .flat_map(|(stmt_range, stmt)| {
+                self.lint_fixes(&doc.text, stmt_range, &stmt)
+                    .into_iter()
+                    .filter(|(_, fix)| fix.safe)
+                    .flat_map(|(_, fix)| fix.edits)
+                    .map(move |(range, text)| (range + stmt_range.start(), text))
+            })
+            .collect::<Vec<_>>();
+        if edits.is_empty() {
+            return None;
+        }
+
+        Some(CodeAction {
+            title: "Fix all auto-fixable problems".to_string(),
+            kind: CodeActionKind::SourceFixAll,
+            edits,
+        })
+    }
+
+    /// Returns the fixes of a document that apply without choosing between alternatives, e.g. for
+    /// `pglsp check --write`
+    ///
+    /// The safe fixes are those of [`Workspace::fix_all`]. Unsafe fixes may change the meaning of
+    /// the sql, e.g. by changing a misspelled name to the closest one, and are only included if
+    /// asked for.
+    pub fn fixes(&self, url: &PgLspPath, include_unsafe: bool) -> Vec<CodeAction> {
+        let mut fixes = self.fix_all(url).into_iter().collect::<Vec<_>>();
+        if !include_unsafe {
+            return fixes;
+        }
+        let Some(doc) = self.documents.get(url) else {
+            return fixes;
+        };
+        let schema_cache = self.document_schema_cache(url);
+
+        for (stmt_range, stmt) in doc.statement_refs_with_range() {
+            let Some(root) = self.pg_query.ast(&stmt) else {
+                continue;
+            };
+            let errors = self.typechecker.errors(&stmt).unwrap_or_default();
+            fixes.extend(
+                code_actions::fix_misspelled_identifiers(
+                    &stmt.text,
+                    &root,
+                    &errors,
+                    TextRange::up_to(TextSize::of(stmt.text.as_str())),
+                    &schema_cache,
+                )
+                .into_iter()
+                .chain(
+                    self.lint_fixes(&doc.text, stmt_range, &stmt)
+                        .into_iter()
+                        .filter(|(_, fix)| !fix.safe)
+                        .map(|(_, fix)| CodeAction {
+                            title: fix.title,
+                            kind: CodeActionKind::QuickFix,
+                            edits: fix.edits,
+                        }),
+                )
+                .map(|action| CodeAction {
+                    edits: action
+                        .edits
+                        .into_iter()
+                        .map(|(r, text)| (r + stmt_range.start(), text))
+                        .collect(),
+                    ..action
+                }),
+            );
+        }
+        fixes
+    }
+
+    /// Returns the fixes of the lint violations of a statement that are not suppressed, with the
+    /// range of the violation within the statement
+    fn lint_fixes(
+        &self,
+        text: &str,
+        stmt_range: TextRange,
+        stmt: &StatementRef,
+    ) -> Vec<(TextRange, pg_lint::RuleFix)> {
+        let statement = TextRange::up_to(stmt_range.len());
+        self.linter
+            .fixes(stmt)
+            .into_iter()
+            .map(|(kind, range, fix)| (kind, range.unwrap_or(statement), fix))
+            .filter(|(kind, range, _)| {
+                !suppressions::is_suppressed(
+                    text,
+                    range.start() + stmt_range.start(),
+                    &kind.to_string(),
+                )
+            })
+            .map(|(_, range, fix)| (range, fix))
+            .collect()
+    }
+
+    /// Returns the enriched AST of a statement, parsing it if it has not been analysed yet
+    fn statement_ast(&self, stmt: &StatementRef) -> Option<Arc<pg_syntax::AST>> {
+        self.pg_query.enriched_ast(stmt).or_else(|| {
+            let root = self.pg_query.ast(stmt)?;
+            Some(Arc::new(pg_syntax::parse_syntax(&stmt.text, &root).ast))
+        })
     }
 
     pub fn remove_document(&self, url: PgLspPath) {
@@ -97,27 +568,72 @@ impl Workspace {
 
     /// Collects all diagnostics for a given document. It does not compute them, it just collects.
     pub fn diagnostics(&self, url: &PgLspPath) -> Vec<pg_diagnostics::Diagnostic> {
-        let mut diagnostics: Vec<pg_diagnostics::Diagnostic> = vec![];
-
-        let doc = self.documents.get(&url);
+        let Some(doc) = self.documents.get(url) else {
+            return Vec::new();
+        };
 
-        if doc.is_none() {
-            return diagnostics;
-        }
+        self.document_diagnostics(&doc, &self.document_schema_cache(url))
+    }
 
-        let doc = doc.unwrap();
+    fn document_diagnostics(
+        &self,
+        doc: &Document,
+        schema_cache: &SchemaCache,
+    ) -> Vec<pg_diagnostics::Diagnostic> {
+        let mut diagnostics: Vec<pg_diagnostics::Diagnostic> = vec![];
 
         for (range, stmt) in doc.statement_refs_with_range() {
             diagnostics.extend(self.pg_query.diagnostics(&stmt, range));
             diagnostics.extend(self.linter.diagnostics(&stmt, range));
             diagnostics.extend(self.typechecker.diagnostics(&stmt, range));
+            if let Some(missing) = self
+                .pg_query
+                .ast(&stmt)
+                .and_then(|root| required_columns::missing_columns(&stmt.text, &root, schema_cache))
+            {
+                let mut diagnostic = required_columns::diagnostic(&missing);
+                diagnostic.range += range.start();
+                diagnostics.push(diagnostic);
+            }
         }
 
+        diagnostics.retain(|d| {
+            d.code
+                .as_ref()
+                .is_none_or(|code| !suppressions::is_suppressed(&doc.text, d.range.start(), code))
+        });
         diagnostics
     }
 
     /// Drain changed statements to kick off analysis
-    pub fn compute(&self, conn: Option<PgPool>) -> Vec<StatementRef> {
+    ///
+    /// Statements are typechecked against the connection their document is routed to, if that
+    /// connection is among the given ones.
+    pub fn compute(&self, conns: &HashMap<String, PgPool>) -> Vec<StatementRef> {
+        self.compute_with_progress(conns, |_, _| true)
+    }
+
+    /// Like `compute`, but reports the progress of the typecheck, see
+    /// `compute_typecheck_with_progress`
+    pub fn compute_with_progress(
+        &self,
+        conns: &HashMap<String, PgPool>,
+        progress: impl FnMut(usize, usize) -> bool,
+    ) -> Vec<StatementRef> {
+        let mut changed = self.compute_syntax();
+        for stmt in self.compute_typecheck_with_progress(conns, progress) {
+            if !changed.contains(&stmt) {
+                changed.push(stmt);
+            }
+        }
+        changed
+    }
+
+    /// Drain changed statements to run the analysis that does not need a database, i.e. parsing
+    /// and linting
+    ///
+    /// The statements are typechecked by the next call to `compute_typecheck`.
+    pub fn compute_syntax(&self) -> Vec<StatementRef> {
         let changed: Vec<StatementRef> = self
             .changed_stmts
             .iter()
@@ -127,46 +643,153 @@ impl Workspace {
         self.changed_stmts.clear();
 
         changed.iter().for_each(|stmt| {
-            self.pg_query.compute_cst(stmt);
-
-            if let Some(ast) = self.pg_query.ast(stmt) {
-                self.linter.compute_statement_violations(
-                    stmt,
-                    ::pg_lint::LinterParams {
-                        ast: ast.as_ref(),
-                        enriched_ast: self
-                            .pg_query
-                            .enriched_ast(stmt)
-                            .as_ref()
-                            .map(|a| a.as_ref()),
-                    },
-                );
-                if let Some(conn) = conn.as_ref() {
-                    self.typechecker.run_typecheck(
-                        stmt,
-                        ::pg_typecheck::TypecheckerParams {
-                            conn,
-                            sql: &stmt.text,
-                            ast: ast.as_ref(),
-                            enriched_ast: self
-                                .pg_query
-                                .enriched_ast(stmt)
-                                .as_ref()
-                                .map(|a| a.as_ref()),
-                        },
-                    );
-                }
+            if self.analyse_syntax(stmt) {
+                self.unchecked_stmts.insert(stmt.clone());
             }
         });
         changed
     }
 
-    pub fn set_schema_cache(&self, cache: SchemaCache) {
-        let mut schema_cache: RwLockWriteGuard<SchemaCache> = self.schema_cache.write().unwrap();
-        *schema_cache = cache;
+    /// Parses and lints a statement, and returns whether it could be parsed
+    fn analyse_syntax(&self, stmt: &StatementRef) -> bool {
+        tracing::debug_span!("parse").in_scope(|| self.pg_query.compute_cst(stmt));
+
+        let Some(ast) = self.pg_query.ast(stmt) else {
+            return false;
+        };
+        let _span = tracing::debug_span!("lint").entered();
+        self.linter.compute_statement_violations(
+            stmt,
+            ast.as_ref(),
+            self.pg_query
+                .enriched_ast(stmt)
+                .as_ref()
+                .map(|a| a.as_ref()),
+            &self.document_schema_cache(&stmt.document_url),
+        );
+        true
+    }
+
+    fn typecheck(&self, stmt: &StatementRef, conn: &PgPool) {
+        let _span = tracing::debug_span!("typecheck").entered();
+        let Some(ast) = self.pg_query.ast(stmt) else {
+            return;
+        };
+        self.typechecker.run_typecheck(
+            stmt,
+            ::pg_typecheck::TypecheckerParams {
+                conn,
+                sql: &stmt.text,
+                ast: ast.as_ref(),
+                enriched_ast: self
+                    .pg_query
+                    .enriched_ast(stmt)
+                    .as_ref()
+                    .map(|a| a.as_ref()),
+            },
+        );
+    }
 
-        // clear all schema cache related diagnostics
-        // and add all statements to the changed statements
+    /// Drain the statements analysed by `compute_syntax` to typecheck them
+    ///
+    /// Statements are typechecked against the connection their document is routed to, if that
+    /// connection is among the given ones.
+    pub fn compute_typecheck(&self, conns: &HashMap<String, PgPool>) -> Vec<StatementRef> {
+        self.compute_typecheck_with_progress(conns, |_, _| true)
+    }
+
+    /// Like `compute_typecheck`, but calls `progress` with the number of checked statements and
+    /// their total before each statement
+    ///
+    /// Once `progress` returns `false`, the typecheck stops and the remaining statements are
+    /// checked by the next call.
+    pub fn compute_typecheck_with_progress(
+        &self,
+        conns: &HashMap<String, PgPool>,
+        mut progress: impl FnMut(usize, usize) -> bool,
+    ) -> Vec<StatementRef> {
+        let unchecked: Vec<StatementRef> = self
+            .unchecked_stmts
+            .iter()
+            .map(|arc| (*arc).clone())
+            .collect();
+
+        let total = unchecked.len();
+        let mut checked = Vec::new();
+        for (idx, stmt) in unchecked.into_iter().enumerate() {
+            if !progress(idx, total) {
+                break;
+            }
+            self.unchecked_stmts.remove(&stmt);
+
+            if let Some(conn) = conns.get(&self.connection(&stmt.document_url)) {
+                self.typecheck(&stmt, conn);
+            }
+            checked.push(stmt);
+        }
+        checked
+    }
+
+    /// Analyses sql that is not a document of the workspace, e.g. a query embedded in a string
+    /// of another language, as a document at `path`
+    ///
+    /// The sql is checked against the connection of the `host` document it is embedded in, until
+    /// `is_cancelled` returns `true`. `f` is called with the document and its diagnostics while
+    /// its statements are parsed, e.g. to compute completions, and the statements are removed
+    /// afterwards.
+    pub fn analyse_embedded<R>(
+        &self,
+        path: PgLspPath,
+        host: &PgLspPath,
+        text: String,
+        conns: &HashMap<String, PgPool>,
+        is_cancelled: &dyn Fn() -> bool,
+        f: impl FnOnce(&Document, Vec<pg_diagnostics::Diagnostic>) -> R,
+    ) -> R {
+        let doc = Document::new(path, Some(text));
+        let connection = self.connection(host);
+
+        let stmts = doc.statement_refs();
+        for stmt in &stmts {
+            self.tree_sitter.add_statement(stmt);
+            self.pg_query.add_statement(stmt);
+            if self.analyse_syntax(stmt) && !is_cancelled() {
+                if let Some(conn) = conns.get(&connection) {
+                    self.typecheck(stmt, conn);
+                }
+            }
+        }
+
+        let diagnostics = self.document_diagnostics(&doc, &self.schema_cache(&connection));
+        let result = f(&doc, diagnostics);
+
+        for stmt in &stmts {
+            self.tree_sitter.remove_statement(stmt);
+            self.pg_query.remove_statement(stmt);
+            self.linter.clear_statement_violations(stmt);
+            self.typechecker.clear_statement_errors(stmt);
+        }
+        result
+    }
+
+    /// Returns the name of the connection the document is checked against
+    pub fn connection(&self, url: &PgLspPath) -> String {
+        self.connection_routes
+            .read()
+            .unwrap()
+            .connection(url)
+            .to_string()
+    }
+
+    pub fn set_connection_routes(&self, routes: ConnectionRoutes) {
+        let mut connection_routes = self.connection_routes.write().unwrap();
+        if *connection_routes == routes {
+            return;
+        }
+        *connection_routes = routes;
+        drop(connection_routes);
+
+        // documents may be checked against another connection now
         self.typechecker.clear_errors();
         self.documents
             .iter()
@@ -175,16 +798,102 @@ impl Workspace {
                 self.changed_stmts.insert(f);
             })
     }
+
+    /// Marks all statements to be linted again by the next call to `compute_syntax`, e.g. because
+    /// the options of the rules changed
+    pub fn invalidate_lint(&self) {
+        self.documents
+            .iter()
+            .flat_map(|entry| entry.value().statement_refs())
+            .for_each(|f| {
+                self.changed_stmts.insert(f);
+            })
+    }
+
+    /// Clears the typecheck results of all documents and adds their statements to the changed
+    /// statements, e.g. after the connections they are checked against changed
+    pub fn invalidate_typecheck(&self) {
+        self.typechecker.clear_errors();
+        self.documents
+            .iter()
+            .flat_map(|entry| entry.value().statement_refs())
+            .for_each(|f| {
+                self.changed_stmts.insert(f);
+            })
+    }
+
+    /// Returns a snapshot of the schema cache of a connection
+    ///
+    /// The snapshot is shared, so it is cheap to take and does not block updates of the cache.
+    pub fn schema_cache(&self, connection: &str) -> Arc<SchemaCache> {
+        self.schema_caches
+            .get(connection)
+            .map(|entry| entry.0.clone())
+            .unwrap_or_else(|| Arc::new(SchemaCache::new()))
+    }
+
+    /// Returns a snapshot of the schema cache of the connection the document is checked against
+    pub fn document_schema_cache(&self, url: &PgLspPath) -> Arc<SchemaCache> {
+        self.schema_cache(&self.connection(url))
+    }
+
+    /// Returns the version hash of the schema cache of a connection
+    pub fn schema_version(&self, connection: &str) -> String {
+        self.schema_caches
+            .get(connection)
+            .map(|entry| entry.1.clone())
+            .unwrap_or_else(|| SchemaCache::new().version_hash())
+    }
+
+    pub fn set_schema_cache(&self, connection: &str, cache: SchemaCache) {
+        let version = cache.version_hash();
+
+        let previous_version = self
+            .schema_caches
+            .insert(connection.to_string(), (Arc::new(cache), version.clone()))
+            .map(|(_, version)| version)
+            .unwrap_or_else(|| SchemaCache::new().version_hash());
+
+        if previous_version == version {
+            // only statistics changed, so all results derived from the schema are still valid
+            return;
+        }
+
+        self.invalidate_connection(connection);
+    }
+
+    /// Clears all schema cache related diagnostics of the documents checked against the
+    /// connection and adds their statements to the changed statements, e.g. to check them again
+    /// after the database changed in a way the schema cache does not reflect
+    pub fn invalidate_connection(&self, connection: &str) {
+        let routes = self.connection_routes.read().unwrap();
+        self.documents
+            .iter()
+            .filter(|entry| routes.connection(entry.key()) == connection)
+            .flat_map(|entry| entry.value().statement_refs())
+            .for_each(|f| {
+                self.typechecker.clear_statement_errors(&f);
+                self.changed_stmts.insert(f);
+            })
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        path::Path,
+    };
 
     use pg_base_db::{Change, DocumentChange};
-    use pg_diagnostics::Diagnostic;
+    use pg_diagnostics::{Diagnostic, Severity};
+    use pg_schema_cache::SchemaCache;
     use text_size::{TextRange, TextSize};
 
-    use crate::{PgLspPath, Workspace};
+    use crate::{
+        apply_code_actions, CodeActionKind, ConnectionRoutes, PgLspPath, Workspace,
+        DEFAULT_CONNECTION,
+    };
 
     #[test]
     fn test_apply_change() {
@@ -202,6 +911,360 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_compute_tiers() {
+        let ide = Workspace::new();
+        let path = PgLspPath::new("test.sql");
+
+        ide.apply_change(
+            path.clone(),
+            DocumentChange::new(
+                1,
+                vec![Change {
+                    range: None,
+                    text: "select 1;\nselect 2;".to_string(),
+                }],
+            ),
+        );
+
+        assert_eq!(ide.compute_syntax().len(), 2);
+        assert!(ide.compute_syntax().is_empty());
+
+        // statements that changed again before their typecheck are only checked once
+        ide.apply_change(
+            path,
+            DocumentChange::new(
+                2,
+                vec![Change {
+                    range: Some(TextRange::new(TextSize::new(7), TextSize::new(8))),
+                    text: "3".to_string(),
+                }],
+            ),
+        );
+        assert_eq!(ide.compute_syntax().len(), 1);
+
+        let checked = ide.compute_typecheck(&HashMap::new());
+        assert_eq!(checked.len(), 2);
+        assert!(checked.iter().any(|s| s.text == "select 3;"));
+        assert!(ide.compute_typecheck(&HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_analyse_embedded() {
+        let ide = Workspace::new();
+        let cancellation_checks = std::cell::Cell::new(0);
+
+        let (stmts, diagnostics) = ide.analyse_embedded(
+            PgLspPath::new("/main.rs/1.sql"),
+            &PgLspPath::new("main.rs"),
+            "select 1;\nalter table test drop column id;".to_string(),
+            &HashMap::new(),
+            &|| {
+                cancellation_checks.set(cancellation_checks.get() + 1);
+                false
+            },
+            |doc, diagnostics| (doc.statement_refs(), diagnostics),
+        );
+        // the cancellation is checked before each statement is typechecked
+        assert_eq!(cancellation_checks.get(), 2);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].source, "lint");
+        assert_eq!(
+            diagnostics[0].range,
+            TextRange::new(TextSize::new(27), TextSize::new(41))
+        );
+
+        // the snippet is not part of the workspace
+        assert!(ide.documents.is_empty());
+        assert!(stmts.iter().all(|s| ide.pg_query.ast(s).is_none()));
+    }
+
+    #[test]
+    fn test_typecheck_progress() {
+        let ide = Workspace::new();
+
+        ide.apply_change(
+            PgLspPath::new("test.sql"),
+            DocumentChange::new(
+                1,
+                vec![Change {
+                    range: None,
+                    text: "select 1;\nselect 2;\nselect 3;".to_string(),
+                }],
+            ),
+        );
+        ide.compute_syntax();
+
+        let mut reported = Vec::new();
+        let checked = ide.compute_typecheck_with_progress(&HashMap::new(), |done, total| {
+            reported.push((done, total));
+            done < 2
+        });
+        assert_eq!(checked.len(), 2);
+        assert_eq!(reported, vec![(0, 3), (1, 3), (2, 3)]);
+
+        // the statements that were not checked are checked next time
+        assert_eq!(ide.compute_typecheck(&HashMap::new()).len(), 1);
+    }
+
+    #[test]
+    fn test_rule_severities() {
+        let ide = Workspace::new();
+        let path = PgLspPath::new("test.sql");
+
+        ide.apply_change(
+            path.clone(),
+            DocumentChange::new(
+                1,
+                vec![Change {
+                    range: None,
+                    text: "alter table test drop column id;".to_string(),
+                }],
+            ),
+        );
+        ide.compute_syntax();
+
+        let lint_severities = |ide: &Workspace| {
+            ide.diagnostics(&path)
+                .into_iter()
+                .filter(|d| d.source == "lint")
+                .map(|d| d.severity)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lint_severities(&ide), vec![Severity::Warning]);
+
+        ide.linter.set_rule_severities(HashMap::from([(
+            pg_lint::RuleViolationKind::BanDropColumn,
+            Some(Severity::Error),
+        )]));
+        assert_eq!(lint_severities(&ide), vec![Severity::Error]);
+
+        ide.linter.set_rule_severities(HashMap::from([(
+            pg_lint::RuleViolationKind::BanDropColumn,
+            None,
+        )]));
+        assert!(lint_severities(&ide).is_empty());
+
+        // the rules of the deepest directory of the file apply
+        let path = PgLspPath::new("/repo/legacy/old/test.sql");
+        ide.apply_change(
+            path.clone(),
+            DocumentChange::new(
+                1,
+                vec![Change {
+                    range: None,
+                    text: "alter table test drop column id;".to_string(),
+                }],
+            ),
+        );
+        ide.compute_syntax();
+        let severity =
+            |severity| HashMap::from([(pg_lint::RuleViolationKind::BanDropColumn, severity)]);
+        ide.linter.set_directory_rule_severities(vec![
+            ("/repo/legacy/old".into(), severity(Some(Severity::Hint))),
+            ("/repo/legacy".into(), severity(Some(Severity::Information))),
+            ("/repo/legacy/new".into(), severity(Some(Severity::Error))),
+        ]);
+        let lint_severities = ide
+            .diagnostics(&path)
+            .into_iter()
+            .filter(|d| d.source == "lint")
+            .map(|d| d.severity)
+            .collect::<Vec<_>>();
+        assert_eq!(lint_severities, vec![Severity::Hint]);
+    }
+
+    #[test]
+    fn test_suppress_diagnostic() {
+        let ide = Workspace::new();
+        let path = PgLspPath::new("test.sql");
+
+        let text = "select 1;\nalter table test drop column id;";
+        ide.apply_change(
+            path.clone(),
+            DocumentChange::new(
+                1,
+                vec![Change {
+                    range: None,
+                    text: text.to_string(),
+                }],
+            ),
+        );
+        ide.compute_syntax();
+        assert_eq!(ide.diagnostics(&path).len(), 1);
+
+        let suppress = ide
+            .code_actions(&path, TextRange::empty(TextSize::new(30)))
+            .into_iter()
+            .find(|a| a.title == "Suppress with `-- pglsp-ignore ban-drop-column`")
+            .unwrap();
+        let mut text = text.to_string();
+        for (range, new_text) in suppress.edits.iter().rev() {
+            text.replace_range(std::ops::Range::<usize>::from(*range), new_text);
+        }
+        assert_eq!(
+            text,
+            "select 1;\n-- pglsp-ignore ban-drop-column\nalter table test drop column id;"
+        );
+
+        ide.apply_change(
+            path.clone(),
+            DocumentChange::new(2, vec![Change { range: None, text }]),
+        );
+        ide.compute_syntax();
+        assert!(ide.diagnostics(&path).is_empty());
+    }
+
+    #[test]
+    fn test_lint_fixes() {
+        let ide = Workspace::new();
+        let path = PgLspPath::new("test.sql");
+        ide.set_schema_cache(
+            DEFAULT_CONNECTION,
+            SchemaCache::from_sql(
+                "create table teams (id int primary key); create table users (team_id int);",
+            ),
+        );
+
+        let text = "alter table users add foreign key (team_id) references teams;";
+        ide.apply_change(
+            path.clone(),
+            DocumentChange::new(
+                1,
+                vec![Change {
+                    range: None,
+                    text: text.to_string(),
+                }],
+            ),
+        );
+        ide.compute_syntax();
+
+        let title = "Add `CREATE INDEX CONCURRENTLY users_team_id_idx ON users (team_id);`";
+        assert!(ide
+            .code_actions(&path, TextRange::empty(TextSize::new(12)))
+            .iter()
+            .any(|a| a.title == title && a.kind == CodeActionKind::QuickFix));
+        // the fix is unsafe, since creating an index takes time and space
+        assert!(ide.fixes(&path, false).is_empty());
+        let fixes = ide.fixes(&path, true);
+        assert_eq!(
+            apply_code_actions(text, &fixes),
+            format!("{}\n{}", text, &title[5..title.len() - 1])
+        );
+    }
+
+    #[test]
+    fn test_style_fixes() {
+        let ide = Workspace::new();
+        let path = PgLspPath::new("test.sql");
+
+        // the statement ends in a tab
+        let text = "select id from users where id != 1\t";
+        ide.apply_change(
+            path.clone(),
+            DocumentChange::new(
+                1,
+                vec![Change {
+                    range: None,
+                    text: text.to_string(),
+                }],
+            ),
+        );
+        ide.compute_syntax();
+
+        // style rules are only reported and fixed if they are configured
+        assert!(ide.diagnostics(&path).is_empty());
+        assert!(ide.fix_all(&path).is_none());
+
+        ide.linter.set_rule_severities(HashMap::from([
+            (
+                pg_lint::RuleViolationKind::KeywordCase,
+                Some(Severity::Warning),
+            ),
+            (
+                pg_lint::RuleViolationKind::RequireTrailingSemicolon,
+                Some(Severity::Warning),
+            ),
+            (
+                pg_lint::RuleViolationKind::NotEqualOperator,
+                Some(Severity::Warning),
+            ),
+        ]));
+        assert_eq!(
+            ide.diagnostics(&path)
+                .iter()
+                .filter_map(|d| d.code.as_deref())
+                .collect::<HashSet<_>>(),
+            HashSet::from([
+                "keyword-case",
+                "require-trailing-semicolon",
+                "not-equal-operator"
+            ])
+        );
+        assert_eq!(
+            apply_code_actions(text, &ide.fixes(&path, false)),
+            "SELECT id FROM users WHERE id <> 1;\t"
+        );
+    }
+
+    #[test]
+    fn test_linked_editing_ranges() {
+        let ide = Workspace::new();
+        let path = PgLspPath::new("test.sql");
+
+        ide.apply_change(
+            path.clone(),
+            DocumentChange::new(
+                1,
+                vec![Change {
+                    range: None,
+                    text: "select 1;\nselect u.id from users u where u.name = 'x';".to_string(),
+                }],
+            ),
+        );
+
+        assert_eq!(
+            ide.linked_editing_ranges(&path, TextSize::new(17)),
+            vec![
+                TextRange::new(17.into(), 18.into()),
+                TextRange::new(33.into(), 34.into()),
+                TextRange::new(41.into(), 42.into()),
+            ]
+        );
+        assert!(ide
+            .linked_editing_ranges(&path, TextSize::new(29))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_document_schema_cache() {
+        let ide = Workspace::new();
+
+        ide.set_connection_routes(
+            ConnectionRoutes::new(
+                Some(Path::new("/repo")),
+                [("billing".to_string(), "billing".to_string())],
+            )
+            .unwrap(),
+        );
+        ide.set_schema_cache(
+            "billing",
+            SchemaCache::from_sql("create table invoices (id int);"),
+        );
+        ide.set_schema_cache(
+            "default",
+            SchemaCache::from_sql("create table users (id int);"),
+        );
+
+        let billing = ide.document_schema_cache(&PgLspPath::new("/repo/billing/report.sql"));
+        assert!(billing.find_table("invoices", None).is_some());
+        assert!(billing.find_table("users", None).is_none());
+
+        let other = ide.document_schema_cache(&PgLspPath::new("/repo/auth/users.sql"));
+        assert!(other.find_table("users", None).is_some());
+    }
+
     #[test]
     fn test_diagnostics_within_statement() {
         let ide = Workspace::new();
@@ -219,7 +1282,7 @@ mod tests {
             ),
         );
 
-        ide.compute(None);
+        ide.compute(&HashMap::new());
 
         assert_eq!(ide.diagnostics(&url).len(), 1);
 
@@ -237,7 +1300,7 @@ mod tests {
             );
         }
 
-        ide.compute(None);
+        ide.compute(&HashMap::new());
 
         assert_eq!(ide.diagnostics(&url).len(), 1);
 
@@ -267,7 +1330,7 @@ mod tests {
         }
 
         // the problem is here!
-        ide.compute(None);
+        ide.compute(&HashMap::new());
 
         assert_eq!(ide.diagnostics(&url).len(), 1);
     }
@@ -289,7 +1352,7 @@ mod tests {
             ),
         );
 
-        ide.compute(None);
+        ide.compute(&HashMap::new());
 
         assert_eq!(ide.diagnostics(&url).len(), 1);
 
@@ -307,7 +1370,7 @@ mod tests {
             );
         }
 
-        ide.compute(None);
+        ide.compute(&HashMap::new());
 
         assert_eq!(ide.diagnostics(&url).len(), 1);
 
@@ -322,7 +1385,7 @@ mod tests {
             ),
         );
 
-        ide.compute(None);
+        ide.compute(&HashMap::new());
 
         assert_eq!(ide.diagnostics(&url).len(), 1);
 
@@ -340,7 +1403,7 @@ mod tests {
             );
         }
 
-        ide.compute(None);
+        ide.compute(&HashMap::new());
 
         assert_eq!(ide.diagnostics(&url).len(), 1);
     }
@@ -376,7 +1439,7 @@ mod tests {
             );
         }
 
-        ide.compute(None);
+        ide.compute(&HashMap::new());
 
         let d = ide.diagnostics(&path);
 
@@ -387,6 +1450,7 @@ mod tests {
             Diagnostic {
                 message: "Dropping a column may break existing clients.".to_string(),
                 description: None,
+                code: Some("ban-drop-column".to_string()),
                 severity: pg_diagnostics::Severity::Warning,
                 source: "lint".to_string(),
                 range: TextRange::new(TextSize::new(50), TextSize::new(64)),
@@ -1,50 +1,171 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
 
 use dashmap::DashMap;
 use pg_base_db::StatementRef;
 use pg_diagnostics::{Diagnostic, Severity};
+use pg_schema_cache::SchemaCache;
+use serde_json::Value;
 use text_size::TextRange;
 
+/// The severity of each rule, or `None` if the rule is disabled
+type RuleSeverities = HashMap<pg_lint::RuleViolationKind, Option<Severity>>;
+
+/// Returns the severity of the diagnostics of a rule, or `None` if it is disabled, which rules
+/// that are not configured are unless they are enabled by default
+fn rule_severity(
+    severities: &RuleSeverities,
+    kind: &pg_lint::RuleViolationKind,
+) -> Option<Severity> {
+    match severities.get(kind) {
+        Some(severity) => *severity,
+        None => kind.is_enabled_by_default().then_some(Severity::Warning),
+    }
+}
+
 pub struct Linter {
     violations: DashMap<StatementRef, Arc<Vec<pg_lint::RuleViolation>>>,
+    /// The severity each rule is reported with instead of the default one of its messages, or
+    /// `None` if the rule is disabled
+    severities: RwLock<RuleSeverities>,
+    /// The severities of the rules for the files within directories, which replace `severities`.
+    /// The deepest directory of a file applies.
+    directory_severities: RwLock<Vec<(PathBuf, RuleSeverities)>>,
+    /// The options of the rules, which apply to the whole workspace
+    options: RwLock<HashMap<pg_lint::RuleViolationKind, Value>>,
 }
 
 impl Linter {
     pub fn new() -> Linter {
         Linter {
             violations: DashMap::new(),
+            severities: RwLock::new(HashMap::new()),
+            directory_severities: RwLock::new(Vec::new()),
+            options: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Sets the options of the rules, which only apply to the violations that are computed
+    /// afterwards, see `Workspace::invalidate_lint`, and returns whether they changed
+    pub fn set_rule_options(&self, options: HashMap<pg_lint::RuleViolationKind, Value>) -> bool {
+        let mut current = self.options.write().unwrap();
+        if *current == options {
+            return false;
         }
+        *current = options;
+        true
+    }
+
+    /// Overrides the severity of the rules, which applies to the violations that were already
+    /// computed as well
+    pub fn set_rule_severities(
+        &self,
+        severities: HashMap<pg_lint::RuleViolationKind, Option<Severity>>,
+    ) {
+        *self.severities.write().unwrap() = severities;
+    }
+
+    /// Overrides the severity of the rules for the files within directories, e.g. to relax the
+    /// rules for legacy code
+    pub fn set_directory_rule_severities(&self, mut severities: Vec<(PathBuf, RuleSeverities)>) {
+        // the deepest directory of a file is found first
+        severities.sort_by_key(|(dir, _)| std::cmp::Reverse(dir.components().count()));
+        *self.directory_severities.write().unwrap() = severities;
+    }
+
+    /// Calls `f` with the severities of the rules that apply to a statement
+    fn with_severities<R>(
+        &self,
+        statement: &StatementRef,
+        f: impl FnOnce(&RuleSeverities) -> R,
+    ) -> R {
+        let global_severities = self.severities.read().unwrap();
+        let directory_severities = self.directory_severities.read().unwrap();
+        f(directory_severities
+            .iter()
+            .find(|(dir, _)| statement.document_url.starts_with(dir))
+            .map_or(&*global_severities, |(_, severities)| severities))
     }
 
     pub fn diagnostics(&self, statement: &StatementRef, at_range: TextRange) -> Vec<Diagnostic> {
+        self.with_severities(statement, |severities| {
+            self.statement_diagnostics(statement, at_range, severities)
+        })
+    }
+
+    fn statement_diagnostics(
+        &self,
+        statement: &StatementRef,
+        at_range: TextRange,
+        severities: &RuleSeverities,
+    ) -> Vec<Diagnostic> {
         let mut diagnostics = Vec::new();
         if let Some(v) = self.violations.get(statement) {
             diagnostics.extend(v.iter().flat_map(|v| {
-                v.messages.iter().map(|m| Diagnostic {
-                    description: None,
-                    source: "lint".to_string(),
-                    range: v.range.map(|r| r + at_range.start()).unwrap_or(at_range),
-                    severity: match m {
-                        pg_lint::ViolationMessage::Note(_) => Severity::Warning,
-                        pg_lint::ViolationMessage::Help(_) => Severity::Hint,
-                    },
-                    message: match m {
-                        pg_lint::ViolationMessage::Note(n) => n.to_owned(),
-                        pg_lint::ViolationMessage::Help(n) => n.to_owned(),
-                    },
-                })
+                rule_severity(severities, &v.kind)
+                    .into_iter()
+                    .flat_map(move |severity| v.messages.iter().map(move |m| (severity, m)))
+                    .map(move |(severity, m)| Diagnostic {
+                        description: None,
+                        code: Some(v.kind.to_string()),
+                        source: "lint".to_string(),
+                        range: v.range.map(|r| r + at_range.start()).unwrap_or(at_range),
+                        severity: match m {
+                            pg_lint::ViolationMessage::Note(_) => severity,
+                            pg_lint::ViolationMessage::Help(_) => Severity::Hint,
+                        },
+                        message: match m {
+                            pg_lint::ViolationMessage::Note(n) => n.to_owned(),
+                            pg_lint::ViolationMessage::Help(n) => n.to_owned(),
+                        },
+                    })
             }));
         }
         diagnostics
     }
 
+    /// Returns the fixes of the violations of the enabled rules in a statement, with the kind and
+    /// range of the violation, which is `None` if it covers the whole statement
+    pub fn fixes(
+        &self,
+        statement: &StatementRef,
+    ) -> Vec<(
+        pg_lint::RuleViolationKind,
+        Option<TextRange>,
+        pg_lint::RuleFix,
+    )> {
+        let Some(violations) = self.violations.get(statement).map(|v| v.clone()) else {
+            return Vec::new();
+        };
+        self.with_severities(statement, |severities| {
+            violations
+                .iter()
+                .filter(|v| rule_severity(severities, &v.kind).is_some())
+                .filter_map(|v| Some((v.kind.clone(), v.range, v.fix.clone()?)))
+                .collect()
+        })
+    }
+
     pub fn compute_statement_violations(
         &self,
         statement: &StatementRef,
-        params: pg_lint::LinterParams<'_>,
+        ast: &pg_query_ext::NodeEnum,
+        enriched_ast: Option<&pg_syntax::AST>,
+        schema_cache: &SchemaCache,
     ) {
+        let options = self.options.read().unwrap();
+        let violations = pg_lint::check_sql(pg_lint::LinterParams {
+            sql: &statement.text,
+            ast,
+            enriched_ast,
+            schema_cache,
+            options: &options,
+        });
         self.violations
-            .insert(statement.clone(), Arc::new(pg_lint::check_sql(params)));
+            .insert(statement.clone(), Arc::new(violations));
     }
 
     pub fn clear_statement_violations(&self, statement: &StatementRef) {
@@ -0,0 +1,538 @@
+use dashmap::DashMap;
+use line_index::LineIndex;
+use pg_base_db::{Document, PgLspPath};
+use pg_query_ext::{protobuf, NodeEnum};
+use pg_type_resolver::Scope;
+use text_size::{TextRange, TextSize};
+
+/// The schema of objects that are created without one
+pub(crate) const DEFAULT_SCHEMA: &str = "public";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Schema,
+    Table,
+    View,
+    MaterializedView,
+    ForeignTable,
+    Function,
+    Procedure,
+    Type,
+}
+
+impl ObjectKind {
+    /// Whether objects of this kind can be referenced in the `FROM` clause of a query
+    pub fn is_relation(&self) -> bool {
+        matches!(
+            self,
+            ObjectKind::Table
+                | ObjectKind::View
+                | ObjectKind::MaterializedView
+                | ObjectKind::ForeignTable
+        )
+    }
+
+    /// The keywords that name this kind in DDL, e.g. `MATERIALIZED VIEW`
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            ObjectKind::Schema => "SCHEMA",
+            ObjectKind::Table => "TABLE",
+            ObjectKind::View => "VIEW",
+            ObjectKind::MaterializedView => "MATERIALIZED VIEW",
+            ObjectKind::ForeignTable => "FOREIGN TABLE",
+            ObjectKind::Function => "FUNCTION",
+            ObjectKind::Procedure => "PROCEDURE",
+            ObjectKind::Type => "TYPE",
+        }
+    }
+
+    fn matches(&self, reference: ReferenceKind) -> bool {
+        match reference {
+            ReferenceKind::Relation => self.is_relation(),
+            ReferenceKind::Function => matches!(self, ObjectKind::Function | ObjectKind::Procedure),
+            ReferenceKind::Type => *self == ObjectKind::Type,
+            ReferenceKind::Column => false,
+        }
+    }
+}
+
+/// An object created by a statement in a workspace file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectDefinition {
+    pub kind: ObjectKind,
+    pub schema: Option<String>,
+    pub name: String,
+    /// The range of the creating statement within its file
+    pub range: TextRange,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    Relation,
+    Function,
+    Type,
+    Column,
+}
+
+/// A reference to an object within a statement, e.g. a table name in a `FROM` clause
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectReference {
+    pub kind: ReferenceKind,
+    /// The schema of the object or, for columns, of their relation
+    pub schema: Option<String>,
+    pub name: String,
+    /// For columns, the relation they belong to if it could be determined
+    pub relation: Option<String>,
+    /// The range of the reference
+    pub range: TextRange,
+}
+
+impl ObjectReference {
+    /// Returns the reference at the given offset of a statement, if any
+    pub fn at(ast: &pg_syntax::AST, offset: TextSize) -> Option<ObjectReference> {
+        let node = ast.covering_node(TextRange::empty(offset))?;
+        let scope = Scope::from_statement(&ast.root_node().node);
+        ObjectReference::from_node(&node.node, node.range(), &scope)
+    }
+
+    /// Returns all references within a statement
+    pub fn collect(ast: &pg_syntax::AST) -> Vec<ObjectReference> {
+        let scope = Scope::from_statement(&ast.root_node().node);
+        ast.iter_nodes()
+            .filter_map(|node| ObjectReference::from_node(&node.node, node.range(), &scope))
+            .collect()
+    }
+
+    fn from_node(node: &NodeEnum, range: TextRange, scope: &Scope) -> Option<ObjectReference> {
+        let mut relation = None;
+        let (kind, schema, name) = match node {
+            NodeEnum::RangeVar(r) => (
+                ReferenceKind::Relation,
+                non_empty(&r.schemaname),
+                r.relname.clone(),
+            ),
+            NodeEnum::FuncCall(f) => {
+                let (schema, name) = qualified_name(&f.funcname);
+                (ReferenceKind::Function, schema, name)
+            }
+            NodeEnum::TypeName(t) => {
+                let (schema, name) = qualified_name(&t.names);
+                (ReferenceKind::Type, schema, name)
+            }
+            NodeEnum::ColumnRef(c) => {
+                let mut names = c
+                    .fields
+                    .iter()
+                    .map(|f| match &f.node {
+                        Some(NodeEnum::String(s)) => Some(s.sval.clone()),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                let name = names.pop()?;
+                let (qualifier_schema, qualifier) = match names.as_slice() {
+                    [] => (None, None),
+                    [relation] => (None, Some(relation.as_str())),
+                    [.., schema, relation] => (Some(schema.as_str()), Some(relation.as_str())),
+                };
+
+                // qualifiers may be aliases, so they are resolved through the scope
+                let owner = match qualifier {
+                    Some(qualifier) => scope
+                        .relations
+                        .iter()
+                        .find(|r| r.matches(qualifier_schema, qualifier)),
+                    None if scope.relations.len() == 1 => scope.relations.first(),
+                    None => None,
+                };
+                let schema = match owner {
+                    Some(owner) => {
+                        relation = Some(owner.name.clone());
+                        owner.schema.clone()
+                    }
+                    None => {
+                        relation = qualifier.map(|q| q.to_string());
+                        qualifier_schema.map(|s| s.to_string())
+                    }
+                };
+                (ReferenceKind::Column, schema, name)
+            }
+            _ => return None,
+        };
+
+        Some(ObjectReference {
+            kind,
+            schema,
+            name,
+            relation,
+            range,
+        })
+    }
+
+    /// Whether both references may refer to the same object
+    ///
+    /// Like for definitions, unqualified references match references in any schema. Columns
+    /// whose relation could not be determined match columns of the same name in any relation.
+    pub fn refers_to_same(&self, other: &ObjectReference) -> bool {
+        self.kind == other.kind
+            && self.name == other.name
+            && match (&self.schema, &other.schema) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            }
+            && match (&self.relation, &other.relation) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            }
+    }
+
+    /// Moves the reference by the offset of its statement within the file
+    fn offset(mut self, offset: TextSize) -> ObjectReference {
+        self.range += offset;
+        self
+    }
+
+    /// Whether the reference may refer to the definition
+    ///
+    /// Unqualified references match definitions in any schema, since the search path of the
+    /// session that runs the statement is unknown.
+    pub fn matches(&self, definition: &ObjectDefinition) -> bool {
+        definition.kind.matches(self.kind)
+            && definition.name == self.name
+            && self.schema.as_deref().is_none_or(|schema| {
+                definition.schema.as_deref().unwrap_or(DEFAULT_SCHEMA) == schema
+            })
+    }
+}
+
+/// The objects defined and referenced in a workspace file
+#[derive(Debug)]
+pub struct IndexedFile {
+    pub line_index: LineIndex,
+    pub definitions: Vec<ObjectDefinition>,
+    /// The references within the file, which are only collected for files that are not open
+    pub references: Vec<ObjectReference>,
+}
+
+/// Indexes where objects are created and referenced across the files of the workspace
+///
+/// The definitions of open documents are indexed from their parsed statements as they change,
+/// while their references are collected on demand. Other files, e.g. migrations, are indexed
+/// when the workspace is opened, when they are closed and when they change on disk.
+pub struct ObjectIndex {
+    files: DashMap<PgLspPath, IndexedFile>,
+}
+
+impl ObjectIndex {
+    pub fn new() -> ObjectIndex {
+        ObjectIndex {
+            files: DashMap::new(),
+        }
+    }
+
+    /// Indexes a file that is not open by splitting and parsing its statements
+    pub fn index_file(&self, path: PgLspPath, text: String) {
+        let doc = Document::new(path.clone(), Some(text));
+        let mut definitions = Vec::new();
+        let mut references = Vec::new();
+        for (range, stmt) in doc.statement_refs_with_range() {
+            let Ok(root) = pg_query_ext::parse(&stmt.text) else {
+                continue;
+            };
+            definitions.extend(statement_definitions(&root, range));
+            let ast = pg_syntax::parse_syntax(&stmt.text, &root).ast;
+            references.extend(
+                ObjectReference::collect(&ast)
+                    .into_iter()
+                    .map(|r| r.offset(range.start())),
+            );
+        }
+        self.files.insert(
+            path,
+            IndexedFile {
+                line_index: doc.line_index,
+                definitions,
+                references,
+            },
+        );
+    }
+
+    /// Removes a file that was deleted
+    pub fn remove_file(&self, path: &PgLspPath) {
+        self.files.remove(path);
+    }
+
+    /// Updates the definitions of an open document
+    pub fn set_definitions(
+        &self,
+        path: PgLspPath,
+        line_index: LineIndex,
+        definitions: Vec<ObjectDefinition>,
+    ) {
+        self.files.insert(
+            path,
+            IndexedFile {
+                line_index,
+                definitions,
+                references: Vec::new(),
+            },
+        );
+    }
+
+    /// Returns the references that may refer to the same object as the given one, except for
+    /// those in the excluded files
+    pub fn find_references(
+        &self,
+        reference: &ObjectReference,
+        exclude: impl Fn(&PgLspPath) -> bool,
+    ) -> Vec<(PgLspPath, TextRange)> {
+        self.files
+            .iter()
+            .filter(|file| !exclude(file.key()))
+            .flat_map(|file| {
+                file.references
+                    .iter()
+                    .filter(|r| r.refers_to_same(reference))
+                    .map(|r| (file.key().clone(), r.range))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Returns the definitions that the reference may refer to, along with their file
+    pub fn find(&self, reference: &ObjectReference) -> Vec<(PgLspPath, TextRange)> {
+        self.files
+            .iter()
+            .flat_map(|file| {
+                file.definitions
+                    .iter()
+                    .filter(|d| reference.matches(d))
+                    .map(|d| (file.key().clone(), d.range))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Returns the definitions of all indexed files
+    pub fn definitions(&self) -> Vec<(PgLspPath, ObjectDefinition)> {
+        self.files
+            .iter()
+            .flat_map(|file| {
+                file.definitions
+                    .iter()
+                    .map(|d| (file.key().clone(), d.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Returns the line index of an indexed file
+    pub fn line_index(&self, path: &PgLspPath) -> Option<LineIndex> {
+        self.files.get(path).map(|f| f.line_index.clone())
+    }
+}
+
+/// Returns the objects a statement creates
+pub fn statement_definitions(stmt: &NodeEnum, range: TextRange) -> Vec<ObjectDefinition> {
+    let mut definitions = Vec::new();
+    collect_definitions(stmt, None, range, &mut definitions);
+    definitions
+}
+
+fn collect_definitions(
+    stmt: &NodeEnum,
+    default_schema: Option<&str>,
+    range: TextRange,
+    definitions: &mut Vec<ObjectDefinition>,
+) {
+    let mut define = |kind: ObjectKind, schema: Option<String>, name: String| {
+        definitions.push(ObjectDefinition {
+            kind,
+            schema: schema.or_else(|| default_schema.map(str::to_string)),
+            name,
+            range,
+        })
+    };
+
+    match stmt {
+        NodeEnum::CreateSchemaStmt(s) => {
+            if s.schemaname.is_empty() {
+                return;
+            }
+            define(ObjectKind::Schema, None, s.schemaname.clone());
+            for elt in &s.schema_elts {
+                if let Some(node) = &elt.node {
+                    collect_definitions(node, Some(&s.schemaname), range, definitions);
+                }
+            }
+        }
+        NodeEnum::CreateStmt(s) => {
+            if let Some(r) = &s.relation {
+                define(
+                    ObjectKind::Table,
+                    non_empty(&r.schemaname),
+                    r.relname.clone(),
+                );
+            }
+        }
+        NodeEnum::CreateForeignTableStmt(s) => {
+            if let Some(r) = s.base_stmt.as_ref().and_then(|b| b.relation.as_ref()) {
+                define(
+                    ObjectKind::ForeignTable,
+                    non_empty(&r.schemaname),
+                    r.relname.clone(),
+                );
+            }
+        }
+        NodeEnum::ViewStmt(s) => {
+            if let Some(r) = &s.view {
+                define(
+                    ObjectKind::View,
+                    non_empty(&r.schemaname),
+                    r.relname.clone(),
+                );
+            }
+        }
+        NodeEnum::CreateTableAsStmt(s) => {
+            let kind = match protobuf::ObjectType::from_i32(s.objtype) {
+                Some(protobuf::ObjectType::ObjectMatview) => ObjectKind::MaterializedView,
+                _ => ObjectKind::Table,
+            };
+            if let Some(r) = s.into.as_ref().and_then(|i| i.rel.as_ref()) {
+                define(kind, non_empty(&r.schemaname), r.relname.clone());
+            }
+        }
+        NodeEnum::CreateFunctionStmt(s) => {
+            let (schema, name) = qualified_name(&s.funcname);
+            let kind = if s.is_procedure {
+                ObjectKind::Procedure
+            } else {
+                ObjectKind::Function
+            };
+            define(kind, schema, name);
+        }
+        NodeEnum::CreateEnumStmt(s) => {
+            let (schema, name) = qualified_name(&s.type_name);
+            define(ObjectKind::Type, schema, name);
+        }
+        NodeEnum::CreateDomainStmt(s) => {
+            let (schema, name) = qualified_name(&s.domainname);
+            define(ObjectKind::Type, schema, name);
+        }
+        NodeEnum::CompositeTypeStmt(s) => {
+            if let Some(r) = &s.typevar {
+                define(
+                    ObjectKind::Type,
+                    non_empty(&r.schemaname),
+                    r.relname.clone(),
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Splits a possibly qualified name, e.g. `public.users`, into schema and name
+pub(crate) fn qualified_name(names: &[protobuf::Node]) -> (Option<String>, String) {
+    let names = names
+        .iter()
+        .filter_map(|n| match &n.node {
+            Some(NodeEnum::String(s)) => Some(s.sval.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    match names.as_slice() {
+        [.., schema, name] => (Some(schema.clone()), name.clone()),
+        [name] => (None, name.clone()),
+        [] => (None, String::new()),
+    }
+}
+
+pub(crate) fn non_empty(s: &str) -> Option<String> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_base_db::PgLspPath;
+    use text_size::{TextRange, TextSize};
+
+    use super::{ObjectIndex, ObjectKind, ObjectReference, ReferenceKind};
+
+    #[test]
+    fn test_find_definitions() {
+        let index = ObjectIndex::new();
+        let path = PgLspPath::new("/migrations/001.sql");
+        index.index_file(
+            path.clone(),
+            "create schema app;\ncreate table app.users (id int);\ncreate view active_users as select 1;\ncreate function add(a int, b int) returns int language sql as 'select a + b';\n".to_string(),
+        );
+
+        let source = "select add(1, 2) from app.users";
+        let root = pg_query_ext::parse(source).unwrap();
+        let ast = pg_syntax::parse_syntax(source, &root).ast;
+
+        let users = ObjectReference::at(&ast, TextSize::new(28)).unwrap();
+        assert_eq!(users.kind, ReferenceKind::Relation);
+        assert_eq!(
+            index.find(&users),
+            vec![(path.clone(), TextRange::new(19.into(), 51.into()))]
+        );
+
+        let add = ObjectReference::at(&ast, TextSize::new(8)).unwrap();
+        assert_eq!(index.find(&add).len(), 1);
+
+        let definitions = &index.files.get(&path).unwrap().definitions;
+        assert_eq!(
+            definitions.iter().map(|d| d.kind).collect::<Vec<_>>(),
+            vec![
+                ObjectKind::Schema,
+                ObjectKind::Table,
+                ObjectKind::View,
+                ObjectKind::Function
+            ]
+        );
+        assert_eq!(definitions[1].schema.as_deref(), Some("app"));
+    }
+
+    #[test]
+    fn test_find_references() {
+        let index = ObjectIndex::new();
+        let path = PgLspPath::new("/queries.sql");
+        index.index_file(
+            path.clone(),
+            "select u.email from users u;\nselect email from users;\nselect email from contacts, users;\nupdate contacts set name = 'x' where email = '';\n"
+                .to_string(),
+        );
+
+        let source = "select id from users where email is not null";
+        let root = pg_query_ext::parse(source).unwrap();
+        let ast = pg_syntax::parse_syntax(source, &root).ast;
+
+        let email = ObjectReference::at(&ast, TextSize::new(28)).unwrap();
+        assert_eq!(email.kind, ReferenceKind::Column);
+        assert_eq!(email.relation.as_deref(), Some("users"));
+
+        // the column of `contacts` is not a reference, the ambiguous one may be
+        let references = index
+            .find_references(&email, |_| false)
+            .into_iter()
+            .map(|(_, range)| range)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            references,
+            vec![
+                TextRange::new(7.into(), 14.into()),
+                TextRange::new(36.into(), 41.into()),
+                TextRange::new(61.into(), 66.into()),
+            ]
+        );
+
+        let users = ObjectReference::at(&ast, TextSize::new(16)).unwrap();
+        assert_eq!(index.find_references(&users, |_| false).len(), 3);
+        assert!(index.find_references(&users, |p| *p == path).is_empty());
+    }
+}
@@ -52,6 +52,7 @@ impl PgQueryParser {
         if let Some(err) = self.native_diagnostics.get(statement) {
             diagnostics.push(Diagnostic {
                 description: None,
+                code: None,
                 source: "pg_query".to_string(),
                 range: at_range,
                 severity: Severity::Error,
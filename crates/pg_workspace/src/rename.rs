@@ -0,0 +1,215 @@
+use pg_lexer::{SyntaxKind, Token, TokenType};
+use pg_query_ext::{protobuf, NodeEnum};
+use text_size::{TextRange, TextSize};
+
+/// A name that is only defined within a statement, i.e. a table alias or a CTE name
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LocalName {
+    Alias(String),
+    Cte(String),
+}
+
+impl LocalName {
+    fn name(&self) -> &str {
+        match self {
+            LocalName::Alias(name) | LocalName::Cte(name) => name,
+        }
+    }
+}
+
+/// Returns the range of the statement-local name at the offset, if it can be renamed
+pub fn rename_range(sql: &str, root: &NodeEnum, offset: TextSize) -> Option<TextRange> {
+    let tokens = identifier_tokens(sql);
+    let token = tokens.iter().find(|t| t.span.contains_inclusive(offset))?;
+    let name = identifier(token)?;
+
+    local_names(root)
+        .iter()
+        .any(|local| local.name() == name)
+        .then_some(token.span)
+}
+
+/// Returns the ranges of all occurrences of the statement-local name at the offset
+///
+/// Occurrences are the definition of the alias or CTE, its uses as a column qualifier, e.g. `u`
+/// in `u.email`, and for CTEs the relations that refer to them. Names are not resolved per
+/// subquery, so all aliases of the same name within the statement are renamed together.
+pub fn rename_occurrences(sql: &str, root: &NodeEnum, offset: TextSize) -> Vec<TextRange> {
+    let tokens = identifier_tokens(sql);
+    let Some(name) = tokens
+        .iter()
+        .find(|t| t.span.contains_inclusive(offset))
+        .and_then(identifier)
+    else {
+        return Vec::new();
+    };
+
+    let locals = local_names(root);
+    if !locals.iter().any(|local| local.name() == name) {
+        return Vec::new();
+    }
+    let is_cte = locals.contains(&LocalName::Cte(name.clone()));
+
+    // bare identifiers at these locations refer to other objects of the same name
+    let mut excluded = Vec::new();
+    let mut output_names = Vec::new();
+    for (node, _, _) in root.nodes() {
+        match node.to_enum() {
+            NodeEnum::ColumnRef(c) => excluded.push(c.location),
+            NodeEnum::FuncCall(f) => excluded.push(f.location),
+            NodeEnum::TypeName(t) => excluded.push(t.location),
+            NodeEnum::RangeVar(r) if !(is_cte && r.schemaname.is_empty() && r.relname == name) => {
+                excluded.push(r.location)
+            }
+            NodeEnum::ResTarget(t) if t.name == name => output_names.push(t.location),
+            _ => {}
+        }
+    }
+
+    let is_dot = |t: Option<&Token>| t.is_some_and(|t| t.kind == SyntaxKind::Ascii46);
+
+    let mut candidates = Vec::new();
+    for (idx, token) in tokens.iter().enumerate() {
+        if identifier(token).as_ref() != Some(&name) {
+            continue;
+        }
+        // the name of a column, e.g. `email` in `u.email`
+        if idx > 0 && is_dot(tokens.get(idx - 1)) {
+            continue;
+        }
+        let qualifier = is_dot(tokens.get(idx + 1));
+        candidates.push((qualifier, token.span));
+    }
+
+    // the name of an output column, e.g. `u` in `select count(*) as u`, is the first bare
+    // occurrence after the start of its target
+    for location in output_names {
+        if let Some(idx) = candidates
+            .iter()
+            .position(|(qualifier, span)| !qualifier && u32::from(span.start()) >= location as u32)
+        {
+            candidates.remove(idx);
+        }
+    }
+
+    candidates
+        .into_iter()
+        .filter(|(qualifier, span)| {
+            *qualifier || !excluded.contains(&(u32::from(span.start()) as i32))
+        })
+        .map(|(_, span)| span)
+        .collect()
+}
+
+/// Quotes a new name if it would not be read back as the same identifier
+pub fn quote_identifier(name: &str) -> String {
+    let tokens = identifier_tokens(name);
+    match tokens.as_slice() {
+        // unreserved keywords, e.g. `name`, are valid identifiers as well
+        [token]
+            if (token.kind == SyntaxKind::Ident
+                || token.token_type == TokenType::UnreservedKeyword)
+                && token.text == name
+                && identifier(token).as_deref() == Some(name) =>
+        {
+            name.to_string()
+        }
+        _ => format!("\"{}\"", name.replace('"', "\"\"")),
+    }
+}
+
+fn identifier_tokens(sql: &str) -> Vec<Token> {
+    pg_lexer::lex(sql)
+        .into_iter()
+        .filter(|t| t.token_type != TokenType::Whitespace)
+        .collect()
+}
+
+/// Returns the normalised name of an identifier token, i.e. lowercased unless quoted
+pub(crate) fn identifier(token: &Token) -> Option<String> {
+    match token.token_type {
+        TokenType::NoKeyword if token.kind == SyntaxKind::Ident => {
+            match token
+                .text
+                .strip_prefix('"')
+                .and_then(|t| t.strip_suffix('"'))
+            {
+                Some(quoted) => Some(quoted.replace("\"\"", "\"")),
+                None => Some(token.text.to_lowercase()),
+            }
+        }
+        TokenType::UnreservedKeyword | TokenType::ColNameKeyword => Some(token.text.to_lowercase()),
+        _ => None,
+    }
+}
+
+fn local_names(root: &NodeEnum) -> Vec<LocalName> {
+    let alias = |alias: &Option<protobuf::Alias>| {
+        alias
+            .as_ref()
+            .filter(|a| !a.aliasname.is_empty())
+            .map(|a| LocalName::Alias(a.aliasname.clone()))
+    };
+
+    root.nodes()
+        .into_iter()
+        .filter_map(|(node, _, _)| match node.to_enum() {
+            NodeEnum::RangeVar(r) => alias(&r.alias),
+            NodeEnum::RangeSubselect(r) => alias(&r.alias),
+            NodeEnum::RangeFunction(r) => alias(&r.alias),
+            NodeEnum::JoinExpr(j) => alias(&j.alias),
+            NodeEnum::CommonTableExpr(c) => Some(LocalName::Cte(c.ctename.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use text_size::{TextRange, TextSize};
+
+    use super::{quote_identifier, rename_occurrences, rename_range};
+
+    fn occurrences(sql: &str, offset: u32) -> Vec<&str> {
+        let root = pg_query_ext::parse(sql).unwrap();
+        rename_occurrences(sql, &root, TextSize::new(offset))
+            .into_iter()
+            .map(|range| &sql[range])
+            .collect()
+    }
+
+    #[test]
+    fn test_rename_alias() {
+        let sql = "select u.id, count(*) as u from users u join events e on e.user_id = u.id where u.name = 'u' group by u.id";
+
+        let root = pg_query_ext::parse(sql).unwrap();
+        assert_eq!(
+            rename_range(sql, &root, TextSize::new(7)),
+            Some(TextRange::new(7.into(), 8.into()))
+        );
+        // `users` is not a local name
+        assert_eq!(rename_range(sql, &root, TextSize::new(33)), None);
+
+        let renamed = occurrences(sql, 7);
+        assert_eq!(renamed.len(), 5);
+        assert!(renamed.iter().all(|name| *name == "u"));
+        assert_eq!(occurrences(sql, 33).len(), 0);
+    }
+
+    #[test]
+    fn test_rename_cte() {
+        let sql = "with recent as (select * from events) select recent.id from recent join (select 1 as id) sub on sub.id = recent.id";
+
+        assert_eq!(occurrences(sql, 5), vec!["recent"; 4]);
+        assert_eq!(occurrences(sql, 89), vec!["sub"; 2]);
+    }
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier("users_2"), "users_2");
+        assert_eq!(quote_identifier("name"), "name");
+        assert_eq!(quote_identifier("Users"), "\"Users\"");
+        assert_eq!(quote_identifier("select"), "\"select\"");
+        assert_eq!(quote_identifier("my alias"), "\"my alias\"");
+    }
+}
@@ -0,0 +1,291 @@
+use pg_diagnostics::{Diagnostic, Severity};
+use pg_lexer::{SyntaxKind, Token};
+use pg_query_ext::{protobuf, ChildrenIterator, NodeEnum};
+use pg_schema_cache::SchemaCache;
+use pg_type_resolver::ScopeRelation;
+use text_size::{TextRange, TextSize};
+
+use crate::{
+    code_actions::{CodeAction, CodeActionKind},
+    rename::quote_identifier,
+};
+
+/// The columns of the table of an `INSERT` that it has to but does not provide values for, i.e.
+/// `NOT NULL` columns without a default
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingColumns {
+    /// The range of the table name
+    pub range: TextRange,
+    pub columns: Vec<String>,
+}
+
+/// Returns the required columns an `INSERT` does not provide values for, if any
+///
+/// Values of an `INSERT` without a column list are assigned to the columns of the table in
+/// order. Such an `INSERT` from a query is skipped, since it is not known how many columns the
+/// query returns.
+pub fn missing_columns(
+    sql: &str,
+    root: &NodeEnum,
+    schema_cache: &SchemaCache,
+) -> Option<MissingColumns> {
+    let NodeEnum::InsertStmt(stmt) = root else {
+        return None;
+    };
+    let relation = stmt.relation.as_ref()?;
+
+    let provided = if !stmt.cols.is_empty() {
+        stmt.cols
+            .iter()
+            .filter_map(|c| match &c.node {
+                Some(NodeEnum::ResTarget(t)) => Some(t.name.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    } else {
+        // without a column list, the values are assigned to the columns in order
+        let count = match values_lists(stmt) {
+            Some(lists) => lists.iter().map(|l| l.len()).min().unwrap_or(0),
+            None if stmt.select_stmt.is_none() => 0,
+            None => return None,
+        };
+        table_columns(relation, schema_cache)
+            .into_iter()
+            .take(count)
+            .map(|c| c.name.clone())
+            .collect()
+    };
+
+    let columns = table_columns(relation, schema_cache)
+        .into_iter()
+        .filter(|c| {
+            !c.is_nullable
+                && c.default_value.is_none()
+                && !c.is_identity
+                && !provided.contains(&c.name)
+        })
+        .map(|c| c.name.clone())
+        .collect::<Vec<_>>();
+    if columns.is_empty() {
+        return None;
+    }
+
+    let start = TextSize::try_from(usize::try_from(relation.location).ok()?).ok()?;
+    let tokens = tokens_from(sql, start);
+    let mut idx = 0;
+    while idx + 2 < tokens.len() && tokens[idx + 1].kind == SyntaxKind::Ascii46 {
+        idx += 2;
+    }
+    let end = tokens.get(idx)?.span.end();
+
+    Some(MissingColumns {
+        range: TextRange::new(start, end),
+        columns,
+    })
+}
+
+pub fn diagnostic(missing: &MissingColumns) -> Diagnostic {
+    Diagnostic {
+        message: format!(
+            "Missing values for required columns: {}",
+            missing.columns.join(", ")
+        ),
+        description: None,
+        code: None,
+        severity: Severity::Error,
+        source: "schema".to_string(),
+        range: missing.range,
+    }
+}
+
+/// Offers to append the missing columns to the column list of an `INSERT ... VALUES` and a
+/// placeholder to every tuple
+///
+/// The placeholders are parameters numbered after the existing ones if the statement has
+/// parameters, and `DEFAULT` otherwise.
+pub fn add_missing_columns(
+    sql: &str,
+    root: &NodeEnum,
+    range: TextRange,
+    schema_cache: &SchemaCache,
+) -> Option<CodeAction> {
+    let missing = missing_columns(sql, root, schema_cache)?;
+    missing.range.intersect(range)?;
+
+    let NodeEnum::InsertStmt(stmt) = root else {
+        return None;
+    };
+    let lists = values_lists(stmt)?;
+    let last_col = stmt.cols.last()?;
+
+    let mut edits = vec![(
+        closing_paren(sql, get_location(last_col)?)?,
+        missing
+            .columns
+            .iter()
+            .map(|c| format!(", {}", quote_identifier(c)))
+            .collect::<String>(),
+    )];
+
+    let mut next_param = ChildrenIterator::new(root.to_owned())
+        .filter_map(|n| match n {
+            NodeEnum::ParamRef(p) => Some(p.number),
+            _ => None,
+        })
+        .max();
+    for values in lists {
+        let placeholders = missing
+            .columns
+            .iter()
+            .map(|_| match next_param.as_mut() {
+                Some(n) => {
+                    *n += 1;
+                    format!(", ${}", n)
+                }
+                None => ", DEFAULT".to_string(),
+            })
+            .collect::<String>();
+        edits.push((
+            closing_paren(sql, get_location(values.last()?)?)?,
+            placeholders,
+        ));
+    }
+
+    Some(CodeAction {
+        title: format!("Add missing columns {}", missing.columns.join(", ")),
+        kind: CodeActionKind::QuickFix,
+        edits,
+    })
+}
+
+fn values_lists(stmt: &protobuf::InsertStmt) -> Option<Vec<&Vec<protobuf::Node>>> {
+    match stmt.select_stmt.as_ref()?.node.as_ref()? {
+        NodeEnum::SelectStmt(s) if !s.values_lists.is_empty() => s
+            .values_lists
+            .iter()
+            .map(|l| match &l.node {
+                Some(NodeEnum::List(l)) => Some(&l.items),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+fn table_columns<'a>(
+    relation: &protobuf::RangeVar,
+    schema_cache: &'a SchemaCache,
+) -> Vec<&'a pg_schema_cache::Column> {
+    let relation = ScopeRelation {
+        schema: Some(relation.schemaname.clone()).filter(|s| !s.is_empty()),
+        name: relation.relname.clone(),
+        alias: None,
+    };
+    relation
+        .resolve(schema_cache)
+        .map(|r| schema_cache.find_columns(r.id()))
+        .unwrap_or_default()
+}
+
+fn get_location(node: &protobuf::Node) -> Option<TextSize> {
+    let location = pg_query_ext::get_location(node.node.as_ref()?)?;
+    TextSize::try_from(location).ok()
+}
+
+fn tokens_from(sql: &str, start: TextSize) -> Vec<Token> {
+    pg_lexer::lex(sql)
+        .into_iter()
+        .filter(|t| !matches!(t.kind, SyntaxKind::Whitespace | SyntaxKind::Newline))
+        .skip_while(|t| t.span.start() < start)
+        .collect()
+}
+
+/// Returns the empty range right before the parenthesis that closes the list an item at
+/// `location` is part of
+fn closing_paren(sql: &str, location: TextSize) -> Option<TextRange> {
+    let mut depth = 0;
+    for token in tokens_from(sql, location) {
+        match token.kind {
+            SyntaxKind::Ascii40 => depth += 1,
+            SyntaxKind::Ascii41 if depth == 0 => return Some(TextRange::empty(token.span.start())),
+            SyntaxKind::Ascii41 => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_schema_cache::SchemaCache;
+    use text_size::{TextRange, TextSize};
+
+    use super::{add_missing_columns, missing_columns};
+
+    #[test]
+    fn test_missing_columns() {
+        let schema_cache = SchemaCache::from_sql(
+            "create table users (
+                id int generated always as identity,
+                email text not null,
+                name text not null,
+                role text not null default 'member',
+                bio text
+            );",
+        );
+
+        let missing = |sql: &'static str| {
+            let root = pg_query_ext::parse(sql).unwrap();
+            missing_columns(sql, &root, &schema_cache).map(|m| (&sql[m.range], m.columns))
+        };
+
+        assert_eq!(
+            missing("insert into public.users (bio) values ('x')"),
+            Some((
+                "public.users",
+                vec!["email".to_string(), "name".to_string()]
+            ))
+        );
+        assert_eq!(
+            missing("insert into users values (1, 'a@b.c')"),
+            Some(("users", vec!["name".to_string()]))
+        );
+        assert_eq!(
+            missing("insert into users (name, email) select 'a', 'b'"),
+            None
+        );
+        assert_eq!(missing("insert into users select * from users"), None);
+    }
+
+    #[test]
+    fn test_add_missing_columns() {
+        let schema_cache = SchemaCache::from_sql(
+            "create table users (email text not null, name text not null, bio text);",
+        );
+
+        let fix = |sql: &str| {
+            let root = pg_query_ext::parse(sql).unwrap();
+            let action = add_missing_columns(
+                sql,
+                &root,
+                TextRange::empty(TextSize::new(13)),
+                &schema_cache,
+            )
+            .unwrap();
+            let mut sql = sql.to_string();
+            for (range, text) in action.edits.into_iter().rev() {
+                sql.replace_range(std::ops::Range::<usize>::from(range), &text);
+            }
+            sql
+        };
+
+        assert_eq!(
+            fix("insert into users (bio) values (lower('X')), ('y')"),
+            "insert into users (bio, email, name) values (lower('X'), DEFAULT, DEFAULT), ('y', DEFAULT, DEFAULT)"
+        );
+        assert_eq!(
+            fix("insert into users (email) values ($1)"),
+            "insert into users (email, name) values ($1, $2)"
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use pg_lexer::SyntaxKind;
+use text_size::{TextRange, TextSize};
+
+/// Returns the ranges that "expand selection" steps through at an offset of a statement, from the
+/// token at the offset to the whole statement
+///
+/// Between the token and the statement are the ranges of all nodes that contain the offset, e.g.
+/// a column reference, the expression it is part of and the clause of that expression.
+pub fn selection_ranges(sql: &str, ast: &pg_syntax::AST, offset: TextSize) -> Vec<TextRange> {
+    let mut ranges = Vec::new();
+
+    if let Some(token) = pg_lexer::lex(sql).into_iter().find(|t| {
+        t.span.contains_inclusive(offset)
+            && !matches!(t.kind, SyntaxKind::Whitespace | SyntaxKind::Newline)
+    }) {
+        ranges.push(token.span);
+    }
+
+    let mut nodes = ast
+        .iter_nodes()
+        .filter(|n| n.end.is_some())
+        .map(|n| balanced(sql, n.range()))
+        .filter(|r| r.contains_inclusive(offset))
+        .collect::<Vec<_>>();
+    nodes.sort_by_key(|r| r.len());
+    ranges.extend(nodes);
+
+    ranges.push(TextRange::up_to(TextSize::of(sql.trim_end())));
+
+    // every range has to contain the previous one
+    let mut result: Vec<TextRange> = Vec::new();
+    for range in ranges {
+        match result.last() {
+            Some(last) if range == *last || !range.contains_range(*last) => {}
+            _ => result.push(range),
+        }
+    }
+    result
+}
+
+/// Extends a node range by the closing parentheses that follow it if it contains more opening
+/// than closing ones, e.g. for function calls, whose range ends before the closing parenthesis
+fn balanced(sql: &str, range: TextRange) -> TextRange {
+    let text = &sql[range];
+    let mut unclosed = text.matches('(').count() as i64 - text.matches(')').count() as i64;
+
+    let mut end = range.end();
+    while unclosed > 0 {
+        let rest = &sql[usize::from(end)..];
+        let skipped = rest.len() - rest.trim_start().len();
+        if !rest.trim_start().starts_with(')') {
+            break;
+        }
+        end += TextSize::try_from(skipped + 1).unwrap();
+        unclosed -= 1;
+    }
+
+    TextRange::new(range.start(), end)
+}
+
+#[cfg(test)]
+mod tests {
+    use text_size::TextSize;
+
+    use super::selection_ranges;
+
+    #[test]
+    fn test_selection_ranges() {
+        let sql = "select id, upper(name) from users where id > 1 and name <> 'x';";
+        let root = pg_query_ext::parse(sql).unwrap();
+        let ast = pg_syntax::parse_syntax(sql, &root).ast;
+
+        let ranges = |offset: u32| {
+            selection_ranges(sql, &ast, TextSize::new(offset))
+                .into_iter()
+                .map(|r| &sql[r])
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            ranges(18),
+            vec![
+                "name",
+                "upper(name)",
+                "select id, upper(name) from users where id > 1 and name <> 'x';"
+            ]
+        );
+        assert_eq!(
+            ranges(41),
+            vec![
+                "id",
+                "id > 1",
+                "id > 1 and name <> 'x'",
+                "select id, upper(name) from users where id > 1 and name <> 'x';"
+            ]
+        );
+    }
+}
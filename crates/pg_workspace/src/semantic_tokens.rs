@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use pg_lexer::{SyntaxKind, Token, TokenType};
+use pg_query_ext::{ChildrenIterator, NodeEnum};
+use text_size::{TextRange, TextSize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SemanticTokenKind {
+    Keyword,
+    Namespace,
+    Relation,
+    Column,
+    Function,
+    Type,
+    Parameter,
+    String,
+    Number,
+    Comment,
+    Operator,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub range: TextRange,
+    pub kind: SemanticTokenKind,
+}
+
+/// Classifies the tokens of a document
+///
+/// Keywords, literals, comments and operators are classified by the lexer. Identifiers are
+/// classified by the node of the parsed statement that starts at them, so identifiers of
+/// statements that could not be parsed are left out. `statements` holds the range of every
+/// statement together with its root node, if it could be parsed.
+pub fn semantic_tokens(
+    text: &str,
+    statements: &[(TextRange, Option<NodeEnum>)],
+) -> Vec<SemanticToken> {
+    let mut names = HashMap::new();
+    for (range, root) in statements {
+        let Some(root) = root else {
+            continue;
+        };
+        for node in ChildrenIterator::new(root.to_owned()) {
+            let (location, kind) = match &node {
+                NodeEnum::RangeVar(r) => (r.location, SemanticTokenKind::Relation),
+                NodeEnum::ColumnRef(c) => (c.location, SemanticTokenKind::Column),
+                NodeEnum::FuncCall(f) => (f.location, SemanticTokenKind::Function),
+                NodeEnum::TypeName(t) => (t.location, SemanticTokenKind::Type),
+                _ => continue,
+            };
+            if let Ok(location) = u32::try_from(location) {
+                names.insert(range.start() + TextSize::new(location), kind);
+            }
+        }
+    }
+
+    let tokens = pg_lexer::lex(text)
+        .into_iter()
+        .filter(|t| !matches!(t.kind, SyntaxKind::Whitespace | SyntaxKind::Newline))
+        .collect::<Vec<_>>();
+
+    let mut result = Vec::new();
+    let mut idx = 0;
+    while idx < tokens.len() {
+        if let Some(kind) = names.get(&tokens[idx].span.start()) {
+            idx = push_name(&tokens, idx, *kind, &mut result);
+            continue;
+        }
+        if let Some(kind) = token_kind(&tokens[idx]) {
+            result.push(SemanticToken {
+                range: tokens[idx].span,
+                kind,
+            });
+        }
+        idx += 1;
+    }
+
+    result
+}
+
+/// Classifies the parts of a possibly qualified name, e.g. `app.users` or `u.*`, and returns the
+/// index of the token after it
+///
+/// The last part is of the kind of the node. The part before the name of a column is its
+/// relation, all other qualifiers are schemas.
+fn push_name(
+    tokens: &[Token],
+    start: usize,
+    kind: SemanticTokenKind,
+    result: &mut Vec<SemanticToken>,
+) -> usize {
+    let mut parts = vec![tokens[start].span];
+    let mut star = false;
+    let mut idx = start + 1;
+    while idx + 1 < tokens.len() && tokens[idx].kind == SyntaxKind::Ascii46 {
+        if tokens[idx + 1].kind == SyntaxKind::Ascii42 {
+            star = true;
+            idx += 2;
+            break;
+        }
+        parts.push(tokens[idx + 1].span);
+        idx += 2;
+    }
+
+    let count = parts.len();
+    for (i, range) in parts.into_iter().enumerate() {
+        let from_end = count - i - if star { 0 } else { 1 };
+        let kind = match (kind, from_end) {
+            (kind, 0) => kind,
+            (SemanticTokenKind::Column, 1) => SemanticTokenKind::Relation,
+            _ => SemanticTokenKind::Namespace,
+        };
+        result.push(SemanticToken { range, kind });
+    }
+
+    idx
+}
+
+fn token_kind(token: &Token) -> Option<SemanticTokenKind> {
+    match token.kind {
+        SyntaxKind::SqlComment | SyntaxKind::CComment => Some(SemanticTokenKind::Comment),
+        SyntaxKind::Sconst | SyntaxKind::Usconst | SyntaxKind::Bconst | SyntaxKind::Xconst => {
+            Some(SemanticTokenKind::String)
+        }
+        SyntaxKind::Iconst | SyntaxKind::Fconst => Some(SemanticTokenKind::Number),
+        SyntaxKind::Param => Some(SemanticTokenKind::Parameter),
+        SyntaxKind::Op
+        | SyntaxKind::Typecast
+        | SyntaxKind::LessEquals
+        | SyntaxKind::GreaterEquals
+        | SyntaxKind::NotEquals
+        | SyntaxKind::Ascii37
+        | SyntaxKind::Ascii43
+        | SyntaxKind::Ascii45
+        | SyntaxKind::Ascii47
+        | SyntaxKind::Ascii60
+        | SyntaxKind::Ascii61
+        | SyntaxKind::Ascii62
+        | SyntaxKind::Ascii94 => Some(SemanticTokenKind::Operator),
+        _ if token.token_type != TokenType::NoKeyword => Some(SemanticTokenKind::Keyword),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use text_size::TextRange;
+
+    use super::{semantic_tokens, SemanticTokenKind};
+
+    #[test]
+    fn test_semantic_tokens() {
+        let text = "-- active users\nselect u.name, count(*)::int from app.users u where u.id > $1 and 'x' = 'y';\nselec 1";
+        let root = pg_query_ext::parse(&text[16..91]).ok();
+        let statements = [
+            (TextRange::new(16.into(), 91.into()), root),
+            (TextRange::new(93.into(), 100.into()), None),
+        ];
+
+        let tokens = semantic_tokens(text, &statements)
+            .into_iter()
+            .map(|t| (&text[t.range], t.kind))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            tokens,
+            vec![
+                ("-- active users", SemanticTokenKind::Comment),
+                ("select", SemanticTokenKind::Keyword),
+                ("u", SemanticTokenKind::Relation),
+                ("name", SemanticTokenKind::Column),
+                ("count", SemanticTokenKind::Function),
+                ("::", SemanticTokenKind::Operator),
+                ("int", SemanticTokenKind::Type),
+                ("from", SemanticTokenKind::Keyword),
+                ("app", SemanticTokenKind::Namespace),
+                ("users", SemanticTokenKind::Relation),
+                ("where", SemanticTokenKind::Keyword),
+                ("u", SemanticTokenKind::Relation),
+                ("id", SemanticTokenKind::Column),
+                (">", SemanticTokenKind::Operator),
+                ("$1", SemanticTokenKind::Parameter),
+                ("and", SemanticTokenKind::Keyword),
+                ("'x'", SemanticTokenKind::String),
+                ("=", SemanticTokenKind::Operator),
+                ("'y'", SemanticTokenKind::String),
+                ("1", SemanticTokenKind::Number),
+            ]
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use text_size::{TextRange, TextSize};
+
+/// Suppresses diagnostics with the codes that follow it that start on the next line, e.g.
+/// `-- pglsp-ignore ban-drop-column 42P01`
+const IGNORE_COMMENT: &str = "pglsp-ignore";
+
+/// Returns whether a diagnostic with a code that starts at an offset is suppressed by an ignore
+/// comment on the preceding line
+pub fn is_suppressed(text: &str, offset: TextSize, code: &str) -> bool {
+    preceding_line(text, offset)
+        .and_then(|(_, line)| ignored_codes(line))
+        .is_some_and(|codes| codes.contains(&code))
+}
+
+/// Returns the edit that suppresses diagnostics with a code that start at an offset
+///
+/// The code is appended to an ignore comment on the preceding line, if there is one, and
+/// otherwise a comment with the indentation of the line of the diagnostic is inserted before it.
+pub fn suppress(text: &str, offset: TextSize, code: &str) -> (TextRange, String) {
+    if let Some((range, line)) = preceding_line(text, offset) {
+        if ignored_codes(line).is_some() {
+            let end = range.start() + TextSize::of(line.trim_end());
+            return (TextRange::empty(end), format!(" {}", code));
+        }
+    }
+
+    let start = line_start(text, offset);
+    let indent = text[usize::from(start)..]
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect::<String>();
+    (
+        TextRange::empty(start),
+        format!("{}-- {} {}\n", indent, IGNORE_COMMENT, code),
+    )
+}
+
+/// Returns the codes of the ignore comment that is the only content of a line
+fn ignored_codes(line: &str) -> Option<Vec<&str>> {
+    let codes = line
+        .trim()
+        .strip_prefix("--")?
+        .trim_start()
+        .strip_prefix(IGNORE_COMMENT)?;
+    if !codes.is_empty() && !codes.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(
+        codes
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|c| !c.is_empty())
+            .collect(),
+    )
+}
+
+fn line_start(text: &str, offset: TextSize) -> TextSize {
+    text[..usize::from(offset)]
+        .rfind('\n')
+        .map_or(TextSize::new(0), |idx| TextSize::try_from(idx + 1).unwrap())
+}
+
+/// Returns the line before the one that contains the offset together with its range
+fn preceding_line(text: &str, offset: TextSize) -> Option<(TextRange, &str)> {
+    let end = line_start(text, offset).checked_sub(TextSize::new(1))?;
+    let start = line_start(text, end);
+    let range = TextRange::new(start, end);
+    Some((range, &text[range]))
+}
+
+#[cfg(test)]
+mod tests {
+    use text_size::TextSize;
+
+    use super::{is_suppressed, suppress};
+
+    #[test]
+    fn test_suppressions() {
+        let text =
+            "select 1;\n  -- pglsp-ignore ban-drop-column, 42P01\n  alter table t drop column c;";
+        let offset = TextSize::try_from(text.find("alter").unwrap()).unwrap();
+
+        assert!(is_suppressed(text, offset, "ban-drop-column"));
+        assert!(is_suppressed(text, offset, "42P01"));
+        assert!(!is_suppressed(text, offset, "42703"));
+        assert!(!is_suppressed(text, TextSize::new(0), "ban-drop-column"));
+
+        let apply = |text: &str, code: &str| {
+            let offset = TextSize::try_from(text.find("alter").unwrap()).unwrap();
+            let (range, new_text) = suppress(text, offset, code);
+            let mut text = text.to_string();
+            text.replace_range(std::ops::Range::<usize>::from(range), &new_text);
+            text
+        };
+        assert_eq!(
+            apply(text, "42703"),
+            "select 1;\n  -- pglsp-ignore ban-drop-column, 42P01 42703\n  alter table t drop column c;"
+        );
+        assert_eq!(
+            apply(
+                "select 1;\n  -- a comment\n  alter table t drop column c;",
+                "ban-drop-column"
+            ),
+            "select 1;\n  -- a comment\n  -- pglsp-ignore ban-drop-column\n  alter table t drop column c;"
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::HashSet;
+
+use pg_base_db::PgLspPath;
+use pg_query_ext::{protobuf, NodeEnum};
+use pg_schema_cache::SchemaCache;
+use text_size::TextRange;
+
+use crate::object_index::{
+    non_empty, qualified_name, statement_definitions, ObjectDefinition, ObjectKind, DEFAULT_SCHEMA,
+};
+
+/// An entry of the outline of a document, one per statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementSymbol {
+    /// A short description of the statement, e.g. `CREATE TABLE users` or `SELECT … FROM orders`
+    pub name: String,
+    /// The kind of object the statement creates, if any
+    pub kind: Option<ObjectKind>,
+    pub range: TextRange,
+}
+
+/// A database object found by a workspace symbol search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectSymbol {
+    pub kind: ObjectKind,
+    pub schema: String,
+    pub name: String,
+    /// The statement that creates the object, if it is defined in a workspace file
+    pub location: Option<(PgLspPath, TextRange)>,
+}
+
+/// Returns the objects whose name matches the query, both those defined in workspace files and
+/// those in the schema caches
+///
+/// Objects of the schema cache that are also defined in a workspace file are only returned once,
+/// with the location of their definition. System schemas are not searched.
+pub fn object_symbols<'a>(
+    query: &str,
+    definitions: Vec<(PgLspPath, ObjectDefinition)>,
+    schema_caches: impl IntoIterator<Item = &'a SchemaCache>,
+) -> Vec<ObjectSymbol> {
+    let mut symbols = definitions
+        .into_iter()
+        .filter(|(_, d)| d.kind != ObjectKind::Schema && matches_query(query, &d.name))
+        .map(|(path, d)| ObjectSymbol {
+            kind: d.kind,
+            schema: d.schema.unwrap_or_else(|| DEFAULT_SCHEMA.to_string()),
+            name: d.name,
+            location: Some((path, d.range)),
+        })
+        .collect::<Vec<_>>();
+
+    let mut seen = symbols
+        .iter()
+        .map(|s| (s.kind.is_relation(), s.schema.clone(), s.name.clone()))
+        .collect::<HashSet<_>>();
+
+    for cache in schema_caches {
+        let tables = cache
+            .tables
+            .iter()
+            .map(|t| (ObjectKind::Table, t.schema.as_str(), t.name.as_str()));
+        let foreign_tables = cache
+            .foreign_tables
+            .iter()
+            .map(|t| (ObjectKind::ForeignTable, t.schema.as_str(), t.name.as_str()));
+        let functions = cache.functions.iter().filter_map(|f| {
+            Some((
+                ObjectKind::Function,
+                f.schema.as_deref()?,
+                f.name.as_deref()?,
+            ))
+        });
+
+        for (kind, schema, name) in tables.chain(foreign_tables).chain(functions) {
+            if is_system_schema(schema)
+                || !matches_query(query, name)
+                || !seen.insert((kind.is_relation(), schema.to_string(), name.to_string()))
+            {
+                continue;
+            }
+            symbols.push(ObjectSymbol {
+                kind,
+                schema: schema.to_string(),
+                name: name.to_string(),
+                location: None,
+            });
+        }
+    }
+
+    symbols.sort_by(|a, b| (&a.name, &a.schema).cmp(&(&b.name, &b.schema)));
+    symbols
+}
+
+/// Whether the characters of the query appear in the name in order, ignoring case, e.g. `usr`
+/// matches `users`
+fn matches_query(query: &str, name: &str) -> bool {
+    let mut chars = name.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|q| chars.any(|c| c == q))
+}
+
+fn is_system_schema(schema: &str) -> bool {
+    schema == "information_schema" || schema.starts_with("pg_")
+}
+
+/// Describes a statement by its main target
+///
+/// Statements that could not be parsed are described by their leading keyword.
+pub fn statement_symbol(text: &str, root: Option<&NodeEnum>, range: TextRange) -> StatementSymbol {
+    let leading_keyword = || {
+        text.split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_uppercase()
+    };
+
+    let Some(root) = root else {
+        return StatementSymbol {
+            name: leading_keyword(),
+            kind: None,
+            range,
+        };
+    };
+
+    if let Some(definition) = statement_definitions(root, range).into_iter().next() {
+        return StatementSymbol {
+            name: format!(
+                "CREATE {} {}",
+                definition.kind.keyword(),
+                qualify(definition.schema.as_deref(), &definition.name)
+            ),
+            kind: Some(definition.kind),
+            range,
+        };
+    }
+
+    let name = match root {
+        NodeEnum::SelectStmt(s) => match first_relation(s) {
+            Some(relation) => format!("SELECT … FROM {}", relation),
+            None => "SELECT …".to_string(),
+        },
+        NodeEnum::InsertStmt(s) => format!("INSERT INTO {}", range_var(&s.relation)),
+        NodeEnum::UpdateStmt(s) => format!("UPDATE {}", range_var(&s.relation)),
+        NodeEnum::DeleteStmt(s) => format!("DELETE FROM {}", range_var(&s.relation)),
+        NodeEnum::AlterTableStmt(s) => format!("ALTER TABLE {}", range_var(&s.relation)),
+        NodeEnum::IndexStmt(s) => match s.idxname.as_str() {
+            "" => format!("CREATE INDEX ON {}", range_var(&s.relation)),
+            name => format!("CREATE INDEX {} ON {}", name, range_var(&s.relation)),
+        },
+        NodeEnum::DropStmt(s) => {
+            let names = s
+                .objects
+                .iter()
+                .filter_map(|o| {
+                    let (schema, name) = match o.node.as_ref()? {
+                        NodeEnum::List(l) => qualified_name(&l.items),
+                        NodeEnum::ObjectWithArgs(f) => qualified_name(&f.objname),
+                        NodeEnum::TypeName(t) => qualified_name(&t.names),
+                        NodeEnum::String(s) => (None, s.sval.clone()),
+                        _ => return None,
+                    };
+                    Some(qualify(schema.as_deref(), &name))
+                })
+                .collect::<Vec<_>>();
+            let keyword = match protobuf::ObjectType::from_i32(s.remove_type) {
+                Some(protobuf::ObjectType::ObjectSchema) => "SCHEMA",
+                Some(protobuf::ObjectType::ObjectTable) => "TABLE",
+                Some(protobuf::ObjectType::ObjectView) => "VIEW",
+                Some(protobuf::ObjectType::ObjectMatview) => "MATERIALIZED VIEW",
+                Some(protobuf::ObjectType::ObjectForeignTable) => "FOREIGN TABLE",
+                Some(protobuf::ObjectType::ObjectIndex) => "INDEX",
+                Some(protobuf::ObjectType::ObjectFunction) => "FUNCTION",
+                Some(protobuf::ObjectType::ObjectProcedure) => "PROCEDURE",
+                Some(protobuf::ObjectType::ObjectType) => "TYPE",
+                _ => "",
+            };
+            [String::from("DROP"), keyword.to_string(), names.join(", ")]
+                .into_iter()
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+        _ => leading_keyword(),
+    };
+
+    StatementSymbol {
+        name,
+        kind: None,
+        range,
+    }
+}
+
+/// Returns the first relation in the `FROM` clause of a query, or of the first query of a set
+/// operation
+fn first_relation(stmt: &protobuf::SelectStmt) -> Option<String> {
+    if let Some(larg) = &stmt.larg {
+        return first_relation(larg);
+    }
+
+    let mut item = stmt.from_clause.first()?.node.as_ref()?;
+    loop {
+        match item {
+            NodeEnum::RangeVar(r) => {
+                return Some(qualify(non_empty(&r.schemaname).as_deref(), &r.relname))
+            }
+            NodeEnum::JoinExpr(j) => item = j.larg.as_ref()?.node.as_ref()?,
+            _ => return None,
+        }
+    }
+}
+
+fn range_var(relation: &Option<protobuf::RangeVar>) -> String {
+    relation
+        .as_ref()
+        .map(|r| qualify(non_empty(&r.schemaname).as_deref(), &r.relname))
+        .unwrap_or_default()
+}
+
+fn qualify(schema: Option<&str>, name: &str) -> String {
+    match schema {
+        Some(schema) => format!("{}.{}", schema, name),
+        None => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use text_size::TextRange;
+
+    use pg_base_db::PgLspPath;
+    use pg_schema_cache::SchemaCache;
+
+    use crate::{object_index::statement_definitions, ObjectKind};
+
+    use super::{object_symbols, statement_symbol};
+
+    fn name(sql: &str) -> String {
+        let root = pg_query_ext::parse(sql).ok();
+        statement_symbol(sql, root.as_ref(), TextRange::default()).name
+    }
+
+    #[test]
+    fn test_statement_symbol() {
+        assert_eq!(
+            name("create table app.users (id int)"),
+            "CREATE TABLE app.users"
+        );
+        assert_eq!(
+            name("create materialized view totals as select 1"),
+            "CREATE MATERIALIZED VIEW totals"
+        );
+        assert_eq!(
+            name("select * from orders o join users u on u.id = o.user_id"),
+            "SELECT … FROM orders"
+        );
+        assert_eq!(name("select 1 union select 2"), "SELECT …");
+        assert_eq!(
+            name("insert into users (id) values (1)"),
+            "INSERT INTO users"
+        );
+        assert_eq!(
+            name("create index users_email on users (email)"),
+            "CREATE INDEX users_email ON users"
+        );
+        assert_eq!(
+            name("drop table users, app.events"),
+            "DROP TABLE users, app.events"
+        );
+        assert_eq!(name("drop function add(int, int)"), "DROP FUNCTION add");
+        assert_eq!(name("grant select on users to app"), "GRANT");
+        assert_eq!(name("selec * from users"), "SELEC");
+
+        let sql = "create function add(a int, b int) returns int as 'select a + b' language sql";
+        let root = pg_query_ext::parse(sql).ok();
+        assert_eq!(
+            statement_symbol(sql, root.as_ref(), TextRange::default()).kind,
+            Some(ObjectKind::Function)
+        );
+    }
+
+    #[test]
+    fn test_object_symbols() {
+        let cache = SchemaCache::from_sql(
+            "
+            create table public.users (id int);
+            create table public.user_roles (id int);
+            create table public.orders (id int);
+            create function public.user_count() returns bigint as 'select 1' language sql;
+            ",
+        );
+
+        let sql = "create table users (id int, email text)";
+        let definitions =
+            statement_definitions(&pg_query_ext::parse(sql).unwrap(), TextRange::default())
+                .into_iter()
+                .map(|d| (PgLspPath::new("/migrations/001.sql"), d))
+                .collect();
+
+        let symbols = object_symbols("usr", definitions, [&cache]);
+        assert_eq!(
+            symbols
+                .iter()
+                .map(|s| (s.name.as_str(), s.location.is_some()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("user_count", false),
+                ("user_roles", false),
+                ("users", true)
+            ]
+        );
+
+        assert_eq!(
+            object_symbols("UsErC", Vec::new(), [&cache])[0].kind,
+            ObjectKind::Function
+        );
+    }
+}
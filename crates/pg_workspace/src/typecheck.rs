@@ -3,22 +3,43 @@ use std::sync::Arc;
 use dashmap::DashMap;
 use pg_base_db::StatementRef;
 use pg_diagnostics::{Diagnostic, Severity};
-use pg_typecheck::{check_sql, PgSeverity, TypeError, TypecheckerParams};
+use pg_typecheck::{check_sql, PgSeverity, ResultColumn, TypeError, TypecheckerParams};
 use text_size::TextRange;
 
 pub struct Typechecker {
     errors: DashMap<StatementRef, Arc<Vec<TypeError>>>,
+    result_columns: DashMap<StatementRef, Arc<Vec<ResultColumn>>>,
+    parameter_types: DashMap<StatementRef, Arc<Vec<String>>>,
 }
 
 impl Typechecker {
     pub fn new() -> Typechecker {
         Typechecker {
             errors: DashMap::new(),
+            result_columns: DashMap::new(),
+            parameter_types: DashMap::new(),
         }
     }
 
     pub fn clear_errors(&self) {
         self.errors.clear();
+        self.result_columns.clear();
+        self.parameter_types.clear();
+    }
+
+    /// Returns the errors of the statement, if it was typechecked
+    pub fn errors(&self, statement: &StatementRef) -> Option<Arc<Vec<TypeError>>> {
+        self.errors.get(statement).map(|e| e.clone())
+    }
+
+    /// Returns the columns of the rows the statement returns, if it was typechecked
+    pub fn result_columns(&self, statement: &StatementRef) -> Option<Arc<Vec<ResultColumn>>> {
+        self.result_columns.get(statement).map(|c| c.clone())
+    }
+
+    /// Returns the types of the parameters of the statement, if it was typechecked
+    pub fn parameter_types(&self, statement: &StatementRef) -> Option<Arc<Vec<String>>> {
+        self.parameter_types.get(statement).map(|p| p.clone())
     }
 
     pub fn diagnostics(&self, statement: &StatementRef, at_range: TextRange) -> Vec<Diagnostic> {
@@ -26,6 +47,7 @@ impl Typechecker {
         if let Some(errs) = self.errors.get(statement) {
             diagnostics.extend(errs.iter().map(|e| Diagnostic {
                 description: None,
+                code: Some(e.code.clone()),
                 source: "typecheck".to_string(),
                 range: e.range.map(|r| r + at_range.start()).unwrap_or(at_range),
                 severity: match e.severity {
@@ -45,13 +67,18 @@ impl Typechecker {
     }
 
     pub fn run_typecheck(&self, statement: &StatementRef, params: TypecheckerParams<'_>) {
-        self.errors.insert(
-            statement.clone(),
-            Arc::new(async_std::task::block_on(check_sql(params))),
-        );
+        let result = async_std::task::block_on(check_sql(params));
+        self.errors
+            .insert(statement.clone(), Arc::new(result.errors));
+        self.result_columns
+            .insert(statement.clone(), Arc::new(result.columns));
+        self.parameter_types
+            .insert(statement.clone(), Arc::new(result.parameters));
     }
 
     pub fn clear_statement_errors(&self, statement: &StatementRef) {
         self.errors.remove(statement);
+        self.result_columns.remove(statement);
+        self.parameter_types.remove(statement);
     }
 }
@@ -1,7 +1,14 @@
-import { ExtensionContext } from 'vscode';
+import {
+    commands,
+    ExtensionContext,
+    StatusBarAlignment,
+    StatusBarItem,
+    window,
+} from 'vscode';
 
 import {
     Executable,
+    ExecuteCommandRequest,
     LanguageClient,
     LanguageClientOptions,
     ServerOptions,
@@ -9,6 +16,15 @@ import {
 
 let client: LanguageClient;
 
+type ConnectionState = 'connected' | 'reconnecting' | 'offline';
+
+interface ConnectionStatusParams {
+    connection: string;
+    state: ConnectionState;
+    message: string | null;
+    retryIn: number | null;
+}
+
 export function activate(context: ExtensionContext) {
     // If the extension is launched in debug mode then the debug server options are used
     // Otherwise the run options are used
@@ -34,10 +50,59 @@ export function activate(context: ExtensionContext) {
         clientOptions
     );
 
+    const statusBar = window.createStatusBarItem(StatusBarAlignment.Left);
+    statusBar.command = 'postgres_lsp.reconnect';
+    context.subscriptions.push(
+        statusBar,
+        commands.registerCommand('postgres_lsp.reconnect', () =>
+            client.sendRequest(ExecuteCommandRequest.type, {
+                command: 'pglsp.reconnect',
+                arguments: [],
+            })
+        )
+    );
+
+    const connections = new Map<string, ConnectionStatusParams>();
+    client.onNotification('pglsp/connectionStatus', (params: ConnectionStatusParams) => {
+        connections.set(params.connection, params);
+        updateStatusBar(statusBar, [...connections.values()]);
+    });
+
     // Start the client. This will also launch the server
     client.start();
 }
 
+// Shows the worst state of all connections, and the state of each one in the tooltip
+function updateStatusBar(statusBar: StatusBarItem, connections: ConnectionStatusParams[]) {
+    const states: ConnectionState[] = ['offline', 'reconnecting', 'connected'];
+    const state = states.find((s) => connections.some((c) => c.state === s));
+    if (!state) {
+        statusBar.hide();
+        return;
+    }
+
+    const icons: Record<ConnectionState, string> = {
+        connected: '$(database)',
+        reconnecting: '$(sync~spin)',
+        offline: '$(debug-disconnect)',
+    };
+    statusBar.text = `${icons[state]} postgres: ${state}`;
+    statusBar.tooltip = connections
+        .map((c) => {
+            let line = `${c.connection}: ${c.state}`;
+            if (c.message) {
+                line += ` (${c.message})`;
+            }
+            if (c.retryIn !== null) {
+                line += `, retrying in ${c.retryIn}s`;
+            }
+            return line;
+        })
+        .concat('Click to reconnect')
+        .join('\n');
+    statusBar.show();
+}
+
 export function deactivate(): Thenable<void> | undefined {
     if (!client) {
         return undefined;
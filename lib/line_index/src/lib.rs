@@ -15,8 +15,16 @@ pub struct LineIndex {
     pub(crate) utf16_lines: HashMap<u32, Vec<Utf16Char>>,
 }
 
+/// An encoding with code units wider than a byte that columns may be counted in
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub struct LineColUtf16 {
+pub enum WideEncoding {
+    Utf16,
+    Utf32,
+}
+
+/// A position whose column is counted in the code units of a [WideEncoding]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WideLineCol {
     /// Zero-based
     pub line: u32,
     /// Zero-based
@@ -45,12 +53,11 @@ impl Utf16Char {
         self.end - self.start
     }
 
-    /// Returns the length in 16-bit UTF-16 code units.
-    fn len_utf16(&self) -> usize {
-        if self.len() == TextSize::from(4) {
-            2
-        } else {
-            1
+    /// Returns the length in the code units of the given encoding.
+    fn len_wide(&self, enc: WideEncoding) -> usize {
+        match enc {
+            WideEncoding::Utf16 if self.len() == TextSize::from(4) => 2,
+            _ => 1,
         }
     }
 }
@@ -117,16 +124,16 @@ impl LineIndex {
         Some(self.newlines.get(line_col.line as usize)? + TextSize::from(line_col.col))
     }
 
-    pub fn to_utf16(&self, line_col: LineCol) -> Option<LineColUtf16> {
-        let col = self.utf8_to_utf16_col(line_col.line, line_col.col.into());
-        Some(LineColUtf16 {
+    pub fn to_wide(&self, enc: WideEncoding, line_col: LineCol) -> Option<WideLineCol> {
+        let col = self.utf8_to_wide_col(enc, line_col.line, line_col.col.into());
+        Some(WideLineCol {
             line: line_col.line,
             col: col as u32,
         })
     }
 
-    pub fn to_utf8(&self, line_col: LineColUtf16) -> Option<LineCol> {
-        let col = self.utf16_to_utf8_col(line_col.line, line_col.col);
+    pub fn to_utf8(&self, enc: WideEncoding, line_col: WideLineCol) -> Option<LineCol> {
+        let col = self.wide_to_utf8_col(enc, line_col.line, line_col.col);
         Some(LineCol {
             line: line_col.line,
             col: col.into(),
@@ -146,12 +153,12 @@ impl LineIndex {
             .filter(|it| !it.is_empty())
     }
 
-    fn utf8_to_utf16_col(&self, line: u32, col: TextSize) -> usize {
+    fn utf8_to_wide_col(&self, enc: WideEncoding, line: u32, col: TextSize) -> usize {
         let mut res: usize = col.into();
         if let Some(utf16_chars) = self.utf16_lines.get(&line) {
             for c in utf16_chars {
                 if c.end <= col {
-                    res -= usize::from(c.len()) - c.len_utf16();
+                    res -= usize::from(c.len()) - c.len_wide(enc);
                 } else {
                     // From here on, all utf16 characters come *after* the character we are mapping,
                     // so we don't need to take them into account
@@ -162,11 +169,11 @@ impl LineIndex {
         res
     }
 
-    fn utf16_to_utf8_col(&self, line: u32, mut col: u32) -> TextSize {
+    fn wide_to_utf8_col(&self, enc: WideEncoding, line: u32, mut col: u32) -> TextSize {
         if let Some(utf16_chars) = self.utf16_lines.get(&line) {
             for c in utf16_chars {
                 if col > u32::from(c.start) {
-                    col += u32::from(c.len()) - c.len_utf16() as u32;
+                    col += u32::from(c.len()) - c.len_wide(enc) as u32;
                 } else {
                     // From here on, all utf16 characters come *after* the character we are mapping,
                     // so we don't need to take them into account